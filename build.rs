@@ -0,0 +1,8 @@
+fn main() {
+    // The gRPC service is generated from proto/ only with `--features grpc`
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/browser.proto");
+        tonic_build::compile_protos("proto/browser.proto").expect("failed to compile proto/browser.proto");
+    }
+}
@@ -0,0 +1,181 @@
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+use tiny_http::{Method, Request, Response};
+
+use crate::buffers::{self, Buffer, BufferStats};
+use crate::overlay::{self, Color};
+
+// ============== Frame Annotations ==============
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    /// Outlined rectangle at x/y with size w/h.
+    Rect,
+    /// Translucent filled rectangle.
+    Highlight,
+    /// Line from x/y to x2/y2.
+    Line,
+    /// Label only, anchored at x/y.
+    Text,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    #[serde(default)]
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub shape: Shape,
+    pub x: i64,
+    pub y: i64,
+    #[serde(default)]
+    pub w: i64,
+    #[serde(default)]
+    pub h: i64,
+    #[serde(default)]
+    pub x2: i64,
+    #[serde(default)]
+    pub y2: i64,
+    #[serde(default = "default_color")]
+    pub color: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    #[serde(default)]
+    pub created_at: u128,
+    #[serde(default)]
+    pub expires_at: Option<u128>,
+}
+
+fn default_color() -> String {
+    "#ff0000".to_string()
+}
+
+#[derive(Debug, Default)]
+pub struct Annotations {
+    items: Vec<Annotation>,
+    next_id: u64,
+    /// Adds refused because `--buffer-size annotations=N` were live.
+    refused: u64,
+}
+
+pub type SharedAnnotations = Arc<Mutex<Annotations>>;
+
+impl Annotations {
+    pub fn add(&mut self, mut annotation: Annotation) -> Result<Annotation, String> {
+        if overlay::parse_color(&annotation.color).is_none() {
+            return Err(format!("invalid color '{}', expected #rgb, #rrggbb or #rrggbbaa", annotation.color));
+        }
+        if matches!(annotation.shape, Shape::Rect | Shape::Highlight) && (annotation.w <= 0 || annotation.h <= 0) {
+            return Err("rect and highlight annotations need positive w and h".to_string());
+        }
+        if annotation.shape == Shape::Text && annotation.label.as_deref().unwrap_or("").is_empty() {
+            return Err("text annotations need a label".to_string());
+        }
+        self.prune();
+        if self.items.len() >= Buffer::Annotations.capacity() {
+            self.refused += 1;
+            return Err(format!("annotation limit of {} reached; delete some first", Buffer::Annotations.capacity()));
+        }
+        self.next_id += 1;
+        annotation.id = self.next_id;
+        annotation.created_at = crate::now_millis();
+        annotation.expires_at = annotation.ttl_secs.map(|ttl| annotation.created_at + ttl as u128 * 1000);
+        self.items.push(annotation.clone());
+        Ok(annotation)
+    }
+
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.items.len();
+        self.items.retain(|a| a.id != id);
+        self.items.len() != before
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    fn prune(&mut self) {
+        let now = crate::now_millis();
+        self.items.retain(|a| a.expires_at.is_none_or(|exp| exp > now));
+    }
+
+    /// True when there is something to draw, so callers can skip re-encoding.
+    pub fn is_active(&mut self) -> bool {
+        self.prune();
+        !self.items.is_empty()
+    }
+
+    pub fn list(&mut self) -> &[Annotation] {
+        self.prune();
+        &self.items
+    }
+
+    pub fn buffer_stats(&mut self) -> BufferStats {
+        self.prune();
+        buffers::stats(Buffer::Annotations, &self.items, self.refused)
+    }
+
+    pub fn render(&mut self, img: &mut RgbImage) {
+        self.prune();
+        for a in &self.items {
+            let color = overlay::parse_color(&a.color).unwrap_or(Color::from([255, 0, 0, 255]));
+            let label_pos = match a.shape {
+                Shape::Rect => {
+                    overlay::stroke_rect(img, a.x, a.y, a.w, a.h, 3, color);
+                    (a.x, a.y - 22)
+                }
+                Shape::Highlight => {
+                    overlay::fill_rect(img, a.x, a.y, a.w, a.h, Color::from([color[0], color[1], color[2], 80]));
+                    overlay::stroke_rect(img, a.x, a.y, a.w, a.h, 1, color);
+                    (a.x, a.y - 22)
+                }
+                Shape::Line => {
+                    overlay::draw_line(img, a.x, a.y, a.x2, a.y2, 3, color);
+                    (a.x2 + 4, a.y2 - 10)
+                }
+                Shape::Text => (a.x, a.y),
+            };
+            if let Some(label) = a.label.as_deref().filter(|l| !l.is_empty()) {
+                let (x, y) = (label_pos.0.max(0), label_pos.1.max(0));
+                overlay::draw_label(img, x, y, label, 2, Color::from([255, 255, 255, 255]), color);
+            }
+        }
+    }
+}
+
+/// Serves `/annotations` and `/annotations/{id}` for either HTTP server.
+pub fn handle(request: &mut Request, path: &str, store: &SharedAnnotations) -> Response<Cursor<Vec<u8>>> {
+    let method = request.method().clone();
+    let id = path.strip_prefix("/annotations/").map(|id| id.parse::<u64>());
+
+    match (method, id) {
+        (Method::Get, None) => {
+            crate::json_response(200, &serde_json::json!({"annotations": store.lock().unwrap().list()}))
+        }
+        (Method::Post, None) => match crate::read_json_body::<Annotation>(request) {
+            Ok(annotation) => match store.lock().unwrap().add(annotation) {
+                Ok(created) => crate::json_response(201, &serde_json::json!(created)),
+                Err(e) => crate::json_response(400, &serde_json::json!({"error": e})),
+            },
+            Err(e) => crate::json_response(400, &serde_json::json!({"error": e})),
+        },
+        (Method::Delete, None) => {
+            store.lock().unwrap().clear();
+            crate::json_response(200, &serde_json::json!({"status": "cleared"}))
+        }
+        (Method::Delete, Some(Ok(id))) => {
+            if store.lock().unwrap().remove(id) {
+                crate::json_response(200, &serde_json::json!({"status": "deleted", "id": id}))
+            } else {
+                crate::json_response(404, &serde_json::json!({"error": "no such annotation"}))
+            }
+        }
+        (_, Some(Err(_))) => crate::json_response(400, &serde_json::json!({"error": "invalid annotation id"})),
+        _ => crate::json_response(405, &serde_json::json!({"error": "method not allowed"})),
+    }
+}
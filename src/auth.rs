@@ -0,0 +1,817 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tiny_http::{Header, Request, Response};
+
+// ============== Access Control ==============
+//
+// With --api-token every route needs one of: the token itself (Authorization:
+// Bearer or ?token=), the session cookie handed out by /login, or a ?sig=
+// from /sign that covers the requested path. Session cookies and signatures
+// are both HMACs under a process-local key, so rotating the key
+// (POST /sign/rotate) invalidates every one of them at once.
+//
+// --tokens FILE adds named tokens with a role: a viewer may only GET the
+// frame, status and other read routes, a controller may use every route
+// except the admin ones. The --api-token is the admin token and alone may
+// sign URLs and manage tokens through /tokens, which rewrites FILE. A /login
+// session remembers which token it came from, so revoking the token ends
+// its sessions too. Every route declares its access in ROUTES; a route
+// missing there needs the admin token. Missing or wrong credentials get a
+// 401, a known token without the role a 403.
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const SESSION_COOKIE: &str = "rb_session";
+const SESSION_TTL_SECS: u64 = 12 * 3600;
+pub const DEFAULT_TTL_SECS: u64 = 300;
+pub const MAX_TTL_SECS: u64 = 7 * 24 * 3600;
+/// Path list of a session cookie: grants every route.
+const ANY_PATH: &str = "*";
+/// `/blocked` is static and only echoes its query, so blocked navigations
+/// can show it without credentials.
+const PUBLIC_PATHS: &[&str] = &["/login", "/logout", "/blocked"];
+/// Session path list prefix naming the token the session came from.
+const SESSION_TOKEN_PREFIX: &str = "*@";
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Controller,
+    /// The --api-token; never given to a named token.
+    #[serde(skip_deserializing)]
+    Admin,
+}
+
+impl Role {
+    fn name(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Controller => "controller",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Access {
+    /// Viewers may GET; other methods need a controller.
+    Read,
+    Control,
+    Admin,
+}
+
+/// Every route of both servers; `/*` also covers the paths below.
+const ROUTES: &[(&str, Access)] = &[
+    ("/", Access::Read),
+    ("/live-stream", Access::Read),
+    ("/frame", Access::Read),
+    ("/mjpeg", Access::Read),
+    ("/ws", Access::Read),
+    ("/events", Access::Read),
+    ("/embed", Access::Read),
+    ("/geometry", Access::Read),
+    ("/fonts", Access::Read),
+    ("/instance", Access::Read),
+    ("/locale", Access::Read),
+    ("/emulation", Access::Read),
+    ("/lifecycle", Access::Read),
+    ("/theme", Access::Read),
+    ("/hud", Access::Read),
+    ("/ticker", Access::Read),
+    ("/annotations", Access::Read),
+    ("/annotations/*", Access::Read),
+    ("/stats/buffers", Access::Read),
+    ("/stats/pipeline", Access::Read),
+    ("/render/pool", Access::Read),
+    ("/blocker/stats", Access::Read),
+    ("/host-rules", Access::Read),
+    ("/block", Access::Read),
+    ("/permissions", Access::Read),
+    ("/redact", Access::Read),
+    ("/frame-hook", Access::Read),
+    ("/sinks", Access::Read),
+    ("/uploads", Access::Read),
+    ("/captures", Access::Read),
+    ("/history", Access::Read),
+    ("/ui-state", Access::Read),
+    ("/window", Access::Read),
+    ("/split", Access::Read),
+    ("/workspaces", Access::Read),
+    ("/workspaces/*", Access::Read),
+    ("/record", Access::Read),
+    ("/record/last/events", Access::Read),
+    ("/decode-qr", Access::Read),
+    ("/decode-qr/events", Access::Read),
+    ("/ocr", Access::Read),
+    ("/probe/color", Access::Read),
+    ("/media", Access::Read),
+    ("/resources", Access::Read),
+    ("/perf", Access::Read),
+    ("/trace/start", Access::Control),
+    ("/trace/stop", Access::Control),
+    ("/frames-tree", Access::Read),
+    ("/status", Access::Read),
+    ("/metrics", Access::Read),
+    ("/source/view", Access::Read),
+    ("/logs", Access::Read),
+    ("/crashes", Access::Read),
+    ("/requests/recent", Access::Read),
+    ("/dialog", Access::Read),
+    ("/dialogs", Access::Read),
+    ("/downloads", Access::Read),
+    ("/errors", Access::Read),
+    ("/network", Access::Read),
+    ("/network/config", Access::Read),
+    ("/navigate", Access::Control),
+    ("/click", Access::Control),
+    ("/type", Access::Control),
+    ("/press", Access::Control),
+    ("/evaluate", Access::Control),
+    ("/hover", Access::Control),
+    ("/select", Access::Control),
+    ("/upload", Access::Control),
+    ("/har", Access::Control),
+    ("/mock", Access::Control),
+    ("/mock/*", Access::Control),
+    ("/cpu-throttle", Access::Read),
+    ("/offline", Access::Read),
+    ("/downloads/*", Access::Control),
+    ("/element", Access::Control),
+    ("/screenshot", Access::Control),
+    ("/html", Access::Control),
+    ("/text", Access::Control),
+    ("/links", Access::Control),
+    ("/cookies", Access::Control),
+    ("/useragent", Access::Control),
+    ("/headers", Access::Control),
+    ("/auth", Access::Control),
+    ("/storage/*", Access::Control),
+    ("/reload", Access::Control),
+    ("/back", Access::Control),
+    ("/forward", Access::Control),
+    ("/media/emulation", Access::Read),
+    ("/media/*", Access::Control),
+    ("/render", Access::Control),
+    ("/latency-test", Access::Control),
+    ("/probe", Access::Control),
+    ("/probe/selector-color", Access::Control),
+    ("/record/start", Access::Control),
+    ("/record/stop", Access::Control),
+    ("/lifecycle/pause", Access::Control),
+    ("/lifecycle/resume", Access::Control),
+    ("/handoff/export", Access::Control),
+    ("/handoff/import", Access::Control),
+    ("/bundle/export", Access::Control),
+    ("/bundle/import", Access::Control),
+    ("/blocker/reload", Access::Control),
+    ("/journal", Access::Control),
+    // GUI ?url= fetches any URL
+    ("/source", Access::Control),
+    ("/login-helper", Access::Control),
+    ("/login-helper/reset", Access::Control),
+    ("/wd/hub", Access::Control),
+    ("/wd/hub/*", Access::Control),
+    ("/sign", Access::Admin),
+    ("/sign/rotate", Access::Admin),
+    ("/tokens", Access::Admin),
+    ("/tokens/*", Access::Admin),
+];
+
+/// Role needed for `method` on `path`.
+fn required_role(method: &tiny_http::Method, path: &str) -> Role {
+    let access = ROUTES
+        .iter()
+        .find(|(route, _)| match route.strip_suffix("/*") {
+            Some(prefix) => path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/')),
+            None => *route == path,
+        })
+        .map_or(Access::Admin, |(_, access)| *access);
+    match access {
+        Access::Read if matches!(method, tiny_http::Method::Get | tiny_http::Method::Head) => Role::Viewer,
+        Access::Read | Access::Control => Role::Controller,
+        Access::Admin => Role::Admin,
+    }
+}
+
+/// A named token from --tokens or POST /tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub name: String,
+    pub token: String,
+    pub role: Role,
+    #[serde(default)]
+    pub created_at: u128,
+}
+
+/// Body of POST /tokens; a token is generated when none is given.
+#[derive(Debug, Deserialize)]
+struct TokenRequest {
+    name: String,
+    role: Role,
+    token: Option<String>,
+}
+
+const LOGIN_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Rust Browser Claude - Sign in</title>
+    <style>
+        body { margin: 0; background: #1a1a1a; display: flex; justify-content: center; align-items: center; min-height: 100vh; font-family: sans-serif; }
+        form { display: flex; flex-direction: column; gap: 10px; width: 320px; }
+        input { padding: 8px 12px; border-radius: 4px; border: none; font-size: 14px; }
+        button { padding: 8px 16px; background: #4a90d9; color: white; border: none; border-radius: 4px; cursor: pointer; }
+        #error { color: #f66; font-size: 13px; min-height: 1em; }
+    </style>
+</head>
+<body>
+    <form method="POST" action="/login">
+        <input type="password" name="token" placeholder="API token" autofocus>
+        <button type="submit">Open viewer</button>
+        <div id="error">{error}</div>
+    </form>
+</body>
+</html>"#;
+
+struct SigningKey {
+    id: u64,
+    secret: [u8; 32],
+    rotated_at: u128,
+}
+
+impl SigningKey {
+    fn generate(id: u64) -> Self {
+        SigningKey { id, secret: random_secret(), rotated_at: crate::now_millis() }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts any key length")
+    }
+}
+
+/// 32 bytes from the OS CSPRNG, for signing keys and generated tokens.
+fn random_secret() -> [u8; 32] {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+fn now_secs() -> u64 {
+    (crate::now_millis() / 1000) as u64
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+fn cookie<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    header(request, "Cookie")?
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v)
+}
+
+fn redirect(location: &str) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string("")
+        .with_status_code(303)
+        .with_header(Header::from_bytes(&b"Location"[..], location.as_bytes()).unwrap())
+}
+
+fn login_page(status: u16, error: &str) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(LOGIN_HTML.replace("{error}", error))
+        .with_status_code(status)
+        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap())
+}
+
+pub struct Auth {
+    token: Option<String>,
+    key: RwLock<SigningKey>,
+    tokens: RwLock<Vec<ApiToken>>,
+    /// --tokens, rewritten when /tokens changes the list.
+    tokens_file: Option<PathBuf>,
+}
+
+impl Auth {
+    pub fn new(token: Option<String>, tokens_file: Option<PathBuf>) -> Result<Self, String> {
+        let tokens: Vec<ApiToken> = match &tokens_file {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                serde_json::from_str(&text).map_err(|e| format!("Invalid tokens in {}: {}", path.display(), e))?
+            }
+            None => Vec::new(),
+        };
+        for (i, t) in tokens.iter().enumerate() {
+            if t.name.is_empty() || t.token.is_empty() {
+                return Err(format!("token #{} needs a name and a token", i + 1));
+            }
+            if tokens[..i].iter().any(|other| other.name == t.name) {
+                return Err(format!("duplicate token name '{}'", t.name));
+            }
+        }
+        Ok(Auth { token, key: RwLock::new(SigningKey::generate(1)), tokens: RwLock::new(tokens), tokens_file })
+    }
+
+    /// On with --api-token or --tokens, even once every named token is revoked.
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some() || self.tokens_file.is_some()
+    }
+
+    /// Role of `given`, compared against every token in constant time.
+    fn role_of(&self, given: &str) -> Option<Role> {
+        let given = given.trim().as_bytes();
+        let admin = self.token.as_ref().is_some_and(|t| constant_time_eq(given, t.as_bytes()));
+        let named = self.tokens.read().unwrap().iter().fold(None, |found, t| {
+            if constant_time_eq(given, t.token.as_bytes()) { Some(t.role) } else { found }
+        });
+        if admin { Some(Role::Admin) } else { named }
+    }
+
+    /// Role of the token in Authorization: Bearer or ?token=, if any.
+    fn token_role(&self, request: &Request, query: &HashMap<String, String>) -> Option<Role> {
+        header(request, "Authorization")
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .or_else(|| query.get("token").map(String::as_str))
+            .and_then(|presented| self.role_of(presented))
+    }
+
+    /// True when auth is on and the request carries the API token itself,
+    /// not a named token, session cookie or signature.
+    pub fn presents_token(&self, request: &Request, query: &HashMap<String, String>) -> bool {
+        self.token_role(request, query) == Some(Role::Admin)
+    }
+
+    /// `<payload>.<mac>`, both base64url, where payload is `<expiry>|<path>,<path>...`.
+    fn sign_payload(&self, paths: &str, expires_at: u64) -> String {
+        let payload = format!("{}|{}", expires_at, paths);
+        let mut mac = self.key.read().unwrap().mac();
+        mac.update(payload.as_bytes());
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(&payload),
+            URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+        )
+    }
+
+    /// Role a signature or session grants on `path`. Signatures cover their
+    /// paths fully, since only the admin can mint them.
+    fn verify(&self, sig: &str, path: &str) -> Option<Role> {
+        let (payload, tag) = sig.split_once('.')?;
+        let (Ok(payload), Ok(tag)) = (URL_SAFE_NO_PAD.decode(payload), URL_SAFE_NO_PAD.decode(tag)) else {
+            return None;
+        };
+        let mut mac = self.key.read().unwrap().mac();
+        mac.update(&payload);
+        mac.verify_slice(&tag).ok()?;
+        let payload = String::from_utf8(payload).ok()?;
+        let (expires_at, paths) = payload.split_once('|')?;
+        if !expires_at.parse::<u64>().is_ok_and(|t| t > now_secs()) {
+            return None;
+        }
+        if let Some(name) = paths.strip_prefix(SESSION_TOKEN_PREFIX) {
+            return self.tokens.read().unwrap().iter().find(|t| t.name == name).map(|t| t.role);
+        }
+        paths.split(',').any(|p| p == ANY_PATH || p == path).then_some(Role::Admin)
+    }
+
+    /// Runs before any handler. `None` lets the request through; otherwise
+    /// the response to send instead (login redirect for the viewer, 401
+    /// elsewhere, 403 for a role that is too low).
+    pub fn check(&self, request: &Request, path: &str, query: &HashMap<String, String>) -> Option<Response<Cursor<Vec<u8>>>> {
+        if !self.is_enabled() || PUBLIC_PATHS.contains(&path) {
+            return None;
+        }
+        let role = self
+            .token_role(request, query)
+            .or_else(|| cookie(request, SESSION_COOKIE).and_then(|s| self.verify(s, path)))
+            .or_else(|| query.get("sig").and_then(|s| self.verify(s, path)));
+        let Some(role) = role else {
+            return Some(if path == "/" {
+                redirect("/login")
+            } else {
+                crate::json_response(401, &serde_json::json!({
+                    "error": "unauthorized: send an API token, a session cookie from /login, or a ?sig= from /sign",
+                }))
+            });
+        };
+        let required = required_role(request.method(), path);
+        if role >= required {
+            return None;
+        }
+        Some(crate::json_response(403, &serde_json::json!({
+            "error": format!("forbidden: {} {} needs the {} role", request.method(), path, required.name()),
+            "role": role,
+            "required": required,
+        })))
+    }
+
+    /// `/login`, `/logout`, `/sign`, `/sign/rotate` and `/tokens`; `None` for other paths.
+    pub fn handle(&self, request: &mut Request, path: &str, query: &HashMap<String, String>) -> Option<Response<Cursor<Vec<u8>>>> {
+        let is_post = *request.method() == tiny_http::Method::Post;
+        let response = match path {
+            "/login" if !self.is_enabled() => redirect("/"),
+            "/login" if is_post => self.login(request),
+            "/login" => login_page(200, ""),
+            "/logout" => redirect("/login").with_header(
+                Header::from_bytes(
+                    &b"Set-Cookie"[..],
+                    format!("{}=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax", SESSION_COOKIE).as_bytes(),
+                )
+                .unwrap(),
+            ),
+            "/sign" | "/sign/rotate" if self.token.is_none() => {
+                crate::json_response(404, &serde_json::json!({"error": "signed URLs need --api-token"}))
+            }
+            // Session cookies and signatures can't mint new signatures
+            "/sign" | "/sign/rotate" if !self.presents_token(request, query) => {
+                crate::json_response(403, &serde_json::json!({"error": "signing requires the API token"}))
+            }
+            "/sign/rotate" if is_post => self.rotate(),
+            "/sign/rotate" => crate::json_response(405, &serde_json::json!({"error": "use POST"})),
+            "/sign" => self.sign(query),
+            _ if (path == "/tokens" || path.starts_with("/tokens/")) && self.token.is_none() => {
+                crate::json_response(404, &serde_json::json!({"error": "managing tokens needs --api-token"}))
+            }
+            _ if path == "/tokens" || path.starts_with("/tokens/") => {
+                if !self.presents_token(request, query) {
+                    crate::json_response(403, &serde_json::json!({"error": "managing tokens requires the API token"}))
+                } else {
+                    self.handle_tokens(request, path)
+                }
+            }
+            _ => return None,
+        };
+        Some(response)
+    }
+
+    fn login(&self, request: &mut Request) -> Response<Cursor<Vec<u8>>> {
+        let mut body = String::new();
+        if request.as_reader().take(4096).read_to_string(&mut body).is_err() {
+            return login_page(400, "Could not read the form");
+        }
+        let token: Option<String> = url::form_urlencoded::parse(body.as_bytes())
+            .find(|(k, _)| k == "token")
+            .map(|(_, v)| v.into_owned());
+        let given = token.unwrap_or_default();
+        let given = given.trim().as_bytes();
+        let admin = self.token.as_ref().is_some_and(|t| constant_time_eq(given, t.as_bytes()));
+        let named = self.tokens.read().unwrap().iter().find(|t| constant_time_eq(given, t.token.as_bytes())).map(|t| t.name.clone());
+        let paths = match (admin, named) {
+            (true, _) => ANY_PATH.to_string(),
+            (false, Some(name)) => format!("{}{}", SESSION_TOKEN_PREFIX, name),
+            (false, None) => {
+                log_info!("Rejected viewer login from {:?}", request.remote_addr());
+                return login_page(401, "Invalid token");
+            }
+        };
+        let session = self.sign_payload(&paths, now_secs() + SESSION_TTL_SECS);
+        let cookie = format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+            SESSION_COOKIE, session, SESSION_TTL_SECS
+        );
+        redirect("/").with_header(Header::from_bytes(&b"Set-Cookie"[..], cookie.as_bytes()).unwrap())
+    }
+
+    fn sign(&self, query: &HashMap<String, String>) -> Response<Cursor<Vec<u8>>> {
+        let ttl = match query.get("ttl").map(|t| t.parse::<u64>()) {
+            None => DEFAULT_TTL_SECS,
+            Some(Ok(t)) if (1..=MAX_TTL_SECS).contains(&t) => t,
+            Some(_) => {
+                return crate::json_response(400, &serde_json::json!({
+                    "error": format!("ttl must be 1..={} seconds", MAX_TTL_SECS),
+                }))
+            }
+        };
+        let paths: Vec<&str> = query
+            .get("paths")
+            .map(|p| p.split(',').map(str::trim).filter(|p| !p.is_empty()).collect())
+            .unwrap_or_default();
+        if paths.is_empty() {
+            return crate::json_response(400, &serde_json::json!({"error": "missing paths parameter, e.g. ?paths=/live-stream,/screenshot"}));
+        }
+        if let Some(bad) = paths.iter().find(|p| !p.starts_with('/') || p.contains('|') || p.contains('?')) {
+            return crate::json_response(400, &serde_json::json!({"error": format!("invalid path '{}'", bad)}));
+        }
+
+        let expires_at = now_secs() + ttl;
+        let sig = self.sign_payload(&paths.join(","), expires_at);
+        let urls: serde_json::Map<String, serde_json::Value> = paths
+            .iter()
+            .map(|p| (p.to_string(), format!("{}?sig={}", p, sig).into()))
+            .collect();
+        crate::json_response(200, &serde_json::json!({
+            "sig": sig,
+            "query": format!("sig={}", sig),
+            "paths": paths,
+            "expires_at": expires_at,
+            "ttl": ttl,
+            "key_id": self.key.read().unwrap().id,
+            "urls": urls,
+        }))
+    }
+
+    /// GET /tokens lists the named tokens without their values, POST adds
+    /// one and DELETE /tokens/NAME revokes one.
+    fn handle_tokens(&self, request: &mut Request, path: &str) -> Response<Cursor<Vec<u8>>> {
+        let method = request.method().clone();
+        match (method, path.strip_prefix("/tokens/")) {
+            (tiny_http::Method::Get, None) => self.list_tokens(),
+            (tiny_http::Method::Post, None) => {
+                let req = match crate::read_json_body::<TokenRequest>(request) {
+                    Ok(req) => req,
+                    Err(e) => return crate::json_response(400, &serde_json::json!({"error": e})),
+                };
+                if req.name.is_empty() || req.token.as_ref().is_some_and(|t| t.trim().is_empty()) {
+                    return crate::json_response(400, &serde_json::json!({"error": "name and token must not be empty"}));
+                }
+                let mut tokens = self.tokens.write().unwrap();
+                if tokens.iter().any(|t| t.name == req.name) {
+                    return crate::json_response(409, &serde_json::json!({"error": format!("token '{}' already exists", req.name)}));
+                }
+                let token = ApiToken {
+                    name: req.name,
+                    token: req.token.map_or_else(|| URL_SAFE_NO_PAD.encode(random_secret()), |t| t.trim().to_string()),
+                    role: req.role,
+                    created_at: crate::now_millis(),
+                };
+                log_info!("Added {} token '{}'", token.role.name(), token.name);
+                tokens.push(token.clone());
+                drop(tokens);
+                match self.save_tokens() {
+                    Ok(()) => crate::json_response(201, &serde_json::json!(token)),
+                    Err(e) => crate::json_response(500, &serde_json::json!({"error": format!("token added but not saved: {}", e), "token": token})),
+                }
+            }
+            (tiny_http::Method::Delete, Some(name)) => {
+                let name = urlencoding::decode(name).unwrap_or_default();
+                let mut tokens = self.tokens.write().unwrap();
+                let before = tokens.len();
+                tokens.retain(|t| t.name != name);
+                if tokens.len() == before {
+                    return crate::json_response(404, &serde_json::json!({"error": format!("no token named '{}'", name)}));
+                }
+                drop(tokens);
+                log_info!("Revoked token '{}'", name);
+                match self.save_tokens() {
+                    Ok(()) => self.list_tokens(),
+                    Err(e) => crate::json_response(500, &serde_json::json!({"error": format!("token revoked but not saved: {}", e)})),
+                }
+            }
+            _ => crate::json_response(405, &serde_json::json!({"error": "use GET or POST /tokens, DELETE /tokens/NAME"})),
+        }
+    }
+
+    fn list_tokens(&self) -> Response<Cursor<Vec<u8>>> {
+        let tokens: Vec<serde_json::Value> = self
+            .tokens
+            .read()
+            .unwrap()
+            .iter()
+            .map(|t| serde_json::json!({"name": t.name, "role": t.role, "created_at": t.created_at}))
+            .collect();
+        crate::json_response(200, &serde_json::json!({"tokens": tokens}))
+    }
+
+    fn save_tokens(&self) -> std::io::Result<()> {
+        let Some(path) = &self.tokens_file else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.tokens.read().unwrap()).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    fn rotate(&self) -> Response<Cursor<Vec<u8>>> {
+        let mut key = self.key.write().unwrap();
+        *key = SigningKey::generate(key.id + 1);
+        log_info!("Signing key rotated (key {}); previous signatures and sessions are invalid", key.id);
+        crate::json_response(200, &serde_json::json!({
+            "key_id": key.id,
+            "rotated_at": key.rotated_at,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_http::{Method, TestRequest};
+
+    const ADMIN: &str = "admin-token";
+    const VIEWER: &str = "viewer-token";
+    const CONTROLLER: &str = "controller-token";
+
+    fn auth() -> Auth {
+        let path = std::env::temp_dir().join(format!("rb-auth-test-{}-{:?}.json", std::process::id(), std::thread::current().id()));
+        let tokens = serde_json::json!([
+            {"name": "viewer", "token": VIEWER, "role": "viewer"},
+            {"name": "controller", "token": CONTROLLER, "role": "controller"},
+        ]);
+        std::fs::write(&path, tokens.to_string()).unwrap();
+        let auth = Auth::new(Some(ADMIN.to_string()), Some(path.clone())).unwrap();
+        std::fs::remove_file(path).unwrap();
+        auth
+    }
+
+    fn request(method: Method, path: &str, header: Option<(&str, &str)>) -> Request {
+        let mut request = TestRequest::new().with_method(method).with_path(path);
+        if let Some((name, value)) = header {
+            request = request.with_header(Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap());
+        }
+        request.into()
+    }
+
+    /// Status `check` answers with, 200 when it lets the request through.
+    fn status(auth: &Auth, method: Method, path: &str, header: Option<(&str, &str)>, query: &[(&str, &str)]) -> u16 {
+        let query = query.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let request = request(method, path, header);
+        auth.check(&request, path, &query).map_or(200, |response| response.status_code().0)
+    }
+
+    fn bearer(token: &str) -> String {
+        format!("Bearer {}", token)
+    }
+
+    #[test]
+    fn required_role_follows_routes() {
+        assert_eq!(required_role(&Method::Get, "/live-stream"), Role::Viewer);
+        assert_eq!(required_role(&Method::Head, "/status"), Role::Viewer);
+        assert_eq!(required_role(&Method::Get, "/metrics"), Role::Viewer);
+        // Read routes need a controller for anything but GET
+        assert_eq!(required_role(&Method::Post, "/offline"), Role::Controller);
+        assert_eq!(required_role(&Method::Get, "/cpu-throttle"), Role::Viewer);
+        assert_eq!(required_role(&Method::Post, "/cpu-throttle"), Role::Controller);
+        assert_eq!(required_role(&Method::Delete, "/cpu-throttle"), Role::Controller);
+        // Viewers read the error log; clearing it is a controller's
+        assert_eq!(required_role(&Method::Get, "/errors"), Role::Viewer);
+        assert_eq!(required_role(&Method::Delete, "/errors"), Role::Controller);
+        assert_eq!(required_role(&Method::Get, "/navigate"), Role::Controller);
+        assert_eq!(required_role(&Method::Post, "/click"), Role::Controller);
+        assert_eq!(required_role(&Method::Get, "/tokens"), Role::Admin);
+        assert_eq!(required_role(&Method::Delete, "/tokens/viewer"), Role::Admin);
+        // Unlisted routes default to admin
+        assert_eq!(required_role(&Method::Get, "/no-such-route"), Role::Admin);
+    }
+
+    #[test]
+    fn required_role_matches_wildcards_and_first_entry() {
+        assert_eq!(required_role(&Method::Get, "/annotations/3"), Role::Viewer);
+        assert_eq!(required_role(&Method::Post, "/mock/rule"), Role::Controller);
+        // `/*` covers sub paths only, not the prefix itself or a longer name
+        assert_eq!(required_role(&Method::Get, "/storage"), Role::Admin);
+        assert_eq!(required_role(&Method::Get, "/mockery"), Role::Admin);
+        // /media/emulation is listed before /media/*
+        assert_eq!(required_role(&Method::Get, "/media/emulation"), Role::Viewer);
+        assert_eq!(required_role(&Method::Get, "/media/play"), Role::Controller);
+    }
+
+    #[test]
+    fn roles_are_ordered() {
+        assert!(Role::Viewer < Role::Controller);
+        assert!(Role::Controller < Role::Admin);
+    }
+
+    #[test]
+    fn constant_time_eq_compares_bytes_and_length() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"secret", b""));
+    }
+
+    #[test]
+    fn random_secrets_differ() {
+        assert_ne!(random_secret(), random_secret());
+    }
+
+    #[test]
+    fn check_is_off_without_tokens() {
+        let auth = Auth::new(None, None).unwrap();
+        assert_eq!(status(&auth, Method::Post, "/navigate", None, &[]), 200);
+    }
+
+    #[test]
+    fn check_needs_credentials() {
+        let auth = auth();
+        assert_eq!(status(&auth, Method::Get, "/status", None, &[]), 401);
+        assert_eq!(status(&auth, Method::Get, "/", None, &[]), 303);
+        assert_eq!(status(&auth, Method::Get, "/login", None, &[]), 200);
+        assert_eq!(status(&auth, Method::Get, "/status", Some(("Authorization", &bearer("wrong"))), &[]), 401);
+    }
+
+    #[test]
+    fn viewer_may_watch_but_not_navigate() {
+        let auth = auth();
+        let header = bearer(VIEWER);
+        assert_eq!(status(&auth, Method::Get, "/live-stream", Some(("Authorization", &header)), &[]), 200);
+        assert_eq!(status(&auth, Method::Post, "/navigate", Some(("Authorization", &header)), &[]), 403);
+        assert_eq!(status(&auth, Method::Get, "/navigate", None, &[("token", VIEWER)]), 403);
+    }
+
+    #[test]
+    fn only_controllers_clear_the_error_log() {
+        let auth = auth();
+        let (viewer, controller) = (bearer(VIEWER), bearer(CONTROLLER));
+        assert_eq!(status(&auth, Method::Get, "/errors", Some(("Authorization", &viewer)), &[]), 200);
+        assert_eq!(status(&auth, Method::Delete, "/errors", Some(("Authorization", &viewer)), &[]), 403);
+        assert_eq!(status(&auth, Method::Delete, "/errors", Some(("Authorization", &controller)), &[]), 200);
+    }
+
+    #[test]
+    fn check_orders_roles() {
+        let auth = auth();
+        let controller = bearer(CONTROLLER);
+        let admin = bearer(ADMIN);
+        assert_eq!(status(&auth, Method::Post, "/navigate", Some(("Authorization", &controller)), &[]), 200);
+        assert_eq!(status(&auth, Method::Get, "/tokens", Some(("Authorization", &controller)), &[]), 403);
+        assert_eq!(status(&auth, Method::Get, "/tokens", Some(("Authorization", &admin)), &[]), 200);
+        assert_eq!(status(&auth, Method::Get, "/live-stream", Some(("Authorization", &admin)), &[]), 200);
+    }
+
+    #[test]
+    fn session_cookie_carries_the_token_role() {
+        let auth = auth();
+        let session = auth.sign_payload("*@viewer", now_secs() + 60);
+        let cookie = format!("other=1; {}={}", SESSION_COOKIE, session);
+        assert_eq!(status(&auth, Method::Get, "/live-stream", Some(("Cookie", &cookie)), &[]), 200);
+        assert_eq!(status(&auth, Method::Post, "/navigate", Some(("Cookie", &cookie)), &[]), 403);
+        // A cookie under another name is no session
+        let misnamed = format!("session={}", session);
+        assert_eq!(status(&auth, Method::Get, "/live-stream", Some(("Cookie", &misnamed)), &[]), 401);
+
+        let admin = auth.sign_payload(ANY_PATH, now_secs() + 60);
+        let cookie = format!("{}={}", SESSION_COOKIE, admin);
+        assert_eq!(status(&auth, Method::Get, "/tokens", Some(("Cookie", &cookie)), &[]), 200);
+    }
+
+    #[test]
+    fn header_token_wins_over_cookie() {
+        let auth = auth();
+        let session = auth.sign_payload(ANY_PATH, now_secs() + 60);
+        let request: Request = TestRequest::new()
+            .with_method(Method::Post)
+            .with_path("/navigate")
+            .with_header(Header::from_bytes(&b"Authorization"[..], bearer(VIEWER).as_bytes()).unwrap())
+            .with_header(Header::from_bytes(&b"Cookie"[..], format!("{}={}", SESSION_COOKIE, session).as_bytes()).unwrap())
+            .into();
+        let response = auth.check(&request, "/navigate", &HashMap::new());
+        assert_eq!(response.map(|r| r.status_code().0), Some(403));
+    }
+
+    #[test]
+    fn signatures_cover_their_paths_until_expiry() {
+        let auth = auth();
+        let sig = auth.sign_payload("/screenshot,/status", now_secs() + 60);
+        assert_eq!(status(&auth, Method::Get, "/screenshot", None, &[("sig", &sig)]), 200);
+        assert_eq!(status(&auth, Method::Get, "/status", None, &[("sig", &sig)]), 200);
+        assert_eq!(status(&auth, Method::Get, "/frame", None, &[("sig", &sig)]), 401);
+
+        let expired = auth.sign_payload("/screenshot", now_secs() - 1);
+        assert_eq!(status(&auth, Method::Get, "/screenshot", None, &[("sig", &expired)]), 401);
+    }
+
+    #[test]
+    fn tampered_or_rotated_signatures_are_rejected() {
+        let auth = auth();
+        let sig = auth.sign_payload("/screenshot", now_secs() + 60);
+        let (payload, tag) = sig.split_once('.').unwrap();
+        let forged_payload = URL_SAFE_NO_PAD.encode(format!("{}|*", now_secs() + 60));
+        let forged = [
+            format!("{}.{}", payload, URL_SAFE_NO_PAD.encode([0u8; 32])),
+            format!("{}.{}", forged_payload, tag),
+            "garbage".to_string(),
+        ];
+        for bad in forged {
+            assert_eq!(status(&auth, Method::Get, "/screenshot", None, &[("sig", &bad)]), 401, "{}", bad);
+        }
+
+        auth.rotate();
+        assert_eq!(status(&auth, Method::Get, "/screenshot", None, &[("sig", &sig)]), 401);
+    }
+
+    #[test]
+    fn revoking_a_token_ends_its_sessions() {
+        let auth = auth();
+        let session = format!("{}={}", SESSION_COOKIE, auth.sign_payload("*@viewer", now_secs() + 60));
+        auth.tokens.write().unwrap().retain(|t| t.name != "viewer");
+        assert_eq!(status(&auth, Method::Get, "/live-stream", Some(("Cookie", &session)), &[]), 401);
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use image::{ImageFormat, Rgb, RgbImage};
+use serde::Serialize;
+
+use crate::report::PageReport;
+
+// ============== Report Baselines ==============
+//
+// --baseline DIR compares a --report run with an earlier one, matching
+// pages by URL. Each pair gets the share of pixels that differ by more than
+// PIXEL_TOLERANCE in any channel (JPEG noise stays below it), a -diff.png
+// with the changed pixels in red, and a line diff of the extracted text.
+// A page changed when its pixel diff exceeds --baseline-max-diff or its
+// text differs; pages only in one run are new or removed. Any of those
+// makes the verdict "changed", which --report turns into exit code 3.
+// Ignore regions are the redaction selectors: both runs must use the same
+// ones, and the same deterministic-render and viewport settings, for the
+// diff to mean anything. index.json records them and a mismatch with the
+// baseline is reported as a warning.
+
+const PIXEL_TOLERANCE: u8 = 32;
+const CHANGED: Rgb<u8> = Rgb([230, 30, 30]);
+/// Text diffs are computed line by line up to this many LCS cells; bigger
+/// pages fall back to comparing line sets.
+const MAX_LCS_CELLS: usize = 4_000_000;
+const MAX_DIFF_LINES: usize = 200;
+
+pub struct BaselineOptions {
+    pub dir: PathBuf,
+    /// Pixel diff percentage above which a page counts as changed.
+    pub max_diff_percent: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageChange {
+    Unchanged,
+    Changed,
+    New,
+    Removed,
+    /// Failed to render in this run or the baseline.
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageDelta {
+    pub url: String,
+    pub change: PageChange,
+    pub pixel_diff_percent: Option<f64>,
+    pub size_changed: bool,
+    pub text_changed: bool,
+    /// `- old` / `+ new` lines, capped at MAX_DIFF_LINES.
+    pub text_diff: Vec<String>,
+    pub diff_image: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Delta {
+    pub baseline: PathBuf,
+    /// "changed" or "unchanged".
+    pub verdict: &'static str,
+    pub max_diff_percent: f64,
+    pub changed: usize,
+    pub unchanged: usize,
+    pub new: usize,
+    pub removed: usize,
+    pub failed: usize,
+    pub warnings: Vec<String>,
+    pub pages: Vec<PageDelta>,
+}
+
+impl Delta {
+    pub fn is_changed(&self) -> bool {
+        self.verdict == "changed"
+    }
+
+    pub fn page(&self, url: &str) -> Option<&PageDelta> {
+        self.pages.iter().find(|p| p.url == url)
+    }
+}
+
+/// Compares this run's pages (already written to `dir`) with the baseline
+/// and writes the diff images next to the current captures.
+pub fn compare(options: &BaselineOptions, dir: &Path, reports: &[PageReport], settings: &serde_json::Value) -> Result<Delta, String> {
+    let index_path = options.dir.join("index.json");
+    let index: serde_json::Value = std::fs::read_to_string(&index_path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Failed to read baseline {}: {}", index_path.display(), e))?;
+    let old_pages: HashMap<String, &serde_json::Value> = index["pages"]
+        .as_array()
+        .map(|pages| pages.iter().filter_map(|p| Some((p["url"].as_str()?.to_string(), p))).collect())
+        .unwrap_or_default();
+
+    let mut warnings = Vec::new();
+    for key in ["deterministic", "ignore_selectors", "viewport"] {
+        if index["settings"][key] != settings[key] {
+            warnings.push(format!("{} differs from the baseline ({} vs {})", key, index["settings"][key], settings[key]));
+        }
+    }
+
+    let mut pages = Vec::new();
+    for report in reports {
+        let delta = match old_pages.get(&report.url) {
+            None => PageDelta::new(&report.url, PageChange::New),
+            Some(old) => compare_page(options, dir, report, old),
+        };
+        pages.push(delta);
+    }
+    let current: HashSet<&str> = reports.iter().map(|r| r.url.as_str()).collect();
+    let mut removed: Vec<&String> = old_pages.keys().filter(|url| !current.contains(url.as_str())).collect();
+    removed.sort();
+    pages.extend(removed.into_iter().map(|url| PageDelta::new(url, PageChange::Removed)));
+
+    let count = |change: PageChange| pages.iter().filter(|p| p.change == change).count();
+    let unchanged = count(PageChange::Unchanged);
+    Ok(Delta {
+        baseline: options.dir.clone(),
+        verdict: if unchanged == pages.len() { "unchanged" } else { "changed" },
+        max_diff_percent: options.max_diff_percent,
+        changed: count(PageChange::Changed),
+        unchanged,
+        new: count(PageChange::New),
+        removed: count(PageChange::Removed),
+        failed: count(PageChange::Failed),
+        warnings,
+        pages,
+    })
+}
+
+impl PageChange {
+    pub fn name(self) -> &'static str {
+        match self {
+            PageChange::Unchanged => "unchanged",
+            PageChange::Changed => "changed",
+            PageChange::New => "new",
+            PageChange::Removed => "removed",
+            PageChange::Failed => "failed",
+        }
+    }
+}
+
+impl PageDelta {
+    fn new(url: &str, change: PageChange) -> Self {
+        PageDelta {
+            url: url.to_string(),
+            change,
+            pixel_diff_percent: None,
+            size_changed: false,
+            text_changed: false,
+            text_diff: Vec::new(),
+            diff_image: None,
+        }
+    }
+}
+
+fn compare_page(options: &BaselineOptions, dir: &Path, report: &PageReport, old: &serde_json::Value) -> PageDelta {
+    let mut delta = PageDelta::new(&report.url, PageChange::Failed);
+    let (Some(screenshot), Some(old_screenshot)) = (&report.screenshot, old["screenshot"].as_str()) else {
+        return delta;
+    };
+    let (Ok(new_img), Ok(old_img)) = (image::open(dir.join(screenshot)), image::open(options.dir.join(old_screenshot))) else {
+        return delta;
+    };
+    let (new_img, old_img) = (new_img.to_rgb8(), old_img.to_rgb8());
+
+    let percent = if new_img.dimensions() == old_img.dimensions() {
+        let (percent, diff) = pixel_diff(&old_img, &new_img);
+        let relative = screenshot.replace(".jpg", "-diff.png");
+        match diff.save_with_format(dir.join(&relative), ImageFormat::Png) {
+            Ok(()) => delta.diff_image = Some(relative),
+            Err(e) => log_error!("Failed to write {}: {}", relative, e),
+        }
+        percent
+    } else {
+        delta.size_changed = true;
+        100.0
+    };
+    delta.pixel_diff_percent = Some(percent);
+
+    let read = |base: &Path, path: Option<&str>| path.and_then(|p| std::fs::read_to_string(base.join(p)).ok()).unwrap_or_default();
+    let (text, old_text) = (read(dir, report.text.as_deref()), read(&options.dir, old["text"].as_str()));
+    if text != old_text {
+        delta.text_changed = true;
+        delta.text_diff = line_diff(&old_text, &text);
+    }
+
+    delta.change = if percent > options.max_diff_percent || delta.text_changed { PageChange::Changed } else { PageChange::Unchanged };
+    delta
+}
+
+/// Percentage of pixels that differ, and an image of `new` faded to grey
+/// with those pixels in red.
+fn pixel_diff(old: &RgbImage, new: &RgbImage) -> (f64, RgbImage) {
+    let mut diff = RgbImage::new(new.width(), new.height());
+    let mut changed = 0u64;
+    for (x, y, pixel) in new.enumerate_pixels() {
+        let before = old.get_pixel(x, y);
+        let delta = pixel.0.iter().zip(before.0).map(|(a, b)| a.abs_diff(b)).max().unwrap_or(0);
+        if delta > PIXEL_TOLERANCE {
+            changed += 1;
+            diff.put_pixel(x, y, CHANGED);
+        } else {
+            let grey = (192 + (pixel[0] as u32 + pixel[1] as u32 + pixel[2] as u32) / 12) as u8;
+            diff.put_pixel(x, y, Rgb([grey, grey, grey]));
+        }
+    }
+    let total = (new.width() as u64 * new.height() as u64).max(1);
+    (changed as f64 * 100.0 / total as f64, diff)
+}
+
+/// `- old` and `+ new` lines from a longest-common-subsequence diff.
+fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let mut out = Vec::new();
+    if a.len().saturating_mul(b.len()) > MAX_LCS_CELLS {
+        let (old_lines, new_lines): (HashSet<&str>, HashSet<&str>) = (a.iter().copied().collect(), b.iter().copied().collect());
+        out.extend(a.iter().filter(|l| !new_lines.contains(*l)).map(|l| format!("- {}", l)));
+        out.extend(b.iter().filter(|l| !old_lines.contains(*l)).map(|l| format!("+ {}", l)));
+    } else {
+        // lcs[i][j]: common subsequence length of a[i..] and b[j..]
+        let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                i += 1;
+                j += 1;
+            } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+                out.push(format!("+ {}", b[j]));
+                j += 1;
+            } else {
+                out.push(format!("- {}", a[i]));
+                i += 1;
+            }
+        }
+    }
+    if out.len() > MAX_DIFF_LINES {
+        let more = out.len() - MAX_DIFF_LINES;
+        out.truncate(MAX_DIFF_LINES);
+        out.push(format!("... {} more lines", more));
+    }
+    out
+}
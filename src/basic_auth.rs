@@ -0,0 +1,132 @@
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    AuthChallengeResponse, AuthChallengeResponseResponse, AuthChallengeSource, ContinueWithAuthParams, EventAuthRequired,
+};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use serde::Deserialize;
+
+// ============== HTTP Basic Auth ==============
+//
+// --basic-auth user:pass and POST /auth register credentials for pages behind
+// HTTP authentication, which otherwise render Chrome's auth failure. They
+// are answered from the Fetch domain (Fetch.enable with handleAuthRequests,
+// installed together with request interception): each Fetch.authRequired
+// from a server gets the credentials when they are unscoped or their origin
+// (--basic-auth-origin, POST `origin`) matches the challenge's. Other origins
+// and proxy challenges get Chrome's default handling, so the password isn't
+// sent to every site that asks. A challenge repeated for a request that was
+// already answered means the credentials were refused and is cancelled
+// instead of retried forever. DELETE /auth clears them. The password is
+// never echoed back; every answer reports how many challenges were answered
+// since launch.
+
+#[derive(Debug, Deserialize)]
+pub struct AuthRequest {
+    pub username: String,
+    pub password: String,
+    #[serde(default)]
+    pub origin: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Credentials {
+    username: String,
+    password: String,
+    /// Serialized origin, e.g. https://intranet.example:8443.
+    origin: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct BasicAuth {
+    credentials: Option<Credentials>,
+    answered: u64,
+    last_answered_at: Option<u128>,
+    /// Request last given the credentials, to spot a refusal.
+    last_request: Option<String>,
+}
+
+pub type SharedBasicAuth = Arc<Mutex<BasicAuth>>;
+
+/// --basic-auth USER:PASS; the password may contain ':'.
+pub fn parse_flag(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((user, pass)) if !user.is_empty() => Ok((user.to_string(), pass.to_string())),
+        _ => Err(format!("invalid credentials '{}', expected USER:PASS", value)),
+    }
+}
+
+/// Normalizes --basic-auth-origin and POST `origin` to scheme://host[:port].
+pub fn parse_origin(value: &str) -> Result<String, String> {
+    let url = url::Url::parse(value.trim()).map_err(|e| format!("invalid origin '{}': {}", value, e))?;
+    match url.origin() {
+        origin @ url::Origin::Tuple(..) => Ok(origin.ascii_serialization()),
+        url::Origin::Opaque(_) => Err(format!("'{}' has no origin", value)),
+    }
+}
+
+impl BasicAuth {
+    pub fn is_configured(&self) -> bool {
+        self.credentials.is_some()
+    }
+
+    pub fn set(&mut self, username: String, password: String, origin: Option<String>) -> Result<(), String> {
+        if username.is_empty() {
+            return Err("username must not be empty".to_string());
+        }
+        let origin = origin.filter(|o| !o.trim().is_empty()).map(|o| parse_origin(&o)).transpose()?;
+        self.credentials = Some(Credentials { username, password, origin });
+        self.last_request = None;
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.credentials = None;
+        self.last_request = None;
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "configured": self.credentials.is_some(),
+            "username": self.credentials.as_ref().map(|c| c.username.as_str()),
+            "origin": self.credentials.as_ref().and_then(|c| c.origin.as_deref()),
+            "challenges_answered": self.answered,
+            "last_answered_at": self.last_answered_at,
+        })
+    }
+
+    fn respond(&mut self, event: &EventAuthRequired) -> AuthChallengeResponse {
+        let pass = |response| AuthChallengeResponse { response, username: None, password: None };
+        if event.auth_challenge.source == Some(AuthChallengeSource::Proxy) {
+            return pass(AuthChallengeResponseResponse::Default);
+        }
+        let request_id: &str = event.request_id.as_ref();
+        let Some(credentials) = &self.credentials else {
+            return pass(AuthChallengeResponseResponse::Default);
+        };
+        if credentials.origin.as_ref().is_some_and(|origin| *origin != event.auth_challenge.origin) {
+            return pass(AuthChallengeResponseResponse::Default);
+        }
+        if self.last_request.as_deref() == Some(request_id) {
+            log_info!("Credentials for {} were refused", event.auth_challenge.origin);
+            return pass(AuthChallengeResponseResponse::CancelAuth);
+        }
+        let response = AuthChallengeResponse {
+            response: AuthChallengeResponseResponse::ProvideCredentials,
+            username: Some(credentials.username.clone()),
+            password: Some(credentials.password.clone()),
+        };
+        self.answered += 1;
+        self.last_answered_at = Some(crate::now_millis());
+        self.last_request = Some(request_id.to_string());
+        response
+    }
+}
+
+/// Answers one Fetch.authRequired of the streamed page.
+pub async fn answer(page: &Page, auth: &SharedBasicAuth, event: &EventAuthRequired) -> Result<(), CdpError> {
+    let response = auth.lock().unwrap().respond(event);
+    page.execute(ContinueWithAuthParams::new(event.request_id.clone(), response)).await?;
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use std::time::{Duration, Instant};
+
+use chromiumoxide::cdp::browser_protocol::page::{CaptureScreenshotFormat, Viewport};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::Page;
+
+use crate::pipeline::PipelineStats;
+
+// ============== Pipeline Benchmark ==============
+
+const QUALITIES: [u8; 3] = [50, 80, 95];
+const SCALES: [f64; 3] = [1.0, 0.5, 0.25];
+
+/// Animated fixture so consecutive frames differ like a real dashboard.
+const FIXTURE_HTML: &str = r#"<!DOCTYPE html>
+<html><head><style>
+body { margin: 0; font-family: sans-serif; background: linear-gradient(135deg, #1d3557, #457b9d); color: #fff; }
+.grid { display: grid; grid-template-columns: repeat(4, 1fr); gap: 16px; padding: 24px; }
+.card { background: rgba(255,255,255,0.12); border-radius: 8px; padding: 16px; height: 120px; }
+.bar { height: 12px; background: #e63946; border-radius: 6px; animation: grow 1.5s ease-in-out infinite alternate; }
+.spin { width: 48px; height: 48px; border: 6px solid #f1faee; border-top-color: transparent; border-radius: 50%; animation: spin 1s linear infinite; }
+@keyframes grow { from { width: 10%; } to { width: 100%; } }
+@keyframes spin { to { transform: rotate(360deg); } }
+</style></head><body>
+<h1 style="padding: 0 24px">Pipeline benchmark <span id="clock"></span></h1>
+<div class="grid" id="grid"></div>
+<script>
+const grid = document.getElementById('grid');
+for (let i = 0; i < 16; i++) {
+    grid.insertAdjacentHTML('beforeend', '<div class="card"><p>Metric ' + i + ' <b id="v' + i + '"></b></p><div class="bar" style="animation-delay:-' + (i / 10) + 's"></div>' + (i % 5 === 0 ? '<div class="spin"></div>' : '') + '</div>');
+}
+setInterval(function() {
+    document.getElementById('clock').textContent = performance.now().toFixed(0);
+    for (let i = 0; i < 16; i++) document.getElementById('v' + i).textContent = (Math.random() * 1000).toFixed(1);
+}, 50);
+</script></body></html>"#;
+
+fn params(quality: u8, scale: f64, viewport: (u32, u32)) -> ScreenshotParams {
+    ScreenshotParams::builder()
+        .format(CaptureScreenshotFormat::Jpeg)
+        .quality(quality as i64)
+        .clip(Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: viewport.0 as f64,
+            height: viewport.1 as f64,
+            scale,
+        })
+        .build()
+}
+
+/// The fixture as a data URL, also the page --soak runs against.
+pub fn fixture_url() -> String {
+    format!("data:text/html,{}", urlencoding::encode(FIXTURE_HTML))
+}
+
+/// Runs capture → decode → encode for every quality/scale combination,
+/// splitting `duration` between them, and returns the per-setting breakdown.
+pub async fn run(page: &Page, viewport: (u32, u32), duration: Duration) -> Result<serde_json::Value, CdpError> {
+    page.goto(fixture_url()).await?;
+
+    let per_setting = (duration / (QUALITIES.len() * SCALES.len()) as u32).max(Duration::from_secs(1));
+    let mut results = Vec::new();
+
+    for scale in SCALES {
+        for quality in QUALITIES {
+            let mut stats = PipelineStats::default();
+            let started = Instant::now();
+            let mut frames = 0u64;
+
+            while started.elapsed() < per_setting {
+                let t = Instant::now();
+                let jpeg = page.screenshot(params(quality, scale, viewport)).await?;
+                stats.record("screenshot", t.elapsed());
+
+                let t = Instant::now();
+                let decoded = image::load_from_memory(&jpeg).map(|img| img.to_rgb8());
+                stats.record("decode", t.elapsed());
+
+                if let Ok(img) = decoded {
+                    let t = Instant::now();
+                    let _ = crate::overlay::encode_jpeg(&img, quality);
+                    stats.record("encode", t.elapsed());
+                }
+                stats.record_frame(jpeg.len());
+                frames += 1;
+            }
+
+            let elapsed = started.elapsed().as_secs_f64();
+            let mut result = stats.to_json();
+            result["quality"] = quality.into();
+            result["scale"] = scale.into();
+            result["width"] = ((viewport.0 as f64 * scale).round() as u64).into();
+            result["height"] = ((viewport.1 as f64 * scale).round() as u64).into();
+            result["frames"] = frames.into();
+            result["fps"] = ((frames as f64 / elapsed * 100.0).round() / 100.0).into();
+            results.push(result);
+        }
+    }
+
+    let frame = page.screenshot(params(crate::JPEG_QUALITY, 1.0, viewport)).await?;
+    let gui_encoders = image::load_from_memory(&frame)
+        .map(|img| compare_gui_encoders(&img.to_rgba8()))
+        .unwrap_or_default();
+
+    Ok(serde_json::json!({
+        "viewport": { "width": viewport.0, "height": viewport.1 },
+        "seconds_per_setting": per_setting.as_secs_f64(),
+        "arch": std::env::consts::ARCH,
+        "os": std::env::consts::OS,
+        "results": results,
+        "gui_encoders": gui_encoders,
+    }))
+}
+
+/// Times the GUI RGBA→JPEG path: the original convert-then-encode through
+/// `DynamicImage` against `FrameEncoder`.
+fn compare_gui_encoders(rgba: &image::RgbaImage) -> serde_json::Value {
+    const ITERATIONS: usize = 30;
+    let mut stats = PipelineStats::default();
+
+    for _ in 0..ITERATIONS {
+        let t = Instant::now();
+        let rgb = image::DynamicImage::ImageRgba8(rgba.clone()).to_rgb8();
+        let _ = crate::overlay::encode_jpeg(&rgb, crate::JPEG_QUALITY);
+        stats.record("original", t.elapsed());
+    }
+
+    let mut encoder = crate::encoder::FrameEncoder::new(crate::JPEG_QUALITY);
+    for _ in 0..ITERATIONS {
+        let t = Instant::now();
+        let _ = encoder.encode_rgba(rgba.as_raw(), rgba.width(), rgba.height(), |_| {});
+        stats.record(crate::encoder::NAME, t.elapsed());
+    }
+
+    let mut result = stats.to_json()["stages_ms"].take();
+    result["active"] = crate::encoder::NAME.into();
+    result
+}
+
+/// Human-readable table of a `run` report.
+pub fn print_table(report: &serde_json::Value) {
+    println!(
+        "Pipeline benchmark ({}/{}, viewport {}x{})",
+        report["os"].as_str().unwrap_or_default(),
+        report["arch"].as_str().unwrap_or_default(),
+        report["viewport"]["width"],
+        report["viewport"]["height"]
+    );
+    println!(
+        "{:>9} {:>7} {:>7} {:>16} {:>16} {:>16} {:>10}",
+        "size", "quality", "fps", "screenshot ms", "decode ms", "encode ms", "KB/frame"
+    );
+    for r in report["results"].as_array().into_iter().flatten() {
+        let stage = |name: &str| {
+            let s = &r["stages_ms"][name];
+            format!("{:.1} / p95 {:.1}", s["avg"].as_f64().unwrap_or(0.0), s["p95"].as_f64().unwrap_or(0.0))
+        };
+        println!(
+            "{:>9} {:>7} {:>7.1} {:>16} {:>16} {:>16} {:>10.1}",
+            format!("{}x{}", r["width"], r["height"]),
+            r["quality"].as_u64().unwrap_or(0),
+            r["fps"].as_f64().unwrap_or(0.0),
+            stage("screenshot"),
+            stage("decode"),
+            stage("encode"),
+            r["bytes_per_frame"]["avg"].as_f64().unwrap_or(0.0) / 1024.0
+        );
+    }
+    let encoders = &report["gui_encoders"];
+    if let Some(active) = encoders["active"].as_str() {
+        let avg = |name: &str| encoders[name]["avg"].as_f64().unwrap_or(0.0);
+        println!(
+            "GUI encode at {}x{}: original {:.1} ms, {} {:.1} ms",
+            report["viewport"]["width"],
+            report["viewport"]["height"],
+            avg("original"),
+            active,
+            avg(active)
+        );
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// ============== ABP Network Filter Blocker ==============
+//
+// Supports the network-filter subset of the Adblock Plus syntax: `||host^`,
+// `|` anchors, `*` and `^` wildcards, `@@` exceptions and the common `$`
+// options (resource types, third-party, domain=, important, match-case).
+// Cosmetic filters (`##`, `#@#`, `#?#`) and filters with options we cannot
+// honour are skipped and counted as unsupported.
+//
+// One filter list drives both modes. Headless checks every request in the
+// Fetch interceptor. The GUI webview only lets us veto top-level
+// navigations, so there the list applies to main-frame documents alone,
+// which ABP only blocks with an explicit `$document`.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Anchor {
+    None,
+    Start,
+    Host,
+}
+
+#[derive(Debug, Default)]
+struct FilterOptions {
+    include_types: Vec<String>,
+    exclude_types: Vec<String>,
+    third_party: Option<bool>,
+    include_domains: Vec<String>,
+    exclude_domains: Vec<String>,
+    important: bool,
+}
+
+#[derive(Debug)]
+struct NetworkFilter {
+    text: String,
+    pattern: Vec<u8>,
+    anchor: Anchor,
+    anchor_end: bool,
+    exception: bool,
+    options: FilterOptions,
+}
+
+pub struct RequestInfo<'a> {
+    pub url: &'a str,
+    /// ABP resource type name: document, subdocument, script, image, ...
+    pub resource_type: &'a str,
+    pub page_host: &'a str,
+}
+
+#[derive(Default)]
+pub struct Blocker {
+    path: Option<PathBuf>,
+    filters: Vec<NetworkFilter>,
+    index: HashMap<String, Vec<usize>>,
+    fallback: Vec<usize>,
+    hits: Vec<u64>,
+    unsupported: usize,
+    checked: u64,
+    blocked: u64,
+    allowed_by_exception: u64,
+    loaded_at: u128,
+}
+
+pub type SharedBlocker = Arc<Mutex<Blocker>>;
+
+const RESOURCE_TYPES: &[&str] = &[
+    "document", "subdocument", "script", "image", "stylesheet", "xmlhttprequest",
+    "font", "media", "websocket", "ping", "object", "other",
+];
+
+/// The --filter-list blocker, or a disabled one without it.
+pub fn load(path: Option<&PathBuf>) -> Result<Blocker, String> {
+    match path {
+        Some(path) => {
+            let blocker = Blocker::from_file(path.clone())
+                .map_err(|e| format!("Failed to load filter list {}: {}", path.display(), e))?;
+            log_info!("Filter list: {} ({} filters)", path.display(), blocker.stats()["filters"]);
+            Ok(blocker)
+        }
+        None => Ok(Blocker::default()),
+    }
+}
+
+/// What the filter list and host rules can reach in each mode.
+pub fn coverage(gui: bool) -> serde_json::Value {
+    if gui {
+        serde_json::json!({
+            "mode": "gui",
+            "filters": "top-level navigations ($document filters)",
+            "host_rules": "top-level navigations are redirected to the target",
+            "subresources": false,
+        })
+    } else {
+        serde_json::json!({
+            "mode": "headless",
+            "filters": "all requests",
+            "host_rules": "launch rules resolve in Chrome; runtime rules rewrite requests and keep the Host header",
+            "subresources": true,
+        })
+    }
+}
+
+/// GET /blocker/stats and POST /blocker/reload; None for other routes.
+pub fn handle(request: &tiny_http::Request, url: &str, blocker: &SharedBlocker, gui: bool) -> Option<tiny_http::Response<std::io::Cursor<Vec<u8>>>> {
+    if url == "/blocker/stats" {
+        let mut stats = blocker.lock().unwrap().stats();
+        stats["coverage"] = coverage(gui);
+        Some(crate::json_response(200, &stats))
+    } else if url == "/blocker/reload" && *request.method() == tiny_http::Method::Post {
+        let result = blocker.lock().unwrap().reload();
+        Some(match result {
+            Ok(count) => crate::json_response(200, &serde_json::json!({"status": "reloaded", "filters": count})),
+            Err(e) => crate::json_response(500, &serde_json::json!({"error": e.to_string()})),
+        })
+    } else {
+        None
+    }
+}
+
+impl Blocker {
+    pub fn from_file(path: PathBuf) -> std::io::Result<Self> {
+        let mut blocker = Blocker { path: Some(path), ..Default::default() };
+        blocker.reload()?;
+        Ok(blocker)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Re-reads the filter list from disk, resetting all counters.
+    pub fn reload(&mut self) -> std::io::Result<usize> {
+        let path = self.path.clone().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no filter list configured")
+        })?;
+        let text = std::fs::read_to_string(&path)?;
+        *self = Blocker { path: Some(path), ..Default::default() };
+        for line in text.lines() {
+            match parse_filter(line.trim()) {
+                Some(Ok(filter)) => self.add(filter),
+                Some(Err(())) => self.unsupported += 1,
+                None => {}
+            }
+        }
+        self.hits = vec![0; self.filters.len()];
+        self.loaded_at = crate::now_millis();
+        Ok(self.filters.len())
+    }
+
+    fn add(&mut self, filter: NetworkFilter) {
+        let idx = self.filters.len();
+        match index_token(&filter) {
+            Some(token) => self.index.entry(token).or_default().push(idx),
+            None => self.fallback.push(idx),
+        }
+        self.filters.push(filter);
+    }
+
+    /// Returns the text of the filter that blocks this request, if any.
+    pub fn check(&mut self, req: &RequestInfo) -> Option<String> {
+        if self.filters.is_empty() {
+            return None;
+        }
+        self.checked += 1;
+
+        let url = req.url.to_ascii_lowercase();
+        let host = url::Url::parse(&url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let third_party = base_domain(&host) != base_domain(req.page_host);
+
+        let mut candidates: Vec<usize> = self.fallback.clone();
+        for token in url.split(|c: char| !c.is_ascii_alphanumeric()).filter(|t| t.len() > 1) {
+            if let Some(ids) = self.index.get(token) {
+                candidates.extend_from_slice(ids);
+            }
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let matches = |f: &NetworkFilter| {
+            f.options.applies(req.resource_type, third_party, req.page_host)
+                && f.matches_url(url.as_bytes(), &host)
+        };
+
+        let block = candidates
+            .iter()
+            .copied()
+            .filter(|&i| !self.filters[i].exception && matches(&self.filters[i]))
+            .max_by_key(|&i| self.filters[i].options.important)?;
+
+        if !self.filters[block].options.important {
+            if let Some(exception) = candidates
+                .iter()
+                .copied()
+                .find(|&i| self.filters[i].exception && matches(&self.filters[i]))
+            {
+                self.hits[exception] += 1;
+                self.allowed_by_exception += 1;
+                return None;
+            }
+        }
+
+        self.hits[block] += 1;
+        self.blocked += 1;
+        Some(self.filters[block].text.clone())
+    }
+
+    pub fn stats(&self) -> serde_json::Value {
+        let mut per_filter: Vec<_> = self
+            .filters
+            .iter()
+            .zip(&self.hits)
+            .filter(|(_, hits)| **hits > 0)
+            .map(|(f, hits)| serde_json::json!({
+                "filter": f.text,
+                "exception": f.exception,
+                "hits": hits,
+            }))
+            .collect();
+        per_filter.sort_by_key(|v| std::cmp::Reverse(v["hits"].as_u64().unwrap_or(0)));
+
+        serde_json::json!({
+            "enabled": self.is_enabled(),
+            "filter_list": self.path.as_ref().map(|p| p.display().to_string()),
+            "loaded_at": self.loaded_at,
+            "filters": self.filters.iter().filter(|f| !f.exception).count(),
+            "exceptions": self.filters.iter().filter(|f| f.exception).count(),
+            "unsupported": self.unsupported,
+            "requests_checked": self.checked,
+            "requests_blocked": self.blocked,
+            "allowed_by_exception": self.allowed_by_exception,
+            "per_filter": per_filter,
+        })
+    }
+}
+
+impl FilterOptions {
+    fn applies(&self, resource_type: &str, third_party: bool, page_host: &str) -> bool {
+        if !self.include_types.is_empty() {
+            if !self.include_types.iter().any(|t| t == resource_type) {
+                return false;
+            }
+        } else if resource_type == "document" {
+            // Main-frame documents are only blocked by an explicit $document.
+            return false;
+        }
+        if self.exclude_types.iter().any(|t| t == resource_type) {
+            return false;
+        }
+        if let Some(want) = self.third_party {
+            if want != third_party {
+                return false;
+            }
+        }
+        let on_domain = |d: &String| page_host == d || page_host.ends_with(&format!(".{}", d));
+        if self.exclude_domains.iter().any(on_domain) {
+            return false;
+        }
+        self.include_domains.is_empty() || self.include_domains.iter().any(on_domain)
+    }
+}
+
+impl NetworkFilter {
+    fn matches_url(&self, url: &[u8], host: &str) -> bool {
+        let pat = &self.pattern;
+        match self.anchor {
+            Anchor::Start => glob_prefix(pat, url, self.anchor_end),
+            Anchor::None => (0..=url.len()).any(|i| glob_prefix(pat, &url[i..], self.anchor_end)),
+            Anchor::Host => {
+                let Some(host_start) = find_host_start(url, host) else {
+                    return false;
+                };
+                let host_end = host_start + host.len();
+                (host_start..host_end)
+                    .filter(|&i| i == host_start || url[i - 1] == b'.')
+                    .any(|i| glob_prefix(pat, &url[i..], self.anchor_end))
+            }
+        }
+    }
+}
+
+/// Parses one list line. `None` for comments/cosmetic rules, `Some(Err)` for
+/// network filters using syntax we do not support.
+fn parse_filter(line: &str) -> Option<Result<NetworkFilter, ()>> {
+    if line.is_empty() || line.starts_with('!') || line.starts_with('[') {
+        return None;
+    }
+    if line.contains("##") || line.contains("#@#") || line.contains("#?#") || line.contains("#$#") {
+        return None;
+    }
+
+    let (exception, rest) = match line.strip_prefix("@@") {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+
+    let (body, options) = match rest.rfind('$') {
+        Some(pos) if is_option_list(&rest[pos + 1..]) => match parse_options(&rest[pos + 1..]) {
+            Some(opts) => (&rest[..pos], opts),
+            None => return Some(Err(())),
+        },
+        _ => (rest, FilterOptions::default()),
+    };
+
+    // Regex filters are rare in practice and expensive; not supported.
+    if body.len() > 1 && body.starts_with('/') && body.ends_with('/') {
+        return Some(Err(()));
+    }
+
+    let (anchor, body) = if let Some(b) = body.strip_prefix("||") {
+        (Anchor::Host, b)
+    } else if let Some(b) = body.strip_prefix('|') {
+        (Anchor::Start, b)
+    } else {
+        (Anchor::None, body)
+    };
+    let (anchor_end, body) = match body.strip_suffix('|') {
+        Some(b) => (true, b),
+        None => (false, body),
+    };
+
+    let mut pattern = Vec::with_capacity(body.len());
+    for b in body.to_ascii_lowercase().bytes() {
+        if b == b'*' && pattern.last() == Some(&b'*') {
+            continue;
+        }
+        pattern.push(b);
+    }
+    if pattern.is_empty() || pattern == b"*" {
+        return Some(Err(()));
+    }
+
+    Some(Ok(NetworkFilter {
+        text: line.to_string(),
+        pattern,
+        anchor,
+        anchor_end,
+        exception,
+        options,
+    }))
+}
+
+fn is_option_list(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || "~=,|.-_".contains(c))
+}
+
+fn parse_options(s: &str) -> Option<FilterOptions> {
+    let mut opts = FilterOptions::default();
+    for opt in s.split(',') {
+        let (negated, name) = match opt.strip_prefix('~') {
+            Some(n) => (true, n),
+            None => (false, opt),
+        };
+        let name = match name {
+            "xhr" => "xmlhttprequest",
+            "css" => "stylesheet",
+            "frame" => "subdocument",
+            "3p" => "third-party",
+            "1p" | "first-party" => {
+                opts.third_party = Some(negated);
+                continue;
+            }
+            other => other,
+        };
+        if let Some(domains) = name.strip_prefix("domain=") {
+            for d in domains.split('|') {
+                match d.strip_prefix('~') {
+                    Some(d) => opts.exclude_domains.push(d.to_ascii_lowercase()),
+                    None => opts.include_domains.push(d.to_ascii_lowercase()),
+                }
+            }
+        } else if name == "third-party" {
+            opts.third_party = Some(!negated);
+        } else if name == "important" {
+            opts.important = true;
+        } else if name == "match-case" {
+            // Matching is case-insensitive; treating these as case-insensitive only widens them.
+        } else if RESOURCE_TYPES.contains(&name) {
+            if negated {
+                opts.exclude_types.push(name.to_string());
+            } else {
+                opts.include_types.push(name.to_string());
+            }
+        } else {
+            return None;
+        }
+    }
+    Some(opts)
+}
+
+/// Picks a literal token that must appear as a whole alphanumeric run in any
+/// matching URL, so lookups only test filters sharing a token with the URL.
+fn index_token(filter: &NetworkFilter) -> Option<String> {
+    let pat = &filter.pattern;
+    let mut best: Option<&[u8]> = None;
+    let mut start = 0;
+    while start < pat.len() {
+        if !pat[start].is_ascii_alphanumeric() {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < pat.len() && pat[end].is_ascii_alphanumeric() {
+            end += 1;
+        }
+        let left_bounded = if start == 0 {
+            filter.anchor != Anchor::None
+        } else {
+            pat[start - 1] != b'*'
+        };
+        let right_bounded = if end == pat.len() {
+            filter.anchor_end
+        } else {
+            pat[end] != b'*'
+        };
+        if left_bounded && right_bounded && end - start > 1 && best.is_none_or(|b| b.len() < end - start) {
+            best = Some(&pat[start..end]);
+        }
+        start = end;
+    }
+    best.map(|b| String::from_utf8_lossy(b).into_owned())
+}
+
+fn is_separator(c: u8) -> bool {
+    !(c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'.' | b'%'))
+}
+
+fn glob_prefix(pat: &[u8], text: &[u8], anchor_end: bool) -> bool {
+    match pat.split_first() {
+        None => !anchor_end || text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|i| glob_prefix(rest, &text[i..], anchor_end)),
+        Some((b'^', rest)) => match text.split_first() {
+            None => glob_prefix(rest, text, anchor_end),
+            Some((c, tail)) => is_separator(*c) && glob_prefix(rest, tail, anchor_end),
+        },
+        Some((p, rest)) => match text.split_first() {
+            Some((c, tail)) if c == p => glob_prefix(rest, tail, anchor_end),
+            _ => false,
+        },
+    }
+}
+
+fn find_host_start(url: &[u8], host: &str) -> Option<usize> {
+    if host.is_empty() {
+        return None;
+    }
+    let after_scheme = url.windows(3).position(|w| w == b"://")? + 3;
+    let rest = &url[after_scheme..];
+    let at = rest
+        .iter()
+        .take_while(|&&c| c != b'/' && c != b'?' && c != b'#')
+        .position(|&c| c == b'@')
+        .map(|p| p + 1)
+        .unwrap_or(0);
+    Some(after_scheme + at)
+}
+
+/// Approximates the registrable domain without a public-suffix list:
+/// the last two labels, or three for short second-level suffixes (co.uk).
+fn base_domain(host: &str) -> &str {
+    let labels: Vec<&str> = host.rsplitn(4, '.').collect();
+    let keep = if labels.len() >= 3 && labels[1].len() <= 3 && labels[0].len() == 2 { 3 } else { 2 };
+    if labels.len() <= keep {
+        return host;
+    }
+    let suffix_len: usize = labels[..keep].iter().map(|l| l.len()).sum::<usize>() + keep - 1;
+    &host[host.len() - suffix_len..]
+}
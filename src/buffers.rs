@@ -0,0 +1,207 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Serialize;
+
+// ============== Buffer Caps ==============
+//
+// Everything that accumulates in memory over a long run is capped, so a
+// stream left up for a month reaches a steady state. Sizes default to
+// `Buffer::default_size` and can be changed with --buffer-size NAME=N.
+// A full buffer drops its oldest entry (logs, requests, journal, history,
+// dialogs, errors, network, finished downloads along with their files) or,
+// where entries are referred to by position or owned by a client, refuses
+// new ones (event track, annotations). GET /stats/buffers reports
+// each buffer's length, capacity, a byte estimate (its entries serialised
+// as JSON) and how many entries it dropped or refused. Caches that are
+// small by construction (OCR results, QR payloads seen, pipeline timing
+// windows, sink and upload queues) have fixed sizes and are not listed.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Buffer {
+    Logs,
+    Requests,
+    Journal,
+    Track,
+    History,
+    Annotations,
+    Dialogs,
+    Errors,
+    Network,
+    Downloads,
+}
+
+const ALL: [Buffer; 10] = [
+    Buffer::Logs,
+    Buffer::Requests,
+    Buffer::Journal,
+    Buffer::Track,
+    Buffer::History,
+    Buffer::Annotations,
+    Buffer::Dialogs,
+    Buffer::Errors,
+    Buffer::Network,
+    Buffer::Downloads,
+];
+
+static SIZES: [AtomicUsize; 10] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+impl Buffer {
+    pub fn name(self) -> &'static str {
+        match self {
+            Buffer::Logs => "logs",
+            Buffer::Requests => "requests",
+            Buffer::Journal => "journal",
+            Buffer::Track => "track",
+            Buffer::History => "history",
+            Buffer::Annotations => "annotations",
+            Buffer::Dialogs => "dialogs",
+            Buffer::Errors => "errors",
+            Buffer::Network => "network",
+            Buffer::Downloads => "downloads",
+        }
+    }
+
+    fn default_size(self) -> usize {
+        match self {
+            Buffer::Logs => 500,
+            Buffer::Requests => 200,
+            Buffer::Journal => 10_000,
+            Buffer::Track => 10_000,
+            Buffer::History => 1000,
+            Buffer::Annotations => 256,
+            Buffer::Dialogs => 200,
+            Buffer::Errors => 100,
+            Buffer::Network => 1000,
+            Buffer::Downloads => 100,
+        }
+    }
+
+    fn index(self) -> usize {
+        ALL.iter().position(|b| *b == self).unwrap_or_default()
+    }
+
+    /// Current cap: --buffer-size if given, else the default.
+    pub fn capacity(self) -> usize {
+        match SIZES[self.index()].load(Ordering::Relaxed) {
+            0 => self.default_size(),
+            size => size,
+        }
+    }
+}
+
+/// `NAME=N` from --buffer-size, e.g. `journal=50000`.
+#[derive(Debug, Clone)]
+pub struct BufferSize {
+    pub buffer: Buffer,
+    pub size: usize,
+}
+
+impl FromStr for BufferSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, size) = s.split_once('=').ok_or_else(|| format!("expected NAME=N, got '{}'", s))?;
+        let names: Vec<&str> = ALL.iter().map(|b| b.name()).collect();
+        let buffer = ALL
+            .into_iter()
+            .find(|b| b.name() == name.trim())
+            .ok_or_else(|| format!("unknown buffer '{}', expected one of {}", name.trim(), names.join(", ")))?;
+        let size = size
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|size| *size > 0)
+            .ok_or_else(|| format!("buffer size must be a positive number, got '{}'", size.trim()))?;
+        Ok(BufferSize { buffer, size })
+    }
+}
+
+pub fn configure(sizes: &[BufferSize]) {
+    for size in sizes {
+        SIZES[size.buffer.index()].store(size.size, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BufferStats {
+    pub name: &'static str,
+    pub len: usize,
+    pub capacity: usize,
+    /// Entries serialised as JSON; the in-memory size is of the same order.
+    pub bytes: usize,
+    /// Evicted or refused because the buffer was full.
+    pub dropped: u64,
+}
+
+pub fn stats<'a, T: Serialize + 'a>(buffer: Buffer, items: impl IntoIterator<Item = &'a T>, dropped: u64) -> BufferStats {
+    let (mut len, mut bytes) = (0, 0);
+    for item in items {
+        len += 1;
+        bytes += serde_json::to_vec(item).map_or(0, |json| json.len());
+    }
+    BufferStats { name: buffer.name(), len, capacity: buffer.capacity(), bytes, dropped }
+}
+
+/// GET /stats/buffers: process-wide buffers plus the server's own.
+pub fn response(mut own: Vec<BufferStats>) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let mut buffers = vec![crate::logs::buffer_stats(), crate::request_log::buffer_stats()];
+    buffers.append(&mut own);
+    crate::json_response(200, &serde_json::json!({
+        "buffers": buffers,
+        "total_bytes": buffers.iter().map(|b| b.bytes).sum::<usize>(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_buffer_sizes() {
+        let size: BufferSize = " journal = 50000 ".parse().unwrap();
+        assert_eq!((size.buffer, size.size), (Buffer::Journal, 50_000));
+        let error = "frames=10".parse::<BufferSize>().unwrap_err();
+        assert!(error.contains("unknown buffer 'frames'") && error.contains("downloads"), "{}", error);
+        for bad in ["logs", "logs=0", "logs=-1", "logs=lots"] {
+            assert!(bad.parse::<BufferSize>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn every_buffer_has_a_name_and_slot() {
+        for (i, buffer) in ALL.into_iter().enumerate() {
+            assert_eq!(buffer.index(), i);
+            assert_eq!(format!("{}=1", buffer.name()).parse::<BufferSize>().unwrap().buffer, buffer);
+        }
+        assert_eq!(SIZES.len(), ALL.len());
+    }
+
+    #[test]
+    fn configured_sizes_replace_defaults() {
+        // Only this test configures dialogs; other buffers keep their defaults
+        assert_eq!(Buffer::Dialogs.capacity(), 200);
+        configure(&["dialogs=7".parse().unwrap()]);
+        assert_eq!(Buffer::Dialogs.capacity(), 7);
+        assert_eq!(Buffer::Errors.capacity(), 100);
+    }
+
+    #[test]
+    fn stats_estimate_bytes_as_json() {
+        let items = ["ab".to_string(), "cde".to_string()];
+        let stats = stats(Buffer::Errors, items.iter(), 3);
+        assert_eq!((stats.name, stats.len, stats.capacity, stats.dropped), ("errors", 2, 100, 3));
+        assert_eq!(stats.bytes, "\"ab\"".len() + "\"cde\"".len());
+    }
+}
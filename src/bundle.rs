@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::annotations::Annotation;
+use crate::commands::{self, PageCommand};
+use crate::host_rules::HostRule;
+use crate::hud::HudRequest;
+use crate::permissions::{self, PermissionsRequest};
+use crate::ticker::TickerRequest;
+use crate::HeadlessState;
+
+// ============== Runtime Bundle ==============
+//
+// GET /bundle/export is one JSON document with everything about a running
+// headless instance that the command line does not say: the page URL and
+// locale, runtime host rules, permission grants, redaction selectors,
+// annotations, the manual ticker text, HUD settings, the forced theme and
+// whether the stream is paused. The command line itself is included for
+// reference, with the values of SECRET_FLAGS and of secret-looking query
+// parameters in the URL replaced by "***". ?include_secrets=true keeps them,
+// and needs --api-token and the token itself, not a session cookie.
+//
+// POST /bundle/import, or --bundle FILE at startup, applies a bundle section
+// by section and reports each as applied, skipped (absent, or fixed at
+// launch like the command line and the filter list) or failed. A section
+// that fails leaves that part of the state as it was; the rest still apply.
+
+const BUNDLE_VERSION: u32 = 1;
+
+const REDACTED: &str = "***";
+
+/// Flags whose values carry credentials or private endpoints.
+const SECRET_FLAGS: &[&str] =
+    &["--api-token", "--ticker-url", "--upload-url", "--frame-sink", "--frame-hook-webhook", "--registry-url", "--login-webhook"];
+
+/// Query parameters of the page URL whose values are redacted.
+const SECRET_PARAMS: &[&str] = &["token", "key", "secret", "password", "auth", "session", "sig"];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bundle {
+    pub version: u32,
+    pub exported_at: u128,
+    /// Version and command line of the exporting instance; informational.
+    pub config: Option<serde_json::Value>,
+    /// Must match this instance's --width/--height.
+    pub viewport: Option<(u32, u32)>,
+    pub url: Option<String>,
+    /// Applied together with `url`, like /navigate?locale=.
+    pub locale: Option<String>,
+    pub host_rules: Option<Vec<HostRule>>,
+    pub permissions: Option<Vec<PermissionsRequest>>,
+    pub redaction: Option<Vec<String>>,
+    pub annotations: Option<Vec<Annotation>>,
+    pub ticker: Option<TickerSection>,
+    pub hud: Option<HudRequest>,
+    pub theme: Option<ThemeSection>,
+    pub lifecycle: Option<LifecycleSection>,
+    /// Filter list stats of the exporting instance; informational.
+    pub blocker: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TickerSection {
+    /// POST /ticker text, `null` for none.
+    pub manual: Option<TickerRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThemeSection {
+    /// POST /theme override, `null` to follow the schedule.
+    pub forced: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LifecycleSection {
+    pub paused: bool,
+}
+
+/// The command line with secret values replaced, unless `include_secrets`.
+fn argv(include_secrets: bool) -> Vec<String> {
+    let mut argv = Vec::new();
+    let mut hide_next = false;
+    for arg in std::env::args().skip(1) {
+        if std::mem::take(&mut hide_next) && !include_secrets {
+            argv.push(REDACTED.to_string());
+            continue;
+        }
+        match arg.split_once('=') {
+            Some((flag, _)) if SECRET_FLAGS.contains(&flag) && !include_secrets => argv.push(format!("{}={}", flag, REDACTED)),
+            _ => {
+                hide_next = SECRET_FLAGS.contains(&arg.as_str());
+                argv.push(arg);
+            }
+        }
+    }
+    argv
+}
+
+fn redact_url(raw: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(raw) else {
+        return raw.to_string();
+    };
+    let secret = |key: &str| {
+        let key = key.to_ascii_lowercase();
+        SECRET_PARAMS.iter().any(|p| key.contains(p))
+    };
+    if !parsed.query_pairs().any(|(key, _)| secret(&key)) {
+        return raw.to_string();
+    }
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = if secret(&key) { REDACTED.to_string() } else { value.into_owned() };
+            (key.into_owned(), value)
+        })
+        .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+}
+
+pub fn export(state: &HeadlessState, include_secrets: bool) -> Bundle {
+    let url = state.current_url.lock().unwrap().clone();
+    Bundle {
+        version: BUNDLE_VERSION,
+        exported_at: crate::now_millis(),
+        config: Some(serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "mode": "headless",
+            "argv": argv(include_secrets),
+        })),
+        viewport: Some(state.viewport),
+        url: Some(if include_secrets { url } else { redact_url(&url) }),
+        locale: state.locale.lock().unwrap().requested().map(ToString::to_string),
+        host_rules: Some(state.host_rules.lock().unwrap().runtime().to_vec()),
+        permissions: Some(state.permissions.lock().unwrap().requests()),
+        redaction: Some(state.redaction.lock().unwrap().selectors().to_vec()),
+        annotations: Some(state.annotations.lock().unwrap().list().to_vec()),
+        ticker: Some(TickerSection { manual: state.ticker.lock().unwrap().manual() }),
+        hud: Some(state.hud.lock().unwrap().settings()),
+        theme: Some(ThemeSection { forced: state.theme.lock().unwrap().forced() }),
+        lifecycle: Some(LifecycleSection { paused: state.lifecycle.lock().unwrap().is_paused() }),
+        blocker: Some(state.blocker.lock().unwrap().stats()),
+    }
+}
+
+/// Per-section outcome of an import.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    sections: Vec<(&'static str, Result<&'static str, String>)>,
+}
+
+impl ImportReport {
+    fn record(&mut self, section: &'static str, result: Result<&'static str, String>) {
+        if let Err(e) = &result {
+            log_error!("Bundle section {} failed: {}", section, e);
+        }
+        self.sections.push((section, result));
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.sections.iter().any(|(_, result)| result.is_err())
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let sections: serde_json::Map<String, serde_json::Value> = self
+            .sections
+            .iter()
+            .map(|(section, result)| {
+                let value = match result {
+                    Ok(status) => serde_json::json!({"status": status}),
+                    Err(e) => serde_json::json!({"status": "failed", "error": e}),
+                };
+                (section.to_string(), value)
+            })
+            .collect();
+        serde_json::json!({"ok": !self.has_failures(), "sections": sections})
+    }
+}
+
+/// `Ok("skipped")` for an absent section, else `apply`'s outcome.
+fn section<T>(value: Option<T>, apply: impl FnOnce(T) -> Result<(), String>) -> Result<&'static str, String> {
+    match value {
+        None => Ok("skipped"),
+        Some(value) => apply(value).map(|_| "applied"),
+    }
+}
+
+pub fn import(state: &HeadlessState, bundle: Bundle) -> ImportReport {
+    let mut report = ImportReport::default();
+    if bundle.version != BUNDLE_VERSION {
+        report.record("version", Err(format!("unsupported bundle version {}, expected {}", bundle.version, BUNDLE_VERSION)));
+        return report;
+    }
+    report.record("config", Ok("skipped"));
+    report.record("blocker", Ok("skipped"));
+    report.record(
+        "viewport",
+        section(bundle.viewport, |viewport| {
+            if viewport == state.viewport {
+                Ok(())
+            } else {
+                Err(format!(
+                    "bundle is for {}x{}, this instance runs at {}x{}; the viewport is fixed at launch",
+                    viewport.0, viewport.1, state.viewport.0, state.viewport.1
+                ))
+            }
+        }),
+    );
+    report.record(
+        "host_rules",
+        section(bundle.host_rules, |rules| {
+            let rules = rules.into_iter().map(HostRule::validated).collect::<Result<Vec<_>, _>>()?;
+            state.host_rules.lock().unwrap().set_runtime(rules);
+            Ok(())
+        }),
+    );
+    report.record(
+        "permissions",
+        section(bundle.permissions, |grants| {
+            for grant in &grants {
+                permissions::validate(&grant.grant)?;
+                permissions::validate(&grant.deny)?;
+            }
+            commands::send(&state.commands, PageCommand::ResetPermissions).map_err(|e| e.message)?;
+            for grant in grants {
+                let command = PageCommand::SetPermissions { origin: grant.origin, grant: grant.grant, deny: grant.deny };
+                commands::send(&state.commands, command).map_err(|e| e.message)?;
+            }
+            Ok(())
+        }),
+    );
+    report.record(
+        "redaction",
+        section(bundle.redaction, |selectors| {
+            state.redaction.lock().unwrap().set(selectors).map_err(|e| format!("rules applied but not saved: {}", e))
+        }),
+    );
+    report.record(
+        "annotations",
+        section(bundle.annotations, |items| {
+            let mut annotations = state.annotations.lock().unwrap();
+            annotations.clear();
+            items.into_iter().try_for_each(|annotation| annotations.add(annotation).map(|_| ()))
+        }),
+    );
+    report.record(
+        "ticker",
+        section(bundle.ticker, |ticker| {
+            let mut state = state.ticker.lock().unwrap();
+            match ticker.manual {
+                Some(manual) => state.set_manual(manual),
+                None => state.clear_manual(),
+            }
+            Ok(())
+        }),
+    );
+    report.record(
+        "hud",
+        section(bundle.hud, |hud| {
+            state.hud.lock().unwrap().update(hud);
+            Ok(())
+        }),
+    );
+    report.record(
+        "theme",
+        section(bundle.theme, |theme| {
+            state.theme.lock().unwrap().set(&crate::theme::ThemeRequest { dark: theme.forced, tab: None });
+            Ok(())
+        }),
+    );
+    report.record(
+        "lifecycle",
+        section(bundle.lifecycle, |lifecycle| {
+            state.lifecycle.lock().unwrap().set_paused(lifecycle.paused);
+            Ok(())
+        }),
+    );
+    // Last, so the page loads with the grants and overrides above in place
+    let locale = bundle.locale.map(|tag| tag.parse::<crate::locale::LocaleProfile>()).transpose();
+    report.record(
+        "url",
+        section(bundle.url, |url| {
+            url::Url::parse(&url).map_err(|e| format!("invalid url '{}': {}", url, e))?;
+            if url.contains(REDACTED) {
+                return Err("the url was redacted on export; export with ?include_secrets=true".to_string());
+            }
+            let profile = locale?;
+            // Both under the locale lock, like /navigate
+            let mut shared = state.locale.lock().unwrap();
+            shared.request(profile);
+            *state.current_url.lock().unwrap() = url;
+            Ok(())
+        }),
+    );
+    report
+}
+
+pub fn load(path: &Path) -> Result<Bundle, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&text).map_err(|e| format!("Invalid bundle in {}: {}", path.display(), e))
+}
+
+/// GET /bundle/export and POST /bundle/import.
+pub fn handle(
+    request: &mut tiny_http::Request,
+    path: &str,
+    query: &HashMap<String, String>,
+    state: &HeadlessState,
+) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let method = request.method().clone();
+    match (path, method) {
+        ("/bundle/export", tiny_http::Method::Get) => {
+            let include_secrets = query.get("include_secrets").is_some_and(|v| v == "true" || v == "1");
+            if include_secrets && !state.auth.presents_token(request, query) {
+                let body = serde_json::json!({"error": "include_secrets needs --api-token and the token itself"});
+                return crate::json_response(403, &body);
+            }
+            crate::json_response(200, &serde_json::json!(export(state, include_secrets)))
+        }
+        ("/bundle/import", tiny_http::Method::Post) => match crate::read_json_body::<Bundle>(request) {
+            Ok(bundle) => {
+                let report = import(state, bundle);
+                crate::json_response(if report.has_failures() { 207 } else { 200 }, &report.to_json())
+            }
+            Err(e) => crate::json_response(400, &serde_json::json!({"error": e})),
+        },
+        _ => crate::json_response(405, &serde_json::json!({"error": "method not allowed"})),
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+// ============== Named Captures ==============
+//
+// Saved frames get a JSON sidecar (`<name>.json` next to `<name>.jpg`) so
+// tools that ingest them don't have to infer context from file names.
+
+/// The navigation that produced the page currently on screen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Navigation {
+    pub seq: u64,
+    pub url: String,
+    /// "startup", "api" or "webdriver"
+    pub source: String,
+    pub started_at: u128,
+}
+
+pub type SharedNavigation = Arc<Mutex<Navigation>>;
+
+impl Navigation {
+    pub fn startup(url: &str) -> Self {
+        Navigation { seq: 1, url: url.to_string(), source: "startup".to_string(), started_at: crate::now_millis() }
+    }
+
+    pub fn next(&mut self, url: &str, source: &str) {
+        self.seq += 1;
+        self.url = url.to_string();
+        self.source = source.to_string();
+        self.started_at = crate::now_millis();
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameMetadata {
+    pub name: String,
+    pub file: String,
+    pub seq: u64,
+    pub url: String,
+    pub title: Option<String>,
+    pub captured_at: u128,
+    pub viewport_width: u32,
+    pub viewport_height: u32,
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub scale: f64,
+    pub navigation: Navigation,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CaptureRequest {
+    pub name: Option<String>,
+}
+
+pub struct CaptureStore {
+    dir: PathBuf,
+    seq: u64,
+}
+
+pub type SharedCaptures = Arc<Mutex<CaptureStore>>;
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl CaptureStore {
+    pub fn open(dir: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let mut store = CaptureStore { dir: dir.to_path_buf(), seq: 0 };
+        store.seq = store.list().iter().map(|m| m.seq).max().unwrap_or(0);
+        Ok(store)
+    }
+
+    /// Picks the capture name, defaulting to `capture-<seq>`.
+    pub fn resolve_name(&self, requested: Option<String>) -> Result<String, String> {
+        match requested {
+            Some(name) if valid_name(&name) => Ok(name),
+            Some(name) => Err(format!("invalid capture name '{}', use up to 64 of [A-Za-z0-9_-]", name)),
+            None => Ok(format!("capture-{}", self.seq + 1)),
+        }
+    }
+
+    /// Writes the JPEG and its sidecar; `meta.seq` and `meta.file` are filled in here.
+    pub fn save(&mut self, frame: &[u8], mut meta: FrameMetadata) -> std::io::Result<FrameMetadata> {
+        self.seq += 1;
+        meta.seq = self.seq;
+        meta.file = format!("{}.jpg", meta.name);
+        std::fs::write(self.dir.join(&meta.file), frame)?;
+        let sidecar = serde_json::to_vec_pretty(&meta).map_err(std::io::Error::other)?;
+        std::fs::write(self.dir.join(format!("{}.json", meta.name)), sidecar)?;
+        Ok(meta)
+    }
+
+    /// Metadata of every saved frame, oldest first.
+    pub fn list(&self) -> Vec<FrameMetadata> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut items: Vec<FrameMetadata> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|p| std::fs::read(p).ok())
+            .filter_map(|data| serde_json::from_slice(&data).ok())
+            .collect();
+        items.sort_by_key(|m| m.seq);
+        items
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
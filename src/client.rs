@@ -0,0 +1,153 @@
+use std::io::Read;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::types::{EvaluateRequest, EvaluateResponse, InteractRequest, NavigateRequest, ScreenshotRequest, Status, WaitUntil};
+
+// ============== HTTP Client ==============
+//
+// A blocking client for the headless HTTP API, built on the request and
+// response types the server itself parses (see types). Each call is one
+// request; a non-2xx answer becomes a ClientError carrying the status and
+// the server's `error` message.
+
+/// Longer than the server's 30 s page command timeout, so its 504 arrives.
+const TIMEOUT: Duration = Duration::from_secs(40);
+/// Screenshots are returned whole; full-page PNGs can be large.
+const MAX_IMAGE_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct ClientError {
+    /// HTTP status, or `None` when the server could not be reached.
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "HTTP {}: {}", status, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<ureq::Error> for ClientError {
+    fn from(e: ureq::Error) -> Self {
+        match e {
+            ureq::Error::Status(status, response) => {
+                let body = response.into_string().unwrap_or_default();
+                let message = serde_json::from_str::<serde_json::Value>(&body)
+                    .ok()
+                    .and_then(|v| v["error"].as_str().map(str::to_string))
+                    .unwrap_or(body);
+                ClientError { status: Some(status), message }
+            }
+            ureq::Error::Transport(transport) => ClientError { status: None, message: transport.to_string() },
+        }
+    }
+}
+
+fn invalid(e: impl std::fmt::Display) -> ClientError {
+    ClientError { status: None, message: format!("unexpected response: {}", e) }
+}
+
+pub struct BrowserClient {
+    base: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl BrowserClient {
+    /// `base` is the server root, e.g. `http://127.0.0.1:8765`.
+    pub fn new(base: impl Into<String>) -> Self {
+        let base = base.into().trim_end_matches('/').to_string();
+        BrowserClient { base, token: None, agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build() }
+    }
+
+    /// Sends `token` as `Authorization: Bearer` (--api-token or --tokens).
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self.agent.request(method, &format!("{}{}", self.base, path));
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    fn post<T: DeserializeOwned>(&self, path: &str, body: &impl Serialize) -> Result<T, ClientError> {
+        let body = serde_json::to_value(body).map_err(invalid)?;
+        self.request("POST", path).send_json(body)?.into_json().map_err(invalid)
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ClientError> {
+        self.request("GET", path).call()?.into_json().map_err(invalid)
+    }
+
+    /// POST /navigate; with `WaitUntil::Load` it answers once the page loaded.
+    pub fn navigate(&self, url: &str, wait_until: WaitUntil) -> Result<serde_json::Value, ClientError> {
+        self.post("/navigate", &NavigateRequest { url: url.to_string(), locale: None, wait_until })
+    }
+
+    /// GET /screenshot: the encoded PNG or JPEG.
+    pub fn screenshot(&self, req: &ScreenshotRequest) -> Result<Vec<u8>, ClientError> {
+        let response = self.request("GET", &format!("/screenshot?{}", req.to_query())).call()?;
+        let mut image = Vec::new();
+        response.into_reader().take(MAX_IMAGE_BYTES).read_to_end(&mut image).map_err(invalid)?;
+        Ok(image)
+    }
+
+    /// POST /click on the first element matching `selector`, once it is
+    /// stable.
+    pub fn click(&self, selector: &str) -> Result<serde_json::Value, ClientError> {
+        self.interact("/click", &InteractRequest { selector: selector.to_string(), ..Default::default() })
+    }
+
+    /// POST /click, /type or /hover with a full request.
+    pub fn interact(&self, path: &str, req: &InteractRequest) -> Result<serde_json::Value, ClientError> {
+        self.post(path, req)
+    }
+
+    /// POST /evaluate, awaiting a returned promise; the result is
+    /// deserialized as `T`.
+    pub fn evaluate<T: DeserializeOwned>(&self, expression: &str) -> Result<T, ClientError> {
+        let response: EvaluateResponse = self.post("/evaluate", &EvaluateRequest { expression: expression.to_string(), await_promise: true })?;
+        serde_json::from_value(response.result).map_err(invalid)
+    }
+
+    /// GET /frame: the latest live JPEG and the URL of the page it shows.
+    pub fn frame(&self) -> Result<(Vec<u8>, String), ClientError> {
+        let response = self.request("GET", "/frame").call()?;
+        let url = response.header("X-Frame-Url").unwrap_or_default().to_string();
+        let mut image = Vec::new();
+        response.into_reader().take(MAX_IMAGE_BYTES).read_to_end(&mut image).map_err(invalid)?;
+        Ok((image, url))
+    }
+
+    pub fn status(&self) -> Result<Status, ClientError> {
+        self.get("/status")
+    }
+
+    /// GET any JSON route, e.g. `/cookies`.
+    pub fn get_json(&self, path: &str) -> Result<serde_json::Value, ClientError> {
+        self.get(path)
+    }
+
+    /// POST any JSON route.
+    pub fn post_json(&self, path: &str, body: &serde_json::Value) -> Result<serde_json::Value, ClientError> {
+        self.post(path, body)
+    }
+
+    /// DELETE any JSON route, e.g. `/cookies?name=session`.
+    pub fn delete_json(&self, path: &str) -> Result<serde_json::Value, ClientError> {
+        self.request("DELETE", path).call()?.into_json().map_err(invalid)
+    }
+}
@@ -0,0 +1,944 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chromiumoxide::browser::Browser;
+use chromiumoxide::cdp::browser_protocol::input::{
+    DispatchKeyEventParams, DispatchKeyEventType, DispatchMouseEventParams, DispatchMouseEventType, InsertTextParams, MouseButton,
+};
+use chromiumoxide::cdp::browser_protocol::page::{CaptureScreenshotFormat, GetNavigationHistoryParams, NavigateToHistoryEntryParams};
+use chromiumoxide::cdp::js_protocol::runtime::{EvaluateParams, ExecutionContextId};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use serde::Deserialize;
+
+use crate::captures::FrameMetadata;
+use crate::deterministic;
+use crate::frames::{self, FrameTarget};
+use crate::handoff::{self, HandoffState};
+use crate::journal::JournalAction;
+use crate::settle::{SettleOptions, Stillness, Verdict};
+use crate::HeadlessState;
+
+pub use crate::types::{ClickButton, EvaluateRequest, InteractRequest, Stability, TypeMethod};
+
+// ============== Page Commands ==============
+//
+// The HTTP server runs on a plain thread while the page handle lives in the
+// async capture loop. Handlers enqueue a command with a reply channel; the
+// loop drains the queue between captures and answers each one.
+
+#[derive(Debug)]
+pub struct CommandError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl CommandError {
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        CommandError { status, message: message.into() }
+    }
+}
+
+impl From<CdpError> for CommandError {
+    fn from(e: CdpError) -> Self {
+        match e {
+            CdpError::JavascriptException(details) => {
+                let message = details
+                    .exception
+                    .as_ref()
+                    .and_then(|ex| ex.description.clone())
+                    .unwrap_or_else(|| details.text.clone());
+                CommandError::new(422, message)
+            }
+            other => CommandError::new(502, other.to_string()),
+        }
+    }
+}
+
+pub type CommandResult = Result<serde_json::Value, CommandError>;
+
+#[derive(Debug)]
+pub enum PageCommand {
+    SetPermissions {
+        origin: Option<String>,
+        grant: Vec<String>,
+        deny: Vec<String>,
+    },
+    ResetPermissions,
+    /// Runtime.evaluate with returnByValue; thrown exceptions map to 422.
+    Evaluate {
+        expression: String,
+        await_promise: bool,
+    },
+    /// Fresh JPEG of the page without overlays, optionally waiting for it to
+    /// settle and with deterministic-render CSS applied for this capture only.
+    Render {
+        settle: Option<SettleOptions>,
+        deterministic: bool,
+    },
+    /// On-demand capture of the streamed page, optionally clipped to an element.
+    Screenshot {
+        request: crate::screenshot::ScreenshotRequest,
+    },
+    /// Saves the current live frame under `name` with a metadata sidecar.
+    SaveCapture {
+        name: String,
+    },
+    /// Burst of frames with an injected timestamp element.
+    LatencyTest {
+        frames: u32,
+    },
+    /// Navigates and waits for the load, unlike `/navigate` which only queues the URL.
+    Navigate {
+        url: String,
+        source: &'static str,
+    },
+    /// Browser.getVersion.
+    BrowserVersion,
+    /// Scrolls the first match of a CSS selector into view and clicks it; no match is a 422.
+    Click {
+        selector: String,
+        /// See `frames`; `None` for the top document.
+        frame: Option<String>,
+        stability: Stability,
+    },
+    /// Presses and releases a mouse button at a top-viewport point; a point
+    /// outside the viewport is a 400.
+    ClickAt {
+        x: f64,
+        y: f64,
+        button: ClickButton,
+        click_count: u32,
+    },
+    /// A key or shortcut sent to the focused element.
+    Press {
+        press: crate::keys::KeyPress,
+    },
+    /// Appends text to an input, textarea or contenteditable, or with an
+    /// empty selector to the focused element; no match is a 422.
+    Type {
+        selector: String,
+        text: String,
+        submit: bool,
+        /// Journal the text as `***` even if the field is not a password input.
+        secret: bool,
+        method: TypeMethod,
+        frame: Option<String>,
+        stability: Stability,
+    },
+    /// Picks options of a <select> by value, label or index, firing input
+    /// and change; an option that matches nothing is a 404.
+    Select {
+        selector: String,
+        /// "value", "label" or "index".
+        by: &'static str,
+        options: Vec<serde_json::Value>,
+        frame: Option<String>,
+        stability: Stability,
+    },
+    /// Hands files already on disk to an <input type=file>.
+    SetFiles {
+        selector: String,
+        paths: Vec<std::path::PathBuf>,
+    },
+    /// Textual response bodies Chrome still holds, for GET /har.
+    ResponseBodies {
+        request_ids: Vec<String>,
+    },
+    /// Moves the mouse to a top-viewport point; outside the viewport is a 400.
+    HoverAt {
+        x: f64,
+        y: f64,
+    },
+    /// Moves the mouse to the centre of the first match of a CSS selector; no
+    /// match is a 404.
+    Hover {
+        selector: String,
+        frame: Option<String>,
+        stability: Stability,
+    },
+    /// Page.getFrameTree as nested id/name/url nodes.
+    FrameTree,
+    /// URL, title and readyState of the page for GET /status.
+    PageInfo,
+    /// URL, cookies, storage, viewport and scroll as a handoff bundle.
+    ExportState,
+    /// Applies a handoff bundle to the page, navigating to its URL.
+    ImportState {
+        state: Box<HandoffState>,
+    },
+    /// Per-page metrics and browser process RSS.
+    Resources,
+    /// Performance.getMetrics and navigation timing of the page for GET /perf.
+    Perf,
+    /// Starts a Chrome trace; 409 while one is running.
+    TraceStart {
+        categories: Option<Vec<String>>,
+    },
+    /// Ends the running trace and returns it.
+    TraceStop,
+    /// Reloads the page, restoring window and container scroll unless disabled.
+    Reload {
+        restore_scroll: bool,
+        ignore_cache: bool,
+    },
+    /// Moves `delta` entries through the session history (-1 back, 1
+    /// forward); no entry there is a 409.
+    History {
+        delta: i64,
+    },
+    /// Replaces the extra headers sent with every request; empty clears them.
+    SetHeaders {
+        headers: std::collections::BTreeMap<String, String>,
+    },
+    /// Cookies visible to the current page.
+    Cookies,
+    SetCookies {
+        cookies: Vec<crate::cookies::Cookie>,
+    },
+    /// Network.deleteCookies for a name, on `domain` or the page's URL.
+    DeleteCookies {
+        name: String,
+        domain: Option<String>,
+    },
+    /// Live DOM serialisation, or the served document body from the Network domain.
+    Source {
+        served: bool,
+    },
+}
+
+pub struct PendingCommand {
+    pub command: PageCommand,
+    pub reply: mpsc::Sender<CommandResult>,
+}
+
+pub type CommandSender = tokio::sync::mpsc::UnboundedSender<PendingCommand>;
+pub type CommandReceiver = tokio::sync::mpsc::UnboundedReceiver<PendingCommand>;
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Consecutive unchanged bounding boxes that count as "stopped moving".
+const STABLE_SAMPLES: u32 = 3;
+const STABLE_INTERVAL_MS: u64 = 50;
+/// Stays under the command channel timeout.
+const MAX_STABLE_TIMEOUT_MS: u64 = 25_000;
+
+impl TypeMethod {
+    fn resolve(self, text: &str) -> TypeMethod {
+        match self {
+            TypeMethod::Auto if text.chars().all(is_plain_key) => TypeMethod::Keys,
+            TypeMethod::Auto => TypeMethod::Insert,
+            method => method,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            TypeMethod::Auto => "auto",
+            TypeMethod::Keys => "keys",
+            TypeMethod::Insert => "insert",
+        }
+    }
+}
+
+/// Printable ASCII and newline, which key events reproduce faithfully.
+fn is_plain_key(c: char) -> bool {
+    c == '\n' || (' '..='~').contains(&c)
+}
+
+/// One option or, for a multiple <select>, several.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Choice<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T: Into<serde_json::Value>> Choice<T> {
+    fn into_values(self) -> Vec<serde_json::Value> {
+        match self {
+            Choice::One(one) => vec![one.into()],
+            Choice::Many(many) => many.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Body of POST /select: exactly one of `value`, `label` and `index`.
+#[derive(Debug, Deserialize)]
+pub struct SelectRequest {
+    pub selector: String,
+    #[serde(default)]
+    pub pierce: bool,
+    #[serde(default)]
+    pub frame: Option<String>,
+    #[serde(default)]
+    pub value: Option<Choice<String>>,
+    #[serde(default)]
+    pub label: Option<Choice<String>>,
+    #[serde(default)]
+    pub index: Option<Choice<u64>>,
+    #[serde(flatten)]
+    pub stability: Stability,
+}
+
+impl SelectRequest {
+    /// The command for this request, or a 400-style error.
+    pub fn into_command(self) -> Result<PageCommand, String> {
+        if self.selector.trim().is_empty() {
+            return Err("missing selector".to_string());
+        }
+        let selector = if self.pierce && !self.selector.trim_start().starts_with(">>>") {
+            format!(">>> {}", self.selector)
+        } else {
+            self.selector
+        };
+        let (by, options) = match (self.value, self.label, self.index) {
+            (Some(value), None, None) => ("value", value.into_values()),
+            (None, Some(label), None) => ("label", label.into_values()),
+            (None, None, Some(index)) => ("index", index.into_values()),
+            _ => return Err("pass exactly one of value, label and index".to_string()),
+        };
+        if options.is_empty() {
+            return Err(format!("{} must name at least one option", by));
+        }
+        Ok(PageCommand::Select { selector, by, options, frame: self.frame, stability: self.stability })
+    }
+}
+
+impl ClickButton {
+    fn cdp(self) -> MouseButton {
+        match self {
+            ClickButton::Left => MouseButton::Left,
+            ClickButton::Middle => MouseButton::Middle,
+            ClickButton::Right => MouseButton::Right,
+        }
+    }
+}
+
+/// The shared element lookup, passed to page scripts by `js_call_with_find`.
+/// `a >>> b` finds `b` inside `a`, descending into open shadow roots at any
+/// depth; a leading `>>>` searches the whole document that way. A custom
+/// element without an open shadow root that hides the next part is reported
+/// as a closed shadow root. With `required` a miss throws, otherwise it is
+/// `null`.
+pub const FIND_SCRIPT: &str = r#"
+function(selector, required) {
+    const deep = function(root, part) {
+        if (root.shadowRoot) {
+            const inner = deep(root.shadowRoot, part);
+            if (inner) return inner;
+        }
+        const found = root.querySelector(part);
+        if (found) return found;
+        const hosts = root.querySelectorAll('*');
+        for (let i = 0; i < hosts.length; i++) {
+            if (!hosts[i].shadowRoot) continue;
+            const inner = deep(hosts[i].shadowRoot, part);
+            if (inner) return inner;
+        }
+        return null;
+    };
+    const parts = selector.split('>>>').map(function(part) { return part.trim(); });
+    let el = null;
+    for (let i = 0; i < parts.length; i++) {
+        if (!parts[i]) {
+            if (i === 0 && parts.length > 1) continue;
+            throw new Error('empty selector around >>> in ' + selector);
+        }
+        if (i === 0) {
+            el = document.querySelector(parts[i]);
+        } else {
+            const root = el || document;
+            el = deep(root, parts[i]);
+            if (!el && root !== document && !root.shadowRoot && root.localName.indexOf('-') > 0) {
+                throw new Error('closed shadow root: <' + root.localName + '> does not expose ' + parts[i]);
+            }
+        }
+        if (!el) {
+            if (required) throw new Error('no element matches ' + selector);
+            return null;
+        }
+    }
+    return el;
+}
+"#;
+
+/// One stability sample: the element's box, scrolled into view first.
+const BOX_SCRIPT: &str = r#"
+function(find, selector, scroll) {
+    const el = find(selector, true);
+    if (scroll) el.scrollIntoView({ block: 'center', inline: 'center' });
+    const r = el.getBoundingClientRect();
+    return [r.left, r.top, r.width, r.height].join(',');
+}
+"#;
+
+/// Finds and scrolls to the element. With a settled `box` from the
+/// stability samples it must still be there, and nothing else may be on top
+/// of its centre point.
+const RESOLVE_SCRIPT: &str = r#"
+function(find, selector, options) {
+    const el = find(selector, true);
+    el.scrollIntoView({ block: 'center', inline: 'center' });
+    if (options.box === null) return el;
+
+    const r = el.getBoundingClientRect();
+    if ([r.left, r.top, r.width, r.height].join(',') !== options.box) throw new Error(selector + ' did not stop moving');
+    if (r.width === 0 || r.height === 0) throw new Error(selector + ' is not visible');
+    // document.elementFromPoint stops at shadow hosts; descend open shadow
+    // roots to the element actually on top, then walk back up through hosts
+    const x = r.left + r.width / 2, y = r.top + r.height / 2;
+    let hit = document.elementFromPoint(x, y);
+    while (hit && hit.shadowRoot) {
+        const inner = hit.shadowRoot.elementFromPoint(x, y);
+        if (!inner || inner === hit) break;
+        hit = inner;
+    }
+    const within = function(node) {
+        for (; node; node = node instanceof ShadowRoot ? node.host : node.parentNode) {
+            if (node === el) return true;
+        }
+        return false;
+    };
+    if (hit && !within(hit)) {
+        let node = hit;
+        while (node.parentElement && !node.id && !(typeof node.className === 'string' && node.className.trim())) {
+            node = node.parentElement;
+        }
+        const name = node.id
+            ? '#' + node.id
+            : typeof node.className === 'string' && node.className.trim()
+                ? '.' + node.className.trim().split(/\s+/)[0]
+                : node.tagName.toLowerCase();
+        throw new Error('element obscured by ' + name);
+    }
+    return el;
+}
+"#;
+
+const CLICK_SCRIPT: &str = r#"
+function(el) {
+    el.click();
+    return true;
+}
+"#;
+
+/// Focuses the field with the caret at the end, so typing appends. Returns
+/// whether it is a password input, so the typed value is masked before it
+/// reaches the journal (as the GUI recorder does).
+const FOCUS_SCRIPT: &str = r#"
+function(el) {
+    if (!el.isContentEditable && !('value' in el)) throw new Error('element is not editable');
+    el.focus();
+    if (el.isContentEditable) {
+        const range = document.createRange();
+        range.selectNodeContents(el);
+        range.collapse(false);
+        const selection = window.getSelection();
+        selection.removeAllRanges();
+        selection.addRange(range);
+    } else {
+        // Some input types (email, number) have no selection API
+        try { el.setSelectionRange(el.value.length, el.value.length); } catch (e) {}
+    }
+    return { password: el.type === 'password' };
+}
+"#;
+
+/// Selects the matching options the way a user would: only the matches end
+/// up selected, then input and change bubble so frameworks see the new
+/// value. Options that match nothing come back as `missing` and nothing is
+/// changed.
+const SELECT_SCRIPT: &str = r#"
+function(el, by, choices) {
+    if (el.localName !== 'select') throw new Error('element is not a <select>');
+    if (el.disabled) throw new Error('<select> is disabled');
+    if (choices.length > 1 && !el.multiple) throw new Error('only a multiple <select> takes several options');
+    const options = Array.from(el.options);
+    const matches = choices.map(function(choice) {
+        return options.find(function(o) {
+            if (by === 'index') return o.index === choice;
+            if (by === 'label') return o.label.trim() === choice.trim() || o.text.trim() === choice.trim();
+            return o.value === choice;
+        });
+    });
+    const missing = choices.filter(function(c, i) { return !matches[i]; });
+    if (missing.length) return { missing: missing };
+    el.focus();
+    if (el.multiple) {
+        options.forEach(function(o) { o.selected = matches.indexOf(o) >= 0; });
+    } else {
+        el.selectedIndex = matches[0].index;
+    }
+    el.dispatchEvent(new Event('input', { bubbles: true }));
+    el.dispatchEvent(new Event('change', { bubbles: true }));
+    const selected = options.filter(function(o) { return o.selected; });
+    return {
+        selectedIndex: el.selectedIndex,
+        text: selected.map(function(o) { return o.text.trim(); }).join(', '),
+        selected: selected.map(function(o) { return { index: o.index, value: o.value, text: o.text.trim() }; }),
+    };
+}
+"#;
+
+/// Checks that the focused element, if any, takes text; used by /type
+/// without a selector.
+const FOCUSED_SCRIPT: &str = r#"
+function() {
+    const el = document.activeElement;
+    if (!el || el === document.body || (!el.isContentEditable && !('value' in el))) return null;
+    return { password: el.type === 'password' };
+}
+"#;
+
+/// Runs on the focused field after typing: the change event a blur would
+/// fire, and the optional form submit.
+const FINISH_SCRIPT: &str = r#"
+function(submit) {
+    const el = document.activeElement;
+    if (!el) return;
+    if ('value' in el) el.dispatchEvent(new Event('change', { bubbles: true }));
+    if (submit && el.form) el.form.requestSubmit();
+}
+"#;
+
+/// Types `text` into the focused element as key events.
+async fn press_keys(page: &Page, text: &str) -> Result<(), CommandError> {
+    for c in text.chars() {
+        let (key, typed, key_code) = match c {
+            '\n' => ("Enter".to_string(), "\r".to_string(), 13),
+            c => (c.to_string(), c.to_string(), c.to_ascii_uppercase() as i64),
+        };
+        let down = DispatchKeyEventParams::builder()
+            .r#type(DispatchKeyEventType::KeyDown)
+            .key(key.clone())
+            .text(typed)
+            .windows_virtual_key_code(key_code)
+            .build()
+            .map_err(|e| CommandError::new(500, e))?;
+        let up = DispatchKeyEventParams::builder()
+            .r#type(DispatchKeyEventType::KeyUp)
+            .key(key)
+            .windows_virtual_key_code(key_code)
+            .build()
+            .map_err(|e| CommandError::new(500, e))?;
+        page.execute(down).await?;
+        page.execute(up).await?;
+    }
+    Ok(())
+}
+
+const CENTER_SCRIPT: &str = r#"
+function(el) {
+    const r = el.getBoundingClientRect();
+    return { x: r.left + r.width / 2, y: r.top + r.height / 2 };
+}
+"#;
+
+/// Samples the box of `selector` until `STABLE_SAMPLES` in a row agree and
+/// returns that box.
+async fn wait_until_still(page: &Page, frame: Option<&FrameTarget>, selector: &str, stability: Stability) -> Result<String, CommandError> {
+    let timeout_ms = stability.timeout_ms.min(MAX_STABLE_TIMEOUT_MS);
+    let mut stillness = Stillness::new(STABLE_SAMPLES, Duration::from_millis(timeout_ms));
+    let mut scroll = true;
+    loop {
+        let expression = js_call_with_find(BOX_SCRIPT, &[selector.into(), scroll.into()]);
+        let sample = evaluate_in(page, frame.map(|f| f.context), expression, false).await?;
+        let sample = sample.as_str().unwrap_or_default().to_string();
+        scroll = false;
+        match stillness.observe(sample.clone()) {
+            Verdict::Still => return Ok(sample),
+            Verdict::TimedOut => {
+                return Err(CommandError::new(422, format!("{} did not stop moving within {} ms", selector, timeout_ms)));
+            }
+            Verdict::Wait => tokio::time::sleep(Duration::from_millis(STABLE_INTERVAL_MS)).await,
+        }
+    }
+}
+
+/// Resolves `selector` per `stability`, then runs `action(el, ...args)` on it
+/// in the same evaluation, which rejects the element if it moved since the
+/// stability samples. With a `frame` all of it runs in that frame's world.
+async fn interact(
+    page: &Page,
+    frame: Option<&FrameTarget>,
+    selector: &str,
+    stability: Stability,
+    action: &str,
+    args: &[serde_json::Value],
+) -> CommandResult {
+    let settled = if stability.stable { Some(wait_until_still(page, frame, selector, stability).await?) } else { None };
+    let options = serde_json::json!({ "box": settled });
+    let mut action_args = vec!["el".to_string()];
+    action_args.extend(args.iter().map(|a| a.to_string()));
+    let expression = format!(
+        "(function(el) {{ return ({})({}); }})({})",
+        action.trim(),
+        action_args.join(", "),
+        js_call_with_find(RESOLVE_SCRIPT, &[selector.into(), options])
+    );
+    evaluate_in(page, frame.map(|f| f.context), expression, true).await
+}
+
+/// The frame named by a command's `frame` field, if any.
+async fn target(page: &Page, frame: Option<&str>) -> Result<Option<FrameTarget>, CommandError> {
+    match frame {
+        Some(spec) => Ok(Some(frames::resolve(page, spec).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Mouse commands at coordinates only take points inside the capture viewport.
+fn check_in_viewport(state: &HeadlessState, x: f64, y: f64) -> Result<(), CommandError> {
+    let (width, height) = (state.viewport.0 as f64, state.viewport.1 as f64);
+    if (0.0..width).contains(&x) && (0.0..height).contains(&y) {
+        return Ok(());
+    }
+    Err(CommandError::new(400, format!("point ({}, {}) is outside the {}x{} viewport", x, y, state.viewport.0, state.viewport.1)))
+}
+
+/// Enqueues `command` for the capture loop and blocks until it is answered.
+pub fn send(tx: &CommandSender, command: PageCommand) -> CommandResult {
+    let (reply, rx) = mpsc::channel();
+    tx.send(PendingCommand { command, reply })
+        .map_err(|_| CommandError::new(503, "browser is not running"))?;
+    rx.recv_timeout(COMMAND_TIMEOUT)
+        .map_err(|_| CommandError::new(504, "timed out waiting for the browser"))?
+}
+
+/// Builds `(function source)(arg, ...)` with JSON-encoded arguments so values
+/// are never spliced into script text unescaped.
+pub fn js_call(function: &str, args: &[serde_json::Value]) -> String {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    format!("({})({})", function.trim(), args.join(", "))
+}
+
+/// `js_call` with FIND_SCRIPT as the first argument, so every script that
+/// takes a selector resolves it the same way.
+pub fn js_call_with_find(function: &str, args: &[serde_json::Value]) -> String {
+    let mut all = vec![FIND_SCRIPT.trim().to_string()];
+    all.extend(args.iter().map(|a| a.to_string()));
+    format!("({})({})", function.trim(), all.join(", "))
+}
+
+/// Init script that adds a `<style>` as soon as the document element exists,
+/// so the rules apply before first paint.
+pub fn init_style_script(id: &str, css: &str) -> String {
+    let call = js_call(
+        r#"function(id, css) {
+    function add() {
+        if (document.getElementById(id)) return;
+        const style = document.createElement('style');
+        style.id = id;
+        style.textContent = css;
+        (document.head || document.documentElement).appendChild(style);
+    }
+    if (document.documentElement) add();
+    else new MutationObserver(function(_, observer) {
+        if (document.documentElement) { observer.disconnect(); add(); }
+    }).observe(document, { childList: true });
+    document.addEventListener('DOMContentLoaded', add);
+}"#,
+        &[id.into(), css.into()],
+    );
+    format!("{};\n", call)
+}
+
+/// Runtime.evaluate with returnByValue, returning the value or `null`.
+pub async fn evaluate(page: &Page, expression: String, await_promise: bool) -> CommandResult {
+    evaluate_in(page, None, expression, await_promise).await
+}
+
+/// `evaluate` in an execution context other than the top document's.
+async fn evaluate_in(page: &Page, context: Option<ExecutionContextId>, expression: String, await_promise: bool) -> CommandResult {
+    let mut builder = EvaluateParams::builder()
+        .expression(expression)
+        .return_by_value(true)
+        .await_promise(await_promise);
+    if let Some(context) = context {
+        builder = builder.context_id(context);
+    }
+    let params = builder.build().map_err(|e| CommandError::new(400, e))?;
+    let result = page.evaluate_expression(params).await?;
+    Ok(result.value().cloned().unwrap_or(serde_json::Value::Null))
+}
+
+pub async fn execute(browser: &Browser, page: &Page, state: &HeadlessState, command: PageCommand) -> CommandResult {
+    match command {
+        PageCommand::SetPermissions { origin, grant, deny } => {
+            crate::permissions::apply(browser, origin.as_deref(), &grant, &deny).await?;
+            let mut grants = state.permissions.lock().unwrap();
+            grants.record(origin, grant, deny);
+            Ok(grants.to_json())
+        }
+        PageCommand::ResetPermissions => {
+            crate::permissions::reset(browser).await?;
+            let mut grants = state.permissions.lock().unwrap();
+            grants.clear();
+            Ok(grants.to_json())
+        }
+        PageCommand::Evaluate { expression, await_promise } => evaluate(page, expression, await_promise).await,
+        PageCommand::LatencyTest { frames } => crate::latency::run(page, state, frames).await,
+        PageCommand::Navigate { url, source } => {
+            log_info!("Navigating to: {}", url);
+            // Recorded first so the capture loop sees no pending URL change
+            state.navigation.lock().unwrap().next(&url, source);
+            *state.current_url.lock().unwrap() = url.clone();
+            state.record_action(JournalAction::Navigate { url: url.clone() });
+            if let Err(e) = page.goto(&url).await {
+                state.status.lock().unwrap().navigation_failed(&url, e.to_string());
+                let failure = serde_json::json!({"url": url, "error": e.to_string()});
+                state.events.lock().unwrap().publish("navigation_failed", failure);
+                return Err(e.into());
+            }
+            Ok(serde_json::json!({ "url": page.url().await? }))
+        }
+        PageCommand::Click { selector, frame, stability } => {
+            let target = target(page, frame.as_deref()).await?;
+            let result = interact(page, target.as_ref(), &selector, stability, CLICK_SCRIPT, &[]).await?;
+            state.record_action(JournalAction::Click { selector, x: 0.0, y: 0.0 });
+            Ok(result)
+        }
+        PageCommand::ClickAt { x, y, button, click_count } => {
+            check_in_viewport(state, x, y)?;
+            for kind in [DispatchMouseEventType::MousePressed, DispatchMouseEventType::MouseReleased] {
+                let mut params = DispatchMouseEventParams::new(kind, x, y);
+                params.button = Some(button.cdp());
+                params.click_count = Some(click_count as i64);
+                page.execute(params).await?;
+            }
+            state.record_action(JournalAction::Click { selector: String::new(), x, y });
+            Ok(serde_json::json!({ "x": x, "y": y }))
+        }
+        PageCommand::Press { press } => {
+            crate::keys::press(page, &press).await?;
+            let modifiers = press.modifier_names().to_vec();
+            state.record_action(JournalAction::Key { selector: String::new(), key: press.key().to_string(), modifiers: modifiers.clone() });
+            Ok(serde_json::json!({ "key": press.key(), "modifiers": modifiers }))
+        }
+        PageCommand::Type { selector, text, submit, secret, method, frame, stability } => {
+            let target = target(page, frame.as_deref()).await?;
+            let field = if selector.trim().is_empty() {
+                let focused = evaluate_in(page, None, js_call(FOCUSED_SCRIPT, &[]), false).await?;
+                if focused.is_null() {
+                    return Err(CommandError::new(422, "no focused element takes text; pass a selector"));
+                }
+                focused
+            } else {
+                // Focusing the field focuses its frame, so the key events below reach it
+                interact(page, target.as_ref(), &selector, stability, FOCUS_SCRIPT, &[]).await?
+            };
+            let method = method.resolve(&text);
+            match method {
+                TypeMethod::Insert => {
+                    page.execute(InsertTextParams::new(text.clone())).await?;
+                }
+                _ => press_keys(page, &text).await?,
+            }
+            // An Enter may have navigated away already, taking the field with it
+            let _ = evaluate_in(page, target.as_ref().map(|t| t.context), js_call(FINISH_SCRIPT, &[submit.into()]), false).await;
+            let password = field["password"].as_bool() == Some(true);
+            let typed = text.chars().count();
+            let value = if secret || password { "***".to_string() } else { text };
+            state.record_action(JournalAction::Type { selector, value });
+            Ok(serde_json::json!({ "password": password, "method": method.name(), "typed": typed }))
+        }
+        PageCommand::Select { selector, by, options, frame, stability } => {
+            let target = target(page, frame.as_deref()).await?;
+            let args = [by.into(), options.into()];
+            let mut result = interact(page, target.as_ref(), &selector, stability, SELECT_SCRIPT, &args).await?;
+            if let Some(missing) = result.get("missing") {
+                let missing: Vec<String> = missing.as_array().into_iter().flatten().map(|m| m.to_string()).collect();
+                return Err(CommandError::new(404, format!("no option of {} has {} {}", selector, by, missing.join(", "))));
+            }
+            let values: Vec<&str> = result["selected"].as_array().into_iter().flatten().filter_map(|o| o["value"].as_str()).collect();
+            state.record_action(JournalAction::Change { selector, value: values.join(",") });
+            Ok(result.take())
+        }
+        PageCommand::SetFiles { selector, paths } => crate::file_upload::set_files(page, &selector, paths).await,
+        PageCommand::ResponseBodies { request_ids } => crate::har::response_bodies(page, request_ids).await,
+        PageCommand::HoverAt { x, y } => {
+            check_in_viewport(state, x, y)?;
+            page.execute(DispatchMouseEventParams::new(DispatchMouseEventType::MouseMoved, x, y)).await?;
+            Ok(serde_json::json!({ "x": x, "y": y }))
+        }
+        PageCommand::Hover { selector, frame, stability } => {
+            let target = target(page, frame.as_deref()).await?;
+            let center = interact(page, target.as_ref(), &selector, stability, CENTER_SCRIPT, &[]).await.map_err(|mut e| {
+                if e.message.contains("no element matches") {
+                    e.status = 404;
+                }
+                e
+            })?;
+            // The box is relative to its frame; the mouse needs top-viewport coordinates
+            let (dx, dy) = target.as_ref().map_or((0.0, 0.0), |t| t.offset);
+            let (x, y) = (center["x"].as_f64().unwrap_or_default() + dx, center["y"].as_f64().unwrap_or_default() + dy);
+            page.execute(DispatchMouseEventParams::new(DispatchMouseEventType::MouseMoved, x, y)).await?;
+            Ok(serde_json::json!({ "x": x, "y": y }))
+        }
+        PageCommand::Reload { restore_scroll, ignore_cache } => {
+            log_info!("Reloading (restore scroll: {})", restore_scroll);
+            crate::scroll::reload(page, restore_scroll, ignore_cache).await
+        }
+        PageCommand::History { delta } => {
+            let history = page.execute(GetNavigationHistoryParams::default()).await?.result;
+            let entry = usize::try_from(history.current_index + delta).ok().and_then(|i| history.entries.get(i)).ok_or_else(|| {
+                CommandError::new(409, if delta < 0 { "no history entry to go back to" } else { "no history entry to go forward to" })
+            })?;
+            log_info!("History {}: {}", if delta < 0 { "back" } else { "forward" }, entry.url);
+            // Recorded first so the capture loop sees no pending URL change
+            state.navigation.lock().unwrap().next(&entry.url, "api");
+            *state.current_url.lock().unwrap() = entry.url.clone();
+            page.execute(NavigateToHistoryEntryParams::new(entry.id)).await?;
+            page.wait_for_navigation().await?;
+            let url = page.url().await?.unwrap_or_else(|| entry.url.clone());
+            if url != entry.url {
+                state.navigation.lock().unwrap().next(&url, "api");
+                *state.current_url.lock().unwrap() = url.clone();
+            }
+            state.record_action(JournalAction::Navigate { url: url.clone() });
+            Ok(serde_json::json!({ "url": url }))
+        }
+        PageCommand::FrameTree => frames::tree(page).await,
+        PageCommand::PageInfo => crate::status::page_info(page).await,
+        PageCommand::SetHeaders { headers } => {
+            let result = crate::headers::apply(page, &headers).await?;
+            *state.extra_headers.lock().unwrap() = headers;
+            Ok(result)
+        }
+        PageCommand::Cookies => crate::cookies::list(page).await,
+        PageCommand::SetCookies { cookies } => crate::cookies::set(page, cookies).await,
+        PageCommand::DeleteCookies { name, domain } => crate::cookies::delete(page, name, domain).await,
+        PageCommand::Source { served } => crate::source::capture(page, &state.document, served).await,
+        PageCommand::ExportState => {
+            let exported = handoff::export(page, state.viewport).await?;
+            Ok(serde_json::to_value(exported).unwrap_or_default())
+        }
+        PageCommand::ImportState { state: handoff } => {
+            handoff.validate().map_err(|e| CommandError::new(400, e))?;
+            log_info!("Importing handoff state for {}", handoff.url);
+            // Recorded first so the capture loop sees no pending URL change
+            state.navigation.lock().unwrap().next(&handoff.url, "handoff");
+            *state.current_url.lock().unwrap() = handoff.url.clone();
+            state.record_action(JournalAction::Navigate { url: handoff.url.clone() });
+            handoff::import(page, &handoff).await?;
+            let mut summary = handoff.summary();
+            // Runtime imports keep the capture viewport; --import-state applies it
+            summary["viewport_applied"] = false.into();
+            Ok(summary)
+        }
+        PageCommand::Resources => {
+            let report = crate::resources::headless_report(browser, page).await?;
+            Ok(serde_json::to_value(report).unwrap_or_default())
+        }
+        PageCommand::Perf => crate::perf::sample(page).await,
+        PageCommand::TraceStart { categories } => crate::trace::start(page, &state.tracer, categories).await,
+        PageCommand::TraceStop => crate::trace::stop(page, &state.tracer).await,
+        PageCommand::BrowserVersion => {
+            let version = browser.version().await?;
+            Ok(serde_json::json!({
+                "product": version.product,
+                "revision": version.revision,
+                "user_agent": version.user_agent,
+                "protocol_version": version.protocol_version,
+            }))
+        }
+        PageCommand::Render { settle, deterministic } => {
+            if deterministic {
+                page.evaluate(deterministic::inject_style_script(&state.deterministic)).await?;
+            }
+            let params = crate::screenshot_params(CaptureScreenshotFormat::Jpeg);
+            let captured = crate::settle::capture(page, params, settle).await;
+            if deterministic {
+                let _ = page.evaluate(deterministic::remove_style_script()).await;
+            }
+            let (frame, report) = captured?;
+            crate::redact::refresh(page, &state.redaction, 0, true).await;
+            let frame = crate::redact::apply(&state.redaction, frame)
+                .ok_or_else(|| CommandError::new(500, "failed to apply redaction to the frame"))?;
+            Ok(serde_json::json!({
+                "frame": BASE64.encode(frame),
+                "url": page.url().await?,
+                "settle": report,
+            }))
+        }
+        PageCommand::Screenshot { request } => crate::screenshot::capture(page, state, request).await,
+        PageCommand::SaveCapture { name } => {
+            let store = state
+                .captures
+                .as_ref()
+                .ok_or_else(|| CommandError::new(404, "captures are disabled, start with --capture-dir"))?;
+            let frame = state
+                .screenshot_buffer
+                .lock()
+                .unwrap()
+                .clone()
+                .ok_or_else(|| CommandError::new(503, "no frame available"))?;
+            let (frame_width, frame_height) = crate::overlay::frame_dimensions(&frame).unwrap_or_default();
+            let meta = FrameMetadata {
+                name,
+                file: String::new(),
+                seq: 0,
+                url: page.url().await?.unwrap_or_default(),
+                title: page.get_title().await?,
+                captured_at: crate::now_millis(),
+                viewport_width: state.viewport.0,
+                viewport_height: state.viewport.1,
+                frame_width,
+                frame_height,
+                scale: frame_width as f64 / state.viewport.0.max(1) as f64,
+                navigation: state.navigation.lock().unwrap().clone(),
+            };
+            let saved = store
+                .lock()
+                .unwrap()
+                .save(&frame, meta)
+                .map_err(|e| CommandError::new(500, format!("failed to save capture: {}", e)))?;
+            if let Some(uploader) = &state.uploader {
+                let sidecar = serde_json::to_vec_pretty(&saved).unwrap_or_default();
+                uploader.enqueue(&format!("captures/{}", saved.file), frame, "image/jpeg");
+                uploader.enqueue(&format!("captures/{}.json", saved.name), sidecar, "application/json");
+            }
+            Ok(serde_json::json!(saved))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_keys_are_printable_ascii_and_newline() {
+        assert!("Hello, world! 0-9 ~{}[]\n".chars().all(is_plain_key));
+        for c in ['\t', '\r', '\u{7f}', 'é', 'ß', 'こ', '世', '👍', 'ש', '\u{200d}'] {
+            assert!(!is_plain_key(c), "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn auto_picks_keys_for_plain_text_and_insert_otherwise() {
+        assert_eq!(TypeMethod::Auto.resolve("user@example.com"), TypeMethod::Keys);
+        assert_eq!(TypeMethod::Auto.resolve("two\nlines"), TypeMethod::Keys);
+        assert_eq!(TypeMethod::Auto.resolve(""), TypeMethod::Keys);
+        assert_eq!(TypeMethod::Auto.resolve("こんにちは"), TypeMethod::Insert);
+        assert_eq!(TypeMethod::Auto.resolve("ok 👍🏽"), TypeMethod::Insert);
+        assert_eq!(TypeMethod::Auto.resolve("שלום"), TypeMethod::Insert);
+        assert_eq!(TypeMethod::Auto.resolve("café"), TypeMethod::Insert);
+    }
+
+    #[test]
+    fn explicit_methods_are_kept() {
+        assert_eq!(TypeMethod::Keys.resolve("こんにちは"), TypeMethod::Keys);
+        assert_eq!(TypeMethod::Insert.resolve("plain"), TypeMethod::Insert);
+        assert_eq!(TypeMethod::Auto.resolve("plain").name(), "keys");
+        assert_eq!(TypeMethod::Auto.resolve("絵文字").name(), "insert");
+    }
+
+    #[test]
+    fn js_call_encodes_arguments() {
+        let call = js_call("function(a, b) { return a + b; }", &["it's \"quoted\"".into(), serde_json::json!({"n": 1})]);
+        assert_eq!(call, r#"(function(a, b) { return a + b; })("it's \"quoted\"", {"n":1})"#);
+    }
+}
@@ -0,0 +1,170 @@
+use chromiumoxide::cdp::browser_protocol::network::{
+    CookieParam, CookieSameSite, DeleteCookiesParams, GetCookiesParams, SetCookiesParams, TimeSinceEpoch,
+};
+use chromiumoxide::Page;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{CommandError, CommandResult};
+
+// ============== Cookies ==============
+//
+// GET /cookies lists the cookies the current page sees (HttpOnly included),
+// POST /cookies sets an array of them through Network.setCookies and
+// DELETE /cookies?name=&domain= removes one name, for the page's host when
+// no domain is given. A cookie without a domain is set for the page's URL.
+// All of them are validated before any is set, so a bad entry sets nothing.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub domain: String,
+    #[serde(default = "default_path")]
+    pub path: String,
+    /// Seconds since the epoch; `None` for session cookies.
+    #[serde(default)]
+    pub expires: Option<f64>,
+    #[serde(default)]
+    pub http_only: bool,
+    #[serde(default)]
+    pub secure: bool,
+    /// "Strict", "Lax" or "None".
+    #[serde(default)]
+    pub same_site: Option<String>,
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+impl Cookie {
+    fn param(&self, page_url: &str) -> Result<CookieParam, CommandError> {
+        let invalid = |reason: String| CommandError::new(400, format!("invalid cookie {}: {}", self.name, reason));
+        if self.name.is_empty() {
+            return Err(CommandError::new(400, "cookie without a name"));
+        }
+        let mut builder = CookieParam::builder()
+            .name(self.name.clone())
+            .value(self.value.clone())
+            .path(self.path.clone())
+            .secure(self.secure)
+            .http_only(self.http_only);
+        builder = if self.domain.is_empty() {
+            builder.url(page_url.to_string())
+        } else {
+            // A leading dot only widens the match to subdomains
+            url::Host::parse(self.domain.trim_start_matches('.'))
+                .map_err(|e| invalid(format!("domain '{}' does not parse: {}", self.domain, e)))?;
+            builder.domain(self.domain.clone())
+        };
+        if let Some(same_site) = &self.same_site {
+            let same_site = same_site
+                .parse::<CookieSameSite>()
+                .map_err(|_| invalid(format!("sameSite '{}', expected Strict, Lax or None", same_site)))?;
+            builder = builder.same_site(same_site);
+        }
+        if let Some(expires) = self.expires {
+            builder = builder.expires(TimeSinceEpoch::new(expires));
+        }
+        builder.build().map_err(invalid)
+    }
+}
+
+async fn page_cookies(page: &Page) -> Result<Vec<Cookie>, CommandError> {
+    let cookies = page.execute(GetCookiesParams::default()).await?.result.cookies;
+    Ok(cookies
+        .into_iter()
+        .map(|c| Cookie {
+            expires: (!c.session && c.expires > 0.0).then_some(c.expires),
+            same_site: c.same_site.map(|s| s.as_ref().to_string()),
+            name: c.name,
+            value: c.value,
+            domain: c.domain,
+            path: c.path,
+            secure: c.secure,
+            http_only: c.http_only,
+        })
+        .collect())
+}
+
+/// GET /cookies.
+pub async fn list(page: &Page) -> CommandResult {
+    Ok(serde_json::json!(page_cookies(page).await?))
+}
+
+/// POST /cookies.
+pub async fn set(page: &Page, cookies: Vec<Cookie>) -> CommandResult {
+    let page_url = page.url().await?.unwrap_or_default();
+    let params = cookies.iter().map(|c| c.param(&page_url)).collect::<Result<Vec<_>, _>>()?;
+    let count = params.len();
+    if count > 0 {
+        page.execute(SetCookiesParams::new(params)).await?;
+    }
+    Ok(serde_json::json!({ "set": count }))
+}
+
+/// DELETE /cookies; answers how many of the page's cookies went away.
+pub async fn delete(page: &Page, name: String, domain: Option<String>) -> CommandResult {
+    let before = page_cookies(page).await?.len();
+    let mut params = DeleteCookiesParams::new(name);
+    match domain {
+        Some(domain) => params.domain = Some(domain),
+        None => params.url = page.url().await?,
+    }
+    page.execute(params).await?;
+    let after = page_cookies(page).await?.len();
+    Ok(serde_json::json!({ "deleted": before.saturating_sub(after) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "http://127.0.0.1:8000/page.html";
+
+    fn cookie(json: serde_json::Value) -> Cookie {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn defaults_to_the_page_url_and_root_path() {
+        let param = cookie(serde_json::json!({"name": "session", "value": "abc"})).param(PAGE).unwrap();
+        assert_eq!(param.url.as_deref(), Some(PAGE));
+        assert_eq!(param.domain, None);
+        assert_eq!(param.path.as_deref(), Some("/"));
+        assert_eq!((param.secure, param.http_only), (Some(false), Some(false)));
+        assert!(param.same_site.is_none() && param.expires.is_none());
+    }
+
+    #[test]
+    fn keeps_domain_same_site_and_expiry() {
+        let param = cookie(serde_json::json!({
+            "name": "wide", "value": "x", "domain": ".example.com", "path": "/app",
+            "expires": 1_900_000_000.0, "httpOnly": true, "sameSite": "Lax",
+        }))
+        .param(PAGE)
+        .unwrap();
+        assert_eq!(param.url, None);
+        assert_eq!(param.domain.as_deref(), Some(".example.com"));
+        assert_eq!(param.path.as_deref(), Some("/app"));
+        assert_eq!(param.http_only, Some(true));
+        assert_eq!(param.same_site, Some(CookieSameSite::Lax));
+        assert_eq!(param.expires.map(|e| *e.inner()), Some(1_900_000_000.0));
+    }
+
+    #[test]
+    fn rejects_bad_cookies() {
+        let error = cookie(serde_json::json!({"name": "bad", "value": "1", "domain": "not a domain"})).param(PAGE).unwrap_err();
+        assert_eq!(error.status, 400);
+        assert!(error.message.contains("domain 'not a domain' does not parse"), "{}", error.message);
+
+        let error = cookie(serde_json::json!({"name": "bad", "value": "1", "sameSite": "Sometimes"})).param(PAGE).unwrap_err();
+        assert_eq!(error.status, 400);
+        assert!(error.message.contains("sameSite 'Sometimes'"), "{}", error.message);
+
+        let error = cookie(serde_json::json!({"name": "", "value": "1"})).param(PAGE).unwrap_err();
+        assert_eq!(error.status, 400);
+    }
+}
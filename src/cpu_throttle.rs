@@ -0,0 +1,80 @@
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::emulation::SetCpuThrottlingRateParams;
+use chromiumoxide::Page;
+use serde::Deserialize;
+
+use crate::source::SharedDocument;
+
+// ============== CPU Throttling (headless) ==============
+//
+// POST /cpu-throttle `{"rate": 4}` slows the streamed page's CPU down four
+// times (Emulation.setCPUThrottlingRate), so the live stream shows how janky
+// it is on slow hardware; DELETE puts it back to 1. Emulation state does not
+// always survive a cross-process navigation, so the capture loop applies the
+// rate again whenever the main-frame document changes. /status reports the
+// rate in effect.
+
+/// Highest accepted rate; beyond this pages barely render at all.
+const MAX_RATE: f64 = 100.0;
+
+#[derive(Debug, Deserialize)]
+pub struct ThrottleRequest {
+    pub rate: f64,
+}
+
+#[derive(Debug)]
+pub struct CpuThrottle {
+    rate: f64,
+    /// Rate last sent to Chrome and the document it was sent for.
+    applied: (f64, Option<String>),
+}
+
+pub type SharedCpuThrottle = Arc<Mutex<CpuThrottle>>;
+
+impl Default for CpuThrottle {
+    fn default() -> Self {
+        CpuThrottle { rate: 1.0, applied: (1.0, None) }
+    }
+}
+
+impl CpuThrottle {
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn set(&mut self, rate: f64) -> Result<(), String> {
+        if !rate.is_finite() || rate < 1.0 {
+            return Err(format!("rate must be at least 1 (no throttling), got {}", rate));
+        }
+        if rate > MAX_RATE {
+            return Err(format!("rate must be at most {}", MAX_RATE));
+        }
+        self.rate = rate;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "rate": self.rate })
+    }
+}
+
+/// Applies the requested rate if it changed, or if a throttled page loaded
+/// a new document since it was applied.
+pub async fn sync(page: &Page, throttle: &SharedCpuThrottle, document: &SharedDocument) {
+    let current = document.lock().unwrap().as_ref().map(|id| id.inner().clone());
+    let rate = {
+        let throttle = throttle.lock().unwrap();
+        let (applied_rate, applied_document) = &throttle.applied;
+        let stale = throttle.rate != 1.0 && *applied_document != current;
+        if *applied_rate == throttle.rate && !stale {
+            return;
+        }
+        throttle.rate
+    };
+    if let Err(e) = page.execute(SetCpuThrottlingRateParams::new(rate)).await {
+        log_error!("Failed to set CPU throttling to {}: {}", rate, e);
+    }
+    // Also on failure, so a broken page isn't retried every frame
+    throttle.lock().unwrap().applied = (rate, current);
+}
@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+// ============== Crash Reports ==============
+//
+// With --crash-dir, a panic hook writes a JSON report (message, backtrace,
+// recent logs, context) and a PID file marks the process as running. If the
+// PID file is still there at the next start, the previous run died without
+// cleanup and an "unclean-shutdown" report is written for it.
+
+const PID_FILE: &str = "running.pid";
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+type ContextFn = Box<dyn Fn() -> serde_json::Value + Send + Sync>;
+static CONTEXT: Mutex<Option<ContextFn>> = Mutex::new(None);
+
+fn uptime_secs() -> f64 {
+    STARTED.get().map_or(0.0, |s| s.elapsed().as_secs_f64())
+}
+
+/// Prepares the crash directory, reports an unclean previous exit and installs the panic hook.
+pub fn init(dir: &Path) -> std::io::Result<()> {
+    STARTED.get_or_init(Instant::now);
+    std::fs::create_dir_all(dir)?;
+    let _ = DIR.set(dir.to_path_buf());
+
+    let pid_path = dir.join(PID_FILE);
+    if let Ok(previous) = std::fs::read_to_string(&pid_path) {
+        let previous: serde_json::Value = serde_json::from_str(&previous).unwrap_or_default();
+        write_report("unclean-shutdown", serde_json::json!({
+            "message": "previous run exited without a clean shutdown",
+            "previous_run": previous,
+        }));
+        log_error!("Previous run (pid {}) did not shut down cleanly", previous["pid"]);
+    }
+    let marker = serde_json::json!({"pid": std::process::id(), "started_at": crate::now_millis()});
+    std::fs::write(&pid_path, marker.to_string())?;
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic payload".to_string());
+        write_report("panic", serde_json::json!({
+            "message": message,
+            "location": info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            "thread": std::thread::current().name().unwrap_or("unnamed"),
+            "backtrace": std::backtrace::Backtrace::force_capture().to_string(),
+        }));
+        default_hook(info);
+    }));
+    Ok(())
+}
+
+/// Registers a snapshot of runtime state (current URL, frame stats, ...)
+/// added to every report. It must only use `try_lock`.
+pub fn set_context(context: impl Fn() -> serde_json::Value + Send + Sync + 'static) {
+    *CONTEXT.lock().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(context));
+}
+
+/// Writes `<kind>-<millis>.json` into the crash directory, if one is configured.
+pub fn write_report(kind: &str, details: serde_json::Value) {
+    let Some(dir) = DIR.get() else {
+        return;
+    };
+    let context = match CONTEXT.try_lock() {
+        Ok(guard) => guard.as_ref().map(|f| f()),
+        Err(_) => None,
+    };
+    let now = crate::now_millis();
+    let report = serde_json::json!({
+        "kind": kind,
+        "timestamp": now,
+        "pid": std::process::id(),
+        "uptime_secs": uptime_secs(),
+        "details": details,
+        "context": context,
+        "logs": crate::logs::try_recent(),
+    });
+    let path = dir.join(format!("{}-{}.json", kind, now));
+    if let Ok(body) = serde_json::to_vec_pretty(&report) {
+        let _ = std::fs::write(path, body);
+    }
+}
+
+/// Removes the PID file so the next start doesn't report this run.
+pub fn clean_exit() {
+    if let Some(dir) = DIR.get() {
+        let _ = std::fs::remove_file(dir.join(PID_FILE));
+    }
+}
+
+/// Every report in the crash directory, newest first.
+pub fn list() -> Option<Vec<serde_json::Value>> {
+    let dir = DIR.get()?;
+    let mut reports: Vec<serde_json::Value> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|p| {
+            let mut report: serde_json::Value = serde_json::from_slice(&std::fs::read(&p).ok()?).ok()?;
+            report["file"] = p.file_name()?.to_string_lossy().into_owned().into();
+            Some(report)
+        })
+        .collect();
+    reports.sort_by_key(|r| std::cmp::Reverse(r["timestamp"].as_u64().unwrap_or(0)));
+    Some(reports)
+}
+
+/// Serves `/crashes` for either HTTP server.
+pub fn response() -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    match list() {
+        Some(reports) => crate::json_response(200, &serde_json::json!({
+            "dir": DIR.get(),
+            "uptime_secs": uptime_secs(),
+            "crashes": reports,
+        })),
+        None => crate::json_response(404, &serde_json::json!({"error": "crash reports are disabled, start with --crash-dir"})),
+    }
+}
@@ -0,0 +1,153 @@
+use chromiumoxide::cdp::browser_protocol::page::AddScriptToEvaluateOnNewDocumentParams;
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+
+// ============== Deterministic Rendering ==============
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Feature {
+    /// animation: none and transition: none everywhere.
+    Animations,
+    /// Transparent text caret.
+    Carets,
+    /// Hidden scrollbars and instant scrolling.
+    Scrollbars,
+    /// Date.now() and new Date() frozen at FIXED_TIME_MS.
+    Clock,
+    /// Math.random() replaced with a seeded generator.
+    Random,
+}
+
+const ALL: [Feature; 5] = [Feature::Animations, Feature::Carets, Feature::Scrollbars, Feature::Clock, Feature::Random];
+
+/// 2024-01-01T00:00:00Z
+const FIXED_TIME_MS: u64 = 1_704_067_200_000;
+const RANDOM_SEED: u32 = 42;
+const STYLE_ID: &str = "__rb_deterministic__";
+/// Separate id for per-request styles so removing them keeps the launch-time one.
+const REQUEST_STYLE_ID: &str = "__rb_deterministic_request__";
+
+/// All features except the skipped ones.
+pub fn features(skip: &[Feature]) -> Vec<Feature> {
+    ALL.into_iter().filter(|f| !skip.contains(f)).collect()
+}
+
+pub fn css(features: &[Feature]) -> String {
+    let mut css = String::new();
+    if features.contains(&Feature::Animations) {
+        css.push_str(
+            "*, *::before, *::after { animation: none !important; transition: none !important; }\n",
+        );
+    }
+    if features.contains(&Feature::Carets) {
+        css.push_str("* { caret-color: transparent !important; }\n");
+    }
+    if features.contains(&Feature::Scrollbars) {
+        css.push_str(
+            "html, * { scroll-behavior: auto !important; scrollbar-width: none !important; }\n\
+             ::-webkit-scrollbar { display: none !important; }\n",
+        );
+    }
+    css
+}
+
+/// Script that adds the CSS to the current document for a single capture.
+pub fn inject_style_script(features: &[Feature]) -> String {
+    crate::commands::js_call(
+        r#"function(id, css) {
+            let style = document.getElementById(id);
+            if (!style) {
+                style = document.createElement('style');
+                style.id = id;
+                (document.head || document.documentElement).appendChild(style);
+            }
+            style.textContent = css;
+            return id;
+        }"#,
+        &[REQUEST_STYLE_ID.into(), css(features).into()],
+    )
+}
+
+pub fn remove_style_script() -> String {
+    crate::commands::js_call(
+        "function(id) { const el = document.getElementById(id); if (el) el.remove(); }",
+        &[REQUEST_STYLE_ID.into()],
+    )
+}
+
+fn init_script(features: &[Feature]) -> String {
+    let mut script = String::new();
+    if features.contains(&Feature::Clock) {
+        script.push_str(&format!(
+            r#"(function() {{
+    const FIXED = {fixed};
+    const RealDate = Date;
+    function FixedDate(...args) {{
+        if (!new.target) return new RealDate(FIXED).toString();
+        return args.length ? new RealDate(...args) : new RealDate(FIXED);
+    }}
+    FixedDate.prototype = RealDate.prototype;
+    FixedDate.now = function() {{ return FIXED; }};
+    FixedDate.parse = RealDate.parse;
+    FixedDate.UTC = RealDate.UTC;
+    window.Date = FixedDate;
+}})();
+"#,
+            fixed = FIXED_TIME_MS
+        ));
+    }
+    if features.contains(&Feature::Random) {
+        // mulberry32
+        script.push_str(&format!(
+            r#"(function() {{
+    let state = {seed} >>> 0;
+    Math.random = function() {{
+        state = (state + 0x6D2B79F5) >>> 0;
+        let t = state;
+        t = Math.imul(t ^ (t >>> 15), t | 1);
+        t ^= t + Math.imul(t ^ (t >>> 7), t | 61);
+        return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+    }};
+}})();
+"#,
+            seed = RANDOM_SEED
+        ));
+    }
+    let css = css(features);
+    if !css.is_empty() {
+        script.push_str(&crate::commands::init_style_script(STYLE_ID, &css));
+    }
+    script
+}
+
+/// Registers the init script for every document the page loads from now on.
+pub async fn install(page: &Page, features: &[Feature]) -> Result<(), CdpError> {
+    page.execute(AddScriptToEvaluateOnNewDocumentParams::new(init_script(features))).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skipped_features_are_left_out() {
+        assert_eq!(features(&[]), ALL);
+        assert_eq!(features(&[Feature::Clock, Feature::Random]), [Feature::Animations, Feature::Carets, Feature::Scrollbars]);
+
+        let css = css(&features(&[Feature::Carets]));
+        assert!(css.contains("animation: none") && css.contains("::-webkit-scrollbar"));
+        assert!(!css.contains("caret-color"));
+    }
+
+    #[test]
+    fn init_script_covers_the_enabled_features() {
+        let script = init_script(&ALL);
+        assert!(script.contains(&FIXED_TIME_MS.to_string()) && script.contains("Math.random"));
+        assert!(script.contains(STYLE_ID));
+
+        let script = init_script(&[Feature::Random]);
+        assert!(script.contains("Math.random") && !script.contains("FixedDate") && !script.contains(STYLE_ID));
+        assert!(init_script(&[]).is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::page::{DialogType, EventJavascriptDialogOpening, HandleJavaScriptDialogParams};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::buffers::{self, Buffer, BufferStats};
+
+// ============== JavaScript Dialogs ==============
+//
+// An alert(), confirm() or prompt() blocks the headless page until someone
+// answers it, which freezes the stream on a stale frame. Every dialog of the
+// streamed page is answered as soon as it opens, following --dialogs
+// accept|dismiss (dismiss by default), changeable with POST /dialog. POST
+// /dialog can also supply `prompt_text` for the next prompt(), which is then
+// accepted with it whatever the policy; accepted prompts otherwise get their
+// default value. beforeunload is always accepted so navigating never stalls
+// on it. GET /dialogs lists the handled dialogs oldest first (type, message,
+// action taken), so tests can assert a confirm actually appeared; DELETE
+// /dialogs clears them. Only the newest `--buffer-size dialogs=N` (200 by
+// default) are kept.
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DialogPolicy {
+    Accept,
+    #[default]
+    Dismiss,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DialogRequest {
+    #[serde(default)]
+    pub policy: Option<DialogPolicy>,
+    #[serde(default)]
+    pub prompt_text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub message: String,
+    pub url: String,
+    /// "accepted" or "dismissed".
+    pub action: &'static str,
+    /// Text an accepted prompt was answered with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_text: Option<String>,
+    pub timestamp: u128,
+}
+
+#[derive(Default)]
+pub struct Dialogs {
+    policy: DialogPolicy,
+    /// For the next prompt() only.
+    prompt_text: Option<String>,
+    entries: VecDeque<Entry>,
+    dropped: u64,
+}
+
+pub type SharedDialogs = Arc<Mutex<Dialogs>>;
+
+fn kind(dialog: &DialogType) -> &'static str {
+    match dialog {
+        DialogType::Alert => "alert",
+        DialogType::Confirm => "confirm",
+        DialogType::Prompt => "prompt",
+        DialogType::Beforeunload => "beforeunload",
+    }
+}
+
+impl Dialogs {
+    pub fn new(policy: DialogPolicy) -> Self {
+        Dialogs { policy, ..Default::default() }
+    }
+
+    pub fn configure(&mut self, req: DialogRequest) {
+        if let Some(policy) = req.policy {
+            self.policy = policy;
+        }
+        if req.prompt_text.is_some() {
+            self.prompt_text = req.prompt_text;
+        }
+    }
+
+    /// `{policy, prompt_text}` for /dialog.
+    pub fn settings_json(&self) -> serde_json::Value {
+        serde_json::json!({ "policy": self.policy, "prompt_text": self.prompt_text })
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "dialogs": self.entries, "dropped": self.dropped })
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn buffer_stats(&self) -> BufferStats {
+        buffers::stats(Buffer::Dialogs, &self.entries, self.dropped)
+    }
+
+    /// Decides `event` and records the decision.
+    fn decide(&mut self, event: &EventJavascriptDialogOpening) -> HandleJavaScriptDialogParams {
+        let (accept, prompt_text) = match event.r#type {
+            DialogType::Beforeunload => (true, None),
+            DialogType::Prompt => match self.prompt_text.take() {
+                Some(text) => (true, Some(text)),
+                None if self.policy == DialogPolicy::Accept => (true, Some(event.default_prompt.clone().unwrap_or_default())),
+                None => (false, None),
+            },
+            _ => (self.policy == DialogPolicy::Accept, None),
+        };
+        while self.entries.len() >= Buffer::Dialogs.capacity() {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(Entry {
+            kind: kind(&event.r#type),
+            message: event.message.clone(),
+            url: event.url.clone(),
+            action: if accept { "accepted" } else { "dismissed" },
+            prompt_text: prompt_text.clone(),
+            timestamp: crate::now_millis(),
+        });
+        HandleJavaScriptDialogParams { accept, prompt_text }
+    }
+}
+
+/// Answers every dialog `page` opens, for as long as the page lives.
+pub async fn watch(page: &Page, dialogs: SharedDialogs) -> Result<tokio::task::JoinHandle<()>, CdpError> {
+    let mut opening = page.event_listener::<EventJavascriptDialogOpening>().await?;
+    let page = page.clone();
+    Ok(tokio::spawn(async move {
+        while let Some(event) = opening.next().await {
+            let params = dialogs.lock().unwrap().decide(&event);
+            if let Err(e) = page.execute(params).await {
+                log_error!("Failed to answer {} dialog: {}", kind(&event.r#type), e);
+            }
+        }
+    }))
+}
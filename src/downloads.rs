@@ -0,0 +1,252 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::browser::{
+    DownloadProgressState, EventDownloadProgress, EventDownloadWillBegin, SetDownloadBehaviorBehavior,
+    SetDownloadBehaviorParams,
+};
+use chromiumoxide::Browser;
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::buffers::{self, Buffer, BufferStats};
+
+// ============== Downloads (headless) ==============
+//
+// Downloads started by the streamed page (a clicked export link, a
+// Content-Disposition: attachment response) are saved into --download-dir,
+// a temp directory by default, under their download id so two files with
+// the same suggested name don't collide. Browser.downloadWillBegin and
+// Browser.downloadProgress keep the list current: GET /downloads shows each
+// download's URL, suggested file name, state (in_progress, completed,
+// canceled), bytes received and total, and path on disk. GET
+// /downloads/{id}/file serves a completed download's bytes under its
+// suggested name; DELETE /downloads/{id} removes the file and the entry, and
+// DELETE /downloads every finished one. The list is capped by the downloads
+// buffer: past it the oldest finished download is evicted with its file.
+// Unfinished downloads are never evicted, Chrome is still writing them.
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    InProgress,
+    Completed,
+    Canceled,
+}
+
+impl State {
+    pub fn name(self) -> &'static str {
+        match self {
+            State::InProgress => "in_progress",
+            State::Completed => "completed",
+            State::Canceled => "canceled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Download {
+    pub id: String,
+    pub url: String,
+    pub suggested_filename: String,
+    pub state: State,
+    pub received_bytes: u64,
+    pub total_bytes: u64,
+    pub path: PathBuf,
+    pub started_at: u128,
+}
+
+impl Download {
+    /// Header values must be ASCII, so anything else in the name becomes `_`.
+    pub fn content_disposition(&self) -> String {
+        let name: String = self
+            .suggested_filename
+            .chars()
+            .map(|c| if c == ' ' || (c.is_ascii_graphic() && c != '"' && c != '\\') { c } else { '_' })
+            .collect();
+        format!("attachment; filename=\"{}\"", name)
+    }
+}
+
+pub struct Downloads {
+    dir: PathBuf,
+    entries: Vec<Download>,
+    dropped: u64,
+}
+
+pub type SharedDownloads = Arc<Mutex<Downloads>>;
+
+/// --download-dir, or a fresh directory under the system temp dir.
+pub fn default_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("hello-cef-downloads-{}", std::process::id()))
+}
+
+impl Downloads {
+    pub fn new(dir: PathBuf) -> Self {
+        Downloads { dir, entries: Vec::new(), dropped: 0 }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "dir": self.dir, "downloads": self.entries })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Download> {
+        self.entries.iter().find(|d| d.id == id)
+    }
+
+    /// Removes one download and its file; an unfinished one is left alone.
+    pub fn remove(&mut self, id: &str) -> Result<(), (u16, String)> {
+        let index = self.entries.iter().position(|d| d.id == id).ok_or((404, format!("no download {}", id)))?;
+        if self.entries[index].state == State::InProgress {
+            return Err((409, format!("download {} is still in progress", id)));
+        }
+        let download = self.entries.remove(index);
+        let _ = std::fs::remove_file(&download.path);
+        Ok(())
+    }
+
+    /// Removes every finished download; answers how many.
+    pub fn clear(&mut self) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|d| {
+            let finished = d.state != State::InProgress;
+            if finished {
+                let _ = std::fs::remove_file(&d.path);
+            }
+            !finished
+        });
+        before - self.entries.len()
+    }
+
+    pub fn buffer_stats(&self) -> BufferStats {
+        buffers::stats(Buffer::Downloads, &self.entries, self.dropped)
+    }
+
+    /// Adds `download`, first evicting the oldest finished downloads and
+    /// their files while the buffer is full.
+    fn push(&mut self, download: Download) {
+        while self.entries.len() >= Buffer::Downloads.capacity() {
+            let Some(index) = self.entries.iter().position(|d| d.state != State::InProgress) else { break };
+            let evicted = self.entries.remove(index);
+            let _ = std::fs::remove_file(&evicted.path);
+            self.dropped += 1;
+        }
+        self.entries.push(download);
+    }
+
+    fn begin(&mut self, event: &EventDownloadWillBegin) {
+        log_info!("Download started: {} ({})", event.url, event.suggested_filename);
+        self.push(Download {
+            id: event.guid.clone(),
+            url: event.url.clone(),
+            suggested_filename: event.suggested_filename.clone(),
+            state: State::InProgress,
+            received_bytes: 0,
+            total_bytes: 0,
+            path: self.dir.join(&event.guid),
+            started_at: crate::now_millis(),
+        });
+    }
+
+    fn progress(&mut self, event: &EventDownloadProgress) {
+        let Some(download) = self.entries.iter_mut().find(|d| d.id == event.guid) else { return };
+        download.received_bytes = event.received_bytes.max(0.0) as u64;
+        download.total_bytes = event.total_bytes.max(0.0) as u64;
+        download.state = match event.state {
+            DownloadProgressState::InProgress => State::InProgress,
+            DownloadProgressState::Completed => State::Completed,
+            DownloadProgressState::Canceled => State::Canceled,
+        };
+        if download.state == State::Completed {
+            log_info!("Download finished: {} ({} bytes)", download.suggested_filename, download.received_bytes);
+        }
+    }
+}
+
+/// Sends downloads to the download directory and tracks their progress.
+pub async fn install(browser: &Browser, downloads: SharedDownloads) -> Result<tokio::task::JoinHandle<()>, Box<dyn std::error::Error>> {
+    let dir = downloads.lock().unwrap().dir().to_path_buf();
+    std::fs::create_dir_all(&dir).map_err(|e| format!("failed to create download dir {}: {}", dir.display(), e))?;
+    let mut begins = browser.event_listener::<EventDownloadWillBegin>().await?;
+    let mut progress = browser.event_listener::<EventDownloadProgress>().await?;
+    let params = SetDownloadBehaviorParams::builder()
+        .behavior(SetDownloadBehaviorBehavior::AllowAndName)
+        .download_path(dir.to_string_lossy())
+        .events_enabled(true)
+        .build()?;
+    browser.execute(params).await?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                Some(event) = begins.next() => downloads.lock().unwrap().begin(&event),
+                Some(event) = progress.next() => downloads.lock().unwrap().progress(&event),
+                else => break,
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(dir: &Path, id: usize, state: State) -> Download {
+        Download {
+            id: id.to_string(),
+            url: format!("http://127.0.0.1/file-{}", id),
+            suggested_filename: format!("file-{}.bin", id),
+            state,
+            received_bytes: 0,
+            total_bytes: 0,
+            path: dir.join(id.to_string()),
+            started_at: id as u128,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rb-downloads-test-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn full_list_evicts_oldest_finished_with_its_file() {
+        let dir = temp_dir("evict");
+        let cap = Buffer::Downloads.capacity();
+        let mut downloads = Downloads::new(dir.clone());
+        // The oldest is still running, so the second one goes first
+        downloads.push(download(&dir, 0, State::InProgress));
+        for id in 1..cap {
+            std::fs::write(dir.join(id.to_string()), b"data").unwrap();
+            downloads.push(download(&dir, id, State::Completed));
+        }
+        downloads.push(download(&dir, cap, State::Completed));
+
+        assert_eq!(downloads.entries.len(), cap);
+        assert!(downloads.get("0").is_some());
+        assert!(downloads.get("1").is_none());
+        assert!(!dir.join("1").exists());
+        assert!(dir.join("2").exists());
+        let stats = downloads.buffer_stats();
+        assert_eq!((stats.name, stats.len, stats.capacity, stats.dropped), ("downloads", cap, cap, 1));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unfinished_downloads_are_never_evicted() {
+        let dir = temp_dir("running");
+        let cap = Buffer::Downloads.capacity();
+        let mut downloads = Downloads::new(dir.clone());
+        for id in 0..=cap {
+            downloads.push(download(&dir, id, State::InProgress));
+        }
+        assert_eq!(downloads.entries.len(), cap + 1);
+        assert_eq!(downloads.buffer_stats().dropped, 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+use crate::commands::{self, CommandError, CommandResult, CommandSender, PageCommand};
+
+// ============== Element Geometry ==============
+//
+// GET /element?selector= turns a selector into what click automation needs:
+// the element's box in top-viewport CSS pixels (the coordinates /click and
+// /hover take), the same box in page coordinates with the scroll offsets
+// added, its centre, whether it is visible and in the viewport, and its tag
+// and the start of its text. Selectors use the shared find, so `>>>`
+// pierces shadow roots. ?all=true lists every match; there the last `>>>`
+// part is matched inside the shadow root of what the parts before it find.
+// Each match carries a `path` that selects it and nothing else: a selector
+// per document or shadow root, outermost first, joined with `>>>`, so a
+// follow-up /click can target it. An invalid selector is a 400, no match a
+// 404.
+
+/// Longest text snippet returned per element, in characters.
+const TEXT_SNIPPET: usize = 80;
+
+const ELEMENT_SCRIPT: &str = r#"
+function(find, selector, all, snippet) {
+    // Selector unique within el's own document or shadow root: an id that
+    // is unique there, else the shortest unique nth-of-type chain.
+    const localPath = function(el) {
+        const root = el.getRootNode();
+        const isUnique = function(sel) {
+            try { return root.querySelectorAll(sel).length === 1; } catch (e) { return false; }
+        };
+        const parts = [];
+        for (let node = el; node; node = node.parentElement) {
+            const id = node.id && '#' + CSS.escape(node.id);
+            if (id && isUnique(id)) {
+                parts.unshift(id);
+                break;
+            }
+            let index = 1;
+            for (let sib = node.previousElementSibling; sib; sib = sib.previousElementSibling) {
+                if (sib.localName === node.localName) index++;
+            }
+            parts.unshift(node.localName + ':nth-of-type(' + index + ')');
+            if (isUnique(parts.join(' > '))) break;
+        }
+        return parts.join(' > ');
+    };
+    const pathOf = function(el) {
+        const parts = [];
+        for (let node = el; node; ) {
+            parts.unshift(localPath(node));
+            const root = node.getRootNode();
+            node = root instanceof ShadowRoot ? root.host : null;
+        }
+        return parts.join(' >>> ');
+    };
+    const describe = function(el) {
+        const r = el.getBoundingClientRect();
+        const style = getComputedStyle(el);
+        const visible = r.width > 0 && r.height > 0
+            && style.visibility !== 'hidden' && style.display !== 'none' && parseFloat(style.opacity) > 0;
+        const inViewport = r.right > 0 && r.bottom > 0 && r.left < innerWidth && r.top < innerHeight;
+        const text = (el.innerText || el.textContent || '').replace(/\s+/g, ' ').trim();
+        return {
+            tag: el.localName,
+            path: pathOf(el),
+            text: text.length > snippet ? text.slice(0, snippet) + '…' : text,
+            x: r.left, y: r.top, width: r.width, height: r.height,
+            page_x: r.left + scrollX, page_y: r.top + scrollY,
+            center: { x: r.left + r.width / 2, y: r.top + r.height / 2 },
+            visible: visible,
+            in_viewport: inViewport,
+        };
+    };
+    let matches;
+    try {
+        if (!all) {
+            const el = find(selector, false);
+            matches = el ? [el] : [];
+        } else {
+            const split = selector.lastIndexOf('>>>');
+            if (split < 0) {
+                matches = Array.from(document.querySelectorAll(selector));
+            } else {
+                const before = selector.slice(0, split).trim();
+                const host = before ? find(before, false) : document;
+                const root = host && (host.shadowRoot || host);
+                matches = root ? Array.from(root.querySelectorAll(selector.slice(split + 3).trim())) : [];
+            }
+        }
+    } catch (e) {
+        return { error: e.name === 'SyntaxError' ? 'invalid selector ' + selector : e.message, invalid: e.name === 'SyntaxError' };
+    }
+    return { matches: matches.map(describe) };
+}
+"#;
+
+pub fn query(tx: &CommandSender, selector: &str, all: bool) -> CommandResult {
+    let args = [selector.into(), all.into(), TEXT_SNIPPET.into()];
+    let expression = commands::js_call_with_find(ELEMENT_SCRIPT, &args);
+    let mut result = commands::send(tx, PageCommand::Evaluate { expression, await_promise: false })?;
+    if let Some(error) = result["error"].as_str() {
+        let status = if result["invalid"].as_bool() == Some(true) { 400 } else { 422 };
+        return Err(CommandError::new(status, error));
+    }
+    let matches = result["matches"].take();
+    match matches.as_array().map(Vec::len) {
+        Some(0) | None => Err(CommandError::new(404, format!("no element matches {}", selector))),
+        _ if all => Ok(matches),
+        _ => Ok(matches[0].clone()),
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use tiny_http::{Header, Response};
+
+// ============== Embeddable Viewer ==============
+//
+// /embed is the viewer stripped down to the frame itself, meant for an
+// <iframe> on a dashboard. Which sites may frame it is set with
+// --embed-allowed-origins and sent as a CSP frame-ancestors directive.
+
+const DEFAULT_FPS: f64 = 2.0;
+const MAX_FPS: f64 = 30.0;
+/// Widest frame `profile=thumb` asks for, in device pixels.
+const THUMB_MAX_WIDTH: u32 = 320;
+
+const EMBED_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <style>
+        html, body { margin: 0; height: 100%; overflow: hidden; background: {background}; }
+        img { display: block; width: 100%; height: 100%; object-fit: contain; }
+        #offline { position: absolute; inset: 0; display: none; align-items: center; justify-content: center; color: {muted}; font: 12px sans-serif; }
+    </style>
+</head>
+<body>
+    <img id="frame" alt="">
+    <div id="offline">No frame</div>
+    <script>
+        const config = {config};
+        const img = document.getElementById('frame');
+        const offline = document.getElementById('offline');
+
+        // Ask for what the iframe can actually show, in device pixels, so
+        // the frame stays sharp on high-DPI dashboards without oversizing.
+        function maxWidth() {
+            const wanted = Math.ceil(document.documentElement.clientWidth * (window.devicePixelRatio || 1));
+            return config.maxWidth ? Math.min(wanted, config.maxWidth) : wanted;
+        }
+
+        async function fetchFrame() {
+            const params = new URLSearchParams({ max_width: maxWidth(), client: config.client });
+            if (config.sig) params.set('sig', config.sig);
+            try {
+                const res = await fetch('/live-stream?' + params, { cache: 'no-store' });
+                const data = await res.json();
+                if (data.frame) {
+                    img.src = 'data:image/jpeg;base64,' + data.frame;
+                    offline.style.display = 'none';
+                } else {
+                    offline.style.display = 'flex';
+                }
+            } catch (e) {
+                offline.style.display = 'flex';
+            }
+            setTimeout(fetchFrame, 1000 / config.fps);
+        }
+
+        fetchFrame();
+    </script>
+</body>
+</html>"#;
+
+/// `--embed-allowed-origins`, validated up front.
+pub struct EmbedConfig {
+    frame_ancestors: String,
+}
+
+impl EmbedConfig {
+    /// Accepts `'self'`, `*` or http(s) origins; with none, only same-origin framing is allowed.
+    pub fn new(origins: &[String]) -> Result<Self, String> {
+        let mut sources = Vec::new();
+        for origin in origins.iter().map(|o| o.trim()).filter(|o| !o.is_empty()) {
+            if origin == "'self'" || origin == "self" {
+                sources.push("'self'".to_string());
+            } else if origin == "*" {
+                sources.push("*".to_string());
+            } else {
+                let url = url::Url::parse(origin).map_err(|e| format!("invalid embed origin '{}': {}", origin, e))?;
+                if url.scheme() != "http" && url.scheme() != "https" {
+                    return Err(format!("embed origin must be http(s), got '{}'", origin));
+                }
+                sources.push(url.origin().ascii_serialization());
+            }
+        }
+        if sources.is_empty() {
+            sources.push("'self'".to_string());
+        }
+        Ok(EmbedConfig { frame_ancestors: format!("frame-ancestors {}", sources.join(" ")) })
+    }
+
+    /// Serves `/embed?fps=2&profile=thumb&theme=dark[&sig=...]`.
+    pub fn response(&self, query: &HashMap<String, String>) -> Response<Cursor<Vec<u8>>> {
+        let fps = match query.get("fps").map(|f| f.parse::<f64>()) {
+            None => DEFAULT_FPS,
+            Some(Ok(fps)) if fps > 0.0 => fps.min(MAX_FPS),
+            Some(_) => return crate::json_response(400, &serde_json::json!({"error": "fps must be a positive number"})),
+        };
+        let max_width = match query.get("profile").map(String::as_str) {
+            None | Some("full") => None,
+            Some("thumb") => Some(THUMB_MAX_WIDTH),
+            Some(other) => {
+                return crate::json_response(400, &serde_json::json!({
+                    "error": format!("unknown profile '{}', expected thumb or full", other),
+                }))
+            }
+        };
+        let (background, muted) = match query.get("theme").map(String::as_str) {
+            None | Some("dark") => ("#000", "#666"),
+            Some("light") => ("#fff", "#999"),
+            Some(other) => {
+                return crate::json_response(400, &serde_json::json!({
+                    "error": format!("unknown theme '{}', expected dark or light", other),
+                }))
+            }
+        };
+
+        let config = serde_json::json!({
+            "fps": fps,
+            "maxWidth": max_width,
+            "sig": query.get("sig"),
+            // One scaler consumer per embed instance rather than per dashboard IP
+            "client": format!("embed-{}", crate::now_millis()),
+        });
+        let html = EMBED_HTML
+            .replace("{background}", background)
+            .replace("{muted}", muted)
+            // `</` can't appear inside the inline script
+            .replace("{config}", &config.to_string().replace("</", "<\\/"));
+
+        Response::from_string(html)
+            .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap())
+            .with_header(Header::from_bytes(&b"Content-Security-Policy"[..], self.frame_ancestors.as_bytes()).unwrap())
+            .with_header(Header::from_bytes(&b"Cache-Control"[..], &b"no-cache"[..]).unwrap())
+    }
+}
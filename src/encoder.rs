@@ -0,0 +1,96 @@
+use image::RgbImage;
+
+// ============== Frame Encoder ==============
+//
+// GUI captures arrive as full-size RGBA. The encoder converts them to RGB in
+// a single pass split across threads, into a buffer reused between frames,
+// and encodes with libjpeg-turbo when built with the `turbojpeg` feature.
+
+#[cfg(feature = "turbojpeg")]
+pub const NAME: &str = "turbojpeg";
+#[cfg(not(feature = "turbojpeg"))]
+pub const NAME: &str = "image";
+
+/// Below this many pixels, splitting the conversion costs more than it saves.
+const PARALLEL_MIN_PIXELS: usize = 512 * 512;
+const MAX_THREADS: usize = 4;
+
+pub struct FrameEncoder {
+    quality: u8,
+    rgb: Vec<u8>,
+    jpeg_capacity: usize,
+}
+
+impl FrameEncoder {
+    pub fn new(quality: u8) -> Self {
+        FrameEncoder { quality, rgb: Vec::new(), jpeg_capacity: 0 }
+    }
+
+    /// Converts `rgba` into the reused RGB buffer and hands it to `draw`
+    /// before encoding, so overlays never need another full-size copy.
+    pub fn encode_rgba(&mut self, rgba: &[u8], width: u32, height: u32, draw: impl FnOnce(&mut RgbImage)) -> Option<Vec<u8>> {
+        let pixels = width as usize * height as usize;
+        if rgba.len() < pixels * 4 {
+            return None;
+        }
+        let mut rgb = std::mem::take(&mut self.rgb);
+        rgb.resize(pixels * 3, 0);
+        rgba_to_rgb(&rgba[..pixels * 4], &mut rgb);
+
+        let mut img = RgbImage::from_raw(width, height, rgb)?;
+        draw(&mut img);
+        let jpeg = self.encode(&img);
+        self.rgb = img.into_raw();
+        jpeg
+    }
+
+    fn encode(&mut self, img: &RgbImage) -> Option<Vec<u8>> {
+        let jpeg = encode_rgb(img, self.quality, self.jpeg_capacity)?;
+        self.jpeg_capacity = self.jpeg_capacity.max(jpeg.len());
+        Some(jpeg)
+    }
+}
+
+fn convert_rows(src: &[u8], dst: &mut [u8]) {
+    for (s, d) in src.chunks_exact(4).zip(dst.chunks_exact_mut(3)) {
+        d.copy_from_slice(&s[..3]);
+    }
+}
+
+fn rgba_to_rgb(src: &[u8], dst: &mut [u8]) {
+    let pixels = dst.len() / 3;
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_THREADS);
+    if pixels < PARALLEL_MIN_PIXELS || threads < 2 {
+        convert_rows(src, dst);
+        return;
+    }
+    let chunk = pixels.div_ceil(threads);
+    std::thread::scope(|scope| {
+        for (s, d) in src.chunks(chunk * 4).zip(dst.chunks_mut(chunk * 3)) {
+            scope.spawn(move || convert_rows(s, d));
+        }
+    });
+}
+
+#[cfg(feature = "turbojpeg")]
+fn encode_rgb(img: &RgbImage, quality: u8, _capacity: usize) -> Option<Vec<u8>> {
+    let image = turbojpeg::Image {
+        pixels: img.as_raw().as_slice(),
+        width: img.width() as usize,
+        pitch: img.width() as usize * 3,
+        height: img.height() as usize,
+        format: turbojpeg::PixelFormat::RGB,
+    };
+    turbojpeg::compress(image, quality as i32, turbojpeg::Subsamp::Sub2x2)
+        .ok()
+        .map(|buf| buf.to_vec())
+}
+
+#[cfg(not(feature = "turbojpeg"))]
+fn encode_rgb(img: &RgbImage, quality: u8, capacity: usize) -> Option<Vec<u8>> {
+    let mut jpeg = Vec::with_capacity(capacity);
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, quality)
+        .encode_image(img)
+        .ok()?;
+    Some(jpeg)
+}
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::js_protocol::runtime::{ConsoleApiCalledType, EventConsoleApiCalled, EventExceptionThrown};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::annotations::Annotation;
+use crate::buffers::{self, Buffer, BufferStats};
+use crate::journal::{JournalAction, JournalEntry};
+
+// ============== Event Track ==============
+//
+// While a recording is running (POST /record/start .. /record/stop), page
+// events are collected with offsets from the recording start so they can be
+// laid over the video as WebVTT subtitles: navigations, API interactions
+// (already redacted by the journal), console errors and annotation lifetimes.
+// A recording holds at most `--buffer-size track=N` events; later ones are
+// counted in `dropped` instead, since open annotation spans refer to events
+// by position.
+
+/// On-screen time for events that have no natural end.
+const POINT_EVENT_MS: u64 = 2000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackEvent {
+    pub start_ms: u64,
+    /// Set for spans (annotations); point events show for POINT_EVENT_MS.
+    pub end_ms: Option<u64>,
+    pub kind: &'static str,
+    pub text: String,
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventTrack {
+    pub started_at: u128,
+    pub stopped_at: Option<u128>,
+    pub events: Vec<TrackEvent>,
+    pub dropped: u64,
+    /// Annotation id → index of its still-open event, None if it was dropped.
+    #[serde(skip)]
+    open_annotations: HashMap<u64, Option<usize>>,
+}
+
+fn vtt_time(ms: u64) -> String {
+    format!("{:02}:{:02}:{:02}.{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+impl EventTrack {
+    fn new() -> Self {
+        EventTrack { started_at: crate::now_millis(), stopped_at: None, events: Vec::new(), dropped: 0, open_annotations: HashMap::new() }
+    }
+
+    fn offset(&self) -> u64 {
+        crate::now_millis().saturating_sub(self.started_at) as u64
+    }
+
+    /// False when the track is full.
+    fn has_room(&mut self) -> bool {
+        let room = self.events.len() < Buffer::Track.capacity();
+        if !room {
+            self.dropped += 1;
+        }
+        room
+    }
+
+    fn push(&mut self, kind: &'static str, text: String, data: serde_json::Value) {
+        if !self.has_room() {
+            return;
+        }
+        let start_ms = self.offset();
+        self.events.push(TrackEvent { start_ms, end_ms: None, kind, text, data });
+    }
+
+    /// Opens a span for each annotation seen for the first time and closes
+    /// the spans of annotations that have expired or been removed.
+    fn observe_annotations(&mut self, annotations: &[Annotation]) {
+        let now = self.offset();
+        let present: Vec<u64> = annotations.iter().map(|a| a.id).collect();
+        let events = &mut self.events;
+        self.open_annotations.retain(|id, index| {
+            let open = present.contains(id);
+            if let (false, Some(index)) = (open, index) {
+                events[*index].end_ms = Some(now);
+            }
+            open
+        });
+        for annotation in annotations {
+            if !self.open_annotations.contains_key(&annotation.id) {
+                if !self.has_room() {
+                    self.open_annotations.insert(annotation.id, None);
+                    continue;
+                }
+                let text = annotation.label.clone().unwrap_or_else(|| format!("annotation {}", annotation.id));
+                self.open_annotations.insert(annotation.id, Some(self.events.len()));
+                self.events.push(TrackEvent {
+                    start_ms: now,
+                    end_ms: None,
+                    kind: "annotation",
+                    text,
+                    data: serde_json::json!(annotation),
+                });
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        let now = self.offset();
+        for index in self.open_annotations.drain().filter_map(|(_, index)| index) {
+            self.events[index].end_ms = Some(now);
+        }
+        self.stopped_at = Some(crate::now_millis());
+    }
+
+    pub fn to_vtt(&self) -> String {
+        let mut out = String::from("WEBVTT\n");
+        for (i, event) in self.events.iter().enumerate() {
+            let end = event.end_ms.unwrap_or(event.start_ms + POINT_EVENT_MS).max(event.start_ms + 1);
+            // Cue text must not contain blank lines or "-->"
+            let text = event.text.replace("-->", "->").replace('\n', " ");
+            out.push_str(&format!(
+                "\n{}\n{} --> {}\n[{}] {}\n",
+                i + 1,
+                vtt_time(event.start_ms),
+                vtt_time(end),
+                event.kind,
+                text
+            ));
+        }
+        out
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Recorder {
+    active: Option<EventTrack>,
+    last: Option<EventTrack>,
+}
+
+pub type SharedRecorder = Arc<Mutex<Recorder>>;
+
+impl Recorder {
+    pub fn start(&mut self) -> Result<u128, String> {
+        if self.active.is_some() {
+            return Err("a recording is already running".to_string());
+        }
+        let track = EventTrack::new();
+        let started_at = track.started_at;
+        self.active = Some(track);
+        Ok(started_at)
+    }
+
+    pub fn stop(&mut self) -> Option<&EventTrack> {
+        let mut track = self.active.take()?;
+        track.finish();
+        self.last = Some(track);
+        self.last.as_ref()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// No-op unless a recording is running.
+    pub fn record(&mut self, kind: &'static str, text: String, data: serde_json::Value) {
+        if let Some(track) = &mut self.active {
+            track.push(kind, text, data);
+        }
+    }
+
+    /// Adds a journal entry (values already redacted) as a navigation or action cue.
+    pub fn record_entry(&mut self, entry: &JournalEntry) {
+        let (kind, text) = match &entry.action {
+            JournalAction::Navigate { url } => ("navigation", url.clone()),
+            JournalAction::Click { selector, .. } => ("action", format!("click {}", selector)),
+            JournalAction::Type { selector, value } => ("action", format!("type {} = {:?}", selector, value)),
+            JournalAction::Change { selector, value } => ("action", format!("change {} = {:?}", selector, value)),
+            JournalAction::Key { selector, key, .. } => ("action", format!("key {} on {}", key, selector)),
+            JournalAction::Scroll { x, y } => ("action", format!("scroll to {}, {}", x, y)),
+        };
+        self.record(kind, text, serde_json::json!(entry));
+    }
+
+    pub fn observe_annotations(&mut self, annotations: &[Annotation]) {
+        if let Some(track) = &mut self.active {
+            track.observe_annotations(annotations);
+        }
+    }
+
+    /// Events of the latest recording.
+    pub fn buffer_stats(&self) -> BufferStats {
+        let track = self.latest();
+        buffers::stats(Buffer::Track, track.into_iter().flat_map(|t| &t.events), track.map_or(0, |t| t.dropped))
+    }
+
+    /// The running recording, else the last finished one.
+    pub fn latest(&self) -> Option<&EventTrack> {
+        self.active.as_ref().or(self.last.as_ref())
+    }
+}
+
+/// Feeds uncaught exceptions and console.error calls into running recordings.
+pub async fn watch_console(page: &Page, recorder: SharedRecorder) -> Result<tokio::task::JoinHandle<()>, CdpError> {
+    let mut exceptions = page.event_listener::<EventExceptionThrown>().await?;
+    let mut console = page.event_listener::<EventConsoleApiCalled>().await?;
+    Ok(tokio::spawn(async move {
+        loop {
+            let text = tokio::select! {
+                Some(event) = exceptions.next() => {
+                    let details = &event.exception_details;
+                    details
+                        .exception
+                        .as_ref()
+                        .and_then(|e| e.description.clone())
+                        .unwrap_or_else(|| details.text.clone())
+                }
+                Some(event) = console.next() => {
+                    if event.r#type != ConsoleApiCalledType::Error {
+                        continue;
+                    }
+                    event
+                        .args
+                        .iter()
+                        .map(|arg| match (&arg.value, &arg.description) {
+                            (Some(serde_json::Value::String(s)), _) => s.clone(),
+                            (Some(value), _) => value.to_string(),
+                            (None, Some(description)) => description.clone(),
+                            (None, None) => String::new(),
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                }
+                else => break,
+            };
+            recorder.lock().unwrap().record("console_error", text.clone(), serde_json::json!({ "message": text }));
+        }
+    }))
+}
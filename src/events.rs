@@ -0,0 +1,104 @@
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::captures::SharedNavigation;
+use crate::pipeline::SharedPipelineStats;
+use crate::FrameNotifier;
+
+// ============== Event Stream ==============
+//
+// GET /events is a text/event-stream for dashboards that would rather not
+// speak WebSocket: `navigation` whenever a new URL is loaded (command line,
+// /navigate, a page command or a handoff import), `navigation_failed` when
+// loading one fails, and at most once per FRAME_EVENT_INTERVAL a `frame`
+// event with the newest frame's sequence number and capture time. Events
+// carry an `id:`, the stream opens with a `retry:` hint, and a comment goes
+// out after HEARTBEAT without events so proxies keep the connection open.
+
+const HEARTBEAT: Duration = Duration::from_secs(15);
+/// Reconnect delay suggested to clients.
+const RETRY_MS: u64 = 3000;
+/// How often the watcher looks for new navigations and frames.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+const FRAME_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Sender<String>>,
+    last_id: u64,
+}
+
+pub type SharedEvents = Arc<Mutex<EventBus>>;
+
+impl EventBus {
+    pub fn publish(&mut self, event: &str, data: serde_json::Value) {
+        self.last_id += 1;
+        let message = format!("id: {}\nevent: {}\ndata: {}\n\n", self.last_id, event, data);
+        self.subscribers.retain(|tx| tx.send(message.clone()).is_ok());
+    }
+}
+
+/// Publishes `navigation` and `frame` events as the page state moves.
+pub fn spawn_watcher(events: SharedEvents, navigation: SharedNavigation, frames: FrameNotifier, pipeline: SharedPipelineStats) {
+    thread::spawn(move || {
+        let mut seen_navigation = navigation.lock().unwrap().seq;
+        let mut seen_frame = *frames.borrow();
+        let mut since_frame_event = Duration::ZERO;
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            since_frame_event += WATCH_INTERVAL;
+            let current = navigation.lock().unwrap().clone();
+            if current.seq != seen_navigation {
+                seen_navigation = current.seq;
+                events.lock().unwrap().publish("navigation", serde_json::json!(current));
+            }
+            let frame = *frames.borrow();
+            if frame != seen_frame && since_frame_event >= FRAME_EVENT_INTERVAL {
+                seen_frame = frame;
+                since_frame_event = Duration::ZERO;
+                let age = pipeline.lock().unwrap().last_frame_age().unwrap_or_default();
+                let captured_at = crate::now_millis().saturating_sub(age.as_millis());
+                events.lock().unwrap().publish("frame", serde_json::json!({"seq": frame, "timestamp": captured_at}));
+            }
+        }
+    });
+}
+
+/// Blocking body of the stream, with a heartbeat comment while idle.
+struct EventReader {
+    rx: Receiver<String>,
+    pending: std::io::Cursor<Vec<u8>>,
+}
+
+impl Read for EventReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pending.position() as usize >= self.pending.get_ref().len() {
+            let message = match self.rx.recv_timeout(HEARTBEAT) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => ": heartbeat\n\n".to_string(),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+            self.pending = std::io::Cursor::new(message.into_bytes());
+        }
+        self.pending.read(buf)
+    }
+}
+
+/// GET /events. Blocks until the client goes away, so call it from its own
+/// thread.
+pub fn serve(request: tiny_http::Request, events: &SharedEvents) {
+    let (tx, rx) = mpsc::channel();
+    // Sent first, which also flushes the headers to the client right away
+    let _ = tx.send(format!("retry: {}\n: connected\n\n", RETRY_MS));
+    events.lock().unwrap().subscribers.push(tx);
+    let headers = vec![
+        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/event-stream"[..]).unwrap(),
+        tiny_http::Header::from_bytes(&b"Cache-Control"[..], &b"no-cache"[..]).unwrap(),
+        tiny_http::Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap(),
+    ];
+    let reader = EventReader { rx, pending: std::io::Cursor::new(Vec::new()) };
+    let _ = crate::request_log::respond(request, tiny_http::Response::new(tiny_http::StatusCode(200), headers, reader, None, None));
+}
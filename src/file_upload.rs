@@ -0,0 +1,223 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chromiumoxide::cdp::browser_protocol::dom::SetFileInputFilesParams;
+use chromiumoxide::cdp::js_protocol::runtime::EvaluateParams;
+use chromiumoxide::Page;
+
+use crate::commands::{self, CommandError, CommandResult, CommandSender, PageCommand};
+
+// ============== File Inputs ==============
+//
+// POST /upload fills an <input type=file> of the streamed page, so upload
+// forms can be driven through the control API. The body is
+// multipart/form-data with a `selector` field and one or more file parts;
+// the files are written to a fresh temp directory and handed to the input
+// with DOM.setFileInputFiles, which fires input and change like a user's
+// pick would. An input without `multiple` takes one file, more is a 400.
+// Chrome reads the files only when the form is sent, so they stay on disk
+// until the page has navigated away from the URL they were attached on, or
+// for UPLOAD_TTL at most; the capture loop sweeps them.
+
+/// Largest multipart body accepted, all files together.
+pub const MAX_UPLOAD_BYTES: u64 = 64 * 1024 * 1024;
+const UPLOAD_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Checks the element and reports whether it takes several files.
+const FILE_INPUT_SCRIPT: &str = r#"
+function(find, selector) {
+    const el = find(selector, true);
+    if (el.localName !== 'input' || el.type !== 'file') throw new Error('element is not an <input type=file>');
+    if (el.disabled) throw new Error('file input is disabled');
+    return { multiple: el.multiple };
+}
+"#;
+
+static BATCH_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug)]
+pub struct FilePart {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Files attached by one POST /upload.
+struct Batch {
+    dir: PathBuf,
+    /// Page URL the files were attached on, without the fragment.
+    url: String,
+    created: Instant,
+}
+
+#[derive(Default)]
+pub struct Uploads {
+    batches: Vec<Batch>,
+}
+
+pub type SharedUploads = Arc<Mutex<Uploads>>;
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack.get(from..)?.windows(needle.len()).position(|w| w == needle).map(|i| i + from)
+}
+
+/// `key="value"` or `key=value` from a header's parameters.
+fn header_param<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    header.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim().eq_ignore_ascii_case(key).then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Only the last path component, so a part can't write outside its directory.
+fn safe_file_name(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default().trim();
+    (!name.is_empty() && name != "." && name != "..").then(|| name.to_string())
+}
+
+/// The `selector` field and the file parts of a multipart/form-data body.
+pub fn read_form(request: &mut tiny_http::Request) -> Result<(String, Vec<FilePart>), String> {
+    use std::io::Read;
+
+    let content_type = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Content-Type"))
+        .map(|h| h.value.as_str().to_string())
+        .unwrap_or_default();
+    if !content_type.trim_start().to_ascii_lowercase().starts_with("multipart/form-data") {
+        return Err("expected a multipart/form-data body".to_string());
+    }
+    let boundary = header_param(&content_type, "boundary").filter(|b| !b.is_empty()).ok_or("multipart body without a boundary")?;
+    let delimiter = format!("--{}", boundary).into_bytes();
+    if request.body_length().is_some_and(|len| len as u64 > MAX_UPLOAD_BYTES) {
+        return Err(format!("upload exceeds {} bytes", MAX_UPLOAD_BYTES));
+    }
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_UPLOAD_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| format!("failed to read body: {}", e))?;
+    if body.len() as u64 > MAX_UPLOAD_BYTES {
+        return Err(format!("upload exceeds {} bytes", MAX_UPLOAD_BYTES));
+    }
+
+    let mut selector = None;
+    let mut files = Vec::new();
+    let mut pos = find(&body, &delimiter, 0).ok_or("multipart body has no parts")? + delimiter.len();
+    // Each part: CRLF, headers, blank line, data, CRLF, delimiter; "--" ends the body
+    while !body[pos..].starts_with(b"--") {
+        let headers_end = find(&body, b"\r\n\r\n", pos).ok_or("malformed multipart part")?;
+        let headers = String::from_utf8_lossy(&body[pos..headers_end]).into_owned();
+        let data_start = headers_end + 4;
+        let mut closing = b"\r\n".to_vec();
+        closing.extend_from_slice(&delimiter);
+        let data_end = find(&body, &closing, data_start).ok_or("multipart body is not terminated")?;
+        let disposition = headers
+            .lines()
+            .find(|line| line.to_ascii_lowercase().starts_with("content-disposition:"))
+            .ok_or("multipart part without Content-Disposition")?;
+        let data = &body[data_start..data_end];
+        match header_param(disposition, "filename") {
+            Some(filename) => {
+                let name = safe_file_name(filename).ok_or_else(|| format!("invalid file name '{}'", filename))?;
+                files.push(FilePart { name, data: data.to_vec() });
+            }
+            None if header_param(disposition, "name") == Some("selector") => {
+                selector = Some(String::from_utf8_lossy(data).trim().to_string());
+            }
+            None => {}
+        }
+        pos = data_end + closing.len();
+    }
+    let selector = selector.filter(|s| !s.is_empty()).ok_or("missing selector field")?;
+    if files.is_empty() {
+        return Err("no file parts in the upload".to_string());
+    }
+    Ok((selector, files))
+}
+
+fn without_fragment(url: &str) -> String {
+    url.split('#').next().unwrap_or_default().to_string()
+}
+
+/// Writes `files` to a new temp directory and attaches them to `selector`.
+/// Answers the names and sizes attached.
+pub fn attach(tx: &CommandSender, uploads: &SharedUploads, selector: String, files: Vec<FilePart>) -> CommandResult {
+    let dir = std::env::temp_dir()
+        .join("hello-cef-uploads")
+        .join(format!("{}-{}", std::process::id(), BATCH_SEQ.fetch_add(1, Ordering::Relaxed)));
+    let write = || -> std::io::Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(&dir)?;
+        let mut paths: Vec<PathBuf> = Vec::new();
+        for file in &files {
+            // Two parts may share a name; keep both
+            let mut path = dir.join(&file.name);
+            if paths.contains(&path) {
+                path = dir.join(format!("{}-{}", paths.len(), file.name));
+            }
+            std::fs::write(&path, &file.data)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    };
+    let paths = match write() {
+        Ok(paths) => paths,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&dir);
+            return Err(CommandError::new(500, format!("failed to store upload: {}", e)));
+        }
+    };
+    match commands::send(tx, PageCommand::SetFiles { selector: selector.clone(), paths }) {
+        Ok(result) => {
+            let url = without_fragment(result["url"].as_str().unwrap_or_default());
+            uploads.lock().unwrap().batches.push(Batch { dir, url, created: Instant::now() });
+            let attached: Vec<serde_json::Value> =
+                files.iter().map(|f| serde_json::json!({"name": f.name, "size": f.data.len()})).collect();
+            Ok(serde_json::json!({ "selector": selector, "files": attached }))
+        }
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&dir);
+            Err(e)
+        }
+    }
+}
+
+/// Hands `paths` to the file input `selector`; answers the page `{url}`.
+pub async fn set_files(page: &Page, selector: &str, paths: Vec<PathBuf>) -> CommandResult {
+    let expression = commands::js_call_with_find(FILE_INPUT_SCRIPT, &[selector.into()]);
+    let input = commands::evaluate(page, expression, false).await?;
+    if paths.len() > 1 && input["multiple"].as_bool() != Some(true) {
+        return Err(CommandError::new(400, format!("{} takes a single file, got {}", selector, paths.len())));
+    }
+    let params = EvaluateParams::builder()
+        .expression(commands::js_call_with_find("function(find, selector) { return find(selector, true); }", &[selector.into()]))
+        .build()
+        .map_err(|e| CommandError::new(400, e))?;
+    let element = page.evaluate_expression(params).await?;
+    let object_id = element.object().object_id.clone().ok_or_else(|| CommandError::new(404, format!("no element matches {}", selector)))?;
+    let files = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect::<Vec<_>>();
+    let params = SetFileInputFilesParams::builder().files(files).object_id(object_id).build().map_err(|e| CommandError::new(500, e))?;
+    page.execute(params).await?;
+    Ok(serde_json::json!({ "url": page.url().await?.unwrap_or_default() }))
+}
+
+/// Removes the files of pages navigated away from and of expired uploads.
+pub async fn sweep(page: &Page, uploads: &SharedUploads) {
+    if uploads.lock().unwrap().batches.is_empty() {
+        return;
+    }
+    let url = match page.url().await {
+        Ok(url) => without_fragment(&url.unwrap_or_default()),
+        Err(_) => return,
+    };
+    uploads.lock().unwrap().batches.retain(|batch| {
+        let keep = batch.url == url && batch.created.elapsed() < UPLOAD_TTL;
+        if !keep {
+            let _ = std::fs::remove_dir_all(&batch.dir);
+        }
+        keep
+    });
+}
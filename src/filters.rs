@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use image::{imageops, RgbImage, RgbaImage};
+use serde::Deserialize;
+
+use crate::pipeline::SharedPipelineStats;
+
+// ============== Frame Filters ==============
+//
+// --frame-filters FILE defines named pipelines of post-processing steps for
+// consumers that want something other than the plain frame, e.g.
+//
+//   {"external": [{"type": "watermark", "image": "logo.png", "opacity": 0.3}],
+//    "eink": [{"type": "crop", "x": 0, "y": 80, "w": 1280, "h": 640},
+//             {"type": "grayscale"}, {"type": "contrast", "amount": 40}]}
+//
+// /live-stream?filters=NAME decodes the live frame, runs it through NAME in
+// order and re-encodes it. The result is kept per profile until the next
+// frame, so any number of clients on one profile cost a single run. Each
+// filter's time goes to /stats/pipeline as filter_<type>, the decode and
+// encode around them as filter_codec. Watermark paths are relative to FILE.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum FilterSpec {
+    Crop {
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+    },
+    /// A struct variant, so deny_unknown_fields covers it too.
+    Grayscale {},
+    /// Percent; negative values lower the contrast.
+    Contrast {
+        amount: f32,
+    },
+    Watermark {
+        image: PathBuf,
+        #[serde(default = "default_opacity")]
+        opacity: f32,
+        #[serde(default)]
+        position: Corner,
+        #[serde(default = "default_margin")]
+        margin: u32,
+    },
+}
+
+fn default_opacity() -> f32 {
+    0.5
+}
+
+fn default_margin() -> u32 {
+    16
+}
+
+pub enum Filter {
+    Crop { x: u32, y: u32, w: u32, h: u32 },
+    Grayscale,
+    Contrast(f32),
+    Watermark { image: RgbaImage, opacity: f32, position: Corner, margin: u32 },
+}
+
+impl Filter {
+    fn from_spec(spec: FilterSpec, base: &Path) -> Result<Self, String> {
+        Ok(match spec {
+            FilterSpec::Crop { x, y, w, h } => {
+                if w == 0 || h == 0 {
+                    return Err("crop needs a non-zero w and h".to_string());
+                }
+                Filter::Crop { x, y, w, h }
+            }
+            FilterSpec::Grayscale {} => Filter::Grayscale,
+            FilterSpec::Contrast { amount } => Filter::Contrast(amount),
+            FilterSpec::Watermark { image, opacity, position, margin } => {
+                let path = base.join(image);
+                let image = image::open(&path).map_err(|e| format!("Failed to read watermark {}: {}", path.display(), e))?;
+                Filter::Watermark { image: image.to_rgba8(), opacity: opacity.clamp(0.0, 1.0), position, margin }
+            }
+        })
+    }
+
+    fn stage(&self) -> &'static str {
+        match self {
+            Filter::Crop { .. } => "filter_crop",
+            Filter::Grayscale => "filter_grayscale",
+            Filter::Contrast(_) => "filter_contrast",
+            Filter::Watermark { .. } => "filter_watermark",
+        }
+    }
+
+    pub fn apply(&self, mut img: RgbImage) -> RgbImage {
+        match self {
+            Filter::Crop { x, y, w, h } => {
+                // Clamped to the frame, so a smaller viewport still gets a frame
+                let x = (*x).min(img.width().saturating_sub(1));
+                let y = (*y).min(img.height().saturating_sub(1));
+                let w = (*w).min(img.width() - x);
+                let h = (*h).min(img.height() - y);
+                imageops::crop_imm(&img, x, y, w, h).to_image()
+            }
+            Filter::Grayscale => {
+                for pixel in img.pixels_mut() {
+                    let [r, g, b] = pixel.0;
+                    let luma = ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8;
+                    pixel.0 = [luma, luma, luma];
+                }
+                img
+            }
+            Filter::Contrast(amount) => imageops::contrast(&img, *amount),
+            Filter::Watermark { image, opacity, position, margin } => {
+                let (x, y) = watermark_origin(&img, image, *position, *margin);
+                for (wx, wy, src) in image.enumerate_pixels() {
+                    let (dx, dy) = (x + wx as i64, y + wy as i64);
+                    if dx < 0 || dy < 0 || dx >= img.width() as i64 || dy >= img.height() as i64 {
+                        continue;
+                    }
+                    let alpha = src[3] as f32 / 255.0 * opacity;
+                    let dst = img.get_pixel_mut(dx as u32, dy as u32);
+                    for c in 0..3 {
+                        dst[c] = (dst[c] as f32 * (1.0 - alpha) + src[c] as f32 * alpha).round() as u8;
+                    }
+                }
+                img
+            }
+        }
+    }
+}
+
+fn watermark_origin(img: &RgbImage, mark: &RgbaImage, position: Corner, margin: u32) -> (i64, i64) {
+    let (w, h) = (img.width() as i64, img.height() as i64);
+    let (mw, mh, m) = (mark.width() as i64, mark.height() as i64, margin as i64);
+    match position {
+        Corner::TopLeft => (m, m),
+        Corner::TopRight => (w - mw - m, m),
+        Corner::BottomLeft => (m, h - mh - m),
+        Corner::BottomRight => (w - mw - m, h - mh - m),
+        Corner::Center => ((w - mw) / 2, (h - mh) / 2),
+    }
+}
+
+#[derive(Default)]
+pub struct FrameFilters {
+    pipelines: HashMap<String, Vec<Filter>>,
+    /// Profile → frame sequence number and the filtered frame.
+    cache: Mutex<HashMap<String, (u64, Vec<u8>)>>,
+}
+
+impl FrameFilters {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let specs: HashMap<String, Vec<FilterSpec>> =
+            serde_json::from_str(&text).map_err(|e| format!("Invalid frame filters in {}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        let mut pipelines = HashMap::new();
+        for (name, specs) in specs {
+            let filters = specs
+                .into_iter()
+                .map(|spec| Filter::from_spec(spec, base))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Frame filter profile '{}': {}", name, e))?;
+            pipelines.insert(name, filters);
+        }
+        Ok(FrameFilters { pipelines, cache: Mutex::default() })
+    }
+
+    pub fn has(&self, profile: &str) -> bool {
+        self.pipelines.contains_key(profile)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.pipelines.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// `jpeg` (frame number `seq`) through `profile`; `None` if the profile
+    /// is unknown or the frame could not be decoded or encoded.
+    pub fn apply(&self, profile: &str, seq: u64, jpeg: &[u8], stats: &SharedPipelineStats) -> Option<Vec<u8>> {
+        let filters = self.pipelines.get(profile)?;
+        if let Some((cached_seq, frame)) = self.cache.lock().unwrap().get(profile) {
+            if *cached_seq == seq {
+                return Some(frame.clone());
+            }
+        }
+
+        let started = Instant::now();
+        let mut img = image::load_from_memory(jpeg).ok()?.to_rgb8();
+        let mut codec = started.elapsed();
+        for filter in filters {
+            let started = Instant::now();
+            img = filter.apply(img);
+            stats.lock().unwrap().record(filter.stage(), started.elapsed());
+        }
+        let started = Instant::now();
+        let frame = crate::overlay::encode_jpeg(&img, crate::JPEG_QUALITY)?;
+        codec += started.elapsed();
+        stats.lock().unwrap().record("filter_codec", codec);
+
+        self.cache.lock().unwrap().insert(profile.to_string(), (seq, frame.clone()));
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, Rgba};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rb-filters-test-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn load(dir: &Path, json: &str) -> Result<FrameFilters, String> {
+        let path = dir.join("filters.json");
+        std::fs::write(&path, json).unwrap();
+        FrameFilters::load(&path)
+    }
+
+    fn jpeg(width: u32, height: u32, color: [u8; 3]) -> Vec<u8> {
+        crate::overlay::encode_jpeg(&RgbImage::from_pixel(width, height, Rgb(color)), 90).unwrap()
+    }
+
+    #[test]
+    fn crop_is_clamped_to_the_frame() {
+        let img = RgbImage::from_fn(100, 50, |x, y| Rgb([x as u8, y as u8, 0]));
+        let cropped = Filter::Crop { x: 10, y: 20, w: 30, h: 10 }.apply(img.clone());
+        assert_eq!(cropped.dimensions(), (30, 10));
+        assert_eq!(cropped.get_pixel(0, 0), &Rgb([10, 20, 0]));
+        // Larger than what is left, or past the edge: still a frame
+        assert_eq!(Filter::Crop { x: 80, y: 40, w: 500, h: 500 }.apply(img.clone()).dimensions(), (20, 10));
+        assert_eq!(Filter::Crop { x: 1000, y: 1000, w: 5, h: 5 }.apply(img).dimensions(), (1, 1));
+    }
+
+    #[test]
+    fn grayscale_uses_luma_weights() {
+        let img = RgbImage::from_fn(3, 1, |x, _| [Rgb([255, 0, 0]), Rgb([0, 255, 0]), Rgb([0, 0, 255])][x as usize]);
+        let gray = Filter::Grayscale.apply(img);
+        assert_eq!(gray.get_pixel(0, 0), &Rgb([76, 76, 76]));
+        assert_eq!(gray.get_pixel(1, 0), &Rgb([149, 149, 149]));
+        assert_eq!(gray.get_pixel(2, 0), &Rgb([29, 29, 29]));
+    }
+
+    #[test]
+    fn contrast_spreads_or_flattens() {
+        let img = RgbImage::from_fn(2, 1, |x, _| if x == 0 { Rgb([100, 100, 100]) } else { Rgb([160, 160, 160]) });
+        let spread = |img: &RgbImage| img.get_pixel(1, 0)[0] as i32 - img.get_pixel(0, 0)[0] as i32;
+        assert!(spread(&Filter::Contrast(50.0).apply(img.clone())) > 60);
+        assert!(spread(&Filter::Contrast(-50.0).apply(img)) < 60);
+    }
+
+    #[test]
+    fn watermark_is_placed_by_corner() {
+        let img = RgbImage::new(200, 100);
+        let mark = RgbaImage::new(20, 10);
+        assert_eq!(watermark_origin(&img, &mark, Corner::TopLeft, 5), (5, 5));
+        assert_eq!(watermark_origin(&img, &mark, Corner::TopRight, 5), (175, 5));
+        assert_eq!(watermark_origin(&img, &mark, Corner::BottomLeft, 5), (5, 85));
+        assert_eq!(watermark_origin(&img, &mark, Corner::BottomRight, 5), (175, 85));
+        assert_eq!(watermark_origin(&img, &mark, Corner::Center, 5), (90, 45));
+    }
+
+    #[test]
+    fn watermark_blends_by_alpha_and_opacity() {
+        let mut mark = RgbaImage::from_pixel(2, 1, Rgba([255, 255, 255, 255]));
+        mark.put_pixel(1, 0, Rgba([255, 255, 255, 0]));
+        let filter = Filter::Watermark { image: mark, opacity: 0.5, position: Corner::TopLeft, margin: 0 };
+        let out = filter.apply(RgbImage::from_pixel(4, 4, Rgb([0, 0, 0])));
+        assert_eq!(out.get_pixel(0, 0), &Rgb([128, 128, 128]));
+        // Transparent pixels and everything outside the mark are untouched
+        assert_eq!(out.get_pixel(1, 0), &Rgb([0, 0, 0]));
+        assert_eq!(out.get_pixel(3, 3), &Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn watermark_larger_than_the_frame_is_clipped() {
+        let mark = RgbaImage::from_pixel(10, 10, Rgba([255, 0, 0, 255]));
+        let filter = Filter::Watermark { image: mark, opacity: 1.0, position: Corner::BottomRight, margin: 0 };
+        let out = filter.apply(RgbImage::new(4, 4));
+        assert_eq!(out.dimensions(), (4, 4));
+        assert!(out.pixels().all(|p| *p == Rgb([255, 0, 0])));
+    }
+
+    #[test]
+    fn loads_profiles_with_watermarks_relative_to_the_file() {
+        let dir = temp_dir("load");
+        RgbaImage::from_pixel(8, 8, Rgba([0, 0, 0, 255])).save(dir.join("logo.png")).unwrap();
+        let filters = load(
+            &dir,
+            r#"{"eink": [{"type": "crop", "x": 0, "y": 0, "w": 10, "h": 10}, {"type": "grayscale"}, {"type": "contrast", "amount": 40}],
+                "external": [{"type": "watermark", "image": "logo.png", "opacity": 3.0}]}"#,
+        )
+        .unwrap();
+        assert_eq!(filters.names(), vec!["eink", "external"]);
+        assert!(filters.has("eink") && !filters.has("other"));
+        match &filters.pipelines["external"][0] {
+            Filter::Watermark { image, opacity, position, margin } => {
+                assert_eq!(image.dimensions(), (8, 8));
+                assert_eq!(*opacity, 1.0);
+                assert_eq!(*position, Corner::BottomRight);
+                assert_eq!(*margin, 16);
+            }
+            _ => panic!("expected a watermark"),
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_profiles() {
+        let dir = temp_dir("invalid");
+        let error = |json: &str| load(&dir, json).err().unwrap_or_default();
+        assert!(error(r#"{"a": [{"type": "crop", "x": 0, "y": 0, "w": 0, "h": 10}]}"#).contains("profile 'a': crop needs a non-zero w and h"));
+        assert!(error(r#"{"a": [{"type": "blur"}]}"#).contains("Invalid frame filters"));
+        assert!(error(r#"{"a": [{"type": "grayscale", "amount": 3}]}"#).contains("Invalid frame filters"));
+        assert!(error(r#"{"a": [{"type": "watermark", "image": "missing.png"}]}"#).contains("Failed to read watermark"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn apply_runs_the_pipeline_once_per_frame() {
+        let dir = temp_dir("apply");
+        let filters = load(&dir, r#"{"small": [{"type": "crop", "x": 0, "y": 0, "w": 16, "h": 8}, {"type": "grayscale"}]}"#).unwrap();
+        let stats = SharedPipelineStats::default();
+
+        assert!(filters.apply("unknown", 1, &jpeg(32, 32, [200, 0, 0]), &stats).is_none());
+        assert!(filters.apply("small", 1, b"not a jpeg", &stats).is_none());
+
+        let red = filters.apply("small", 2, &jpeg(32, 32, [200, 0, 0]), &stats).unwrap();
+        let img = image::load_from_memory(&red).unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (16, 8));
+        let [r, g, b] = img.get_pixel(4, 4).0;
+        assert!(r.abs_diff(g) <= 2 && g.abs_diff(b) <= 2, "not gray: {:?}", (r, g, b));
+
+        // The same frame number answers from the cache, a new one reruns
+        assert_eq!(filters.apply("small", 2, &jpeg(32, 32, [0, 0, 200]), &stats).unwrap(), red);
+        assert_ne!(filters.apply("small", 3, &jpeg(32, 32, [0, 0, 200]), &stats).unwrap(), red);
+
+        let stages = &stats.lock().unwrap().to_json()["stages_ms"];
+        for stage in ["filter_crop", "filter_grayscale", "filter_codec"] {
+            assert!(stages.get(stage).is_some(), "missing {}", stage);
+        }
+        assert!(stages.get("filter_contrast").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chromiumoxide::cdp::browser_protocol::page::AddScriptToEvaluateOnNewDocumentParams;
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use serde::Serialize;
+
+// ============== Bundled Fonts ==============
+//
+// Font fallback differs between macOS and Linux, so identical pages render
+// differently across machines. Fonts from --bundled-fonts are embedded as
+// data URLs in an init script and the generic families are pointed at them.
+
+const STYLE_ID: &str = "__rb_bundled_fonts__";
+
+const GENERIC_FAMILIES: [&str; 5] = ["serif", "sans-serif", "monospace", "cursive", "system-ui"];
+
+/// Elements whose browser default is a given generic family.
+fn generic_selector(generic: &str) -> &'static str {
+    match generic {
+        "monospace" => "code, kbd, pre, samp, tt, textarea",
+        "system-ui" => "button, input, select, textarea",
+        _ => "html, body",
+    }
+}
+
+/// `GENERIC=FAMILY` pair from --font-map, e.g. `monospace=JetBrains Mono`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FontMapping {
+    pub generic: String,
+    pub family: String,
+}
+
+impl FromStr for FontMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (generic, family) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected GENERIC=FAMILY, got '{}'", s))?;
+        let generic = generic.trim().to_ascii_lowercase();
+        if !GENERIC_FAMILIES.contains(&generic.as_str()) {
+            return Err(format!("unknown generic family '{}', expected one of {}", generic, GENERIC_FAMILIES.join(", ")));
+        }
+        let family = family.trim();
+        if family.is_empty() {
+            return Err("font family must not be empty".to_string());
+        }
+        Ok(FontMapping { generic, family: family.to_string() })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FontFace {
+    pub family: String,
+    pub weight: u16,
+    pub italic: bool,
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Default)]
+pub struct FontConfig {
+    pub dir: Option<PathBuf>,
+    pub faces: Vec<FontFace>,
+    pub mappings: Vec<FontMapping>,
+    /// Chrome flags added for font rendering.
+    pub chrome_flags: Vec<String>,
+    css: String,
+}
+
+fn font_format(path: &Path) -> Option<(&'static str, &'static str)> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "ttf" => Some(("font/ttf", "truetype")),
+        "otf" => Some(("font/otf", "opentype")),
+        "woff" => Some(("font/woff", "woff")),
+        "woff2" => Some(("font/woff2", "woff2")),
+        _ => None,
+    }
+}
+
+/// Guesses family, weight and style from names like `Inter-SemiBoldItalic.ttf`.
+fn describe(path: &Path) -> (String, u16, bool) {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let (family, style) = stem.split_once('-').unwrap_or((stem, ""));
+    let style = style.to_ascii_lowercase();
+    const WEIGHTS: [(&str, u16); 9] = [
+        ("extralight", 200),
+        ("semibold", 600),
+        ("extrabold", 800),
+        ("thin", 100),
+        ("light", 300),
+        ("medium", 500),
+        ("bold", 700),
+        ("black", 900),
+        ("regular", 400),
+    ];
+    let weight = WEIGHTS
+        .iter()
+        .find(|(name, _)| style.contains(name))
+        .map(|(_, w)| *w)
+        .unwrap_or(400);
+    (family.to_string(), weight, style.contains("italic"))
+}
+
+impl FontConfig {
+    pub fn load(dir: Option<&Path>, mappings: Vec<FontMapping>, hinting: Option<&str>) -> std::io::Result<Self> {
+        let mut config = FontConfig { dir: dir.map(Path::to_path_buf), mappings, ..Default::default() };
+        if let Some(hinting) = hinting {
+            config.chrome_flags.push(format!("--font-render-hinting={}", hinting));
+            config.chrome_flags.push("--disable-font-subpixel-positioning".to_string());
+        }
+        let Some(dir) = dir else {
+            return Ok(config);
+        };
+
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| font_format(p).is_some())
+            .collect();
+        paths.sort();
+
+        let mut css = String::new();
+        for path in paths {
+            let (mime, format) = font_format(&path).unwrap_or(("font/ttf", "truetype"));
+            let data = std::fs::read(&path)?;
+            let (family, weight, italic) = describe(&path);
+            css.push_str(&format!(
+                "@font-face {{ font-family: {}; src: url(data:{};base64,{}) format('{}'); font-weight: {}; font-style: {}; }}\n",
+                serde_json::Value::from(family.as_str()),
+                mime,
+                BASE64.encode(&data),
+                format,
+                weight,
+                if italic { "italic" } else { "normal" },
+            ));
+            config.faces.push(FontFace { family, weight, italic, file: path });
+        }
+
+        // With a single bundled family and no explicit map, use it for body text
+        if config.mappings.is_empty() {
+            let mut families: Vec<&str> = config.faces.iter().map(|f| f.family.as_str()).collect();
+            families.dedup();
+            if let [family] = families.as_slice() {
+                config.mappings.push(FontMapping { generic: "sans-serif".to_string(), family: family.to_string() });
+            }
+        }
+        for mapping in &config.mappings {
+            css.push_str(&format!(
+                "{} {{ font-family: {}, {}; }}\n",
+                generic_selector(&mapping.generic),
+                serde_json::Value::from(mapping.family.as_str()),
+                mapping.generic,
+            ));
+        }
+        config.css = css;
+        Ok(config)
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.css.is_empty()
+    }
+
+    pub fn init_script(&self) -> String {
+        crate::commands::init_style_script(STYLE_ID, &self.css)
+    }
+
+    pub async fn install(&self, page: &Page) -> Result<(), CdpError> {
+        page.execute(AddScriptToEvaluateOnNewDocumentParams::new(self.init_script())).await?;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "bundled_dir": self.dir,
+            "faces": self.faces,
+            "mappings": self.mappings,
+            "chrome_flags": self.chrome_flags,
+        })
+    }
+}
@@ -0,0 +1,316 @@
+use std::io::{Read, Write};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ============== Frame Hook ==============
+//
+// --frame-hook runs an external analyser (OCR, object detection, ...) on a
+// sample of live frames: every Nth frame is written as JPEG to the command's
+// stdin and its stdout is parsed as JSON. Selected fields of the latest
+// result are merged into /live-stream as `hook`, and a webhook fires when
+// the output starts matching --frame-hook-match. Runs happen on their own
+// threads, bounded by a timeout and a concurrency limit; frames arriving
+// while all slots are busy are skipped, so a slow hook only makes the
+// metadata older and never delays frame delivery.
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `FIELD`, `FIELD=VALUE`, `FIELD!=VALUE`, `FIELD>N` or `FIELD<N`; FIELD is
+/// a dotted path into the hook output.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    source: String,
+    path: Vec<String>,
+    op: Op,
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Truthy,
+    Eq(String),
+    Ne(String),
+    Gt(f64),
+    Lt(f64),
+}
+
+pub fn parse_condition_arg(s: &str) -> Result<Condition, String> {
+    let number = |v: &str| v.trim().parse::<f64>().map_err(|_| format!("invalid number '{}' in condition '{}'", v, s));
+    let (field, op) = if let Some((field, value)) = s.split_once("!=") {
+        (field, Op::Ne(value.trim().to_string()))
+    } else if let Some((field, value)) = s.split_once('=') {
+        (field, Op::Eq(value.trim().to_string()))
+    } else if let Some((field, value)) = s.split_once('>') {
+        (field, Op::Gt(number(value)?))
+    } else if let Some((field, value)) = s.split_once('<') {
+        (field, Op::Lt(number(value)?))
+    } else {
+        (s, Op::Truthy)
+    };
+    let path: Vec<String> = field.trim().split('.').map(str::to_string).collect();
+    if path.iter().any(String::is_empty) {
+        return Err(format!("invalid condition '{}': expected FIELD, FIELD=VALUE, FIELD!=VALUE, FIELD>N or FIELD<N", s));
+    }
+    Ok(Condition { source: s.to_string(), path, op })
+}
+
+impl Condition {
+    fn matches(&self, output: &serde_json::Value) -> bool {
+        let value = self.path.iter().try_fold(output, |v, key| v.get(key));
+        let text = |v: &serde_json::Value| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string());
+        match (&self.op, value) {
+            (Op::Truthy, Some(v)) => !matches!(v, serde_json::Value::Null | serde_json::Value::Bool(false))
+                && v.as_f64() != Some(0.0)
+                && v.as_str() != Some("")
+                && !v.as_array().is_some_and(|a| a.is_empty()),
+            (Op::Eq(expected), Some(v)) => text(v) == *expected,
+            (Op::Ne(expected), v) => v.map(text).as_deref() != Some(expected.as_str()),
+            (Op::Gt(limit), Some(v)) => v.as_f64().is_some_and(|n| n > *limit),
+            (Op::Lt(limit), Some(v)) => v.as_f64().is_some_and(|n| n < *limit),
+            (_, None) => false,
+        }
+    }
+}
+
+pub struct HookConfig {
+    pub command: String,
+    /// Run on every Nth published frame.
+    pub every: u64,
+    pub timeout: Duration,
+    pub concurrency: usize,
+    /// Top-level output fields merged into /live-stream; empty means all.
+    pub fields: Vec<String>,
+    pub condition: Option<Condition>,
+    pub webhook: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct HookStats {
+    runs: u64,
+    failures: u64,
+    timeouts: u64,
+    /// Sampled frames dropped because every slot was busy.
+    skipped: u64,
+    webhooks_sent: u64,
+    webhook_failures: u64,
+    last_error: Option<String>,
+    last_elapsed_ms: Option<u128>,
+    /// Selected fields of the last successful run.
+    latest: Option<serde_json::Value>,
+    latest_at: Option<u128>,
+    matching: bool,
+}
+
+#[derive(Clone)]
+pub struct FrameHook {
+    config: Arc<HookConfig>,
+    frames: Arc<AtomicU64>,
+    in_flight: Arc<AtomicUsize>,
+    stats: Arc<Mutex<HookStats>>,
+}
+
+impl FrameHook {
+    pub fn new(config: HookConfig) -> Self {
+        log_info!(
+            "Frame hook:  {} (every {} frames, {} ms timeout, {} concurrent)",
+            config.command,
+            config.every,
+            config.timeout.as_millis(),
+            config.concurrency
+        );
+        FrameHook {
+            config: Arc::new(HookConfig { every: config.every.max(1), concurrency: config.concurrency.max(1), ..config }),
+            frames: Arc::default(),
+            in_flight: Arc::default(),
+            stats: Arc::default(),
+        }
+    }
+
+    /// Hands a sampled frame to the hook without ever blocking the caller.
+    pub fn offer(&self, frame: &[u8], url: &str) {
+        if !self.frames.fetch_add(1, Ordering::Relaxed).is_multiple_of(self.config.every) {
+            return;
+        }
+        let claimed = self.in_flight.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+            (n < self.config.concurrency).then_some(n + 1)
+        });
+        if claimed.is_err() {
+            self.stats.lock().unwrap().skipped += 1;
+            return;
+        }
+        let hook = self.clone();
+        let frame = frame.to_vec();
+        let url = url.to_string();
+        thread::spawn(move || {
+            hook.process(frame, &url);
+            hook.in_flight.fetch_sub(1, Ordering::AcqRel);
+        });
+    }
+
+    fn process(&self, frame: Vec<u8>, url: &str) {
+        let started = Instant::now();
+        let result = run(&self.config.command, frame, self.config.timeout);
+        let elapsed_ms = started.elapsed().as_millis();
+
+        let output = {
+            let mut stats = self.stats.lock().unwrap();
+            stats.runs += 1;
+            stats.last_elapsed_ms = Some(elapsed_ms);
+            match result {
+                Ok(output) => output,
+                Err(e) => {
+                    if matches!(e, RunError::Timeout) {
+                        stats.timeouts += 1;
+                    }
+                    stats.failures += 1;
+                    let message = e.to_string();
+                    if stats.last_error.as_ref() != Some(&message) {
+                        log_error!("Frame hook failed: {}", message);
+                    }
+                    stats.last_error = Some(message);
+                    return;
+                }
+            }
+        };
+
+        let selected = self.select(&output);
+        let rising = {
+            let mut stats = self.stats.lock().unwrap();
+            stats.latest = Some(selected);
+            stats.latest_at = Some(crate::now_millis());
+            let matching = self.config.condition.as_ref().is_some_and(|c| c.matches(&output));
+            let rising = matching && !stats.matching;
+            stats.matching = matching;
+            rising
+        };
+        if rising {
+            self.fire_webhook(&output, url);
+        }
+    }
+
+    fn select(&self, output: &serde_json::Value) -> serde_json::Value {
+        match output.as_object() {
+            Some(object) if !self.config.fields.is_empty() => serde_json::Value::Object(
+                object
+                    .iter()
+                    .filter(|(key, _)| self.config.fields.contains(key))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            ),
+            _ => output.clone(),
+        }
+    }
+
+    /// Fires once per transition into the matching state, not on every match.
+    fn fire_webhook(&self, output: &serde_json::Value, url: &str) {
+        let (Some(webhook), Some(condition)) = (&self.config.webhook, &self.config.condition) else {
+            return;
+        };
+        let payload = serde_json::json!({
+            "event": "frame_hook_match",
+            "condition": condition.source,
+            "url": url,
+            "output": output,
+            "timestamp": crate::now_millis(),
+        });
+        let sent = ureq::post(webhook).timeout(WEBHOOK_TIMEOUT).send_json(payload);
+        let mut stats = self.stats.lock().unwrap();
+        match sent {
+            Ok(_) => stats.webhooks_sent += 1,
+            Err(e) => {
+                log_error!("Frame hook webhook to {} failed: {}", webhook, e);
+                stats.webhook_failures += 1;
+            }
+        }
+    }
+
+    /// The `hook` object embedded in /live-stream.
+    pub fn latest(&self) -> serde_json::Value {
+        let stats = self.stats.lock().unwrap();
+        serde_json::json!({
+            "output": stats.latest,
+            "at": stats.latest_at,
+            "matching": stats.matching,
+        })
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let stats = self.stats.lock().unwrap();
+        serde_json::json!({
+            "command": self.config.command,
+            "every": self.config.every,
+            "timeout_ms": self.config.timeout.as_millis() as u64,
+            "concurrency": self.config.concurrency,
+            "in_flight": self.in_flight.load(Ordering::Acquire),
+            "condition": self.config.condition.as_ref().map(|c| &c.source),
+            "runs": stats.runs,
+            "failures": stats.failures,
+            "timeouts": stats.timeouts,
+            "skipped": stats.skipped,
+            "webhooks_sent": stats.webhooks_sent,
+            "webhook_failures": stats.webhook_failures,
+            "last_elapsed_ms": stats.last_elapsed_ms,
+            "last_error": stats.last_error,
+            "matching": stats.matching,
+        })
+    }
+}
+
+enum RunError {
+    Timeout,
+    Other(String),
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::Timeout => f.write_str("timed out"),
+            RunError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+/// One hook execution: the frame on stdin, JSON expected on stdout. The
+/// child is killed once `timeout` passes.
+fn run(command: &str, frame: Vec<u8>, timeout: Duration) -> Result<serde_json::Value, RunError> {
+    let mut child = crate::sink::shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| RunError::Other(format!("failed to spawn: {}", e)))?;
+
+    // Feeding and draining on their own threads keeps a hook that reads
+    // lazily or writes a lot from deadlocking against us
+    let mut stdin = child.stdin.take().ok_or_else(|| RunError::Other("child has no stdin".to_string()))?;
+    thread::spawn(move || {
+        let _ = stdin.write_all(&frame);
+    });
+    let mut stdout = child.stdout.take().ok_or_else(|| RunError::Other("child has no stdout".to_string()))?;
+    let reader = thread::spawn(move || {
+        let mut out = Vec::new();
+        let _ = stdout.read_to_end(&mut out);
+        out
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(RunError::Timeout);
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(10)),
+            Err(e) => return Err(RunError::Other(e.to_string())),
+        }
+    };
+    let out = reader.join().unwrap_or_default();
+    if !status.success() {
+        return Err(RunError::Other(format!("exited with {}", status)));
+    }
+    serde_json::from_slice(&out).map_err(|e| RunError::Other(format!("invalid JSON on stdout: {}", e)))
+}
@@ -0,0 +1,84 @@
+use chromiumoxide::cdp::browser_protocol::dom::{GetBoxModelParams, GetFrameOwnerParams};
+use chromiumoxide::cdp::browser_protocol::page::{CreateIsolatedWorldParams, Frame, FrameTree, GetFrameTreeParams};
+use chromiumoxide::cdp::js_protocol::runtime::ExecutionContextId;
+use chromiumoxide::Page;
+
+use crate::commands::CommandError;
+
+// ============== Frames ==============
+//
+// Selectors resolve in the top document unless /click, /type or /hover name
+// a "frame": a frame id from GET /frames-tree, else a frame name, else a URL
+// glob where `*` matches anything; the first match in document order wins.
+// The command then runs in an isolated world created in that frame
+// (Page.createIsolatedWorld), which sees the frame's DOM but not its page
+// scripts' globals.
+//
+// Headless Chrome is launched with site isolation off, so a cross-origin
+// iframe shares the page's renderer and shows up in its frame tree like any
+// other, instead of being a separate target. Points inside a frame, like the
+// /hover mouse position, are moved by the frame's content box in the top
+// viewport (DOM.getBoxModel of the <iframe> element).
+
+const WORLD_NAME: &str = "__rb_frames";
+
+/// Keep cross-origin iframes in the page's process and frame tree.
+pub const CHROME_FLAGS: &[&str] = &["--disable-site-isolation-trials", "--disable-features=IsolateOrigins,site-per-process"];
+
+/// A frame resolved from a "frame" field.
+pub struct FrameTarget {
+    pub context: ExecutionContextId,
+    /// Top-left of the frame's content in top-viewport CSS pixels.
+    pub offset: (f64, f64),
+}
+
+fn node_json(tree: &FrameTree) -> serde_json::Value {
+    let children: Vec<serde_json::Value> = tree.child_frames.iter().flatten().map(node_json).collect();
+    serde_json::json!({
+        "id": tree.frame.id.inner(),
+        "name": tree.frame.name,
+        "url": tree.frame.url,
+        "children": children,
+    })
+}
+
+fn flatten<'a>(tree: &'a FrameTree, out: &mut Vec<&'a Frame>) {
+    out.push(&tree.frame);
+    for child in tree.child_frames.iter().flatten() {
+        flatten(child, out);
+    }
+}
+
+/// GET /frames-tree: the main frame with nested `children`.
+pub async fn tree(page: &Page) -> Result<serde_json::Value, CommandError> {
+    let tree = page.execute(GetFrameTreeParams::default()).await?.result.frame_tree;
+    Ok(node_json(&tree))
+}
+
+/// Looks up `spec` among the page's frames and creates a world in it.
+pub async fn resolve(page: &Page, spec: &str) -> Result<FrameTarget, CommandError> {
+    let tree = page.execute(GetFrameTreeParams::default()).await?.result.frame_tree;
+    let mut frames = Vec::new();
+    flatten(&tree, &mut frames);
+    let frame = frames
+        .iter()
+        .find(|f| f.id.inner() == spec)
+        .or_else(|| frames.iter().find(|f| f.name.as_deref() == Some(spec)))
+        .or_else(|| frames.iter().find(|f| crate::url_match::glob_matches(spec, &f.url)))
+        .ok_or_else(|| CommandError::new(404, format!("no frame matches '{}', see GET /frames-tree", spec)))?;
+
+    let offset = if frame.parent_id.is_none() {
+        (0.0, 0.0)
+    } else {
+        let owner = page.execute(GetFrameOwnerParams::new(frame.id.clone())).await?.result;
+        let params = GetBoxModelParams { backend_node_id: Some(owner.backend_node_id), ..Default::default() };
+        let content = page.execute(params).await?.result.model.content;
+        let quad = content.inner();
+        (quad.first().copied().unwrap_or_default(), quad.get(1).copied().unwrap_or_default())
+    };
+
+    let mut params = CreateIsolatedWorldParams::new(frame.id.clone());
+    params.world_name = Some(WORLD_NAME.to_string());
+    let context = page.execute(params).await?.result.execution_context_id;
+    Ok(FrameTarget { context, offset })
+}
@@ -0,0 +1,166 @@
+use crate::HeadlessState;
+
+// ============== gRPC Control Server ==============
+//
+// Optional tonic server for the core operations, defined in
+// proto/browser.proto and running alongside the HTTP server on --grpc-port.
+// Calls go through the page command channel like the HTTP handlers;
+// StreamFrames follows the live frame notifier. Needs the `grpc` cargo feature.
+
+/// Messages, server and client generated from proto/browser.proto.
+#[cfg(feature = "grpc")]
+pub mod proto {
+    tonic::include_proto!("hellocef.v1");
+}
+
+#[cfg(feature = "grpc")]
+pub fn start(port: u16, state: HeadlessState) -> Result<(), String> {
+    let addr: std::net::SocketAddr = format!("0.0.0.0:{}", port)
+        .parse()
+        .map_err(|e| format!("invalid gRPC address: {}", e))?;
+    tokio::spawn(async move {
+        log_info!("gRPC:        {}", addr);
+        let result = tonic::transport::Server::builder()
+            .add_service(proto::browser_control_server::BrowserControlServer::new(service::Service { state }))
+            .serve(addr)
+            .await;
+        if let Err(e) = result {
+            log_error!("gRPC server failed: {}", e);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "grpc"))]
+pub fn start(_port: u16, _state: HeadlessState) -> Result<(), String> {
+    Err("--grpc-port needs a build with `--features grpc`".to_string())
+}
+
+#[cfg(feature = "grpc")]
+mod service {
+    use std::pin::Pin;
+    use std::time::Duration;
+
+    use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+    use futures::Stream;
+    use tonic::{Request, Response, Status};
+
+    use crate::commands::{self, CommandError, PageCommand};
+    use super::proto::browser_control_server::BrowserControl;
+    use super::proto::*;
+    use crate::HeadlessState;
+
+    fn to_status(e: CommandError) -> Status {
+        match e.status {
+            400 | 422 => Status::invalid_argument(e.message),
+            404 => Status::not_found(e.message),
+            503 => Status::unavailable(e.message),
+            504 => Status::deadline_exceeded(e.message),
+            _ => Status::internal(e.message),
+        }
+    }
+
+    /// `commands::send` blocks, so it runs off the async workers.
+    async fn send(state: &HeadlessState, command: PageCommand) -> Result<serde_json::Value, Status> {
+        let tx = state.commands.clone();
+        tokio::task::spawn_blocking(move || commands::send(&tx, command))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(to_status)
+    }
+
+    pub struct Service {
+        pub state: HeadlessState,
+    }
+
+    #[tonic::async_trait]
+    impl BrowserControl for Service {
+        async fn navigate(&self, request: Request<NavigateRequest>) -> Result<Response<NavigateResponse>, Status> {
+            let url = request.into_inner().url;
+            let result = send(&self.state, PageCommand::Navigate { url, source: "grpc" }).await?;
+            Ok(Response::new(NavigateResponse {
+                url: result["url"].as_str().unwrap_or_default().to_string(),
+            }))
+        }
+
+        async fn screenshot(&self, request: Request<ScreenshotRequest>) -> Result<Response<Frame>, Status> {
+            let req = request.into_inner();
+            let command = PageCommand::Render {
+                settle: req.settle.then_some(self.state.settle),
+                deterministic: req.deterministic,
+            };
+            let result = send(&self.state, command).await?;
+            let jpeg = result["frame"]
+                .as_str()
+                .and_then(|f| BASE64.decode(f).ok())
+                .ok_or_else(|| Status::internal("render returned no frame"))?;
+            Ok(Response::new(Frame {
+                jpeg,
+                url: result["url"].as_str().unwrap_or_default().to_string(),
+                seq: 0,
+                timestamp_ms: crate::now_millis() as u64,
+            }))
+        }
+
+        type StreamFramesStream = Pin<Box<dyn Stream<Item = Result<Frame, Status>> + Send>>;
+
+        async fn stream_frames(&self, request: Request<StreamFramesRequest>) -> Result<Response<Self::StreamFramesStream>, Status> {
+            let interval = match request.into_inner().max_fps {
+                0 => Duration::ZERO,
+                fps => Duration::from_secs_f64(1.0 / fps as f64),
+            };
+            let state = self.state.clone();
+            let frames = self.state.frames.subscribe();
+            let stream = futures::stream::unfold((frames, true), move |(mut frames, first)| {
+                let state = state.clone();
+                async move {
+                    if !first && !interval.is_zero() {
+                        tokio::time::sleep(interval).await;
+                    }
+                    // Ends the stream once the capture loop is gone
+                    frames.changed().await.ok()?;
+                    let seq = *frames.borrow_and_update();
+                    let jpeg = state.screenshot_buffer.lock().unwrap().clone()?;
+                    let frame = Frame {
+                        jpeg,
+                        url: state.current_url.lock().unwrap().clone(),
+                        seq,
+                        timestamp_ms: crate::now_millis() as u64,
+                    };
+                    Some((Ok(frame), (frames, false)))
+                }
+            });
+            Ok(Response::new(Box::pin(stream)))
+        }
+
+        async fn click(&self, request: Request<ClickRequest>) -> Result<Response<ClickResponse>, Status> {
+            let req = request.into_inner();
+            let mut stability = commands::Stability::default();
+            stability.stable = req.stable.unwrap_or(stability.stable);
+            if req.timeout_ms > 0 {
+                stability.timeout_ms = req.timeout_ms as u64;
+            }
+            send(&self.state, PageCommand::Click { selector: req.selector, frame: None, stability }).await?;
+            Ok(Response::new(ClickResponse {}))
+        }
+
+        async fn evaluate(&self, request: Request<EvaluateRequest>) -> Result<Response<EvaluateResponse>, Status> {
+            let req = request.into_inner();
+            let command = PageCommand::Evaluate { expression: req.expression, await_promise: req.await_promise };
+            let result = send(&self.state, command).await?;
+            Ok(Response::new(EvaluateResponse { result_json: result.to_string() }))
+        }
+
+        async fn status(&self, _request: Request<StatusRequest>) -> Result<Response<StatusResponse>, Status> {
+            let state = &self.state;
+            Ok(Response::new(StatusResponse {
+                url: state.current_url.lock().unwrap().clone(),
+                navigation_seq: state.navigation.lock().unwrap().seq,
+                frame_seq: *state.frames.borrow(),
+                fps: state.pipeline.lock().unwrap().fps(),
+                viewport_width: state.viewport.0,
+                viewport_height: state.viewport.1,
+            }))
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chromiumoxide::cdp::browser_protocol::network::{CookieParam, CookieSameSite, SetCookiesParams, TimeSinceEpoch};
+use chromiumoxide::cdp::browser_protocol::storage::GetCookiesParams;
+use chromiumoxide::Page;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{self, CommandError};
+
+// ============== Session Handoff ==============
+//
+// POST /handoff/export bundles what is needed to continue the streamed page
+// elsewhere: URL, viewport, scroll position, cookies and local/session
+// storage. POST /handoff/import (or --import-state FILE at startup) applies
+// such a bundle in either mode, so a logged-in page can move from the
+// desktop to a server instance and back. Storage is origin-scoped, so an
+// import loads the URL, writes storage, reloads for the page to pick it up
+// and then restores the scroll position.
+
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    /// "Strict", "Lax" or "None".
+    pub same_site: Option<String>,
+    /// Seconds since the epoch; `None` for session cookies.
+    pub expires: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Scroll {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HandoffState {
+    pub version: u32,
+    pub mode: String,
+    pub exported_at: u128,
+    pub url: String,
+    pub viewport: Viewport,
+    #[serde(default)]
+    pub scroll: Scroll,
+    #[serde(default)]
+    pub cookies: Vec<Cookie>,
+    #[serde(default)]
+    pub local_storage: BTreeMap<String, String>,
+    #[serde(default)]
+    pub session_storage: BTreeMap<String, String>,
+}
+
+/// Page-side half of an export: everything but cookies and the viewport.
+#[derive(Debug, Deserialize)]
+pub struct PageSnapshot {
+    pub url: String,
+    pub scroll: Scroll,
+    pub local_storage: BTreeMap<String, String>,
+    pub session_storage: BTreeMap<String, String>,
+}
+
+pub const EXPORT_SCRIPT: &str = r#"
+function() {
+    const dump = function(storage) {
+        const out = {};
+        try {
+            for (let i = 0; i < storage.length; i++) {
+                const key = storage.key(i);
+                out[key] = storage.getItem(key);
+            }
+        } catch (e) {}
+        return out;
+    };
+    return {
+        url: location.href,
+        scroll: { x: window.scrollX, y: window.scrollY },
+        local_storage: dump(window.localStorage),
+        session_storage: dump(window.sessionStorage),
+    };
+}
+"#;
+
+/// Writes storage (and, with `cookies`, the cookies scripts may set) for the
+/// current origin; returns how many cookies had to be skipped.
+pub const RESTORE_SCRIPT: &str = r#"
+function(state, cookies) {
+    const fill = function(storage, items) {
+        try {
+            for (const key in items) storage.setItem(key, items[key]);
+        } catch (e) {}
+    };
+    fill(window.localStorage, state.local_storage || {});
+    fill(window.sessionStorage, state.session_storage || {});
+    let skipped = 0;
+    if (cookies) {
+        for (const c of state.cookies || []) {
+            if (c.http_only) { skipped++; continue; }
+            let cookie = c.name + '=' + c.value + '; path=' + (c.path || '/');
+            if (c.domain) cookie += '; domain=' + c.domain;
+            if (c.expires) cookie += '; expires=' + new Date(c.expires * 1000).toUTCString();
+            if (c.secure) cookie += '; secure';
+            if (c.same_site) cookie += '; samesite=' + c.same_site;
+            document.cookie = cookie;
+        }
+    }
+    return skipped;
+}
+"#;
+
+pub fn scroll_script(scroll: Scroll) -> String {
+    format!("window.scrollTo({}, {})", scroll.x, scroll.y)
+}
+
+impl HandoffState {
+    pub fn new(mode: &str, snapshot: PageSnapshot, viewport: Viewport, cookies: Vec<Cookie>) -> Self {
+        HandoffState {
+            version: VERSION,
+            mode: mode.to_string(),
+            exported_at: crate::now_millis(),
+            url: snapshot.url,
+            viewport,
+            scroll: snapshot.scroll,
+            cookies,
+            local_storage: snapshot.local_storage,
+            session_storage: snapshot.session_storage,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version > VERSION {
+            return Err(format!("handoff version {} is newer than supported ({})", self.version, VERSION));
+        }
+        url::Url::parse(&self.url).map_err(|e| format!("invalid handoff url '{}': {}", self.url, e))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let body = std::fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let state: HandoffState =
+            serde_json::from_slice(&body).map_err(|e| format!("invalid handoff state {}: {}", path.display(), e))?;
+        state.validate()?;
+        Ok(state)
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "url": self.url,
+            "viewport": self.viewport,
+            "scroll": self.scroll,
+            "cookies": self.cookies.len(),
+            "local_storage": self.local_storage.len(),
+            "session_storage": self.session_storage.len(),
+        })
+    }
+}
+
+/// Headless export: all browser cookies (HttpOnly included) over CDP.
+pub async fn export(page: &Page, viewport: (u32, u32)) -> Result<HandoffState, CommandError> {
+    let snapshot = commands::evaluate(page, commands::js_call(EXPORT_SCRIPT, &[]), false).await?;
+    let snapshot: PageSnapshot =
+        serde_json::from_value(snapshot).map_err(|e| CommandError::new(500, format!("unexpected page state: {}", e)))?;
+    let cookies = page
+        .execute(GetCookiesParams::default())
+        .await?
+        .result
+        .cookies
+        .into_iter()
+        .map(|c| Cookie {
+            expires: (!c.session && c.expires > 0.0).then_some(c.expires),
+            same_site: c.same_site.map(|s| s.as_ref().to_string()),
+            name: c.name,
+            value: c.value,
+            domain: c.domain,
+            path: c.path,
+            secure: c.secure,
+            http_only: c.http_only,
+        })
+        .collect();
+    let viewport = Viewport { width: viewport.0, height: viewport.1 };
+    Ok(HandoffState::new("headless", snapshot, viewport, cookies))
+}
+
+/// Headless import: cookies over CDP, then load, storage, reload and scroll.
+/// The viewport is only applied at startup, where it replaces --width/--height.
+pub async fn import(page: &Page, state: &HandoffState) -> Result<(), CommandError> {
+    let cookies = state
+        .cookies
+        .iter()
+        .map(|c| {
+            let mut builder = CookieParam::builder()
+                .name(c.name.clone())
+                .value(c.value.clone())
+                .domain(c.domain.clone())
+                .path(c.path.clone())
+                .secure(c.secure)
+                .http_only(c.http_only);
+            if let Some(same_site) = c.same_site.as_deref().and_then(|s| s.parse::<CookieSameSite>().ok()) {
+                builder = builder.same_site(same_site);
+            }
+            if let Some(expires) = c.expires {
+                builder = builder.expires(TimeSinceEpoch::new(expires));
+            }
+            builder.build().map_err(|e| CommandError::new(400, format!("invalid cookie {}: {}", c.name, e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if !cookies.is_empty() {
+        page.execute(SetCookiesParams::new(cookies)).await?;
+    }
+
+    page.goto(&state.url).await?;
+    if !state.local_storage.is_empty() || !state.session_storage.is_empty() {
+        let payload = serde_json::to_value(state).unwrap_or_default();
+        commands::evaluate(page, commands::js_call(RESTORE_SCRIPT, &[payload, false.into()]), false).await?;
+        page.reload().await?;
+    }
+    page.evaluate(scroll_script(state.scroll)).await?;
+    Ok(())
+}
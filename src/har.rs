@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use chromiumoxide::cdp::browser_protocol::network::{GetResponseBodyParams, ResourceTiming};
+use chromiumoxide::Page;
+
+use crate::commands::{self, CommandError, CommandResult, CommandSender, PageCommand};
+use crate::network_log::{Entry, PageLoad, SharedNetworkLog};
+
+// ============== HAR Export ==============
+//
+// GET /har turns the network log entries of the most recent main-frame
+// navigation into a HAR 1.2 document (one page, its entries with headers,
+// query strings, sizes and timings), loadable in Chrome devtools' HAR
+// import. Values Chrome did not report are -1, as the format asks. With
+// ?include_bodies=true textual responses (text/*, JSON, JavaScript, XML,
+// SVG) up to MAX_BODY_BYTES are fetched with Network.getResponseBody and
+// embedded; others carry no `text`. Bodies Chrome no longer holds (evicted,
+// redirected) are left out the same way.
+
+/// Largest response body embedded per entry.
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+const PAGE_ID: &str = "page_1";
+
+fn is_textual(mime_type: &str) -> bool {
+    let mime = mime_type.to_ascii_lowercase();
+    mime.starts_with("text/")
+        || ["json", "javascript", "ecmascript", "xml", "svg", "x-www-form-urlencoded"].iter().any(|t| mime.contains(t))
+}
+
+fn iso_time(millis: u128) -> String {
+    chrono::DateTime::from_timestamp_millis(millis as i64)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+fn name_values(pairs: &[(String, String)]) -> Vec<serde_json::Value> {
+    pairs.iter().map(|(name, value)| serde_json::json!({ "name": name, "value": value })).collect()
+}
+
+fn http_version(protocol: Option<&str>) -> String {
+    match protocol {
+        Some("h2") => "HTTP/2.0".to_string(),
+        Some("h3") => "HTTP/3.0".to_string(),
+        Some(p) if !p.is_empty() => p.to_ascii_uppercase(),
+        _ => String::new(),
+    }
+}
+
+/// A phase between two ResourceTiming offsets, or -1 when it didn't happen.
+fn span(start: f64, end: f64) -> f64 {
+    if start >= 0.0 && end >= start { end - start } else { -1.0 }
+}
+
+/// HAR `timings`; `send`, `wait` and `receive` must not be -1, and their sum
+/// with the others should be the entry's `time`.
+fn timings(timing: Option<&ResourceTiming>, total: f64) -> serde_json::Value {
+    let Some(t) = timing else {
+        return serde_json::json!({ "blocked": -1, "dns": -1, "connect": -1, "ssl": -1, "send": 0, "wait": total, "receive": 0 });
+    };
+    let first = [t.dns_start, t.connect_start, t.send_start].into_iter().find(|v| *v >= 0.0).unwrap_or(0.0);
+    let send = span(t.send_start, t.send_end).max(0.0);
+    let wait = span(t.send_end, t.receive_headers_end).max(0.0);
+    let receive = (total - t.receive_headers_end).max(0.0);
+    serde_json::json!({
+        "blocked": first,
+        "dns": span(t.dns_start, t.dns_end),
+        "connect": span(t.connect_start, t.connect_end),
+        "ssl": span(t.ssl_start, t.ssl_end),
+        "send": send,
+        "wait": wait,
+        "receive": receive,
+    })
+}
+
+fn entry_json(entry: &Entry, body: Option<&String>) -> serde_json::Value {
+    let query: Vec<(String, String)> = url::Url::parse(&entry.url)
+        .map(|u| u.query_pairs().map(|(k, v)| (k.into_owned(), v.into_owned())).collect())
+        .unwrap_or_default();
+    let version = http_version(entry.protocol.as_deref());
+    let location = entry.response_headers.iter().find(|(name, _)| name.eq_ignore_ascii_case("location")).map(|(_, v)| v.as_str());
+    let total = entry.duration_ms.unwrap_or(0.0).max(0.0);
+    let mut content = serde_json::json!({
+        "size": body.map_or(entry.body_size, |b| b.len() as u64),
+        "mimeType": entry.mime_type.clone().unwrap_or_default(),
+    });
+    if let Some(body) = body {
+        content["text"] = body.as_str().into();
+    }
+    let mut json = serde_json::json!({
+        "pageref": PAGE_ID,
+        "startedDateTime": iso_time(entry.started_at),
+        "time": total,
+        "request": {
+            "method": entry.method,
+            "url": entry.url,
+            "httpVersion": version,
+            "cookies": [],
+            "headers": name_values(&entry.request_headers),
+            "queryString": name_values(&query),
+            "headersSize": -1,
+            "bodySize": -1,
+        },
+        "response": {
+            "status": entry.status.unwrap_or(0),
+            "statusText": entry.status_text,
+            "httpVersion": version,
+            "cookies": [],
+            "headers": name_values(&entry.response_headers),
+            "content": content,
+            "redirectURL": location.unwrap_or_default(),
+            "headersSize": -1,
+            "bodySize": entry.transfer_size.map_or(-1, |s| s as i64),
+        },
+        "cache": {},
+        "timings": timings(entry.timing.as_ref(), total),
+    });
+    if let Some(ip) = &entry.remote_ip {
+        json["serverIPAddress"] = ip.as_str().into();
+    }
+    if let Some(error) = &entry.error {
+        json["_error"] = error.as_str().into();
+    }
+    json
+}
+
+/// The HAR document for `page` and its `entries`; `bodies` by request id.
+pub fn build(page: &PageLoad, entries: &[Entry], bodies: &HashMap<String, String>) -> serde_json::Value {
+    // A redirect shares its request id with the request it led to, not its body
+    let body = |e: &Entry| bodies.get(&e.request_id).filter(|_| !e.status.is_some_and(|s| (300..400).contains(&s)));
+    let entries: Vec<serde_json::Value> = entries.iter().map(|e| entry_json(e, body(e))).collect();
+    serde_json::json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+            "pages": [{
+                "startedDateTime": iso_time(page.started_at),
+                "id": PAGE_ID,
+                "title": page.url,
+                "pageTimings": { "onContentLoad": -1, "onLoad": -1 },
+            }],
+            "entries": entries,
+        }
+    })
+}
+
+/// GET /har: snapshots the log and, if asked, fetches the bodies through
+/// the page task.
+pub fn export(tx: &CommandSender, log: &SharedNetworkLog, include_bodies: bool) -> CommandResult {
+    let (page, entries) = log.lock().unwrap().last_page().ok_or_else(|| CommandError::new(404, "no navigation recorded yet"))?;
+    let mut bodies = HashMap::new();
+    if include_bodies {
+        let ids: Vec<String> = entries
+            .iter()
+            .filter(|e| !e.failed && e.body_size <= MAX_BODY_BYTES && e.mime_type.as_deref().is_some_and(is_textual))
+            .map(|e| e.request_id.clone())
+            .collect();
+        let fetched = commands::send(tx, PageCommand::ResponseBodies { request_ids: ids })?;
+        if let Some(fetched) = fetched.as_object() {
+            bodies.extend(fetched.iter().filter_map(|(id, body)| Some((id.clone(), body.as_str()?.to_string()))));
+        }
+    }
+    Ok(build(&page, &entries, &bodies))
+}
+
+/// `{request_id: text}` for the bodies Chrome still holds as text.
+pub async fn response_bodies(page: &Page, request_ids: Vec<String>) -> CommandResult {
+    let mut bodies = serde_json::Map::new();
+    for id in request_ids {
+        let Ok(body) = page.execute(GetResponseBodyParams::new(id.clone())).await else { continue };
+        if !body.result.base64_encoded && body.result.body.len() as u64 <= MAX_BODY_BYTES {
+            bodies.insert(id, body.result.body.clone().into());
+        }
+    }
+    Ok(serde_json::Value::Object(bodies))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    /// Offsets in ms from requestTime; -1 for phases that didn't happen.
+    fn timing(dns: (f64, f64), connect: (f64, f64), ssl: (f64, f64), send: (f64, f64), headers_end: f64) -> ResourceTiming {
+        serde_json::from_value(serde_json::json!({
+            "requestTime": 1000.0,
+            "proxyStart": -1, "proxyEnd": -1,
+            "dnsStart": dns.0, "dnsEnd": dns.1,
+            "connectStart": connect.0, "connectEnd": connect.1,
+            "sslStart": ssl.0, "sslEnd": ssl.1,
+            "workerStart": -1, "workerReady": -1, "workerFetchStart": -1, "workerRespondWithSettled": -1,
+            "sendStart": send.0, "sendEnd": send.1,
+            "pushStart": 0, "pushEnd": 0,
+            "receiveHeadersStart": headers_end - 1.0, "receiveHeadersEnd": headers_end,
+        }))
+        .unwrap()
+    }
+
+    /// A document over a fresh HTTPS connection and a redirected POST
+    /// Chrome reported no timing for.
+    fn sample() -> (PageLoad, Vec<Entry>) {
+        let page = PageLoad { navigation: 1, url: "https://example.com/?q=1&lang=en".to_string(), started_at: 1_700_000_000_000 };
+
+        let mut document = Entry::sample("1", "GET", "https://example.com/?q=1&lang=en");
+        document.resource_type = Some("Document".to_string());
+        document.status = Some(200);
+        document.status_text = "OK".to_string();
+        document.mime_type = Some("text/html".to_string());
+        document.transfer_size = Some(1234);
+        document.body_size = 5000;
+        document.started_at = 1_700_000_000_000;
+        document.duration_ms = Some(120.5);
+        document.protocol = Some("h2".to_string());
+        document.remote_ip = Some("93.184.216.34".to_string());
+        document.request_headers = headers(&[("Accept", "text/html")]);
+        document.response_headers = headers(&[("Content-Type", "text/html"), ("Set-Cookie", "a=1"), ("Set-Cookie", "b=2")]);
+        document.timing = Some(timing((0.0, 10.0), (10.0, 30.0), (15.0, 30.0), (30.0, 31.0), 80.0));
+
+        let mut redirect = Entry::sample("2", "POST", "https://api.example.com/data");
+        redirect.status = Some(302);
+        redirect.status_text = "Found".to_string();
+        redirect.started_at = 1_700_000_000_250;
+        redirect.protocol = Some("http/1.1".to_string());
+        redirect.response_headers = headers(&[("Location", "https://api.example.com/done")]);
+
+        (page, vec![document, redirect])
+    }
+
+    #[test]
+    fn builds_a_har_log() {
+        let (page, entries) = sample();
+        let har = build(&page, &entries, &HashMap::new());
+        let log = &har["log"];
+        assert_eq!(log["version"], "1.2");
+        assert_eq!(log["creator"]["name"], env!("CARGO_PKG_NAME"));
+        assert_eq!(log["pages"][0]["id"], PAGE_ID);
+        assert_eq!(log["pages"][0]["title"], page.url);
+        assert_eq!(log["pages"][0]["startedDateTime"], "2023-11-14T22:13:20.000Z");
+        assert_eq!(log["entries"].as_array().unwrap().len(), 2);
+
+        let entry = &log["entries"][0];
+        assert_eq!(entry["pageref"], PAGE_ID);
+        assert_eq!(entry["startedDateTime"], "2023-11-14T22:13:20.000Z");
+        assert_eq!(entry["time"], 120.5);
+        assert_eq!(entry["serverIPAddress"], "93.184.216.34");
+
+        let request = &entry["request"];
+        assert_eq!(request["method"], "GET");
+        assert_eq!(request["url"], "https://example.com/?q=1&lang=en");
+        assert_eq!(request["httpVersion"], "HTTP/2.0");
+        assert_eq!(request["headers"], serde_json::json!([{"name": "Accept", "value": "text/html"}]));
+        assert_eq!(request["queryString"], serde_json::json!([{"name": "q", "value": "1"}, {"name": "lang", "value": "en"}]));
+        assert_eq!(request["headersSize"], -1);
+        assert_eq!(request["bodySize"], -1);
+
+        let response = &entry["response"];
+        assert_eq!(response["status"], 200);
+        assert_eq!(response["statusText"], "OK");
+        assert_eq!(response["headers"].as_array().unwrap().len(), 3);
+        assert_eq!(response["content"], serde_json::json!({"size": 5000, "mimeType": "text/html"}));
+        assert_eq!(response["redirectURL"], "");
+        assert_eq!(response["bodySize"], 1234);
+    }
+
+    #[test]
+    fn converts_resource_timing_to_phases() {
+        let (page, entries) = sample();
+        let har = build(&page, &entries, &HashMap::new());
+        let timings = &har["log"]["entries"][0]["timings"];
+        assert_eq!(*timings, serde_json::json!({"blocked": 0.0, "dns": 10.0, "connect": 20.0, "ssl": 15.0, "send": 1.0, "wait": 49.0, "receive": 40.5}));
+        // ssl is part of connect, so the rest adds up to the entry's time
+        let sum: f64 = ["blocked", "dns", "connect", "send", "wait", "receive"].iter().map(|k| timings[k].as_f64().unwrap()).sum();
+        assert_eq!(sum, 120.5);
+
+        // Without timing everything but the wait is unknown
+        let redirect = &har["log"]["entries"][1];
+        assert_eq!(redirect["time"], 0.0);
+        assert_eq!(redirect["timings"], serde_json::json!({"blocked": -1, "dns": -1, "connect": -1, "ssl": -1, "send": 0, "wait": 0.0, "receive": 0}));
+    }
+
+    #[test]
+    fn redirects_point_at_their_target_without_a_body() {
+        let (page, entries) = sample();
+        let bodies = HashMap::from([("1".to_string(), "<html></html>".to_string()), ("2".to_string(), "{}".to_string())]);
+        let har = build(&page, &entries, &bodies);
+
+        let document = &har["log"]["entries"][0]["response"]["content"];
+        assert_eq!(document["text"], "<html></html>");
+        assert_eq!(document["size"], 13);
+
+        let redirect = &har["log"]["entries"][1]["response"];
+        assert_eq!(redirect["redirectURL"], "https://api.example.com/done");
+        assert_eq!(redirect["bodySize"], -1);
+        assert!(redirect["content"].get("text").is_none());
+        assert_eq!(redirect["content"]["mimeType"], "");
+        assert_eq!(har["log"]["entries"][1]["request"]["httpVersion"], "HTTP/1.1");
+    }
+
+    #[test]
+    fn only_textual_bodies_are_fetched() {
+        for mime in ["text/html", "application/json", "application/javascript", "image/svg+xml", "TEXT/CSS"] {
+            assert!(is_textual(mime), "{}", mime);
+        }
+        for mime in ["image/png", "font/woff2", "application/octet-stream"] {
+            assert!(!is_textual(mime), "{}", mime);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::network::{Headers, SetExtraHttpHeadersParams};
+use chromiumoxide::Page;
+
+use crate::commands::CommandResult;
+
+// ============== Extra Request Headers ==============
+//
+// PUT /headers replaces the set of headers every request of the streamed
+// page carries (Network.setExtraHTTPHeaders), e.g. an Authorization token
+// for an internal dashboard or a tracing header; DELETE /headers clears it
+// and GET /headers shows it, with ?redact=true hiding the values. Names must
+// be HTTP tokens, and headers Chrome manages per connection or per body
+// (Host, Content-Length, the hop-by-hop ones) are refused. The set is kept
+// here only once Chrome accepted it.
+
+/// Headers that belong to the connection or the body, not to the page.
+const FORBIDDEN: &[&str] = &[
+    "host",
+    "content-length",
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "trailers",
+    "transfer-encoding",
+    "upgrade",
+];
+
+const REDACTED: &str = "***";
+
+pub type SharedHeaders = Arc<Mutex<BTreeMap<String, String>>>;
+
+fn is_token(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+/// Checks a PUT /headers body.
+pub fn validate(headers: &BTreeMap<String, String>) -> Result<(), String> {
+    for (name, value) in headers {
+        if !is_token(name) {
+            return Err(format!("invalid header name '{}'", name));
+        }
+        if FORBIDDEN.contains(&name.to_ascii_lowercase().as_str()) {
+            return Err(format!("{} is set by the browser and can't be overridden", name));
+        }
+        if value.chars().any(|c| c.is_control() && c != '\t') {
+            return Err(format!("value of {} contains control characters", name));
+        }
+    }
+    Ok(())
+}
+
+pub fn to_json(headers: &BTreeMap<String, String>, redact: bool) -> serde_json::Value {
+    let headers: BTreeMap<&str, &str> =
+        headers.iter().map(|(name, value)| (name.as_str(), if redact { REDACTED } else { value.as_str() })).collect();
+    serde_json::json!({ "headers": headers })
+}
+
+/// Replaces the page's extra headers; an empty map clears them.
+pub async fn apply(page: &Page, headers: &BTreeMap<String, String>) -> CommandResult {
+    page.execute(SetExtraHttpHeadersParams::new(Headers::new(serde_json::json!(headers)))).await?;
+    Ok(serde_json::json!({ "count": headers.len() }))
+}
@@ -0,0 +1,85 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::buffers::{self, Buffer, BufferStats};
+
+// ============== Navigation History ==============
+//
+// Top-level navigations per tab as the GUI navigation handler saw them:
+// allowed, blocked (with the matching filter) or redirected (with the
+// target). GET /history returns them oldest first, optionally for one tab
+// (?tab=ID); DELETE /history clears them. Only the newest
+// `--buffer-size history=N` entries (1000 by default) are kept.
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "outcome", rename_all = "lowercase")]
+pub enum Outcome {
+    Allowed,
+    Blocked { rule: String },
+    Redirected { to: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    /// None when the navigation could not be attributed to a tab.
+    pub tab: Option<usize>,
+    pub url: String,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    pub timestamp: u128,
+}
+
+#[derive(Default)]
+pub struct History {
+    entries: VecDeque<Entry>,
+    dropped: u64,
+}
+
+pub type SharedHistory = Arc<Mutex<History>>;
+
+impl History {
+    pub fn record(&mut self, tab: Option<usize>, url: &str, outcome: Outcome) {
+        while self.entries.len() >= Buffer::History.capacity() {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(Entry { tab, url: url.to_string(), outcome, timestamp: crate::now_millis() });
+    }
+
+    pub fn buffer_stats(&self) -> BufferStats {
+        buffers::stats(Buffer::History, &self.entries, self.dropped)
+    }
+
+    pub fn to_json(&self, tab: Option<usize>) -> serde_json::Value {
+        let entries: Vec<&Entry> = self.entries.iter().filter(|e| tab.is_none() || e.tab == tab).collect();
+        serde_json::json!({
+            "entries": entries,
+            "dropped": self.dropped,
+        })
+    }
+}
+
+/// GET and DELETE /history.
+pub fn handle(
+    request: &tiny_http::Request,
+    query: &HashMap<String, String>,
+    history: &SharedHistory,
+) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    match request.method() {
+        tiny_http::Method::Get => {
+            let tab = match query.get("tab").map(|t| t.parse::<usize>()) {
+                Some(Ok(tab)) => Some(tab),
+                Some(Err(_)) => return crate::json_response(400, &serde_json::json!({"error": "tab must be a tab id"})),
+                None => None,
+            };
+            crate::json_response(200, &history.lock().unwrap().to_json(tab))
+        }
+        tiny_http::Method::Delete => {
+            *history.lock().unwrap() = History::default();
+            crate::json_response(200, &serde_json::json!({"status": "cleared"}))
+        }
+        _ => crate::json_response(405, &serde_json::json!({"error": "method not allowed"})),
+    }
+}
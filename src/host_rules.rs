@@ -0,0 +1,188 @@
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+
+// ============== Host Remapping ==============
+//
+// Headless passes launch rules to Chrome's resolver and applies runtime
+// rules by rewriting request URLs in the Fetch interceptor. The GUI webview
+// has neither, so both kinds redirect top-level navigations to the target
+// there; the page then sees the target host.
+
+/// Maps a hostname (optionally `*.example.com`) to `host[:port]`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HostRule {
+    pub host: String,
+    pub target: String,
+}
+
+impl FromStr for HostRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, target) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected HOST=TARGET[:PORT], got '{}'", s))?;
+        HostRule { host: host.trim().to_ascii_lowercase(), target: target.trim().to_string() }.validated()
+    }
+}
+
+impl HostRule {
+    pub fn validated(self) -> Result<Self, String> {
+        let host_ok = !self.host.is_empty()
+            && self.host.trim_start_matches("*.").chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+        if !host_ok {
+            return Err(format!("invalid host '{}'", self.host));
+        }
+        let (target_host, port) = self.split_target();
+        if target_host.is_empty() || target_host.contains('/') || target_host.contains(' ') {
+            return Err(format!("invalid target '{}'", self.target));
+        }
+        if let Some(Err(_)) = port {
+            return Err(format!("invalid port in target '{}'", self.target));
+        }
+        Ok(self)
+    }
+
+    fn split_target(&self) -> (&str, Option<Result<u16, std::num::ParseIntError>>) {
+        let target = self.target.as_str();
+        let port_sep = if target.starts_with('[') {
+            target.find(']').and_then(|end| target[end..].find(':').map(|p| end + p))
+        } else {
+            target.rfind(':')
+        };
+        match port_sep {
+            Some(i) => (&target[..i], Some(target[i + 1..].parse())),
+            None => (target, None),
+        }
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        match self.host.strip_prefix("*.") {
+            Some(suffix) => host.ends_with(&format!(".{}", suffix)),
+            None => host == self.host,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HostRules {
+    /// Passed to Chrome as --host-resolver-rules; fixed for the browser lifetime.
+    launch: Vec<HostRule>,
+    /// Applied by rewriting request URLs in the Fetch interceptor.
+    runtime: Vec<HostRule>,
+}
+
+pub type SharedHostRules = Arc<Mutex<HostRules>>;
+
+impl HostRules {
+    pub fn new(launch: Vec<HostRule>) -> Self {
+        HostRules { launch, runtime: Vec::new() }
+    }
+
+    pub fn resolver_flag(&self) -> Option<String> {
+        if self.launch.is_empty() {
+            return None;
+        }
+        let maps: Vec<String> = self.launch.iter().map(|r| format!("MAP {} {}", r.host, r.target)).collect();
+        Some(format!("--host-resolver-rules={}", maps.join(", ")))
+    }
+
+    pub fn has_runtime_rules(&self) -> bool {
+        !self.runtime.is_empty()
+    }
+
+    /// Adds or replaces the runtime rule for `rule.host`.
+    pub fn add(&mut self, rule: HostRule) {
+        self.runtime.retain(|r| r.host != rule.host);
+        self.runtime.push(rule);
+    }
+
+    pub fn remove(&mut self, host: &str) -> bool {
+        let before = self.runtime.len();
+        self.runtime.retain(|r| r.host != host);
+        self.runtime.len() != before
+    }
+
+    pub fn runtime(&self) -> &[HostRule] {
+        &self.runtime
+    }
+
+    /// Replaces all runtime rules; launch rules are untouched.
+    pub fn set_runtime(&mut self, rules: Vec<HostRule>) {
+        self.runtime = rules;
+    }
+
+    pub fn is_launch_rule(&self, host: &str) -> bool {
+        self.launch.iter().any(|r| r.host == host)
+    }
+
+    /// Returns the rewritten URL and the original Host header value when a
+    /// runtime rule matches `url`.
+    pub fn rewrite(&self, url: &str) -> Option<(String, String)> {
+        let mut parsed = url::Url::parse(url).ok()?;
+        let host = parsed.host_str()?.to_ascii_lowercase();
+        let rule = self.runtime.iter().find(|r| r.matches(&host))?;
+
+        let host_header = parsed[url::Position::BeforeHost..url::Position::AfterPort].to_string();
+        retarget(&mut parsed, rule)?;
+        Some((parsed.to_string(), host_header))
+    }
+
+    /// GUI mode: the URL to load instead of `url` when any rule matches it.
+    pub fn redirect(&self, url: &str) -> Option<String> {
+        let mut parsed = url::Url::parse(url).ok()?;
+        let host = parsed.host_str()?.to_ascii_lowercase();
+        let rule = self.launch.iter().chain(&self.runtime).find(|r| r.matches(&host))?;
+        retarget(&mut parsed, rule)?;
+        Some(parsed.to_string())
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "launch": self.launch,
+            "runtime": self.runtime,
+        })
+    }
+}
+
+fn retarget(parsed: &mut url::Url, rule: &HostRule) -> Option<()> {
+    let (target_host, port) = rule.split_target();
+    parsed.set_host(Some(target_host)).ok()?;
+    if let Some(Ok(port)) = port {
+        parsed.set_port(Some(port)).ok()?;
+    }
+    Some(())
+}
+
+/// GET, POST and DELETE /host-rules.
+pub fn handle(
+    request: &mut tiny_http::Request,
+    query: &std::collections::HashMap<String, String>,
+    rules: &SharedHostRules,
+) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let method = request.method().clone();
+    match method {
+        tiny_http::Method::Get => crate::json_response(200, &rules.lock().unwrap().to_json()),
+        tiny_http::Method::Post => match crate::read_json_body::<HostRule>(request).and_then(|r| r.validated()) {
+            Ok(rule) => {
+                let mut rules = rules.lock().unwrap();
+                rules.add(rule);
+                crate::json_response(200, &rules.to_json())
+            }
+            Err(e) => crate::json_response(400, &serde_json::json!({"error": e})),
+        },
+        tiny_http::Method::Delete => {
+            let host = query.get("host").map(|h| h.to_ascii_lowercase()).unwrap_or_default();
+            let mut rules = rules.lock().unwrap();
+            if rules.remove(&host) {
+                crate::json_response(200, &rules.to_json())
+            } else if rules.is_launch_rule(&host) {
+                crate::json_response(409, &serde_json::json!({"error": "launch rules are fixed for the browser lifetime"}))
+            } else {
+                crate::json_response(404, &serde_json::json!({"error": "no runtime rule for host"}))
+            }
+        }
+        _ => crate::json_response(405, &serde_json::json!({"error": "method not allowed"})),
+    }
+}
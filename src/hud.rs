@@ -0,0 +1,147 @@
+use std::sync::{Arc, Mutex};
+
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+use crate::overlay::{self, Color};
+use crate::ticker::Position;
+
+// ============== HUD Strip ==============
+//
+// With --hud, a one-line strip with the current URL, load state and clock is
+// composited onto live frames, for kiosk consumers that show the raw stream
+// without the viewer. It is drawn Rust-side like the ticker, never injected
+// into the page, so CSP-strict pages work and layout is untouched. Frames
+// used for settling and /render are taken without overlays, so the ticking
+// clock never shows up as a page change there.
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Item {
+    Url,
+    Load,
+    Clock,
+}
+
+pub struct Hud {
+    enabled: bool,
+    items: Vec<Item>,
+    position: Position,
+    fg: Color,
+    bg: Color,
+    /// document.readyState of the streamed page, polled by the capture loop.
+    load_state: String,
+}
+
+pub type SharedHud = Arc<Mutex<Hud>>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HudRequest {
+    pub enabled: Option<bool>,
+    pub items: Option<Vec<Item>>,
+    pub position: Option<Position>,
+}
+
+/// `HH:MM:SS UTC` of a Unix timestamp in milliseconds.
+fn clock(millis: u128) -> String {
+    let secs = (millis / 1000) as u64;
+    format!("{:02}:{:02}:{:02} UTC", secs / 3600 % 24, secs / 60 % 60, secs % 60)
+}
+
+impl Hud {
+    pub fn new(enabled: bool, items: Vec<Item>, position: Position, fg: Color, bg: Color) -> Self {
+        Hud { enabled, items, position, fg, bg, load_state: "loading".to_string() }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.items.is_empty()
+    }
+
+    /// True when the capture loop should keep `load_state` up to date.
+    pub fn wants_load_state(&self) -> bool {
+        self.is_active() && self.items.contains(&Item::Load)
+    }
+
+    pub fn set_load_state(&mut self, state: String) {
+        self.load_state = state;
+    }
+
+    pub fn update(&mut self, req: HudRequest) {
+        if let Some(enabled) = req.enabled {
+            self.enabled = enabled;
+        }
+        if let Some(items) = req.items {
+            self.items = items;
+        }
+        if let Some(position) = req.position {
+            self.position = position;
+        }
+    }
+
+    /// The settings as a POST /hud body.
+    pub fn settings(&self) -> HudRequest {
+        HudRequest { enabled: Some(self.enabled), items: Some(self.items.clone()), position: Some(self.position) }
+    }
+
+    fn text(&self, url: &str) -> String {
+        self.items
+            .iter()
+            .map(|item| match item {
+                Item::Url => url.to_string(),
+                Item::Load => self.load_state.clone(),
+                Item::Clock => clock(crate::now_millis()),
+            })
+            .collect::<Vec<_>>()
+            .join("  |  ")
+    }
+
+    pub fn render(&self, img: &mut RgbImage, url: &str) {
+        if !self.is_active() {
+            return;
+        }
+        let scale = if img.width() >= 1000 { 2 } else { 1 };
+        let pad = 4 * scale;
+        let height = overlay::GLYPH_HEIGHT * scale + 2 * pad;
+        let max_chars = ((img.width() as i64 - 2 * pad) / (overlay::GLYPH_WIDTH * scale)).max(0) as usize;
+        let text = self.text(url);
+        // Keep the tail (load state, clock) visible and shorten the URL instead
+        let text: String = if text.chars().count() > max_chars {
+            let skip = text.chars().count() - max_chars.saturating_sub(3);
+            "...".chars().chain(text.chars().skip(skip)).collect()
+        } else {
+            text
+        };
+        let y = match self.position {
+            Position::Top => 0,
+            Position::Bottom => img.height() as i64 - height,
+        };
+        overlay::fill_rect(img, 0, y, img.width() as i64, height, self.bg);
+        overlay::draw_text(img, pad, y + pad, &text, scale, self.fg);
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "enabled": self.enabled,
+            "items": self.items,
+            "position": self.position,
+            "load_state": self.load_state,
+        })
+    }
+}
+
+/// Serves `/hud`: GET the settings, POST a partial update.
+pub fn handle(request: &mut tiny_http::Request, hud: &SharedHud) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let method = request.method().clone();
+    match method {
+        tiny_http::Method::Get => crate::json_response(200, &hud.lock().unwrap().to_json()),
+        tiny_http::Method::Post => match crate::read_json_body::<HudRequest>(request) {
+            Ok(req) => {
+                let mut hud = hud.lock().unwrap();
+                hud.update(req);
+                crate::json_response(200, &hud.to_json())
+            }
+            Err(e) => crate::json_response(400, &serde_json::json!({"error": e})),
+        },
+        _ => crate::json_response(405, &serde_json::json!({"error": "method not allowed"})),
+    }
+}
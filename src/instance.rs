@@ -0,0 +1,178 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+// ============== Instance Discovery ==============
+//
+// GET /instance describes this process for fleet dashboards: a stable id
+// (generated on first run and kept in --instance-id-file), hostname,
+// version, mode and current URL. The same payload is POSTed as a heartbeat
+// to --registry-url, and --announce advertises the instance over mDNS as
+// _rustbrowser._tcp with mode, URL and port in the TXT record.
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+#[cfg(feature = "announce")]
+const SERVICE_TYPE: &str = "_rustbrowser._tcp.local.";
+/// How often the mDNS TXT record is checked against the current URL.
+#[cfg(feature = "announce")]
+const ANNOUNCE_REFRESH: Duration = Duration::from_secs(5);
+
+pub type UrlSource = Box<dyn Fn() -> String + Send + Sync>;
+
+pub struct Instance {
+    pub id: String,
+    pub hostname: String,
+    pub mode: &'static str,
+    pub port: u16,
+    started_at: u128,
+    url: UrlSource,
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Per-port default, so several instances on one host keep distinct ids.
+fn default_id_file(port: u16) -> PathBuf {
+    crate::config_dir().join(format!("instance-{}.id", port))
+}
+
+fn load_or_create_id(path: &Path, hostname: &str, port: u16) -> std::io::Result<String> {
+    if let Ok(id) = std::fs::read_to_string(path) {
+        let id = id.trim();
+        if !id.is_empty() {
+            return Ok(id.to_string());
+        }
+    }
+    let seed = format!("{}|{}|{}|{}", hostname, port, std::process::id(), crate::now_millis());
+    let hash = Sha256::digest(seed.as_bytes());
+    let hex: String = hash[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    let id = format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, &id)?;
+    log_info!("Generated instance id {} ({})", id, path.display());
+    Ok(id)
+}
+
+impl Instance {
+    pub fn load(id_file: Option<PathBuf>, mode: &'static str, port: u16, url: UrlSource) -> Result<Self, String> {
+        let hostname = hostname();
+        let path = id_file.unwrap_or_else(|| default_id_file(port));
+        let id = load_or_create_id(&path, &hostname, port)
+            .map_err(|e| format!("failed to persist instance id in {}: {}", path.display(), e))?;
+        Ok(Instance { id, hostname, mode, port, started_at: crate::now_millis(), url })
+    }
+
+    pub fn url(&self) -> String {
+        (self.url)()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "hostname": self.hostname,
+            "version": env!("CARGO_PKG_VERSION"),
+            "mode": self.mode,
+            "port": self.port,
+            "url": self.url(),
+            "started_at": self.started_at,
+        })
+    }
+}
+
+/// POSTs `/instance` to `registry` every `interval`; failures are retried
+/// with exponential backoff up to the interval.
+pub fn start_heartbeat(instance: Arc<Instance>, registry: String, interval: Duration) {
+    log_info!("Heartbeat:   {} every {}s", registry, interval.as_secs());
+    thread::spawn(move || {
+        let mut backoff = MIN_BACKOFF;
+        let mut failing = false;
+        loop {
+            let result = ureq::post(&registry)
+                .timeout(Duration::from_secs(10))
+                .send_json(instance.to_json());
+            match result {
+                Ok(_) => {
+                    if failing {
+                        log_info!("Registry {} reachable again", registry);
+                    }
+                    failing = false;
+                    backoff = MIN_BACKOFF;
+                    thread::sleep(interval);
+                }
+                Err(e) => {
+                    // Log only the first failure of a streak
+                    if !failing {
+                        log_error!("Registry heartbeat to {} failed, retrying: {}", registry, e);
+                    }
+                    failing = true;
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(interval);
+                }
+            }
+        }
+    });
+}
+
+/// TXT values are limited to 255 bytes.
+#[cfg(feature = "announce")]
+fn txt_value(value: &str) -> String {
+    let mut end = value.len().min(255);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    value[..end].to_string()
+}
+
+#[cfg(feature = "announce")]
+pub fn announce(instance: Arc<Instance>) -> Result<(), String> {
+    let daemon = mdns_sd::ServiceDaemon::new().map_err(|e| format!("failed to start mDNS: {}", e))?;
+    let host = format!("{}.local.", instance.hostname.trim_end_matches(".local"));
+    let name = format!("{}-{}", instance.hostname, instance.port);
+    let current = instance.clone();
+    let register = move |url: &str| -> Result<(), String> {
+        let port = instance.port.to_string();
+        let url = txt_value(url);
+        let properties = [
+            ("id", instance.id.as_str()),
+            ("mode", instance.mode),
+            ("port", port.as_str()),
+            ("url", url.as_str()),
+        ];
+        let info = mdns_sd::ServiceInfo::new(SERVICE_TYPE, &name, &host, "", instance.port, &properties[..])
+            .map_err(|e| e.to_string())?
+            .enable_addr_auto();
+        daemon.register(info).map_err(|e| e.to_string())
+    };
+    let mut announced = current.url();
+    register(&announced).map_err(|e| format!("failed to announce over mDNS: {}", e))?;
+    log_info!("Announcing {} over mDNS", SERVICE_TYPE);
+    thread::spawn(move || loop {
+        thread::sleep(ANNOUNCE_REFRESH);
+        let url = current.url();
+        // Re-registering the same name updates the TXT record
+        if url != announced {
+            match register(&url) {
+                Ok(()) => announced = url,
+                Err(e) => log_error!("Failed to update mDNS record: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "announce"))]
+pub fn announce(_instance: Arc<Instance>) -> Result<(), String> {
+    Err("--announce needs a build with `--features announce`".to_string())
+}
@@ -0,0 +1,149 @@
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    ContinueRequestParams, EnableParams, EventAuthRequired, EventRequestPaused, FailRequestParams, HeaderEntry,
+    RequestPattern, RequestStage,
+};
+use chromiumoxide::cdp::browser_protocol::network::{ErrorReason, ResourceType};
+use chromiumoxide::cdp::browser_protocol::page::FrameId;
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use futures::StreamExt;
+
+use crate::blocker::RequestInfo;
+use crate::HeadlessState;
+
+// ============== Request Interception (headless) ==============
+
+/// Enables the Fetch domain on `page` and decides every paused request and
+/// auth challenge (see basic_auth). Must be installed before the first
+/// navigation so nothing slips through.
+pub async fn install(
+    page: &Page,
+    state: HeadlessState,
+) -> Result<tokio::task::JoinHandle<()>, Box<dyn std::error::Error>> {
+    let main_frame = page.mainframe().await?;
+    let mut paused = page.event_listener::<EventRequestPaused>().await?;
+    let mut challenges = page.event_listener::<EventAuthRequired>().await?;
+    let page_task = page.clone();
+
+    let handle = tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                Some(event) = paused.next() => {
+                    if let Err(e) = handle_paused(&page_task, &state, main_frame.as_ref(), &event).await {
+                        log_error!("Interception error for {}: {}", event.request.url, e);
+                    }
+                }
+                Some(event) = challenges.next() => {
+                    if let Err(e) = crate::basic_auth::answer(&page_task, &state.basic_auth, &event).await {
+                        log_error!("Failed to answer auth challenge for {}: {}", event.auth_challenge.origin, e);
+                    }
+                }
+                else => break,
+            }
+        }
+    });
+
+    page.execute(
+        EnableParams::builder()
+            .pattern(
+                RequestPattern::builder()
+                    .url_pattern("*")
+                    .request_stage(RequestStage::Request)
+                    .build(),
+            )
+            .handle_auth_requests(true)
+            .build(),
+    )
+    .await?;
+
+    Ok(handle)
+}
+
+async fn handle_paused(
+    page: &Page,
+    state: &HeadlessState,
+    main_frame: Option<&FrameId>,
+    event: &EventRequestPaused,
+) -> Result<(), CdpError> {
+    let is_main_frame = main_frame == Some(&event.frame_id);
+    let page_host = url::Url::parse(&state.current_url.lock().unwrap())
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_default();
+
+    // The page's own document stays reachable whatever the patterns say
+    let is_document = is_main_frame && event.resource_type == ResourceType::Document;
+    if !is_document && state.url_blocks.lock().unwrap().check(&event.request.url).is_some() {
+        page.execute(FailRequestParams::new(event.request_id.clone(), ErrorReason::BlockedByClient))
+            .await?;
+        return Ok(());
+    }
+
+    let mock = state.mocks.lock().unwrap().check(&event.request.method, &event.request.url);
+    if let Some(response) = mock {
+        // Delayed answers must not hold up the requests behind them
+        let (page, request_id, url) = (page.clone(), event.request_id.clone(), event.request.url.clone());
+        tokio::spawn(async move {
+            if let Err(e) = crate::mock::fulfill(&page, request_id, response).await {
+                log_error!("Failed to mock {}: {}", url, e);
+            }
+        });
+        return Ok(());
+    }
+
+    let blocked_by = state.blocker.lock().unwrap().check(&RequestInfo {
+        url: &event.request.url,
+        resource_type: abp_resource_type(&event.resource_type, is_main_frame),
+        page_host: &page_host,
+    });
+    if blocked_by.is_some() {
+        page.execute(FailRequestParams::new(event.request_id.clone(), ErrorReason::BlockedByClient))
+            .await?;
+        return Ok(());
+    }
+
+    let rewrite = state.host_rules.lock().unwrap().rewrite(&event.request.url);
+    if let Some((new_url, host_header)) = rewrite {
+        let mut headers: Vec<HeaderEntry> = event
+            .request
+            .headers
+            .inner()
+            .as_object()
+            .map(|map| {
+                map.iter()
+                    .filter(|(name, _)| !name.eq_ignore_ascii_case("host"))
+                    .map(|(name, value)| HeaderEntry::new(name.clone(), value.as_str().unwrap_or_default()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        headers.push(HeaderEntry::new("Host", host_header));
+
+        let params = ContinueRequestParams::builder()
+            .request_id(event.request_id.clone())
+            .url(new_url)
+            .headers(headers)
+            .build()
+            .map_err(CdpError::msg)?;
+        page.execute(params).await?;
+        return Ok(());
+    }
+
+    page.execute(ContinueRequestParams::new(event.request_id.clone())).await?;
+    Ok(())
+}
+
+fn abp_resource_type(resource_type: &ResourceType, is_main_frame: bool) -> &'static str {
+    match resource_type {
+        ResourceType::Document if is_main_frame => "document",
+        ResourceType::Document => "subdocument",
+        ResourceType::Stylesheet => "stylesheet",
+        ResourceType::Image => "image",
+        ResourceType::Media => "media",
+        ResourceType::Font => "font",
+        ResourceType::Script => "script",
+        ResourceType::Xhr | ResourceType::Fetch | ResourceType::EventSource => "xmlhttprequest",
+        ResourceType::WebSocket => "websocket",
+        ResourceType::Ping | ResourceType::CspViolationReport => "ping",
+        _ => "other",
+    }
+}
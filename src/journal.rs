@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+
+use crate::buffers::{self, Buffer, BufferStats};
+
+// ============== Activity Journal ==============
+
+/// A single semantically meaningful user action. The same shape is used for
+/// actions captured from the GUI and for actions issued through the HTTP API,
+/// so a journal recorded by hand can be replayed against a headless page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum JournalAction {
+    Navigate { url: String },
+    Click { selector: String, x: f64, y: f64 },
+    Type { selector: String, value: String },
+    Change { selector: String, value: String },
+    Key { selector: String, key: String, #[serde(default)] modifiers: Vec<String> },
+    Scroll { x: f64, y: f64 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalEntry {
+    pub seq: u64,
+    pub timestamp: u128,
+    pub url: String,
+    #[serde(flatten)]
+    pub action: JournalAction,
+}
+
+/// Replaces typed values whose selector mentions one of the patterns
+/// (case-insensitive), before an entry is stored anywhere.
+#[derive(Debug, Clone, Default)]
+pub struct Redaction {
+    patterns: Vec<String>,
+}
+
+pub const REDACTED: &str = "[redacted]";
+
+impl Redaction {
+    pub fn new(patterns: &[String]) -> Self {
+        Redaction {
+            patterns: patterns
+                .iter()
+                .map(|p| p.trim().to_lowercase())
+                .filter(|p| !p.is_empty())
+                .collect(),
+        }
+    }
+
+    fn apply(&self, action: &mut JournalAction) {
+        if let JournalAction::Type { selector, value } | JournalAction::Change { selector, value } = action {
+            let selector = selector.to_lowercase();
+            if self.patterns.iter().any(|p| selector.contains(p.as_str())) {
+                *value = REDACTED.to_string();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Journal {
+    /// The newest `--buffer-size journal=N` entries.
+    entries: VecDeque<JournalEntry>,
+    next_seq: u64,
+    dropped: u64,
+    redaction: Redaction,
+}
+
+pub type SharedJournal = Arc<Mutex<Journal>>;
+
+impl Journal {
+    pub fn new(redaction: Redaction) -> Self {
+        Journal { redaction, ..Default::default() }
+    }
+
+    /// Stores `action` after redaction and returns the stored entry.
+    pub fn record(&mut self, url: &str, mut action: JournalAction) -> &JournalEntry {
+        self.redaction.apply(&mut action);
+        self.next_seq += 1;
+        while self.entries.len() >= Buffer::Journal.capacity() {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(JournalEntry {
+            seq: self.next_seq,
+            timestamp: crate::now_millis(),
+            url: url.to_string(),
+            action,
+        });
+        self.entries.back().unwrap()
+    }
+
+    pub fn entries(&self) -> &VecDeque<JournalEntry> {
+        &self.entries
+    }
+
+    pub fn buffer_stats(&self) -> BufferStats {
+        buffers::stats(Buffer::Journal, &self.entries, self.dropped)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// One JSON object per line, suitable for streaming into a replay tool.
+    pub fn to_jsonl(&self) -> String {
+        self.entries
+            .iter()
+            .filter_map(|e| serde_json::to_string(e).ok())
+            .map(|line| line + "\n")
+            .collect()
+    }
+}
+
+// ============== GUI Recorder Script ==============
+
+pub const RECORDER_SCRIPT: &str = r#"
+(function() {
+    if (window.__rbRecorderInstalled) return;
+    window.__rbRecorderInstalled = true;
+
+    const STABLE_ATTRS = ['data-testid', 'data-test-id', 'data-test', 'data-qa', 'data-cy', 'data-id', 'name'];
+
+    function esc(value) {
+        if (window.CSS && CSS.escape) return CSS.escape(value);
+        return value.replace(/([^\w-])/g, '\\$1');
+    }
+
+    function isUnique(selector) {
+        try { return document.querySelectorAll(selector).length === 1; } catch (e) { return false; }
+    }
+
+    // Ids produced by frameworks (react-select-3-input, ember1234, :r0:) change between loads.
+    function looksGenerated(id) {
+        return /^\d|\d{3,}|:|[a-f0-9]{8,}/i.test(id);
+    }
+
+    function stepFor(node) {
+        const tag = node.tagName.toLowerCase();
+        if (node.id && !looksGenerated(node.id)) {
+            return { token: '#' + esc(node.id), anchor: true };
+        }
+        for (const attr of STABLE_ATTRS) {
+            const value = node.getAttribute(attr);
+            if (value && !looksGenerated(value)) {
+                return { token: tag + '[' + attr + '="' + value.replace(/"/g, '\\"') + '"]', anchor: false };
+            }
+        }
+        const parent = node.parentElement;
+        if (!parent) return { token: tag, anchor: false };
+        const sameTag = Array.prototype.filter.call(parent.children, function(c) { return c.tagName === node.tagName; });
+        if (sameTag.length === 1) return { token: tag, anchor: false };
+        return { token: tag + ':nth-of-type(' + (sameTag.indexOf(node) + 1) + ')', anchor: false };
+    }
+
+    // Prefers ids, then stable data-* attributes, then nth-of-type paths,
+    // stopping at the shortest suffix that is unique in the document.
+    window.__rbSelector = function(el) {
+        if (!el || el.nodeType !== 1) return null;
+        const parts = [];
+        let node = el;
+        while (node && node.nodeType === 1) {
+            const step = stepFor(node);
+            parts.unshift(step.token);
+            const selector = parts.join(' > ');
+            if (isUnique(selector)) return selector;
+            if (node === document.documentElement) return selector;
+            node = node.parentElement;
+        }
+        return parts.join(' > ');
+    };
+
+    function inToolbar(el) {
+        return el && el.closest && el.closest('#__rust_browser_toolbar__');
+    }
+
+    function post(record) {
+        record.url = location.href;
+        window.ipc.postMessage(JSON.stringify({ record: record }));
+    }
+
+    function valueOf(el) {
+        if (el.type === 'password') return '***';
+        if (el.type === 'checkbox' || el.type === 'radio') return String(el.checked);
+        if (el.isContentEditable) return el.innerText;
+        return el.value;
+    }
+
+    document.addEventListener('click', function(e) {
+        if (inToolbar(e.target)) return;
+        const selector = window.__rbSelector(e.target);
+        if (selector) post({ action: 'click', selector: selector, x: e.clientX, y: e.clientY });
+    }, true);
+
+    const pendingInput = new Map();
+    document.addEventListener('input', function(e) {
+        const el = e.target;
+        if (inToolbar(el) || el.type === 'checkbox' || el.type === 'radio' || el.tagName === 'SELECT') return;
+        clearTimeout(pendingInput.get(el));
+        pendingInput.set(el, setTimeout(function() {
+            pendingInput.delete(el);
+            const selector = window.__rbSelector(el);
+            if (selector) post({ action: 'type', selector: selector, value: valueOf(el) });
+        }, 400));
+    }, true);
+
+    document.addEventListener('change', function(e) {
+        const el = e.target;
+        if (inToolbar(el)) return;
+        if (el.tagName !== 'SELECT' && el.type !== 'checkbox' && el.type !== 'radio' && el.type !== 'file') return;
+        const selector = window.__rbSelector(el);
+        if (selector) post({ action: 'change', selector: selector, value: valueOf(el) });
+    }, true);
+
+    const NAV_KEYS = ['Enter', 'Tab', 'Escape', 'ArrowUp', 'ArrowDown', 'ArrowLeft', 'ArrowRight', 'PageUp', 'PageDown', 'Home', 'End'];
+    document.addEventListener('keydown', function(e) {
+        if (inToolbar(e.target) || NAV_KEYS.indexOf(e.key) === -1) return;
+        const modifiers = [];
+        if (e.ctrlKey) modifiers.push('ctrl');
+        if (e.altKey) modifiers.push('alt');
+        if (e.shiftKey) modifiers.push('shift');
+        if (e.metaKey) modifiers.push('meta');
+        const selector = window.__rbSelector(e.target) || 'body';
+        post({ action: 'key', selector: selector, key: e.key, modifiers: modifiers });
+    }, true);
+
+    let scrollTimer = null;
+    window.addEventListener('scroll', function() {
+        clearTimeout(scrollTimer);
+        scrollTimer = setTimeout(function() {
+            post({ action: 'scroll', x: window.scrollX, y: window.scrollY });
+        }, 250);
+    }, true);
+})();
+"#;
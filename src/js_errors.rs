@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::js_protocol::runtime::{EnableParams, EventExceptionThrown};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::buffers::{self, Buffer, BufferStats};
+
+// ============== JavaScript Exceptions ==============
+//
+// A page that throws while loading often just looks blank in the stream.
+// Uncaught exceptions of the streamed page (Runtime.exceptionThrown) are
+// kept with their message, stack, script URL and time: GET /errors lists
+// them oldest first, DELETE /errors empties the list so test runs start
+// clean (a controller's job, like clearing /network), and /status carries js_error_count and the most recent
+// one. Only the newest `--buffer-size errors=N` (100 by default) are kept;
+// js_error_count counts every exception since start or the last clear.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JsError {
+    pub message: String,
+    pub stack: Option<String>,
+    pub url: Option<String>,
+    pub line: i64,
+    pub column: i64,
+    pub timestamp: u128,
+}
+
+#[derive(Default)]
+pub struct JsErrors {
+    entries: VecDeque<JsError>,
+    count: u64,
+    dropped: u64,
+}
+
+pub type SharedJsErrors = Arc<Mutex<JsErrors>>;
+
+impl JsErrors {
+    fn record(&mut self, error: JsError) {
+        while self.entries.len() >= Buffer::Errors.capacity() {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(error);
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn last(&self) -> Option<&JsError> {
+        self.entries.back()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.count = 0;
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "errors": self.entries, "count": self.count, "dropped": self.dropped })
+    }
+
+    pub fn buffer_stats(&self) -> BufferStats {
+        buffers::stats(Buffer::Errors, &self.entries, self.dropped)
+    }
+}
+
+fn describe(event: &EventExceptionThrown) -> JsError {
+    let details = &event.exception_details;
+    let description = details.exception.as_ref().and_then(|e| e.description.clone());
+    // An Error's description is "Name: message" followed by its stack
+    let message = description
+        .as_deref()
+        .and_then(|d| d.lines().next())
+        .map(str::to_string)
+        .unwrap_or_else(|| details.text.clone());
+    let stack = details.stack_trace.as_ref().filter(|s| !s.call_frames.is_empty()).map(|trace| {
+        trace
+            .call_frames
+            .iter()
+            .map(|f| {
+                let name = if f.function_name.is_empty() { "<anonymous>" } else { f.function_name.as_str() };
+                format!("at {} ({}:{}:{})", name, f.url, f.line_number + 1, f.column_number + 1)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+    JsError {
+        message,
+        stack,
+        url: details.url.clone().filter(|u| !u.is_empty()),
+        line: details.line_number + 1,
+        column: details.column_number + 1,
+        timestamp: crate::now_millis(),
+    }
+}
+
+/// Collects the uncaught exceptions of `page`.
+pub async fn watch(page: &Page, errors: SharedJsErrors) -> Result<tokio::task::JoinHandle<()>, CdpError> {
+    let mut exceptions = page.event_listener::<EventExceptionThrown>().await?;
+    page.execute(EnableParams::default()).await?;
+    Ok(tokio::spawn(async move {
+        while let Some(event) = exceptions.next().await {
+            errors.lock().unwrap().record(describe(&event));
+        }
+    }))
+}
@@ -0,0 +1,228 @@
+use chromiumoxide::cdp::browser_protocol::input::{DispatchKeyEventParams, DispatchKeyEventType};
+use chromiumoxide::Page;
+use serde::Deserialize;
+
+use crate::commands::CommandError;
+
+// ============== Key Presses ==============
+//
+// POST /press sends one key to the focused element: a named key from NAMED
+// (Enter, Tab, arrows, F1-F12...) or a single character, with optional
+// modifiers. Names are matched case-insensitively. Each press is a keyDown
+// and a keyUp carrying the modifiers bitmask; a character without ctrl, alt
+// or meta also carries its text, so it is typed, while a combination like
+// ctrl+a is only a shortcut. Modifier keys themselves are not pressed.
+// The key may also be a chord like `Control+Shift+A`, whose leading parts
+// are modifiers on top of `modifiers`; `Control++` presses `+`.
+
+#[derive(Debug, Deserialize)]
+pub struct PressRequest {
+    pub key: String,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+}
+
+/// A validated /press.
+#[derive(Debug, Clone)]
+pub struct KeyPress {
+    key: String,
+    code: String,
+    key_code: i64,
+    text: Option<String>,
+    modifiers: i64,
+    names: Vec<String>,
+}
+
+/// (key, code, windowsVirtualKeyCode, text)
+const NAMED: &[(&str, &str, i64, &str)] = &[
+    ("Enter", "Enter", 13, "\r"),
+    ("Tab", "Tab", 9, ""),
+    ("Escape", "Escape", 27, ""),
+    ("Backspace", "Backspace", 8, ""),
+    ("Delete", "Delete", 46, ""),
+    ("Insert", "Insert", 45, ""),
+    ("ArrowLeft", "ArrowLeft", 37, ""),
+    ("ArrowUp", "ArrowUp", 38, ""),
+    ("ArrowRight", "ArrowRight", 39, ""),
+    ("ArrowDown", "ArrowDown", 40, ""),
+    ("PageUp", "PageUp", 33, ""),
+    ("PageDown", "PageDown", 34, ""),
+    ("Home", "Home", 36, ""),
+    ("End", "End", 35, ""),
+    ("F1", "F1", 112, ""),
+    ("F2", "F2", 113, ""),
+    ("F3", "F3", 114, ""),
+    ("F4", "F4", 115, ""),
+    ("F5", "F5", 116, ""),
+    ("F6", "F6", 117, ""),
+    ("F7", "F7", 118, ""),
+    ("F8", "F8", 119, ""),
+    ("F9", "F9", 120, ""),
+    ("F10", "F10", 121, ""),
+    ("F11", "F11", 122, ""),
+    ("F12", "F12", 123, ""),
+];
+
+/// (name, CDP modifier bit)
+const MODIFIERS: &[(&str, i64)] = &[("alt", 1), ("ctrl", 2), ("control", 2), ("meta", 4), ("cmd", 4), ("shift", 8)];
+
+const SHORTCUT_MODIFIERS: i64 = 1 | 2 | 4;
+const SHIFT: i64 = 8;
+
+/// `Control+Shift+A` → (["Control", "Shift"], "A"); a single character,
+/// `+` included, or a name without `+` is just the key.
+fn split_chord(key: &str) -> (Vec<&str>, &str) {
+    if key.chars().count() <= 1 {
+        return (Vec::new(), key);
+    }
+    let (chord, key) = match key.strip_suffix("++") {
+        Some(chord) => (chord, "+"),
+        None => match key.rsplit_once('+') {
+            Some(split) => split,
+            None => return (Vec::new(), key),
+        },
+    };
+    (chord.split('+').collect(), key)
+}
+
+impl PressRequest {
+    pub fn parse(self) -> Result<KeyPress, String> {
+        let (chord, key) = split_chord(&self.key);
+        let mut modifiers = 0;
+        let mut names = Vec::new();
+        for name in self.modifiers.iter().map(String::as_str).chain(chord) {
+            let lower = name.to_ascii_lowercase();
+            let (_, bit) = MODIFIERS
+                .iter()
+                .find(|(m, _)| *m == lower)
+                .ok_or_else(|| format!("unknown modifier '{}', expected alt, ctrl, meta or shift", name))?;
+            if modifiers & bit == 0 {
+                names.push(lower);
+            }
+            modifiers |= bit;
+        }
+
+        let mut chars = key.chars();
+        let press = match (chars.next(), chars.next()) {
+            (Some(c), None) if !c.is_control() => {
+                let (code, key_code) = match c {
+                    'a'..='z' | 'A'..='Z' => (format!("Key{}", c.to_ascii_uppercase()), c.to_ascii_uppercase() as i64),
+                    '0'..='9' => (format!("Digit{}", c), c as i64),
+                    ' ' => ("Space".to_string(), 32),
+                    _ => (String::new(), 0),
+                };
+                let key = if modifiers & SHIFT != 0 { c.to_uppercase().to_string() } else { c.to_string() };
+                let text = (modifiers & SHORTCUT_MODIFIERS == 0).then(|| key.clone());
+                KeyPress { key, code, key_code, text, modifiers, names }
+            }
+            _ => {
+                let (key, code, key_code, text) = NAMED
+                    .iter()
+                    .find(|(name, ..)| name.eq_ignore_ascii_case(key))
+                    .ok_or_else(|| format!("unknown key '{}', expected a single character or a name like Enter, Tab or ArrowDown", key))?;
+                let text = (!text.is_empty() && modifiers & SHORTCUT_MODIFIERS == 0).then(|| text.to_string());
+                KeyPress { key: key.to_string(), code: code.to_string(), key_code: *key_code, text, modifiers, names }
+            }
+        };
+        Ok(press)
+    }
+}
+
+impl KeyPress {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn modifier_names(&self) -> &[String] {
+        &self.names
+    }
+}
+
+/// Dispatches the keyDown (rawKeyDown without text) and keyUp pair.
+pub async fn press(page: &Page, press: &KeyPress) -> Result<(), CommandError> {
+    let kind = if press.text.is_some() { DispatchKeyEventType::KeyDown } else { DispatchKeyEventType::RawKeyDown };
+    let mut down = DispatchKeyEventParams::builder()
+        .r#type(kind)
+        .key(press.key.clone())
+        .code(press.code.clone())
+        .windows_virtual_key_code(press.key_code)
+        .modifiers(press.modifiers);
+    if let Some(text) = &press.text {
+        down = down.text(text.clone());
+    }
+    let up = DispatchKeyEventParams::builder()
+        .r#type(DispatchKeyEventType::KeyUp)
+        .key(press.key.clone())
+        .code(press.code.clone())
+        .windows_virtual_key_code(press.key_code)
+        .modifiers(press.modifiers)
+        .build()
+        .map_err(|e| CommandError::new(500, e))?;
+    page.execute(down.build().map_err(|e| CommandError::new(500, e))?).await?;
+    page.execute(up).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(key: &str, modifiers: &[&str]) -> Result<KeyPress, String> {
+        PressRequest { key: key.to_string(), modifiers: modifiers.iter().map(|m| m.to_string()).collect() }.parse()
+    }
+
+    /// (key, code, keyCode, text, modifiers)
+    fn fields(press: &KeyPress) -> (&str, &str, i64, Option<&str>, i64) {
+        (&press.key, &press.code, press.key_code, press.text.as_deref(), press.modifiers)
+    }
+
+    #[test]
+    fn parses_chords() {
+        let press = parse("Control+Shift+A", &[]).unwrap();
+        assert_eq!(fields(&press), ("A", "KeyA", 65, None, 2 | 8));
+        assert_eq!(press.modifier_names(), ["control", "shift"]);
+
+        assert_eq!(fields(&parse("shift+a", &[]).unwrap()), ("A", "KeyA", 65, Some("A"), 8));
+        assert_eq!(fields(&parse("Alt+Meta+F4", &[]).unwrap()), ("F4", "F4", 115, None, 1 | 4));
+        assert_eq!(fields(&parse("Shift+Enter", &[]).unwrap()), ("Enter", "Enter", 13, Some("\r"), 8));
+        assert_eq!(fields(&parse("Control++", &[]).unwrap()), ("+", "", 0, None, 2));
+    }
+
+    #[test]
+    fn chord_and_modifiers_add_up_once() {
+        let press = parse("Ctrl+Z", &["shift", "CTRL"]).unwrap();
+        assert_eq!(press.modifiers, 2 | 8);
+        assert_eq!(press.modifier_names(), ["shift", "ctrl"]);
+    }
+
+    #[test]
+    fn single_characters_map_to_codes() {
+        assert_eq!(fields(&parse("a", &[]).unwrap()), ("a", "KeyA", 65, Some("a"), 0));
+        assert_eq!(fields(&parse("7", &[]).unwrap()), ("7", "Digit7", 55, Some("7"), 0));
+        assert_eq!(fields(&parse(" ", &[]).unwrap()), (" ", "Space", 32, Some(" "), 0));
+        assert_eq!(fields(&parse("+", &[]).unwrap()), ("+", "", 0, Some("+"), 0));
+        assert_eq!(fields(&parse("é", &["shift"]).unwrap()), ("É", "", 0, Some("É"), 8));
+        // A shortcut types nothing
+        assert_eq!(fields(&parse("a", &["ctrl"]).unwrap()), ("a", "KeyA", 65, None, 2));
+    }
+
+    #[test]
+    fn named_keys_ignore_case() {
+        assert_eq!(fields(&parse("enter", &[]).unwrap()), ("Enter", "Enter", 13, Some("\r"), 0));
+        assert_eq!(fields(&parse("ARROWDOWN", &[]).unwrap()), ("ArrowDown", "ArrowDown", 40, None, 0));
+        assert_eq!(fields(&parse("Tab", &["shift"]).unwrap()), ("Tab", "Tab", 9, None, 8));
+        assert_eq!(fields(&parse("f12", &[]).unwrap()), ("F12", "F12", 123, None, 0));
+        assert_eq!(fields(&parse("Enter", &["ctrl"]).unwrap()), ("Enter", "Enter", 13, None, 2));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        assert!(parse("Foo", &[]).unwrap_err().contains("unknown key 'Foo'"));
+        assert!(parse("Control+Foo", &[]).unwrap_err().contains("unknown key 'Foo'"));
+        assert!(parse("Control+", &[]).unwrap_err().contains("unknown key ''"));
+        assert!(parse("\u{7}", &[]).unwrap_err().contains("unknown key"));
+        assert!(parse("", &[]).unwrap_err().contains("unknown key"));
+        assert!(parse("Hyper+A", &[]).unwrap_err().contains("unknown modifier 'Hyper'"));
+        assert!(parse("a", &["super"]).unwrap_err().contains("unknown modifier 'super'"));
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+// ============== Kiosk & Window Control ==============
+//
+// --kiosk turns the GUI into a wall display: fullscreen, borderless and
+// always on top, with the injected toolbar and the tab shortcuts switched
+// off. Only the --kiosk-exit chord leaves kiosk mode again. Outside kiosk
+// mode F11 toggles fullscreen, and POST /window changes fullscreen,
+// always-on-top, position and size remotely. The event loop applies these;
+// WindowRect follows through the resulting Moved/Resized events, so capture
+// keeps working in fullscreen.
+
+/// Key combination such as `ctrl+shift+q`, matched page-side against `KeyboardEvent`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub meta: bool,
+    /// Lowercase `KeyboardEvent.key`.
+    pub key: String,
+}
+
+impl std::fmt::Display for Chord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (held, name) in [(self.ctrl, "ctrl"), (self.shift, "shift"), (self.alt, "alt"), (self.meta, "meta")] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        f.write_str(&self.key)
+    }
+}
+
+pub fn parse_chord_arg(s: &str) -> Result<Chord, String> {
+    let mut chord = Chord { ctrl: false, shift: false, alt: false, meta: false, key: String::new() };
+    for part in s.split('+').map(|p| p.trim().to_ascii_lowercase()) {
+        match part.as_str() {
+            "ctrl" | "control" => chord.ctrl = true,
+            "shift" => chord.shift = true,
+            "alt" | "option" => chord.alt = true,
+            "meta" | "cmd" | "super" => chord.meta = true,
+            "" => return Err(format!("invalid chord '{}': empty key", s)),
+            key if chord.key.is_empty() => chord.key = key.to_string(),
+            _ => return Err(format!("invalid chord '{}': more than one non-modifier key", s)),
+        }
+    }
+    if chord.key.is_empty() {
+        return Err(format!("invalid chord '{}': expected modifiers and a key, e.g. ctrl+shift+q", s));
+    }
+    Ok(chord)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct WindowMode {
+    pub fullscreen: bool,
+    pub always_on_top: bool,
+    pub kiosk: bool,
+}
+
+impl WindowMode {
+    pub fn kiosk() -> Self {
+        WindowMode { fullscreen: true, always_on_top: true, kiosk: true }
+    }
+}
+
+/// POST /window; omitted fields are left as they are. Position and size
+/// are physical pixels, like GET /geometry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WindowRequest {
+    pub fullscreen: Option<bool>,
+    pub always_on_top: Option<bool>,
+    /// `false` leaves kiosk mode as the exit chord does; `true` enters it.
+    pub kiosk: Option<bool>,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl WindowRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.x.is_some() != self.y.is_some() {
+            return Err("x and y must be given together".to_string());
+        }
+        if self.width.is_some() != self.height.is_some() {
+            return Err("width and height must be given together".to_string());
+        }
+        if self.width == Some(0) || self.height == Some(0) {
+            return Err("width and height must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Evaluated after every load in kiosk mode instead of the toolbar: marks the
+/// page so INIT_SCRIPT ignores its shortcuts, and reports the exit chord.
+pub const KIOSK_SCRIPT: &str = r#"
+function(chord) {
+    window.__rbKiosk = true;
+    const old = document.getElementById('__rust_browser_toolbar__');
+    if (old) old.remove();
+    const style = document.getElementById('__rb_style__');
+    if (style) style.remove();
+    if (window.__rbKioskChord) { window.__rbKioskChord = chord; return; }
+    window.__rbKioskChord = chord;
+    document.addEventListener('keydown', function(e) {
+        const c = window.__rbKioskChord;
+        if (!window.__rbKiosk || !c) return;
+        if (e.ctrlKey === c.ctrl && e.shiftKey === c.shift && e.altKey === c.alt && e.metaKey === c.meta
+            && e.key.toLowerCase() === c.key) {
+            e.preventDefault();
+            window.ipc.postMessage(JSON.stringify({kioskExit: true}));
+        }
+    }, true);
+}
+"#;
@@ -0,0 +1,60 @@
+use std::time::Instant;
+
+use chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat;
+use chromiumoxide::Page;
+
+use crate::commands::{self, CommandResult};
+use crate::pipeline::{self, Samples};
+use crate::HeadlessState;
+
+// ============== Latency Test ==============
+
+pub const DEFAULT_FRAMES: u32 = 10;
+pub const MAX_FRAMES: u32 = 50;
+
+const ELEMENT_ID: &str = "__rb_latency__";
+
+/// Shows `ts` in a corner element and resolves after two animation frames,
+/// i.e. once the new text has been painted.
+const INJECT_SCRIPT: &str = r#"
+async function(id, ts) {
+    let el = document.getElementById(id);
+    if (!el) {
+        el = document.createElement('div');
+        el.id = id;
+        el.style.cssText = 'position:fixed;top:0;right:0;z-index:2147483647;background:#000;color:#0f0;font:bold 20px monospace;padding:4px 8px;pointer-events:none';
+        document.documentElement.appendChild(el);
+    }
+    el.textContent = String(ts);
+    await new Promise(function(resolve) { requestAnimationFrame(function() { requestAnimationFrame(resolve); }); });
+    return true;
+}
+"#;
+
+const REMOVE_SCRIPT: &str = "function(id) { const el = document.getElementById(id); if (el) el.remove(); }";
+
+/// Captures `frames` frames, each right after stamping the page, and feeds
+/// them to the live stream. Reports injection→capture latency.
+pub async fn run(page: &Page, state: &HeadlessState, frames: u32) -> CommandResult {
+    let result = burst(page, state, frames).await;
+    let _ = commands::evaluate(page, commands::js_call(REMOVE_SCRIPT, &[ELEMENT_ID.into()]), false).await;
+    result
+}
+
+async fn burst(page: &Page, state: &HeadlessState, frames: u32) -> CommandResult {
+    let mut inject_to_capture = Samples::default();
+    for _ in 0..frames {
+        let injected = Instant::now();
+        let stamp = crate::now_millis() as u64;
+        commands::evaluate(page, commands::js_call(INJECT_SCRIPT, &[ELEMENT_ID.into(), stamp.into()]), true).await?;
+        let frame = page.screenshot(crate::screenshot_params(CaptureScreenshotFormat::Jpeg)).await?;
+        inject_to_capture.push(pipeline::millis(injected.elapsed()));
+
+        state.publish_frame(frame);
+    }
+    Ok(serde_json::json!({
+        "frames": frames,
+        "inject_to_capture_ms": inject_to_capture.summary(),
+        "captured_at": crate::now_millis(),
+    }))
+}
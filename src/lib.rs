@@ -0,0 +1,4600 @@
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use std::thread;
+use std::io::Cursor;
+use clap::Parser;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use tiny_http::{Server, Response, Header};
+use types::{NavigateRequest, WaitUntil};
+
+// Declared first so log_info!/log_error! are visible in every module below
+#[macro_use]
+mod logs;
+
+mod annotations;
+mod auth;
+mod baseline;
+mod basic_auth;
+mod bench;
+mod buffers;
+mod blocker;
+mod bundle;
+mod captures;
+pub mod client;
+mod commands;
+mod cookies;
+mod cpu_throttle;
+mod crash;
+mod deterministic;
+mod dialogs;
+mod downloads;
+mod element;
+mod embed;
+mod encoder;
+mod event_track;
+mod events;
+mod file_upload;
+mod filters;
+mod fonts;
+mod frame_hook;
+mod frames;
+mod grpc;
+mod handoff;
+mod har;
+mod headers;
+mod history;
+mod host_rules;
+mod hud;
+mod instance;
+mod intercept;
+mod js_errors;
+mod journal;
+mod keys;
+mod kiosk;
+mod latency;
+mod lifecycle;
+mod links;
+mod locale;
+mod login;
+mod mcp;
+mod media;
+mod media_emulation;
+mod mjpeg;
+mod mock;
+mod navigation;
+mod network_log;
+mod ocr;
+mod offline;
+mod overlay;
+mod perf;
+mod permissions;
+mod pipeline;
+mod probe;
+mod qr;
+mod redact;
+mod render_pool;
+mod report;
+mod request_log;
+mod resources;
+mod scaling;
+mod screen_record;
+mod screenshot;
+mod scroll;
+mod settle;
+mod snapshot;
+mod soak;
+mod sink;
+mod source;
+mod split;
+mod status;
+mod storage;
+mod theme;
+mod ticker;
+mod trace;
+pub mod types;
+mod ui_state;
+mod upload;
+mod url_block;
+mod url_match;
+mod watchdog;
+mod webdriver;
+mod workspaces;
+mod ws;
+
+/// The GUI recorder, for the selector generator's fixture tests.
+#[doc(hidden)]
+pub use journal::RECORDER_SCRIPT;
+/// The generated gRPC client and messages, for --grpc-port.
+#[cfg(feature = "grpc")]
+pub use grpc::proto as grpc_proto;
+
+#[derive(Parser, Debug)]
+#[command(name = "Rust Browser Claude")]
+#[command(about = "Desktop browser with live streaming capability")]
+pub struct Args {
+    /// Run in headless mode (no GUI, uses Chrome)
+    #[arg(long)]
+    headless: bool,
+
+    /// Initial URL to load
+    #[arg(long, default_value = "https://example.com")]
+    url: String,
+
+    /// HTTP server port for live stream
+    #[arg(long, default_value = "8765")]
+    port: u16,
+
+    /// Viewport width (headless mode)
+    #[arg(long, default_value = "1200")]
+    width: u32,
+
+    /// Viewport height (headless mode)
+    #[arg(long, default_value = "800")]
+    height: u32,
+
+    /// Stream the whole scrollable page instead of the viewport (headless mode)
+    #[arg(long)]
+    fullpage: bool,
+
+    /// Tallest full-page capture in CSS pixels, for --fullpage and /screenshot?fullpage=true
+    #[arg(long, default_value = "16384")]
+    fullpage_max_height: u32,
+
+    /// Stream only the rectangle X,Y,WIDTH,HEIGHT of the page, in CSS pixels (headless mode)
+    #[arg(long, conflicts_with = "fullpage")]
+    clip: Option<screenshot::Clip>,
+
+    /// Record manual clicks, typing and scrolling into the activity journal (GUI mode)
+    #[arg(long)]
+    record_activity: bool,
+
+    /// ABP/EasyList filter list used to block ad and tracker requests (GUI mode: top-level navigations only)
+    #[arg(long)]
+    filter_list: Option<std::path::PathBuf>,
+
+    /// Fail requests whose URL matches one of these patterns, `*` matching anything, e.g. *google-analytics.com* (headless mode)
+    #[arg(long, value_delimiter = ',', value_name = "PATTERN")]
+    block: Vec<String>,
+
+    /// Resolve HOST to TARGET[:PORT] without touching /etc/hosts, e.g. www.example.com=127.0.0.1:8443 (repeatable; GUI mode redirects navigations)
+    #[arg(long = "host-rule")]
+    host_rules: Vec<host_rules::HostRule>,
+
+    /// Grant permissions to all origins, e.g. camera,microphone,notifications (headless mode)
+    #[arg(long, value_delimiter = ',')]
+    grant_permissions: Vec<String>,
+
+    /// Use Chrome's fake camera/microphone and auto-accept media prompts (headless mode)
+    #[arg(long)]
+    fake_media: bool,
+
+    /// Chrome autoplay policy, e.g. no-user-gesture-required so muted videos play in the stream (headless mode)
+    #[arg(long, value_parser = media::AUTOPLAY_POLICIES)]
+    autoplay_policy: Option<String>,
+
+    /// URL polled for a text banner composited onto every frame (plain text or JSON {"text": ...})
+    #[arg(long)]
+    ticker_url: Option<String>,
+
+    /// Ticker URL polling interval in seconds
+    #[arg(long, default_value = "30")]
+    ticker_interval: u64,
+
+    /// Ticker banner position
+    #[arg(long, value_enum, default_value = "bottom")]
+    ticker_position: ticker::Position,
+
+    /// Ticker text color (#rgb, #rrggbb or #rrggbbaa)
+    #[arg(long, default_value = "#ffffff", value_parser = overlay::parse_color_arg)]
+    ticker_color: overlay::Color,
+
+    /// Ticker banner background color; the alpha channel controls transparency
+    #[arg(long, default_value = "#000000b0", value_parser = overlay::parse_color_arg)]
+    ticker_background: overlay::Color,
+
+    /// Composite a status strip (URL, load state, clock) onto every live frame, for consumers without the viewer (headless mode)
+    #[arg(long)]
+    hud: bool,
+
+    /// What the HUD strip shows, in order
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "url,load,clock")]
+    hud_items: Vec<hud::Item>,
+
+    /// HUD strip position; pick the other edge than --ticker-position when both are shown
+    #[arg(long, value_enum, default_value = "top")]
+    hud_position: ticker::Position,
+
+    /// HUD text color (#rgb, #rrggbb or #rrggbbaa)
+    #[arg(long, default_value = "#ffffff", value_parser = overlay::parse_color_arg)]
+    hud_color: overlay::Color,
+
+    /// HUD strip background color; the alpha channel controls transparency
+    #[arg(long, default_value = "#202020c0", value_parser = overlay::parse_color_arg)]
+    hud_background: overlay::Color,
+
+    /// One-shot mode: load --url, save a screenshot to PATH (.png or .jpg) and exit (headless mode)
+    #[arg(long)]
+    screenshot: Option<std::path::PathBuf>,
+
+    /// In one-shot mode, wait until consecutive frames are identical before saving
+    #[arg(long)]
+    wait_settled: bool,
+
+    /// Identical consecutive frames required for a capture to count as settled
+    #[arg(long, default_value = "3")]
+    settle_frames: u32,
+
+    /// Milliseconds between settle captures
+    #[arg(long, default_value = "100")]
+    settle_interval: u64,
+
+    /// Milliseconds to wait for the page to settle before giving up (max 20000)
+    #[arg(long, default_value = "5000")]
+    settle_timeout: u64,
+
+    /// Disable animations, carets and scrollbars and freeze Date/Math.random for reproducible captures (headless mode)
+    #[arg(long)]
+    deterministic_render: bool,
+
+    /// Deterministic-render parts to leave alone, e.g. animations,clock for pages that break without them
+    #[arg(long, value_enum, value_delimiter = ',')]
+    deterministic_skip: Vec<deterministic::Feature>,
+
+    /// Directory of .ttf/.otf/.woff/.woff2 files injected into every page via @font-face
+    #[arg(long)]
+    bundled_fonts: Option<std::path::PathBuf>,
+
+    /// Point a generic family at a bundled font, e.g. monospace=JetBrains Mono (repeatable)
+    #[arg(long = "font-map")]
+    font_map: Vec<fonts::FontMapping>,
+
+    /// Chrome font hinting; none gives the most consistent output across platforms (headless mode)
+    #[arg(long, value_parser = ["none", "slight", "medium", "full"])]
+    font_render_hinting: Option<String>,
+
+    /// Locale profile LANG[@TIMEZONE], e.g. fr-FR@Europe/Paris: sets Accept-Language, navigator.language, the Intl locale and timezone together (headless mode)
+    #[arg(long, value_name = "LANG[@TIMEZONE]")]
+    locale: Option<locale::LocaleProfile>,
+
+    /// IANA timezone, e.g. America/New_York, for the streamed page and /render when the locale profile names none (headless mode)
+    #[arg(long, value_parser = locale::parse_timezone)]
+    timezone: Option<String>,
+
+    /// Clear the browser cache whenever the streamed page switches locale, so localized assets don't carry over (headless mode)
+    #[arg(long)]
+    locale_clear_cache: bool,
+
+    /// Replace the browser's user agent for the streamed page and /render (headless mode)
+    #[arg(long, value_parser = parse_user_agent)]
+    user_agent: Option<String>,
+
+    /// Credentials USER:PASS answered to HTTP authentication challenges of the streamed page (headless mode)
+    #[arg(long, value_name = "USER:PASS", value_parser = basic_auth::parse_flag)]
+    basic_auth: Option<(String, String)>,
+
+    /// Only send --basic-auth to this origin, e.g. https://intranet.example (headless mode)
+    #[arg(long, requires = "basic_auth", value_parser = basic_auth::parse_origin)]
+    basic_auth_origin: Option<String>,
+
+    /// Directory downloads of the streamed page are saved into; a temp directory by default (headless mode)
+    #[arg(long)]
+    download_dir: Option<std::path::PathBuf>,
+
+    /// Answer alert/confirm/prompt dialogs of the streamed page by accepting or dismissing them (headless mode)
+    #[arg(long, value_enum, default_value = "dismiss")]
+    dialogs: dialogs::DialogPolicy,
+
+    /// Directory for frames saved via POST /captures, each with a JSON metadata sidecar (headless mode)
+    #[arg(long)]
+    capture_dir: Option<std::path::PathBuf>,
+
+    /// Upload saved frames to an S3-compatible bucket, https://ENDPOINT/BUCKET[/PREFIX]; credentials from AWS_* env vars (needs the `upload` feature)
+    #[arg(long)]
+    upload_url: Option<String>,
+
+    /// Benchmark the capture pipeline against a built-in fixture page for SECS seconds and exit (headless mode)
+    #[arg(long, value_name = "SECS")]
+    bench: Option<u64>,
+
+    /// Print the --bench report as JSON
+    #[arg(long)]
+    bench_json: bool,
+
+    /// One-shot mode: render --report-urls (or --url) with settled captures, write an HTML report with thumbnails and index.json to DIR and exit (headless mode)
+    #[arg(long, value_name = "DIR")]
+    report: Option<std::path::PathBuf>,
+
+    /// File with one URL per line for --report; # starts a comment
+    #[arg(long, value_name = "FILE")]
+    report_urls: Option<std::path::PathBuf>,
+
+    /// Also crawl same-origin links this many levels deep from the --report URLs
+    #[arg(long, default_value = "0")]
+    report_depth: u32,
+
+    /// Stop the --report crawl after this many pages
+    #[arg(long, default_value = "100")]
+    report_max_pages: usize,
+
+    /// Compare --report with an earlier report directory; exits with code 3 when pages changed
+    #[arg(long, value_name = "DIR")]
+    baseline: Option<std::path::PathBuf>,
+
+    /// Percentage of differing pixels above which a --baseline page counts as changed
+    #[arg(long, default_value = "0.5", value_name = "PERCENT")]
+    baseline_max_diff: f64,
+
+    /// Let Chrome capture downscaled frames while every /live-stream client asks for ?max_width at or below PX (headless mode)
+    #[arg(long, value_name = "PX")]
+    thumbnail_threshold: Option<u32>,
+
+    /// JSON file of named frame filter pipelines (crop, grayscale, contrast, watermark), selected with /live-stream?filters=NAME (headless mode)
+    #[arg(long, value_name = "FILE")]
+    frame_filters: Option<std::path::PathBuf>,
+
+    /// Force a dark theme during this local time window, e.g. 20:00-07:00; POST /theme and the GUI toolbar toggle override it
+    #[arg(long, value_name = "HH:MM-HH:MM")]
+    dark_schedule: Option<theme::DarkSchedule>,
+
+    /// JSON list of per-URL dark stylesheets, [{"url": "GLOB", "css": "..."} or {"url": "GLOB", "css_file": "PATH"}]; other pages get an inverting filter
+    #[arg(long, value_name = "FILE")]
+    theme_rules: Option<std::path::PathBuf>,
+
+    /// Emulate prefers-color-scheme for the streamed page; POST /media/emulation changes it at runtime (headless mode)
+    #[arg(long, value_enum)]
+    color_scheme: Option<media_emulation::ColorScheme>,
+
+    /// Use Chrome's auto dark mode instead of the inverting filter for pages without a --theme-rules entry (headless mode)
+    #[arg(long)]
+    dark_auto: bool,
+
+    /// What to do with the page while nobody watches (headless mode): keep it running, freeze it, pause its virtual time or discard it until a viewer returns
+    #[arg(long, value_enum, default_value = "none")]
+    idle_page_policy: lifecycle::IdlePagePolicy,
+
+    /// Seconds without a /live-stream poll, sink, frame hook, gRPC stream or recording before --idle-page-policy applies
+    #[arg(long, default_value = "30", value_name = "SECS")]
+    idle_after: u64,
+
+    /// Write panic and unclean-shutdown reports to DIR, listed by GET /crashes
+    #[arg(long, value_name = "DIR")]
+    crash_dir: Option<std::path::PathBuf>,
+
+    /// Require this token (Authorization: Bearer, ?token=, /login session or a /sign signature) on every route
+    #[arg(long, value_name = "TOKEN")]
+    api_token: Option<String>,
+
+    /// JSON file of named viewer/controller tokens ([{"name","token","role"}]); rewritten by POST/DELETE /tokens
+    #[arg(long, value_name = "FILE")]
+    tokens: Option<std::path::PathBuf>,
+
+    /// Origins allowed to frame /embed (comma-separated; default: same origin only)
+    #[arg(long, value_name = "ORIGINS", value_delimiter = ',')]
+    embed_allowed_origins: Vec<String>,
+
+    /// Serve a minimal W3C WebDriver endpoint set under /wd/hub (headless mode)
+    #[arg(long)]
+    webdriver: bool,
+
+    /// Speak the Model Context Protocol over stdio for LLM agents (implies --headless; the HTTP server stays up)
+    #[arg(long)]
+    mcp: bool,
+
+    /// Also serve the gRPC control API (proto/browser.proto) on this port (headless mode, needs `--features grpc`)
+    #[arg(long, value_name = "PORT")]
+    grpc_port: Option<u16>,
+
+    /// Spawn CMD (via the shell) and write every live frame as JPEG to its stdin, e.g. "ffmpeg -f image2pipe -i - ..." (headless mode)
+    #[arg(long, value_name = "CMD")]
+    pipe_to_ffmpeg: Option<String>,
+
+    /// Write every live frame as raw JPEG to tcp://HOST:PORT or unix:///PATH (headless mode)
+    #[arg(long, value_name = "URL")]
+    frame_sink: Option<String>,
+
+    /// Run CMD (via the shell) on sampled live frames: JPEG on stdin, JSON expected on stdout, merged into /live-stream as `hook` (headless mode)
+    #[arg(long, value_name = "CMD")]
+    frame_hook: Option<String>,
+
+    /// Feed every Nth live frame to --frame-hook
+    #[arg(long, default_value = "10", value_name = "N")]
+    frame_hook_every: u64,
+
+    /// Kill a --frame-hook run after this many milliseconds
+    #[arg(long, default_value = "2000", value_name = "MS")]
+    frame_hook_timeout_ms: u64,
+
+    /// Concurrent --frame-hook runs; sampled frames beyond this are skipped
+    #[arg(long, default_value = "1", value_name = "N")]
+    frame_hook_concurrency: usize,
+
+    /// Top-level hook output fields to merge into /live-stream (comma-separated; default all)
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    frame_hook_fields: Vec<String>,
+
+    /// Condition on the hook output that fires --frame-hook-webhook: FIELD, FIELD=VALUE, FIELD!=VALUE, FIELD>N or FIELD<N (dotted paths)
+    #[arg(long, value_name = "COND", value_parser = frame_hook::parse_condition_arg)]
+    frame_hook_match: Option<frame_hook::Condition>,
+
+    /// POST a JSON event here when the hook output starts matching --frame-hook-match
+    #[arg(long, value_name = "URL")]
+    frame_hook_webhook: Option<String>,
+
+    /// Tesseract language(s) for GET /ocr, e.g. "eng" or "eng+deu" (needs the `ocr` feature)
+    #[arg(long, default_value = "eng", value_name = "LANG")]
+    ocr_lang: String,
+
+    /// Typed values are redacted from the journal and event track when the field selector contains one of these (comma-separated, case-insensitive)
+    #[arg(long, value_name = "PATTERNS", value_delimiter = ',', default_value = "password,passwd,secret,token,otp,cvv,card")]
+    redact_fields: Vec<String>,
+
+    /// JSON file ({"selectors": [...]}) holding the frame redaction rules; loaded at startup, rewritten by POST/DELETE /redact
+    #[arg(long, value_name = "PATH")]
+    redact_file: Option<std::path::PathBuf>,
+
+    /// Re-resolve redaction boxes at most this often within a page (0 = before every frame)
+    #[arg(long, default_value = "0", value_name = "MS")]
+    redact_refresh_ms: u64,
+
+    /// GET /render requests rendered at once, each in its own page; more get 503 with Retry-After
+    #[arg(long, default_value = "2", value_name = "N")]
+    render_concurrency: usize,
+
+    /// Upper bound for one GET /render, from context creation to the encoded frame
+    #[arg(long, default_value = "20000", value_name = "MS")]
+    render_timeout_ms: u64,
+
+    /// Advertise this instance over mDNS as _rustbrowser._tcp (needs the `announce` feature)
+    #[arg(long)]
+    announce: bool,
+
+    /// Where the stable instance id is kept [default: ~/.config/<app>/instance-<port>.id]
+    #[arg(long, value_name = "PATH")]
+    instance_id_file: Option<std::path::PathBuf>,
+
+    /// POST the GET /instance payload to this URL as a heartbeat
+    #[arg(long, value_name = "URL")]
+    registry_url: Option<String>,
+
+    /// Registry heartbeat interval in seconds
+    #[arg(long, default_value = "30", value_name = "SECS")]
+    registry_interval: u64,
+
+    /// Resume a session exported by POST /handoff/export: its URL and viewport replace --url/--width/--height, then cookies, storage and scroll are applied
+    #[arg(long, value_name = "FILE")]
+    import_state: Option<std::path::PathBuf>,
+
+    /// Warm standby primary: write this instance's tabs, URL, cookies, storage and scroll to DIR/snapshot.json, refusing a DIR another running instance owns
+    #[arg(long, value_name = "DIR")]
+    snapshot_to: Option<std::path::PathBuf>,
+
+    /// Seconds between --snapshot-to snapshots
+    #[arg(long, default_value = "10", value_name = "SECS")]
+    snapshot_interval_secs: u64,
+
+    /// Warm standby: apply the snapshots another instance writes to DIR with --snapshot-to, loading the page only when it changed
+    #[arg(long, value_name = "DIR")]
+    follow: Option<std::path::PathBuf>,
+
+    /// Append GET /resources samples to this file: CSV for a .csv path, JSON lines otherwise
+    #[arg(long, value_name = "PATH")]
+    resource_log: Option<std::path::PathBuf>,
+
+    /// Seconds between --resource-log samples
+    #[arg(long, default_value = "60", value_name = "SECS")]
+    resource_log_interval: u64,
+
+    /// Wall-display mode (GUI): fullscreen, borderless and always on top, without toolbar or tab shortcuts
+    #[arg(long)]
+    kiosk: bool,
+
+    /// Key chord that leaves --kiosk mode
+    #[arg(long, default_value = "ctrl+shift+q", value_name = "CHORD", value_parser = kiosk::parse_chord_arg)]
+    kiosk_exit: kiosk::Chord,
+
+    /// Where named workspaces are kept [default: ~/.config/<app>/workspaces.json]
+    #[arg(long, value_name = "PATH")]
+    workspaces_file: Option<std::path::PathBuf>,
+
+    /// Reopen the tabs of the workspace that was active when the browser last quit (GUI)
+    #[arg(long)]
+    restore_workspace: bool,
+
+    /// Encrypted login recipes (see --encrypt-credentials); login pages are detected and logged into automatically (headless mode)
+    #[arg(long, value_name = "PATH")]
+    credentials_file: Option<std::path::PathBuf>,
+
+    /// Log HTTP requests that take longer than this, with their queue/handler/write breakdown
+    #[arg(long, default_value = "1000", value_name = "MS")]
+    slow_request_ms: u64,
+
+    /// Cap for an in-memory buffer, NAME=N with NAME one of logs, requests, journal, track, history, annotations, dialogs, errors, network, downloads (repeatable)
+    #[arg(long = "buffer-size", value_name = "NAME=N")]
+    buffer_sizes: Vec<buffers::BufferSize>,
+
+    /// Soak test: run the headless pipeline against the benchmark fixture for MINUTES with every buffer busy, failing (exit code 4) if RSS exceeds --soak-rss-budget-mb
+    #[arg(long, value_name = "MINUTES")]
+    soak: Option<u64>,
+
+    /// RSS budget in MB for --soak, covering this process and Chrome
+    #[arg(long, default_value = "1024", value_name = "MB")]
+    soak_rss_budget_mb: u64,
+
+    /// Apply a GET /bundle/export document once the page is up, like POST /bundle/import (headless mode)
+    #[arg(long, value_name = "FILE")]
+    bundle: Option<std::path::PathBuf>,
+
+    /// Directory for F9 / POST /record/start screen recordings (GUI; default: ~/Downloads)
+    #[arg(long, value_name = "DIR")]
+    record_dir: Option<std::path::PathBuf>,
+
+    /// Frame rate of GUI screen recordings
+    #[arg(long, default_value = "15", value_name = "FPS")]
+    record_fps: u32,
+
+    /// Container of GUI screen recordings
+    #[arg(long, value_enum, default_value = "mp4")]
+    record_format: screen_record::RecordFormat,
+
+    /// ffmpeg system audio input for GUI recordings, FORMAT:DEVICE (default: pulse:@DEFAULT_MONITOR@ on Linux, none elsewhere)
+    #[arg(long, value_name = "FORMAT:DEVICE")]
+    record_audio_input: Option<screen_record::AudioInput>,
+
+    /// ffmpeg microphone input for GUI recordings, FORMAT:DEVICE (default: pulse:default on Linux, avfoundation::0 on macOS)
+    #[arg(long, value_name = "FORMAT:DEVICE")]
+    record_mic_input: Option<screen_record::AudioInput>,
+
+    /// Recreate a pane's webview at its last URL after it has been unresponsive this long (GUI)
+    #[arg(long, value_name = "SECS")]
+    unresponsive_recover_secs: Option<u64>,
+
+    /// Print the encrypted form of a plain JSON credentials file, using $LOGIN_CREDENTIALS_KEY, and exit
+    #[arg(long, value_name = "PATH")]
+    encrypt_credentials: Option<std::path::PathBuf>,
+
+    /// Seconds between checks for a login page
+    #[arg(long, default_value = "5", value_name = "SECS")]
+    login_check_interval: u64,
+
+    /// Failed logins in a row before an entry stops retrying and needs attention
+    #[arg(long, default_value = "3", value_name = "N")]
+    login_max_attempts: u32,
+
+    /// POST auto-login events (succeeded, failed, needs attention) to this URL (repeatable)
+    #[arg(long = "login-webhook", value_name = "URL")]
+    login_webhooks: Vec<String>,
+}
+
+// ============== Shared Types ==============
+
+type ScreenshotBuffer = Arc<Mutex<Option<Vec<u8>>>>;
+
+const JPEG_QUALITY: u8 = 80;
+type CurrentUrl = Arc<Mutex<String>>;
+type FrameNotifier = Arc<tokio::sync::watch::Sender<u64>>;
+
+/// Shared state between the headless capture loop and its HTTP server.
+#[derive(Clone)]
+struct HeadlessState {
+    screenshot_buffer: ScreenshotBuffer,
+    current_url: CurrentUrl,
+    blocker: blocker::SharedBlocker,
+    /// --block and /block URL patterns.
+    url_blocks: url_block::SharedBlockList,
+    /// Canned responses registered with /mock.
+    mocks: mock::SharedMocks,
+    /// /cpu-throttle rate, reapplied after navigations.
+    cpu_throttle: cpu_throttle::SharedCpuThrottle,
+    /// /offline network emulation.
+    offline: offline::SharedOffline,
+    /// /media/emulation set, reapplied after navigations.
+    media_emulation: media_emulation::SharedMedia,
+    /// /trace/start and /trace/stop.
+    tracer: trace::SharedTracer,
+    host_rules: host_rules::SharedHostRules,
+    permissions: permissions::SharedPermissions,
+    annotations: annotations::SharedAnnotations,
+    ticker: ticker::SharedTicker,
+    hud: hud::SharedHud,
+    theme: theme::SharedTheme,
+    commands: commands::CommandSender,
+    viewport: (u32, u32),
+    settle: settle::SettleOptions,
+    /// Deterministic-render features honouring --deterministic-skip, also used by /render?deterministic=true.
+    deterministic: Vec<deterministic::Feature>,
+    fonts: Arc<fonts::FontConfig>,
+    locale: locale::SharedLocale,
+    captures: Option<captures::SharedCaptures>,
+    navigation: captures::SharedNavigation,
+    uploader: Option<upload::Uploader>,
+    pipeline: pipeline::SharedPipelineStats,
+    scaler: Option<scaling::SharedScaler>,
+    /// Pause and --idle-page-policy state of the streamed page.
+    lifecycle: lifecycle::SharedLifecycle,
+    auth: Arc<auth::Auth>,
+    embed: Arc<embed::EmbedConfig>,
+    webdriver: Option<webdriver::SharedSession>,
+    /// Sequence number of the live frame, bumped on every `publish_frame`.
+    frames: FrameNotifier,
+    sinks: Vec<sink::FrameSink>,
+    frame_hook: Option<frame_hook::FrameHook>,
+    /// --frame-filters profiles for /live-stream?filters=.
+    frame_filters: Arc<filters::FrameFilters>,
+    /// GET /ocr results, cached per frame sequence number and region.
+    ocr: ocr::SharedOcr,
+    /// Standing QR detector behind GET /decode-qr?watch=true.
+    qr_watch: qr::SharedWatch,
+    /// Subscribers of GET /events.
+    events: events::SharedEvents,
+    /// Capture counters and errors for GET /status.
+    status: status::SharedStatus,
+    /// --fullpage and its height cap.
+    full_page: screenshot::FullPage,
+    /// PUT /headers, as accepted by Chrome.
+    extra_headers: headers::SharedHeaders,
+    /// --basic-auth and /auth credentials, answered by the interception task.
+    basic_auth: basic_auth::SharedBasicAuth,
+    /// --dialogs policy and the dialogs answered, for /dialog and /dialogs.
+    dialogs: dialogs::SharedDialogs,
+    /// Temp files attached by POST /upload, until the page moves on.
+    file_uploads: file_upload::SharedUploads,
+    /// Files the streamed page downloaded, for /downloads.
+    downloads: downloads::SharedDownloads,
+    /// Uncaught exceptions of the streamed page, for /errors and /status.
+    js_errors: js_errors::SharedJsErrors,
+    /// Requests of the streamed page, for /network.
+    network_log: network_log::SharedNetworkLog,
+    /// Latest main-frame document response, for GET /source?kind=served.
+    document: source::SharedDocument,
+    /// API interactions (click/type/navigate), redacted on the way in.
+    journal: journal::SharedJournal,
+    login: login::SharedLoginHelper,
+    recorder: event_track::SharedRecorder,
+    redaction: redact::SharedRedaction,
+    /// Isolated pages for GET /render, away from the streamed page.
+    render_pool: render_pool::RenderPool,
+    instance: Arc<instance::Instance>,
+}
+
+impl HeadlessState {
+    fn needs_interception(&self) -> bool {
+        self.blocker.lock().unwrap().is_enabled()
+            || self.url_blocks.lock().unwrap().is_active()
+            || self.mocks.lock().unwrap().is_active()
+            || self.host_rules.lock().unwrap().has_runtime_rules()
+            || self.basic_auth.lock().unwrap().is_configured()
+    }
+
+    /// True when a frame must be decoded to composite overlays onto it.
+    fn has_overlays(&self) -> bool {
+        self.annotations.lock().unwrap().is_active()
+            || self.ticker.lock().unwrap().is_active()
+            || self.hud.lock().unwrap().is_active()
+            || self.redaction.lock().unwrap().is_active()
+    }
+
+    fn draw_overlays(&self, img: &mut image::RgbImage) {
+        self.annotations.lock().unwrap().render(img);
+        self.ticker.lock().unwrap().render(img);
+        self.hud.lock().unwrap().render(img, &self.current_url.lock().unwrap());
+        // Last, so nothing is drawn over the blacked-out regions
+        self.redaction.lock().unwrap().render(img);
+    }
+
+    /// Journals an interaction and adds it to a running recording.
+    fn record_action(&self, action: journal::JournalAction) {
+        let url = self.current_url.lock().unwrap().clone();
+        let entry = self.journal.lock().unwrap().record(&url, action).clone();
+        self.recorder.lock().unwrap().record_entry(&entry);
+    }
+
+    /// Makes `frame` the live frame and wakes everything following the stream.
+    fn publish_frame(&self, frame: Vec<u8>) {
+        for sink in &self.sinks {
+            sink.push(&frame);
+        }
+        if let Some(hook) = &self.frame_hook {
+            hook.offer(&frame, &self.current_url.lock().unwrap());
+        }
+        self.pipeline.lock().unwrap().record_frame(frame.len());
+        self.status.lock().unwrap().frame_captured();
+        *self.screenshot_buffer.lock().unwrap() = Some(frame);
+        self.frames.send_modify(|seq| *seq += 1);
+    }
+}
+
+fn create_ticker(args: &Args) -> ticker::SharedTicker {
+    let ticker = Arc::new(Mutex::new(ticker::Ticker::new(
+        args.ticker_url.clone(),
+        args.ticker_position,
+        args.ticker_color,
+        args.ticker_background,
+    )));
+    if let Some(url) = &args.ticker_url {
+        ticker::spawn_poller(ticker.clone(), url.clone(), std::time::Duration::from_secs(args.ticker_interval.max(1)));
+    }
+    ticker
+}
+
+fn load_instance(args: &Args, mode: &'static str, url: instance::UrlSource) -> Result<Arc<instance::Instance>, String> {
+    Ok(Arc::new(instance::Instance::load(args.instance_id_file.clone(), mode, args.port, url)?))
+}
+
+/// Starts the mDNS announcement and registry heartbeat, if configured.
+fn advertise_instance(args: &Args, instance: &Arc<instance::Instance>) -> Result<(), String> {
+    if args.announce {
+        instance::announce(instance.clone())?;
+    }
+    if let Some(url) = &args.registry_url {
+        let interval = std::time::Duration::from_secs(args.registry_interval.max(1));
+        instance::start_heartbeat(instance.clone(), url.clone(), interval);
+    }
+    Ok(())
+}
+
+fn load_fonts(args: &Args) -> Result<fonts::FontConfig, String> {
+    let config = fonts::FontConfig::load(args.bundled_fonts.as_deref(), args.font_map.clone(), args.font_render_hinting.as_deref())
+        .map_err(|e| format!("Failed to load bundled fonts: {}", e))?;
+    if let Some(dir) = &config.dir {
+        log_info!("Bundled fonts: {} ({} faces)", dir.display(), config.faces.len());
+    }
+    Ok(config)
+}
+
+/// `/logs` and `/crashes`, served the same way by both HTTP servers.
+fn diagnostics_response(path: &str) -> Option<Response<Cursor<Vec<u8>>>> {
+    match path {
+        "/logs" => Some(json_response(200, &serde_json::json!({
+            "capacity": buffers::Buffer::Logs.capacity(),
+            "logs": logs::recent(),
+        }))),
+        "/crashes" => Some(crash::response()),
+        "/requests/recent" => Some(json_response(200, &serde_json::json!({
+            "capacity": buffers::Buffer::Requests.capacity(),
+            "slow_threshold_ms": request_log::slow_threshold_ms(),
+            "requests": request_log::recent(),
+        }))),
+        _ => None,
+    }
+}
+
+fn command_response(result: commands::CommandResult) -> Response<Cursor<Vec<u8>>> {
+    match result {
+        Ok(body) => json_response(200, &body),
+        Err(e) => json_response(e.status, &serde_json::json!({"error": e.message})),
+    }
+}
+
+/// Turns a `PageCommand::Render` result into a JPEG response with the settle
+/// outcome in headers.
+fn render_response(body: &serde_json::Value) -> Response<Cursor<Vec<u8>>> {
+    let frame = match body["frame"].as_str().map(|f| BASE64.decode(f)) {
+        Some(Ok(frame)) => frame,
+        _ => return json_response(500, &serde_json::json!({"error": "render returned no frame"})),
+    };
+    let mut response = Response::from_data(frame)
+        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"image/jpeg"[..]).unwrap())
+        .with_header(Header::from_bytes(&b"Cache-Control"[..], &b"no-cache"[..]).unwrap());
+    if let Some(settle) = body["settle"].as_object() {
+        for (name, key) in [("X-Settled", "settled"), ("X-Settle-Frames", "frames_captured"), ("X-Settle-Elapsed-Ms", "elapsed_ms")] {
+            let value = settle.get(key).map(|v| v.to_string()).unwrap_or_default();
+            response.add_header(Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap());
+        }
+    }
+    response
+}
+
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+/// Reads and deserializes a JSON request body, capped at `MAX_BODY_BYTES`.
+fn read_json_body<T: serde::de::DeserializeOwned>(request: &mut tiny_http::Request) -> Result<T, String> {
+    let length = request.body_length();
+    parse_json_body(request.as_reader(), length)
+}
+
+/// A JSON body from `reader`; `length` is the declared Content-Length. An
+/// empty body reads as `{}`.
+fn parse_json_body<T: serde::de::DeserializeOwned>(reader: impl std::io::Read, length: Option<usize>) -> Result<T, String> {
+    use std::io::Read;
+
+    if length.is_some_and(|len| len as u64 > MAX_BODY_BYTES) {
+        return Err(format!("request body exceeds {} bytes", MAX_BODY_BYTES));
+    }
+    let mut body = String::new();
+    reader
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_string(&mut body)
+        .map_err(|e| format!("failed to read body: {}", e))?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(format!("request body exceeds {} bytes", MAX_BODY_BYTES));
+    }
+    if body.trim().is_empty() {
+        body = "{}".to_string();
+    }
+    serde_json::from_str(&body).map_err(|e| format!("invalid JSON body: {}", e))
+}
+
+/// Per-user config directory for files this binary keeps between runs.
+fn config_dir() -> std::path::PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| std::path::PathBuf::from(home).join(".config")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join(env!("CARGO_PKG_NAME"))
+}
+
+fn now_millis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+fn json_response(status: u16, body: &serde_json::Value) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+}
+
+/// Splits a request URL into its path and decoded query parameters.
+fn parse_query(url: &str) -> (&str, std::collections::HashMap<String, String>) {
+    match url.split_once('?') {
+        Some((path, query)) => (
+            path,
+            url::form_urlencoded::parse(query.as_bytes()).into_owned().collect(),
+        ),
+        None => (url, std::collections::HashMap::new()),
+    }
+}
+
+// ============== HTTP Server ==============
+
+#[derive(Debug, serde::Deserialize)]
+struct UserAgentRequest {
+    value: String,
+}
+
+/// --user-agent and POST /useragent: a blank user agent is never sent.
+fn parse_user_agent(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("user agent must not be empty".to_string());
+    }
+    if value.chars().any(char::is_control) {
+        return Err("user agent must not contain control characters".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// Both forms of /navigate.
+fn navigate(
+    req: NavigateRequest,
+    current_url: &CurrentUrl,
+    locale: &locale::SharedLocale,
+    command_tx: &commands::CommandSender,
+) -> Response<Cursor<Vec<u8>>> {
+    if req.url.trim().is_empty() {
+        return json_response(400, &serde_json::json!({"error": "missing url"}));
+    }
+    let profile = match req.locale.map(|tag| tag.parse::<locale::LocaleProfile>()).transpose() {
+        Ok(profile) => profile,
+        Err(e) => return json_response(400, &serde_json::json!({"error": e})),
+    };
+    match req.wait_until {
+        WaitUntil::None => {
+            // Both under the locale lock, so the capture loop sees them together
+            let mut locale = locale.lock().unwrap();
+            locale.request(profile);
+            *current_url.lock().unwrap() = req.url;
+            drop(locale);
+            json_response(200, &serde_json::json!({"status": "navigating"}))
+        }
+        // The capture loop applies a locale with its own reload, so only a queued navigation takes one
+        WaitUntil::Load if profile.is_some() => {
+            json_response(400, &serde_json::json!({"error": "locale needs wait_until \"none\""}))
+        }
+        WaitUntil::Load => command_response(commands::send(command_tx, commands::PageCommand::Navigate { url: req.url, source: "api" })),
+    }
+}
+
+fn start_http_server_headless(port: u16, state: HeadlessState) {
+    let shared = state.clone();
+    let HeadlessState {
+        screenshot_buffer,
+        current_url,
+        blocker,
+        url_blocks,
+        mocks,
+        cpu_throttle,
+        offline,
+        media_emulation,
+        host_rules,
+        permissions,
+        annotations,
+        ticker,
+        hud,
+        theme,
+        commands: command_tx,
+        viewport,
+        settle: settle_defaults,
+        fonts,
+        locale,
+        captures,
+        uploader,
+        pipeline,
+        scaler,
+        lifecycle,
+        sinks,
+        frame_hook,
+        frame_filters,
+        frames,
+        ocr,
+        qr_watch,
+        events,
+        extra_headers,
+        basic_auth,
+        dialogs,
+        file_uploads,
+        downloads,
+        js_errors,
+        network_log,
+        login,
+        journal,
+        recorder,
+        redaction,
+        auth,
+        embed,
+        webdriver,
+        render_pool,
+        instance,
+        ..
+    } = state;
+
+    thread::spawn(move || {
+        let addr = format!("0.0.0.0:{}", port);
+        let server = match Server::http(&addr) {
+            Ok(s) => s,
+            Err(e) => {
+                log_error!("Failed to start HTTP server: {}", e);
+                return;
+            }
+        };
+
+        log_info!("Live stream: http://localhost:{}/live-stream", port);
+        log_info!("Viewer:      http://localhost:{}/", port);
+
+        for mut request in request_log::incoming(server) {
+            let request_url = request.url().to_string();
+            let (path, query) = parse_query(&request_url);
+            let url = request_url.as_str();
+
+            if let Some(response) = auth.check(&request, path, &query) {
+                let _ = request_log::respond(request, response);
+                continue;
+            }
+            if let Some(response) = auth.handle(&mut request, path, &query) {
+                let _ = request_log::respond(request, response);
+                continue;
+            }
+
+            // /frame is /live-stream without the JSON: raw JPEG, URL and time in headers
+            if path == "/live-stream" || path == "/frame" {
+                lifecycle.lock().unwrap().touch();
+                if let Some(scaler) = &scaler {
+                    let client = query
+                        .get("client")
+                        .cloned()
+                        .or_else(|| request.remote_addr().map(|addr| addr.ip().to_string()))
+                        .unwrap_or_default();
+                    let max_width = query.get("max_width").and_then(|w| w.parse().ok());
+                    scaler.lock().unwrap().observe(client, max_width);
+                }
+                let profile = query.get("filters");
+                if let Some(profile) = profile.filter(|p| !frame_filters.has(p)) {
+                    let body = serde_json::json!({
+                        "error": format!("unknown filter profile '{}'", profile),
+                        "profiles": frame_filters.names(),
+                    });
+                    let _ = request_log::respond(request, json_response(400, &body));
+                    continue;
+                }
+                let buffer = screenshot_buffer.lock().unwrap().clone();
+                if let Some(ref jpeg_bytes) = buffer {
+                    {
+                        let mut stats = pipeline.lock().unwrap();
+                        if let Some(age) = stats.last_frame_age() {
+                            stats.record("capture_to_serve", age);
+                        }
+                    }
+                    let filtered = profile.map(|p| frame_filters.apply(p, *frames.borrow(), jpeg_bytes, &pipeline));
+                    if let Some(None) = filtered {
+                        let body = serde_json::json!({"error": "failed to filter the frame"});
+                        let _ = request_log::respond(request, json_response(500, &body));
+                        continue;
+                    }
+                    if path == "/frame" {
+                        let current = current_url.lock().unwrap().clone();
+                        // Header values must be ASCII; Url percent-encodes the rest
+                        let current = url::Url::parse(&current).map(|u| u.to_string()).unwrap_or(current);
+                        let mut response = Response::from_data(filtered.flatten().unwrap_or_else(|| jpeg_bytes.clone()))
+                            .with_header(Header::from_bytes(&b"Content-Type"[..], &b"image/jpeg"[..]).unwrap())
+                            .with_header(Header::from_bytes(&b"X-Frame-Timestamp"[..], now_millis().to_string().as_bytes()).unwrap())
+                            .with_header(Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap())
+                            .with_header(Header::from_bytes(&b"Access-Control-Expose-Headers"[..], &b"X-Frame-Url, X-Frame-Timestamp"[..]).unwrap())
+                            .with_header(Header::from_bytes(&b"Cache-Control"[..], &b"no-cache"[..]).unwrap());
+                        if let Ok(header) = Header::from_bytes(&b"X-Frame-Url"[..], current.as_bytes()) {
+                            response.add_header(header);
+                        }
+                        let _ = request_log::respond(request, response);
+                        continue;
+                    }
+                    let base64_frame = BASE64.encode(filtered.flatten().as_deref().unwrap_or(jpeg_bytes));
+                    let current = current_url.lock().unwrap().clone();
+                    let mut json = serde_json::json!({
+                        "frame": base64_frame,
+                        "url": current,
+                        "timestamp": std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap()
+                            .as_millis()
+                    });
+                    if let Some(hook) = &frame_hook {
+                        json["hook"] = hook.latest();
+                    }
+
+                    let response = Response::from_string(json.to_string())
+                        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+                        .with_header(Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap())
+                        .with_header(Header::from_bytes(&b"Cache-Control"[..], &b"no-cache"[..]).unwrap());
+                    let _ = request_log::respond(request, response);
+                } else if path == "/frame" {
+                    let _ = request_log::respond(request, Response::from_data(Vec::new()).with_status_code(503));
+                } else {
+                    let response = Response::from_string(r#"{"error":"no frame available"}"#)
+                        .with_status_code(503)
+                        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap());
+                    let _ = request_log::respond(request, response);
+                }
+            } else if path == "/mjpeg" {
+                lifecycle.lock().unwrap().touch();
+                let (buffer, frames) = (screenshot_buffer.clone(), frames.clone());
+                let trace = request_log::detach();
+                thread::spawn(move || {
+                    request_log::attach(trace);
+                    mjpeg::serve(request, buffer, &frames);
+                });
+            } else if path == "/ws" {
+                lifecycle.lock().unwrap().touch();
+                let source = ws::FrameSource { buffer: screenshot_buffer.clone(), frames: frames.clone(), current_url: current_url.clone() };
+                let json = query.get("format").is_some_and(|f| f == "json");
+                let trace = request_log::detach();
+                thread::spawn(move || {
+                    request_log::attach(trace);
+                    ws::serve(request, source, json);
+                });
+            } else if path == "/navigate" {
+                let parsed = if *request.method() == tiny_http::Method::Post {
+                    read_json_body::<NavigateRequest>(&mut request)
+                } else {
+                    // Navigate to URL: /navigate?url=https%3A%2F%2Fexample.com
+                    NavigateRequest::from_query(&query)
+                };
+                let response = match parsed {
+                    Ok(req) => navigate(req, &current_url, &locale, &command_tx),
+                    Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/headers" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => {
+                        let redact = query.get("redact").is_some_and(|v| v == "true" || v == "1");
+                        json_response(200, &headers::to_json(&extra_headers.lock().unwrap(), redact))
+                    }
+                    tiny_http::Method::Put => {
+                        match read_json_body::<std::collections::BTreeMap<String, String>>(&mut request).and_then(|h| headers::validate(&h).map(|_| h)) {
+                            Ok(headers) => command_response(commands::send(&command_tx, commands::PageCommand::SetHeaders { headers })),
+                            Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                        }
+                    }
+                    tiny_http::Method::Delete => command_response(commands::send(&command_tx, commands::PageCommand::SetHeaders { headers: Default::default() })),
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/auth" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &basic_auth.lock().unwrap().to_json()),
+                    tiny_http::Method::Post => {
+                        match read_json_body::<basic_auth::AuthRequest>(&mut request)
+                            .and_then(|req| basic_auth.lock().unwrap().set(req.username, req.password, req.origin))
+                        {
+                            Ok(()) => json_response(200, &basic_auth.lock().unwrap().to_json()),
+                            Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                        }
+                    }
+                    tiny_http::Method::Delete => {
+                        basic_auth.lock().unwrap().clear();
+                        json_response(200, &basic_auth.lock().unwrap().to_json())
+                    }
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/downloads" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &downloads.lock().unwrap().to_json()),
+                    tiny_http::Method::Delete => json_response(200, &serde_json::json!({"deleted": downloads.lock().unwrap().clear()})),
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if let Some(rest) = path.strip_prefix("/downloads/") {
+                match (request.method(), rest.strip_suffix("/file")) {
+                    (tiny_http::Method::Get, Some(id)) => {
+                        let download = downloads.lock().unwrap().get(id).cloned();
+                        match download {
+                            Some(d) if d.state == downloads::State::Completed => match std::fs::File::open(&d.path) {
+                                Ok(file) => {
+                                    let disposition = d.content_disposition();
+                                    let response = Response::from_file(file)
+                                        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/octet-stream"[..]).unwrap())
+                                        .with_header(Header::from_bytes(&b"Content-Disposition"[..], disposition.as_bytes()).unwrap());
+                                    let _ = request_log::respond(request, response);
+                                }
+                                Err(e) => {
+                                    let _ = request_log::respond(request, json_response(410, &serde_json::json!({"error": format!("download file is gone: {}", e)})));
+                                }
+                            },
+                            Some(d) => {
+                                let error = format!("download {} is {}", d.id, d.state.name());
+                                let _ = request_log::respond(request, json_response(409, &serde_json::json!({"error": error})));
+                            }
+                            None => {
+                                let _ = request_log::respond(request, json_response(404, &serde_json::json!({"error": format!("no download {}", id)})));
+                            }
+                        }
+                    }
+                    (tiny_http::Method::Delete, None) if !rest.contains('/') => {
+                        let response = match downloads.lock().unwrap().remove(rest) {
+                            Ok(()) => json_response(200, &serde_json::json!({"deleted": rest})),
+                            Err((status, e)) => json_response(status, &serde_json::json!({"error": e})),
+                        };
+                        let _ = request_log::respond(request, response);
+                    }
+                    (tiny_http::Method::Get | tiny_http::Method::Delete, _) => {
+                        let _ = request_log::respond(request, json_response(404, &serde_json::json!({"error": "not found"})));
+                    }
+                    _ => {
+                        let _ = request_log::respond(request, json_response(405, &serde_json::json!({"error": "method not allowed"})));
+                    }
+                }
+            } else if path == "/errors" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &js_errors.lock().unwrap().to_json()),
+                    tiny_http::Method::Delete => {
+                        js_errors.lock().unwrap().clear();
+                        json_response(200, &serde_json::json!({"status": "cleared"}))
+                    }
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/network" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => match network_log::Filter::from_query(&query) {
+                        Ok(filter) => json_response(200, &network_log.lock().unwrap().to_json(&filter)),
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    },
+                    tiny_http::Method::Delete => {
+                        network_log.lock().unwrap().clear();
+                        json_response(200, &serde_json::json!({"status": "cleared"}))
+                    }
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/har" {
+                let include_bodies = query.get("include_bodies").is_some_and(|v| v == "true" || v == "1");
+                let response = match har::export(&command_tx, &network_log, include_bodies) {
+                    Ok(har) => json_response(200, &har),
+                    Err(e) => json_response(e.status, &serde_json::json!({"error": e.message})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/network/config" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &network_log.lock().unwrap().config_json()),
+                    tiny_http::Method::Post => match read_json_body::<network_log::ConfigRequest>(&mut request) {
+                        Ok(req) => {
+                            let mut log = network_log.lock().unwrap();
+                            log.set_persist(req.persist);
+                            json_response(200, &log.config_json())
+                        }
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    },
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/dialog" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &dialogs.lock().unwrap().settings_json()),
+                    tiny_http::Method::Post => match read_json_body::<dialogs::DialogRequest>(&mut request) {
+                        Ok(req) => {
+                            let mut dialogs = dialogs.lock().unwrap();
+                            dialogs.configure(req);
+                            json_response(200, &dialogs.settings_json())
+                        }
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    },
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/dialogs" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &dialogs.lock().unwrap().to_json()),
+                    tiny_http::Method::Delete => {
+                        dialogs.lock().unwrap().clear();
+                        json_response(200, &serde_json::json!({"status": "cleared"}))
+                    }
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/cpu-throttle" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &cpu_throttle.lock().unwrap().to_json()),
+                    tiny_http::Method::Post => {
+                        match read_json_body::<cpu_throttle::ThrottleRequest>(&mut request).and_then(|req| cpu_throttle.lock().unwrap().set(req.rate)) {
+                            Ok(()) => json_response(200, &cpu_throttle.lock().unwrap().to_json()),
+                            Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                        }
+                    }
+                    tiny_http::Method::Delete => {
+                        let mut throttle = cpu_throttle.lock().unwrap();
+                        let _ = throttle.set(1.0);
+                        json_response(200, &throttle.to_json())
+                    }
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/offline" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &offline.lock().unwrap().to_json()),
+                    tiny_http::Method::Post => match read_json_body::<offline::OfflineRequest>(&mut request) {
+                        Ok(req) => {
+                            let mut offline = offline.lock().unwrap();
+                            offline.set(req.offline);
+                            json_response(200, &offline.to_json())
+                        }
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    },
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/useragent" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &serde_json::json!({"user_agent": locale.lock().unwrap().user_agent_override()})),
+                    tiny_http::Method::Post => match read_json_body::<UserAgentRequest>(&mut request).and_then(|req| parse_user_agent(&req.value)) {
+                        Ok(value) => {
+                            locale.lock().unwrap().set_user_agent(Some(value.clone()));
+                            json_response(200, &serde_json::json!({"user_agent": value, "status": "applying"}))
+                        }
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    },
+                    tiny_http::Method::Delete => {
+                        locale.lock().unwrap().set_user_agent(None);
+                        json_response(200, &serde_json::json!({"user_agent": null, "status": "applying"}))
+                    }
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/emulation" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &locale.lock().unwrap().emulation_json()),
+                    tiny_http::Method::Post => match read_json_body::<locale::EmulationRequest>(&mut request) {
+                        Ok(req) => command_response(locale::emulate(&command_tx, &locale, req)),
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    },
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/locale" {
+                let _ = request_log::respond(request, json_response(200, &locale.lock().unwrap().to_json()));
+            } else if path == "/lifecycle" {
+                let _ = request_log::respond(request, json_response(200, &lifecycle.lock().unwrap().to_json()));
+            } else if (path == "/lifecycle/pause" || path == "/lifecycle/resume") && *request.method() == tiny_http::Method::Post {
+                let mut state = lifecycle.lock().unwrap();
+                state.set_paused(path == "/lifecycle/pause");
+                let body = state.to_json();
+                drop(state);
+                let _ = request_log::respond(request, json_response(200, &body));
+            } else if let Some(response) = login::handle(&request, path, &login) {
+                let _ = request_log::respond(request, response);
+            } else if let Some(response) = blocker::handle(&request, url, &blocker, false) {
+                let _ = request_log::respond(request, response);
+            } else if path == "/host-rules" {
+                let response = host_rules::handle(&mut request, &query, &host_rules);
+                let _ = request_log::respond(request, response);
+            } else if path == "/mock" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &mocks.lock().unwrap().to_json()),
+                    tiny_http::Method::Post => match read_json_body::<mock::MockRequest>(&mut request).and_then(|req| mocks.lock().unwrap().add(req)) {
+                        Ok(rule) => json_response(200, &serde_json::json!(rule)),
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    },
+                    tiny_http::Method::Delete => json_response(200, &serde_json::json!({"deleted": mocks.lock().unwrap().clear()})),
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if let Some(id) = path.strip_prefix("/mock/") {
+                let response = match (request.method(), id.parse::<u64>()) {
+                    (tiny_http::Method::Delete, Ok(id)) if mocks.lock().unwrap().remove(id) => json_response(200, &serde_json::json!({"deleted": id})),
+                    (tiny_http::Method::Delete, _) => json_response(404, &serde_json::json!({"error": format!("no mock rule {}", id)})),
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/block" {
+                let page_url = current_url.lock().unwrap().clone();
+                let response = url_block::handle(&mut request, &query, &url_blocks, &page_url);
+                let _ = request_log::respond(request, response);
+            } else if path == "/permissions" {
+                let method = request.method().clone();
+                let response = match method {
+                    tiny_http::Method::Get => json_response(200, &permissions.lock().unwrap().to_json()),
+                    tiny_http::Method::Post => {
+                        let parsed = read_json_body::<permissions::PermissionsRequest>(&mut request).and_then(|req| {
+                            permissions::validate(&req.grant)?;
+                            permissions::validate(&req.deny)?;
+                            Ok(req)
+                        });
+                        match parsed {
+                            Ok(req) => command_response(commands::send(&command_tx, commands::PageCommand::SetPermissions {
+                                origin: req.origin,
+                                grant: req.grant,
+                                deny: req.deny,
+                            })),
+                            Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                        }
+                    }
+                    tiny_http::Method::Delete => command_response(commands::send(&command_tx, commands::PageCommand::ResetPermissions)),
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/annotations" || path.starts_with("/annotations/") {
+                let response = annotations::handle(&mut request, path, &annotations);
+                let _ = request_log::respond(request, response);
+            } else if path == "/hud" {
+                let response = hud::handle(&mut request, &hud);
+                let _ = request_log::respond(request, response);
+            } else if path == "/theme" {
+                // The capture loop applies the change before its next frame
+                let (response, _) = theme::handle(&mut request, &theme, None);
+                let _ = request_log::respond(request, response);
+            } else if path == "/ticker" {
+                let response = ticker::handle(&mut request, &ticker);
+                let _ = request_log::respond(request, response);
+            } else if path == "/bundle/export" || path == "/bundle/import" {
+                let response = bundle::handle(&mut request, path, &query, &shared);
+                let _ = request_log::respond(request, response);
+            } else if path == "/render" {
+                let wait_settled = query.get("wait_settled").is_some_and(|v| v == "true" || v == "1");
+                let settle = if wait_settled { settle_defaults.with_query(&query).map(Some) } else { Ok(None) };
+                let dimension = |name: &str, default: u32| match query.get(name) {
+                    None => Ok(default),
+                    Some(v) => v.parse::<u32>().ok().filter(|v| (1..=8192).contains(v)).ok_or(format!("{} must be 1..8192", name)),
+                };
+                let target = query.get("url").cloned().unwrap_or_else(|| current_url.lock().unwrap().clone());
+                let job = settle.and_then(|settle| {
+                    url::Url::parse(&target).map_err(|e| format!("invalid url '{}': {}", target, e))?;
+                    let profile = match query.get("locale") {
+                        Some(tag) => Some(tag.parse::<locale::LocaleProfile>()?),
+                        None => locale.lock().unwrap().default.clone(),
+                    };
+                    Ok(render_pool::RenderJob {
+                        url: target,
+                        viewport: (dimension("width", viewport.0)?, dimension("height", viewport.1)?),
+                        settle,
+                        deterministic: query.get("deterministic").is_some_and(|v| v == "true" || v == "1"),
+                        locale: profile,
+                        timeout: render_pool.timeout,
+                    })
+                });
+                let as_json = query.get("format").is_some_and(|f| f == "json");
+                match job.map(|job| render_pool.submit(job)) {
+                    Ok(Ok(wait)) => {
+                        // Renders take seconds; keep serving the stream meanwhile
+                        let trace = request_log::detach();
+                        thread::spawn(move || {
+                            request_log::attach(trace);
+                            let response = match wait() {
+                                Ok(body) if as_json => json_response(200, &body),
+                                Ok(body) => render_response(&body),
+                                Err(e) => json_response(e.status, &serde_json::json!({"error": e.message})),
+                            };
+                            let _ = request_log::respond(request, response);
+                        });
+                    }
+                    Ok(Err(e)) => {
+                        let mut response = json_response(e.status, &serde_json::json!({"error": e.message}));
+                        if e.status == 503 {
+                            let retry_after = render_pool.retry_after().to_string();
+                            response.add_header(Header::from_bytes(&b"Retry-After"[..], retry_after.as_bytes()).unwrap());
+                        }
+                        let _ = request_log::respond(request, response);
+                    }
+                    Err(e) => {
+                        let _ = request_log::respond(request, json_response(400, &serde_json::json!({"error": e})));
+                    }
+                }
+            } else if path == "/render/pool" {
+                let _ = request_log::respond(request, json_response(200, &render_pool.to_json()));
+            } else if path == "/captures" {
+                let method = request.method().clone();
+                let response = match (method, &captures) {
+                    (_, None) => json_response(404, &serde_json::json!({"error": "captures are disabled, start with --capture-dir"})),
+                    (tiny_http::Method::Get, Some(store)) => {
+                        let store = store.lock().unwrap();
+                        json_response(200, &serde_json::json!({"dir": store.dir(), "captures": store.list()}))
+                    }
+                    (tiny_http::Method::Post, Some(store)) => {
+                        let name = read_json_body::<captures::CaptureRequest>(&mut request)
+                            .and_then(|req| store.lock().unwrap().resolve_name(req.name));
+                        match name {
+                            Ok(name) => match commands::send(&command_tx, commands::PageCommand::SaveCapture { name }) {
+                                Ok(meta) => json_response(201, &meta),
+                                Err(e) => json_response(e.status, &serde_json::json!({"error": e.message})),
+                            },
+                            Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                        }
+                    }
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if let Some(response) = diagnostics_response(path) {
+                let _ = request_log::respond(request, response);
+            } else if path == "/stats/buffers" {
+                let response = buffers::response(vec![
+                    journal.lock().unwrap().buffer_stats(),
+                    recorder.lock().unwrap().buffer_stats(),
+                    annotations.lock().unwrap().buffer_stats(),
+                    dialogs.lock().unwrap().buffer_stats(),
+                    js_errors.lock().unwrap().buffer_stats(),
+                    network_log.lock().unwrap().buffer_stats(),
+                    downloads.lock().unwrap().buffer_stats(),
+                ]);
+                let _ = request_log::respond(request, response);
+            } else if path == "/instance" {
+                let _ = request_log::respond(request, json_response(200, &instance.to_json()));
+            } else if path == "/click" || path == "/type" || path == "/hover" {
+                let response = if request.method() != &tiny_http::Method::Post {
+                    json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                } else {
+                    match read_json_body::<commands::InteractRequest>(&mut request) {
+                        Ok(commands::InteractRequest { x: Some(x), y: Some(y), button, click_count, .. }) if path == "/click" => {
+                            command_response(commands::send(&command_tx, commands::PageCommand::ClickAt { x, y, button, click_count }))
+                        }
+                        Ok(commands::InteractRequest { x: Some(x), y: Some(y), .. }) if path == "/hover" => {
+                            command_response(commands::send(&command_tx, commands::PageCommand::HoverAt { x, y }))
+                        }
+                        // /type without a selector types into the focused element
+                        Ok(req) if req.selector.trim().is_empty() && (path != "/type" || req.frame.is_some()) => {
+                            let error = match path {
+                                "/type" => "frame needs a selector",
+                                _ => "missing selector, or x and y",
+                            };
+                            json_response(400, &serde_json::json!({"error": error}))
+                        }
+                        Ok(req) => {
+                            let selector = req.target_selector();
+                            let (frame, stability) = (req.frame, req.stability);
+                            let command = match path {
+                                "/click" => commands::PageCommand::Click { selector, frame, stability },
+                                "/hover" => commands::PageCommand::Hover { selector, frame, stability },
+                                _ => commands::PageCommand::Type { selector, text: req.text, submit: req.submit, secret: false, method: req.method, frame, stability },
+                            };
+                            command_response(commands::send(&command_tx, command))
+                        }
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    }
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/select" {
+                let response = if request.method() != &tiny_http::Method::Post {
+                    json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                } else {
+                    match read_json_body::<commands::SelectRequest>(&mut request).and_then(commands::SelectRequest::into_command) {
+                        Ok(command) => command_response(commands::send(&command_tx, command)),
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    }
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/upload" {
+                let response = if request.method() != &tiny_http::Method::Post {
+                    json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                } else {
+                    match file_upload::read_form(&mut request) {
+                        Ok((selector, files)) => command_response(file_upload::attach(&command_tx, &file_uploads, selector, files)),
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    }
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/evaluate" {
+                let response = if request.method() != &tiny_http::Method::Post {
+                    json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                } else {
+                    match read_json_body::<commands::EvaluateRequest>(&mut request) {
+                        Ok(req) => {
+                            let command = commands::PageCommand::Evaluate { expression: req.expression, await_promise: req.await_promise };
+                            command_response(commands::send(&command_tx, command).map(|result| serde_json::json!({ "result": result })))
+                        }
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    }
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/press" {
+                let response = if request.method() != &tiny_http::Method::Post {
+                    json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                } else {
+                    match read_json_body::<keys::PressRequest>(&mut request).and_then(keys::PressRequest::parse) {
+                        Ok(press) => command_response(commands::send(&command_tx, commands::PageCommand::Press { press })),
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    }
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/cookies" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => command_response(commands::send(&command_tx, commands::PageCommand::Cookies)),
+                    tiny_http::Method::Post => match read_json_body::<Vec<cookies::Cookie>>(&mut request) {
+                        Ok(cookies) => command_response(commands::send(&command_tx, commands::PageCommand::SetCookies { cookies })),
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    },
+                    tiny_http::Method::Delete => match query.get("name").filter(|n| !n.is_empty()) {
+                        Some(name) => {
+                            let command = commands::PageCommand::DeleteCookies { name: name.clone(), domain: query.get("domain").cloned() };
+                            command_response(commands::send(&command_tx, command))
+                        }
+                        None => json_response(400, &serde_json::json!({"error": "missing name parameter"})),
+                    },
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if let Some(area) = path.strip_prefix("/storage/").filter(|a| *a == "local" || *a == "session") {
+                let action = match request.method() {
+                    tiny_http::Method::Get => Some(Ok(storage::Action::Get)),
+                    tiny_http::Method::Put => Some(read_json_body::<storage::PutRequest>(&mut request).map(storage::Action::Put)),
+                    tiny_http::Method::Delete => Some(Ok(storage::Action::Delete {
+                        key: query.get("key").cloned(),
+                        all: query.get("all").is_some_and(|v| v == "true" || v == "1"),
+                    })),
+                    _ => None,
+                };
+                let response = match action {
+                    Some(Ok(action)) => command_response(storage::handle(&command_tx, area, action)),
+                    Some(Err(e)) => json_response(400, &serde_json::json!({"error": e})),
+                    None => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/frames-tree" {
+                let _ = request_log::respond(request, command_response(commands::send(&command_tx, commands::PageCommand::FrameTree)));
+            } else if path == "/resources" {
+                let _ = request_log::respond(request, command_response(commands::send(&command_tx, commands::PageCommand::Resources)));
+            } else if path == "/perf" {
+                let _ = request_log::respond(request, command_response(commands::send(&command_tx, commands::PageCommand::Perf)));
+            } else if path == "/trace/start" || path == "/trace/stop" {
+                let response = if request.method() != &tiny_http::Method::Post {
+                    json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                } else if path == "/trace/start" {
+                    match read_json_body::<trace::StartRequest>(&mut request) {
+                        Ok(req) => command_response(commands::send(&command_tx, commands::PageCommand::TraceStart { categories: req.categories })),
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    }
+                } else {
+                    match read_json_body::<trace::StopRequest>(&mut request) {
+                        Ok(req) => {
+                            let file = req.path.map(|path| trace::create(&path).map(|file| (path, file))).transpose();
+                            command_response(file.and_then(|file| {
+                                let trace = commands::send(&command_tx, commands::PageCommand::TraceStop);
+                                match file {
+                                    Some((path, file)) => match trace {
+                                        Ok(trace) => trace::save(file, &path, &trace),
+                                        Err(e) => {
+                                            // Nothing was recorded, so leave no empty file behind
+                                            let _ = std::fs::remove_file(&path);
+                                            Err(e)
+                                        }
+                                    },
+                                    None => trace,
+                                }
+                            }))
+                        }
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    }
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/back" || path == "/forward" {
+                let response = if request.method() != &tiny_http::Method::Post {
+                    json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                } else {
+                    let delta = if path == "/back" { -1 } else { 1 };
+                    command_response(commands::send(&command_tx, commands::PageCommand::History { delta }))
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/reload" {
+                let response = if request.method() != &tiny_http::Method::Post {
+                    json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                } else {
+                    match read_json_body::<scroll::ReloadRequest>(&mut request) {
+                        Ok(req) => {
+                            let ignore_cache = req.ignore_cache || query.get("ignore_cache").is_some_and(|v| v == "true" || v == "1");
+                            command_response(commands::send(&command_tx, commands::PageCommand::Reload { restore_scroll: req.restore_scroll, ignore_cache }))
+                        }
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    }
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/handoff/export" || path == "/handoff/import" {
+                let command = if request.method() != &tiny_http::Method::Post {
+                    Err(json_response(405, &serde_json::json!({"error": "method not allowed"})))
+                } else if path == "/handoff/export" {
+                    Ok(commands::PageCommand::ExportState)
+                } else {
+                    read_json_body::<handoff::HandoffState>(&mut request)
+                        .map(|state| commands::PageCommand::ImportState { state: Box::new(state) })
+                        .map_err(|e| json_response(400, &serde_json::json!({"error": e})))
+                };
+                let response = match command {
+                    Ok(command) => command_response(commands::send(&command_tx, command)),
+                    Err(response) => response,
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/latency-test" {
+                let frames = query
+                    .get("frames")
+                    .and_then(|f| f.parse().ok())
+                    .unwrap_or(latency::DEFAULT_FRAMES)
+                    .clamp(1, latency::MAX_FRAMES);
+                let response = match commands::send(&command_tx, commands::PageCommand::LatencyTest { frames }) {
+                    Ok(mut body) => {
+                        let captured_at = body["captured_at"].as_u64().unwrap_or_default() as u128;
+                        let stats = pipeline.lock().unwrap();
+                        body["capture_to_serve_ms"] = stats.stage_summary("capture_to_serve");
+                        body["last_capture_to_response_ms"] = (now_millis().saturating_sub(captured_at) as u64).into();
+                        body["fps"] = stats.fps().into();
+                        body["quality"] = JPEG_QUALITY.into();
+                        body["capture_scale"] = scaler.as_ref().map_or(1.0, |s| s.lock().unwrap().scale()).into();
+                        body["viewport"] = serde_json::json!({"width": viewport.0, "height": viewport.1});
+                        json_response(200, &body)
+                    }
+                    Err(e) => json_response(e.status, &serde_json::json!({"error": e.message})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/status" {
+                let _ = request_log::respond(request, json_response(200, &serde_json::json!(status::report(&shared))));
+            } else if path == "/metrics" {
+                let response = Response::from_string(status::metrics(&shared))
+                    .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..]).unwrap());
+                let _ = request_log::respond(request, response);
+            } else if path == "/stats/pipeline" {
+                let mut body = pipeline.lock().unwrap().to_json();
+                if let Some(scaler) = &scaler {
+                    body["capture_scale"] = scaler.lock().unwrap().to_json();
+                }
+                let _ = request_log::respond(request, json_response(200, &body));
+            } else if path == "/record/start" && *request.method() == tiny_http::Method::Post {
+                let response = match recorder.lock().unwrap().start() {
+                    Ok(started_at) => json_response(200, &serde_json::json!({"recording": true, "started_at": started_at})),
+                    Err(e) => json_response(409, &serde_json::json!({"error": e})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/record/stop" && *request.method() == tiny_http::Method::Post {
+                let response = match recorder.lock().unwrap().stop() {
+                    Some(track) => json_response(200, &serde_json::json!({
+                        "recording": false,
+                        "started_at": track.started_at,
+                        "stopped_at": track.stopped_at,
+                        "events": track.events.len(),
+                    })),
+                    None => json_response(409, &serde_json::json!({"error": "no recording is running"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/record/last/events" {
+                let recorder = recorder.lock().unwrap();
+                let response = match (recorder.latest(), query.get("format").map(String::as_str)) {
+                    (None, _) => json_response(404, &serde_json::json!({"error": "nothing has been recorded"})),
+                    (Some(track), Some("json")) => json_response(200, &serde_json::json!(track)),
+                    (Some(track), None | Some("vtt")) => Response::from_string(track.to_vtt())
+                        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/vtt; charset=utf-8"[..]).unwrap())
+                        .with_header(
+                            Header::from_bytes(
+                                &b"Content-Disposition"[..],
+                                format!("attachment; filename=\"events-{}.vtt\"", track.started_at).as_bytes(),
+                            )
+                            .unwrap(),
+                        ),
+                    (Some(_), Some(other)) => json_response(400, &serde_json::json!({"error": format!("unknown format '{}', expected vtt or json", other)})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/redact" {
+                let response = match *request.method() {
+                    tiny_http::Method::Get => json_response(200, &redaction.lock().unwrap().to_json()),
+                    tiny_http::Method::Post | tiny_http::Method::Delete => {
+                        let selectors = if *request.method() == tiny_http::Method::Delete {
+                            Ok(Vec::new())
+                        } else {
+                            read_json_body::<redact::RedactRequest>(&mut request).map(|r| r.selectors)
+                        };
+                        match selectors {
+                            Ok(selectors) => {
+                                let mut rules = redaction.lock().unwrap();
+                                match rules.set(selectors) {
+                                    Ok(()) => json_response(200, &rules.to_json()),
+                                    Err(e) => json_response(500, &serde_json::json!({"error": format!("rules applied but not saved: {}", e)})),
+                                }
+                            }
+                            Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                        }
+                    }
+                    _ => json_response(405, &serde_json::json!({"error": "use GET, POST or DELETE"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/ocr" {
+                let frame = screenshot_buffer.lock().unwrap().clone();
+                let seq = *frames.borrow();
+                match frame {
+                    Some(frame) => {
+                        // Recognition takes a while; keep serving the stream meanwhile
+                        let ocr = ocr.clone();
+                        let query = query.clone();
+                        let trace = request_log::detach();
+                        thread::spawn(move || {
+                            request_log::attach(trace);
+                            let _ = request_log::respond(request, ocr::response(&ocr, seq, &frame, &query));
+                        });
+                    }
+                    None => {
+                        let _ = request_log::respond(request, json_response(503, &serde_json::json!({"error": "no frame available"})));
+                    }
+                }
+            } else if path == "/probe/color" || path == "/probe/selector-color" {
+                let frame = || screenshot_buffer.lock().unwrap().clone();
+                let result = probe::single(&command_tx, frame, viewport, path == "/probe/selector-color", &query);
+                let _ = request_log::respond(request, command_response(result));
+            } else if path == "/probe" {
+                let response = if request.method() != &tiny_http::Method::Post {
+                    json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                } else {
+                    match read_json_body::<probe::ProbeRequest>(&mut request) {
+                        Ok(req) => {
+                            let frame = || screenshot_buffer.lock().unwrap().clone();
+                            let seq = *frames.borrow();
+                            let result = probe::run(&command_tx, frame, viewport, &req.probes)
+                                .map(|results| serde_json::json!({"seq": seq, "results": results}));
+                            command_response(result)
+                        }
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    }
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/source/view" {
+                let _ = request_log::respond(request, source::viewer_response());
+            } else if path == "/screenshot" {
+                let response = match screenshot::ScreenshotRequest::from_query(&query) {
+                    Ok(req) => {
+                        let content_type = req.content_type();
+                        match commands::send(&command_tx, commands::PageCommand::Screenshot { request: req }) {
+                            Ok(body) => match body["image"].as_str().map(|i| BASE64.decode(i)) {
+                                Some(Ok(image)) => {
+                                    let mut response = Response::from_data(image)
+                                        .with_header(Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap())
+                                        .with_header(Header::from_bytes(&b"Cache-Control"[..], &b"no-cache"[..]).unwrap());
+                                    if body["clipped"].as_bool() == Some(true) {
+                                        response.add_header(Header::from_bytes(&b"X-Clipped"[..], &b"true"[..]).unwrap());
+                                    }
+                                    response
+                                }
+                                _ => json_response(500, &serde_json::json!({"error": "screenshot returned no image"})),
+                            },
+                            Err(e) => json_response(e.status, &serde_json::json!({"error": e.message})),
+                        }
+                    }
+                    Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/element" {
+                let response = match query.get("selector").filter(|s| !s.trim().is_empty()) {
+                    Some(selector) => {
+                        let all = query.get("all").is_some_and(|v| v == "true" || v == "1");
+                        command_response(element::query(&command_tx, selector, all))
+                    }
+                    None => json_response(400, &serde_json::json!({"error": "missing selector parameter"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/html" {
+                // Answered by the capture loop; nothing here waits on the frame buffer
+                let response = source::outer_html(&command_tx, query.get("selector").map(String::as_str));
+                let _ = request_log::respond(request, response);
+            } else if path == "/text" {
+                let response = match query.get("max_length").map(|v| v.parse::<usize>()).transpose() {
+                    Ok(max_length) => source::visible_text(&command_tx, query.get("selector").map(String::as_str), max_length),
+                    Err(_) => json_response(400, &serde_json::json!({"error": "max_length must be a non-negative integer"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/links" {
+                let same_origin = query.get("same_origin").is_some_and(|v| v == "true" || v == "1");
+                let response = command_response(links::list(&command_tx, query.get("selector").map(String::as_str), same_origin));
+                let _ = request_log::respond(request, response);
+            } else if path == "/source" {
+                let served = match query.get("kind").map(String::as_str) {
+                    None | Some("dom") => Ok(false),
+                    Some("served") => Ok(true),
+                    Some(other) => Err(format!("unknown kind '{}', expected dom or served", other)),
+                };
+                let response = match served {
+                    Ok(served) => command_response(commands::send(&command_tx, commands::PageCommand::Source { served })),
+                    Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/events" {
+                let events = events.clone();
+                let trace = request_log::detach();
+                thread::spawn(move || {
+                    request_log::attach(trace);
+                    events::serve(request, &events);
+                });
+            } else if path == "/decode-qr/events" {
+                let qr_watch = qr_watch.clone();
+                let trace = request_log::detach();
+                thread::spawn(move || {
+                    request_log::attach(trace);
+                    qr::serve_events(request, &qr_watch);
+                });
+            } else if path == "/decode-qr" {
+                let frame = screenshot_buffer.lock().unwrap().clone();
+                let seq = *frames.borrow();
+                let response = match frame {
+                    Some(frame) => qr::response(&qr_watch, seq, &frame, &query),
+                    None => json_response(503, &serde_json::json!({"error": "no frame available"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/frame-hook" {
+                let body = match &frame_hook {
+                    Some(hook) => hook.to_json(),
+                    None => serde_json::json!({"enabled": false}),
+                };
+                let _ = request_log::respond(request, json_response(200, &body));
+            } else if path == "/sinks" {
+                let sinks: Vec<serde_json::Value> = sinks.iter().map(|s| s.to_json()).collect();
+                let _ = request_log::respond(request, json_response(200, &serde_json::json!({"sinks": sinks})));
+            } else if path == "/uploads" {
+                let response = match &uploader {
+                    Some(uploader) => json_response(200, &uploader.to_json()),
+                    None => json_response(404, &serde_json::json!({"error": "uploads are disabled, start with --upload-url"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/embed" {
+                let _ = request_log::respond(request, embed.response(&query));
+            } else if let Some(session) = webdriver.as_ref().filter(|_| path == webdriver::PREFIX || path.starts_with("/wd/hub/")) {
+                let response = webdriver::handle(&mut request, path, &command_tx, session);
+                let _ = request_log::respond(request, response);
+            } else if path == "/fonts" {
+                let _ = request_log::respond(request, json_response(200, &fonts.to_json()));
+            } else if path == "/geometry" {
+                let frame = screenshot_buffer.lock().unwrap().as_deref().and_then(overlay::frame_dimensions);
+                let response = match frame {
+                    Some((frame_width, frame_height)) => json_response(200, &serde_json::json!({
+                        "frame_width": frame_width,
+                        "frame_height": frame_height,
+                        "viewport_width": viewport.0,
+                        "viewport_height": viewport.1,
+                        "scale": frame_width as f64 / viewport.0.max(1) as f64,
+                    })),
+                    None => json_response(503, &serde_json::json!({"error": "no frame available"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if path == "/media" && *request.method() == tiny_http::Method::Get {
+                let req = media::MediaRequest { selector: query.get("selector").cloned(), time: None };
+                let _ = request_log::respond(request, command_response(media::control(&command_tx, "list", req)));
+            } else if path == "/media/emulation" {
+                let response = match request.method() {
+                    tiny_http::Method::Get => json_response(200, &media_emulation.lock().unwrap().to_json()),
+                    tiny_http::Method::Post => match read_json_body::<media_emulation::MediaEmulation>(&mut request) {
+                        Ok(emulation) => {
+                            let mut emulated = media_emulation.lock().unwrap();
+                            emulated.set(emulation);
+                            json_response(200, &emulated.to_json())
+                        }
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    },
+                    tiny_http::Method::Delete => {
+                        let mut emulated = media_emulation.lock().unwrap();
+                        emulated.set(media_emulation::MediaEmulation::default());
+                        json_response(200, &emulated.to_json())
+                    }
+                    _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                };
+                let _ = request_log::respond(request, response);
+            } else if let Some(action @ ("pause" | "play" | "seek")) = path.strip_prefix("/media/") {
+                let response = if *request.method() != tiny_http::Method::Post {
+                    json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                } else {
+                    match read_json_body::<media::MediaRequest>(&mut request) {
+                        Ok(req) => command_response(media::control(&command_tx, action, req)),
+                        Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                    }
+                };
+                let _ = request_log::respond(request, response);
+            } else if url == "/" {
+                let html = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Rust Browser Claude - Live Stream</title>
+    <style>
+        body { margin: 0; background: #1a1a1a; display: flex; flex-direction: column; align-items: center; min-height: 100vh; padding: 20px; box-sizing: border-box; }
+        #controls { display: flex; gap: 10px; margin-bottom: 10px; width: 100%; max-width: 1200px; }
+        #url-input { flex: 1; padding: 8px 12px; border-radius: 4px; border: none; font-size: 14px; }
+        #go-btn, .tool-btn { padding: 8px 16px; background: #4a90d9; color: white; border: none; border-radius: 4px; cursor: pointer; }
+        #go-btn:hover, .tool-btn:hover { background: #3a80c9; }
+        .tool-btn.active { background: #d94a4a; }
+        #stage { position: relative; line-height: 0; }
+        #stage.drawing { cursor: crosshair; }
+        #rubber { position: absolute; border: 2px dashed #f33; display: none; pointer-events: none; }
+        img { max-width: 100%; max-height: calc(100vh - 100px); border: 1px solid #333; user-select: none; -webkit-user-drag: none; }
+        #status { position: fixed; top: 10px; right: 10px; color: #0f0; font-family: monospace; background: rgba(0,0,0,0.7); padding: 5px 10px; border-radius: 4px; }
+        #current-url { color: #888; font-family: monospace; font-size: 12px; margin-bottom: 10px; }
+    </style>
+</head>
+<body>
+    <div id="controls">
+        <input type="text" id="url-input" placeholder="Enter URL..." />
+        <button id="go-btn">Go</button>
+        <button id="draw-btn" class="tool-btn" title="Drag on the stream to annotate it">Draw</button>
+        <button id="clear-btn" class="tool-btn" title="Remove all annotations">Clear notes</button>
+    </div>
+    <div id="current-url">-</div>
+    <div id="status">Connecting...</div>
+    <div id="stage"><img id="screen" /><div id="rubber"></div></div>
+    <script>
+        const img = document.getElementById('screen');
+        const status = document.getElementById('status');
+        const currentUrlEl = document.getElementById('current-url');
+        const urlInput = document.getElementById('url-input');
+        const goBtn = document.getElementById('go-btn');
+        let frameCount = 0;
+
+        async function navigate(url) {
+            if (!url.startsWith('http')) url = 'https://' + url;
+            await fetch('/navigate?url=' + encodeURIComponent(url));
+        }
+
+        goBtn.onclick = () => navigate(urlInput.value);
+        urlInput.onkeydown = (e) => { if (e.key === 'Enter') navigate(urlInput.value); };
+
+        // Draw mode: drag a box on the image, converted to frame pixels via /geometry
+        const stage = document.getElementById('stage');
+        const rubber = document.getElementById('rubber');
+        const drawBtn = document.getElementById('draw-btn');
+        let drawing = false;
+        let dragStart = null;
+
+        drawBtn.onclick = () => {
+            drawing = !drawing;
+            drawBtn.classList.toggle('active', drawing);
+            stage.classList.toggle('drawing', drawing);
+        };
+        document.getElementById('clear-btn').onclick = () => fetch('/annotations', { method: 'DELETE' });
+
+        function stagePoint(e) {
+            const rect = img.getBoundingClientRect();
+            return {
+                x: Math.max(0, Math.min(rect.width, e.clientX - rect.left)),
+                y: Math.max(0, Math.min(rect.height, e.clientY - rect.top))
+            };
+        }
+
+        stage.onmousedown = (e) => {
+            if (!drawing) return;
+            e.preventDefault();
+            dragStart = stagePoint(e);
+        };
+        window.addEventListener('mousemove', (e) => {
+            if (!dragStart) return;
+            const p = stagePoint(e);
+            rubber.style.display = 'block';
+            rubber.style.left = Math.min(p.x, dragStart.x) + 'px';
+            rubber.style.top = Math.min(p.y, dragStart.y) + 'px';
+            rubber.style.width = Math.abs(p.x - dragStart.x) + 'px';
+            rubber.style.height = Math.abs(p.y - dragStart.y) + 'px';
+        });
+        window.addEventListener('mouseup', async (e) => {
+            if (!dragStart) return;
+            const start = dragStart;
+            const end = stagePoint(e);
+            dragStart = null;
+            rubber.style.display = 'none';
+            if (Math.abs(end.x - start.x) < 4 || Math.abs(end.y - start.y) < 4) return;
+
+            const geometry = await (await fetch('/geometry')).json();
+            if (!geometry.frame_width) return;
+            const sx = geometry.frame_width / img.clientWidth;
+            const sy = geometry.frame_height / img.clientHeight;
+            const label = prompt('Annotation label (optional):') || null;
+            await fetch('/annotations', {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({
+                    type: 'rect',
+                    x: Math.round(Math.min(start.x, end.x) * sx),
+                    y: Math.round(Math.min(start.y, end.y) * sy),
+                    w: Math.round(Math.abs(end.x - start.x) * sx),
+                    h: Math.round(Math.abs(end.y - start.y) * sy),
+                    color: '#ff3333',
+                    label: label,
+                    ttl_secs: 300
+                })
+            });
+        });
+
+        let frameUrl = null;
+
+        async function fetchFrame() {
+            try {
+                const response = await fetch('/frame');
+                if (response.ok) {
+                    const blob = await response.blob();
+                    if (frameUrl) URL.revokeObjectURL(frameUrl);
+                    frameUrl = URL.createObjectURL(blob);
+                    img.src = frameUrl;
+                    frameCount++;
+                    status.textContent = 'Frames: ' + frameCount;
+                    const url = response.headers.get('X-Frame-Url');
+                    if (url) {
+                        currentUrlEl.textContent = url;
+                        urlInput.value = url;
+                    }
+                }
+            } catch (e) {
+                status.textContent = 'Error: ' + e.message;
+            }
+
+            setTimeout(fetchFrame, 100);
+        }
+
+        status.textContent = 'Connected';
+        fetchFrame();
+    </script>
+</body>
+</html>"#;
+
+                let response = Response::from_string(html)
+                    .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap());
+                let _ = request_log::respond(request, response);
+            } else {
+                let response = Response::from_string("Not Found").with_status_code(404);
+                let _ = request_log::respond(request, response);
+            }
+        }
+    });
+}
+
+// ============== Headless Mode (Chrome CDP) ==============
+
+fn screenshot_params(format: chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat) -> chromiumoxide::page::ScreenshotParams {
+    use chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat;
+
+    let builder = chromiumoxide::page::ScreenshotParams::builder().format(format.clone());
+    if format == CaptureScreenshotFormat::Jpeg {
+        builder.quality(JPEG_QUALITY as i64).build()
+    } else {
+        builder.build()
+    }
+}
+
+async fn run_headless(args: Args, imported: Option<handoff::HandoffState>) -> Result<(), Box<dyn std::error::Error>> {
+    use chromiumoxide::browser::{Browser, BrowserConfig};
+    use futures::StreamExt;
+
+    log_info!("Starting headless browser...");
+
+    let screenshot_buffer: ScreenshotBuffer = Arc::new(Mutex::new(None));
+    let current_url: CurrentUrl = Arc::new(Mutex::new(args.url.clone()));
+
+    let blocker = blocker::load(args.filter_list.as_ref())?;
+
+    let host_rules = host_rules::HostRules::new(args.host_rules.clone());
+    let resolver_flag = host_rules.resolver_flag();
+
+    permissions::validate(&args.grant_permissions)?;
+    let mut basic_auth = basic_auth::BasicAuth::default();
+    if let Some((username, password)) = args.basic_auth.clone() {
+        basic_auth.set(username, password, args.basic_auth_origin.clone()).map_err(|e| format!("--basic-auth: {}", e))?;
+    }
+    if let Some(clip) = &args.clip {
+        clip.check_within(args.width as f64, args.height as f64).map_err(|e| format!("--clip: {}", e))?;
+    }
+    let (command_tx, mut command_rx): (commands::CommandSender, commands::CommandReceiver) =
+        tokio::sync::mpsc::unbounded_channel();
+    let (render_pool, render_rx) =
+        render_pool::RenderPool::new(args.render_concurrency, std::time::Duration::from_millis(args.render_timeout_ms));
+
+    let state = HeadlessState {
+        screenshot_buffer,
+        current_url: current_url.clone(),
+        blocker: Arc::new(Mutex::new(blocker)),
+        url_blocks: Arc::new(Mutex::new(url_block::BlockList::new(&args.block, &args.url).map_err(|e| format!("--block: {}", e))?)),
+        mocks: Arc::default(),
+        cpu_throttle: Arc::default(),
+        offline: Arc::default(),
+        media_emulation: Arc::new(Mutex::new(media_emulation::Media::new(args.color_scheme))),
+        tracer: Arc::default(),
+        host_rules: Arc::new(Mutex::new(host_rules)),
+        permissions: Arc::default(),
+        annotations: Arc::default(),
+        ticker: create_ticker(&args),
+        hud: Arc::new(Mutex::new(hud::Hud::new(
+            args.hud,
+            args.hud_items.clone(),
+            args.hud_position,
+            args.hud_color,
+            args.hud_background,
+        ))),
+        theme: Arc::new(Mutex::new(theme::Theme::load(args.theme_rules.as_deref(), args.dark_schedule, args.dark_auto)?)),
+        commands: command_tx,
+        viewport: (args.width, args.height),
+        settle: settle::SettleOptions::new(args.settle_frames, args.settle_interval, args.settle_timeout),
+        deterministic: deterministic::features(&args.deterministic_skip),
+        fonts: Arc::new(load_fonts(&args)?),
+        locale: Arc::new(Mutex::new(locale::LocaleState::new(
+            args.locale.clone(),
+            args.locale_clear_cache,
+            args.user_agent.clone(),
+            args.timezone.clone(),
+        ))),
+        captures: match &args.capture_dir {
+            Some(dir) => Some(Arc::new(Mutex::new(
+                captures::CaptureStore::open(dir).map_err(|e| format!("Failed to open capture dir {}: {}", dir.display(), e))?,
+            ))),
+            None => None,
+        },
+        navigation: Arc::new(Mutex::new(captures::Navigation::startup(&args.url))),
+        uploader: match &args.upload_url {
+            Some(url) => {
+                let uploader = upload::Uploader::start(url).map_err(|e| format!("Failed to start uploader: {}", e))?;
+                log_info!("Uploading saved frames to {}", url);
+                Some(uploader)
+            }
+            None => None,
+        },
+        pipeline: Arc::default(),
+        scaler: args.thumbnail_threshold.map(|px| Arc::new(Mutex::new(scaling::CaptureScaler::new(px)))),
+        lifecycle: Arc::new(Mutex::new(lifecycle::PageLifecycle::new(
+            args.idle_page_policy,
+            std::time::Duration::from_secs(args.idle_after),
+        ))),
+        auth: Arc::new(auth::Auth::new(args.api_token.clone(), args.tokens.clone())?),
+        embed: Arc::new(embed::EmbedConfig::new(&args.embed_allowed_origins)?),
+        webdriver: args.webdriver.then(Arc::default),
+        frames: Arc::new(tokio::sync::watch::channel(0).0),
+        sinks: {
+            let mut sinks = Vec::new();
+            if let Some(command) = &args.pipe_to_ffmpeg {
+                sinks.push(sink::FrameSink::pipe(command));
+            }
+            if let Some(url) = &args.frame_sink {
+                sinks.push(sink::FrameSink::socket(url)?);
+            }
+            sinks
+        },
+        frame_filters: Arc::new(match &args.frame_filters {
+            Some(path) => filters::FrameFilters::load(path)?,
+            None => filters::FrameFilters::default(),
+        }),
+        frame_hook: args.frame_hook.as_ref().map(|command| {
+            frame_hook::FrameHook::new(frame_hook::HookConfig {
+                command: command.clone(),
+                every: args.frame_hook_every,
+                timeout: std::time::Duration::from_millis(args.frame_hook_timeout_ms),
+                concurrency: args.frame_hook_concurrency,
+                fields: args.frame_hook_fields.clone(),
+                condition: args.frame_hook_match.clone(),
+                webhook: args.frame_hook_webhook.clone(),
+            })
+        }),
+        ocr: Arc::new(Mutex::new(ocr::Ocr::new(args.ocr_lang.clone()))),
+        qr_watch: Arc::default(),
+        events: Arc::default(),
+        status: Arc::default(),
+        extra_headers: Arc::default(),
+        basic_auth: Arc::new(Mutex::new(basic_auth)),
+        dialogs: Arc::new(Mutex::new(dialogs::Dialogs::new(args.dialogs))),
+        file_uploads: Arc::default(),
+        js_errors: Arc::default(),
+        network_log: Arc::default(),
+        downloads: Arc::new(Mutex::new(downloads::Downloads::new(args.download_dir.clone().unwrap_or_else(downloads::default_dir)))),
+        full_page: screenshot::FullPage { stream: args.fullpage, max_height: args.fullpage_max_height.max(1), clip: args.clip },
+        document: Arc::default(),
+        journal: Arc::new(Mutex::new(journal::Journal::new(journal::Redaction::new(&args.redact_fields)))),
+        login: Arc::new(Mutex::new(login::LoginHelper::load(
+            args.credentials_file.as_deref(),
+            args.login_max_attempts,
+            args.login_webhooks.clone(),
+        )?)),
+        recorder: Arc::default(),
+        redaction: Arc::new(Mutex::new(redact::Redaction::load(
+            args.redact_file.clone(),
+            std::time::Duration::from_millis(args.redact_refresh_ms),
+        )?)),
+        render_pool,
+        instance: {
+            let current_url = current_url.clone();
+            load_instance(&args, "headless", Box::new(move || current_url.lock().unwrap().clone()))?
+        },
+    };
+
+    {
+        let current_url = current_url.clone();
+        let pipeline = state.pipeline.clone();
+        crash::set_context(move || serde_json::json!({
+            "mode": "headless",
+            "url": current_url.try_lock().ok().map(|u| u.clone()),
+            "frame_stats": pipeline.try_lock().ok().map(|p| p.to_json()),
+        }));
+    }
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            crash::clean_exit();
+            std::process::exit(0);
+        }
+    });
+
+    // Start HTTP server (not needed for a one-shot screenshot or benchmark)
+    if args.screenshot.is_none() && args.bench.is_none() {
+        start_http_server_headless(args.port, state.clone());
+        advertise_instance(&args, &state.instance)?;
+        {
+            let state = state.clone();
+            qr::spawn_watcher(state.qr_watch.clone(), move || {
+                let frame = state.screenshot_buffer.lock().unwrap().clone()?;
+                Some((*state.frames.borrow(), frame))
+            });
+        }
+        events::spawn_watcher(state.events.clone(), state.navigation.clone(), state.frames.clone(), state.pipeline.clone());
+        login::spawn_watcher(
+            state.login.clone(),
+            state.commands.clone(),
+            std::time::Duration::from_secs(args.login_check_interval.max(1)),
+        );
+        if let Some(path) = &args.resource_log {
+            let tx = state.commands.clone();
+            resources::start_log(path.clone(), std::time::Duration::from_secs(args.resource_log_interval.max(1)), move || {
+                let report = commands::send(&tx, commands::PageCommand::Resources).map_err(|e| e.message)?;
+                serde_json::from_value(report).map_err(|e| e.to_string())
+            });
+        }
+    }
+    if args.mcp {
+        mcp::serve(state.commands.clone());
+    }
+    if let Some(port) = args.grpc_port {
+        grpc::start(port, state.clone())?;
+    }
+
+    // Launch headless Chrome, with a profile per port so instances on one
+    // host (both ends of a handoff, say) do not share Chrome's singleton
+    let mut config = BrowserConfig::builder()
+        .window_size(args.width, args.height)
+        .user_data_dir(std::env::temp_dir().join(format!("hello-cef-one-shoot-claude-chrome-{}", args.port)));
+    if let Some(flag) = resolver_flag {
+        config = config.arg(flag);
+    }
+    if let Some(policy) = &args.autoplay_policy {
+        config = config.arg(format!("--autoplay-policy={}", policy));
+    }
+    for flag in &state.fonts.chrome_flags {
+        config = config.arg(flag);
+    }
+    for flag in frames::CHROME_FLAGS {
+        config = config.arg(*flag);
+    }
+    if args.fake_media {
+        config = config
+            .arg("--use-fake-device-for-media-stream")
+            .arg("--use-fake-ui-for-media-stream");
+    }
+    let config = config
+        .build()
+        .map_err(|e| format!("Failed to build browser config: {}", e))?;
+
+    let (browser, mut handler) = Browser::launch(config).await?;
+    let browser = Arc::new(browser);
+
+    // Spawn browser handler
+    let _handle = tokio::spawn(async move {
+        while let Some(event) = handler.next().await {
+            let _ = event;
+        }
+    });
+
+    if !args.grant_permissions.is_empty() {
+        permissions::apply(&browser, None, &args.grant_permissions, &[]).await?;
+        state.permissions.lock().unwrap().record(None, args.grant_permissions.clone(), Vec::new());
+    }
+
+    // Create a blank page so interception is in place before the first navigation
+    let page = browser.new_page("about:blank").await?;
+
+    if let Some(secs) = args.bench {
+        log_info!("Benchmarking for {}s...", secs);
+        let report = bench::run(&page, (args.width, args.height), std::time::Duration::from_secs(secs)).await?;
+        if args.bench_json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            bench::print_table(&report);
+        }
+        return Ok(());
+    }
+
+    if let Some(dir) = &args.report {
+        let urls = match &args.report_urls {
+            Some(path) => report::read_urls(path)?,
+            None => vec![args.url.clone()],
+        };
+        let spec = report::ReportSpec {
+            dir: dir.clone(),
+            urls,
+            depth: args.report_depth,
+            max_pages: args.report_max_pages,
+            concurrency: args.render_concurrency,
+            timeout: std::time::Duration::from_millis(args.render_timeout_ms),
+            deterministic: args.deterministic_render,
+            baseline: args.baseline.clone().map(|dir| baseline::BaselineOptions { dir, max_diff_percent: args.baseline_max_diff }),
+        };
+        state.locale.lock().unwrap().user_agent = browser.version().await?.user_agent;
+        let (pages, delta) = report::run(&browser, &state, &spec).await?;
+        let failed = pages.iter().filter(|p| p.error.is_some()).count();
+        let verdict = delta.as_ref().map(|d| d.verdict);
+        println!("{}", serde_json::json!({"path": dir.join("index.html"), "pages": pages.len(), "failed": failed, "verdict": verdict}));
+        if delta.is_some_and(|d| d.is_changed()) {
+            std::process::exit(3);
+        }
+        return Ok(());
+    }
+
+    let mut intercept_handle = if state.needs_interception() {
+        Some(intercept::install(&page, state.clone()).await?)
+    } else {
+        None
+    };
+
+    if args.deterministic_render {
+        deterministic::install(&page, &state.deterministic).await?;
+    }
+    if state.fonts.is_active() {
+        state.fonts.install(&page).await?;
+    }
+    state.locale.lock().unwrap().user_agent = browser.version().await?.user_agent;
+    if let Some(timezone) = &args.timezone {
+        locale::check_timezone(&page, timezone).await.map_err(|e| format!("--timezone: {}", e))?;
+    }
+    locale::sync(&page, &state.locale).await;
+    let _console_handle = event_track::watch_console(&page, state.recorder.clone()).await?;
+    let _source_handle = source::watch_documents(&page, state.document.clone()).await?;
+    let _dialog_handle = dialogs::watch(&page, state.dialogs.clone()).await?;
+    let _errors_handle = js_errors::watch(&page, state.js_errors.clone()).await?;
+    let _network_handle = network_log::watch(&page, state.network_log.clone()).await?;
+    let _download_handle = downloads::install(&browser, state.downloads.clone()).await?;
+    let _render_handle = render_pool::spawn(render_rx, browser.clone(), state.clone());
+
+    match &imported {
+        Some(state) => handoff::import(&page, state).await.map_err(|e| format!("Failed to import state: {}", e.message))?,
+        None => {
+            page.goto(&args.url).await?;
+        }
+    }
+
+    if let Some(path) = &args.screenshot {
+        use chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat;
+
+        let format = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")) {
+            CaptureScreenshotFormat::Png
+        } else {
+            CaptureScreenshotFormat::Jpeg
+        };
+        let params = screenshot_params(format);
+        let settle = args.wait_settled.then_some(state.settle);
+        let (frame, report) = settle::capture(&page, params, settle).await?;
+        redact::refresh(&page, &state.redaction, 0, true).await;
+        let frame = redact::apply(&state.redaction, frame).ok_or("failed to apply redaction to the screenshot")?;
+        std::fs::write(path, frame)?;
+        println!("{}", serde_json::json!({"path": path, "url": args.url, "settle": report}));
+        return Ok(());
+    }
+
+    log_info!("Headless browser started!");
+    log_info!("Initial URL: {}", args.url);
+    if !args.mcp {
+        println!();
+    }
+    log_info!("Navigate via: http://localhost:{}/navigate?url=<URL>", args.port);
+
+    if let Some(path) = &args.bundle {
+        let bundle = bundle::load(path)?;
+        let state = state.clone();
+        // Its commands wait for the main loop below
+        thread::spawn(move || {
+            let report = bundle::import(&state, bundle);
+            log_info!("Bundle applied: {}", report.to_json()["sections"]);
+        });
+    }
+
+    if let Some(dir) = &args.snapshot_to {
+        let owner = snapshot::Owner::new(&state.instance, std::time::Duration::from_secs(args.snapshot_interval_secs.max(1)));
+        snapshot::claim(dir, &owner)?;
+        let state = state.clone();
+        snapshot::spawn_writer(dir.clone(), owner, move || {
+            // Exporting would wake a suspended page; the last snapshot still holds
+            if state.lifecycle.lock().unwrap().is_suspended() {
+                return Err("page is suspended".to_string());
+            }
+            let exported = commands::send(&state.commands, commands::PageCommand::ExportState).map_err(|e| e.message)?;
+            let exported = serde_json::from_value(exported).map_err(|e| format!("unexpected export: {}", e))?;
+            Ok(snapshot::Contents { state: exported, tabs: None })
+        });
+    }
+    if let Some(dir) = &args.follow {
+        let state = state.clone();
+        snapshot::spawn_follower(dir.clone(), state.instance.id.clone(), move |changes| {
+            if let Some(exported) = changes.state {
+                let command = commands::PageCommand::ImportState { state: Box::new(exported) };
+                commands::send(&state.commands, command).map_err(|e| e.message)?;
+            }
+            if let Some(scroll) = changes.scroll {
+                let command = commands::PageCommand::Evaluate { expression: handoff::scroll_script(scroll), await_promise: false };
+                commands::send(&state.commands, command).map_err(|e| e.message)?;
+            }
+            Ok(())
+        });
+    }
+
+    if let Some(minutes) = args.soak {
+        soak::spawn(state.clone(), args.url.clone(), std::time::Duration::from_secs(minutes * 60), args.soak_rss_budget_mb, args.port);
+    }
+
+    let mut applied_scale = 1.0;
+
+    // Main loop: capture screenshots and handle navigation
+    loop {
+        // Runtime rules added over HTTP need interception even if startup didn't
+        if intercept_handle.is_none() && state.needs_interception() {
+            match intercept::install(&page, state.clone()).await {
+                Ok(handle) => intercept_handle = Some(handle),
+                Err(e) => log_error!("Failed to enable request interception: {}", e),
+            }
+        }
+
+        // Answer commands queued by the HTTP server
+        while let Ok(pending) = command_rx.try_recv() {
+            lifecycle::resume(&page, &state.lifecycle, false).await;
+            let result = commands::execute(&browser, &page, &state, pending.command).await;
+            let _ = pending.reply.send(result);
+        }
+
+        // Check if URL changed (via HTTP API); commands that navigate record it themselves
+        let relocalize = state.locale.lock().unwrap().is_pending();
+        let new_url = current_url.lock().unwrap().clone();
+        if relocalize || new_url != state.navigation.lock().unwrap().url {
+            lifecycle::resume(&page, &state.lifecycle, true).await;
+            locale::sync(&page, &state.locale).await;
+            log_info!("Navigating to: {}", new_url);
+            state.navigation.lock().unwrap().next(&new_url, "api");
+            state.record_action(journal::JournalAction::Navigate { url: new_url.clone() });
+            if let Err(e) = page.goto(&new_url).await {
+                // Expected while offline; /status has it
+                if state.offline.lock().unwrap().is_offline() {
+                    log_info!("Navigation failed while offline: {}", e);
+                } else {
+                    log_error!("Navigation error: {}", e);
+                }
+                state.status.lock().unwrap().navigation_failed(&new_url, e.to_string());
+                let failure = serde_json::json!({"url": new_url, "error": e.to_string()});
+                state.events.lock().unwrap().publish("navigation_failed", failure);
+            }
+        }
+
+        // Nobody watching: stop capturing and apply --idle-page-policy
+        let busy = !state.sinks.is_empty()
+            || state.frame_hook.is_some()
+            || state.frames.receiver_count() > 0
+            || state.recorder.lock().unwrap().is_recording();
+        let suspend = state.lifecycle.lock().unwrap().should_suspend(busy);
+        if suspend {
+            lifecycle::suspend(&page, &state.lifecycle, state.viewport).await;
+            tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+            continue;
+        }
+        lifecycle::resume(&page, &state.lifecycle, false).await;
+
+        // Wait for page to be ready
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        // Capture screenshot
+        // Thumbnail-only consumers: have Chrome render at a lower device scale factor
+        if let Some(scaler) = &state.scaler {
+            let scale = scaler.lock().unwrap().update(args.width);
+            if scale != applied_scale {
+                let params = chromiumoxide::cdp::browser_protocol::emulation::SetDeviceMetricsOverrideParams::new(
+                    args.width as i64,
+                    args.height as i64,
+                    scale,
+                    false,
+                );
+                match page.execute(params).await {
+                    Ok(_) => applied_scale = scale,
+                    Err(e) => log_error!("Failed to change capture scale: {}", e),
+                }
+            }
+        }
+
+        if state.hud.lock().unwrap().wants_load_state() {
+            if let Ok(ready) = page.evaluate("document.readyState").await {
+                if let Ok(ready) = ready.into_value::<String>() {
+                    state.hud.lock().unwrap().set_load_state(ready);
+                }
+            }
+        }
+
+        theme::sync(&page, &state.theme).await;
+        file_upload::sweep(&page, &state.file_uploads).await;
+        cpu_throttle::sync(&page, &state.cpu_throttle, &state.document).await;
+        offline::sync(&page, &state.offline).await;
+        media_emulation::sync(&page, &state.media_emulation, &state.document).await;
+
+        let navigation_seq = state.navigation.lock().unwrap().seq;
+        redact::refresh(&page, &state.redaction, navigation_seq, false).await;
+
+        let capture_started = std::time::Instant::now();
+        let params = screenshot_params(chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat::Jpeg);
+        let params = screenshot::stream_params(&page, &state, params).await;
+        match page.screenshot(params).await {
+            Ok(png_data) => {
+                state.pipeline.lock().unwrap().record("screenshot", capture_started.elapsed());
+                let frame = if state.has_overlays() {
+                    let composed = pipeline::time(&state.pipeline, "overlay", || {
+                        overlay::recompose(&png_data, JPEG_QUALITY, |img| state.draw_overlays(img))
+                    });
+                    match composed {
+                        Some(frame) => frame,
+                        // An unredacted frame must never go out
+                        None if state.redaction.lock().unwrap().is_active() => continue,
+                        None => png_data,
+                    }
+                } else {
+                    png_data
+                };
+                state.publish_frame(frame);
+                if state.recorder.lock().unwrap().is_recording() {
+                    let annotations = state.annotations.lock().unwrap().list().to_vec();
+                    state.recorder.lock().unwrap().observe_annotations(&annotations);
+                }
+            }
+            Err(e) => {
+                log_error!("Screenshot error: {}", e);
+                state.status.lock().unwrap().capture_failed(e.to_string());
+            }
+        }
+
+        // Small delay between captures
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+    }
+
+    #[allow(unreachable_code)]
+    {
+        _handle.abort();
+        Ok(())
+    }
+}
+
+// ============== GUI Mode (wry/tao) ==============
+
+mod gui {
+    use super::*;
+    use tao::{
+        dpi::LogicalSize,
+        event::{Event, WindowEvent},
+        event_loop::{ControlFlow, EventLoopBuilder, EventLoopProxy},
+        window::{Fullscreen, Window, WindowBuilder},
+    };
+    use wry::{WebView, WebViewBuilder};
+    use crate::annotations::{self, SharedAnnotations};
+    use crate::handoff::{self, HandoffState};
+    use crate::journal::{self, JournalAction, SharedJournal};
+    use crate::kiosk::{WindowMode, WindowRequest};
+    use crate::split::{Pane, Placement, Split, SplitRequest, Switch};
+    use crate::screen_record::{self, SharedScreenRecorder};
+    use crate::theme::{self, SharedTheme};
+    use crate::ticker::{self, SharedTicker};
+    use crate::ui_state::{SharedUiState, TabState, UiState, WindowState};
+    use crate::workspaces::{SharedWorkspaces, Workspace};
+
+    #[derive(Debug, Clone)]
+    pub struct Tab {
+        pub id: usize,
+        pub url: String,
+        pub title: String,
+        pub favicon: Option<String>,
+        pub loading: bool,
+        /// Background tab: the single webview shows another page, so this
+        /// one is kept as URL plus a scroll / form snapshot until activated.
+        pub hibernated: bool,
+        pub snapshot: Option<serde_json::Value>,
+        /// Shown in a pane whose webview stopped answering watchdog probes.
+        pub unresponsive: bool,
+    }
+
+    impl Tab {
+        fn new(id: usize, url: String) -> Self {
+            Tab { id, url, title: "New Tab".to_string(), favicon: None, loading: true, hibernated: false, snapshot: None, unresponsive: false }
+        }
+    }
+
+    /// Posts the active tab's scroll position (window and tallest container)
+    /// and, with `withFields`, its form values back as `tabSnapshot` before
+    /// the webview leaves it. Passwords are not kept.
+    const SNAPSHOT_TAB_SCRIPT: &str = r#"
+function(id, withFields) {
+    const fields = [];
+    if (withFields) document.querySelectorAll('input, textarea, select').forEach(function(el) {
+        if (el.closest('#__rust_browser_toolbar__')) return;
+        const type = (el.type || '').toLowerCase();
+        if (['password', 'hidden', 'file', 'submit', 'button', 'reset', 'image'].includes(type)) return;
+        let key = el.id ? '#' + CSS.escape(el.id)
+            : el.name ? el.tagName.toLowerCase() + '[name="' + CSS.escape(el.name) + '"]'
+            : null;
+        if (!key) return;
+        if (type === 'radio') key += '[value="' + CSS.escape(el.value) + '"]';
+        fields.push([key, type === 'checkbox' || type === 'radio' ? el.checked : el.value]);
+    });
+    window.ipc.postMessage(JSON.stringify({
+        tabSnapshot: { id: id, state: { scroll: window.__rbScroll.capture(), fields: fields } },
+    }));
+}
+"#;
+
+    const RESTORE_TAB_SCRIPT: &str = r#"
+function(state, timeoutMs) {
+    (state.fields || []).forEach(function(field) {
+        const el = document.querySelector(field[0]);
+        if (!el) return;
+        if (typeof field[1] === 'boolean') el.checked = field[1]; else el.value = field[1];
+        el.dispatchEvent(new Event('input', { bubbles: true }));
+        el.dispatchEvent(new Event('change', { bubbles: true }));
+    });
+    if (state.scroll) window.__rbScroll.restore(state.scroll, timeoutMs);
+}
+"#;
+
+    /// Hands the live DOM to the HTTP thread before leaving for view-source.
+    fn source_snapshot_script() -> String {
+        format!(
+            "window.ipc.postMessage(JSON.stringify({{source: {}}}))",
+            commands::js_call(crate::source::DOM_SCRIPT, &[])
+        )
+    }
+
+    /// DOM snapshot taken when view-source: was opened: (page URL, HTML).
+    pub type SourceSnapshot = Arc<Mutex<Option<(String, String)>>>;
+
+    /// Tabs of `workspace` with ids from `first_id`: (tabs, active id, next id).
+    /// Only the active one loads; the rest wait hibernated.
+    fn workspace_tabs(workspace: &Workspace, first_id: usize) -> (Vec<Tab>, usize, usize) {
+        let active = workspace.active.min(workspace.tabs.len().saturating_sub(1));
+        let tabs: Vec<Tab> = workspace
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(i, saved)| {
+                let mut tab = Tab::new(first_id + i, saved.url.clone());
+                if !saved.title.is_empty() {
+                    tab.title = saved.title.clone();
+                }
+                tab.loading = i == active;
+                tab.hibernated = i != active;
+                tab
+            })
+            .collect();
+        let next_id = first_id + tabs.len();
+        (tabs, first_id + active, next_id)
+    }
+
+    /// Marks the active tab hibernated and returns the script that snapshots it.
+    fn hibernate_active(tabs_vec: &mut [Tab], active_id: usize) -> String {
+        match tabs_vec.iter_mut().find(|t| t.id == active_id) {
+            Some(tab) => {
+                tab.hibernated = true;
+                commands::js_call(SNAPSHOT_TAB_SCRIPT, &[tab.id.into(), true.into()])
+            }
+            None => String::new(),
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub enum UserEvent {
+        Navigate(String),
+        NewTab,
+        CloseTab(usize),
+        SwitchTab(usize),
+        PageLoaded(Pane),
+        PageInfo {
+            pane: Pane,
+            url: String,
+            title: String,
+            favicon: Option<String>,
+        },
+        TabSnapshot {
+            id: usize,
+            state: serde_json::Value,
+        },
+        /// POST /handoff/export, answered once the page has reported its state.
+        ExportState(std::sync::mpsc::Sender<Result<HandoffState, String>>),
+        /// POST /handoff/import or --import-state.
+        ImportState(Box<HandoffState>),
+        /// POST /window.
+        SetWindow(WindowRequest),
+        /// The --kiosk-exit chord was pressed.
+        KioskExit,
+        /// POST /reload.
+        Reload {
+            restore_scroll: bool,
+        },
+        /// POST /workspaces/NAME/activate: replaces all tabs.
+        ActivateWorkspace(Workspace),
+        /// POST /split, "Open in split" or a click into the unfocused pane.
+        SetSplit(SplitRequest),
+        /// The divider handle in `pane` was dragged to `x` CSS pixels.
+        ResizeSplit {
+            pane: Pane,
+            x: f64,
+        },
+        /// DELETE /split or "Close split": the other pane fills the window.
+        CloseSplit(Pane),
+        /// Time for the next responsiveness probe of each pane.
+        Probe,
+        /// A navigation in `pane` was blocked or redirected; load `url` instead.
+        LoadInPane {
+            pane: Pane,
+            url: String,
+        },
+        /// The toolbar's theme button in `pane`.
+        ToggleTheme(Pane),
+        /// POST /theme changed which tabs are dark.
+        ThemeChanged,
+        /// A screen recording started or stopped.
+        RecordingChanged,
+        /// --follow: the snapshot only scrolled the page.
+        ScrollTo(handoff::Scroll),
+    }
+
+    /// How long POST /handoff/export waits for the page.
+    const EXPORT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+    /// Page scripts can't set HttpOnly cookies, so the GUI import drops them.
+    fn warn_http_only_cookies(state: &HandoffState) {
+        let skipped = state.cookies.iter().filter(|c| c.http_only).count();
+        if skipped > 0 {
+            log_error!("Skipping {} HttpOnly cookie(s) from the handoff: GUI mode can only set script-visible cookies", skipped);
+        }
+    }
+
+    pub type Tabs = Arc<Mutex<(Vec<Tab>, usize, usize)>>;
+    pub type WindowRect = Arc<Mutex<(i32, i32, u32, u32)>>;
+
+    pub const INIT_SCRIPT: &str = r#"
+window.__rustBrowserReady = true;
+window.__injectToolbar = function(tabsHtml, currentUrl) {
+    const old = document.getElementById('__rust_browser_toolbar__');
+    if (old && document.getElementById('__rb_style__')) {
+        // Update in place: recreating the URL field would drop an IME composition or a half-typed URL
+        old.querySelector('.tab-bar').innerHTML = tabsHtml
+            + '<button class="new-tab-btn" id="__rb_newtab__" title="New Tab">+</button>';
+        const input = document.getElementById('__rb_url__');
+        if (document.activeElement !== input) input.value = currentUrl;
+        window.__rbBindTabs();
+        return;
+    }
+    if (old) old.remove();
+
+    if (!document.body) {
+        setTimeout(function() { window.__injectToolbar(tabsHtml, currentUrl); }, 50);
+        return;
+    }
+
+    let oldStyle = document.getElementById('__rb_style__');
+    if (oldStyle) oldStyle.remove();
+
+    const style = document.createElement('style');
+    style.id = '__rb_style__';
+    style.textContent = `
+        #__rust_browser_toolbar__ {
+            position: fixed !important;
+            top: 0 !important;
+            left: 0 !important;
+            right: 0 !important;
+            height: 72px !important;
+            background: #e8e8e8 !important;
+            border-bottom: 1px solid #b0b0b0 !important;
+            z-index: 2147483647 !important;
+            font-family: -apple-system, BlinkMacSystemFont, sans-serif !important;
+            box-sizing: border-box !important;
+            display: flex !important;
+            flex-direction: column !important;
+        }
+        .tab-bar {
+            display: flex !important;
+            align-items: center !important;
+            padding: 4px 8px !important;
+            gap: 2px !important;
+            background: #d0d0d0 !important;
+            height: 32px !important;
+        }
+        .tab {
+            display: flex !important;
+            align-items: center !important;
+            padding: 4px 8px !important;
+            background: #c0c0c0 !important;
+            border-radius: 6px 6px 0 0 !important;
+            cursor: pointer !important;
+            font-size: 12px !important;
+            max-width: 150px !important;
+            gap: 4px !important;
+        }
+        .tab:hover { background: #d0d0d0 !important; }
+        .tab.active { background: #e8e8e8 !important; }
+        .tab.hibernated { opacity: 0.55 !important; }
+        .tab-title {
+            overflow: hidden !important;
+            text-overflow: ellipsis !important;
+            white-space: nowrap !important;
+        }
+        .tab-close {
+            font-size: 14px !important;
+            width: 16px !important;
+            height: 16px !important;
+            display: flex !important;
+            align-items: center !important;
+            justify-content: center !important;
+            border-radius: 50% !important;
+            cursor: pointer !important;
+        }
+        .tab-close:hover { background: rgba(0,0,0,0.1) !important; }
+        .new-tab-btn {
+            width: 24px !important;
+            height: 24px !important;
+            border: none !important;
+            background: transparent !important;
+            cursor: pointer !important;
+            font-size: 18px !important;
+            color: #666 !important;
+        }
+        .new-tab-btn:hover { color: #000 !important; }
+        .nav-bar {
+            display: flex !important;
+            align-items: center !important;
+            padding: 4px 8px !important;
+            gap: 6px !important;
+            height: 40px !important;
+        }
+        .nav-bar button {
+            width: 28px !important;
+            height: 26px !important;
+            border: 1px solid #a0a0a0 !important;
+            border-radius: 4px !important;
+            background: linear-gradient(to bottom, #fff, #e8e8e8) !important;
+            cursor: pointer !important;
+            font-size: 14px !important;
+        }
+        .nav-bar button:hover { background: linear-gradient(to bottom, #fff, #d8d8d8) !important; }
+        .nav-bar input {
+            flex: 1 !important;
+            height: 26px !important;
+            border: 1px solid #a0a0a0 !important;
+            border-radius: 13px !important;
+            padding: 0 12px !important;
+            font-size: 12px !important;
+            outline: none !important;
+            background: white !important;
+        }
+        .nav-bar input:focus { border-color: #4a90d9 !important; }
+        html { margin-top: 72px !important; }
+    `;
+    document.head.appendChild(style);
+
+    const toolbar = document.createElement('div');
+    toolbar.id = '__rust_browser_toolbar__';
+    toolbar.innerHTML = `
+        <div class="tab-bar">
+            ${tabsHtml}
+            <button class="new-tab-btn" id="__rb_newtab__" title="New Tab">+</button>
+        </div>
+        <div class="nav-bar">
+            <button id="__rb_back__" title="Back">←</button>
+            <button id="__rb_fwd__" title="Forward">→</button>
+            <button id="__rb_reload__" title="Reload">⟳</button>
+            <button id="__rb_theme__" title="Toggle dark theme">◐</button>
+            <input type="text" id="__rb_url__" value="${currentUrl}" placeholder="Enter URL...">
+        </div>
+    `;
+
+    document.body.insertBefore(toolbar, document.body.firstChild);
+
+    document.getElementById('__rb_back__').onclick = function() { history.back(); };
+    document.getElementById('__rb_fwd__').onclick = function() { history.forward(); };
+    document.getElementById('__rb_reload__').onclick = function() { location.reload(); };
+    document.getElementById('__rb_theme__').onclick = function() {
+        window.ipc.postMessage(JSON.stringify({toggleTheme: true}));
+    };
+
+    const urlInput = document.getElementById('__rb_url__');
+    urlInput.onkeydown = function(e) {
+        // Enter that commits an IME candidate is not a navigation
+        if (e.key === 'Enter' && !e.isComposing && e.keyCode !== 229) {
+            window.ipc.postMessage(JSON.stringify({navigate: urlInput.value.trim()}));
+        }
+    };
+    urlInput.onfocus = function() { this.select(); };
+
+    window.__rbBindTabs();
+};
+
+window.__rbBindTabs = function() {
+    document.getElementById('__rb_newtab__').onclick = function() {
+        window.ipc.postMessage(JSON.stringify({newTab: true}));
+    };
+
+    document.querySelectorAll('.tab').forEach(function(tab) {
+        tab.onclick = function(e) {
+            if (!e.target.classList.contains('tab-close')) {
+                window.ipc.postMessage(JSON.stringify({switchTab: parseInt(tab.dataset.id)}));
+            }
+        };
+        tab.oncontextmenu = function(e) {
+            e.preventDefault();
+            window.__rbTabMenu(parseInt(tab.dataset.id), e.clientX, e.clientY);
+        };
+    });
+
+    document.querySelectorAll('.tab-close').forEach(function(btn) {
+        btn.onclick = function(e) {
+            e.stopPropagation();
+            window.ipc.postMessage(JSON.stringify({closeTab: parseInt(btn.dataset.id)}));
+        };
+    });
+};
+
+window.__rbTabMenu = function(id, x, y) {
+    const old = document.getElementById('__rb_tab_menu__');
+    if (old) old.remove();
+    const menu = document.createElement('div');
+    menu.id = '__rb_tab_menu__';
+    menu.style.cssText = 'position:fixed;z-index:2147483647;background:#fff;border:1px solid #b0b0b0;border-radius:4px;'
+        + 'box-shadow:0 2px 8px rgba(0,0,0,0.2);padding:4px 0;font:12px -apple-system,BlinkMacSystemFont,sans-serif;';
+    menu.style.left = x + 'px';
+    menu.style.top = y + 'px';
+    const items = [['Open in split', {openInSplit: id}]];
+    if (window.__rbPane && window.__rbPane.split) items.push(['Close split', {closePane: true}]);
+    items.forEach(function(entry) {
+        const item = document.createElement('div');
+        item.textContent = entry[0];
+        item.style.cssText = 'padding:4px 16px;cursor:pointer;';
+        item.onmouseenter = function() { item.style.background = '#e8e8e8'; };
+        item.onmouseleave = function() { item.style.background = ''; };
+        item.onclick = function() {
+            menu.remove();
+            window.ipc.postMessage(JSON.stringify(entry[1]));
+        };
+        menu.appendChild(item);
+    });
+    document.documentElement.appendChild(menu);
+    setTimeout(function() {
+        document.addEventListener('click', function() { menu.remove(); }, {once: true});
+    }, 0);
+};
+
+window.ipc.postMessage(JSON.stringify({pageLoaded: true}));
+
+window.addEventListener('load', function() {
+    const icon = document.querySelector('link[rel~="icon"]');
+    window.ipc.postMessage(JSON.stringify({pageInfo: {
+        url: location.href,
+        title: document.title,
+        favicon: icon ? icon.href : (location.protocol.startsWith('http') ? location.origin + '/favicon.ico' : null)
+    }}));
+});
+
+document.addEventListener('keydown', function(e) {
+    if (window.__rbKiosk) return;
+    if ((e.metaKey || e.ctrlKey) && e.key === 'l') {
+        e.preventDefault();
+        const urlInput = document.getElementById('__rb_url__');
+        if (urlInput) { urlInput.focus(); urlInput.select(); }
+    }
+    if ((e.metaKey || e.ctrlKey) && e.key === 't') {
+        e.preventDefault();
+        window.ipc.postMessage(JSON.stringify({newTab: true}));
+    }
+    if ((e.metaKey || e.ctrlKey) && e.key === 'w') {
+        e.preventDefault();
+        window.ipc.postMessage(JSON.stringify({closeCurrentTab: true}));
+    }
+    if ((e.metaKey || e.ctrlKey) && e.shiftKey && e.key.toLowerCase() === 's') {
+        e.preventDefault();
+        const name = prompt('Save open tabs as workspace:');
+        if (name) window.ipc.postMessage(JSON.stringify({saveWorkspace: name}));
+    }
+});
+"#;
+
+    pub fn build_tabs_html(tabs: &[Tab], active_id: usize) -> String {
+        tabs.iter().map(|tab| {
+            let state_class = if tab.id == active_id {
+                "active"
+            } else if tab.hibernated {
+                "hibernated"
+            } else {
+                ""
+            };
+            let short_title = if tab.title.len() > 18 {
+                format!("{}...", &tab.title[..15])
+            } else {
+                tab.title.clone()
+            };
+            format!(
+                r#"<div class="tab {}" data-id="{}"><span class="tab-title">{}</span><span class="tab-close" data-id="{}">×</span></div>"#,
+                state_class, tab.id, short_title, tab.id
+            )
+        }).collect()
+    }
+
+    pub fn inject_toolbar_script(tabs_html: &str, current_url: &str) -> String {
+        format!(
+            r#"if (window.__injectToolbar) {{ window.__injectToolbar(`{}`, `{}`); }}"#,
+            tabs_html.replace('`', "\\`"),
+            current_url.replace('`', "\\`")
+        )
+    }
+
+    /// Toolbar for the page in `pane`, whose tab is `tab_id`.
+    fn toolbar_script(tabs: &[Tab], active_id: usize, tab_id: usize, split: Option<&Split>, pane: Pane) -> String {
+        let current_url = tabs.iter().find(|t| t.id == tab_id).map(|t| t.url.as_str()).unwrap_or("about:blank");
+        let script = inject_toolbar_script(&build_tabs_html(tabs, active_id), current_url);
+        match split {
+            Some(_) => format!("{}; {}", script, crate::split::pane_script(split, pane)),
+            None => script,
+        }
+    }
+
+    /// The tab shown in `pane`; without a split that is the active tab.
+    fn pane_tab(split: Option<Split>, active_id: usize, pane: Pane) -> usize {
+        split.map_or(active_id, |s| s.tab(pane))
+    }
+
+    fn pane_view<'a>(main: &'a WebView, right: &'a RightPane, pane: Pane) -> &'a WebView {
+        match (pane, right) {
+            (Pane::Right, Some((view, _))) => view,
+            _ => main,
+        }
+    }
+
+    /// Re-renders the toolbar in every pane after the tabs or the focus changed.
+    fn refresh_toolbars(main: &WebView, right: &RightPane, tabs: &Tabs, split: Option<Split>) {
+        let (tabs_vec, active_id, _) = &*tabs.lock().unwrap();
+        let left_tab = pane_tab(split, *active_id, Pane::Left);
+        let _ = main.evaluate_script(&toolbar_script(tabs_vec, *active_id, left_tab, split.as_ref(), Pane::Left));
+        if let (Some(s), Some((view, _))) = (split, right) {
+            let _ = view.evaluate_script(&toolbar_script(tabs_vec, *active_id, s.right_tab, Some(&s), Pane::Right));
+        }
+    }
+
+    /// Applies each pane's tab theme, after a toggle, POST /theme or a schedule boundary.
+    fn refresh_themes(main: &WebView, right: &RightPane, tabs: &Tabs, split: Option<Split>, theme: &SharedTheme) {
+        let (_, active_id, _) = &*tabs.lock().unwrap();
+        let theme = theme.lock().unwrap();
+        let left_tab = pane_tab(split, *active_id, Pane::Left);
+        let _ = main.evaluate_script(&theme.script(theme.is_dark(Some(left_tab))));
+        if let (Some(s), Some((view, _))) = (split, right) {
+            let _ = view.evaluate_script(&theme.script(theme.is_dark(Some(s.right_tab))));
+        }
+    }
+
+    /// Fits the webviews to the window: the main one alone, or both panes.
+    fn layout(window: &Window, main: &WebView, right: &RightPane, split: Option<Split>) {
+        let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+        match (split, right) {
+            (Some(s), Some((view, _))) => {
+                let (left_bounds, right_bounds) = s.bounds(size.width, size.height);
+                let _ = main.set_bounds(left_bounds);
+                let _ = view.set_bounds(right_bounds);
+            }
+            _ => {
+                let _ = main.set_bounds(crate::split::full(size.width, size.height));
+            }
+        }
+    }
+
+    /// Shared state a pane's IPC and navigation handlers use.
+    #[derive(Clone)]
+    struct IpcContext {
+        tabs: Tabs,
+        proxy: EventLoopProxy<UserEvent>,
+        screen_changed: Arc<AtomicBool>,
+        journal: SharedJournal,
+        workspaces: SharedWorkspaces,
+        ui_state: SharedUiState,
+        source_snapshot: SourceSnapshot,
+        navigator: Arc<crate::navigation::Navigator>,
+    }
+
+    /// Which pane a webview currently serves; it changes when the left pane
+    /// closes and the right webview takes its place.
+    type PaneSlot = Arc<Mutex<Pane>>;
+
+    /// The right pane's webview while the window is split.
+    type RightPane = Option<(WebView, PaneSlot)>;
+
+    fn handle_ipc(ctx: &IpcContext, pane: Pane, body: &str) {
+        if let Ok(msg) = serde_json::from_str::<serde_json::Value>(body) {
+            if let Some(record) = msg.get("record") {
+                if let Ok(action) = serde_json::from_value::<JournalAction>(record.clone()) {
+                    let page_url = record["url"].as_str().unwrap_or_default();
+                    ctx.journal.lock().unwrap().record(page_url, action);
+                }
+            }
+            if let Some(source) = msg.get("source") {
+                let page_url = source["url"].as_str().unwrap_or_default().to_string();
+                let html = source["html"].as_str().unwrap_or_default().to_string();
+                *ctx.source_snapshot.lock().unwrap() = Some((page_url, html));
+            }
+            if let Some(url) = msg["navigate"].as_str() {
+                let _ = ctx.proxy.send_event(UserEvent::Navigate(url.to_string()));
+            }
+            if msg["newTab"].as_bool() == Some(true) {
+                let _ = ctx.proxy.send_event(UserEvent::NewTab);
+            }
+            if let Some(id) = msg["switchTab"].as_u64() {
+                let _ = ctx.proxy.send_event(UserEvent::SwitchTab(id as usize));
+            }
+            if let Some(id) = msg["closeTab"].as_u64() {
+                let _ = ctx.proxy.send_event(UserEvent::CloseTab(id as usize));
+            }
+            if msg["closeCurrentTab"].as_bool() == Some(true) {
+                let (_, active_id, _) = &*ctx.tabs.lock().unwrap();
+                let _ = ctx.proxy.send_event(UserEvent::CloseTab(*active_id));
+            }
+            if let Some(snapshot) = msg.get("tabSnapshot") {
+                if let Some(id) = snapshot["id"].as_u64() {
+                    let _ = ctx.proxy.send_event(UserEvent::TabSnapshot { id: id as usize, state: snapshot["state"].clone() });
+                }
+            }
+            if let Some(name) = msg["saveWorkspace"].as_str() {
+                let workspace = Workspace::from_ui(name.to_string(), &ctx.ui_state.read().unwrap());
+                let mut store = ctx.workspaces.lock().unwrap();
+                match store.put(workspace).and_then(|_| store.set_active(name)) {
+                    Ok(()) => log_info!("Saved workspace '{}'", name),
+                    Err(e) => log_error!("Failed to save workspace '{}': {}", name, e),
+                }
+            }
+            if let Some(id) = msg["openInSplit"].as_u64() {
+                let req = SplitRequest { tab: Some(id as usize), ..Default::default() };
+                let _ = ctx.proxy.send_event(UserEvent::SetSplit(req));
+            }
+            if msg["paneFocus"].as_bool() == Some(true) {
+                let req = SplitRequest { focus: Some(pane), ..Default::default() };
+                let _ = ctx.proxy.send_event(UserEvent::SetSplit(req));
+            }
+            if let Some(x) = msg["splitResize"].as_f64() {
+                let _ = ctx.proxy.send_event(UserEvent::ResizeSplit { pane, x });
+            }
+            if msg["closePane"].as_bool() == Some(true) {
+                let _ = ctx.proxy.send_event(UserEvent::CloseSplit(pane));
+            }
+            if msg["toggleTheme"].as_bool() == Some(true) {
+                let _ = ctx.proxy.send_event(UserEvent::ToggleTheme(pane));
+            }
+            if msg["kioskExit"].as_bool() == Some(true) {
+                let _ = ctx.proxy.send_event(UserEvent::KioskExit);
+            }
+            if msg["pageLoaded"].as_bool() == Some(true) {
+                let _ = ctx.proxy.send_event(UserEvent::PageLoaded(pane));
+            }
+            if let Some(info) = msg.get("pageInfo") {
+                let _ = ctx.proxy.send_event(UserEvent::PageInfo {
+                    pane,
+                    url: info["url"].as_str().unwrap_or_default().to_string(),
+                    title: info["title"].as_str().unwrap_or_default().to_string(),
+                    favicon: info["favicon"].as_str().map(str::to_string),
+                });
+            }
+        }
+        ctx.screen_changed.store(true, Ordering::Relaxed);
+    }
+
+    /// Builds a pane's webview, placed at `bounds` inside the window.
+    fn build_pane(window: &Window, url: &str, bounds: wry::Rect, scripts: &[String], ctx: IpcContext, slot: PaneSlot) -> wry::Result<WebView> {
+        let mut builder = WebViewBuilder::new().with_url(url).with_bounds(bounds);
+        for script in scripts {
+            builder = builder.with_initialization_script(script);
+        }
+        let (nav_ctx, nav_slot) = (ctx.clone(), slot.clone());
+        builder
+            .with_navigation_handler(move |url| {
+                let pane = *nav_slot.lock().unwrap();
+                // The event loop may be updating the UI state; then the tab stays unknown
+                let tab = nav_ctx.ui_state.try_read().ok().map(|ui| pane_tab(ui.split, ui.active_tab, pane));
+                match nav_ctx.navigator.check(tab, &url) {
+                    Some(replacement) => {
+                        let _ = nav_ctx.proxy.send_event(UserEvent::LoadInPane { pane, url: replacement });
+                        false
+                    }
+                    None => true,
+                }
+            })
+            .with_ipc_handler(move |req| {
+                let pane = *slot.lock().unwrap();
+                handle_ipc(&ctx, pane, req.body());
+            })
+            .with_devtools(true)
+            .build_as_child(window)
+    }
+
+    /// Applies a /window request; returns the new kiosk state when it changed.
+    fn apply_window(window: &Window, mode: &mut WindowMode, req: &WindowRequest) -> Option<bool> {
+        let kiosk_changed = req.kiosk.filter(|kiosk| *kiosk != mode.kiosk);
+        match kiosk_changed {
+            Some(true) => *mode = WindowMode::kiosk(),
+            Some(false) => *mode = WindowMode::default(),
+            None => {}
+        }
+        if let Some(fullscreen) = req.fullscreen {
+            mode.fullscreen = fullscreen;
+        }
+        if let Some(always_on_top) = req.always_on_top {
+            mode.always_on_top = always_on_top;
+        }
+        if kiosk_changed.is_some() {
+            window.set_decorations(!mode.kiosk);
+        }
+        window.set_fullscreen(mode.fullscreen.then_some(Fullscreen::Borderless(None)));
+        window.set_always_on_top(mode.always_on_top);
+        if let (Some(x), Some(y)) = (req.x, req.y) {
+            window.set_outer_position(tao::dpi::PhysicalPosition::new(x, y));
+        }
+        if let (Some(width), Some(height)) = (req.width, req.height) {
+            window.set_inner_size(tao::dpi::PhysicalSize::new(width, height));
+        }
+        kiosk_changed
+    }
+
+    fn capture_window(
+        window_rect: &WindowRect,
+        annotations: &SharedAnnotations,
+        ticker: &SharedTicker,
+        stats: &pipeline::SharedPipelineStats,
+        encoder: &mut encoder::FrameEncoder,
+        ui_state: &SharedUiState,
+    ) -> Option<Vec<u8>> {
+        use screenshots::Screen;
+
+        let (x, y, width, height) = *window_rect.lock().ok()?;
+
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let screens = Screen::all().ok()?;
+        let screen = screens.first()?;
+
+        let capture = pipeline::time(stats, "capture", || screen.capture_area(x, y, width, height)).ok()?;
+        let unresponsive = ui_state.try_read().is_ok_and(|ui| ui.tabs.iter().any(|t| t.unresponsive));
+
+        // "encode" covers RGBA→RGB conversion and compression; "overlay" is nested inside it
+        let jpeg_bytes = pipeline::time(stats, "encode", || {
+            encoder.encode_rgba(capture.as_raw(), capture.width(), capture.height(), |img| {
+                pipeline::time(stats, "overlay", || {
+                    annotations.lock().unwrap().render(img);
+                    ticker.lock().unwrap().render(img);
+                    if unresponsive {
+                        crate::watchdog::render_badge(img);
+                    }
+                })
+            })
+        })?;
+
+        stats.lock().unwrap().record_frame(jpeg_bytes.len());
+        Some(jpeg_bytes)
+    }
+
+    /// The rule set the navigation handler enforces, for its HTTP endpoints.
+    struct NavigationRules {
+        blocker: crate::blocker::SharedBlocker,
+        host_rules: crate::host_rules::SharedHostRules,
+        history: crate::history::SharedHistory,
+    }
+
+    fn start_http_server_gui(
+        port: u16,
+        screen_changed: Arc<AtomicBool>,
+        window_rect: WindowRect,
+        journal: SharedJournal,
+        annotations: SharedAnnotations,
+        ticker: SharedTicker,
+        fonts: Arc<crate::fonts::FontConfig>,
+        ui_state: SharedUiState,
+        auth: Arc<crate::auth::Auth>,
+        embed: crate::embed::EmbedConfig,
+        instance: Arc<crate::instance::Instance>,
+        source_snapshot: SourceSnapshot,
+        workspaces: SharedWorkspaces,
+        navigation: NavigationRules,
+        theme: SharedTheme,
+        screen_recorder: SharedScreenRecorder,
+        proxy: EventLoopProxy<UserEvent>,
+    ) {
+        let stats: pipeline::SharedPipelineStats = Arc::default();
+        let started = std::time::Instant::now();
+
+        thread::spawn(move || {
+            // Requests are served one at a time, so a single encoder can reuse its buffers
+            let mut frame_encoder = encoder::FrameEncoder::new(JPEG_QUALITY);
+
+            let addr = format!("0.0.0.0:{}", port);
+            let server = match Server::http(&addr) {
+                Ok(s) => s,
+                Err(e) => {
+                    log_error!("Failed to start HTTP server: {}", e);
+                    return;
+                }
+            };
+
+            for mut request in request_log::incoming(server) {
+                let request_url = request.url().to_string();
+                let (url, query) = parse_query(&request_url);
+
+                if let Some(response) = auth.check(&request, url, &query) {
+                    let _ = request_log::respond(request, response);
+                    continue;
+                }
+                if let Some(response) = auth.handle(&mut request, url, &query) {
+                    let _ = request_log::respond(request, response);
+                    continue;
+                }
+
+                if url == "/annotations" || url.starts_with("/annotations/") {
+                    let response = annotations::handle(&mut request, url, &annotations);
+                    let _ = request_log::respond(request, response);
+                } else if url == "/record" || url == "/record/start" || url == "/record/stop" {
+                    let response = screen_record::handle(&mut request, url, &screen_recorder);
+                    let _ = request_log::respond(request, response);
+                } else if url == "/ticker" {
+                    let response = ticker::handle(&mut request, &ticker);
+                    let _ = request_log::respond(request, response);
+                } else if let Some(response) = diagnostics_response(url) {
+                    let _ = request_log::respond(request, response);
+                } else if url == "/stats/buffers" {
+                    let response = buffers::response(vec![
+                        journal.lock().unwrap().buffer_stats(),
+                        navigation.history.lock().unwrap().buffer_stats(),
+                        annotations.lock().unwrap().buffer_stats(),
+                    ]);
+                    let _ = request_log::respond(request, response);
+                } else if url == "/instance" {
+                    let _ = request_log::respond(request, json_response(200, &instance.to_json()));
+                } else if url == "/resources" {
+                    let _ = request_log::respond(request, json_response(200, &serde_json::json!(crate::resources::gui_report())));
+                } else if url == "/handoff/export" || url == "/handoff/import" {
+                    let response = if request.method() != &tiny_http::Method::Post {
+                        json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                    } else if url == "/handoff/export" {
+                        let (tx, rx) = std::sync::mpsc::channel();
+                        let _ = proxy.send_event(UserEvent::ExportState(tx));
+                        match rx.recv_timeout(EXPORT_TIMEOUT) {
+                            Ok(Ok(state)) => json_response(200, &serde_json::json!(state)),
+                            Ok(Err(e)) => json_response(502, &serde_json::json!({"error": e})),
+                            Err(_) => json_response(504, &serde_json::json!({"error": "page did not report its state in time"})),
+                        }
+                    } else {
+                        match read_json_body::<HandoffState>(&mut request).and_then(|state| state.validate().map(|_| state)) {
+                            Ok(state) => {
+                                let summary = state.summary();
+                                let _ = proxy.send_event(UserEvent::ImportState(Box::new(state)));
+                                // Applied asynchronously by the event loop: load, storage, reload, scroll
+                                json_response(202, &summary)
+                            }
+                            Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                        }
+                    };
+                    let _ = request_log::respond(request, response);
+                } else if url == "/source/view" {
+                    let _ = request_log::respond(request, crate::source::viewer_response());
+                } else if url == "/blocked" {
+                    let _ = request_log::respond(request, crate::navigation::blocked_response());
+                } else if let Some(response) = crate::blocker::handle(&request, url, &navigation.blocker, true) {
+                    let _ = request_log::respond(request, response);
+                } else if url == "/host-rules" {
+                    let response = crate::host_rules::handle(&mut request, &query, &navigation.host_rules);
+                    let _ = request_log::respond(request, response);
+                } else if url == "/history" {
+                    let response = crate::history::handle(&request, &query, &navigation.history);
+                    let _ = request_log::respond(request, response);
+                } else if url == "/source" {
+                    let target = query.get("url").cloned().or_else(|| ui_state.read().unwrap().active().map(|t| t.url.clone()));
+                    match (query.get("kind").map(String::as_str), target) {
+                        (_, None) => {
+                            let _ = request_log::respond(request, json_response(400, &serde_json::json!({"error": "missing url"})));
+                        }
+                        (Some("served"), Some(target)) => {
+                            // Fetching takes a while; keep serving the stream meanwhile
+                            let trace = request_log::detach();
+                            thread::spawn(move || {
+                                request_log::attach(trace);
+                                let _ = request_log::respond(request, command_response(crate::source::refetch(&target)));
+                            });
+                        }
+                        (None | Some("dom"), Some(target)) => {
+                            let response = match &*source_snapshot.lock().unwrap() {
+                                Some((page_url, html)) if *page_url == target => {
+                                    json_response(200, &serde_json::json!({"url": page_url, "kind": "dom", "html": html}))
+                                }
+                                _ => json_response(404, &serde_json::json!({"error": "no live DOM snapshot for this URL; open it and use view-source: from its tab"})),
+                            };
+                            let _ = request_log::respond(request, response);
+                        }
+                        (Some(other), _) => {
+                            let error = format!("unknown kind '{}', expected dom or served", other);
+                            let _ = request_log::respond(request, json_response(400, &serde_json::json!({"error": error})));
+                        }
+                    }
+                } else if url == "/workspaces" || url.starts_with("/workspaces/") {
+                    let response = crate::workspaces::handle(&mut request, url, &workspaces, &ui_state, |workspace| {
+                        let _ = proxy.send_event(UserEvent::ActivateWorkspace(workspace));
+                    });
+                    let _ = request_log::respond(request, response);
+                } else if url == "/split" {
+                    let response = match *request.method() {
+                        tiny_http::Method::Get => json_response(200, &serde_json::json!({"split": ui_state.read().unwrap().split})),
+                        tiny_http::Method::Post => {
+                            match read_json_body::<SplitRequest>(&mut request).and_then(|req| req.validate().map(|_| req)) {
+                                Ok(req) => {
+                                    let ui = ui_state.read().unwrap();
+                                    if req.tab.is_some_and(|id| !ui.tabs.iter().any(|t| t.id == id)) {
+                                        json_response(404, &serde_json::json!({"error": "no such tab"}))
+                                    } else if ui.split.is_none() && !req.tab.is_some_and(|id| id != ui.active_tab) {
+                                        json_response(409, &serde_json::json!({"error": "not split; give a background tab to open on the right"}))
+                                    } else {
+                                        let body = serde_json::json!(req);
+                                        let _ = proxy.send_event(UserEvent::SetSplit(req));
+                                        // Applied by the event loop; GET /ui-state shows the panes once it has
+                                        json_response(202, &body)
+                                    }
+                                }
+                                Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                            }
+                        }
+                        tiny_http::Method::Delete => {
+                            let pane = match query.get("pane").map(String::as_str) {
+                                None | Some("right") => Ok(Pane::Right),
+                                Some("left") => Ok(Pane::Left),
+                                Some(other) => Err(format!("unknown pane '{}', expected left or right", other)),
+                            };
+                            match pane {
+                                Ok(_) if ui_state.read().unwrap().split.is_none() => json_response(409, &serde_json::json!({"error": "not split"})),
+                                Ok(pane) => {
+                                    let _ = proxy.send_event(UserEvent::CloseSplit(pane));
+                                    json_response(202, &serde_json::json!({"status": "closing", "pane": pane}))
+                                }
+                                Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                            }
+                        }
+                        _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                    };
+                    let _ = request_log::respond(request, response);
+                } else if url == "/reload" {
+                    let response = if request.method() != &tiny_http::Method::Post {
+                        json_response(405, &serde_json::json!({"error": "method not allowed"}))
+                    } else {
+                        match read_json_body::<scroll::ReloadRequest>(&mut request) {
+                            Ok(req) => {
+                                let _ = proxy.send_event(UserEvent::Reload { restore_scroll: req.restore_scroll });
+                                // Scroll is reapplied by the event loop once the page has loaded again
+                                json_response(202, &serde_json::json!({"status": "reloading", "restore_scroll": req.restore_scroll}))
+                            }
+                            Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                        }
+                    };
+                    let _ = request_log::respond(request, response);
+                } else if url == "/window" {
+                    let response = match *request.method() {
+                        tiny_http::Method::Get => json_response(200, &serde_json::json!(ui_state.read().unwrap().window)),
+                        tiny_http::Method::Post => {
+                            match read_json_body::<WindowRequest>(&mut request).and_then(|req| req.validate().map(|_| req)) {
+                                Ok(req) => {
+                                    let body = serde_json::json!(req);
+                                    let _ = proxy.send_event(UserEvent::SetWindow(req));
+                                    // Applied by the event loop; GET /window reflects it once the window has settled
+                                    json_response(202, &body)
+                                }
+                                Err(e) => json_response(400, &serde_json::json!({"error": e})),
+                            }
+                        }
+                        _ => json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                    };
+                    let _ = request_log::respond(request, response);
+                } else if url == "/ui-state" {
+                    let body = serde_json::json!(*ui_state.read().unwrap());
+                    let _ = request_log::respond(request, json_response(200, &body));
+                } else if url == "/theme" {
+                    let active_tab = ui_state.read().unwrap().active_tab;
+                    let (response, changed) = theme::handle(&mut request, &theme, Some(active_tab));
+                    if changed {
+                        let _ = proxy.send_event(UserEvent::ThemeChanged);
+                    }
+                    let _ = request_log::respond(request, response);
+                } else if url == "/stats/pipeline" {
+                    let mut body = stats.lock().unwrap().to_json();
+                    body["encoder"] = encoder::NAME.into();
+                    let _ = request_log::respond(request, json_response(200, &body));
+                } else if url == "/status" {
+                    let mut pipeline = stats.lock().unwrap().to_json();
+                    pipeline["encoder"] = encoder::NAME.into();
+                    let (ui, unresponsive_tabs) = {
+                        let ui = ui_state.read().unwrap();
+                        let unresponsive: Vec<usize> = ui.tabs.iter().filter(|t| t.unresponsive).map(|t| t.id).collect();
+                        (ui.summary(), unresponsive)
+                    };
+                    let body = serde_json::json!({
+                        "mode": "gui",
+                        "ui": ui,
+                        "unresponsive_tabs": unresponsive_tabs,
+                        "recording": screen_recorder.lock().unwrap().to_json(),
+                        "blocking": crate::status::blocking(&navigation.blocker, true),
+                        "host_rules": navigation.host_rules.lock().unwrap().to_json(),
+                        "fonts": fonts.to_json(),
+                        "pipeline": pipeline,
+                        "uptime_secs": started.elapsed().as_secs(),
+                    });
+                    let _ = request_log::respond(request, json_response(200, &body));
+                } else if url == "/embed" {
+                    let _ = request_log::respond(request, embed.response(&query));
+                } else if url == "/fonts" {
+                    let _ = request_log::respond(request, json_response(200, &fonts.to_json()));
+                } else if url == "/geometry" {
+                    let (_, _, width, height) = *window_rect.lock().unwrap();
+                    let response = json_response(200, &serde_json::json!({
+                        "frame_width": width,
+                        "frame_height": height,
+                        "viewport_width": width,
+                        "viewport_height": height,
+                        "scale": 1.0,
+                    }));
+                    let _ = request_log::respond(request, response);
+                } else if url == "/journal" {
+                    if *request.method() == tiny_http::Method::Delete {
+                        journal.lock().unwrap().clear();
+                        let _ = request_log::respond(request, json_response(200, &serde_json::json!({"status": "cleared"})));
+                    } else if query.get("format").map(String::as_str) == Some("jsonl") {
+                        let body = journal.lock().unwrap().to_jsonl();
+                        let response = Response::from_string(body)
+                            .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/x-ndjson"[..]).unwrap());
+                        let _ = request_log::respond(request, response);
+                    } else {
+                        let body = serde_json::json!({"entries": journal.lock().unwrap().entries()});
+                        let _ = request_log::respond(request, json_response(200, &body));
+                    }
+                } else if url == "/live-stream" {
+                    screen_changed.store(false, Ordering::Relaxed);
+
+                    if let Some(jpeg_bytes) = capture_window(&window_rect, &annotations, &ticker, &stats, &mut frame_encoder, &ui_state) {
+                        let base64_frame = BASE64.encode(&jpeg_bytes);
+                        let json = serde_json::json!({
+                            "frame": base64_frame,
+                            "timestamp": std::time::SystemTime::now()
+                                .duration_since(std::time::UNIX_EPOCH)
+                                .unwrap()
+                                .as_millis(),
+                            "ui": ui_state.read().unwrap().summary(),
+                        });
+
+                        let response = Response::from_string(json.to_string())
+                            .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+                            .with_header(Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap())
+                            .with_header(Header::from_bytes(&b"Cache-Control"[..], &b"no-cache"[..]).unwrap());
+                        let _ = request_log::respond(request, response);
+                    } else {
+                        let response = Response::from_string(r#"{"error":"capture failed"}"#)
+                            .with_status_code(500)
+                            .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap());
+                        let _ = request_log::respond(request, response);
+                    }
+                } else if url == "/" {
+                    let html = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Rust Browser Claude - Live Stream</title>
+    <style>
+        body { margin: 0; background: #1a1a1a; display: flex; justify-content: center; align-items: center; min-height: 100vh; }
+        img { max-width: 100%; max-height: 100vh; }
+        #status { position: fixed; top: 10px; left: 10px; color: #0f0; font-family: monospace; background: rgba(0,0,0,0.7); padding: 5px 10px; border-radius: 4px; }
+    </style>
+</head>
+<body>
+    <div id="status">Connecting...</div>
+    <img id="screen" />
+    <script>
+        const img = document.getElementById('screen');
+        const status = document.getElementById('status');
+        let frameCount = 0;
+
+        async function fetchFrame() {
+            try {
+                const response = await fetch('/live-stream');
+                const data = await response.json();
+
+                if (data.frame) {
+                    img.src = 'data:image/jpeg;base64,' + data.frame;
+                    frameCount++;
+                    status.textContent = 'Frames: ' + frameCount;
+                }
+            } catch (e) {
+                status.textContent = 'Error: ' + e.message;
+            }
+
+            setTimeout(fetchFrame, 100);
+        }
+
+        status.textContent = 'Connected';
+        fetchFrame();
+    </script>
+</body>
+</html>"#;
+
+                    let response = Response::from_string(html)
+                        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap());
+                    let _ = request_log::respond(request, response);
+                } else {
+                    let response = Response::from_string("Not Found").with_status_code(404);
+                    let _ = request_log::respond(request, response);
+                }
+            }
+        });
+    }
+
+    /// Rebuilds the shared UI model from the event loop's own state.
+    fn sync_ui_state(
+        ui_state: &SharedUiState,
+        tabs: &Tabs,
+        window_rect: &WindowRect,
+        mode: WindowMode,
+        split: Option<Split>,
+        devtools_open: bool,
+        theme: &SharedTheme,
+    ) {
+        let (tabs_vec, active_id, _) = &*tabs.lock().unwrap();
+        let theme = theme.lock().unwrap();
+        let (x, y, width, height) = *window_rect.lock().unwrap();
+        *ui_state.write().unwrap() = UiState {
+            tabs: tabs_vec
+                .iter()
+                .map(|t| TabState {
+                    id: t.id,
+                    url: t.url.clone(),
+                    title: t.title.clone(),
+                    favicon: t.favicon.clone(),
+                    pinned: false,
+                    muted: false,
+                    loading: t.loading,
+                    hibernated: t.hibernated,
+                    pane: split.and_then(|s| s.pane_of(t.id)),
+                    unresponsive: t.unresponsive,
+                    dark: theme.is_dark(Some(t.id)),
+                })
+                .collect(),
+            active_tab: *active_id,
+            window: WindowState { x, y, width, height, mode },
+            split,
+            zoom: 1.0,
+            devtools_open,
+            updated_at: now_millis(),
+        };
+    }
+
+    pub fn run_gui(args: Args, imported: Option<HandoffState>) -> Result<(), Box<dyn std::error::Error>> {
+        let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+        let proxy = event_loop.create_proxy();
+
+        let mut window_mode = if args.kiosk { WindowMode::kiosk() } else { WindowMode::default() };
+        let window = WindowBuilder::new()
+            .with_title("Rust Browser Claude")
+            .with_inner_size(LogicalSize::new(args.width as f64, args.height as f64))
+            .with_fullscreen(window_mode.fullscreen.then_some(Fullscreen::Borderless(None)))
+            .with_decorations(!window_mode.kiosk)
+            .with_always_on_top(window_mode.always_on_top)
+            .build(&event_loop)?;
+        let kiosk_chord = serde_json::json!(args.kiosk_exit);
+
+        let screen_changed = Arc::new(AtomicBool::new(true));
+        let window_rect: WindowRect = Arc::new(Mutex::new((0, 0, args.width, args.height)));
+
+        if let Ok(pos) = window.outer_position() {
+            let size = window.outer_size();
+            *window_rect.lock().unwrap() = (pos.x, pos.y, size.width, size.height);
+        }
+
+        let journal: SharedJournal = Arc::new(Mutex::new(journal::Journal::new(journal::Redaction::new(&args.redact_fields))));
+        let annotations: SharedAnnotations = Arc::default();
+        let record_activity = args.record_activity;
+        let fonts = Arc::new(load_fonts(&args)?);
+        let embed = crate::embed::EmbedConfig::new(&args.embed_allowed_origins)?;
+        let ui_state: SharedUiState = Arc::default();
+        let source_snapshot: SourceSnapshot = Arc::default();
+        let workspaces_file = args.workspaces_file.clone().unwrap_or_else(crate::workspaces::default_file);
+        let workspaces: SharedWorkspaces = Arc::new(Mutex::new(crate::workspaces::Store::load(workspaces_file)?));
+        let blocker: crate::blocker::SharedBlocker = Arc::new(Mutex::new(crate::blocker::load(args.filter_list.as_ref())?));
+        let host_rules: crate::host_rules::SharedHostRules = Arc::new(Mutex::new(crate::host_rules::HostRules::new(args.host_rules.clone())));
+        let history: crate::history::SharedHistory = Arc::default();
+        let theme: SharedTheme = Arc::new(Mutex::new(theme::Theme::load(args.theme_rules.as_deref(), args.dark_schedule, false)?));
+        let screen_recorder: SharedScreenRecorder = {
+            let config = screen_record::RecordConfig::new(
+                args.record_dir.clone(),
+                args.record_fps,
+                args.record_format,
+                args.record_audio_input.clone(),
+                args.record_mic_input.clone(),
+            );
+            let rect = window_rect.clone();
+            let capture: screen_record::Capture = Arc::new(move || {
+                let (x, y, width, height) = *rect.lock().ok()?;
+                let screens = screenshots::Screen::all().ok()?;
+                let capture = screens.first()?.capture_area(x, y, width, height).ok()?;
+                image::RgbaImage::from_raw(capture.width(), capture.height(), capture.into_raw())
+            });
+            let proxy = Mutex::new(proxy.clone());
+            let notify = Arc::new(move || {
+                let _ = proxy.lock().unwrap().send_event(UserEvent::RecordingChanged);
+            });
+            Arc::new(Mutex::new(screen_record::ScreenRecorder::new(config, capture, notify)))
+        };
+        let instance = {
+            let ui_state = ui_state.clone();
+            load_instance(&args, "gui", Box::new(move || {
+                let ui = ui_state.read().unwrap();
+                ui.active().map(|tab| tab.url.clone()).unwrap_or_default()
+            }))?
+        };
+        {
+            let ui_state = ui_state.clone();
+            crash::set_context(move || serde_json::json!({
+                "mode": "gui",
+                "ui": ui_state.try_read().ok().map(|ui| ui.summary()),
+            }));
+        }
+
+        start_http_server_gui(
+            args.port,
+            screen_changed.clone(),
+            window_rect.clone(),
+            journal.clone(),
+            annotations,
+            create_ticker(&args),
+            fonts.clone(),
+            ui_state.clone(),
+            Arc::new(crate::auth::Auth::new(args.api_token.clone(), args.tokens.clone())?),
+            embed,
+            instance.clone(),
+            source_snapshot.clone(),
+            workspaces.clone(),
+            NavigationRules { blocker: blocker.clone(), host_rules: host_rules.clone(), history: history.clone() },
+            theme.clone(),
+            screen_recorder.clone(),
+            proxy.clone(),
+        );
+        advertise_instance(&args, &instance)?;
+        if let Some(dir) = &args.snapshot_to {
+            let owner = crate::snapshot::Owner::new(&instance, std::time::Duration::from_secs(args.snapshot_interval_secs.max(1)));
+            crate::snapshot::claim(dir, &owner)?;
+            let (proxy, ui_state) = (proxy.clone(), ui_state.clone());
+            crate::snapshot::spawn_writer(dir.clone(), owner, move || {
+                let (tx, rx) = std::sync::mpsc::channel();
+                let _ = proxy.send_event(UserEvent::ExportState(tx));
+                let state = rx.recv_timeout(EXPORT_TIMEOUT).map_err(|_| "page did not report its state in time".to_string())??;
+                let tabs = Workspace::from_ui("snapshot".to_string(), &ui_state.read().unwrap());
+                Ok(crate::snapshot::Contents { state, tabs: Some(tabs) })
+            });
+        }
+        if let Some(dir) = &args.follow {
+            let proxy = proxy.clone();
+            crate::snapshot::spawn_follower(dir.clone(), instance.id.clone(), move |changes| {
+                // Tabs first: the import then loads its page into the new active tab
+                if let Some(workspace) = changes.tabs {
+                    let _ = proxy.send_event(UserEvent::ActivateWorkspace(workspace));
+                }
+                if let Some(state) = changes.state {
+                    let _ = proxy.send_event(UserEvent::ImportState(Box::new(state)));
+                }
+                if let Some(scroll) = changes.scroll {
+                    let _ = proxy.send_event(UserEvent::ScrollTo(scroll));
+                }
+                Ok(())
+            });
+        }
+        if let Some(path) = &args.resource_log {
+            let interval = std::time::Duration::from_secs(args.resource_log_interval.max(1));
+            crate::resources::start_log(path.clone(), interval, || Ok(crate::resources::gui_report()));
+        }
+
+        let restored = match (args.restore_workspace, &imported) {
+            (true, Some(_)) => {
+                log_error!("Ignoring --restore-workspace: --import-state decides the first page");
+                None
+            }
+            (true, None) => workspaces.lock().unwrap().last_active().cloned(),
+            (false, _) => None,
+        };
+        let (start_url, tabs): (String, Tabs) = match restored {
+            Some(workspace) => {
+                log_info!("Restoring workspace '{}' ({} tabs)", workspace.name, workspace.tabs.len());
+                let (tabs_vec, active_id, next_id) = workspace_tabs(&workspace, 1);
+                let url = tabs_vec.iter().find(|t| t.id == active_id).map(|t| t.url.clone()).unwrap_or_else(|| args.url.clone());
+                (url, Arc::new(Mutex::new((tabs_vec, active_id, next_id))))
+            }
+            None => (args.url.clone(), Arc::new(Mutex::new((vec![Tab::new(1, args.url.clone())], 1, 2)))),
+        };
+        sync_ui_state(&ui_state, &tabs, &window_rect, window_mode, None, false, &theme);
+
+        let ipc = IpcContext {
+            tabs: tabs.clone(),
+            proxy: proxy.clone(),
+            screen_changed: screen_changed.clone(),
+            journal: journal.clone(),
+            workspaces: workspaces.clone(),
+            ui_state: ui_state.clone(),
+            source_snapshot,
+            navigator: Arc::new(crate::navigation::Navigator::new(blocker.clone(), host_rules.clone(), history.clone(), args.port)),
+        };
+        let mut init_scripts = vec![INIT_SCRIPT.to_string(), scroll::HELPER_SCRIPT.to_string()];
+        if record_activity {
+            init_scripts.push(journal::RECORDER_SCRIPT.to_string());
+        }
+        if fonts.is_active() {
+            init_scripts.push(fonts.init_script());
+        }
+
+        let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+        let mut main_slot: PaneSlot = Arc::new(Mutex::new(Pane::Left));
+        let mut webview = build_pane(&window, &start_url, crate::split::full(size.width, size.height), &init_scripts, ipc.clone(), main_slot.clone())?;
+
+        log_info!("Rust Browser Claude started (GUI mode)");
+        if window_mode.kiosk {
+            log_info!("Kiosk mode: press {} to leave it", args.kiosk_exit);
+        } else {
+            log_info!("Cmd+T: New tab | Cmd+W: Close tab | Cmd+L: Focus URL | Cmd+Shift+S: Save workspace | F11: Fullscreen | F12: DevTools");
+        }
+        println!();
+        log_info!("Live stream: http://localhost:{}/live-stream", args.port);
+        log_info!("Viewer:      http://localhost:{}/", args.port);
+
+        // Handoff being applied; the flag is set once storage is written and the page reloads
+        let mut pending_import = imported.map(|state| {
+            warn_http_only_cookies(&state);
+            (state, false)
+        });
+        let proxy_loop = proxy.clone();
+        let port = args.port;
+        // Split view state; a closed pane's webview is kept hidden until its tab snapshot arrives
+        let mut split: Option<Split> = None;
+        let mut right_view: RightPane = None;
+        let mut retiring: Option<(usize, WebView)> = None;
+        // Watchdog probes, indexed by pane
+        let mut probes: [watchdog::Probe; 2] = Default::default();
+        let recover_after = args.unresponsive_recover_secs.map(std::time::Duration::from_secs);
+        {
+            let proxy = proxy.clone();
+            thread::spawn(move || loop {
+                thread::sleep(watchdog::PROBE_INTERVAL);
+                if proxy.send_event(UserEvent::Probe).is_err() {
+                    break;
+                }
+            });
+        }
+
+        event_loop.run(move |event, _, control_flow| {
+            *control_flow = ControlFlow::Wait;
+
+            // Probes only touch the UI when a tab's responsiveness changes
+            let ui_changed = matches!(event, Event::WindowEvent { .. } | Event::UserEvent(_))
+                && !matches!(event, Event::UserEvent(UserEvent::Probe));
+            if ui_changed {
+                screen_changed.store(true, Ordering::Relaxed);
+            }
+
+            match event {
+                Event::WindowEvent {
+                    event: WindowEvent::CloseRequested,
+                    ..
+                } => {
+                    // The active workspace follows the tabs, so --restore-workspace reopens what was open
+                    if let Err(e) = workspaces.lock().unwrap().sync_active(&ui_state.read().unwrap()) {
+                        log_error!("Failed to update the active workspace: {}", e);
+                    }
+                    // Finish the file rather than leave ffmpeg mid-write
+                    screen_record::stop(&screen_recorder);
+                    *control_flow = ControlFlow::Exit;
+                }
+
+                Event::LoopDestroyed => crash::clean_exit(),
+
+                Event::WindowEvent {
+                    event: WindowEvent::Moved(position),
+                    ..
+                } => {
+                    let mut rect = window_rect.lock().unwrap();
+                    rect.0 = position.x;
+                    rect.1 = position.y;
+                }
+
+                Event::WindowEvent {
+                    event: WindowEvent::Resized(size),
+                    ..
+                } => {
+                    let mut rect = window_rect.lock().unwrap();
+                    rect.2 = size.width;
+                    rect.3 = size.height;
+                    // Entering or leaving fullscreen moves the window without always sending Moved
+                    if let Ok(pos) = window.outer_position() {
+                        rect.0 = pos.x;
+                        rect.1 = pos.y;
+                    }
+                    // A page leaving element fullscreen takes the window out of it too
+                    if window_mode.kiosk && window.fullscreen().is_none() {
+                        window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+                    }
+                    layout(&window, &webview, &right_view, split);
+                }
+
+                Event::WindowEvent {
+                    event: WindowEvent::KeyboardInput { event: key_event, .. },
+                    ..
+                } => {
+                    if key_event.state == tao::event::ElementState::Pressed && !window_mode.kiosk {
+                        match key_event.physical_key {
+                            tao::keyboard::KeyCode::F11 => {
+                                window_mode.fullscreen = window.fullscreen().is_none();
+                                window.set_fullscreen(window_mode.fullscreen.then_some(Fullscreen::Borderless(None)));
+                            }
+                            tao::keyboard::KeyCode::F9 => screen_record::toggle(&screen_recorder),
+                            tao::keyboard::KeyCode::F12 => {
+                                let focused = pane_view(&webview, &right_view, crate::split::focused_pane(split));
+                                if focused.is_devtools_open() {
+                                    focused.close_devtools();
+                                } else {
+                                    focused.open_devtools();
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                Event::UserEvent(ref user_event) => {
+                    match user_event {
+                        // A closed pane's webview lingers until its snapshot arrives; its page is no longer shown
+                        UserEvent::PageLoaded(Pane::Right) | UserEvent::PageInfo { pane: Pane::Right, .. } if split.is_none() => {}
+
+                        UserEvent::PageLoaded(pane) => {
+                            let view = pane_view(&webview, &right_view, *pane);
+                            let (tabs_vec, active_id, _) = &mut *tabs.lock().unwrap();
+                            let tab_id = pane_tab(split, *active_id, *pane);
+                            let restore = tabs_vec.iter_mut().find(|t| t.id == tab_id).and_then(|t| t.snapshot.take());
+                            let script = if window_mode.kiosk {
+                                commands::js_call(kiosk::KIOSK_SCRIPT, &[kiosk_chord.clone()])
+                            } else {
+                                toolbar_script(tabs_vec, *active_id, tab_id, split.as_ref(), *pane)
+                            };
+                            let _ = view.evaluate_script(&script);
+                            if screen_recorder.lock().unwrap().is_recording() {
+                                let _ = view.evaluate_script(&screen_record::badge_script(true));
+                            }
+                            {
+                                let theme = theme.lock().unwrap();
+                                let _ = view.evaluate_script(&theme.script(theme.is_dark(Some(tab_id))));
+                            }
+                            if let Some(state) = restore {
+                                let _ = view.evaluate_script(&commands::js_call(RESTORE_TAB_SCRIPT, &[state, scroll::RESTORE_TIMEOUT_MS.into()]));
+                            }
+
+                            // A handoff is applied to the focused pane, where its navigation went
+                            let import = if *pane == crate::split::focused_pane(split) { pending_import.take() } else { None };
+                            match import {
+                                Some((state, false)) => {
+                                    let payload = serde_json::to_value(&state).unwrap_or_default();
+                                    let restore = commands::js_call(handoff::RESTORE_SCRIPT, &[payload, true.into()]);
+                                    let _ = view.evaluate_script(&format!("{}; location.reload();", restore));
+                                    pending_import = Some((state, true));
+                                }
+                                Some((state, true)) => {
+                                    let _ = view.evaluate_script(&handoff::scroll_script(state.scroll));
+                                    log_info!("Handoff state applied for {}", state.url);
+                                }
+                                None => {}
+                            }
+                        }
+
+                        UserEvent::TabSnapshot { id, state } => {
+                            let (tabs_vec, _, _) = &mut *tabs.lock().unwrap();
+                            if let Some(tab) = tabs_vec.iter_mut().find(|t| t.id == *id) {
+                                tab.snapshot = Some(state.clone());
+                            }
+                            if retiring.as_ref().is_some_and(|(tab_id, _)| tab_id == id) {
+                                retiring = None;
+                            }
+                        }
+
+                        UserEvent::ExportState(reply) => {
+                            let cookies: Vec<handoff::Cookie> = match webview.cookies() {
+                                Ok(cookies) => cookies
+                                    .iter()
+                                    .map(|c| handoff::Cookie {
+                                        name: c.name().to_string(),
+                                        value: c.value().to_string(),
+                                        domain: c.domain().unwrap_or_default().to_string(),
+                                        path: c.path().unwrap_or("/").to_string(),
+                                        secure: c.secure().unwrap_or(false),
+                                        http_only: c.http_only().unwrap_or(false),
+                                        same_site: c.same_site().map(|s| s.to_string()),
+                                        expires: c.expires_datetime().map(|t| t.unix_timestamp() as f64),
+                                    })
+                                    .collect(),
+                                Err(e) => {
+                                    log_error!("Failed to read cookies for handoff: {}", e);
+                                    Vec::new()
+                                }
+                            };
+                            let size = window.inner_size().to_logical::<u32>(window.scale_factor());
+                            let viewport = handoff::Viewport { width: size.width, height: size.height };
+                            let (reply, error_reply) = (reply.clone(), reply.clone());
+                            let script = commands::js_call(handoff::EXPORT_SCRIPT, &[]);
+                            let focused = pane_view(&webview, &right_view, crate::split::focused_pane(split));
+                            let result = focused.evaluate_script_with_callback(&script, move |result| {
+                                let state = serde_json::from_str::<handoff::PageSnapshot>(&result)
+                                    .map(|snapshot| HandoffState::new("gui", snapshot, viewport, cookies.clone()))
+                                    .map_err(|e| format!("unexpected page state: {}", e));
+                                let _ = reply.send(state);
+                            });
+                            if let Err(e) = result {
+                                let _ = error_reply.send(Err(e.to_string()));
+                            }
+                        }
+
+                        UserEvent::ImportState(state) => {
+                            log_info!("Importing handoff state for {}", state.url);
+                            warn_http_only_cookies(state);
+                            if state.viewport.width > 0 && state.viewport.height > 0 {
+                                window.set_inner_size(LogicalSize::new(state.viewport.width as f64, state.viewport.height as f64));
+                            }
+                            pending_import = Some((state.as_ref().clone(), false));
+                            let _ = proxy_loop.send_event(UserEvent::Navigate(state.url.clone()));
+                        }
+
+                        UserEvent::ScrollTo(scroll) => {
+                            let focused = pane_view(&webview, &right_view, crate::split::focused_pane(split));
+                            let _ = focused.evaluate_script(&handoff::scroll_script(*scroll));
+                        }
+
+                        UserEvent::Reload { restore_scroll } => {
+                            let focused = pane_view(&webview, &right_view, crate::split::focused_pane(split));
+                            let (_, active_id, _) = &*tabs.lock().unwrap();
+                            // The snapshot reaches the event loop before the reloaded page reports in
+                            let js = if *restore_scroll {
+                                format!("{}; location.reload()", commands::js_call(SNAPSHOT_TAB_SCRIPT, &[(*active_id).into(), false.into()]))
+                            } else {
+                                "location.reload()".to_string()
+                            };
+                            let _ = focused.evaluate_script(&js);
+                        }
+
+                        UserEvent::KioskExit => {
+                            let req = WindowRequest { kiosk: Some(false), ..Default::default() };
+                            let _ = proxy_loop.send_event(UserEvent::SetWindow(req));
+                        }
+
+                        UserEvent::SetWindow(req) => {
+                            match apply_window(&window, &mut window_mode, req) {
+                                Some(true) => {
+                                    log_info!("Entered kiosk mode");
+                                    let script = commands::js_call(kiosk::KIOSK_SCRIPT, &[kiosk_chord.clone()]);
+                                    for view in std::iter::once(&webview).chain(right_view.as_ref().map(|(view, _)| view)) {
+                                        let _ = view.evaluate_script(&script);
+                                    }
+                                }
+                                Some(false) => {
+                                    log_info!("Left kiosk mode");
+                                    for view in std::iter::once(&webview).chain(right_view.as_ref().map(|(view, _)| view)) {
+                                        let _ = view.evaluate_script("window.__rbKiosk = false");
+                                    }
+                                    refresh_toolbars(&webview, &right_view, &tabs, split);
+                                }
+                                None => {}
+                            }
+                        }
+
+                        UserEvent::ActivateWorkspace(workspace) => {
+                            // The workspace's tabs replace the panes' tabs too
+                            if split.take().is_some() {
+                                right_view = None;
+                                layout(&window, &webview, &right_view, split);
+                            }
+                            let url;
+                            {
+                                let (tabs_vec, active_id, next_id) = &mut *tabs.lock().unwrap();
+                                let (new_tabs, new_active, new_next) = workspace_tabs(workspace, *next_id);
+                                url = new_tabs.iter().find(|t| t.id == new_active).map(|t| t.url.clone()).unwrap_or_default();
+                                *tabs_vec = new_tabs;
+                                *active_id = new_active;
+                                *next_id = new_next;
+                            }
+                            log_info!("Activated workspace '{}' ({} tabs)", workspace.name, workspace.tabs.len());
+                            let js = format!("window.location.href = '{}'", url.replace('\'', "\\'"));
+                            let _ = webview.evaluate_script(&js);
+                        }
+
+                        UserEvent::Navigate(url) if url == "rb://workspaces" => {
+                            let focused = pane_view(&webview, &right_view, crate::split::focused_pane(split));
+                            let page = format!("http://127.0.0.1:{}/workspaces/view", port);
+                            let _ = focused.evaluate_script(&format!("window.location.href = '{}'", page));
+                        }
+
+                        UserEvent::Navigate(url) if url.starts_with("view-source:") => {
+                            let focused = pane_view(&webview, &right_view, crate::split::focused_pane(split));
+                            let target = url.trim_start_matches("view-source:");
+                            let viewer = format!("http://127.0.0.1:{}/source/view?url={}", port, urlencoding::encode(target));
+                            let js = format!("{}; window.location.href = '{}'", source_snapshot_script(), viewer);
+                            let _ = focused.evaluate_script(&js);
+                        }
+
+                        UserEvent::Navigate(url) => {
+                            let url = if !url.starts_with("http://") && !url.starts_with("https://") {
+                                if url.contains('.') && !url.contains(' ') {
+                                    format!("https://{}", url)
+                                } else {
+                                    format!("https://www.google.com/search?q={}", url.replace(' ', "+"))
+                                }
+                            } else {
+                                url.clone()
+                            };
+
+                            {
+                                let (tabs_vec, active_id, _) = &mut *tabs.lock().unwrap();
+                                if let Some(tab) = tabs_vec.iter_mut().find(|t| t.id == *active_id) {
+                                    tab.url = url.clone();
+                                    tab.loading = true;
+                                    if let Ok(parsed) = url::Url::parse(&url) {
+                                        tab.title = parsed.host_str().unwrap_or("Page").to_string();
+                                    }
+                                }
+                            }
+
+                            if record_activity {
+                                journal.lock().unwrap().record(&url, JournalAction::Navigate { url: url.clone() });
+                            }
+
+                            let focused = pane_view(&webview, &right_view, crate::split::focused_pane(split));
+                            let js = format!("window.location.href = '{}'", url.replace('\'', "\\'"));
+                            let _ = focused.evaluate_script(&js);
+                        }
+
+                        UserEvent::NewTab => {
+                            let new_url = "https://example.com".to_string();
+                            let snapshot;
+                            {
+                                let (tabs_vec, active_id, next_id) = &mut *tabs.lock().unwrap();
+                                snapshot = hibernate_active(tabs_vec, *active_id);
+                                tabs_vec.push(Tab::new(*next_id, new_url.clone()));
+                                *active_id = *next_id;
+                                *next_id += 1;
+                                // While split, the new tab opens in the focused pane
+                                if let Some(s) = split.as_mut() {
+                                    s.set_tab(s.focused, *active_id);
+                                }
+                            }
+
+                            let focused = pane_view(&webview, &right_view, crate::split::focused_pane(split));
+                            let js = format!("{}; window.location.href = '{}'", snapshot, new_url);
+                            let _ = focused.evaluate_script(&js);
+                        }
+
+                        UserEvent::CloseTab(id) => {
+                            // Closing a pane's tab closes the pane first; the tab is then a background tab
+                            if let Some(pane) = split.and_then(|s| s.pane_of(*id)) {
+                                let _ = proxy_loop.send_event(UserEvent::CloseSplit(pane));
+                                let _ = proxy_loop.send_event(UserEvent::CloseTab(*id));
+                                return;
+                            }
+
+                            let should_navigate: Option<String>;
+                            {
+                                let (tabs_vec, active_id, _) = &mut *tabs.lock().unwrap();
+                                if tabs_vec.len() <= 1 {
+                                    return;
+                                }
+
+                                let idx = tabs_vec.iter().position(|t| t.id == *id);
+                                if let Some(idx) = idx {
+                                    tabs_vec.remove(idx);
+                                    theme.lock().unwrap().forget_tab(*id);
+
+                                    if *active_id == *id {
+                                        let new_idx = idx.min(tabs_vec.len() - 1);
+                                        *active_id = tabs_vec[new_idx].id;
+                                        tabs_vec[new_idx].loading = true;
+                                        tabs_vec[new_idx].hibernated = false;
+                                        should_navigate = Some(tabs_vec[new_idx].url.clone());
+                                    } else {
+                                        should_navigate = None;
+                                    }
+                                } else {
+                                    should_navigate = None;
+                                }
+                            }
+
+                            if let Some(url) = should_navigate {
+                                let js = format!("window.location.href = '{}'", url.replace('\'', "\\'"));
+                                let _ = webview.evaluate_script(&js);
+                            } else {
+                                refresh_toolbars(&webview, &right_view, &tabs, split);
+                            }
+                        }
+
+                        UserEvent::SwitchTab(id) => {
+                            // A tab already showing in a pane only takes the focus
+                            if let Some(Switch::Focus(pane)) = split.map(|s| s.switch_to(*id)) {
+                                let req = SplitRequest { focus: Some(pane), ..Default::default() };
+                                let _ = proxy_loop.send_event(UserEvent::SetSplit(req));
+                                return;
+                            }
+
+                            let url: String;
+                            let snapshot;
+                            {
+                                let (tabs_vec, active_id, _) = &mut *tabs.lock().unwrap();
+                                if *active_id == *id || !tabs_vec.iter().any(|t| t.id == *id) {
+                                    return;
+                                }
+                                snapshot = hibernate_active(tabs_vec, *active_id);
+                                let tab = tabs_vec.iter_mut().find(|t| t.id == *id).unwrap();
+                                *active_id = *id;
+                                tab.loading = true;
+                                tab.hibernated = false;
+                                url = tab.url.clone();
+                            }
+                            // Any other tab replaces the one in the focused pane
+                            if let Some(s) = split.as_mut() {
+                                if let Switch::Replace(pane) = s.switch_to(*id) {
+                                    s.set_tab(pane, *id);
+                                }
+                            }
+
+                            let focused = pane_view(&webview, &right_view, crate::split::focused_pane(split));
+                            let js = format!("{}; window.location.href = '{}'", snapshot, url.replace('\'', "\\'"));
+                            let _ = focused.evaluate_script(&js);
+                        }
+
+                        UserEvent::SetSplit(req) => {
+                            {
+                                let (tabs_vec, active_id, _) = &mut *tabs.lock().unwrap();
+                                if let Some(tab_id) = req.tab {
+                                    match crate::split::place(split, *active_id, tab_id) {
+                                        _ if !tabs_vec.iter().any(|t| t.id == tab_id) => {
+                                            log_error!("Can't open tab {} in split: no such tab", tab_id);
+                                        }
+                                        Placement::Focus(pane) => {
+                                            if let Some(s) = split.as_mut() {
+                                                s.focused = pane;
+                                            }
+                                        }
+                                        Placement::AlreadyShown => {
+                                            log_error!("Tab {} is already shown; open another tab in the split", tab_id);
+                                        }
+                                        Placement::Split => {
+                                            let tab = tabs_vec.iter_mut().find(|t| t.id == tab_id).unwrap();
+                                            tab.hibernated = false;
+                                            tab.loading = true;
+                                            let new_split = Split::new(*active_id, tab_id);
+                                            let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+                                            let (_, bounds) = new_split.bounds(size.width, size.height);
+                                            let slot: PaneSlot = Arc::new(Mutex::new(Pane::Right));
+                                            match build_pane(&window, &tab.url, bounds, &init_scripts, ipc.clone(), slot.clone()) {
+                                                Ok(view) => {
+                                                    right_view = Some((view, slot));
+                                                    split = Some(new_split);
+                                                    log_info!("Split view: tab {} | tab {}", new_split.left_tab, tab_id);
+                                                }
+                                                Err(e) => log_error!("Failed to open the split pane: {}", e),
+                                            }
+                                        }
+                                        // A background tab replaces the right pane's tab, which hibernates
+                                        Placement::ReplaceRight => {
+                                            let s = split.as_mut().unwrap();
+                                            if let Some(old) = tabs_vec.iter_mut().find(|t| t.id == s.right_tab) {
+                                                old.hibernated = true;
+                                            }
+                                            let snapshot = commands::js_call(SNAPSHOT_TAB_SCRIPT, &[s.right_tab.into(), true.into()]);
+                                            let tab = tabs_vec.iter_mut().find(|t| t.id == tab_id).unwrap();
+                                            tab.hibernated = false;
+                                            tab.loading = true;
+                                            s.right_tab = tab_id;
+                                            s.focused = Pane::Right;
+                                            if let Some((view, _)) = &right_view {
+                                                let js = format!("{}; window.location.href = '{}'", snapshot, tab.url.replace('\'', "\\'"));
+                                                let _ = view.evaluate_script(&js);
+                                            }
+                                        }
+                                    }
+                                }
+                                if let Some(s) = split.as_mut() {
+                                    if let Some(focus) = req.focus {
+                                        s.focused = focus;
+                                    }
+                                    if let Some(ratio) = req.ratio {
+                                        s.set_ratio(ratio);
+                                    }
+                                    *active_id = s.tab(s.focused);
+                                    let _ = pane_view(&webview, &right_view, s.focused).focus();
+                                }
+                            }
+                            layout(&window, &webview, &right_view, split);
+                            if !window_mode.kiosk {
+                                refresh_toolbars(&webview, &right_view, &tabs, split);
+                            }
+                        }
+
+                        UserEvent::ResizeSplit { pane, x } => {
+                            if let Some(s) = split.as_mut() {
+                                let width = window.inner_size().to_logical::<f64>(window.scale_factor()).width;
+                                s.drag_to(width, *pane, *x);
+                                layout(&window, &webview, &right_view, split);
+                            }
+                        }
+
+                        UserEvent::CloseSplit(pane) => {
+                            let Some(s) = split.take() else { return };
+                            // The remaining webview becomes the main one and keeps its live page
+                            if *pane == Pane::Left {
+                                if let Some((view, slot)) = right_view.as_mut() {
+                                    std::mem::swap(&mut webview, view);
+                                    std::mem::swap(&mut main_slot, slot);
+                                    *main_slot.lock().unwrap() = Pane::Left;
+                                    *slot.lock().unwrap() = Pane::Right;
+                                }
+                            }
+                            let closed_tab = s.tab(*pane);
+                            if let Some((view, _)) = right_view.take() {
+                                let _ = view.set_visible(false);
+                                let _ = view.evaluate_script(&commands::js_call(SNAPSHOT_TAB_SCRIPT, &[closed_tab.into(), true.into()]));
+                                retiring = Some((closed_tab, view));
+                            }
+                            {
+                                let (tabs_vec, active_id, _) = &mut *tabs.lock().unwrap();
+                                if let Some(tab) = tabs_vec.iter_mut().find(|t| t.id == closed_tab) {
+                                    tab.hibernated = true;
+                                }
+                                *active_id = s.tab(pane.other());
+                            }
+                            log_info!("Split view closed");
+                            layout(&window, &webview, &right_view, split);
+                            let _ = webview.evaluate_script(&crate::split::pane_script(None, Pane::Left));
+                            let _ = webview.focus();
+                            if !window_mode.kiosk {
+                                refresh_toolbars(&webview, &right_view, &tabs, split);
+                            }
+                        }
+
+                        UserEvent::Probe => {
+                            for pane in [Pane::Left, Pane::Right] {
+                                let probe = &mut probes[pane as usize];
+                                let view = match (pane, &right_view) {
+                                    (Pane::Right, None) => None,
+                                    _ => Some(pane_view(&webview, &right_view, pane)),
+                                };
+                                match view {
+                                    Some(view) if !view.is_devtools_open() => {
+                                        let (seq, answered) = probe.next();
+                                        let _ = view.evaluate_script_with_callback("1", move |_| answered.store(seq, Ordering::Relaxed));
+                                    }
+                                    // No pane, or a debugger that may be paused on purpose
+                                    _ => *probe = watchdog::Probe::default(),
+                                }
+                            }
+
+                            let mut recover = Vec::new();
+                            let changed = {
+                                let (tabs_vec, active_id, _) = &mut *tabs.lock().unwrap();
+                                let mut changed = false;
+                                for pane in [Pane::Left, Pane::Right] {
+                                    let probe = &probes[pane as usize];
+                                    if recover_after.is_some_and(|after| probe.needs_recovery(after)) {
+                                        recover.push(pane);
+                                    }
+                                }
+                                for tab in tabs_vec.iter_mut() {
+                                    let unresponsive = [Pane::Left, Pane::Right]
+                                        .into_iter()
+                                        .any(|pane| probes[pane as usize].is_unresponsive() && pane_tab(split, *active_id, pane) == tab.id);
+                                    if tab.unresponsive != unresponsive {
+                                        if unresponsive {
+                                            log_error!("Tab {} is unresponsive ({})", tab.id, tab.url);
+                                        } else {
+                                            log_info!("Tab {} is responsive again", tab.id);
+                                        }
+                                        tab.unresponsive = unresponsive;
+                                        changed = true;
+                                    }
+                                }
+                                changed
+                            };
+
+                            for pane in recover {
+                                let url = {
+                                    let (tabs_vec, active_id, _) = &*tabs.lock().unwrap();
+                                    let tab_id = pane_tab(split, *active_id, pane);
+                                    tabs_vec.iter().find(|t| t.id == tab_id).map(|t| t.url.clone()).unwrap_or_default()
+                                };
+                                log_error!("Recreating the unresponsive {:?} pane at {}", pane, url);
+                                let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+                                let bounds = crate::split::full(size.width, size.height);
+                                let slot = match (pane, &right_view) {
+                                    (Pane::Right, Some((_, slot))) => slot.clone(),
+                                    _ => main_slot.clone(),
+                                };
+                                match build_pane(&window, &url, bounds, &init_scripts, ipc.clone(), slot) {
+                                    // The old webview, and with it the hung page, goes away on assignment
+                                    Ok(view) => match (pane, right_view.as_mut()) {
+                                        (Pane::Right, Some((right, _))) => *right = view,
+                                        _ => webview = view,
+                                    },
+                                    Err(e) => log_error!("Failed to recreate the pane: {}", e),
+                                }
+                                probes[pane as usize] = watchdog::Probe::default();
+                                layout(&window, &webview, &right_view, split);
+                            }
+
+                            let changed = if theme.lock().unwrap().schedule_flipped() {
+                                refresh_themes(&webview, &right_view, &tabs, split, &theme);
+                                true
+                            } else {
+                                changed
+                            };
+                            if changed {
+                                screen_changed.store(true, Ordering::Relaxed);
+                                sync_ui_state(&ui_state, &tabs, &window_rect, window_mode, split, webview.is_devtools_open(), &theme);
+                            }
+                        }
+
+                        UserEvent::LoadInPane { pane, url } => {
+                            let _ = pane_view(&webview, &right_view, *pane).load_url(url);
+                        }
+
+                        UserEvent::ToggleTheme(pane) => {
+                            let tab_id = pane_tab(split, tabs.lock().unwrap().1, *pane);
+                            let mut theme_state = theme.lock().unwrap();
+                            theme_state.toggle(tab_id);
+                            let script = theme_state.script(theme_state.is_dark(Some(tab_id)));
+                            drop(theme_state);
+                            let _ = pane_view(&webview, &right_view, *pane).evaluate_script(&script);
+                        }
+
+                        UserEvent::ThemeChanged => {
+                            refresh_themes(&webview, &right_view, &tabs, split, &theme);
+                        }
+
+                        UserEvent::RecordingChanged => {
+                            let script = screen_record::badge_script(screen_recorder.lock().unwrap().is_recording());
+                            let _ = webview.evaluate_script(&script);
+                            if let Some((view, _)) = &right_view {
+                                let _ = view.evaluate_script(&script);
+                            }
+                        }
+
+                        UserEvent::PageInfo { pane, url, title, favicon } => {
+                            let (tabs_vec, active_id, _) = &mut *tabs.lock().unwrap();
+                            let tab_id = pane_tab(split, *active_id, *pane);
+                            if let Some(tab) = tabs_vec.iter_mut().find(|t| t.id == tab_id) {
+                                if !url.is_empty() {
+                                    tab.url = url.clone();
+                                }
+                                if !title.is_empty() {
+                                    tab.title = title.clone();
+                                }
+                                tab.favicon = favicon.clone();
+                                tab.loading = false;
+                            }
+                        }
+                    }
+                }
+
+                _ => {}
+            }
+
+            if ui_changed {
+                sync_ui_state(&ui_state, &tabs, &window_rect, window_mode, split, webview.is_devtools_open(), &theme);
+            }
+        });
+    }
+}
+
+// ============== Entry Point ==============
+
+/// Runs the browser as `args` ask; src/main.rs and the integration tests
+/// call this.
+pub fn run(mut args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    if args.mcp {
+        logs::redirect_to_stderr();
+    }
+
+    request_log::set_slow_threshold(args.slow_request_ms);
+    buffers::configure(&args.buffer_sizes);
+    if args.soak.is_some() {
+        args.url = bench::fixture_url();
+    }
+    if args.snapshot_to.is_some() && args.follow.is_some() {
+        return Err("--snapshot-to and --follow exclude each other: an instance is either the primary or the standby".into());
+    }
+
+    if let Some(dir) = &args.crash_dir {
+        crash::init(dir).map_err(|e| format!("Failed to prepare crash dir {}: {}", dir.display(), e))?;
+    }
+
+    if let Some(path) = &args.encrypt_credentials {
+        println!("{}", login::encrypt_file(path)?);
+        return Ok(());
+    }
+
+    let imported = match &args.import_state {
+        Some(path) => {
+            let state = handoff::HandoffState::load(path)?;
+            log_info!("Resuming handoff state from {} ({})", path.display(), state.url);
+            args.url = state.url.clone();
+            if state.viewport.width > 0 && state.viewport.height > 0 {
+                args.width = state.viewport.width;
+                args.height = state.viewport.height;
+            }
+            Some(state)
+        }
+        None => None,
+    };
+
+    let result = if args.headless || args.mcp {
+        // Run headless mode with tokio runtime
+        let rt = tokio::runtime::Runtime::new()?;
+        rt.block_on(run_headless(args, imported))
+    } else {
+        // Run GUI mode
+        gui::run_gui(args, imported)
+    };
+
+    if let Err(e) = &result {
+        crash::write_report("error", serde_json::json!({"message": e.to_string()}));
+    }
+    crash::clean_exit();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_bodies_are_read_with_a_limit() {
+        let body = br#"{"url": "https://example.com", "wait_until": "load"}"#;
+        let req: NavigateRequest = parse_json_body(&body[..], Some(body.len())).unwrap();
+        assert_eq!((req.url.as_str(), req.wait_until), ("https://example.com", WaitUntil::Load));
+
+        let error = parse_json_body::<NavigateRequest>(&br#"{"url": "#[..], None).unwrap_err();
+        assert!(error.starts_with("invalid JSON body"), "{}", error);
+        // Empty reads as {}, which still lacks the url
+        assert!(parse_json_body::<NavigateRequest>(&b"  "[..], Some(2)).unwrap_err().contains("missing field `url`"));
+        assert!(parse_json_body::<serde_json::Value>(&b""[..], Some(0)).unwrap().as_object().is_some_and(|o| o.is_empty()));
+
+        // Refused up front by Content-Length, or after reading one byte too many
+        let huge = format!(r#"{{"url": "{}"}}"#, "a".repeat(MAX_BODY_BYTES as usize));
+        assert!(parse_json_body::<NavigateRequest>(&b"{}"[..], Some(huge.len())).unwrap_err().contains("exceeds"));
+        assert!(parse_json_body::<NavigateRequest>(huge.as_bytes(), None).unwrap_err().contains("exceeds"));
+    }
+}
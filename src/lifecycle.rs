@@ -0,0 +1,228 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chromiumoxide::cdp::browser_protocol::emulation::{SetVirtualTimePolicyParams, VirtualTimePolicy};
+use chromiumoxide::cdp::browser_protocol::page::{SetWebLifecycleStateParams, SetWebLifecycleStateState};
+use chromiumoxide::Page;
+use serde::Serialize;
+
+use crate::handoff::{self, HandoffState};
+
+// ============== Idle Page Policy ==============
+//
+// Captures stop while the stream is paused (POST /lifecycle/pause) or, with
+// an --idle-page-policy, once nothing has polled /live-stream for
+// --idle-after seconds and no sink, frame hook, gRPC stream or recording
+// needs frames. The policy decides what happens to the page meanwhile:
+//
+// - freeze: Page.setWebLifecycleState frozen. Timers, animations and
+//   rendering stop; open WebSockets stay connected and their messages are
+//   delivered after the page is unfrozen, although the page cannot answer
+//   application-level pings while frozen.
+// - virtual-time: Emulation.setVirtualTimePolicy pause stops the page's
+//   clock, with connections untouched. Chrome has no way back to wall-clock
+//   time, so resuming switches the policy to advance: from then on timers
+//   fire as soon as the page is otherwise idle. Suits pages without
+//   intervals that would then spin.
+// - discard: the page is snapshotted like POST /handoff/export and replaced
+//   by about:blank, freeing its renderer; resuming imports the snapshot.
+//   Connections are closed and the page reloads.
+//
+// A viewer coming back, an HTTP command or a navigation resumes the page
+// before anything else touches it, and the next frame is captured right
+// after a forced reflow so it isn't stale.
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdlePagePolicy {
+    /// Keep the page running and capturing.
+    None,
+    Freeze,
+    VirtualTime,
+    Discard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageState {
+    Active,
+    /// Not captured, but otherwise untouched (paused without a policy).
+    Idle,
+    Frozen,
+    Paused,
+    Discarded,
+}
+
+impl PageState {
+    fn name(self) -> &'static str {
+        match self {
+            PageState::Active => "active",
+            PageState::Idle => "idle",
+            PageState::Frozen => "frozen",
+            PageState::Paused => "paused",
+            PageState::Discarded => "discarded",
+        }
+    }
+}
+
+pub struct PageLifecycle {
+    policy: IdlePagePolicy,
+    idle_after: Duration,
+    /// Set by POST /lifecycle/pause.
+    paused: bool,
+    last_viewer: Instant,
+    state: PageState,
+    /// Page kept by the discard policy.
+    discarded: Option<HandoffState>,
+    suspensions: u64,
+    last_change_at: Option<u128>,
+    last_error: Option<String>,
+}
+
+pub type SharedLifecycle = Arc<Mutex<PageLifecycle>>;
+
+impl PageLifecycle {
+    pub fn new(policy: IdlePagePolicy, idle_after: Duration) -> Self {
+        PageLifecycle {
+            policy,
+            idle_after,
+            paused: false,
+            last_viewer: Instant::now(),
+            state: PageState::Active,
+            discarded: None,
+            suspensions: 0,
+            last_change_at: None,
+            last_error: None,
+        }
+    }
+
+    /// A frame consumer or command showed up.
+    pub fn touch(&mut self) {
+        self.last_viewer = Instant::now();
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        if !paused {
+            self.touch();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether captures should stop; `busy` is true while something other
+    /// than /live-stream needs frames.
+    pub fn should_suspend(&self, busy: bool) -> bool {
+        self.paused || (self.policy != IdlePagePolicy::None && !busy && self.last_viewer.elapsed() >= self.idle_after)
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.state != PageState::Active
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "policy": self.policy,
+            "idle_after_secs": self.idle_after.as_secs(),
+            "state": self.state,
+            "paused": self.paused,
+            "idle_secs": self.last_viewer.elapsed().as_secs(),
+            "suspensions": self.suspensions,
+            "last_change_at": self.last_change_at,
+            "last_error": self.last_error,
+        })
+    }
+
+    fn set_state(&mut self, state: PageState) {
+        self.state = state;
+        self.last_change_at = Some(crate::now_millis());
+    }
+}
+
+/// Applies the policy to a page that should stop. On failure the page stays
+/// active, with captures stopped.
+pub async fn suspend(page: &Page, lifecycle: &SharedLifecycle, viewport: (u32, u32)) {
+    let policy = {
+        let state = lifecycle.lock().unwrap();
+        if state.is_suspended() {
+            return;
+        }
+        state.policy
+    };
+    let result = match policy {
+        IdlePagePolicy::None => Ok(PageState::Idle),
+        IdlePagePolicy::Freeze => page
+            .execute(SetWebLifecycleStateParams::new(SetWebLifecycleStateState::Frozen))
+            .await
+            .map(|_| PageState::Frozen)
+            .map_err(|e| e.to_string()),
+        IdlePagePolicy::VirtualTime => page
+            .execute(SetVirtualTimePolicyParams::new(VirtualTimePolicy::Pause))
+            .await
+            .map(|_| PageState::Paused)
+            .map_err(|e| e.to_string()),
+        IdlePagePolicy::Discard => match handoff::export(page, viewport).await {
+            Ok(snapshot) => {
+                lifecycle.lock().unwrap().discarded = Some(snapshot);
+                page.goto("about:blank").await.map(|_| PageState::Discarded).map_err(|e| e.to_string())
+            }
+            Err(e) => Err(e.message),
+        },
+    };
+    let mut state = lifecycle.lock().unwrap();
+    match result {
+        Ok(page_state) => {
+            log_info!("Page {}: no viewers", page_state.name());
+            state.set_state(page_state);
+            state.suspensions += 1;
+        }
+        Err(e) => {
+            log_error!("Failed to suspend the page: {}", e);
+            state.discarded = None;
+            state.last_error = Some(e);
+            state.set_state(PageState::Idle);
+        }
+    }
+}
+
+/// Undoes `suspend`. With `navigating` a discarded page is not restored,
+/// since a new URL is about to replace it anyway.
+pub async fn resume(page: &Page, lifecycle: &SharedLifecycle, navigating: bool) {
+    let (page_state, discarded) = {
+        let mut state = lifecycle.lock().unwrap();
+        state.touch();
+        if !state.is_suspended() {
+            return;
+        }
+        (state.state, state.discarded.take())
+    };
+    let result = match page_state {
+        PageState::Frozen => page
+            .execute(SetWebLifecycleStateParams::new(SetWebLifecycleStateState::Active))
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        PageState::Paused => page
+            .execute(SetVirtualTimePolicyParams::new(VirtualTimePolicy::Advance))
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        PageState::Discarded if !navigating => match &discarded {
+            Some(snapshot) => handoff::import(page, snapshot).await.map_err(|e| e.message),
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    };
+    // Layout may be stale after a freeze; make the next capture reflect now
+    let _ = page.evaluate("document.documentElement && document.documentElement.offsetHeight").await;
+
+    let mut state = lifecycle.lock().unwrap();
+    if let Err(e) = result {
+        log_error!("Failed to resume the page: {}", e);
+        state.last_error = Some(e);
+    }
+    log_info!("Page active");
+    state.set_state(PageState::Active);
+}
@@ -0,0 +1,65 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::commands::{self, CommandError, CommandResult, CommandSender};
+
+// ============== Links ==============
+//
+// GET /links lists the anchors of the headless page in document order as
+// {href, text, rel}, for driving a crawl without an evaluate snippet per
+// caller. hrefs are resolved against the document base URL here rather than
+// trusting `a.href`, so a malformed one is dropped instead of passed on, and
+// a repeated href is only listed where it first appears. ?selector= limits
+// the list to one container, ?same_origin=true to the page's own origin.
+
+/// Raw anchors of the whole document or of the first match of a selector.
+const LINKS_SCRIPT: &str = r#"
+function(find, selector) {
+    const root = selector === null ? document : find(selector, false);
+    if (!root) return null;
+    const links = [];
+    root.querySelectorAll('a[href]').forEach(function(a) {
+        links.push({ href: a.getAttribute('href'), text: a.innerText.trim(), rel: a.rel });
+    });
+    return { url: location.href, base: document.baseURI, links: links };
+}
+"#;
+
+#[derive(Debug, Serialize)]
+struct Link {
+    href: String,
+    text: String,
+    rel: String,
+}
+
+pub fn list(tx: &CommandSender, selector: Option<&str>, same_origin: bool) -> CommandResult {
+    let expression = commands::js_call_with_find(LINKS_SCRIPT, &[selector.into()]);
+    let found = commands::send(tx, commands::PageCommand::Evaluate { expression, await_promise: false })?;
+    if found.is_null() {
+        return Err(CommandError::new(404, format!("no element matches {}", selector.unwrap_or_default())));
+    }
+    let page = url::Url::parse(found["url"].as_str().unwrap_or_default())
+        .map_err(|e| CommandError::new(502, format!("page has no usable URL: {}", e)))?;
+    let base = found["base"].as_str().and_then(|b| url::Url::parse(b).ok()).unwrap_or_else(|| page.clone());
+
+    let mut seen = HashSet::new();
+    let mut links = Vec::new();
+    for raw in found["links"].as_array().into_iter().flatten() {
+        let Some(href) = raw["href"].as_str().and_then(|h| base.join(h.trim()).ok()) else {
+            continue;
+        };
+        if same_origin && href.origin() != page.origin() {
+            continue;
+        }
+        if !seen.insert(href.to_string()) {
+            continue;
+        }
+        links.push(Link {
+            href: href.into(),
+            text: raw["text"].as_str().unwrap_or_default().to_string(),
+            rel: raw["rel"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+    Ok(serde_json::json!(links))
+}
@@ -0,0 +1,317 @@
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    SetLocaleOverrideParams, SetTimezoneOverrideParams, SetUserAgentOverrideParams,
+};
+use chromiumoxide::cdp::browser_protocol::network::ClearBrowserCacheParams;
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{self, CommandError, CommandResult, CommandSender, PageCommand};
+
+// ============== Locale Profiles ==============
+//
+// A profile is a BCP 47 tag with an optional IANA timezone, e.g.
+// `fr-FR@Europe/Paris`. Applying one sets the Accept-Language header and
+// navigator.language(s) through the user-agent override (so the two cannot
+// disagree), the Intl locale and, when given, the timezone. --locale is the
+// default for the streamed page and /render; `locale=` on /navigate and
+// /render overrides it for that navigation or render, and a later /navigate
+// without one goes back to the default. The streamed page keeps its HTTP
+// cache across switches unless --locale-clear-cache is set; /render pages
+// have their own browser context and never share a cache.
+//
+// The same override carries the user agent, so --user-agent and POST
+// /useragent live here too: a replacement for the browser's own user agent,
+// applied together with the profile before the next load (the streamed page
+// is reloaded for it) and used by /render pages. DELETE /useragent goes
+// back to the browser's own.
+//
+// --timezone and POST /emulation `{"timezone": "America/New_York"}` set a
+// timezone for profiles that name none (and for no profile at all), and
+// /emulation `{"locale": "de-DE"}` replaces the --locale default at runtime;
+// both go through the same reload. Timezone names are checked against the
+// page's own Intl data first, so an unknown one is a 400, not a CDP error.
+
+const MAX_TAG_LEN: usize = 35;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocaleProfile {
+    pub locale: String,
+    pub timezone: Option<String>,
+}
+
+impl FromStr for LocaleProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (locale, timezone) = match s.trim().split_once('@') {
+            Some((locale, timezone)) => (locale.trim(), Some(timezone.trim())),
+            None => (s.trim(), None),
+        };
+        let valid_tag = !locale.is_empty()
+            && locale.len() <= MAX_TAG_LEN
+            && locale.split('-').all(|part| (1..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric()));
+        if !valid_tag {
+            return Err(format!("invalid locale '{}', expected a tag like fr-FR", locale));
+        }
+        let timezone = timezone.map(parse_timezone).transpose()?;
+        Ok(LocaleProfile { locale: locale.to_string(), timezone })
+    }
+}
+
+/// Syntax check of an IANA timezone name; whether Chrome knows it is up to
+/// `check_timezone`.
+pub fn parse_timezone(timezone: &str) -> Result<String, String> {
+    let timezone = timezone.trim();
+    if timezone.is_empty() || !timezone.chars().all(|c| c.is_ascii_alphanumeric() || "/_+-".contains(c)) {
+        return Err(format!("invalid timezone '{}', expected an IANA name like Europe/Paris", timezone));
+    }
+    Ok(timezone.to_string())
+}
+
+/// Evaluates to whether the page's Intl accepts `timezone`, which is what
+/// Emulation.setTimezoneOverride checks against.
+fn timezone_script(timezone: &str) -> String {
+    format!(
+        "(() => {{ try {{ new Intl.DateTimeFormat('en-US', {{ timeZone: {} }}); return true; }} catch (e) {{ return false; }} }})()",
+        serde_json::Value::from(timezone)
+    )
+}
+
+fn unknown_timezone(timezone: &str) -> String {
+    format!("unknown timezone '{}', expected an IANA name like America/New_York", timezone)
+}
+
+/// --timezone, checked on the streamed page before it is applied.
+pub async fn check_timezone(page: &Page, timezone: &str) -> Result<(), String> {
+    let known = page.evaluate(timezone_script(timezone)).await.map_err(|e| e.to_string())?;
+    match known.into_value::<bool>() {
+        Ok(true) => Ok(()),
+        _ => Err(unknown_timezone(timezone)),
+    }
+}
+
+impl LocaleProfile {
+    /// `fr-FR,fr;q=0.9`: the tag, then its bare language as a fallback.
+    pub fn accept_language(&self) -> String {
+        match self.locale.split_once('-') {
+            Some((language, _)) => format!("{},{};q=0.9", self.locale, language),
+            None => self.locale.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for LocaleProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.timezone {
+            Some(timezone) => write!(f, "{}@{}", self.locale, timezone),
+            None => f.write_str(&self.locale),
+        }
+    }
+}
+
+/// Applies `profile` to `page`, or restores the browser's own settings for
+/// `None`. The Accept-Language override has to restate the user agent, so
+/// `user_agent` is the effective one, see `LocaleState::effective_user_agent`.
+/// `timezone` is used when the profile names none.
+pub async fn apply(page: &Page, user_agent: &str, profile: Option<&LocaleProfile>, timezone: Option<&str>) -> Result<(), CdpError> {
+    let mut user_agent = SetUserAgentOverrideParams::new(user_agent);
+    user_agent.accept_language = profile.map(LocaleProfile::accept_language);
+    page.execute(user_agent).await?;
+    // Chrome refuses a new locale override while another is in effect
+    page.execute(SetLocaleOverrideParams { locale: None }).await?;
+    if let Some(profile) = profile {
+        page.execute(SetLocaleOverrideParams { locale: Some(profile.locale.clone()) }).await?;
+    }
+    let timezone = profile.and_then(|p| p.timezone.as_deref()).or(timezone).unwrap_or_default();
+    page.execute(SetTimezoneOverrideParams::new(timezone)).await?;
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct LocaleState {
+    /// --locale, used when a request names none.
+    pub default: Option<LocaleProfile>,
+    /// Applied to the streamed page.
+    active: Option<LocaleProfile>,
+    /// Wanted by the last /navigate; applied before it loads.
+    requested: Option<LocaleProfile>,
+    clear_cache: bool,
+    switches: u64,
+    /// Browser user agent, filled in once Chrome is up.
+    pub user_agent: String,
+    /// --user-agent or POST /useragent, replacing `user_agent`.
+    user_agent_override: Option<String>,
+    /// Override the streamed page is on.
+    applied_user_agent: Option<String>,
+    /// --timezone or POST /emulation, for profiles without a timezone.
+    timezone_override: Option<String>,
+    /// Timezone override the streamed page is on.
+    applied_timezone: Option<String>,
+}
+
+pub type SharedLocale = Arc<Mutex<LocaleState>>;
+
+impl LocaleState {
+    pub fn new(
+        default: Option<LocaleProfile>,
+        clear_cache: bool,
+        user_agent_override: Option<String>,
+        timezone_override: Option<String>,
+    ) -> Self {
+        LocaleState { requested: default.clone(), default, clear_cache, user_agent_override, timezone_override, ..Default::default() }
+    }
+
+    /// Replaces the user agent from the next load on; `None` restores the
+    /// browser's own.
+    pub fn set_user_agent(&mut self, user_agent: Option<String>) {
+        self.user_agent_override = user_agent;
+    }
+
+    pub fn user_agent_override(&self) -> Option<&str> {
+        self.user_agent_override.as_deref()
+    }
+
+    /// What the override restates: --user-agent / POST /useragent, else the
+    /// browser's own.
+    pub fn effective_user_agent(&self) -> String {
+        self.user_agent_override.clone().unwrap_or_else(|| self.user_agent.clone())
+    }
+
+    pub fn timezone_override(&self) -> Option<&str> {
+        self.timezone_override.as_deref()
+    }
+
+    /// Profile for the next /navigate: `profile` or the default.
+    pub fn request(&mut self, profile: Option<LocaleProfile>) {
+        self.requested = profile.or_else(|| self.default.clone());
+    }
+
+    /// Profile the streamed page is on, or about to be switched to.
+    pub fn requested(&self) -> Option<&LocaleProfile> {
+        self.requested.as_ref()
+    }
+
+    /// True while the streamed page is not yet on the requested profile,
+    /// user agent or timezone.
+    pub fn is_pending(&self) -> bool {
+        self.requested != self.active
+            || self.user_agent_override != self.applied_user_agent
+            || self.timezone_override != self.applied_timezone
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "default": self.default.as_ref().map(ToString::to_string),
+            "active": self.active.as_ref().map(ToString::to_string),
+            "accept_language": self.active.as_ref().map(LocaleProfile::accept_language),
+            "timezone": self.active.as_ref().and_then(|p| p.timezone.clone()).or_else(|| self.applied_timezone.clone()),
+            "clear_cache": self.clear_cache,
+            "switches": self.switches,
+            "user_agent": self.applied_user_agent,
+        })
+    }
+
+    /// GET /emulation: the default locale and the timezone override.
+    pub fn emulation_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "locale": self.default.as_ref().map(ToString::to_string),
+            "timezone": self.timezone_override,
+        })
+    }
+}
+
+/// POST /emulation body; an empty string clears the field, a missing one
+/// leaves it alone.
+#[derive(Debug, Deserialize)]
+pub struct EmulationRequest {
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+/// POST /emulation: validates both fields before changing either, then
+/// leaves the switch to the capture loop.
+pub fn emulate(tx: &CommandSender, locale: &SharedLocale, req: EmulationRequest) -> CommandResult {
+    let profile = match req.locale.as_deref().map(str::trim) {
+        Some("") => Some(None),
+        Some(tag) => Some(Some(tag.parse::<LocaleProfile>().map_err(|e| CommandError::new(400, e))?)),
+        None => None,
+    };
+    let timezone = match req.timezone.as_deref().map(str::trim) {
+        Some("") => Some(None),
+        Some(timezone) => Some(Some(parse_timezone(timezone).map_err(|e| CommandError::new(400, e))?)),
+        None => None,
+    };
+    let to_check = profile.iter().flatten().filter_map(|p| p.timezone.as_deref()).chain(timezone.iter().flatten().map(String::as_str));
+    for name in to_check {
+        let known = commands::send(tx, PageCommand::Evaluate { expression: timezone_script(name), await_promise: false })?;
+        if known != serde_json::Value::Bool(true) {
+            return Err(CommandError::new(400, unknown_timezone(name)));
+        }
+    }
+    let mut state = locale.lock().unwrap();
+    if let Some(profile) = profile {
+        state.default = profile;
+        state.request(None);
+    }
+    if let Some(timezone) = timezone {
+        state.timezone_override = timezone;
+    }
+    let mut json = state.emulation_json();
+    json["status"] = "applying".into();
+    Ok(json)
+}
+
+/// Brings the streamed page onto the requested profile, clearing the cache
+/// first if configured. On failure the page stays on its current profile.
+pub async fn sync(page: &Page, locale: &SharedLocale) {
+    let (wanted, clear_cache, user_agent, wanted_override, wanted_timezone) = {
+        let state = locale.lock().unwrap();
+        if !state.is_pending() {
+            return;
+        }
+        (
+            state.requested.clone(),
+            state.clear_cache,
+            state.effective_user_agent(),
+            state.user_agent_override.clone(),
+            state.timezone_override.clone(),
+        )
+    };
+    if clear_cache {
+        if let Err(e) = page.execute(ClearBrowserCacheParams::default()).await {
+            log_error!("Failed to clear the browser cache: {}", e);
+        }
+    }
+    match apply(page, &user_agent, wanted.as_ref(), wanted_timezone.as_deref()).await {
+        Ok(()) => {
+            match &wanted {
+                Some(profile) => log_info!("Locale: {}", profile),
+                None => log_info!("Locale: browser default"),
+            }
+            if let Some(user_agent) = &wanted_override {
+                log_info!("User agent: {}", user_agent);
+            }
+            if let Some(timezone) = &wanted_timezone {
+                log_info!("Timezone: {}", timezone);
+            }
+            let mut state = locale.lock().unwrap();
+            state.active = wanted;
+            state.applied_user_agent = wanted_override;
+            state.applied_timezone = wanted_timezone;
+            state.switches += 1;
+        }
+        Err(e) => {
+            log_error!("Failed to switch locale: {}", e);
+            let mut state = locale.lock().unwrap();
+            state.requested = state.active.clone();
+            state.user_agent_override = state.applied_user_agent.clone();
+            state.timezone_override = state.applied_timezone.clone();
+        }
+    }
+}
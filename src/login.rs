@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::commands::{self, CommandError, CommandSender, PageCommand, Stability, TypeMethod};
+
+// ============== Auto Login ==============
+//
+// Dashboards whose sessions expire leave the stream on a login page. With
+// --credentials-file, a watcher checks the page every --login-check-interval
+// seconds against each entry's URL pattern and detect selector. When one
+// matches, it runs the entry's recipe: either username/password/submit
+// selectors or a list of steps. The login page still being there after
+// the recipe counts as a failed attempt. After --login-max-attempts
+// failures in a row the entry stops retrying and is marked as needing
+// attention until POST /login-helper/reset. Every outcome is logged and
+// POSTed to each --login-webhook.
+//
+// The file is encrypted at rest with ChaCha20-Poly1305 under the SHA-256 of
+// $LOGIN_CREDENTIALS_KEY; --encrypt-credentials turns a plain JSON file
+// into one. Passwords are typed as secrets, so the journal and recordings
+// show `***`, and they never reach the log, webhooks or GET /login-helper.
+
+pub const KEY_ENV: &str = "LOGIN_CREDENTIALS_KEY";
+const FILE_PREFIX: &str = "rbcred1:";
+const NONCE_LEN: usize = 12;
+/// Time the page gets to leave the login page after the recipe.
+const SUBMIT_SETTLE: Duration = Duration::from_secs(5);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Step {
+    /// `text` may contain `{{username}}` and `{{password}}`.
+    Type { selector: String, text: String },
+    Click { selector: String },
+    Wait { ms: u64 },
+    Navigate { url: String },
+}
+
+/// No Debug, so the password can't end up in a log line.
+#[derive(Clone, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    /// URL glob where `*` matches anything, e.g. `https://grafana.example.com/login*`.
+    pub url: Option<String>,
+    /// Selector present only on the login page.
+    pub detect: Option<String>,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    pub username_selector: Option<String>,
+    pub password_selector: Option<String>,
+    pub submit_selector: Option<String>,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+#[derive(Deserialize)]
+struct CredentialsFile {
+    entries: Vec<Entry>,
+}
+
+impl Entry {
+    fn validate(&self) -> Result<(), String> {
+        if self.url.is_none() && self.detect.is_none() {
+            return Err(format!("entry '{}' needs a url pattern or a detect selector", self.name));
+        }
+        let selectors = [&self.username_selector, &self.password_selector, &self.submit_selector];
+        match (self.steps.is_empty(), selectors.iter().all(|s| s.is_some())) {
+            (true, false) => Err(format!("entry '{}' needs username/password/submit selectors or steps", self.name)),
+            (false, true) => Err(format!("entry '{}' has both selectors and steps", self.name)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The recipe as steps, with the selector form expanded.
+    fn recipe(&self) -> Vec<Step> {
+        match (&self.username_selector, &self.password_selector, &self.submit_selector) {
+            (Some(user), Some(pass), Some(submit)) if self.steps.is_empty() => vec![
+                Step::Type { selector: user.clone(), text: "{{username}}".to_string() },
+                Step::Type { selector: pass.clone(), text: "{{password}}".to_string() },
+                Step::Click { selector: submit.clone() },
+            ],
+            _ => self.steps.clone(),
+        }
+    }
+}
+
+fn cipher() -> Result<ChaCha20Poly1305, String> {
+    let secret = std::env::var(KEY_ENV).ok().filter(|v| !v.is_empty()).ok_or_else(|| format!("{} is not set", KEY_ENV))?;
+    Ok(cipher_for(&secret))
+}
+
+fn cipher_for(secret: &str) -> ChaCha20Poly1305 {
+    let key = Sha256::digest(secret.as_bytes());
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+/// Parses and validates a plain credentials file.
+fn parse_entries(plain: &[u8]) -> Result<Vec<Entry>, String> {
+    let file: CredentialsFile = serde_json::from_slice(plain).map_err(|e| format!("Invalid credentials file: {}", e))?;
+    for entry in &file.entries {
+        entry.validate()?;
+    }
+    Ok(file.entries)
+}
+
+/// --encrypt-credentials: the encrypted form of a plain credentials file.
+pub fn encrypt_file(path: &Path) -> Result<String, String> {
+    let plain = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_entries(&plain)?;
+    seal(&cipher()?, &plain)
+}
+
+fn seal(cipher: &ChaCha20Poly1305, plain: &[u8]) -> Result<String, String> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut sealed = nonce.to_vec();
+    sealed.extend(cipher.encrypt(&nonce, plain).map_err(|_| "encryption failed".to_string())?);
+    Ok(format!("{}{}", FILE_PREFIX, BASE64.encode(sealed)))
+}
+
+fn decrypt_file(path: &Path) -> Result<Vec<Entry>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    open(&cipher()?, &text, path)
+}
+
+/// The entries of an encrypted file's `text`; `path` is for messages.
+fn open(cipher: &ChaCha20Poly1305, text: &str, path: &Path) -> Result<Vec<Entry>, String> {
+    let sealed = text
+        .trim()
+        .strip_prefix(FILE_PREFIX)
+        .and_then(|b64| BASE64.decode(b64).ok())
+        .filter(|sealed| sealed.len() > NONCE_LEN)
+        .ok_or_else(|| format!("{} is not an encrypted credentials file; see --encrypt-credentials", path.display()))?;
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let plain = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| format!("Failed to decrypt {}: wrong {}?", path.display(), KEY_ENV))?;
+    parse_entries(&plain)
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+struct EntryState {
+    /// Failed attempts in a row.
+    attempts: u32,
+    needs_attention: bool,
+    successes: u64,
+}
+
+#[derive(Default)]
+pub struct LoginHelper {
+    entries: Vec<Entry>,
+    states: HashMap<String, EntryState>,
+    max_attempts: u32,
+    webhooks: Vec<String>,
+    last_event: Option<serde_json::Value>,
+}
+
+pub type SharedLoginHelper = Arc<Mutex<LoginHelper>>;
+
+impl LoginHelper {
+    pub fn load(path: Option<&Path>, max_attempts: u32, webhooks: Vec<String>) -> Result<Self, String> {
+        let Some(path) = path else { return Ok(LoginHelper::default()) };
+        let entries = decrypt_file(path)?;
+        log_info!("Auto login: {} entries from {}", entries.len(), path.display());
+        Ok(LoginHelper { entries, states: HashMap::new(), max_attempts: max_attempts.max(1), webhooks, last_event: None })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.entries.is_empty()
+    }
+
+    /// The first entry whose pattern and detect selector both hold on the
+    /// page; `present` has one flag per detect selector, in entry order.
+    fn matching(&self, url: &str, present: &[bool]) -> Option<Entry> {
+        let mut detected = present.iter();
+        self.entries
+            .iter()
+            .find(|entry| {
+                let selector_ok = entry.detect.is_none() || detected.next().copied().unwrap_or(false);
+                let url_ok = match &entry.url {
+                    Some(pattern) => crate::url_match::glob_matches(pattern, url),
+                    None => true,
+                };
+                url_ok && selector_ok
+            })
+            .cloned()
+    }
+
+    fn detect_selectors(&self) -> Vec<serde_json::Value> {
+        self.entries.iter().filter_map(|e| e.detect.clone()).map(serde_json::Value::from).collect()
+    }
+
+    pub fn reset(&mut self) {
+        self.states.clear();
+    }
+
+    fn needs_attention(&self, name: &str) -> bool {
+        self.states.get(name).is_some_and(|s| s.needs_attention)
+    }
+
+    /// Counts the outcome of a recipe run and answers the event to fire.
+    fn record(&mut self, name: &str, result: &Result<(), String>) -> serde_json::Value {
+        let max_attempts = self.max_attempts;
+        let state = self.states.entry(name.to_string()).or_default();
+        match result {
+            Ok(()) => {
+                state.attempts = 0;
+                state.successes += 1;
+                log_info!("Auto login '{}' succeeded", name);
+                serde_json::json!({"event": "login_succeeded", "entry": name})
+            }
+            Err(e) => {
+                state.attempts += 1;
+                state.needs_attention = state.attempts >= max_attempts;
+                log_error!("Auto login '{}' failed (attempt {}/{}): {}", name, state.attempts, max_attempts, e);
+                let event = if state.needs_attention { "login_needs_attention" } else { "login_failed" };
+                serde_json::json!({"event": event, "entry": name, "attempt": state.attempts, "error": e})
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let entries: Vec<serde_json::Value> = self
+            .entries
+            .iter()
+            .map(|e| {
+                serde_json::json!({
+                    "name": e.name,
+                    "url": e.url,
+                    "detect": e.detect,
+                    "state": self.states.get(&e.name).cloned().unwrap_or_default(),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "enabled": self.is_enabled(),
+            "needs_attention": self.states.values().any(|s| s.needs_attention),
+            "max_attempts": self.max_attempts,
+            "entries": entries,
+            "last_event": self.last_event,
+        })
+    }
+}
+
+/// `{url, present}` with one flag per detect selector.
+const DETECT_SCRIPT: &str = r#"
+function(selectors) {
+    return {
+        url: location.href,
+        present: selectors.map(function(selector) {
+            try { return document.querySelector(selector) !== null; } catch (e) { return false; }
+        }),
+    };
+}
+"#;
+
+const CLEAR_SCRIPT: &str = r#"
+function(selector) {
+    const el = document.querySelector(selector);
+    if (el && 'value' in el) el.value = '';
+}
+"#;
+
+/// The entry whose login page is showing, if any.
+fn detect(helper: &SharedLoginHelper, tx: &CommandSender) -> Result<Option<Entry>, CommandError> {
+    let selectors = helper.lock().unwrap().detect_selectors();
+    let expression = commands::js_call(DETECT_SCRIPT, &[serde_json::Value::Array(selectors)]);
+    let page = commands::send(tx, PageCommand::Evaluate { expression, await_promise: false })?;
+    let present: Vec<bool> = page["present"].as_array().map(|a| a.iter().map(|v| v.as_bool() == Some(true)).collect()).unwrap_or_default();
+    Ok(helper.lock().unwrap().matching(page["url"].as_str().unwrap_or_default(), &present))
+}
+
+fn run_recipe(entry: &Entry, tx: &CommandSender) -> Result<(), CommandError> {
+    for step in entry.recipe() {
+        match step {
+            Step::Type { selector, text } => {
+                let secret = text.contains("{{password}}");
+                let text = text.replace("{{username}}", &entry.username).replace("{{password}}", &entry.password);
+                let expression = commands::js_call(CLEAR_SCRIPT, &[selector.clone().into()]);
+                commands::send(tx, PageCommand::Evaluate { expression, await_promise: false })?;
+                commands::send(tx, PageCommand::Type { selector, text, submit: false, secret, method: TypeMethod::Auto, frame: None, stability: Stability::default() })?;
+            }
+            Step::Click { selector } => {
+                commands::send(tx, PageCommand::Click { selector, frame: None, stability: Stability::default() })?;
+            }
+            Step::Wait { ms } => thread::sleep(Duration::from_millis(ms)),
+            Step::Navigate { url } => {
+                commands::send(tx, PageCommand::Navigate { url, source: "login" })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Logs `event`, keeps it for GET /login-helper and POSTs it to the webhooks.
+fn fire(helper: &SharedLoginHelper, event: serde_json::Value) {
+    let webhooks = {
+        let mut helper = helper.lock().unwrap();
+        helper.last_event = Some(event.clone());
+        helper.webhooks.clone()
+    };
+    for url in webhooks {
+        if let Err(e) = ureq::post(&url).timeout(WEBHOOK_TIMEOUT).send_json(event.clone()) {
+            log_error!("Login webhook to {} failed: {}", url, e);
+        }
+    }
+}
+
+/// Checks the page every `interval` and logs in when a login page shows.
+pub fn spawn_watcher(helper: SharedLoginHelper, tx: CommandSender, interval: Duration) {
+    if !helper.lock().unwrap().is_enabled() {
+        return;
+    }
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let entry = match detect(&helper, &tx) {
+            Ok(Some(entry)) => entry,
+            Ok(None) => continue,
+            // The browser may still be starting or navigating
+            Err(_) => continue,
+        };
+        if helper.lock().unwrap().needs_attention(&entry.name) {
+            continue;
+        }
+
+        log_info!("Login page detected for '{}', running its recipe", entry.name);
+        let result = run_recipe(&entry, &tx).map_err(|e| e.message).and_then(|_| {
+            thread::sleep(SUBMIT_SETTLE);
+            match detect(&helper, &tx) {
+                Ok(Some(still)) if still.name == entry.name => Err("still on the login page after submitting".to_string()),
+                Ok(_) => Ok(()),
+                Err(e) => Err(e.message),
+            }
+        });
+
+        let mut event = helper.lock().unwrap().record(&entry.name, &result);
+        event["timestamp"] = serde_json::json!(crate::now_millis());
+        fire(&helper, event);
+    });
+}
+
+/// GET /login-helper and POST /login-helper/reset; None for other routes.
+pub fn handle(request: &tiny_http::Request, path: &str, helper: &SharedLoginHelper) -> Option<tiny_http::Response<std::io::Cursor<Vec<u8>>>> {
+    match path {
+        "/login-helper" => Some(crate::json_response(200, &helper.lock().unwrap().to_json())),
+        "/login-helper/reset" if *request.method() == tiny_http::Method::Post => {
+            let mut helper = helper.lock().unwrap();
+            helper.reset();
+            log_info!("Auto login attempts reset");
+            Some(crate::json_response(200, &helper.to_json()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r##"{"entries": [
+        {"name": "grafana", "url": "https://grafana.example.com/login*", "username": "admin", "password": "s3cret",
+         "username_selector": "#user", "password_selector": "#pass", "submit_selector": "button[type=submit]"},
+        {"name": "sso", "detect": "form#sso", "username": "me", "password": "pw",
+         "steps": [{"action": "navigate", "url": "https://sso.example.com/"},
+                   {"action": "type", "selector": "#login", "text": "{{username}}@corp"},
+                   {"action": "wait", "ms": 100},
+                   {"action": "click", "selector": "#go"}]}
+    ]}"##;
+
+    fn helper(max_attempts: u32) -> LoginHelper {
+        LoginHelper { entries: parse_entries(FILE.as_bytes()).unwrap(), max_attempts, ..Default::default() }
+    }
+
+    fn path() -> &'static Path {
+        Path::new("credentials.enc")
+    }
+
+    #[test]
+    fn encrypted_files_round_trip() {
+        let sealed = seal(&cipher_for("key"), FILE.as_bytes()).unwrap();
+        assert!(sealed.starts_with(FILE_PREFIX));
+        assert!(!sealed.contains("s3cret") && !sealed.contains("admin"));
+        // A fresh nonce every time
+        assert_ne!(sealed, seal(&cipher_for("key"), FILE.as_bytes()).unwrap());
+
+        let entries = open(&cipher_for("key"), &format!("{}\n", sealed), path()).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["grafana", "sso"]);
+        assert_eq!((entries[0].username.as_str(), entries[0].password.as_str()), ("admin", "s3cret"));
+    }
+
+    #[test]
+    fn rejects_wrong_keys_and_plain_files() {
+        let sealed = seal(&cipher_for("key"), FILE.as_bytes()).unwrap();
+        let error = open(&cipher_for("other"), &sealed, path()).err().unwrap();
+        assert!(error.contains("wrong LOGIN_CREDENTIALS_KEY"), "{}", error);
+
+        for text in [FILE, "rbcred1:not base64!", "rbcred1:AAAA"] {
+            let error = open(&cipher_for("key"), text, path()).err().unwrap();
+            assert!(error.contains("is not an encrypted credentials file"), "{}", error);
+        }
+
+        let tampered = {
+            let mut bytes = BASE64.decode(sealed.strip_prefix(FILE_PREFIX).unwrap()).unwrap();
+            *bytes.last_mut().unwrap() ^= 1;
+            format!("{}{}", FILE_PREFIX, BASE64.encode(bytes))
+        };
+        assert!(open(&cipher_for("key"), &tampered, path()).is_err());
+    }
+
+    #[test]
+    fn validates_entries() {
+        let parse = |entry: &str| parse_entries(format!(r#"{{"entries": [{}]}}"#, entry).as_bytes()).err();
+        assert!(parse(r##"{"name": "a", "url": "https://a/*", "steps": [{"action": "click", "selector": "#x"}]}"##).is_none());
+        assert!(parse(r#"{"name": "a", "steps": [{"action": "wait", "ms": 1}]}"#).unwrap().contains("needs a url pattern or a detect selector"));
+        assert!(parse(r##"{"name": "a", "url": "https://a/*", "username_selector": "#u"}"##).unwrap().contains("needs username/password/submit"));
+        let both = r##"{"name": "a", "url": "https://a/*", "username_selector": "#u", "password_selector": "#p",
+            "submit_selector": "#s", "steps": [{"action": "wait", "ms": 1}]}"##;
+        assert!(parse(both).unwrap().contains("has both selectors and steps"));
+        assert!(parse(r#"{"name": "a", "url": "x", "steps": [{"action": "dance"}]}"#).unwrap().starts_with("Invalid credentials file"));
+    }
+
+    #[test]
+    fn selector_recipes_expand_to_steps() {
+        let helper = helper(3);
+        let recipe: Vec<String> = helper.entries[0].recipe().iter().map(|s| format!("{:?}", s)).collect();
+        assert_eq!(
+            recipe,
+            [
+                r##"Type { selector: "#user", text: "{{username}}" }"##,
+                r##"Type { selector: "#pass", text: "{{password}}" }"##,
+                r#"Click { selector: "button[type=submit]" }"#,
+            ]
+        );
+        assert_eq!(helper.entries[1].recipe().len(), 4);
+    }
+
+    #[test]
+    fn matches_url_patterns_and_detect_selectors() {
+        let helper = helper(3);
+        let name = |url: &str, present: &[bool]| helper.matching(url, present).map(|e| e.name);
+        assert_eq!(name("https://grafana.example.com/login?redirect=/d/1", &[false]).as_deref(), Some("grafana"));
+        assert_eq!(name("https://grafana.example.com/d/1", &[false]), None);
+        // The sso entry has no URL pattern, only its selector
+        assert_eq!(name("https://anything.example.com/", &[true]).as_deref(), Some("sso"));
+        assert_eq!(name("https://anything.example.com/", &[]), None);
+        assert_eq!(helper.detect_selectors(), [serde_json::json!("form#sso")]);
+    }
+
+    #[test]
+    fn failures_in_a_row_need_attention() {
+        let mut helper = helper(2);
+        let failed = Err("still on the login page after submitting".to_string());
+
+        let event = helper.record("grafana", &failed);
+        assert_eq!((event["event"].as_str(), event["attempt"].as_u64()), (Some("login_failed"), Some(1)));
+        assert!(!helper.needs_attention("grafana"));
+        // A success in between starts the count over
+        assert_eq!(helper.record("grafana", &Ok(()))["event"], "login_succeeded");
+        helper.record("grafana", &failed);
+        let event = helper.record("grafana", &failed);
+        assert_eq!((event["event"].as_str(), event["attempt"].as_u64()), (Some("login_needs_attention"), Some(2)));
+        assert!(helper.needs_attention("grafana") && !helper.needs_attention("sso"));
+
+        let status = helper.to_json();
+        assert_eq!(status["needs_attention"], true);
+        assert_eq!(status["entries"][0]["state"], serde_json::json!({"attempts": 2, "needs_attention": true, "successes": 1}));
+
+        helper.reset();
+        assert!(!helper.needs_attention("grafana"));
+        assert_eq!(helper.to_json()["needs_attention"], false);
+    }
+
+    #[test]
+    fn status_never_shows_credentials() {
+        let mut helper = helper(1);
+        helper.record("sso", &Err("timed out".to_string()));
+        let status = helper.to_json().to_string();
+        for secret in ["s3cret", "admin", "\"pw\"", "\"me\""] {
+            assert!(!status.contains(secret), "{} in {}", secret, status);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::buffers::{self, Buffer, BufferStats};
+
+// ============== Log Ring Buffer ==============
+//
+// Runtime messages go through log_info!/log_error!, which print as before and
+// keep the last `--buffer-size logs=N` records (500 by default) for
+// GET /logs and crash reports. Lines
+// written while an HTTP request is handled carry its id.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    pub timestamp: u128,
+    pub level: &'static str,
+    pub message: String,
+}
+
+static RING: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+static STDERR_ONLY: AtomicBool = AtomicBool::new(false);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// For modes where stdout carries a protocol (--mcp): info messages go to stderr too.
+pub fn redirect_to_stderr() {
+    STDERR_ONLY.store(true, Ordering::Relaxed);
+}
+
+pub fn stderr_only() -> bool {
+    STDERR_ONLY.load(Ordering::Relaxed)
+}
+
+pub fn record(level: &'static str, message: String) {
+    // A poisoned lock only means another thread panicked mid-push; keep logging
+    let mut ring = RING.lock().unwrap_or_else(|e| e.into_inner());
+    while ring.len() >= Buffer::Logs.capacity() {
+        ring.pop_front();
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+    ring.push_back(LogRecord { timestamp: crate::now_millis(), level, message });
+}
+
+/// Prefixes the id of the HTTP request being handled on this thread, if any.
+pub fn tagged(message: String) -> String {
+    match crate::request_log::current_id() {
+        Some(id) => format!("[{}] {}", id, message),
+        None => message,
+    }
+}
+
+/// Snapshot of the buffer, oldest first.
+pub fn recent() -> Vec<LogRecord> {
+    RING.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+}
+
+pub fn buffer_stats() -> BufferStats {
+    let ring = RING.lock().unwrap_or_else(|e| e.into_inner());
+    buffers::stats(Buffer::Logs, ring.iter(), DROPPED.load(Ordering::Relaxed))
+}
+
+/// Like `recent`, but gives up instead of waiting, so the panic hook can
+/// never deadlock on a lock held by the panicking thread.
+pub fn try_recent() -> Vec<LogRecord> {
+    match RING.try_lock() {
+        Ok(ring) => ring.iter().cloned().collect(),
+        Err(std::sync::TryLockError::Poisoned(e)) => e.into_inner().iter().cloned().collect(),
+        Err(std::sync::TryLockError::WouldBlock) => Vec::new(),
+    }
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {{
+        let message = $crate::logs::tagged(format!($($arg)*));
+        if $crate::logs::stderr_only() {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+        $crate::logs::record("info", message);
+    }};
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => {{
+        let message = $crate::logs::tagged(format!($($arg)*));
+        eprintln!("{}", message);
+        $crate::logs::record("error", message);
+    }};
+}
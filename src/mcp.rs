@@ -0,0 +1,255 @@
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value};
+
+use crate::commands::{self, CommandSender, PageCommand, TypeMethod};
+
+// ============== MCP Server ==============
+//
+// With --mcp, the Model Context Protocol is spoken over stdio (JSON-RPC 2.0,
+// one message per line) so an LLM agent can drive the headless page. Tools
+// go through the same command channel as the HTTP API, which stays up for
+// watching the agent on the live stream. stdout belongs to the protocol, so
+// log messages are sent to stderr in this mode.
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+/// Stays under the command channel timeout.
+const MAX_WAIT_MS: u64 = 25_000;
+
+const READ_TEXT_SCRIPT: &str = r#"
+function(find, selector) {
+    const el = selector ? find(selector, true) : document.body;
+    return el.innerText;
+}
+"#;
+
+const WAIT_FOR_SCRIPT: &str = r#"
+async function(find, selector, timeoutMs) {
+    const deadline = Date.now() + timeoutMs;
+    while (!find(selector, false)) {
+        if (Date.now() > deadline) throw new Error('timed out after ' + timeoutMs + ' ms waiting for ' + selector);
+        await new Promise(function(resolve) { setTimeout(resolve, 100); });
+    }
+    return true;
+}
+"#;
+
+fn tools() -> Value {
+    json!([
+        {
+            "name": "navigate",
+            "description": "Open a URL in the browser and wait for it to load.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "url": { "type": "string", "description": "Absolute URL" } },
+                "required": ["url"],
+            },
+        },
+        {
+            "name": "screenshot",
+            "description": "Capture the current page as a JPEG image.",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "click",
+            "description": "Scroll the first element matching a CSS selector into view and click it.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "selector": { "type": "string" },
+                    "stable": { "type": "boolean", "description": STABLE_DESCRIPTION, "default": true },
+                    "timeout_ms": { "type": "integer", "minimum": 0, "maximum": MAX_WAIT_MS, "default": 2000 },
+                },
+                "required": ["selector"],
+            },
+        },
+        {
+            "name": "type",
+            "description": "Append text to the input, textarea or contenteditable matching a CSS selector.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "selector": { "type": "string" },
+                    "text": { "type": "string" },
+                    "submit": { "type": "boolean", "description": "Submit the element's form afterwards", "default": false },
+                    "method": { "type": "string", "enum": ["auto", "keys", "insert"], "description": "keys: one key event per character; insert: IME-style text insertion for non-ASCII text; auto picks by content", "default": "auto" },
+                    "stable": { "type": "boolean", "description": STABLE_DESCRIPTION, "default": true },
+                    "timeout_ms": { "type": "integer", "minimum": 0, "maximum": MAX_WAIT_MS, "default": 2000 },
+                },
+                "required": ["selector", "text"],
+            },
+        },
+        {
+            "name": "read_text",
+            "description": "Visible text of the element matching a CSS selector, or of the whole page.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "selector": { "type": "string" } },
+            },
+        },
+        {
+            "name": "evaluate",
+            "description": "Evaluate a JavaScript expression in the page; promises are awaited and the result is returned as JSON.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "expression": { "type": "string" } },
+                "required": ["expression"],
+            },
+        },
+        {
+            "name": "wait_for",
+            "description": "Wait until an element matching a CSS selector exists.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "selector": { "type": "string" },
+                    "timeout_ms": { "type": "integer", "minimum": 0, "maximum": MAX_WAIT_MS, "default": 5000 },
+                },
+                "required": ["selector"],
+            },
+        },
+    ])
+}
+
+const STABLE_DESCRIPTION: &str = "Wait until the element stops moving and fail if another element covers it";
+
+/// `stable` / `timeout_ms` tool arguments.
+fn stability(args: &Value) -> commands::Stability {
+    let mut stability = commands::Stability::default();
+    if let Some(stable) = args["stable"].as_bool() {
+        stability.stable = stable;
+    }
+    if let Some(timeout_ms) = args["timeout_ms"].as_u64() {
+        stability.timeout_ms = timeout_ms.min(MAX_WAIT_MS);
+    }
+    stability
+}
+
+fn text(value: impl Into<String>) -> Value {
+    json!({ "type": "text", "text": value.into() })
+}
+
+fn str_arg<'a>(args: &'a Value, name: &str) -> Result<&'a str, String> {
+    args[name].as_str().ok_or_else(|| format!("missing string argument '{}'", name))
+}
+
+fn evaluate(tx: &CommandSender, expression: String) -> Result<Value, String> {
+    commands::send(tx, PageCommand::Evaluate { expression, await_promise: true }).map_err(|e| e.message)
+}
+
+/// Runs one tool; `Err` becomes an `isError` result rather than a protocol error.
+fn call_tool(tx: &CommandSender, name: &str, args: &Value) -> Result<Vec<Value>, String> {
+    match name {
+        "navigate" => {
+            let url = str_arg(args, "url")?.to_string();
+            let result = commands::send(tx, PageCommand::Navigate { url, source: "mcp" }).map_err(|e| e.message)?;
+            Ok(vec![text(format!("Loaded {}", result["url"].as_str().unwrap_or_default()))])
+        }
+        "screenshot" => {
+            let result = commands::send(tx, PageCommand::Render { settle: None, deterministic: false }).map_err(|e| e.message)?;
+            let frame = result["frame"].as_str().ok_or("render returned no frame")?;
+            Ok(vec![json!({ "type": "image", "data": frame, "mimeType": "image/jpeg" })])
+        }
+        "click" => {
+            let selector = str_arg(args, "selector")?.to_string();
+            commands::send(tx, PageCommand::Click { selector: selector.clone(), frame: None, stability: stability(args) }).map_err(|e| e.message)?;
+            Ok(vec![text(format!("Clicked {}", selector))])
+        }
+        "type" => {
+            let selector = str_arg(args, "selector")?.to_string();
+            let typed = str_arg(args, "text")?.to_string();
+            let submit = args["submit"].as_bool().unwrap_or(false);
+            let method = match args["method"].as_str() {
+                None | Some("auto") => TypeMethod::Auto,
+                Some("keys") => TypeMethod::Keys,
+                Some("insert") => TypeMethod::Insert,
+                Some(_) => return Err("method must be auto, keys or insert".to_string()),
+            };
+            let count = typed.chars().count();
+            let result = commands::send(tx, PageCommand::Type { selector: selector.clone(), text: typed, submit, secret: false, method, frame: None, stability: stability(args) }).map_err(|e| e.message)?;
+            let used = result["method"].as_str().unwrap_or("auto");
+            Ok(vec![text(format!("Typed {} characters into {} ({})", count, selector, used))])
+        }
+        "read_text" => {
+            let selector = args["selector"].as_str().map(Value::from).unwrap_or(Value::Null);
+            let result = evaluate(tx, commands::js_call_with_find(READ_TEXT_SCRIPT, &[selector]))?;
+            Ok(vec![text(result.as_str().unwrap_or_default())])
+        }
+        "evaluate" => {
+            let result = evaluate(tx, str_arg(args, "expression")?.to_string())?;
+            Ok(vec![text(serde_json::to_string_pretty(&result).unwrap_or_default())])
+        }
+        "wait_for" => {
+            let selector = str_arg(args, "selector")?;
+            let timeout_ms = args["timeout_ms"].as_u64().unwrap_or(5000).min(MAX_WAIT_MS);
+            evaluate(tx, commands::js_call_with_find(WAIT_FOR_SCRIPT, &[selector.into(), timeout_ms.into()]))?;
+            Ok(vec![text(format!("{} is present", selector))])
+        }
+        _ => unreachable!("tool names are checked before dispatch"),
+    }
+}
+
+/// Answers one JSON-RPC request; `None` for notifications.
+fn handle_message(tx: &CommandSender, message: &Value) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let params = &message["params"];
+    let result: Result<Value, (i64, String)> = match message["method"].as_str().unwrap_or_default() {
+        "initialize" => Ok(json!({
+            "protocolVersion": params["protocolVersion"].as_str().unwrap_or(PROTOCOL_VERSION),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tools() })),
+        "tools/call" => {
+            let name = params["name"].as_str().unwrap_or_default();
+            if tools().as_array().unwrap().iter().any(|t| t["name"] == name) {
+                let args = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                Ok(match call_tool(tx, name, &args) {
+                    Ok(content) => json!({ "content": content, "isError": false }),
+                    Err(e) => {
+                        log_error!("MCP tool {} failed: {}", name, e);
+                        json!({ "content": [text(e)], "isError": true })
+                    }
+                })
+            } else {
+                Err((-32602, format!("unknown tool '{}'", name)))
+            }
+        }
+        method => Err((-32601, format!("method not found: {}", method))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }),
+    })
+}
+
+/// Reads requests from stdin until it closes, then exits the process.
+pub fn serve(tx: CommandSender) {
+    std::thread::spawn(move || {
+        log_info!("MCP server listening on stdio");
+        let stdin = std::io::stdin();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => handle_message(&tx, &message),
+                Err(e) => Some(json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": -32700, "message": format!("parse error: {}", e) },
+                })),
+            };
+            if let Some(reply) = reply {
+                let mut stdout = std::io::stdout().lock();
+                let _ = writeln!(stdout, "{}", reply);
+                let _ = stdout.flush();
+            }
+        }
+        log_info!("MCP client closed stdin, shutting down");
+        crate::crash::clean_exit();
+        std::process::exit(0);
+    });
+}
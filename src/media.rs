@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+use crate::commands::{self, CommandError, CommandResult, CommandSender, PageCommand};
+
+// ============== Media Playback Control ==============
+
+pub const AUTOPLAY_POLICIES: [&str; 3] = [
+    "no-user-gesture-required",
+    "user-gesture-required",
+    "document-user-activation-required",
+];
+
+const DEFAULT_SELECTOR: &str = "video, audio";
+
+const MEDIA_SCRIPT: &str = r#"
+async function(selector, action, time) {
+    const elements = Array.from(document.querySelectorAll(selector))
+        .filter(function(el) { return el instanceof HTMLMediaElement; });
+    const results = [];
+    for (let i = 0; i < elements.length; i++) {
+        const el = elements[i];
+        let error = null;
+        try {
+            if (action === 'pause') el.pause();
+            else if (action === 'play') await el.play();
+            else if (action === 'seek') el.currentTime = time;
+        } catch (e) {
+            error = e.message;
+        }
+        results.push({
+            index: i,
+            tag: el.tagName.toLowerCase(),
+            id: el.id || null,
+            src: el.currentSrc || el.src || null,
+            duration: isFinite(el.duration) ? el.duration : null,
+            currentTime: el.currentTime,
+            paused: el.paused,
+            muted: el.muted,
+            error: error
+        });
+    }
+    return results;
+}
+"#;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct MediaRequest {
+    pub selector: Option<String>,
+    pub time: Option<f64>,
+}
+
+/// Runs `action` (list, play, pause, seek) on every media element matching the selector.
+pub fn control(tx: &CommandSender, action: &str, req: MediaRequest) -> CommandResult {
+    if action == "seek" && !req.time.is_some_and(|t| t.is_finite() && t >= 0.0) {
+        return Err(CommandError::new(400, "seek requires a non-negative \"time\" in seconds"));
+    }
+    let selector = req.selector.unwrap_or_else(|| DEFAULT_SELECTOR.to_string());
+    let expression = commands::js_call(
+        MEDIA_SCRIPT,
+        &[selector.clone().into(), action.into(), req.time.unwrap_or(0.0).into()],
+    );
+    let media = commands::send(tx, PageCommand::Evaluate { expression, await_promise: true })?;
+
+    if action != "list" && media.as_array().is_some_and(|m| m.is_empty()) {
+        return Err(CommandError::new(404, format!("no media elements match '{}'", selector)));
+    }
+    Ok(serde_json::json!({ "selector": selector, "media": media }))
+}
@@ -0,0 +1,117 @@
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::emulation::{MediaFeature, SetEmulatedMediaParams};
+use chromiumoxide::Page;
+use serde::{Deserialize, Serialize};
+
+use crate::source::SharedDocument;
+
+// ============== CSS Media Emulation (headless) ==============
+//
+// POST /media/emulation `{"color_scheme": "dark", "reduced_motion": true,
+// "media": "print"}` drives Emulation.setEmulatedMedia, so the page's own
+// prefers-color-scheme, prefers-reduced-motion and print rules apply to the
+// stream without touching its toggles (unlike /theme, which forces a dark
+// stylesheet onto pages that have none). It sits under /media, whose root
+// lists the page's media elements. A POST replaces the whole set; fields
+// left out go back to the browser's value, and DELETE clears it.
+// --color-scheme sets the starting scheme. Like /cpu-throttle, the capture
+// loop applies changes and reapplies the set when the main-frame document
+// changes; /status reports it.
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+impl ColorScheme {
+    fn name(self) -> &'static str {
+        match self {
+            ColorScheme::Light => "light",
+            ColorScheme::Dark => "dark",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    Screen,
+    Print,
+}
+
+/// The emulated set; `None` leaves the browser's own value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MediaEmulation {
+    #[serde(default)]
+    pub color_scheme: Option<ColorScheme>,
+    #[serde(default)]
+    pub reduced_motion: Option<bool>,
+    #[serde(default)]
+    pub media: Option<MediaType>,
+}
+
+impl MediaEmulation {
+    fn params(&self) -> SetEmulatedMediaParams {
+        let mut features = Vec::new();
+        if let Some(scheme) = self.color_scheme {
+            features.push(MediaFeature::new("prefers-color-scheme", scheme.name()));
+        }
+        if let Some(reduce) = self.reduced_motion {
+            features.push(MediaFeature::new("prefers-reduced-motion", if reduce { "reduce" } else { "no-preference" }));
+        }
+        // An empty media type ends that override; the features replace all earlier ones
+        let media = match self.media {
+            Some(MediaType::Print) => "print",
+            Some(MediaType::Screen) => "screen",
+            None => "",
+        };
+        SetEmulatedMediaParams { media: Some(media.to_string()), features: Some(features) }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Media {
+    wanted: MediaEmulation,
+    /// Set last sent to Chrome and the document it was sent for.
+    applied: (MediaEmulation, Option<String>),
+}
+
+pub type SharedMedia = Arc<Mutex<Media>>;
+
+impl Media {
+    pub fn new(color_scheme: Option<ColorScheme>) -> Self {
+        Media { wanted: MediaEmulation { color_scheme, ..Default::default() }, ..Default::default() }
+    }
+
+    pub fn set(&mut self, emulation: MediaEmulation) {
+        self.wanted = emulation;
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!(self.wanted)
+    }
+}
+
+/// Applies the wanted set if it changed, or if a page with emulation on
+/// loaded a new document since it was applied.
+pub async fn sync(page: &Page, media: &SharedMedia, document: &SharedDocument) {
+    let current = document.lock().unwrap().as_ref().map(|id| id.inner().clone());
+    let wanted = {
+        let media = media.lock().unwrap();
+        let (applied, applied_document) = &media.applied;
+        let stale = media.wanted != MediaEmulation::default() && *applied_document != current;
+        if *applied == media.wanted && !stale {
+            return;
+        }
+        media.wanted.clone()
+    };
+    if let Err(e) = page.execute(wanted.params()).await {
+        log_error!("Failed to set emulated media: {}", e);
+    }
+    // Also on failure, so a broken page isn't retried every frame
+    media.lock().unwrap().applied = (wanted, current);
+}
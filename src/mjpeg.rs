@@ -0,0 +1,63 @@
+use std::io::{Cursor, Read};
+
+use crate::{FrameNotifier, ScreenshotBuffer};
+
+// ============== MJPEG Stream ==============
+//
+// GET /mjpeg holds the connection open and writes each new live frame as a
+// part of a multipart/x-mixed-replace stream, so a plain
+// `<img src="/mjpeg">` shows the page without any script. A part is written
+// when the frame counter moves, so an unchanged buffer is never sent twice
+// and a slow client skips the frames published while it was still reading.
+// An open stream subscribes to the counter, which keeps the page captured
+// like a gRPC frame stream does. A client that goes away fails the write and
+// ends the stream's own thread.
+
+const BOUNDARY: &str = "frame";
+
+fn part(jpeg: &[u8]) -> Vec<u8> {
+    let mut part = format!("--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n", BOUNDARY, jpeg.len()).into_bytes();
+    part.extend_from_slice(jpeg);
+    part.extend_from_slice(b"\r\n");
+    part
+}
+
+/// Blocking body of the stream: one part per published frame.
+struct FrameReader {
+    frames: tokio::sync::watch::Receiver<u64>,
+    buffer: ScreenshotBuffer,
+    pending: Cursor<Vec<u8>>,
+}
+
+impl Read for FrameReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pending.position() as usize >= self.pending.get_ref().len() {
+            // Capture loop gone: end of stream
+            if futures::executor::block_on(self.frames.changed()).is_err() {
+                return Ok(0);
+            }
+            self.frames.borrow_and_update();
+            if let Some(frame) = self.buffer.lock().unwrap().clone() {
+                self.pending = Cursor::new(part(&frame));
+            }
+        }
+        self.pending.read(buf)
+    }
+}
+
+/// GET /mjpeg. Blocks until the client goes away, so call it from its own
+/// thread.
+pub fn serve(request: tiny_http::Request, buffer: ScreenshotBuffer, frames: &FrameNotifier) {
+    let mut receiver = frames.subscribe();
+    receiver.borrow_and_update();
+    // The current frame goes out right away instead of after the next capture
+    let first = buffer.lock().unwrap().as_deref().map(part).unwrap_or_default();
+    let content_type = format!("multipart/x-mixed-replace; boundary={}", BOUNDARY);
+    let headers = vec![
+        tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap(),
+        tiny_http::Header::from_bytes(&b"Cache-Control"[..], &b"no-cache"[..]).unwrap(),
+        tiny_http::Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap(),
+    ];
+    let reader = FrameReader { frames: receiver, buffer, pending: Cursor::new(first) };
+    let _ = crate::request_log::respond(request, tiny_http::Response::new(tiny_http::StatusCode(200), headers, reader, None, None));
+}
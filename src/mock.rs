@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chromiumoxide::cdp::browser_protocol::fetch::{FulfillRequestParams, HeaderEntry, RequestId};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use serde::{Deserialize, Serialize};
+
+// ============== Response Mocking (headless) ==============
+//
+// POST /mock registers a canned response for requests whose URL matches
+// `url_pattern` (`*` matches anything), optionally only for one `method`:
+// the Fetch interceptor answers them with Fetch.fulfillRequest and they
+// never reach the network, so frontends can be captured against fixed API
+// data. `body` is text, `body_base64` a binary payload; `delay_ms` holds the
+// answer back to imitate a slow backend without stalling other requests.
+// The first matching rule wins. GET /mock lists the rules with how often
+// each matched, DELETE /mock/{id} removes one and DELETE /mock all of them.
+
+/// Longest accepted `delay_ms`.
+const MAX_DELAY_MS: u64 = 60_000;
+
+#[derive(Debug, Deserialize)]
+pub struct MockRequest {
+    pub url_pattern: String,
+    #[serde(default)]
+    pub method: Option<String>,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub body_base64: Option<String>,
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MockRule {
+    pub id: u64,
+    pub url_pattern: String,
+    pub method: Option<String>,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    /// Body size in bytes; the body itself is not echoed back.
+    pub body_bytes: usize,
+    pub delay_ms: u64,
+    pub hits: u64,
+    #[serde(skip)]
+    body: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+pub struct Mocks {
+    rules: Vec<MockRule>,
+    next_id: u64,
+}
+
+pub type SharedMocks = Arc<Mutex<Mocks>>;
+
+/// What the interceptor sends for a mocked request.
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<HeaderEntry>,
+    body: String,
+    delay_ms: u64,
+}
+
+impl Mocks {
+    pub fn is_active(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    pub fn add(&mut self, req: MockRequest) -> Result<MockRule, String> {
+        let url_pattern = req.url_pattern.trim().to_string();
+        if url_pattern.is_empty() {
+            return Err("url_pattern must not be empty".to_string());
+        }
+        if !(100..=599).contains(&req.status) {
+            return Err(format!("status {} is not an HTTP status", req.status));
+        }
+        if req.delay_ms > MAX_DELAY_MS {
+            return Err(format!("delay_ms must be at most {}", MAX_DELAY_MS));
+        }
+        let body = match (req.body, req.body_base64) {
+            (Some(_), Some(_)) => return Err("pass either body or body_base64".to_string()),
+            (Some(text), None) => text.into_bytes(),
+            (None, Some(encoded)) => BASE64.decode(encoded.trim()).map_err(|e| format!("invalid body_base64: {}", e))?,
+            (None, None) => Vec::new(),
+        };
+        if let Some(name) = req.headers.keys().find(|name| name.is_empty() || name.chars().any(|c| c.is_whitespace() || c == ':')) {
+            return Err(format!("invalid header name '{}'", name));
+        }
+        self.next_id += 1;
+        let rule = MockRule {
+            id: self.next_id,
+            url_pattern,
+            method: req.method.map(|m| m.trim().to_ascii_uppercase()).filter(|m| !m.is_empty()),
+            status: req.status,
+            content_type: req.content_type.filter(|c| !c.trim().is_empty()),
+            headers: req.headers,
+            body_bytes: body.len(),
+            delay_ms: req.delay_ms,
+            hits: 0,
+            body,
+        };
+        self.rules.push(rule.clone());
+        Ok(rule)
+    }
+
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|r| r.id != id);
+        self.rules.len() != before
+    }
+
+    pub fn clear(&mut self) -> usize {
+        std::mem::take(&mut self.rules).len()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "rules": self.rules })
+    }
+
+    /// The response of the first rule matching the request, counting the hit.
+    pub fn check(&mut self, method: &str, url: &str) -> Option<MockResponse> {
+        let rule = self
+            .rules
+            .iter_mut()
+            .find(|r| r.method.as_deref().is_none_or(|m| m.eq_ignore_ascii_case(method)) && crate::url_match::glob_matches(&r.url_pattern, url))?;
+        rule.hits += 1;
+        let mut headers: Vec<HeaderEntry> = rule.headers.iter().map(|(name, value)| HeaderEntry::new(name.clone(), value.clone())).collect();
+        if let Some(content_type) = &rule.content_type {
+            headers.retain(|h| !h.name.eq_ignore_ascii_case("content-type"));
+            headers.push(HeaderEntry::new("Content-Type", content_type.clone()));
+        }
+        Some(MockResponse { status: rule.status, headers, body: BASE64.encode(&rule.body), delay_ms: rule.delay_ms })
+    }
+}
+
+/// Answers a paused request with `response`, after its delay.
+pub async fn fulfill(page: &Page, request_id: RequestId, response: MockResponse) -> Result<(), CdpError> {
+    if response.delay_ms > 0 {
+        tokio::time::sleep(std::time::Duration::from_millis(response.delay_ms)).await;
+    }
+    let params = FulfillRequestParams::builder()
+        .request_id(request_id)
+        .response_code(response.status as i64)
+        .response_headers(response.headers)
+        .body(response.body)
+        .build()
+        .map_err(CdpError::msg)?;
+    page.execute(params).await?;
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use std::sync::Mutex;
+
+use crate::blocker::{RequestInfo, SharedBlocker};
+use crate::history::{Outcome, SharedHistory};
+use crate::host_rules::SharedHostRules;
+
+// ============== GUI Navigation Rules ==============
+//
+// The GUI webview offers no request interception, only a veto over each
+// top-level navigation. The navigation handler runs every http(s)
+// navigation through the headless filter list and host rules. A blocked
+// navigation loads the internal /blocked page naming the filter instead.
+// A navigation to a remapped host loads the same URL on the target host.
+// Every decision is recorded in the navigation history. Subresources,
+// iframes and requests made by scripts are not covered.
+
+pub struct Navigator {
+    blocker: SharedBlocker,
+    host_rules: SharedHostRules,
+    history: SharedHistory,
+    port: u16,
+    /// The last redirect target, let through once so a rule whose target
+    /// matches itself cannot loop.
+    redirected: Mutex<Option<String>>,
+}
+
+impl Navigator {
+    pub fn new(blocker: SharedBlocker, host_rules: SharedHostRules, history: SharedHistory, port: u16) -> Self {
+        Navigator { blocker, host_rules, history, port, redirected: Mutex::new(None) }
+    }
+
+    /// The URL to load instead of `url`, or None to let the navigation through.
+    pub fn check(&self, tab: Option<usize>, url: &str) -> Option<String> {
+        let parsed = url::Url::parse(url).ok()?;
+        if !matches!(parsed.scheme(), "http" | "https") || self.is_internal(&parsed) {
+            return None;
+        }
+        let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+
+        let mut redirected = self.redirected.lock().unwrap();
+        if redirected.as_deref() == Some(url) {
+            *redirected = None;
+            self.history.lock().unwrap().record(tab, url, Outcome::Allowed);
+            return None;
+        }
+
+        let info = RequestInfo { url, resource_type: "document", page_host: &host };
+        let blocked = self.blocker.lock().unwrap().check(&info);
+        if let Some(rule) = blocked {
+            log_info!("Blocked navigation to {} by {}", url, rule);
+            let page = format!(
+                "http://127.0.0.1:{}/blocked?url={}&rule={}",
+                self.port,
+                urlencoding::encode(url),
+                urlencoding::encode(&rule)
+            );
+            self.history.lock().unwrap().record(tab, url, Outcome::Blocked { rule });
+            return Some(page);
+        }
+
+        let target = self.host_rules.lock().unwrap().redirect(url);
+        if let Some(target) = target {
+            log_info!("Redirecting navigation from {} to {}", url, target);
+            *redirected = Some(target.clone());
+            self.history.lock().unwrap().record(tab, url, Outcome::Redirected { to: target.clone() });
+            return Some(target);
+        }
+
+        self.history.lock().unwrap().record(tab, url, Outcome::Allowed);
+        None
+    }
+
+    /// Our own pages: the toolbar, viewers and the blocked page itself.
+    fn is_internal(&self, url: &url::Url) -> bool {
+        matches!(url.host_str(), Some("127.0.0.1") | Some("localhost")) && url.port() == Some(self.port)
+    }
+}
+
+pub const BLOCKED_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8">
+    <title>Blocked</title>
+    <style>
+        body { margin: 0; padding: 60px 40px; font: 14px/1.5 sans-serif; background: #f5f5f5; color: #333; }
+        h1 { font-size: 20px; margin: 0 0 16px; }
+        dt { color: #777; margin-top: 12px; }
+        dd { margin: 2px 0 0; font-family: Menlo, Consolas, monospace; word-break: break-all; }
+    </style>
+</head>
+<body>
+    <h1>This page was blocked</h1>
+    <p>The navigation matched a filter in the configured filter list.</p>
+    <dl>
+        <dt>URL</dt><dd id="url"></dd>
+        <dt>Filter</dt><dd id="rule"></dd>
+    </dl>
+    <script>
+        const params = new URLSearchParams(location.search);
+        document.getElementById('url').textContent = params.get('url') || '';
+        document.getElementById('rule').textContent = params.get('rule') || '';
+        document.title = 'Blocked: ' + (params.get('url') || '');
+    </script>
+</body>
+</html>"#;
+
+pub fn blocked_response() -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    tiny_http::Response::from_string(BLOCKED_HTML)
+        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::blocker::Blocker;
+    use crate::history::History;
+    use crate::host_rules::{HostRule, HostRules};
+
+    const PORT: u16 = 8765;
+
+    fn navigator(name: &str, filters: &str, rules: &[&str]) -> (Navigator, SharedHistory) {
+        let path = std::env::temp_dir().join(format!("rb-navigation-test-{}-{}.txt", name, std::process::id()));
+        std::fs::write(&path, filters).unwrap();
+        let blocker = Arc::new(Mutex::new(Blocker::from_file(path).unwrap()));
+        let rules = HostRules::new(rules.iter().map(|r| r.parse::<HostRule>().unwrap()).collect());
+        let history: SharedHistory = Arc::new(Mutex::new(History::default()));
+        (Navigator::new(blocker, Arc::new(Mutex::new(rules)), history.clone(), PORT), history)
+    }
+
+    fn outcomes(history: &SharedHistory, tab: Option<usize>) -> Vec<serde_json::Value> {
+        let entries = history.lock().unwrap().to_json(tab)["entries"].as_array().unwrap().clone();
+        entries
+            .into_iter()
+            .map(|mut e| {
+                e.as_object_mut().unwrap().remove("timestamp");
+                e
+            })
+            .collect()
+    }
+
+    #[test]
+    fn blocks_document_filters_only() {
+        let (navigator, history) = navigator("block", "||ads.example^$document\n||tracker.example^\n@@||ads.example/ok$document\n", &[]);
+
+        let page = navigator.check(Some(1), "https://ads.example/landing").unwrap();
+        assert_eq!(
+            page,
+            "http://127.0.0.1:8765/blocked?url=https%3A%2F%2Fads.example%2Flanding&rule=%7C%7Cads.example%5E%24document"
+        );
+        // Filters without $document don't apply to top-level navigations
+        assert_eq!(navigator.check(Some(1), "https://tracker.example/"), None);
+        assert_eq!(navigator.check(Some(1), "https://ads.example/ok"), None);
+
+        assert_eq!(
+            outcomes(&history, None),
+            [
+                serde_json::json!({"tab": 1, "url": "https://ads.example/landing", "outcome": "blocked", "rule": "||ads.example^$document"}),
+                serde_json::json!({"tab": 1, "url": "https://tracker.example/", "outcome": "allowed"}),
+                serde_json::json!({"tab": 1, "url": "https://ads.example/ok", "outcome": "allowed"}),
+            ]
+        );
+    }
+
+    #[test]
+    fn redirects_remapped_hosts_once() {
+        let (navigator, history) = navigator("redirect", "", &["staging.example=127.0.0.1:9000", "*.example.com=www.example.com"]);
+
+        assert_eq!(navigator.check(Some(2), "https://staging.example/app?q=1#top").as_deref(), Some("https://127.0.0.1:9000/app?q=1#top"));
+        assert_eq!(navigator.check(Some(2), "https://127.0.0.1:9000/app?q=1#top"), None);
+
+        // The target matches the wildcard itself, so only the guard stops a loop
+        assert_eq!(navigator.check(Some(3), "http://docs.example.com/").as_deref(), Some("http://www.example.com/"));
+        assert_eq!(navigator.check(Some(3), "http://www.example.com/"), None);
+        assert_eq!(navigator.check(Some(3), "http://www.example.com/").as_deref(), Some("http://www.example.com/"));
+
+        let tab_two = outcomes(&history, Some(2));
+        assert_eq!(
+            tab_two,
+            [
+                serde_json::json!({"tab": 2, "url": "https://staging.example/app?q=1#top", "outcome": "redirected", "to": "https://127.0.0.1:9000/app?q=1#top"}),
+                serde_json::json!({"tab": 2, "url": "https://127.0.0.1:9000/app?q=1#top", "outcome": "allowed"}),
+            ]
+        );
+        assert_eq!(outcomes(&history, Some(3)).len(), 3);
+    }
+
+    #[test]
+    fn blocking_wins_over_redirects() {
+        let (navigator, _) = navigator("order", "||staging.example^$document\n", &["staging.example=127.0.0.1:9000"]);
+        assert!(navigator.check(None, "https://staging.example/").unwrap().contains("/blocked?"));
+    }
+
+    #[test]
+    fn skips_internal_pages_and_other_schemes() {
+        let (navigator, history) = navigator("skip", "||localhost^$document\n||127.0.0.1^$document\n", &[]);
+
+        assert_eq!(navigator.check(Some(1), "http://127.0.0.1:8765/blocked?url=x"), None);
+        assert_eq!(navigator.check(Some(1), "http://localhost:8765/workspaces/view"), None);
+        for url in ["about:blank", "data:text/html,hi", "file:///etc/hosts", "not a url"] {
+            assert_eq!(navigator.check(Some(1), url), None, "{}", url);
+        }
+        assert!(outcomes(&history, None).is_empty());
+
+        // The same hosts on another port are ordinary pages
+        assert!(navigator.check(Some(1), "http://127.0.0.1:9000/").is_some());
+    }
+}
@@ -0,0 +1,430 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::network::{
+    EnableParams, EventDataReceived, EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent,
+    EventResponseReceived, Headers, ResourceTiming, ResourceType, Response,
+};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::buffers::{self, Buffer, BufferStats};
+use crate::url_match::glob_matches;
+
+// ============== Network Log ==============
+//
+// Every request of the streamed page, collected from the Network domain
+// (requestWillBeSent, responseReceived, loadingFinished/loadingFailed), so
+// "why is the page blank" can be answered without a devtools client. GET
+// /network lists them oldest first with method, URL, resource type, status,
+// MIME type, transfer size, start time, duration and whether they failed;
+// ?status=4xx (or 404, or failed), ?method=GET,POST, ?type=xhr,fetch
+// (resource types, case-insensitive), ?url= (a `*` glob over the whole URL),
+// ?url_contains= and ?since= (epoch millis) filter the list, DELETE /network
+// empties it. A redirect ends its entry with the 3xx status and starts a new
+// one. The log starts over on every main-frame navigation unless POST
+// /network/config set `{"persist": true}`. Only the newest
+// `--buffer-size network=N` (1000 by default) are kept. Entries also keep
+// what GET /har needs (headers, timing, sizes), which /network leaves out.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    pub request_id: String,
+    pub method: String,
+    pub url: String,
+    pub resource_type: Option<String>,
+    pub status: Option<i64>,
+    pub mime_type: Option<String>,
+    /// Bytes received over the wire, headers included.
+    pub transfer_size: Option<u64>,
+    pub started_at: u128,
+    pub duration_ms: Option<f64>,
+    pub failed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Monotonic start in seconds, for the duration.
+    #[serde(skip)]
+    start: f64,
+    /// Main-frame navigation the request belongs to, see `NetworkLog::page`.
+    #[serde(skip)]
+    pub navigation: u64,
+    #[serde(skip)]
+    pub request_headers: Vec<(String, String)>,
+    #[serde(skip)]
+    pub response_headers: Vec<(String, String)>,
+    #[serde(skip)]
+    pub status_text: String,
+    /// e.g. "http/1.1" or "h2".
+    #[serde(skip)]
+    pub protocol: Option<String>,
+    #[serde(skip)]
+    pub remote_ip: Option<String>,
+    #[serde(skip)]
+    pub timing: Option<ResourceTiming>,
+    /// Decoded body bytes, from Network.dataReceived.
+    #[serde(skip)]
+    pub body_size: u64,
+}
+
+impl Entry {
+    fn respond(&mut self, response: &Response) {
+        self.status = Some(response.status);
+        self.status_text = response.status_text.clone();
+        self.mime_type = Some(response.mime_type.clone());
+        self.response_headers = header_list(&response.headers);
+        self.protocol = response.protocol.clone();
+        self.remote_ip = response.remote_ip_address.clone();
+        self.timing = response.timing.clone();
+    }
+}
+
+#[cfg(test)]
+impl Entry {
+    /// A request that was sent and nothing more; tests fill in the rest.
+    pub(crate) fn sample(request_id: &str, method: &str, url: &str) -> Entry {
+        Entry {
+            request_id: request_id.to_string(),
+            method: method.to_string(),
+            url: url.to_string(),
+            resource_type: None,
+            status: None,
+            mime_type: None,
+            transfer_size: None,
+            started_at: 0,
+            duration_ms: None,
+            failed: false,
+            error: None,
+            start: 0.0,
+            navigation: 1,
+            request_headers: Vec::new(),
+            response_headers: Vec::new(),
+            status_text: String::new(),
+            protocol: None,
+            remote_ip: None,
+            timing: None,
+            body_size: 0,
+        }
+    }
+}
+
+/// The main-frame document load the latest entries belong to.
+#[derive(Debug, Clone)]
+pub struct PageLoad {
+    pub navigation: u64,
+    pub url: String,
+    pub started_at: u128,
+}
+
+/// CDP joins repeated headers with newlines; split them again.
+fn header_list(headers: &Headers) -> Vec<(String, String)> {
+    headers
+        .inner()
+        .as_object()
+        .into_iter()
+        .flatten()
+        .flat_map(|(name, value)| {
+            let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+            value.split('\n').map(|v| (name.clone(), v.to_string())).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfigRequest {
+    pub persist: bool,
+}
+
+/// `status` filter: an exact code, a class like `4xx`, or `failed`.
+enum StatusFilter {
+    Exact(i64),
+    Class(i64),
+    Failed,
+}
+
+pub struct Filter {
+    status: Option<StatusFilter>,
+    /// Uppercased.
+    methods: Vec<String>,
+    /// Lowercased.
+    resource_types: Vec<String>,
+    url: Option<String>,
+    url_contains: Option<String>,
+    since: Option<u128>,
+}
+
+/// A comma-separated query value, trimmed and without empty items.
+fn list(query: &HashMap<String, String>, key: &str) -> Vec<String> {
+    query.get(key).map_or_else(Vec::new, |v| v.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+}
+
+impl Filter {
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, String> {
+        let status = match query.get("status").map(|s| s.trim().to_ascii_lowercase()) {
+            None => None,
+            Some(s) if s == "failed" => Some(StatusFilter::Failed),
+            Some(s) => match s.strip_suffix("xx").map(str::parse::<i64>) {
+                Some(Ok(class)) if (1..=5).contains(&class) => Some(StatusFilter::Class(class)),
+                Some(_) => return Err(format!("invalid status class '{}', expected 1xx..5xx", s)),
+                None => Some(StatusFilter::Exact(s.parse().map_err(|_| format!("invalid status '{}'", s))?)),
+            },
+        };
+        let since = match query.get("since") {
+            Some(v) => Some(v.trim().parse().map_err(|_| format!("since must be epoch milliseconds, got '{}'", v))?),
+            None => None,
+        };
+        let methods = list(query, "method").iter().map(|m| m.to_ascii_uppercase()).collect();
+        let resource_types = list(query, "type").iter().map(|t| t.to_ascii_lowercase()).collect();
+        let url = query.get("url").filter(|s| !s.is_empty()).cloned();
+        let url_contains = query.get("url_contains").filter(|s| !s.is_empty()).cloned();
+        Ok(Filter { status, methods, resource_types, url, url_contains, since })
+    }
+
+    fn matches(&self, entry: &Entry) -> bool {
+        let status = match &self.status {
+            None => true,
+            Some(StatusFilter::Failed) => entry.failed,
+            Some(StatusFilter::Exact(code)) => entry.status == Some(*code),
+            Some(StatusFilter::Class(class)) => entry.status.is_some_and(|s| s / 100 == *class),
+        };
+        status
+            && (self.methods.is_empty() || self.methods.contains(&entry.method.to_ascii_uppercase()))
+            && (self.resource_types.is_empty()
+                || entry.resource_type.as_ref().is_some_and(|t| self.resource_types.contains(&t.to_ascii_lowercase())))
+            && self.url.as_ref().is_none_or(|pattern| glob_matches(pattern, &entry.url))
+            && self.url_contains.as_ref().is_none_or(|part| entry.url.contains(part.as_str()))
+            && self.since.is_none_or(|since| entry.started_at >= since)
+    }
+}
+
+#[derive(Default)]
+pub struct NetworkLog {
+    entries: VecDeque<Entry>,
+    dropped: u64,
+    /// Keep entries across main-frame navigations.
+    persist: bool,
+    page: Option<PageLoad>,
+}
+
+pub type SharedNetworkLog = Arc<Mutex<NetworkLog>>;
+
+impl NetworkLog {
+    pub fn set_persist(&mut self, persist: bool) {
+        self.persist = persist;
+    }
+
+    pub fn config_json(&self) -> serde_json::Value {
+        serde_json::json!({ "persist": self.persist })
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn to_json(&self, filter: &Filter) -> serde_json::Value {
+        let entries: Vec<&Entry> = self.entries.iter().filter(|e| filter.matches(e)).collect();
+        serde_json::json!({ "entries": entries, "dropped": self.dropped, "persist": self.persist })
+    }
+
+    pub fn buffer_stats(&self) -> BufferStats {
+        buffers::stats(Buffer::Network, &self.entries, self.dropped)
+    }
+
+    /// The latest main-frame load and its entries, oldest first.
+    pub fn last_page(&self) -> Option<(PageLoad, Vec<Entry>)> {
+        let page = self.page.clone()?;
+        let entries = self.entries.iter().filter(|e| e.navigation == page.navigation).cloned().collect();
+        Some((page, entries))
+    }
+
+    /// Latest entry of a request; redirects reuse the request id.
+    fn entry(&mut self, request_id: &str) -> Option<&mut Entry> {
+        self.entries.iter_mut().rev().find(|e| e.request_id == request_id)
+    }
+
+    fn sent(&mut self, event: &EventRequestWillBeSent, main_frame: Option<&str>) {
+        let request_id: &str = event.request_id.inner();
+        if let Some(redirect) = &event.redirect_response {
+            if let Some(entry) = self.entry(request_id) {
+                entry.respond(redirect);
+                entry.duration_ms = Some((event.timestamp.inner() - entry.start) * 1000.0);
+            }
+        } else if event.r#type == Some(ResourceType::Document)
+            && event.loader_id.inner() == request_id
+            && event.frame_id.as_ref().map(|f| f.inner().as_str()) == main_frame
+        {
+            if !self.persist {
+                self.entries.clear();
+            }
+            let navigation = self.page.as_ref().map_or(1, |p| p.navigation + 1);
+            let started_at = (event.wall_time.inner() * 1000.0) as u128;
+            self.page = Some(PageLoad { navigation, url: event.request.url.clone(), started_at });
+        }
+        while self.entries.len() >= Buffer::Network.capacity() {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(Entry {
+            request_id: request_id.to_string(),
+            method: event.request.method.clone(),
+            url: event.request.url.clone(),
+            resource_type: event.r#type.as_ref().map(|t| t.as_ref().to_string()),
+            status: None,
+            mime_type: None,
+            transfer_size: None,
+            started_at: (event.wall_time.inner() * 1000.0) as u128,
+            duration_ms: None,
+            failed: false,
+            error: None,
+            start: *event.timestamp.inner(),
+            navigation: self.page.as_ref().map_or(0, |p| p.navigation),
+            request_headers: header_list(&event.request.headers),
+            response_headers: Vec::new(),
+            status_text: String::new(),
+            protocol: None,
+            remote_ip: None,
+            timing: None,
+            body_size: 0,
+        });
+    }
+
+    fn response(&mut self, event: &EventResponseReceived) {
+        if let Some(entry) = self.entry(event.request_id.inner()) {
+            entry.respond(&event.response);
+        }
+    }
+
+    fn data(&mut self, event: &EventDataReceived) {
+        if let Some(entry) = self.entry(event.request_id.inner()) {
+            entry.body_size += event.data_length.max(0) as u64;
+        }
+    }
+
+    fn finished(&mut self, event: &EventLoadingFinished) {
+        if let Some(entry) = self.entry(event.request_id.inner()) {
+            entry.transfer_size = Some(event.encoded_data_length.max(0.0) as u64);
+            entry.duration_ms = Some((event.timestamp.inner() - entry.start) * 1000.0);
+        }
+    }
+
+    fn failed(&mut self, event: &EventLoadingFailed) {
+        if let Some(entry) = self.entry(event.request_id.inner()) {
+            entry.failed = true;
+            entry.error = Some(event.error_text.clone());
+            entry.duration_ms = Some((event.timestamp.inner() - entry.start) * 1000.0);
+        }
+    }
+}
+
+/// Feeds the Network events of `page` into `log`.
+pub async fn watch(page: &Page, log: SharedNetworkLog) -> Result<tokio::task::JoinHandle<()>, CdpError> {
+    let main_frame = page.mainframe().await?.map(|f| f.inner().clone());
+    let mut sent = page.event_listener::<EventRequestWillBeSent>().await?;
+    let mut responses = page.event_listener::<EventResponseReceived>().await?;
+    let mut finished = page.event_listener::<EventLoadingFinished>().await?;
+    let mut failed = page.event_listener::<EventLoadingFailed>().await?;
+    let mut data = page.event_listener::<EventDataReceived>().await?;
+    page.execute(EnableParams::default()).await?;
+    Ok(tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                Some(event) = sent.next() => log.lock().unwrap().sent(&event, main_frame.as_deref()),
+                Some(event) = responses.next() => log.lock().unwrap().response(&event),
+                Some(event) = finished.next() => log.lock().unwrap().finished(&event),
+                Some(event) = failed.next() => log.lock().unwrap().failed(&event),
+                Some(event) = data.next() => log.lock().unwrap().data(&event),
+                else => break,
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(pairs: &[(&str, &str)]) -> Result<Filter, String> {
+        Filter::from_query(&pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+    }
+
+    fn entry(method: &str, url: &str, resource_type: &str, status: Option<i64>) -> Entry {
+        let mut entry = Entry::sample(url, method, url);
+        entry.resource_type = Some(resource_type.to_string());
+        entry.status = status;
+        entry
+    }
+
+    /// URLs of `entries` that `pairs` lets through.
+    fn matching(pairs: &[(&str, &str)], entries: &[Entry]) -> Vec<String> {
+        let filter = filter(pairs).unwrap();
+        entries.iter().filter(|e| filter.matches(e)).map(|e| e.url.clone()).collect()
+    }
+
+    fn entries() -> Vec<Entry> {
+        let mut failed = entry("GET", "https://cdn.test/app.js", "Script", None);
+        failed.failed = true;
+        failed.started_at = 300;
+        let mut late = entry("POST", "https://site.test/api/login", "Fetch", Some(401));
+        late.started_at = 200;
+        vec![
+            entry("GET", "https://site.test/", "Document", Some(200)),
+            entry("get", "https://site.test/api/items?page=2", "XHR", Some(200)),
+            late,
+            entry("GET", "https://site.test/logo.png", "Image", Some(404)),
+            failed,
+        ]
+    }
+
+    #[test]
+    fn no_filter_keeps_everything() {
+        assert_eq!(matching(&[], &entries()).len(), 5);
+    }
+
+    #[test]
+    fn filters_by_status() {
+        let entries = entries();
+        assert_eq!(matching(&[("status", "4xx")], &entries), ["https://site.test/api/login", "https://site.test/logo.png"]);
+        assert_eq!(matching(&[("status", "404")], &entries), ["https://site.test/logo.png"]);
+        assert_eq!(matching(&[("status", "FAILED")], &entries), ["https://cdn.test/app.js"]);
+        assert!(filter(&[("status", "6xx")]).is_err());
+        assert!(filter(&[("status", "ok")]).is_err());
+    }
+
+    #[test]
+    fn filters_by_method() {
+        let entries = entries();
+        assert_eq!(matching(&[("method", "post")], &entries), ["https://site.test/api/login"]);
+        assert_eq!(matching(&[("method", "GET")], &entries).len(), 4);
+        assert_eq!(matching(&[("method", "PUT, post")], &entries), ["https://site.test/api/login"]);
+    }
+
+    #[test]
+    fn filters_by_resource_type() {
+        let entries = entries();
+        assert_eq!(matching(&[("type", "xhr,fetch")], &entries), ["https://site.test/api/items?page=2", "https://site.test/api/login"]);
+        assert_eq!(matching(&[("type", "document")], &entries), ["https://site.test/"]);
+        let mut unknown = entry("GET", "https://site.test/other", "Other", None);
+        unknown.resource_type = None;
+        assert!(matching(&[("type", "other")], &[unknown]).is_empty());
+    }
+
+    #[test]
+    fn filters_by_url_glob_and_substring() {
+        let entries = entries();
+        assert_eq!(matching(&[("url", "*/api/*")], &entries), ["https://site.test/api/items?page=2", "https://site.test/api/login"]);
+        assert_eq!(matching(&[("url", "https://cdn.test/*")], &entries), ["https://cdn.test/app.js"]);
+        // The glob covers the whole URL
+        assert!(matching(&[("url", "*.png?*")], &entries).is_empty());
+        assert_eq!(matching(&[("url_contains", "logo")], &entries), ["https://site.test/logo.png"]);
+    }
+
+    #[test]
+    fn filters_combine() {
+        let entries = entries();
+        assert_eq!(matching(&[("url", "*/api/*"), ("method", "GET")], &entries), ["https://site.test/api/items?page=2"]);
+        assert_eq!(matching(&[("since", "250"), ("type", "script")], &entries), ["https://cdn.test/app.js"]);
+        assert!(matching(&[("since", "250"), ("status", "2xx")], &entries).is_empty());
+        assert!(filter(&[("since", "yesterday")]).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+// ============== OCR ==============
+//
+// GET /ocr runs text recognition (Tesseract, behind the `ocr` feature) on
+// the current live frame, for dashboards that draw text into canvases or
+// images where DOM queries can't see it. `?x=&y=&w=&h=` restricts it to a
+// region and `?contains=TEXT` turns it into an assertion: `found` plus the
+// matching boxes. OCR takes hundreds of milliseconds, so results are cached
+// per frame sequence number and region. The live frame includes overlays
+// (ticker, HUD, annotations), whose text is recognised like any other.
+
+/// Cached (frame, region) results; the stream moves on quickly, so only the
+/// most recent few are worth keeping.
+const CACHE_ENTRIES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Region {
+    /// `x`, `y`, `w` and `h` from the query, all or none.
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Option<Self>, String> {
+        let keys = ["x", "y", "w", "h"];
+        if keys.iter().all(|k| !query.contains_key(*k)) {
+            return Ok(None);
+        }
+        let get = |key: &str| -> Result<u32, String> {
+            let value = query.get(key).ok_or_else(|| "x, y, w and h must be given together".to_string())?;
+            value.parse().map_err(|_| format!("invalid {} '{}'", key, value))
+        };
+        let region = Region { x: get("x")?, y: get("y")?, w: get("w")?, h: get("h")? };
+        if region.w == 0 || region.h == 0 {
+            return Err("w and h must be positive".to_string());
+        }
+        Ok(Some(region))
+    }
+}
+
+/// One recognised line, in frame coordinates.
+#[derive(Debug, Clone, Serialize)]
+pub struct TextBlock {
+    pub text: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Mean word confidence, 0-100.
+    pub confidence: f32,
+}
+
+/// Frame sequence number and region of a cached result.
+type CacheKey = (u64, Option<Region>);
+
+pub struct Ocr {
+    language: String,
+    cache: VecDeque<(CacheKey, Arc<Vec<TextBlock>>)>,
+}
+
+pub type SharedOcr = Arc<Mutex<Ocr>>;
+
+impl Ocr {
+    pub fn new(language: String) -> Self {
+        Ocr { language, cache: VecDeque::new() }
+    }
+}
+
+/// Recognises `frame` (or `region` of it), reusing the result for a frame
+/// sequence number and region seen before. The lock is not held while
+/// recognising, so concurrent requests for a new frame may both run.
+pub fn recognize_cached(ocr: &SharedOcr, seq: u64, frame: &[u8], region: Option<Region>) -> Result<(Arc<Vec<TextBlock>>, bool), String> {
+    let language = {
+        let ocr = ocr.lock().unwrap();
+        if let Some((_, blocks)) = ocr.cache.iter().find(|(key, _)| *key == (seq, region)) {
+            return Ok((blocks.clone(), true));
+        }
+        ocr.language.clone()
+    };
+    let blocks = Arc::new(recognize(frame, region, &language)?);
+    let mut ocr = ocr.lock().unwrap();
+    ocr.cache.push_back(((seq, region), blocks.clone()));
+    while ocr.cache.len() > CACHE_ENTRIES {
+        ocr.cache.pop_front();
+    }
+    Ok((blocks, false))
+}
+
+/// PNG of the region to recognise, plus the offset to map boxes back.
+#[cfg_attr(not(feature = "ocr"), allow(dead_code))]
+fn prepare(frame: &[u8], region: Option<Region>) -> Result<(Vec<u8>, u32, u32), String> {
+    let img = image::load_from_memory(frame).map_err(|e| format!("failed to decode frame: {}", e))?;
+    let (img, dx, dy) = match region {
+        Some(r) => {
+            if r.x >= img.width() || r.y >= img.height() {
+                return Err(format!("region starts outside the {}x{} frame", img.width(), img.height()));
+            }
+            (img.crop_imm(r.x, r.y, r.w.min(img.width() - r.x), r.h.min(img.height() - r.y)), r.x, r.y)
+        }
+        None => (img, 0, 0),
+    };
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageFormat::Png).map_err(|e| format!("failed to encode region: {}", e))?;
+    Ok((png.into_inner(), dx, dy))
+}
+
+/// Groups Tesseract's word-level TSV into lines.
+#[cfg_attr(not(feature = "ocr"), allow(dead_code))]
+fn parse_tsv(tsv: &str, dx: u32, dy: u32) -> Vec<TextBlock> {
+    struct Line {
+        words: Vec<String>,
+        left: u32,
+        top: u32,
+        right: u32,
+        bottom: u32,
+        confidence: f32,
+    }
+    let mut lines: Vec<((u32, u32, u32), Line)> = Vec::new();
+    for row in tsv.lines().skip(1) {
+        let cols: Vec<&str> = row.split('\t').collect();
+        // level 5 rows are words: level, page, block, par, line, word, left, top, width, height, conf, text
+        if cols.len() < 12 || cols[0] != "5" || cols[11].trim().is_empty() {
+            continue;
+        }
+        let num = |i: usize| cols[i].parse::<u32>().unwrap_or_default();
+        let key = (num(2), num(3), num(4));
+        let (left, top) = (num(6), num(7));
+        let (right, bottom) = (left + num(8), top + num(9));
+        let confidence = cols[10].parse::<f32>().unwrap_or_default().max(0.0);
+        match lines.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, line)) => {
+                line.words.push(cols[11].to_string());
+                line.left = line.left.min(left);
+                line.top = line.top.min(top);
+                line.right = line.right.max(right);
+                line.bottom = line.bottom.max(bottom);
+                line.confidence += confidence;
+            }
+            None => lines.push((key, Line { words: vec![cols[11].to_string()], left, top, right, bottom, confidence })),
+        }
+    }
+    lines
+        .into_iter()
+        .map(|(_, line)| TextBlock {
+            confidence: line.confidence / line.words.len() as f32,
+            text: line.words.join(" "),
+            x: line.left + dx,
+            y: line.top + dy,
+            width: line.right - line.left,
+            height: line.bottom - line.top,
+        })
+        .collect()
+}
+
+#[cfg(feature = "ocr")]
+fn recognize(frame: &[u8], region: Option<Region>, language: &str) -> Result<Vec<TextBlock>, String> {
+    let (png, dx, dy) = prepare(frame, region)?;
+    let tsv = tesseract::Tesseract::new(None, Some(language))
+        .map_err(|e| format!("failed to start tesseract: {}", e))?
+        .set_image_from_mem(&png)
+        .map_err(|e| format!("failed to load frame into tesseract: {}", e))?
+        .recognize()
+        .map_err(|e| format!("recognition failed: {}", e))?
+        .get_tsv_text(0)
+        .map_err(|e| format!("recognition failed: {}", e))?;
+    Ok(parse_tsv(&tsv, dx, dy))
+}
+
+#[cfg(not(feature = "ocr"))]
+fn recognize(_frame: &[u8], _region: Option<Region>, _language: &str) -> Result<Vec<TextBlock>, String> {
+    Err("/ocr needs a build with `--features ocr`".to_string())
+}
+
+/// Serves GET /ocr for the frame with sequence number `seq`.
+pub fn response(ocr: &SharedOcr, seq: u64, frame: &[u8], query: &HashMap<String, String>) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let region = match Region::from_query(query) {
+        Ok(region) => region,
+        Err(e) => return crate::json_response(400, &serde_json::json!({"error": e})),
+    };
+    if !cfg!(feature = "ocr") {
+        return crate::json_response(501, &serde_json::json!({"error": "/ocr needs a build with `--features ocr`"}));
+    }
+    let (blocks, cached) = match recognize_cached(ocr, seq, frame, region) {
+        Ok(result) => result,
+        Err(e) => return crate::json_response(422, &serde_json::json!({"error": e})),
+    };
+    let mut body = serde_json::json!({
+        "seq": seq,
+        "region": region,
+        "cached": cached,
+        "blocks": *blocks,
+    });
+    if let Some(needle) = query.get("contains") {
+        let needle = needle.to_lowercase();
+        let matches: Vec<&TextBlock> = blocks.iter().filter(|b| b.text.to_lowercase().contains(&needle)).collect();
+        body["found"] = (!matches.is_empty()).into();
+        body["matches"] = serde_json::json!(matches);
+    }
+    crate::json_response(200, &body)
+}
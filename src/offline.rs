@@ -0,0 +1,59 @@
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::network::EmulateNetworkConditionsParams;
+use chromiumoxide::Page;
+use serde::Deserialize;
+
+// ============== Offline Mode (headless) ==============
+//
+// POST /offline `{"offline": true}` cuts the streamed page off the network
+// with Network.emulateNetworkConditions, so the offline fallback of a PWA
+// can be captured; `{"offline": false}` restores normal conditions in place,
+// without reloading. The capture loop applies changes like /cpu-throttle.
+// Navigations made while offline fail: they are reported once as
+// `last_navigation_error` in /status instead of logged as errors. Only the
+// page target is taken offline, service workers keep their own network.
+
+#[derive(Debug, Deserialize)]
+pub struct OfflineRequest {
+    pub offline: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Offline {
+    offline: bool,
+    /// State last sent to Chrome.
+    applied: bool,
+}
+
+pub type SharedOffline = Arc<Mutex<Offline>>;
+
+impl Offline {
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    pub fn set(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "offline": self.offline })
+    }
+}
+
+/// Applies the requested state if it changed since the last call.
+pub async fn sync(page: &Page, offline: &SharedOffline) {
+    let wanted = {
+        let offline = offline.lock().unwrap();
+        if offline.offline == offline.applied {
+            return;
+        }
+        offline.offline
+    };
+    // Zero latency and -1 throughput mean no throttling
+    if let Err(e) = page.execute(EmulateNetworkConditionsParams::new(wanted, 0.0, -1.0, -1.0)).await {
+        log_error!("Failed to switch offline mode {}: {}", if wanted { "on" } else { "off" }, e);
+    }
+    offline.lock().unwrap().applied = wanted;
+}
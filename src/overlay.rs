@@ -0,0 +1,183 @@
+use std::io::Cursor;
+
+use image::{ImageFormat, RgbImage, Rgba};
+
+// ============== Overlay Drawing ==============
+//
+// Minimal raster primitives for compositing onto outgoing frames. Text uses a
+// built-in 5x7 bitmap font (printable ASCII) so no font files are needed.
+
+pub type Color = Rgba<u8>;
+
+/// Parses `#rgb`, `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(s: &str) -> Option<Color> {
+    let hex = s.trim().strip_prefix('#')?;
+    let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        3 => {
+            let nibble = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok().map(|v| v * 17);
+            Some(Rgba([nibble(0)?, nibble(1)?, nibble(2)?, 255]))
+        }
+        6 => Some(Rgba([byte(0)?, byte(2)?, byte(4)?, 255])),
+        8 => Some(Rgba([byte(0)?, byte(2)?, byte(4)?, byte(6)?])),
+        _ => None,
+    }
+}
+
+fn blend(img: &mut RgbImage, x: i64, y: i64, color: Color) {
+    if x < 0 || y < 0 || x >= img.width() as i64 || y >= img.height() as i64 {
+        return;
+    }
+    let alpha = color[3] as u32;
+    let px = img.get_pixel_mut(x as u32, y as u32);
+    for c in 0..3 {
+        px[c] = ((color[c] as u32 * alpha + px[c] as u32 * (255 - alpha)) / 255) as u8;
+    }
+}
+
+pub fn fill_rect(img: &mut RgbImage, x: i64, y: i64, w: i64, h: i64, color: Color) {
+    let x0 = x.max(0);
+    let y0 = y.max(0);
+    let x1 = (x + w).min(img.width() as i64);
+    let y1 = (y + h).min(img.height() as i64);
+    for py in y0..y1 {
+        for px in x0..x1 {
+            blend(img, px, py, color);
+        }
+    }
+}
+
+pub fn stroke_rect(img: &mut RgbImage, x: i64, y: i64, w: i64, h: i64, thickness: i64, color: Color) {
+    let t = thickness.max(1).min(w.max(1)).min(h.max(1));
+    fill_rect(img, x, y, w, t, color);
+    fill_rect(img, x, y + h - t, w, t, color);
+    fill_rect(img, x, y + t, t, h - 2 * t, color);
+    fill_rect(img, x + w - t, y + t, t, h - 2 * t, color);
+}
+
+pub fn draw_line(img: &mut RgbImage, x0: i64, y0: i64, x1: i64, y1: i64, thickness: i64, color: Color) {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+    let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+    let half = thickness.max(1) / 2;
+    loop {
+        fill_rect(img, x - half, y - half, thickness.max(1), thickness.max(1), color);
+        if x == x1 && y == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+pub const GLYPH_WIDTH: i64 = 6;
+pub const GLYPH_HEIGHT: i64 = 8;
+
+pub fn text_size(text: &str, scale: i64) -> (i64, i64) {
+    (text.chars().count() as i64 * GLYPH_WIDTH * scale, GLYPH_HEIGHT * scale)
+}
+
+pub fn draw_text(img: &mut RgbImage, x: i64, y: i64, text: &str, scale: i64, color: Color) {
+    let scale = scale.max(1);
+    for (i, ch) in text.chars().enumerate() {
+        let glyph = glyph(ch);
+        let gx = x + i as i64 * GLYPH_WIDTH * scale;
+        for (col, bits) in glyph.iter().enumerate() {
+            for row in 0..7 {
+                if bits & (1 << row) != 0 {
+                    fill_rect(img, gx + col as i64 * scale, y + row * scale, scale, scale, color);
+                }
+            }
+        }
+    }
+}
+
+/// Text on a padded background box; returns the box height.
+pub fn draw_label(img: &mut RgbImage, x: i64, y: i64, text: &str, scale: i64, fg: Color, bg: Color) -> i64 {
+    let pad = 2 * scale;
+    let (w, h) = text_size(text, scale);
+    fill_rect(img, x, y, w + 2 * pad, h + 2 * pad, bg);
+    draw_text(img, x + pad, y + pad, text, scale, fg);
+    h + 2 * pad
+}
+
+/// Decodes a JPEG frame, lets `draw` composite onto it and re-encodes it.
+pub fn recompose(jpeg: &[u8], quality: u8, draw: impl FnOnce(&mut RgbImage)) -> Option<Vec<u8>> {
+    let mut img = image::load_from_memory_with_format(jpeg, ImageFormat::Jpeg).ok()?.to_rgb8();
+    draw(&mut img);
+    encode_jpeg(&img, quality)
+}
+
+pub fn encode_jpeg(img: &RgbImage, quality: u8) -> Option<Vec<u8>> {
+    let mut out = Cursor::new(Vec::new());
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, quality)
+        .encode_image(img)
+        .ok()?;
+    Some(out.into_inner())
+}
+
+fn glyph(ch: char) -> [u8; 5] {
+    let code = ch as u32;
+    if (0x20..=0x7e).contains(&code) {
+        FONT_5X7[(code - 0x20) as usize]
+    } else {
+        FONT_5X7[('?' as u32 - 0x20) as usize]
+    }
+}
+
+/// Column-major 5x7 glyphs for ASCII 0x20..=0x7E, bit 0 is the top row.
+const FONT_5X7: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5F, 0x00, 0x00], [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], [0x24, 0x2A, 0x7F, 0x2A, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00], [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00], [0x14, 0x08, 0x3E, 0x08, 0x14], [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02], [0x3E, 0x51, 0x49, 0x45, 0x3E], [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4B, 0x31], [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39], [0x3C, 0x4A, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1E], [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00], [0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06], [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], [0x7F, 0x49, 0x49, 0x49, 0x36], [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], [0x7F, 0x49, 0x49, 0x49, 0x41], [0x7F, 0x09, 0x09, 0x09, 0x01],
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], [0x7F, 0x08, 0x08, 0x08, 0x7F], [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01], [0x7F, 0x08, 0x14, 0x22, 0x41], [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], [0x7F, 0x04, 0x08, 0x10, 0x7F], [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06], [0x3E, 0x41, 0x51, 0x21, 0x5E], [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31], [0x01, 0x01, 0x7F, 0x01, 0x01], [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], [0x3F, 0x40, 0x38, 0x40, 0x3F], [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07], [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7F, 0x00], [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40], [0x00, 0x01, 0x02, 0x04, 0x00], [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20], [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18], [0x08, 0x7E, 0x09, 0x01, 0x02], [0x0C, 0x52, 0x52, 0x52, 0x3E],
+    [0x7F, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7D, 0x40, 0x00], [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x7F, 0x10, 0x28, 0x44, 0x00], [0x00, 0x41, 0x7F, 0x40, 0x00], [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38], [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C], [0x7C, 0x08, 0x04, 0x04, 0x08], [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20], [0x3C, 0x40, 0x40, 0x20, 0x7C], [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], [0x44, 0x28, 0x10, 0x28, 0x44], [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44], [0x00, 0x08, 0x36, 0x41, 0x00], [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00], [0x10, 0x08, 0x08, 0x10, 0x08],
+];
+
+/// Reads the pixel size of an encoded frame without decoding it.
+pub fn frame_dimensions(encoded: &[u8]) -> Option<(u32, u32)> {
+    image::ImageReader::new(Cursor::new(encoded))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// clap value parser for color options.
+pub fn parse_color_arg(s: &str) -> Result<Color, String> {
+    parse_color(s).ok_or_else(|| format!("invalid color '{}', expected #rgb, #rrggbb or #rrggbbaa", s))
+}
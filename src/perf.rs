@@ -0,0 +1,79 @@
+use chromiumoxide::cdp::browser_protocol::performance::{EnableParams, GetMetricsParams};
+use chromiumoxide::Page;
+
+use crate::commands::{self, CommandResult};
+
+// ============== Performance Metrics ==============
+//
+// GET /perf samples the streamed page: Performance.getMetrics (listeners,
+// nodes, layout and style recalc counts, script and task time, JS heap) and
+// the navigation timing entry of performance.getEntriesByType('navigation')
+// for TTFB, DOMContentLoaded and load. Chrome reports durations and
+// timestamps in seconds; here every time is in milliseconds (timestamps
+// relative to the navigation start), sizes are in bytes and the rest are
+// counts, each metric with its `unit`. Events that haven't happened yet
+// are null.
+
+/// Monotonic timestamps in seconds; reported relative to NavigationStart.
+const TIMESTAMP_METRICS: [&str; 3] = ["Timestamp", "DomContentLoaded", "FirstMeaningfulPaint"];
+
+/// Offsets from the navigation start (already milliseconds) and sizes, or
+/// null when the page has no navigation entry (e.g. about:blank).
+const NAVIGATION_SCRIPT: &str = r#"(() => {
+    const n = performance.getEntriesByType('navigation')[0];
+    if (!n) return null;
+    const at = (v) => v > 0 ? Math.round(v * 10) / 10 : null;
+    const span = (start, end) => start > 0 && end >= start ? Math.round((end - start) * 10) / 10 : null;
+    return {
+        type: n.type,
+        redirect_count: n.redirectCount,
+        dns_ms: span(n.domainLookupStart, n.domainLookupEnd),
+        connect_ms: span(n.connectStart, n.connectEnd),
+        tls_ms: span(n.secureConnectionStart, n.connectEnd),
+        ttfb_ms: at(n.responseStart),
+        response_ms: span(n.responseStart, n.responseEnd),
+        dom_interactive_ms: at(n.domInteractive),
+        dom_content_loaded_ms: at(n.domContentLoadedEventEnd),
+        load_event_ms: at(n.loadEventEnd),
+        duration_ms: at(n.duration),
+        transfer_size_bytes: n.transferSize,
+        encoded_body_size_bytes: n.encodedBodySize,
+        decoded_body_size_bytes: n.decodedBodySize,
+    };
+})()"#;
+
+fn round(ms: f64) -> f64 {
+    (ms * 10.0).round() / 10.0
+}
+
+/// `{value, unit}` for one getMetrics entry; `navigation_start` in seconds.
+fn normalize(name: &str, value: f64, navigation_start: f64) -> serde_json::Value {
+    if TIMESTAMP_METRICS.contains(&name) {
+        let since_start = (value > 0.0 && navigation_start > 0.0).then(|| round((value - navigation_start) * 1000.0));
+        serde_json::json!({ "value": since_start, "unit": "ms" })
+    } else if name.ends_with("Duration") || name == "ThreadTime" || name == "ProcessTime" {
+        serde_json::json!({ "value": round(value * 1000.0), "unit": "ms" })
+    } else if name.ends_with("Size") {
+        serde_json::json!({ "value": value.max(0.0) as u64, "unit": "bytes" })
+    } else {
+        serde_json::json!({ "value": value.max(0.0) as u64, "unit": "count" })
+    }
+}
+
+pub async fn sample(page: &Page) -> CommandResult {
+    page.execute(EnableParams::default()).await?;
+    let metrics = page.execute(GetMetricsParams::default()).await?.result.metrics;
+    let navigation_start = metrics.iter().find(|m| m.name == "NavigationStart").map_or(0.0, |m| m.value);
+    let normalized: serde_json::Map<String, serde_json::Value> = metrics
+        .iter()
+        .filter(|m| m.name != "NavigationStart")
+        .map(|m| (m.name.clone(), normalize(&m.name, m.value, navigation_start)))
+        .collect();
+    let navigation = commands::evaluate(page, NAVIGATION_SCRIPT.to_string(), false).await?;
+    Ok(serde_json::json!({
+        "url": page.url().await?,
+        "captured_at": crate::now_millis(),
+        "metrics": normalized,
+        "navigation": navigation,
+    }))
+}
@@ -0,0 +1,119 @@
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::browser::Browser;
+use chromiumoxide::cdp::browser_protocol::browser::{
+    GrantPermissionsParams, PermissionDescriptor, PermissionSetting, PermissionType,
+    ResetPermissionsParams, SetPermissionParams,
+};
+use chromiumoxide::error::CdpError;
+use serde::Serialize;
+
+// ============== Permission Management ==============
+
+/// User-facing permission names accepted by the API and --grant-permissions.
+const PERMISSIONS: &[(&str, PermissionType)] = &[
+    ("camera", PermissionType::VideoCapture),
+    ("microphone", PermissionType::AudioCapture),
+    ("notifications", PermissionType::Notifications),
+    ("clipboard-read", PermissionType::ClipboardReadWrite),
+    ("clipboard-write", PermissionType::ClipboardSanitizedWrite),
+    ("geolocation", PermissionType::Geolocation),
+    ("midi", PermissionType::Midi),
+    ("sensors", PermissionType::Sensors),
+    ("display-capture", PermissionType::DisplayCapture),
+    ("idle-detection", PermissionType::IdleDetection),
+    ("background-sync", PermissionType::BackgroundSync),
+];
+
+pub fn validate(names: &[String]) -> Result<(), String> {
+    match names.iter().find(|n| lookup(n).is_none()) {
+        Some(unknown) => Err(format!(
+            "unknown permission '{}', expected one of: {}",
+            unknown,
+            PERMISSIONS.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+fn lookup(name: &str) -> Option<PermissionType> {
+    let name = if name == "clipboard" { "clipboard-read" } else { name };
+    PERMISSIONS.iter().find(|(n, _)| *n == name).map(|(_, t)| t.clone())
+}
+
+/// Grants `grant` and explicitly denies `deny` for `origin` (all origins when `None`).
+pub async fn apply(browser: &Browser, origin: Option<&str>, grant: &[String], deny: &[String]) -> Result<(), CdpError> {
+    if !grant.is_empty() {
+        let mut params = GrantPermissionsParams::builder().permissions(grant.iter().filter_map(|n| lookup(n)));
+        if let Some(origin) = origin {
+            params = params.origin(origin);
+        }
+        browser.execute(params.build().map_err(CdpError::msg)?).await?;
+    }
+
+    for name in deny {
+        // PermissionDescriptor uses the W3C names, which match ours apart from the clipboard alias.
+        let name = if name == "clipboard" { "clipboard-read" } else { name.as_str() };
+        let mut params = SetPermissionParams::builder()
+            .permission(PermissionDescriptor::new(name))
+            .setting(PermissionSetting::Denied);
+        if let Some(origin) = origin {
+            params = params.origin(origin);
+        }
+        browser.execute(params.build().map_err(CdpError::msg)?).await?;
+    }
+    Ok(())
+}
+
+pub async fn reset(browser: &Browser) -> Result<(), CdpError> {
+    browser.execute(ResetPermissionsParams::default()).await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Grant {
+    origin: Option<String>,
+    granted: Vec<String>,
+    denied: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct PermissionGrants {
+    grants: Vec<Grant>,
+}
+
+pub type SharedPermissions = Arc<Mutex<PermissionGrants>>;
+
+impl PermissionGrants {
+    /// Replaces any previous entry for the same origin, mirroring Chrome's
+    /// "grant these and reject all others" semantics.
+    pub fn record(&mut self, origin: Option<String>, granted: Vec<String>, denied: Vec<String>) {
+        self.grants.retain(|g| g.origin != origin);
+        self.grants.push(Grant { origin, granted, denied });
+    }
+
+    pub fn clear(&mut self) {
+        self.grants.clear();
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "permissions": self.grants })
+    }
+
+    /// The grants as the POST /permissions bodies that recreate them.
+    pub fn requests(&self) -> Vec<PermissionsRequest> {
+        self.grants
+            .iter()
+            .map(|g| PermissionsRequest { origin: g.origin.clone(), grant: g.granted.clone(), deny: g.denied.clone() })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
+pub struct PermissionsRequest {
+    pub origin: Option<String>,
+    #[serde(default)]
+    pub grant: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
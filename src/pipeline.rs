@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// ============== Pipeline Timing ==============
+
+/// Samples kept per stage; percentiles describe this recent window.
+const WINDOW: usize = 512;
+
+#[derive(Debug, Default)]
+pub struct Samples {
+    values: VecDeque<f64>,
+    count: u64,
+}
+
+impl Samples {
+    pub fn push(&mut self, value: f64) {
+        if self.values.len() == WINDOW {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+        self.count += 1;
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        let mut sorted: Vec<f64> = self.values.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let pct = |p: f64| -> f64 {
+            if sorted.is_empty() {
+                return 0.0;
+            }
+            sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+        };
+        let avg = if sorted.is_empty() { 0.0 } else { sorted.iter().sum::<f64>() / sorted.len() as f64 };
+        serde_json::json!({
+            "count": self.count,
+            "avg": round(avg),
+            "p50": round(pct(0.5)),
+            "p95": round(pct(0.95)),
+            "p99": round(pct(0.99)),
+            "max": round(sorted.last().copied().unwrap_or(0.0)),
+        })
+    }
+}
+
+fn round(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+/// Milliseconds as used by every stage sample.
+pub fn millis(elapsed: Duration) -> f64 {
+    elapsed.as_secs_f64() * 1000.0
+}
+
+/// Per-stage timings in milliseconds plus frame sizes and delivery rate.
+#[derive(Debug, Default)]
+pub struct PipelineStats {
+    stages: BTreeMap<&'static str, Samples>,
+    bytes: Samples,
+    frame_times: VecDeque<Instant>,
+}
+
+pub type SharedPipelineStats = Arc<Mutex<PipelineStats>>;
+
+impl PipelineStats {
+    pub fn record(&mut self, stage: &'static str, elapsed: Duration) {
+        self.stages.entry(stage).or_default().push(millis(elapsed));
+    }
+
+    pub fn stage_summary(&self, stage: &str) -> serde_json::Value {
+        self.stages.get(stage).map_or_else(|| Samples::default().summary(), Samples::summary)
+    }
+
+    /// Time since the newest frame was produced.
+    pub fn last_frame_age(&self) -> Option<Duration> {
+        self.frame_times.back().map(Instant::elapsed)
+    }
+
+    /// Marks a finished frame of `bytes` encoded size.
+    pub fn record_frame(&mut self, bytes: usize) {
+        self.bytes.push(bytes as f64);
+        if self.frame_times.len() == WINDOW {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(Instant::now());
+    }
+
+    pub fn fps(&self) -> f64 {
+        match (self.frame_times.front(), self.frame_times.back()) {
+            (Some(first), Some(last)) if self.frame_times.len() > 1 => {
+                let secs = last.duration_since(*first).as_secs_f64();
+                if secs > 0.0 { (self.frame_times.len() - 1) as f64 / secs } else { 0.0 }
+            }
+            _ => 0.0,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let stages: serde_json::Map<String, serde_json::Value> =
+            self.stages.iter().map(|(name, s)| (name.to_string(), s.summary())).collect();
+        serde_json::json!({
+            "stages_ms": stages,
+            "bytes_per_frame": self.bytes.summary(),
+            "fps": round(self.fps()),
+        })
+    }
+}
+
+/// Runs `f` and records its duration under `stage`.
+pub fn time<T>(stats: &SharedPipelineStats, stage: &'static str, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = f();
+    stats.lock().unwrap().record(stage, started.elapsed());
+    result
+}
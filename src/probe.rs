@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use image::RgbImage;
+use serde::Deserialize;
+
+use crate::commands::{self, CommandError, CommandSender, PageCommand};
+use crate::ocr::Region;
+
+// ============== Color Probes ==============
+//
+// "Is that status tile green?" without OCR or DOM assumptions: the probes
+// sample a region of the live frame and report its average color, its
+// dominant color and the nearest named color. Regions are either frame
+// pixels (x/y/w/h, like /ocr) or an element box resolved from a CSS
+// selector and scaled to the frame. POST /probe evaluates several named
+// probes against one frame so they are consistent with each other.
+
+/// Bits kept per channel when bucketing pixels for the dominant color.
+const BUCKET_BITS: u32 = 4;
+
+const NAMED_COLORS: &[(&str, [u8; 3])] = &[
+    ("black", [0, 0, 0]),
+    ("white", [255, 255, 255]),
+    ("gray", [128, 128, 128]),
+    ("silver", [192, 192, 192]),
+    ("red", [220, 40, 40]),
+    ("maroon", [128, 0, 0]),
+    ("orange", [255, 150, 0]),
+    ("yellow", [250, 220, 30]),
+    ("olive", [128, 128, 0]),
+    ("green", [40, 170, 60]),
+    ("darkgreen", [0, 100, 0]),
+    ("teal", [0, 128, 128]),
+    ("cyan", [0, 210, 230]),
+    ("blue", [40, 90, 220]),
+    ("navy", [0, 0, 128]),
+    ("purple", [128, 0, 128]),
+    ("magenta", [230, 0, 200]),
+    ("pink", [255, 170, 190]),
+    ("brown", [140, 80, 30]),
+];
+
+fn hex(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+fn nearest_name(rgb: [u8; 3]) -> &'static str {
+    let distance = |c: [u8; 3]| -> i32 { (0..3).map(|i| (rgb[i] as i32 - c[i] as i32).pow(2)).sum() };
+    NAMED_COLORS.iter().min_by_key(|(_, c)| distance(*c)).map_or("black", |(name, _)| name)
+}
+
+/// Average, dominant and nearest named color of `region`, clipped to the frame.
+fn sample(img: &RgbImage, region: Region) -> Result<serde_json::Value, String> {
+    if region.x >= img.width() || region.y >= img.height() {
+        return Err(format!("region starts outside the {}x{} frame", img.width(), img.height()));
+    }
+    let (right, bottom) = ((region.x + region.w).min(img.width()), (region.y + region.h).min(img.height()));
+    let mut sum = [0u64; 3];
+    let mut buckets: HashMap<u32, ([u64; 3], u64)> = HashMap::new();
+    for y in region.y..bottom {
+        for x in region.x..right {
+            let p = img.get_pixel(x, y).0;
+            let key = p.iter().fold(0, |key, c| (key << BUCKET_BITS) | (*c as u32 >> (8 - BUCKET_BITS)));
+            let bucket = buckets.entry(key).or_default();
+            for i in 0..3 {
+                sum[i] += p[i] as u64;
+                bucket.0[i] += p[i] as u64;
+            }
+            bucket.1 += 1;
+        }
+    }
+    let pixels = ((right - region.x) * (bottom - region.y)) as u64;
+    let mean = |s: [u64; 3], n: u64| [(s[0] / n) as u8, (s[1] / n) as u8, (s[2] / n) as u8];
+    let average = mean(sum, pixels);
+    let (dominant_sum, dominant_count) = buckets.into_values().max_by_key(|(_, n)| *n).unwrap_or_default();
+    let dominant = mean(dominant_sum, dominant_count.max(1));
+    Ok(serde_json::json!({
+        "region": Region { x: region.x, y: region.y, w: right - region.x, h: bottom - region.y },
+        "average": hex(average),
+        "dominant": hex(dominant),
+        "dominant_share": dominant_count as f64 / pixels as f64,
+        "name": nearest_name(dominant),
+        "average_name": nearest_name(average),
+    }))
+}
+
+const BOXES_SCRIPT: &str = r#"
+function(find, selectors) {
+    return selectors.map(function(selector) {
+        let el;
+        try {
+            el = find(selector, false);
+        } catch (e) {
+            return { error: e.name === 'SyntaxError' ? 'invalid selector' : e.message };
+        }
+        if (!el) return { error: 'no element matches' };
+        const r = el.getBoundingClientRect();
+        return { x: r.left, y: r.top, width: r.width, height: r.height };
+    });
+}
+"#;
+
+#[derive(Debug, Deserialize)]
+pub struct ProbeSpec {
+    pub name: String,
+    pub selector: Option<String>,
+    pub x: Option<u32>,
+    pub y: Option<u32>,
+    pub w: Option<u32>,
+    pub h: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProbeRequest {
+    pub probes: Vec<ProbeSpec>,
+}
+
+enum Target {
+    Region(Region),
+    /// Index into the resolved selector boxes.
+    Selector(usize),
+}
+
+/// Evaluates `specs` against one frame; per-probe failures are reported in
+/// place so one missing element doesn't fail the batch.
+pub fn run(
+    command_tx: &CommandSender,
+    frame: impl FnOnce() -> Option<Vec<u8>>,
+    viewport: (u32, u32),
+    specs: &[ProbeSpec],
+) -> Result<serde_json::Map<String, serde_json::Value>, CommandError> {
+    let mut selectors = Vec::new();
+    let mut targets = Vec::new();
+    for spec in specs {
+        let target = match (&spec.selector, spec.x, spec.y, spec.w, spec.h) {
+            (Some(selector), None, None, None, None) => {
+                selectors.push(serde_json::Value::from(selector.as_str()));
+                Target::Selector(selectors.len() - 1)
+            }
+            (None, Some(x), Some(y), Some(w), Some(h)) if w > 0 && h > 0 => Target::Region(Region { x, y, w, h }),
+            _ => return Err(CommandError::new(400, format!("probe '{}' needs either a selector or a positive x/y/w/h region", spec.name))),
+        };
+        targets.push(target);
+    }
+
+    // Boxes first, then the frame, so the elements are at most one frame older
+    let boxes = if selectors.is_empty() {
+        serde_json::Value::Array(Vec::new())
+    } else {
+        let expression = commands::js_call_with_find(BOXES_SCRIPT, &[serde_json::Value::Array(selectors)]);
+        commands::send(command_tx, PageCommand::Evaluate { expression, await_promise: false })?
+    };
+    let frame = frame().ok_or_else(|| CommandError::new(503, "no frame available"))?;
+    let img = image::load_from_memory(&frame)
+        .map_err(|e| CommandError::new(500, format!("failed to decode frame: {}", e)))?
+        .to_rgb8();
+    let scale = img.width() as f64 / viewport.0.max(1) as f64;
+
+    let mut results = serde_json::Map::new();
+    for (spec, target) in specs.iter().zip(targets) {
+        let region = match target {
+            Target::Region(region) => Ok(region),
+            Target::Selector(i) => {
+                let rect = &boxes[i];
+                match rect["error"].as_str() {
+                    Some(e) => Err(format!("{}: {}", e, spec.selector.as_deref().unwrap_or_default())),
+                    None => {
+                        let at = |key: &str| rect[key].as_f64().unwrap_or_default() * scale;
+                        let (x, y) = (at("x").max(0.0), at("y").max(0.0));
+                        let (w, h) = (at("width").round() as u32, at("height").round() as u32);
+                        if w == 0 || h == 0 {
+                            Err(format!("element has no visible box: {}", spec.selector.as_deref().unwrap_or_default()))
+                        } else {
+                            Ok(Region { x: x.round() as u32, y: y.round() as u32, w, h })
+                        }
+                    }
+                }
+            }
+        };
+        let result = region.and_then(|region| sample(&img, region));
+        let value = result.unwrap_or_else(|e| serde_json::json!({"error": e}));
+        results.insert(spec.name.clone(), value);
+    }
+    Ok(results)
+}
+
+/// GET /probe/color and /probe/selector-color: a single probe from the query.
+pub fn single(
+    command_tx: &CommandSender,
+    frame: impl FnOnce() -> Option<Vec<u8>>,
+    viewport: (u32, u32),
+    by_selector: bool,
+    query: &HashMap<String, String>,
+) -> commands::CommandResult {
+    let spec = if by_selector {
+        let selector = query.get("selector").cloned().ok_or_else(|| CommandError::new(400, "missing selector"))?;
+        ProbeSpec { name: "probe".to_string(), selector: Some(selector), x: None, y: None, w: None, h: None }
+    } else {
+        let region = Region::from_query(query)
+            .map_err(|e| CommandError::new(400, e))?
+            .ok_or_else(|| CommandError::new(400, "missing x, y, w and h"))?;
+        ProbeSpec { name: "probe".to_string(), selector: None, x: Some(region.x), y: Some(region.y), w: Some(region.w), h: Some(region.h) }
+    };
+    let mut results = run(command_tx, frame, viewport, std::slice::from_ref(&spec))?;
+    let result = results.remove("probe").unwrap_or_default();
+    match result["error"].as_str() {
+        Some(e) => Err(CommandError::new(422, e)),
+        None => Ok(result),
+    }
+}
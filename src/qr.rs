@@ -0,0 +1,232 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::ocr::Region;
+
+// ============== QR Detection ==============
+//
+// GET /decode-qr scans the current frame (or a region of it) for QR codes and
+// returns each payload with its corner points, for flows whose next step is
+// a code shown on screen. `?watch=true` keeps a detector running on the
+// stream: every payload not seen before is pushed to GET /decode-qr/events
+// (server-sent events) and POSTed to the optional `webhook`. `?watch=false`
+// stops it and forgets the seen payloads.
+
+/// How often the standing detector looks at the newest frame.
+const SCAN_INTERVAL: Duration = Duration::from_millis(500);
+/// Payloads remembered for de-duplication.
+const SEEN_CAPACITY: usize = 256;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Code {
+    pub payload: String,
+    /// Always "qr"; rqrr does not read 1D barcodes.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub version: usize,
+    /// Corners in frame coordinates, clockwise from top-left of the code.
+    pub corners: [[i32; 2]; 4],
+}
+
+pub fn detect(frame: &[u8], region: Option<Region>) -> Result<Vec<Code>, String> {
+    let img = image::load_from_memory(frame).map_err(|e| format!("failed to decode frame: {}", e))?.to_luma8();
+    let (dx, dy, width, height) = match region {
+        Some(r) => {
+            if r.x >= img.width() || r.y >= img.height() {
+                return Err(format!("region starts outside the {}x{} frame", img.width(), img.height()));
+            }
+            (r.x, r.y, r.w.min(img.width() - r.x), r.h.min(img.height() - r.y))
+        }
+        None => (0, 0, img.width(), img.height()),
+    };
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(width as usize, height as usize, |x, y| {
+        img.get_pixel(dx + x as u32, dy + y as u32).0[0]
+    });
+    let codes = prepared
+        .detect_grids()
+        .into_iter()
+        .filter_map(|grid| {
+            let (meta, payload) = grid.decode().ok()?;
+            let corners = grid.bounds.map(|p| [p.x + dx as i32, p.y + dy as i32]);
+            Some(Code { payload, kind: "qr", version: meta.version.0, corners })
+        })
+        .collect();
+    Ok(codes)
+}
+
+#[derive(Default)]
+pub struct Watch {
+    enabled: bool,
+    webhook: Option<String>,
+    seen: VecDeque<String>,
+    subscribers: Vec<Sender<String>>,
+    events: u64,
+}
+
+pub type SharedWatch = Arc<Mutex<Watch>>;
+
+impl Watch {
+    fn start(&mut self, webhook: Option<String>) {
+        if !self.enabled {
+            log_info!("QR watch started");
+        }
+        self.enabled = true;
+        if webhook.is_some() {
+            self.webhook = webhook;
+        }
+    }
+
+    fn stop(&mut self) {
+        self.enabled = false;
+        self.webhook = None;
+        self.seen.clear();
+    }
+
+    /// Codes among `codes` whose payload has not been reported yet.
+    fn fresh(&mut self, codes: Vec<Code>) -> Vec<Code> {
+        let mut fresh = Vec::new();
+        for code in codes {
+            if self.seen.contains(&code.payload) {
+                continue;
+            }
+            self.seen.push_back(code.payload.clone());
+            if self.seen.len() > SEEN_CAPACITY {
+                self.seen.pop_front();
+            }
+            fresh.push(code);
+        }
+        fresh
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "watching": self.enabled,
+            "webhook": self.webhook,
+            "seen": self.seen.len(),
+            "subscribers": self.subscribers.len(),
+            "events": self.events,
+        })
+    }
+}
+
+/// Runs the standing detector against the newest frame from `frame`, a
+/// source of (sequence number, JPEG).
+pub fn spawn_watcher<F>(watch: SharedWatch, frame: F)
+where
+    F: Fn() -> Option<(u64, Vec<u8>)> + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut last_seq = None;
+        loop {
+            thread::sleep(SCAN_INTERVAL);
+            if !watch.lock().unwrap().enabled {
+                continue;
+            }
+            let Some((seq, jpeg)) = frame() else { continue };
+            if last_seq == Some(seq) {
+                continue;
+            }
+            last_seq = Some(seq);
+            let codes = match detect(&jpeg, None) {
+                Ok(codes) => codes,
+                Err(e) => {
+                    log_error!("QR watch scan failed: {}", e);
+                    continue;
+                }
+            };
+            let (fresh, webhook) = {
+                let mut watch = watch.lock().unwrap();
+                let fresh = watch.fresh(codes);
+                (fresh, watch.webhook.clone())
+            };
+            for code in fresh {
+                log_info!("QR code detected: {}", code.payload);
+                let event = serde_json::json!({
+                    "event": "qr_detected",
+                    "seq": seq,
+                    "code": code,
+                    "timestamp": crate::now_millis(),
+                });
+                {
+                    let mut watch = watch.lock().unwrap();
+                    watch.events += 1;
+                    let data = format!("event: qr\ndata: {}\n\n", event);
+                    watch.subscribers.retain(|tx| tx.send(data.clone()).is_ok());
+                }
+                if let Some(url) = &webhook {
+                    if let Err(e) = ureq::post(url).timeout(WEBHOOK_TIMEOUT).send_json(event) {
+                        log_error!("QR webhook to {} failed: {}", url, e);
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Blocking body of an SSE response, fed by the watcher.
+struct EventReader {
+    rx: Receiver<String>,
+    pending: std::io::Cursor<Vec<u8>>,
+}
+
+impl Read for EventReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pending.position() as usize >= self.pending.get_ref().len() {
+            match self.rx.recv() {
+                Ok(event) => self.pending = std::io::Cursor::new(event.into_bytes()),
+                // Watcher gone: end of stream
+                Err(_) => return Ok(0),
+            }
+        }
+        self.pending.read(buf)
+    }
+}
+
+/// GET /decode-qr/events: a text/event-stream of newly seen codes. Blocks
+/// until the client goes away, so call it from its own thread.
+pub fn serve_events(request: tiny_http::Request, watch: &SharedWatch) {
+    let (tx, rx) = mpsc::channel();
+    // An initial comment flushes the headers to the client right away
+    let _ = tx.send(": watching for QR codes\n\n".to_string());
+    watch.lock().unwrap().subscribers.push(tx);
+    let headers = vec![
+        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/event-stream"[..]).unwrap(),
+        tiny_http::Header::from_bytes(&b"Cache-Control"[..], &b"no-cache"[..]).unwrap(),
+    ];
+    let reader = EventReader { rx, pending: std::io::Cursor::new(Vec::new()) };
+    let _ = crate::request_log::respond(request, tiny_http::Response::new(tiny_http::StatusCode(200), headers, reader, None, None));
+}
+
+/// Serves GET /decode-qr for the frame with sequence number `seq`.
+pub fn response(watch: &SharedWatch, seq: u64, frame: &[u8], query: &HashMap<String, String>) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let region = match Region::from_query(query) {
+        Ok(region) => region,
+        Err(e) => return crate::json_response(400, &serde_json::json!({"error": e})),
+    };
+    let codes = match detect(frame, region) {
+        Ok(codes) => codes,
+        Err(e) => return crate::json_response(422, &serde_json::json!({"error": e})),
+    };
+    let mut watch = watch.lock().unwrap();
+    match query.get("watch").map(String::as_str) {
+        Some("true") | Some("1") => watch.start(query.get("webhook").cloned()),
+        Some("false") | Some("0") => {
+            watch.stop();
+            log_info!("QR watch stopped");
+        }
+        _ => {}
+    }
+    crate::json_response(200, &serde_json::json!({
+        "seq": seq,
+        "region": region,
+        "codes": codes,
+        "watch": watch.to_json(),
+    }))
+}
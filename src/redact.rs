@@ -0,0 +1,219 @@
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chromiumoxide::Page;
+use image::{ImageFormat, RgbImage, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::overlay;
+
+// ============== Selector Redaction ==============
+//
+// Elements matching the redaction selectors are blacked out in every frame
+// that leaves the process: live frames (and so sinks and saved captures),
+// /render and one-shot screenshots. Boxes are resolved in the page as
+// viewport-relative client rects, clipped to the viewport, and scaled onto
+// the frame by frame width / CSS viewport width, which covers DPR and the
+// thumbnail scaler. If they can't be resolved, the whole frame is blacked
+// out rather than risk leaking.
+
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+/// Extra CSS pixels around each box, against anti-aliased edges.
+const PADDING: f64 = 2.0;
+
+const RESOLVE_SCRIPT: &str = r#"
+function(selectors) {
+    const w = window.innerWidth, h = window.innerHeight;
+    const boxes = [], invalid = [];
+    for (const selector of selectors) {
+        let els;
+        try { els = document.querySelectorAll(selector); } catch (e) { invalid.push(selector); continue; }
+        for (const el of els) {
+            for (const r of el.getClientRects()) {
+                const x1 = Math.max(0, r.left), y1 = Math.max(0, r.top);
+                const x2 = Math.min(w, r.right), y2 = Math.min(h, r.bottom);
+                if (x2 > x1 && y2 > y1) boxes.push([x1, y1, x2 - x1, y2 - y1]);
+            }
+        }
+    }
+    return { width: w, boxes: boxes, invalid: invalid };
+}
+"#;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedactRequest {
+    pub selectors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Resolved {
+    width: f64,
+    boxes: Vec<[f64; 4]>,
+    invalid: Vec<String>,
+}
+
+pub struct Redaction {
+    selectors: Vec<String>,
+    file: Option<PathBuf>,
+    refresh: Duration,
+    /// `None` while rules are active means resolution failed: black out everything.
+    resolved: Option<Resolved>,
+    resolved_at: Option<Instant>,
+    resolved_navigation: u64,
+}
+
+pub type SharedRedaction = Arc<Mutex<Redaction>>;
+
+/// Blacks out the resolved boxes, or the whole image if resolution failed.
+fn paint(resolved: Option<&Resolved>, img: &mut RgbImage) {
+    let Some(resolved) = resolved else {
+        overlay::fill_rect(img, 0, 0, img.width() as i64, img.height() as i64, BLACK);
+        return;
+    };
+    let scale = img.width() as f64 / resolved.width.max(1.0);
+    for [x, y, w, h] in &resolved.boxes {
+        let x0 = ((x - PADDING) * scale).floor() as i64;
+        let y0 = ((y - PADDING) * scale).floor() as i64;
+        let x1 = ((x + w + PADDING) * scale).ceil() as i64;
+        let y1 = ((y + h + PADDING) * scale).ceil() as i64;
+        overlay::fill_rect(img, x0, y0, x1 - x0, y1 - y0, BLACK);
+    }
+}
+
+async fn resolve(page: &Page, selectors: &[String]) -> Result<Resolved, String> {
+    let expression = crate::commands::js_call(RESOLVE_SCRIPT, &[serde_json::json!(selectors)]);
+    crate::commands::evaluate(page, expression, false)
+        .await
+        .map_err(|e| e.message)
+        .and_then(|v| serde_json::from_value::<Resolved>(v).map_err(|e| e.to_string()))
+}
+
+impl Redaction {
+    /// Loads persisted rules from `file` if it exists.
+    pub fn load(file: Option<PathBuf>, refresh: Duration) -> Result<Self, String> {
+        let selectors = match &file {
+            Some(path) if path.exists() => {
+                let body = std::fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+                serde_json::from_slice::<RedactRequest>(&body)
+                    .map_err(|e| format!("invalid redaction file {}: {}", path.display(), e))?
+                    .selectors
+            }
+            _ => Vec::new(),
+        };
+        if !selectors.is_empty() {
+            log_info!("Redacting {} selector(s)", selectors.len());
+        }
+        Ok(Redaction { selectors, file, refresh, resolved: None, resolved_at: None, resolved_navigation: 0 })
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.selectors.is_empty()
+    }
+
+    pub fn selectors(&self) -> &[String] {
+        &self.selectors
+    }
+
+    /// Replaces the rules and persists them; boxes are re-resolved before the next frame.
+    pub fn set(&mut self, selectors: Vec<String>) -> std::io::Result<()> {
+        self.selectors = selectors.into_iter().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+        self.resolved = None;
+        self.resolved_at = None;
+        if let Some(path) = &self.file {
+            let body = serde_json::to_vec_pretty(&RedactRequest { selectors: self.selectors.clone() }).unwrap_or_default();
+            std::fs::write(path, body)?;
+        }
+        Ok(())
+    }
+
+    fn is_due(&self, navigation: u64) -> bool {
+        match self.resolved_at {
+            None => true,
+            Some(at) => navigation != self.resolved_navigation || at.elapsed() >= self.refresh,
+        }
+    }
+
+    pub fn render(&self, img: &mut RgbImage) {
+        if self.is_active() {
+            paint(self.resolved.as_ref(), img);
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "selectors": self.selectors,
+            "file": self.file,
+            "refresh_ms": self.refresh.as_millis() as u64,
+            "boxes": self.resolved.as_ref().map(|r| &r.boxes),
+            "invalid_selectors": self.resolved.as_ref().map(|r| &r.invalid),
+            "viewport_width": self.resolved.as_ref().map(|r| r.width),
+        })
+    }
+}
+
+/// Re-resolves boxes if rules are active and they are stale (new navigation
+/// or refresh interval elapsed), or always with `force`.
+pub async fn refresh(page: &Page, shared: &SharedRedaction, navigation: u64, force: bool) {
+    let selectors = {
+        let redaction = shared.lock().unwrap();
+        if !redaction.is_active() || !(force || redaction.is_due(navigation)) {
+            return;
+        }
+        redaction.selectors.clone()
+    };
+    let resolved = resolve(page, &selectors).await;
+
+    let mut redaction = shared.lock().unwrap();
+    redaction.resolved = match resolved {
+        Ok(resolved) => Some(resolved),
+        Err(e) => {
+            log_error!("Failed to resolve redaction boxes, blanking frames: {}", e);
+            None
+        }
+    };
+    redaction.resolved_at = Some(Instant::now());
+    redaction.resolved_navigation = navigation;
+}
+
+/// Applies redaction to an encoded JPEG or PNG frame, keeping its format.
+/// `None` if the frame couldn't be decoded: callers must not send it.
+pub fn apply(shared: &SharedRedaction, frame: Vec<u8>) -> Option<Vec<u8>> {
+    let redaction = shared.lock().unwrap();
+    if !redaction.is_active() {
+        return Some(frame);
+    }
+    redraw(frame, |img| redaction.render(img))
+}
+
+/// Like `refresh` + `apply`, but with boxes resolved on `page` only, leaving
+/// the live page's cached boxes alone. For pages other than the streamed one.
+pub async fn apply_to(page: &Page, shared: &SharedRedaction, frame: Vec<u8>) -> Option<Vec<u8>> {
+    let selectors = {
+        let redaction = shared.lock().unwrap();
+        if !redaction.is_active() {
+            return Some(frame);
+        }
+        redaction.selectors.clone()
+    };
+    let resolved = resolve(page, &selectors)
+        .await
+        .map_err(|e| log_error!("Failed to resolve redaction boxes, blanking frame: {}", e))
+        .ok();
+    redraw(frame, |img| paint(resolved.as_ref(), img))
+}
+
+fn redraw(frame: Vec<u8>, draw: impl FnOnce(&mut RgbImage)) -> Option<Vec<u8>> {
+    let format = image::guess_format(&frame).ok()?;
+    let mut img = image::load_from_memory_with_format(&frame, format).ok()?.to_rgb8();
+    draw(&mut img);
+    match format {
+        ImageFormat::Png => {
+            let mut out = Cursor::new(Vec::new());
+            img.write_to(&mut out, ImageFormat::Png).ok()?;
+            Some(out.into_inner())
+        }
+        _ => overlay::encode_jpeg(&img, crate::JPEG_QUALITY),
+    }
+}
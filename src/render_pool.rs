@@ -0,0 +1,225 @@
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chromiumoxide::browser::Browser;
+use chromiumoxide::cdp::browser_protocol::emulation::SetDeviceMetricsOverrideParams;
+use chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat;
+use chromiumoxide::cdp::browser_protocol::target::{CreateBrowserContextParams, CreateTargetParams, DisposeBrowserContextParams};
+use chromiumoxide::Page;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::commands::{CommandError, CommandResult};
+use crate::locale::LocaleProfile;
+use crate::settle::SettleOptions;
+use crate::HeadlessState;
+
+// ============== Render Pool ==============
+//
+// GET /render never touches the streamed page: each request gets a throwaway
+// page in its own browser context (no shared cookies, storage or viewport),
+// which is closed and disposed afterwards whatever the outcome. A semaphore
+// caps concurrent renders; once it is exhausted /render answers 503 with
+// Retry-After instead of queueing behind slow pages.
+
+/// Slack on top of the render timeout for cleanup before the caller gives up.
+const REPLY_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct RenderJob {
+    pub url: String,
+    pub viewport: (u32, u32),
+    pub settle: Option<SettleOptions>,
+    pub deterministic: bool,
+    pub locale: Option<LocaleProfile>,
+    pub timeout: Duration,
+}
+
+pub struct PendingRender {
+    job: RenderJob,
+    /// Held until the render is cleaned up.
+    permit: OwnedSemaphorePermit,
+    reply: mpsc::Sender<CommandResult>,
+}
+
+pub type RenderReceiver = tokio::sync::mpsc::UnboundedReceiver<PendingRender>;
+
+#[derive(Clone)]
+pub struct RenderPool {
+    tx: tokio::sync::mpsc::UnboundedSender<PendingRender>,
+    permits: Arc<Semaphore>,
+    concurrency: usize,
+    pub timeout: Duration,
+}
+
+impl RenderPool {
+    pub fn new(concurrency: usize, timeout: Duration) -> (Self, RenderReceiver) {
+        let concurrency = concurrency.max(1);
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let pool = RenderPool { tx, permits: Arc::new(Semaphore::new(concurrency)), concurrency, timeout };
+        (pool, rx)
+    }
+
+    /// Queues a render if a slot is free; 503 when the pool is saturated.
+    /// The returned closure blocks until the render finishes or times out.
+    pub fn submit(&self, job: RenderJob) -> Result<impl FnOnce() -> CommandResult, CommandError> {
+        let permit = self
+            .permits
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| CommandError::new(503, format!("all {} render slots are busy", self.concurrency)))?;
+        let wait = job.timeout + REPLY_GRACE;
+        let (reply, rx) = mpsc::channel();
+        self.tx
+            .send(PendingRender { job, permit, reply })
+            .map_err(|_| CommandError::new(503, "render pool is not running"))?;
+        Ok(move || {
+            rx.recv_timeout(wait)
+                .unwrap_or_else(|_| Err(CommandError::new(504, "render did not finish in time")))
+        })
+    }
+
+    /// Seconds a saturated caller should wait before retrying.
+    pub fn retry_after(&self) -> u64 {
+        self.timeout.as_secs().clamp(1, 30)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "concurrency": self.concurrency,
+            "in_flight": self.concurrency - self.permits.available_permits(),
+            "timeout_ms": self.timeout.as_millis() as u64,
+        })
+    }
+}
+
+/// Runs queued renders concurrently against `browser` until the pool is dropped.
+pub fn spawn(mut rx: RenderReceiver, browser: Arc<Browser>, state: HeadlessState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(pending) = rx.recv().await {
+            let browser = browser.clone();
+            let state = state.clone();
+            tokio::spawn(async move {
+                let result = render(&browser, &state, &pending.job).await;
+                if let Err(e) = &result {
+                    log_error!("Render of {} failed: {}", pending.job.url, e.message);
+                }
+                let _ = pending.reply.send(result);
+                drop(pending.permit);
+            });
+        }
+    })
+}
+
+async fn render(browser: &Browser, state: &HeadlessState, job: &RenderJob) -> CommandResult {
+    let context = browser.execute(CreateBrowserContextParams::default()).await?.result.browser_context_id;
+    let target = CreateTargetParams::builder()
+        .url("about:blank")
+        .browser_context_id(context.clone())
+        .build()
+        .map_err(|e| CommandError::new(500, e))?;
+    let result = match browser.new_page(target).await {
+        Ok(page) => {
+            let result = tokio::time::timeout(job.timeout, capture(&page, state, job))
+                .await
+                .unwrap_or_else(|_| Err(CommandError::new(504, format!("render timed out after {} ms", job.timeout.as_millis()))));
+            let _ = page.close().await;
+            result
+        }
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = browser.execute(DisposeBrowserContextParams::new(context)).await {
+        log_error!("Failed to dispose render context: {}", e);
+    }
+    result
+}
+
+async fn capture(page: &Page, state: &HeadlessState, job: &RenderJob) -> CommandResult {
+    let (width, height) = job.viewport;
+    page.execute(SetDeviceMetricsOverrideParams::new(width as i64, height as i64, 1.0, false)).await?;
+    if job.deterministic {
+        crate::deterministic::install(page, &state.deterministic).await?;
+    }
+    if state.fonts.is_active() {
+        state.fonts.install(page).await?;
+    }
+    let (user_agent, ua_override, timezone) = {
+        let locale = state.locale.lock().unwrap();
+        (locale.effective_user_agent(), locale.user_agent_override().is_some(), locale.timezone_override().map(str::to_string))
+    };
+    // A fresh tab starts on the browser's own user agent, so an override alone needs apply too
+    if job.locale.is_some() || ua_override || timezone.is_some() {
+        crate::locale::apply(page, &user_agent, job.locale.as_ref(), timezone.as_deref()).await?;
+    }
+    page.goto(&job.url).await?;
+    let params = crate::screenshot_params(CaptureScreenshotFormat::Jpeg);
+    let (frame, report) = crate::settle::capture(page, params, job.settle).await?;
+    let frame = crate::redact::apply_to(page, &state.redaction, frame)
+        .await
+        .ok_or_else(|| CommandError::new(500, "failed to apply redaction to the frame"))?;
+    Ok(serde_json::json!({
+        "frame": BASE64.encode(frame),
+        "url": page.url().await?,
+        "settle": report,
+        "viewport": [width, height],
+        "locale": job.locale.as_ref().map(ToString::to_string),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(timeout: Duration) -> RenderJob {
+        RenderJob { url: "http://127.0.0.1/b".to_string(), viewport: (800, 600), settle: None, deterministic: false, locale: None, timeout }
+    }
+
+    #[test]
+    fn saturated_pool_answers_503() {
+        let (pool, mut rx) = RenderPool::new(1, Duration::from_secs(20));
+        let _wait = pool.submit(job(pool.timeout)).ok().unwrap();
+        assert_eq!(pool.to_json()["in_flight"], 1);
+
+        let error = pool.submit(job(pool.timeout)).err().unwrap();
+        assert_eq!((error.status, error.message.as_str()), (503, "all 1 render slots are busy"));
+
+        // The slot frees once the queued render is done with
+        drop(rx.try_recv().unwrap());
+        assert_eq!(pool.to_json()["in_flight"], 0);
+        assert!(pool.submit(job(pool.timeout)).is_ok());
+    }
+
+    #[test]
+    fn answers_the_render_result() {
+        let (pool, mut rx) = RenderPool::new(0, Duration::from_millis(10));
+        assert_eq!(pool.to_json()["concurrency"], 1);
+
+        let wait = pool.submit(job(Duration::from_millis(10))).ok().unwrap();
+        let pending = rx.try_recv().unwrap();
+        pending.reply.send(Ok(serde_json::json!({"url": pending.job.url}))).unwrap();
+        assert_eq!(wait().unwrap()["url"], "http://127.0.0.1/b");
+        drop(pending.permit);
+
+        // A render that fails answers its error
+        let wait = pool.submit(job(Duration::from_millis(10))).ok().unwrap();
+        let pending = rx.try_recv().unwrap();
+        pending.reply.send(Err(CommandError::new(504, "render timed out after 10 ms"))).unwrap();
+        assert_eq!(wait().unwrap_err().status, 504);
+    }
+
+    #[test]
+    fn a_stopped_pool_refuses_work() {
+        let (pool, rx) = RenderPool::new(2, Duration::from_secs(20));
+        drop(rx);
+        let error = pool.submit(job(pool.timeout)).err().unwrap();
+        assert_eq!((error.status, error.message.as_str()), (503, "render pool is not running"));
+        assert_eq!(pool.to_json()["in_flight"], 0);
+    }
+
+    #[test]
+    fn retry_after_follows_the_timeout() {
+        assert_eq!(RenderPool::new(1, Duration::from_millis(200)).0.retry_after(), 1);
+        assert_eq!(RenderPool::new(1, Duration::from_secs(20)).0.retry_after(), 20);
+        assert_eq!(RenderPool::new(1, Duration::from_secs(300)).0.retry_after(), 30);
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chromiumoxide::browser::Browser;
+use chromiumoxide::cdp::browser_protocol::emulation::SetDeviceMetricsOverrideParams;
+use chromiumoxide::cdp::browser_protocol::network::{EnableParams, EventResponseReceived, ResourceType};
+use chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat;
+use chromiumoxide::cdp::browser_protocol::target::{CreateBrowserContextParams, CreateTargetParams, DisposeBrowserContextParams};
+use chromiumoxide::cdp::js_protocol::runtime::{ConsoleApiCalledType, EventConsoleApiCalled, EventExceptionThrown};
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::baseline::{BaselineOptions, Delta, PageDelta};
+use crate::commands::{self, CommandError};
+use crate::HeadlessState;
+
+// ============== Screenshot Report ==============
+//
+// --report DIR renders a list of URLs the way /render does (one throwaway
+// page and browser context each, settled capture, fonts, locale and
+// redaction applied) and writes a static report: index.html with a grid of
+// thumbnails linking to the full captures, and index.json with the same
+// per-page data for machines. With --report-depth the URLs are also
+// crawled breadth-first through same-origin links. A page that fails to
+// load or capture becomes an error card; the report is always written.
+// Each page's visible text is saved next to its capture for --baseline.
+
+const THUMB_WIDTH: u32 = 320;
+
+const LINKS_SCRIPT: &str = r#"
+function() {
+    return Array.from(document.links, a => a.href);
+}
+"#;
+
+const TEXT_SCRIPT: &str = r#"
+function() {
+    return document.body ? document.body.innerText : '';
+}
+"#;
+
+pub struct ReportSpec {
+    pub dir: PathBuf,
+    pub urls: Vec<String>,
+    pub depth: u32,
+    pub max_pages: usize,
+    pub concurrency: usize,
+    pub timeout: Duration,
+    pub deterministic: bool,
+    pub baseline: Option<BaselineOptions>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PageReport {
+    pub url: String,
+    pub depth: u32,
+    pub final_url: Option<String>,
+    pub title: Option<String>,
+    /// HTTP status of the main document.
+    pub status: Option<i64>,
+    pub load_ms: Option<u128>,
+    pub console_errors: usize,
+    pub settled: Option<bool>,
+    /// Paths relative to the report directory.
+    pub screenshot: Option<String>,
+    pub thumbnail: Option<String>,
+    pub text: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Reads --report-urls: one URL per line, `#` starts a comment.
+pub fn read_urls(path: &Path) -> Result<Vec<String>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Renders every page and writes index.html and index.json (and delta.json
+/// against a baseline); returns the pages in crawl order and the delta.
+pub async fn run(browser: &Browser, state: &HeadlessState, spec: &ReportSpec) -> Result<(Vec<PageReport>, Option<Delta>), String> {
+    let pages_dir = spec.dir.join("pages");
+    std::fs::create_dir_all(&pages_dir).map_err(|e| format!("Failed to create {}: {}", pages_dir.display(), e))?;
+
+    let origins: HashSet<String> = spec
+        .urls
+        .iter()
+        .filter_map(|u| url::Url::parse(u).ok())
+        .map(|u| u.origin().ascii_serialization())
+        .collect();
+    let mut seen: HashSet<String> = spec.urls.iter().cloned().collect();
+    let mut level: Vec<String> = spec.urls.clone();
+    let mut reports = Vec::new();
+
+    for depth in 0..=spec.depth {
+        level.truncate(spec.max_pages.saturating_sub(reports.len()));
+        if level.is_empty() {
+            break;
+        }
+        let first = reports.len();
+        let results: Vec<(PageReport, Vec<String>)> = futures::stream::iter(level.iter().enumerate())
+            .map(|(i, url)| render_page(browser, state, spec, first + i, url, depth))
+            .buffered(spec.concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut next = Vec::new();
+        for (report, links) in results {
+            log_info!("Report page {}: {} ({})", reports.len() + 1, report.url, report.error.as_deref().unwrap_or("ok"));
+            reports.push(report);
+            for link in links {
+                let Ok(mut parsed) = url::Url::parse(&link) else { continue };
+                parsed.set_fragment(None);
+                if !matches!(parsed.scheme(), "http" | "https") || !origins.contains(&parsed.origin().ascii_serialization()) {
+                    continue;
+                }
+                if seen.insert(parsed.to_string()) {
+                    next.push(parsed.to_string());
+                }
+            }
+        }
+        level = next;
+    }
+
+    let settings = serde_json::json!({
+        "deterministic": spec.deterministic,
+        "ignore_selectors": state.redaction.lock().unwrap().selectors(),
+        "viewport": [state.viewport.0, state.viewport.1],
+        "locale": state.locale.lock().unwrap().default.as_ref().map(ToString::to_string),
+    });
+    let delta = match &spec.baseline {
+        Some(baseline) => {
+            let delta = crate::baseline::compare(baseline, &spec.dir, &reports, &settings)?;
+            let json = serde_json::to_string_pretty(&delta).unwrap_or_default();
+            std::fs::write(spec.dir.join("delta.json"), json).map_err(|e| format!("Failed to write delta.json: {}", e))?;
+            Some(delta)
+        }
+        None => None,
+    };
+    write_index(&spec.dir, &reports, &settings, delta.as_ref())?;
+    Ok((reports, delta))
+}
+
+async fn render_page(browser: &Browser, state: &HeadlessState, spec: &ReportSpec, index: usize, url: &str, depth: u32) -> (PageReport, Vec<String>) {
+    let mut report = PageReport { url: url.to_string(), depth, ..Default::default() };
+    let crawl = depth < spec.depth;
+    let result = match browser.execute(CreateBrowserContextParams::default()).await {
+        Ok(context) => {
+            let context = context.result.browser_context_id;
+            let result = match isolated_page(browser, &context).await {
+                Ok(page) => {
+                    let result = tokio::time::timeout(spec.timeout, capture(&page, state, spec, index, crawl, &mut report))
+                        .await
+                        .unwrap_or_else(|_| Err(CommandError::new(504, format!("timed out after {} ms", spec.timeout.as_millis()))));
+                    let _ = page.close().await;
+                    result
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = browser.execute(DisposeBrowserContextParams::new(context)).await {
+                log_error!("Failed to dispose report context: {}", e);
+            }
+            result
+        }
+        Err(e) => Err(e.into()),
+    };
+    match result {
+        Ok(links) => (report, links),
+        Err(e) => {
+            report.error = Some(e.message);
+            (report, Vec::new())
+        }
+    }
+}
+
+async fn isolated_page(browser: &Browser, context: &chromiumoxide::cdp::browser_protocol::browser::BrowserContextId) -> Result<Page, CommandError> {
+    let target = CreateTargetParams::builder()
+        .url("about:blank")
+        .browser_context_id(context.clone())
+        .build()
+        .map_err(|e| CommandError::new(500, e))?;
+    Ok(browser.new_page(target).await?)
+}
+
+/// Loads and captures one page into `report`; returns its links when the
+/// crawl goes deeper.
+async fn capture(page: &Page, state: &HeadlessState, spec: &ReportSpec, index: usize, crawl: bool, report: &mut PageReport) -> Result<Vec<String>, CommandError> {
+    let (width, height) = state.viewport;
+    page.execute(SetDeviceMetricsOverrideParams::new(width as i64, height as i64, 1.0, false)).await?;
+    if spec.deterministic {
+        crate::deterministic::install(page, &state.deterministic).await?;
+    }
+    if state.fonts.is_active() {
+        state.fonts.install(page).await?;
+    }
+    let (profile, user_agent, ua_override, timezone) = {
+        let locale = state.locale.lock().unwrap();
+        (
+            locale.default.clone(),
+            locale.effective_user_agent(),
+            locale.user_agent_override().is_some(),
+            locale.timezone_override().map(str::to_string),
+        )
+    };
+    if profile.is_some() || ua_override || timezone.is_some() {
+        crate::locale::apply(page, &user_agent, profile.as_ref(), timezone.as_deref()).await?;
+    }
+
+    let errors = Arc::new(AtomicUsize::new(0));
+    let status = Arc::new(Mutex::new(None));
+    let watcher = {
+        let mut exceptions = page.event_listener::<EventExceptionThrown>().await?;
+        let mut console = page.event_listener::<EventConsoleApiCalled>().await?;
+        let mut responses = page.event_listener::<EventResponseReceived>().await?;
+        page.execute(EnableParams::default()).await?;
+        let main_frame = page.mainframe().await?;
+        let (errors, status) = (errors.clone(), status.clone());
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Some(_) = exceptions.next() => {
+                        errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Some(event) = console.next() => {
+                        if event.r#type == ConsoleApiCalledType::Error {
+                            errors.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    Some(event) = responses.next() => {
+                        // The last main-frame document response is the one after redirects
+                        if event.r#type == ResourceType::Document && (main_frame.is_none() || event.frame_id == main_frame) {
+                            *status.lock().unwrap() = Some(event.response.status);
+                        }
+                    }
+                    else => break,
+                }
+            }
+        })
+    };
+
+    let started = Instant::now();
+    let loaded = page.goto(&report.url).await;
+    report.load_ms = Some(started.elapsed().as_millis());
+    report.status = *status.lock().unwrap();
+    if let Err(e) = loaded {
+        watcher.abort();
+        return Err(e.into());
+    }
+
+    let params = crate::screenshot_params(CaptureScreenshotFormat::Jpeg);
+    let (frame, settle) = crate::settle::capture(page, params, Some(state.settle)).await?;
+    let frame = crate::redact::apply_to(page, &state.redaction, frame)
+        .await
+        .ok_or_else(|| CommandError::new(500, "failed to apply redaction to the frame"))?;
+    report.settled = settle.map(|s| s.settled);
+    report.final_url = Some(page.url().await?.unwrap_or_default());
+    report.title = commands::evaluate(page, "document.title".to_string(), false).await?.as_str().map(str::to_string);
+    let text = commands::evaluate(page, commands::js_call(TEXT_SCRIPT, &[]), false).await?;
+    let links = if crawl {
+        let links = commands::evaluate(page, commands::js_call(LINKS_SCRIPT, &[]), false).await?;
+        serde_json::from_value(links).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    watcher.abort();
+    report.status = *status.lock().unwrap();
+    report.console_errors = errors.load(Ordering::Relaxed);
+
+    let name = format!("pages/{:04}", index + 1);
+    let thumbnail = image::load_from_memory(&frame)
+        .ok()
+        .map(|img| img.thumbnail(THUMB_WIDTH, u32::MAX).to_rgb8())
+        .and_then(|img| crate::overlay::encode_jpeg(&img, crate::JPEG_QUALITY))
+        .ok_or_else(|| CommandError::new(500, "failed to make the thumbnail"))?;
+    let write = |suffix: &str, data: &[u8]| {
+        let relative = format!("{}{}.jpg", name, suffix);
+        std::fs::write(spec.dir.join(&relative), data).map_err(|e| CommandError::new(500, format!("failed to write {}: {}", relative, e)))?;
+        Ok::<_, CommandError>(relative)
+    };
+    report.screenshot = Some(write("", &frame)?);
+    report.thumbnail = Some(write("-thumb", &thumbnail)?);
+    let relative = format!("{}.txt", name);
+    std::fs::write(spec.dir.join(&relative), text.as_str().unwrap_or_default())
+        .map_err(|e| CommandError::new(500, format!("failed to write {}: {}", relative, e)))?;
+    report.text = Some(relative);
+    Ok(links)
+}
+
+fn write_index(dir: &Path, reports: &[PageReport], settings: &serde_json::Value, delta: Option<&Delta>) -> Result<(), String> {
+    let failed = reports.iter().filter(|r| r.error.is_some()).count();
+    let index = serde_json::json!({
+        "generated_at": crate::now_millis(),
+        "total": reports.len(),
+        "failed": failed,
+        "console_errors": reports.iter().map(|r| r.console_errors).sum::<usize>(),
+        "settings": settings,
+        "verdict": delta.map(|d| d.verdict),
+        "pages": reports,
+    });
+    let write = |name: &str, data: String| {
+        std::fs::write(dir.join(name), data).map_err(|e| format!("Failed to write {}: {}", dir.join(name).display(), e))
+    };
+    write("index.json", serde_json::to_string_pretty(&index).unwrap_or_default())?;
+
+    let cards: String = reports.iter().map(|r| card(r, delta.and_then(|d| d.page(&r.url)))).collect();
+    let html = REPORT_HTML
+        .replace("{{SUMMARY}}", &format!("{} pages, {} failed", reports.len(), failed))
+        .replace("{{DELTA}}", &delta.map(delta_summary).unwrap_or_default())
+        .replace("{{CARDS}}", &cards);
+    write("index.html", html)
+}
+
+fn delta_summary(delta: &Delta) -> String {
+    let removed: String = delta
+        .pages
+        .iter()
+        .filter(|p| p.change == crate::baseline::PageChange::Removed)
+        .map(|p| format!("<li>{}</li>", escape(&p.url)))
+        .collect();
+    let warnings: String = delta.warnings.iter().map(|w| format!("<li>{}</li>", escape(w))).collect();
+    format!(
+        r#"<div class="delta {}"><b>{}</b> against {} &middot; {} changed, {} unchanged, {} new, {} removed, {} failed (pixel threshold {}%) &middot; <a href="delta.json">delta.json</a>{}{}</div>"#,
+        delta.verdict,
+        delta.verdict.to_uppercase(),
+        escape(&delta.baseline.display().to_string()),
+        delta.changed,
+        delta.unchanged,
+        delta.new,
+        delta.removed,
+        delta.failed,
+        delta.max_diff_percent,
+        if removed.is_empty() { String::new() } else { format!("<p>Removed:</p><ul>{}</ul>", removed) },
+        if warnings.is_empty() { String::new() } else { format!("<p>Warnings:</p><ul>{}</ul>", warnings) },
+    )
+}
+
+/// The baseline row of a card's list, and its text diff.
+fn delta_details(delta: &PageDelta) -> (String, String) {
+    let mut row = format!(r#"<dt>Baseline</dt><dd class="change-{0}">{0}"#, delta.change.name());
+    if let Some(percent) = delta.pixel_diff_percent {
+        row.push_str(&format!(" &middot; {:.2}% pixels", percent));
+    }
+    if let Some(image) = &delta.diff_image {
+        row.push_str(&format!(r#" &middot; <a href="{}">diff</a>"#, escape(image)));
+    }
+    row.push_str("</dd>");
+    let text = if delta.text_changed {
+        let lines: String = delta.text_diff.iter().map(|l| escape(l) + "\n").collect();
+        format!("<details><summary>Text diff</summary><pre>{}</pre></details>", lines)
+    } else {
+        String::new()
+    };
+    (row, text)
+}
+
+fn card(report: &PageReport, delta: Option<&PageDelta>) -> String {
+    let title = escape(report.title.as_deref().filter(|t| !t.is_empty()).unwrap_or(&report.url));
+    let url = escape(&report.url);
+    let status = report.status.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string());
+    let load = report.load_ms.map(|ms| format!("{} ms", ms)).unwrap_or_else(|| "-".to_string());
+    let bad = report.error.is_some() || report.status.is_some_and(|s| s >= 400);
+    let (delta_row, text_diff) = delta.map(delta_details).unwrap_or_default();
+    let preview = match (&report.screenshot, &report.thumbnail, &report.error) {
+        (Some(full), Some(thumb), _) => format!(r#"<a href="{}"><img src="{}" alt="" loading="lazy"></a>"#, escape(full), escape(thumb)),
+        (_, _, Some(error)) => format!(r#"<div class="error">{}</div>"#, escape(error)),
+        _ => String::new(),
+    };
+    format!(
+        r#"<div class="card{}">{}<h2 title="{}">{}</h2><a class="url" href="{}">{}</a><dl><dt>Status</dt><dd>{}</dd><dt>Load</dt><dd>{}</dd><dt>Console errors</dt><dd>{}</dd><dt>Settled</dt><dd>{}</dd>{}</dl>{}</div>"#,
+        if bad { " bad" } else { "" },
+        preview,
+        title,
+        title,
+        url,
+        url,
+        status,
+        load,
+        report.console_errors,
+        report.settled.map(|s| if s { "yes" } else { "no" }).unwrap_or("-"),
+        delta_row,
+        text_diff,
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const REPORT_HTML: &str = r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><title>Screenshot report</title><style>
+body { margin: 0; padding: 24px; font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; background: #f4f5f7; color: #1d2330; }
+h1 { margin: 0 0 4px; font-size: 22px; }
+.summary { color: #667; margin-bottom: 20px; }
+.grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(320px, 1fr)); gap: 16px; }
+.card { background: #fff; border-radius: 8px; box-shadow: 0 1px 3px rgba(0,0,0,0.12); overflow: hidden; border-top: 4px solid #2a9d8f; }
+.card.bad { border-top-color: #e63946; }
+.card img { display: block; width: 100%; }
+.card h2 { font-size: 15px; margin: 10px 12px 2px; white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
+.card .url { display: block; margin: 0 12px; font-size: 12px; color: #457b9d; word-break: break-all; }
+.card dl { display: grid; grid-template-columns: auto 1fr; gap: 2px 10px; margin: 10px 12px 12px; font-size: 13px; }
+.card dt { color: #667; }
+.card dd { margin: 0; }
+.delta { padding: 12px 16px; margin-bottom: 20px; border-radius: 8px; background: #e9f5f3; }
+.delta.changed { background: #fdecee; }
+.delta p { margin: 8px 0 2px; }
+.change-changed, .change-new, .change-failed { color: #a4161a; font-weight: bold; }
+details { margin: 0 12px 12px; font-size: 13px; }
+details pre { font-size: 11px; max-height: 240px; overflow: auto; background: #f4f5f7; padding: 6px; }
+.error { padding: 24px 12px; background: #fdecee; color: #a4161a; font-family: monospace; font-size: 12px; white-space: pre-wrap; }
+</style></head><body>
+<h1>Screenshot report</h1>
+<div class="summary">{{SUMMARY}} &middot; <a href="index.json">index.json</a></div>
+{{DELTA}}
+<div class="grid">{{CARDS}}</div>
+</body></html>
+"#;
@@ -0,0 +1,285 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::buffers::{self, Buffer, BufferStats};
+
+// ============== Request Tracing ==============
+//
+// Every HTTP request gets an id: the incoming X-Request-Id when it is sane,
+// a generated one otherwise. It goes back in the X-Request-Id response
+// header and is prefixed to every log line written while the request is
+// handled. Connections are accepted on their own thread, so `queue` is the
+// time a request waited behind the one being served; `handler` runs up to
+// the response and `write` covers sending it. The last
+// `--buffer-size requests=N` requests (200 by default) are kept for GET /requests/recent, and requests slower than
+// --slow-request-ms are logged with that breakdown. Streamed responses
+// (no known length) are recorded but never count as slow.
+
+const MAX_ID_LEN: usize = 128;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestRecord {
+    pub id: String,
+    pub method: String,
+    /// Without the query, which may carry tokens.
+    pub path: String,
+    pub status: u16,
+    pub duration_ms: f64,
+    pub queue_ms: f64,
+    pub handler_ms: f64,
+    pub write_ms: f64,
+    pub streamed: bool,
+    pub slow: bool,
+    pub timestamp: u128,
+}
+
+static RECENT: Mutex<VecDeque<RequestRecord>> = Mutex::new(VecDeque::new());
+static SLOW_MS: AtomicU64 = AtomicU64::new(1000);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// A request from arrival to its response; moves with the request when a
+/// handler answers from another thread.
+pub struct Trace {
+    id: String,
+    method: String,
+    path: String,
+    received: Instant,
+    started: Instant,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Trace>> = const { RefCell::new(None) };
+}
+
+pub fn set_slow_threshold(ms: u64) {
+    SLOW_MS.store(ms, Ordering::Relaxed);
+}
+
+/// Id of the request being handled on this thread, for log lines.
+pub fn current_id() -> Option<String> {
+    CURRENT
+        .try_with(|current| current.try_borrow().ok().and_then(|t| t.as_ref().map(|t| t.id.clone())))
+        .ok()
+        .flatten()
+}
+
+fn request_id(request: &tiny_http::Request) -> String {
+    let incoming = request.headers().iter().find(|h| h.field.equiv("X-Request-Id")).and_then(|h| honored(h.value.as_str()));
+    match incoming {
+        Some(id) => id.to_string(),
+        None => format!("{:x}-{:x}", crate::now_millis(), NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+    }
+}
+
+/// An incoming X-Request-Id worth keeping: short, printable ASCII.
+fn honored(value: &str) -> Option<&str> {
+    Some(value.trim()).filter(|id| !id.is_empty() && id.len() <= MAX_ID_LEN && id.chars().all(|c| c.is_ascii_graphic()))
+}
+
+/// Requests from `server`, each one made current on this thread as it is
+/// handed out.
+pub fn incoming(server: tiny_http::Server) -> impl Iterator<Item = tiny_http::Request> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            if tx.send((Instant::now(), request)).is_err() {
+                break;
+            }
+        }
+    });
+    rx.into_iter().map(|(received, request)| {
+        let trace = Trace {
+            id: request_id(&request),
+            method: request.method().to_string(),
+            path: request.url().split('?').next().unwrap_or_default().to_string(),
+            received,
+            started: Instant::now(),
+        };
+        attach(Some(trace));
+        request
+    })
+}
+
+/// Takes the current request's trace off this thread, for a handler that
+/// answers from another one; `attach` it there.
+pub fn detach() -> Option<Trace> {
+    CURRENT.with(|current| current.borrow_mut().take())
+}
+
+pub fn attach(trace: Option<Trace>) {
+    CURRENT.with(|current| *current.borrow_mut() = trace);
+}
+
+/// Sends `response` with the X-Request-Id header and records the request.
+pub fn respond<R: Read>(request: tiny_http::Request, mut response: tiny_http::Response<R>) -> std::io::Result<()> {
+    let Some(trace) = detach() else { return request.respond(response) };
+    response.add_header(tiny_http::Header::from_bytes(&b"X-Request-Id"[..], trace.id.as_bytes()).unwrap());
+    let status = response.status_code().0;
+    let streamed = response.data_length().is_none();
+    let handled = Instant::now();
+    let result = request.respond(response);
+    finish(trace, status, streamed, handled);
+    result
+}
+
+fn finish(trace: Trace, status: u16, streamed: bool, handled: Instant) {
+    let ms = |from: Instant, to: Instant| to.duration_since(from).as_secs_f64() * 1000.0;
+    let now = Instant::now();
+    let duration_ms = ms(trace.received, now);
+    let slow = !streamed && duration_ms > SLOW_MS.load(Ordering::Relaxed) as f64;
+    let record = RequestRecord {
+        id: trace.id,
+        method: trace.method,
+        path: trace.path,
+        status,
+        duration_ms,
+        queue_ms: ms(trace.received, trace.started),
+        handler_ms: ms(trace.started, handled),
+        write_ms: ms(handled, now),
+        streamed,
+        slow,
+        timestamp: crate::now_millis(),
+    };
+    if slow {
+        log_info!(
+            "[{}] Slow request: {} {} -> {} in {:.0}ms (queue {:.0}ms, handler {:.0}ms, write {:.0}ms)",
+            record.id,
+            record.method,
+            record.path,
+            record.status,
+            record.duration_ms,
+            record.queue_ms,
+            record.handler_ms,
+            record.write_ms
+        );
+    }
+    let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    let dropped = keep(&mut recent, record, Buffer::Requests.capacity());
+    DROPPED.fetch_add(dropped, Ordering::Relaxed);
+}
+
+/// Appends `record`, dropping the oldest beyond `capacity`; answers how many.
+fn keep(recent: &mut VecDeque<RequestRecord>, record: RequestRecord, capacity: usize) -> u64 {
+    let mut dropped = 0;
+    while recent.len() >= capacity {
+        recent.pop_front();
+        dropped += 1;
+    }
+    recent.push_back(record);
+    dropped
+}
+
+/// Snapshot of the kept requests, oldest first.
+pub fn recent() -> Vec<RequestRecord> {
+    RECENT.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+}
+
+pub fn buffer_stats() -> BufferStats {
+    let recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    buffers::stats(Buffer::Requests, recent.iter(), DROPPED.load(Ordering::Relaxed))
+}
+
+pub fn slow_threshold_ms() -> u64 {
+    SLOW_MS.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    fn record(id: &str) -> RequestRecord {
+        RequestRecord {
+            id: id.to_string(),
+            method: "GET".to_string(),
+            path: "/status".to_string(),
+            status: 200,
+            duration_ms: 1.0,
+            queue_ms: 0.0,
+            handler_ms: 1.0,
+            write_ms: 0.0,
+            streamed: false,
+            slow: false,
+            timestamp: 0,
+        }
+    }
+
+    /// Sends `head` to a fresh server and answers it through `incoming` and
+    /// `respond`; returns the id current while handling and the raw response.
+    fn exchange(head: &str) -> (Option<String>, String) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        let head = head.to_string();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(format!("{}Connection: close\r\n\r\n", head).as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        let request = incoming(server).next().unwrap();
+        let current = current_id();
+        respond(request, tiny_http::Response::from_string("ok")).unwrap();
+        (current, client.join().unwrap())
+    }
+
+    fn response_id(response: &str) -> &str {
+        response.lines().find_map(|l| l.strip_prefix("X-Request-Id: ")).unwrap().trim()
+    }
+
+    #[test]
+    fn honors_sane_incoming_ids() {
+        assert_eq!(honored("  abc-123 "), Some("abc-123"));
+        assert_eq!(honored("trace=7f/01:x"), Some("trace=7f/01:x"));
+        assert_eq!(honored(&"a".repeat(MAX_ID_LEN)).map(str::len), Some(MAX_ID_LEN));
+        for bad in ["", "   ", "has space", "tab\there", "ключ", &"a".repeat(MAX_ID_LEN + 1)] {
+            assert_eq!(honored(bad), None, "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn echoes_and_records_the_incoming_id() {
+        let (current, response) = exchange("GET /status?token=secret HTTP/1.1\r\nHost: x\r\nX-Request-Id: it-echoes-1\r\n");
+        assert_eq!(current.as_deref(), Some("it-echoes-1"));
+        assert_eq!(response_id(&response), "it-echoes-1");
+        assert_eq!(current_id(), None);
+
+        let kept = recent().into_iter().find(|r| r.id == "it-echoes-1").unwrap();
+        assert_eq!((kept.method.as_str(), kept.path.as_str(), kept.status), ("GET", "/status", 200));
+        assert!(!kept.streamed);
+    }
+
+    #[test]
+    fn generates_an_id_otherwise() {
+        let (current, response) = exchange("POST /navigate HTTP/1.1\r\nHost: x\r\nX-Request-Id: not sane\r\nContent-Length: 0\r\n");
+        let id = response_id(&response).to_string();
+        assert_ne!(id, "not");
+        assert_eq!(current.as_deref(), Some(id.as_str()));
+        assert!(recent().iter().any(|r| r.id == id && r.path == "/navigate"));
+
+        let (_, again) = exchange("GET / HTTP/1.1\r\nHost: x\r\n");
+        assert_ne!(response_id(&again), id);
+    }
+
+    #[test]
+    fn keeps_the_newest_within_capacity() {
+        let mut recent = VecDeque::new();
+        let dropped: u64 = (0..5).map(|i| keep(&mut recent, record(&i.to_string()), 3)).sum();
+        assert_eq!(dropped, 2);
+        let ids: Vec<&str> = recent.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["2", "3", "4"]);
+
+        // A smaller capacity takes effect on the next request
+        assert_eq!(keep(&mut recent, record("5"), 2), 2);
+        assert_eq!(recent.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), ["4", "5"]);
+    }
+}
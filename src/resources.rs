@@ -0,0 +1,266 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use chromiumoxide::browser::Browser;
+use chromiumoxide::cdp::browser_protocol::performance::{EnableParams, GetMetricsParams};
+use chromiumoxide::cdp::browser_protocol::system_info::GetProcessInfoParams;
+use chromiumoxide::Page;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::CommandError;
+
+// ============== Resource Usage ==============
+//
+// GET /resources breaks memory use down per page (Performance.getMetrics:
+// JS heap, documents, nodes, listeners) and per browser process (RSS from the
+// OS process table, process types from SystemInfo.getProcessInfo). CDP has no
+// page -> renderer pid mapping, so processes are listed next to the pages
+// rather than attributed to them. GUI mode reports the process tree of this
+// binary, which includes the webview's helper processes where the platform
+// spawns them as children. With --resource-log, samples are appended to a
+// CSV or JSON-lines file for trend analysis.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageUsage {
+    pub target_id: String,
+    pub url: Option<String>,
+    /// The streamed page, as opposed to /render pool pages.
+    pub primary: bool,
+    pub js_heap_used: u64,
+    pub js_heap_total: u64,
+    pub documents: u64,
+    pub nodes: u64,
+    pub listeners: u64,
+    pub frames: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessUsage {
+    pub pid: u32,
+    pub kind: String,
+    pub command: Option<String>,
+    pub rss_kb: Option<u64>,
+    pub cpu_time_secs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub mode: String,
+    pub sampled_at: u128,
+    /// Sorted by JS heap in use, largest first.
+    pub pages: Vec<PageUsage>,
+    pub processes: Vec<ProcessUsage>,
+    pub total_rss_kb: u64,
+}
+
+struct PsEntry {
+    pid: u32,
+    ppid: u32,
+    rss_kb: u64,
+    command: String,
+}
+
+/// The OS process table via `ps` (Linux and macOS); empty where unavailable.
+fn process_table() -> Vec<PsEntry> {
+    let Ok(output) = Command::new("ps").args(["-A", "-o", "pid=,ppid=,rss=,comm="]).output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some(PsEntry {
+                pid: fields.next()?.parse().ok()?,
+                ppid: fields.next()?.parse().ok()?,
+                rss_kb: fields.next()?.parse().ok()?,
+                command: fields.collect::<Vec<_>>().join(" "),
+            })
+        })
+        .collect()
+}
+
+/// `root` and every process below it.
+fn process_tree(table: &[PsEntry], root: u32) -> Vec<&PsEntry> {
+    let mut tree: Vec<&PsEntry> = table.iter().filter(|p| p.pid == root).collect();
+    let mut i = 0;
+    while i < tree.len() {
+        let parent = tree[i].pid;
+        tree.extend(table.iter().filter(|p| p.ppid == parent && p.pid != root));
+        i += 1;
+    }
+    tree
+}
+
+fn metric(metrics: &[(String, f64)], name: &str) -> u64 {
+    metrics.iter().find(|(n, _)| n == name).map_or(0, |(_, v)| *v as u64)
+}
+
+async fn page_usage(page: &Page, primary: bool) -> Result<PageUsage, CommandError> {
+    page.execute(EnableParams::default()).await?;
+    let metrics: Vec<(String, f64)> = page
+        .execute(GetMetricsParams::default())
+        .await?
+        .result
+        .metrics
+        .into_iter()
+        .map(|m| (m.name, m.value))
+        .collect();
+    Ok(PageUsage {
+        target_id: page.target_id().as_ref().to_string(),
+        url: page.url().await?,
+        primary,
+        js_heap_used: metric(&metrics, "JSHeapUsedSize"),
+        js_heap_total: metric(&metrics, "JSHeapTotalSize"),
+        documents: metric(&metrics, "Documents"),
+        nodes: metric(&metrics, "Nodes"),
+        listeners: metric(&metrics, "JSEventListeners"),
+        frames: metric(&metrics, "Frames"),
+    })
+}
+
+pub async fn headless_report(browser: &Browser, primary: &Page) -> Result<Report, CommandError> {
+    let mut pages = Vec::new();
+    for page in browser.pages().await? {
+        let is_primary = page.target_id() == primary.target_id();
+        match page_usage(&page, is_primary).await {
+            Ok(usage) => pages.push(usage),
+            // Render pool pages can close between listing and sampling
+            Err(e) if !is_primary => log_error!("Skipping page in resource report: {}", e.message),
+            Err(e) => return Err(e),
+        }
+    }
+    pages.sort_by_key(|p| std::cmp::Reverse(p.js_heap_used));
+
+    let table = process_table();
+    let processes: Vec<ProcessUsage> = browser
+        .execute(GetProcessInfoParams::default())
+        .await?
+        .result
+        .process_info
+        .into_iter()
+        .map(|info| {
+            let pid = info.id as u32;
+            let entry = table.iter().find(|p| p.pid == pid);
+            ProcessUsage {
+                pid,
+                kind: info.r#type,
+                command: entry.map(|p| p.command.clone()),
+                rss_kb: entry.map(|p| p.rss_kb),
+                cpu_time_secs: Some(info.cpu_time),
+            }
+        })
+        .collect();
+    Ok(Report {
+        mode: "headless".to_string(),
+        sampled_at: crate::now_millis(),
+        total_rss_kb: processes.iter().filter_map(|p| p.rss_kb).sum(),
+        pages,
+        processes,
+    })
+}
+
+/// Resident memory of this process and everything below it (in headless
+/// mode that includes Chrome).
+pub fn process_tree_rss_kb() -> u64 {
+    let table = process_table();
+    process_tree(&table, std::process::id()).iter().map(|p| p.rss_kb).sum()
+}
+
+/// This process and its children; no per-page metrics are available from wry.
+pub fn gui_report() -> Report {
+    let table = process_table();
+    let own = std::process::id();
+    let processes: Vec<ProcessUsage> = process_tree(&table, own)
+        .into_iter()
+        .map(|p| ProcessUsage {
+            pid: p.pid,
+            kind: if p.pid == own { "browser" } else { "child" }.to_string(),
+            command: Some(p.command.clone()),
+            rss_kb: Some(p.rss_kb),
+            cpu_time_secs: None,
+        })
+        .collect();
+    Report {
+        mode: "gui".to_string(),
+        sampled_at: crate::now_millis(),
+        total_rss_kb: processes.iter().filter_map(|p| p.rss_kb).sum(),
+        pages: Vec::new(),
+        processes,
+    }
+}
+
+const CSV_HEADER: &str = "sampled_at,kind,id,url,js_heap_used,js_heap_total,documents,nodes,listeners,rss_kb\n";
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One row per page and per process.
+fn csv_rows(report: &Report) -> String {
+    let mut out = String::new();
+    for page in &report.pages {
+        out.push_str(&format!(
+            "{},page,{},{},{},{},{},{},{},\n",
+            report.sampled_at,
+            page.target_id,
+            csv_field(page.url.as_deref().unwrap_or_default()),
+            page.js_heap_used,
+            page.js_heap_total,
+            page.documents,
+            page.nodes,
+            page.listeners
+        ));
+    }
+    for process in &report.processes {
+        out.push_str(&format!(
+            "{},{},{},{},,,,,,{}\n",
+            report.sampled_at,
+            csv_field(&process.kind),
+            process.pid,
+            csv_field(process.command.as_deref().unwrap_or_default()),
+            process.rss_kb.map(|kb| kb.to_string()).unwrap_or_default()
+        ));
+    }
+    out
+}
+
+/// Appends a sample from `sample` to `path` every `interval`: CSV for a
+/// `.csv` path, JSON lines otherwise.
+pub fn start_log<F>(path: PathBuf, interval: Duration, sample: F)
+where
+    F: Fn() -> Result<Report, String> + Send + 'static,
+{
+    let csv = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    log_info!("Resource log: {} every {}s", path.display(), interval.as_secs());
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let report = match sample() {
+            Ok(report) => report,
+            Err(e) => {
+                log_error!("Resource sample failed: {}", e);
+                continue;
+            }
+        };
+        let needs_header = csv && std::fs::metadata(&path).map_or(true, |m| m.len() == 0);
+        let line = if csv {
+            format!("{}{}", if needs_header { CSV_HEADER } else { "" }, csv_rows(&report))
+        } else {
+            format!("{}\n", serde_json::json!(report))
+        };
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(e) = written {
+            log_error!("Failed to write resource log {}: {}", path.display(), e);
+        }
+    });
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// ============== Capture Scaling ==============
+//
+// When every client polling /live-stream asks for a thumbnail no wider than
+// the threshold, Chrome is asked for frames already at that size. Scaling up
+// happens as soon as a full-size consumer shows up; scaling down waits until
+// the thumbnail-only condition has held for DOWN_HOLD so the capture size
+// doesn't flap with every poll.
+
+/// A consumer that hasn't polled for this long no longer counts.
+const CONSUMER_TTL: Duration = Duration::from_secs(5);
+const DOWN_HOLD: Duration = Duration::from_secs(3);
+/// Scale changes smaller than this are ignored.
+const MIN_CHANGE: f64 = 0.05;
+
+pub struct CaptureScaler {
+    threshold: u32,
+    /// Client → requested max width (`None` = full size) and last poll.
+    consumers: HashMap<String, (Option<u32>, Instant)>,
+    scale: f64,
+    down_since: Option<Instant>,
+    switches: u64,
+    last_switch_at: Option<u128>,
+}
+
+pub type SharedScaler = Arc<Mutex<CaptureScaler>>;
+
+impl CaptureScaler {
+    pub fn new(threshold: u32) -> Self {
+        CaptureScaler {
+            threshold,
+            consumers: HashMap::new(),
+            scale: 1.0,
+            down_since: None,
+            switches: 0,
+            last_switch_at: None,
+        }
+    }
+
+    pub fn observe(&mut self, client: String, max_width: Option<u32>) {
+        self.consumers.insert(client, (max_width, Instant::now()));
+    }
+
+    /// Scale every active consumer could live with, or 1.0 if any needs full size.
+    fn target(&self, viewport_width: u32) -> f64 {
+        let mut widest = 0;
+        for (max_width, _) in self.consumers.values() {
+            match max_width {
+                Some(w) if *w <= self.threshold => widest = widest.max(*w),
+                _ => return 1.0,
+            }
+        }
+        if widest == 0 {
+            return 1.0;
+        }
+        (widest as f64 / viewport_width.max(1) as f64).clamp(0.1, 1.0)
+    }
+
+    /// Called once per capture; returns the scale to capture at.
+    pub fn update(&mut self, viewport_width: u32) -> f64 {
+        self.consumers.retain(|_, (_, seen)| seen.elapsed() < CONSUMER_TTL);
+        let target = self.target(viewport_width);
+
+        if (target - self.scale).abs() < MIN_CHANGE {
+            self.down_since = None;
+        } else if target > self.scale {
+            self.switch(target);
+        } else {
+            let since = *self.down_since.get_or_insert_with(Instant::now);
+            if since.elapsed() >= DOWN_HOLD {
+                self.switch(target);
+            }
+        }
+        self.scale
+    }
+
+    fn switch(&mut self, scale: f64) {
+        log_info!("Capture scale {:.2} -> {:.2}", self.scale, scale);
+        self.scale = scale;
+        self.down_since = None;
+        self.switches += 1;
+        self.last_switch_at = Some(crate::now_millis());
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let consumers: Vec<serde_json::Value> = self
+            .consumers
+            .iter()
+            .map(|(client, (max_width, seen))| {
+                serde_json::json!({
+                    "client": client,
+                    "max_width": max_width,
+                    "last_seen_ms_ago": seen.elapsed().as_millis(),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "threshold": self.threshold,
+            "scale": self.scale,
+            "pending_downscale": self.down_since.is_some(),
+            "switches": self.switches,
+            "last_switch_at": self.last_switch_at,
+            "consumers": consumers,
+        })
+    }
+}
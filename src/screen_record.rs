@@ -0,0 +1,444 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+// ============== GUI Screen Recording ==============
+//
+// POST /record/start or F9 records the browser window into an MP4 or WebM
+// file in --record-dir (the Downloads folder by default). A thread
+// captures the window region --record-fps times a second and pipes raw
+// frames to ffmpeg, which encodes them and, with "audio": true, muxes in
+// system output audio and, with "mic": true, the microphone. ffmpeg
+// captures audio from the platform's own input device: on Linux the
+// PulseAudio monitor of the default output and the default source; on macOS
+// the default microphone, with system output only through a loopback device
+// such as BlackHole passed as --record-audio-input; on Windows only the
+// dshow devices given as --record-audio-input and --record-mic-input.
+//
+// Frames keep the size the window had when recording started. MP4 files
+// are fragmented and WebM is streamable, so whatever was written before an
+// abrupt stop still plays. The recording stops on its own, keeping the
+// file, when the disk runs low (under MIN_FREE_MB, checked where `df` is
+// available) or ffmpeg stops accepting frames, e.g. because the disk is
+// full. The toolbar shows a REC badge while recording; GET /record reports
+// the state and the last finished recording.
+
+/// Free space below which a recording stops.
+const MIN_FREE_MB: u64 = 256;
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// How long ffmpeg gets to finish the file after its input closes.
+const FINISH_TIMEOUT: Duration = Duration::from_secs(15);
+
+const BADGE_ID: &str = "__rb_rec__";
+
+/// Shows or removes the REC badge in the injected toolbar.
+const BADGE_SCRIPT: &str = r#"
+function(id, on) {
+    const old = document.getElementById(id);
+    if (old) old.remove();
+    const bar = document.querySelector('#__rust_browser_toolbar__ .nav-bar');
+    if (!on || !bar) return;
+    const badge = document.createElement('span');
+    badge.id = id;
+    badge.textContent = '● REC';
+    badge.title = 'Recording (F9 to stop)';
+    badge.style.cssText = 'color:#d00;font:bold 12px sans-serif;padding:0 4px;';
+    bar.appendChild(badge);
+}"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    Mp4,
+    Webm,
+}
+
+impl RecordFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            RecordFormat::Mp4 => "mp4",
+            RecordFormat::Webm => "webm",
+        }
+    }
+
+    fn codec_args(self) -> &'static [&'static str] {
+        match self {
+            RecordFormat::Mp4 => &[
+                "-c:v", "libx264", "-preset", "veryfast", "-pix_fmt", "yuv420p", "-c:a", "aac",
+                "-movflags", "+frag_keyframe+empty_moov+default_base_moof",
+            ],
+            RecordFormat::Webm => &["-c:v", "libvpx", "-deadline", "realtime", "-cpu-used", "8", "-b:v", "2M", "-c:a", "libopus"],
+        }
+    }
+}
+
+/// `FORMAT:DEVICE` as given to ffmpeg's `-f FORMAT -i DEVICE`, e.g.
+/// `pulse:default` or `avfoundation::0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioInput {
+    pub format: String,
+    pub device: String,
+}
+
+impl std::str::FromStr for AudioInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((format, device)) if !format.is_empty() && !device.is_empty() => {
+                Ok(AudioInput { format: format.to_string(), device: device.to_string() })
+            }
+            _ => Err(format!("expected FORMAT:DEVICE, e.g. pulse:default, got '{}'", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for AudioInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.format, self.device)
+    }
+}
+
+/// The platform's system output capture, where there is one without extra drivers.
+fn default_audio_input() -> Option<AudioInput> {
+    cfg!(target_os = "linux").then(|| AudioInput { format: "pulse".to_string(), device: "@DEFAULT_MONITOR@".to_string() })
+}
+
+fn default_mic_input() -> Option<AudioInput> {
+    if cfg!(target_os = "linux") {
+        Some(AudioInput { format: "pulse".to_string(), device: "default".to_string() })
+    } else if cfg!(target_os = "macos") {
+        Some(AudioInput { format: "avfoundation".to_string(), device: ":0".to_string() })
+    } else {
+        None
+    }
+}
+
+/// `~/Downloads`, or the working directory without a home.
+fn downloads_dir() -> PathBuf {
+    std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+        .map(|home| PathBuf::from(home).join("Downloads"))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Free space under `dir` in MB, where `df` is available.
+fn free_mb(dir: &Path) -> Option<u64> {
+    if cfg!(windows) {
+        return None;
+    }
+    let output = Command::new("df").arg("-Pk").arg(dir).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let available_kb: u64 = text.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available_kb / 1024)
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordConfig {
+    pub dir: PathBuf,
+    pub fps: u32,
+    pub format: RecordFormat,
+    pub audio_input: Option<AudioInput>,
+    pub mic_input: Option<AudioInput>,
+}
+
+impl RecordConfig {
+    pub fn new(dir: Option<PathBuf>, fps: u32, format: RecordFormat, audio_input: Option<AudioInput>, mic_input: Option<AudioInput>) -> Self {
+        RecordConfig {
+            dir: dir.unwrap_or_else(downloads_dir),
+            fps: fps.clamp(1, 60),
+            format,
+            audio_input: audio_input.or_else(default_audio_input),
+            mic_input: mic_input.or_else(default_mic_input),
+        }
+    }
+}
+
+/// Body of POST /record/start in GUI mode.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RecordRequest {
+    pub audio: bool,
+    pub mic: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Recording {
+    pub path: PathBuf,
+    pub started_at: u128,
+    pub stopped_at: Option<u128>,
+    pub audio: bool,
+    pub mic: bool,
+    pub width: u32,
+    pub height: u32,
+    pub frames: u64,
+    pub bytes: u64,
+    /// Why it stopped, when not asked to.
+    pub stop_reason: Option<String>,
+}
+
+struct Active {
+    recording: Arc<Mutex<Recording>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Window region grabber, supplied by the GUI.
+pub type Capture = Arc<dyn Fn() -> Option<RgbaImage> + Send + Sync>;
+
+pub struct ScreenRecorder {
+    config: RecordConfig,
+    capture: Capture,
+    /// Called whenever recording starts or stops, to update the toolbars.
+    notify: Arc<dyn Fn() + Send + Sync>,
+    active: Option<Active>,
+    last: Option<Recording>,
+}
+
+pub type SharedScreenRecorder = Arc<Mutex<ScreenRecorder>>;
+
+impl ScreenRecorder {
+    pub fn new(config: RecordConfig, capture: Capture, notify: Arc<dyn Fn() + Send + Sync>) -> Self {
+        ScreenRecorder { config, capture, notify, active: None, last: None }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "recording": self.active.as_ref().map(|a| a.recording.lock().unwrap().clone()),
+            "last": self.last,
+            "dir": self.config.dir,
+            "fps": self.config.fps,
+            "format": self.config.format,
+            "audio_input": self.config.audio_input.as_ref().map(ToString::to_string),
+            "mic_input": self.config.mic_input.as_ref().map(ToString::to_string),
+        })
+    }
+}
+
+/// Starts a recording; `Err` with an HTTP status and message otherwise.
+pub fn start(shared: &SharedScreenRecorder, req: RecordRequest) -> Result<Recording, (u16, String)> {
+    let mut recorder = shared.lock().unwrap();
+    if recorder.active.is_some() {
+        return Err((409, "already recording".to_string()));
+    }
+    let config = recorder.config.clone();
+    let audio = match (req.audio, &config.audio_input) {
+        (true, None) => return Err((400, "no system audio input on this platform, set --record-audio-input".to_string())),
+        (audio, input) => input.as_ref().filter(|_| audio),
+    };
+    let mic = match (req.mic, &config.mic_input) {
+        (true, None) => return Err((400, "no microphone input on this platform, set --record-mic-input".to_string())),
+        (mic, input) => input.as_ref().filter(|_| mic),
+    };
+    if free_mb(&config.dir).is_some_and(|free| free < MIN_FREE_MB) {
+        return Err((507, format!("less than {} MB free in {}", MIN_FREE_MB, config.dir.display())));
+    }
+    let first = (recorder.capture)().ok_or((500, "window capture failed".to_string()))?;
+    let (width, height) = first.dimensions();
+
+    std::fs::create_dir_all(&config.dir).map_err(|e| (500, format!("Failed to create {}: {}", config.dir.display(), e)))?;
+    let name = format!("recording-{}.{}", chrono::Local::now().format("%Y%m%d-%H%M%S"), config.format.extension());
+    let path = config.dir.join(name);
+    let child = spawn_ffmpeg(&config, audio, mic, width, height, &path)
+        .map_err(|e| (500, format!("Failed to start ffmpeg (is it installed?): {}", e)))?;
+
+    let recording = Arc::new(Mutex::new(Recording {
+        path,
+        started_at: crate::now_millis(),
+        stopped_at: None,
+        audio: req.audio,
+        mic: req.mic,
+        width,
+        height,
+        frames: 0,
+        bytes: 0,
+        stop_reason: None,
+    }));
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let (shared, recording, stop) = (shared.clone(), recording.clone(), stop.clone());
+        let capture = recorder.capture.clone();
+        thread::spawn(move || run(shared, capture, child, first, config.fps, recording, stop))
+    };
+    let snapshot = recording.lock().unwrap().clone();
+    log_info!("Recording to {}", snapshot.path.display());
+    recorder.active = Some(Active { recording, stop, handle: Some(handle) });
+    let notify = recorder.notify.clone();
+    drop(recorder);
+    notify();
+    Ok(snapshot)
+}
+
+/// Stops the recording and waits for the file to be finished.
+pub fn stop(shared: &SharedScreenRecorder) -> Option<Recording> {
+    let handle = {
+        let mut recorder = shared.lock().unwrap();
+        let active = recorder.active.as_mut()?;
+        active.stop.store(true, Ordering::Relaxed);
+        active.handle.take()
+    };
+    if let Some(handle) = handle {
+        let _ = handle.join();
+    }
+    shared.lock().unwrap().last.clone()
+}
+
+fn spawn_ffmpeg(config: &RecordConfig, audio: Option<&AudioInput>, mic: Option<&AudioInput>, width: u32, height: u32, path: &Path) -> std::io::Result<Child> {
+    let mut command = Command::new("ffmpeg");
+    command.args(["-hide_banner", "-loglevel", "error", "-y"]);
+    command.args(["-use_wallclock_as_timestamps", "1", "-thread_queue_size", "64", "-f", "rawvideo", "-pix_fmt", "rgba"]);
+    command.arg("-s").arg(format!("{}x{}", width, height)).args(["-i", "-"]);
+    let inputs: Vec<&AudioInput> = audio.into_iter().chain(mic).collect();
+    for input in &inputs {
+        command.args(["-thread_queue_size", "1024", "-f", &input.format, "-i", &input.device]);
+    }
+    command.args(["-map", "0:v"]);
+    match inputs.len() {
+        0 => {}
+        1 => {
+            command.args(["-map", "1:a"]);
+        }
+        _ => {
+            command.args(["-filter_complex", "[1:a][2:a]amix=inputs=2:duration=longest[a]", "-map", "[a]"]);
+        }
+    }
+    // Even dimensions for yuv420p
+    command.args(["-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2", "-fps_mode", "cfr"]);
+    command.arg("-r").arg(config.fps.to_string());
+    command.args(config.format.codec_args());
+    command.arg("-shortest").arg(path);
+    command.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).spawn()
+}
+
+/// The capture loop of one recording; clears `active` when it ends.
+fn run(shared: SharedScreenRecorder, capture: Capture, mut child: Child, first: RgbaImage, fps: u32, recording: Arc<Mutex<Recording>>, stop: Arc<AtomicBool>) {
+    let interval = Duration::from_secs_f64(1.0 / fps as f64);
+    let (width, height) = first.dimensions();
+    let (path, dir) = {
+        let r = recording.lock().unwrap();
+        (r.path.clone(), r.path.parent().map(Path::to_path_buf).unwrap_or_default())
+    };
+    let mut stdin = child.stdin.take();
+    let mut frame = Some(first);
+    let mut last_disk_check = Instant::now();
+    let mut reason = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        let started = Instant::now();
+        let img = match frame.take().or_else(|| capture()) {
+            // The window size is fixed for the whole file
+            Some(img) if img.dimensions() != (width, height) => imageops::resize(&img, width, height, imageops::FilterType::Triangle),
+            Some(img) => img,
+            None => {
+                thread::sleep(interval);
+                continue;
+            }
+        };
+        let Some(writer) = stdin.as_mut() else {
+            break;
+        };
+        if let Err(e) = writer.write_all(img.as_raw()) {
+            reason = Some(format!("ffmpeg stopped accepting frames ({}); the disk may be full", e));
+            break;
+        }
+        recording.lock().unwrap().frames += 1;
+
+        if last_disk_check.elapsed() >= DISK_CHECK_INTERVAL {
+            last_disk_check = Instant::now();
+            if free_mb(&dir).is_some_and(|free| free < MIN_FREE_MB) {
+                reason = Some(format!("less than {} MB free", MIN_FREE_MB));
+                break;
+            }
+        }
+        thread::sleep(interval.saturating_sub(started.elapsed()));
+    }
+
+    // Closing the video input makes ffmpeg finish the file
+    drop(stdin);
+    let deadline = Instant::now() + FINISH_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(100)),
+            _ => {
+                let _ = child.kill();
+                break child.wait().ok();
+            }
+        }
+    };
+    if !status.is_some_and(|s| s.success()) {
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            use std::io::Read;
+            let _ = pipe.read_to_string(&mut stderr);
+        }
+        log_error!("ffmpeg exited with {:?}: {}", status, stderr.trim());
+    }
+
+    let mut finished = recording.lock().unwrap().clone();
+    finished.stopped_at = Some(crate::now_millis());
+    finished.bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    finished.stop_reason = reason;
+    match &finished.stop_reason {
+        Some(reason) => log_error!("Recording stopped: {}; kept {}", reason, path.display()),
+        None => log_info!("Recording saved: {} ({} frames)", path.display(), finished.frames),
+    }
+    let notify = {
+        let mut recorder = shared.lock().unwrap();
+        recorder.active = None;
+        recorder.last = Some(finished);
+        recorder.notify.clone()
+    };
+    notify();
+}
+
+/// F9: starts a recording with system audio where there is an input for
+/// it, or stops the running one in the background.
+pub fn toggle(shared: &SharedScreenRecorder) {
+    if shared.lock().unwrap().is_recording() {
+        let shared = shared.clone();
+        thread::spawn(move || stop(&shared));
+        return;
+    }
+    let audio = shared.lock().unwrap().config.audio_input.is_some();
+    if let Err((_, e)) = start(shared, RecordRequest { audio, mic: false }) {
+        log_error!("Failed to start recording: {}", e);
+    }
+}
+
+/// GUI `/record`, `/record/start` and `/record/stop`.
+pub fn handle(request: &mut tiny_http::Request, path: &str, shared: &SharedScreenRecorder) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let method = request.method().clone();
+    match (path, method) {
+        ("/record", tiny_http::Method::Get) => crate::json_response(200, &shared.lock().unwrap().to_json()),
+        ("/record/start", tiny_http::Method::Post) => {
+            let req = match crate::read_json_body::<RecordRequest>(request) {
+                Ok(req) => req,
+                Err(e) => return crate::json_response(400, &serde_json::json!({"error": e})),
+            };
+            match start(shared, req) {
+                Ok(recording) => crate::json_response(200, &serde_json::json!(recording)),
+                Err((status, e)) => crate::json_response(status, &serde_json::json!({"error": e})),
+            }
+        }
+        ("/record/stop", tiny_http::Method::Post) => match stop(shared) {
+            Some(recording) => crate::json_response(200, &serde_json::json!(recording)),
+            None => crate::json_response(409, &serde_json::json!({"error": "not recording"})),
+        },
+        _ => crate::json_response(405, &serde_json::json!({"error": "method not allowed"})),
+    }
+}
+
+pub fn badge_script(on: bool) -> String {
+    crate::commands::js_call(BADGE_SCRIPT, &[BADGE_ID.into(), on.into()])
+}
@@ -0,0 +1,145 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chromiumoxide::cdp::browser_protocol::page::{CaptureScreenshotFormat, GetLayoutMetricsParams, Viewport};
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::Page;
+
+use crate::commands::{self, CommandError, CommandResult};
+use crate::HeadlessState;
+
+pub use crate::types::{Clip, ScreenshotRequest};
+
+// ============== On-demand Screenshots ==============
+//
+// GET /screenshot captures the streamed page when asked instead of handing
+// out the last live frame: ?format=png|jpeg (default jpeg), ?quality=0..100
+// for JPEG, and ?selector= to clip to one element. The element is scrolled
+// into view and its box, moved by the scroll offset into document
+// coordinates, becomes the clip rectangle of Page.captureScreenshot, so
+// nothing around it is captured. Redaction applies like it does to the live
+// frames. Redaction boxes are in viewport coordinates and would not line up
+// with a clipped capture, so element, clip and full-page screenshots are
+// refused (409) while redaction is active instead of risking an unredacted
+// region.
+//
+// ?fullpage=true (and --fullpage for the live stream) captures the whole
+// scrollable page: the clip is the CSS content size from
+// Page.getLayoutMetrics, captured beyond the viewport. Chrome fails on
+// absurdly tall captures, so the height is capped at --fullpage-max-height
+// and a capped /screenshot carries X-Clipped: true. The live stream falls
+// back to the viewport while redaction is active, for the same reason.
+//
+// ?x=&y=&width=&height= (and --clip X,Y,W,H for the live stream) capture one
+// rectangle of the page in CSS pixels, e.g. a single dashboard widget. A
+// rectangle outside the page content is a 400 naming the content size;
+// --clip is checked against the viewport at startup.
+
+/// Scrolls the first match into view; `null` for no match.
+const CLIP_SCRIPT: &str = r#"
+function(find, selector) {
+    const el = find(selector, false);
+    if (!el) return null;
+    el.scrollIntoView({ block: 'center', inline: 'center' });
+    const r = el.getBoundingClientRect();
+    return { x: r.left + scrollX, y: r.top + scrollY, width: r.width, height: r.height };
+}
+"#;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FullPage {
+    /// --fullpage: the live stream captures the whole page.
+    pub stream: bool,
+    pub max_height: u32,
+    /// --clip: the live stream captures only this rectangle.
+    pub clip: Option<Clip>,
+}
+
+impl Clip {
+    fn viewport(&self) -> Viewport {
+        Viewport { x: self.x, y: self.y, width: self.width, height: self.height, scale: 1.0 }
+    }
+}
+
+/// Clip covering the page's content, and whether the height cap cut it.
+async fn full_page_clip(page: &Page, max_height: u32) -> Result<(Viewport, bool), CommandError> {
+    let metrics = page.execute(GetLayoutMetricsParams::default()).await?.result;
+    let size = metrics.css_content_size;
+    let clipped = size.height > max_height as f64;
+    let clip = Viewport { x: 0.0, y: 0.0, width: size.width, height: size.height.min(max_height as f64), scale: 1.0 };
+    Ok((clip, clipped))
+}
+
+/// The live stream's capture params: `params` as is, or with --clip that
+/// rectangle and with --fullpage the whole page, unless redaction is active
+/// or the metrics can't be read.
+pub async fn stream_params(page: &Page, state: &HeadlessState, params: ScreenshotParams) -> ScreenshotParams {
+    if !(state.full_page.stream || state.full_page.clip.is_some()) || state.redaction.lock().unwrap().is_active() {
+        return params;
+    }
+    if let Some(clip) = state.full_page.clip {
+        let mut params = params;
+        params.cdp_params.clip = Some(clip.viewport());
+        params.cdp_params.capture_beyond_viewport = Some(true);
+        return params;
+    }
+    match full_page_clip(page, state.full_page.max_height).await {
+        Ok((clip, _)) => {
+            let mut params = params;
+            params.cdp_params.clip = Some(clip);
+            params.cdp_params.capture_beyond_viewport = Some(true);
+            params
+        }
+        Err(_) => params,
+    }
+}
+
+/// `{image, clipped}` with the image as base64, decoded again by the HTTP
+/// handler.
+pub async fn capture(page: &Page, state: &HeadlessState, req: ScreenshotRequest) -> CommandResult {
+    let mut params = ScreenshotParams::builder();
+    params = if req.png {
+        params.format(CaptureScreenshotFormat::Png)
+    } else {
+        params.format(CaptureScreenshotFormat::Jpeg).quality(req.quality as i64)
+    };
+    let clipping = req.selector.is_some() || req.full_page || req.clip.is_some();
+    if clipping && state.redaction.lock().unwrap().is_active() {
+        return Err(CommandError::new(409, "element, clip and full-page screenshots are unavailable while redaction is active"));
+    }
+    if let Some(clip) = req.clip {
+        let size = page.execute(GetLayoutMetricsParams::default()).await?.result.css_content_size;
+        clip.check_within(size.width, size.height).map_err(|e| CommandError::new(400, e))?;
+        params = params.clip(clip.viewport()).capture_beyond_viewport(true);
+    }
+    let mut clipped = false;
+    if req.full_page {
+        let (clip, cut) = full_page_clip(page, state.full_page.max_height).await?;
+        clipped = cut;
+        params = params.clip(clip).capture_beyond_viewport(true);
+    }
+    if let Some(selector) = &req.selector {
+        let expression = commands::js_call_with_find(CLIP_SCRIPT, &[selector.as_str().into()]);
+        let clip = commands::evaluate(page, expression, false).await?;
+        if clip.is_null() {
+            return Err(CommandError::new(404, format!("no element matches {}", selector)));
+        }
+        let dimension = |name: &str| clip[name].as_f64().unwrap_or_default();
+        if dimension("width") <= 0.0 || dimension("height") <= 0.0 {
+            return Err(CommandError::new(422, format!("{} has no size to capture", selector)));
+        }
+        params = params.clip(Viewport {
+            x: dimension("x"),
+            y: dimension("y"),
+            width: dimension("width"),
+            height: dimension("height"),
+            scale: 1.0,
+        });
+    }
+    let image = page.screenshot(params.build()).await?;
+    let image = if clipping {
+        image
+    } else {
+        crate::redact::refresh(page, &state.redaction, 0, true).await;
+        crate::redact::apply(&state.redaction, image).ok_or_else(|| CommandError::new(500, "failed to apply redaction to the screenshot"))?
+    };
+    Ok(serde_json::json!({ "image": BASE64.encode(image), "clipped": clipped }))
+}
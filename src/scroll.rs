@@ -0,0 +1,124 @@
+use chromiumoxide::cdp::browser_protocol::page::ReloadParams;
+use chromiumoxide::Page;
+use serde::Deserialize;
+
+use crate::commands::{self, CommandResult};
+
+// ============== Scroll Restoration ==============
+//
+// POST /reload keeps a monitored page where it was: the window scroll and
+// the tallest scrollable container (SPA dashboards often scroll an inner
+// element, not the document) are captured before the reload and reapplied
+// once the page has grown tall enough again. The same helper backs GUI tab
+// hibernation. `"restore_scroll": false` reloads to the top instead, and
+// `"ignore_cache": true` (or ?ignore_cache=true) bypasses the cache.
+
+/// How long the restore waits for the page to regain its height.
+pub const RESTORE_TIMEOUT_MS: u64 = 5000;
+
+#[derive(Debug, Deserialize)]
+pub struct ReloadRequest {
+    #[serde(default = "default_restore")]
+    pub restore_scroll: bool,
+    /// Bypass the cache, like a shift-reload.
+    #[serde(default)]
+    pub ignore_cache: bool,
+}
+
+fn default_restore() -> bool {
+    true
+}
+
+/// Defines `window.__rbScroll.capture()` and `.restore(position, timeoutMs)`;
+/// safe to run more than once. Injected as an init script in GUI mode and
+/// prepended to the calls in headless mode.
+pub const HELPER_SCRIPT: &str = r#"
+if (!window.__rbScroll) {
+    const pathOf = function(el) {
+        const parts = [];
+        while (el && el.nodeType === 1 && el !== document.body) {
+            if (el.id) { parts.unshift('#' + CSS.escape(el.id)); break; }
+            let index = 1;
+            for (let sib = el.previousElementSibling; sib; sib = sib.previousElementSibling) {
+                if (sib.tagName === el.tagName) index++;
+            }
+            parts.unshift(el.tagName.toLowerCase() + ':nth-of-type(' + index + ')');
+            el = el.parentElement;
+        }
+        return parts.length && !parts[0].startsWith('#') ? 'body > ' + parts.join(' > ') : parts.join(' > ');
+    };
+    const tallest = function() {
+        let best = null;
+        document.querySelectorAll('body *').forEach(function(el) {
+            if (el.scrollHeight <= el.clientHeight + 1) return;
+            const overflow = getComputedStyle(el).overflowY;
+            if (overflow !== 'auto' && overflow !== 'scroll') return;
+            if (!best || el.scrollHeight > best.scrollHeight) best = el;
+        });
+        return best;
+    };
+    window.__rbScroll = {
+        capture: function() {
+            const el = tallest();
+            return {
+                x: window.scrollX,
+                y: window.scrollY,
+                container: el && (el.scrollTop || el.scrollLeft)
+                    ? { selector: pathOf(el), top: el.scrollTop, left: el.scrollLeft }
+                    : null,
+            };
+        },
+        restore: function(pos, timeoutMs) {
+            return new Promise(function(resolve) {
+                const deadline = Date.now() + timeoutMs;
+                const attempt = function() {
+                    const root = document.scrollingElement || document.documentElement;
+                    const windowReady = root.scrollHeight - window.innerHeight >= pos.y;
+                    if (windowReady) window.scrollTo(pos.x, pos.y);
+                    let containerReady = true;
+                    if (pos.container) {
+                        const el = document.querySelector(pos.container.selector);
+                        containerReady = !!el && el.scrollHeight - el.clientHeight >= pos.container.top;
+                        if (containerReady) {
+                            el.scrollTop = pos.container.top;
+                            el.scrollLeft = pos.container.left;
+                        }
+                    }
+                    if ((windowReady && containerReady) || Date.now() > deadline) {
+                        resolve({ restored: windowReady && containerReady, x: window.scrollX, y: window.scrollY });
+                    } else {
+                        setTimeout(attempt, 100);
+                    }
+                };
+                attempt();
+            });
+        },
+    };
+}
+"#;
+
+pub async fn capture(page: &Page) -> CommandResult {
+    commands::evaluate(page, format!("{}; window.__rbScroll.capture()", HELPER_SCRIPT), false).await
+}
+
+/// Waits up to RESTORE_TIMEOUT_MS for the page to regain its height.
+pub async fn restore(page: &Page, position: &serde_json::Value) -> CommandResult {
+    let call = format!("window.__rbScroll.restore({}, {})", position, RESTORE_TIMEOUT_MS);
+    commands::evaluate(page, format!("{}; {}", HELPER_SCRIPT, call), true).await
+}
+
+/// Reloads the page, carrying the scroll position over when `restore_scroll` is set.
+pub async fn reload(page: &Page, restore_scroll: bool, ignore_cache: bool) -> CommandResult {
+    let position = if restore_scroll { Some(capture(page).await?) } else { None };
+    page.execute(ReloadParams { ignore_cache: Some(ignore_cache), ..Default::default() }).await?;
+    page.wait_for_navigation().await?;
+    let restored = match &position {
+        Some(position) => restore(page, position).await?,
+        None => serde_json::Value::Null,
+    };
+    Ok(serde_json::json!({
+        "url": page.url().await?,
+        "scroll": position,
+        "restored": restored,
+    }))
+}
@@ -0,0 +1,202 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use chromiumoxide::error::CdpError;
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::Page;
+use serde::Serialize;
+
+// ============== Settled Capture ==============
+//
+// CSS animations and skeleton loaders make a single screenshot taken right
+// after load unreliable. A settled capture keeps grabbing frames until the
+// last `frames` captures are byte-identical or the timeout passes. The same
+// decision, `Stillness`, waits for an element's box to stop moving before
+// /click, /type and /hover use it.
+
+/// Upper bound for the settle timeout so a command never outlives its HTTP reply.
+const MAX_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Clone, Copy)]
+pub struct SettleOptions {
+    /// Consecutive identical frames required.
+    pub frames: u32,
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettleReport {
+    pub settled: bool,
+    pub frames_captured: u32,
+    pub elapsed_ms: u128,
+}
+
+impl SettleOptions {
+    pub fn new(frames: u32, interval_ms: u64, timeout_ms: u64) -> Self {
+        SettleOptions {
+            frames: frames.max(2),
+            interval: Duration::from_millis(interval_ms),
+            timeout: Duration::from_millis(timeout_ms).min(MAX_TIMEOUT),
+        }
+    }
+
+    /// Applies `settle_frames`, `settle_interval_ms` and `settle_timeout_ms` query overrides.
+    pub fn with_query(self, query: &HashMap<String, String>) -> Result<Self, String> {
+        let get = |key: &str, default: u64| -> Result<u64, String> {
+            match query.get(key) {
+                Some(v) => v.parse().map_err(|_| format!("invalid {} '{}'", key, v)),
+                None => Ok(default),
+            }
+        };
+        Ok(SettleOptions::new(
+            get("settle_frames", self.frames as u64)? as u32,
+            get("settle_interval_ms", self.interval.as_millis() as u64)?,
+            get("settle_timeout_ms", self.timeout.as_millis() as u64)?,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    /// Not enough identical samples yet; sample again.
+    Wait,
+    /// The last `required` samples were identical.
+    Still,
+    /// The timeout passed before that happened.
+    TimedOut,
+}
+
+/// Counts consecutive identical samples against a timeout.
+#[derive(Debug)]
+pub struct Stillness<T> {
+    required: u32,
+    timeout: Duration,
+    started: Instant,
+    last: Option<T>,
+    run: u32,
+}
+
+impl<T: PartialEq> Stillness<T> {
+    /// The timeout runs from now.
+    pub fn new(required: u32, timeout: Duration) -> Self {
+        Self::starting_at(required, timeout, Instant::now())
+    }
+
+    fn starting_at(required: u32, timeout: Duration, started: Instant) -> Self {
+        Stillness { required: required.max(1), timeout, started, last: None, run: 0 }
+    }
+
+    pub fn observe(&mut self, sample: T) -> Verdict {
+        self.observe_at(sample, Instant::now())
+    }
+
+    fn observe_at(&mut self, sample: T, now: Instant) -> Verdict {
+        self.run = if self.last.as_ref() == Some(&sample) { self.run + 1 } else { 1 };
+        self.last = Some(sample);
+        if self.run >= self.required {
+            Verdict::Still
+        } else if now.duration_since(self.started) >= self.timeout {
+            Verdict::TimedOut
+        } else {
+            Verdict::Wait
+        }
+    }
+}
+
+fn frame_hash(frame: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    frame.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Takes a single screenshot, or a settled one when `settle` is given.
+pub async fn capture(page: &Page, params: ScreenshotParams, settle: Option<SettleOptions>) -> Result<(Vec<u8>, Option<SettleReport>), CdpError> {
+    match settle {
+        Some(options) => {
+            let (frame, report) = capture_settled(page, params, options).await?;
+            Ok((frame, Some(report)))
+        }
+        None => Ok((page.screenshot(params).await?, None)),
+    }
+}
+
+/// ScreenshotParams is not Clone; every capture needs its own.
+fn copy(params: &ScreenshotParams) -> ScreenshotParams {
+    ScreenshotParams { cdp_params: params.cdp_params.clone(), full_page: params.full_page, omit_background: params.omit_background }
+}
+
+/// Captures until the page stops changing and returns the last frame. On
+/// timeout the most recent frame is returned with `settled: false`.
+async fn capture_settled(page: &Page, params: ScreenshotParams, options: SettleOptions) -> Result<(Vec<u8>, SettleReport), CdpError> {
+    let started = Instant::now();
+    let mut stillness = Stillness::new(options.frames, options.timeout);
+    let mut frame = page.screenshot(copy(&params)).await?;
+    let mut captured = 1;
+    let mut verdict = stillness.observe(frame_hash(&frame));
+
+    while verdict == Verdict::Wait {
+        tokio::time::sleep(options.interval).await;
+        frame = page.screenshot(copy(&params)).await?;
+        captured += 1;
+        verdict = stillness.observe(frame_hash(&frame));
+    }
+
+    let report = SettleReport {
+        settled: verdict == Verdict::Still,
+        frames_captured: captured,
+        elapsed_ms: started.elapsed().as_millis(),
+    };
+    Ok((frame, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    /// Feeds `samples` one `step` apart and returns every verdict.
+    fn verdicts(required: u32, timeout: Duration, step: Duration, samples: &[u32]) -> Vec<Verdict> {
+        let started = Instant::now();
+        let mut stillness = Stillness::starting_at(required, timeout, started);
+        samples.iter().enumerate().map(|(i, &s)| stillness.observe_at(s, started + step * i as u32)).collect()
+    }
+
+    #[test]
+    fn still_after_the_required_identical_run() {
+        use Verdict::*;
+        assert_eq!(verdicts(3, 1000 * MS, 50 * MS, &[1, 1, 1]), [Wait, Wait, Still]);
+        // A layout shift restarts the run
+        assert_eq!(verdicts(3, 1000 * MS, 50 * MS, &[1, 1, 2, 2, 2]), [Wait, Wait, Wait, Wait, Still]);
+        assert_eq!(verdicts(2, 1000 * MS, 50 * MS, &[1, 2, 1, 2, 2]), [Wait, Wait, Wait, Wait, Still]);
+        // One sample is enough when only one is required
+        assert_eq!(verdicts(1, 1000 * MS, 50 * MS, &[7]), [Still]);
+        assert_eq!(verdicts(0, 1000 * MS, 50 * MS, &[7]), [Still]);
+    }
+
+    #[test]
+    fn times_out_while_moving() {
+        use Verdict::*;
+        assert_eq!(verdicts(3, 100 * MS, 50 * MS, &[1, 2, 3]), [Wait, Wait, TimedOut]);
+        // Reaching the run on the deadline still counts
+        assert_eq!(verdicts(3, 100 * MS, 50 * MS, &[1, 1, 1]), [Wait, Wait, Still]);
+        assert_eq!(verdicts(2, Duration::ZERO, 50 * MS, &[1]), [TimedOut]);
+    }
+
+    #[test]
+    fn options_clamp_and_take_query_overrides() {
+        let options = SettleOptions::new(1, 100, 60_000);
+        assert_eq!((options.frames, options.timeout), (2, MAX_TIMEOUT));
+
+        let query: HashMap<String, String> =
+            [("settle_frames", "4"), ("settle_interval_ms", "25")].iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let options = options.with_query(&query).unwrap();
+        assert_eq!((options.frames, options.interval, options.timeout), (4, 25 * MS, MAX_TIMEOUT));
+
+        let query: HashMap<String, String> = [("settle_frames".to_string(), "many".to_string())].into_iter().collect();
+        assert_eq!(options.with_query(&query).unwrap_err(), "invalid settle_frames 'many'");
+    }
+}
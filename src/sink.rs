@@ -0,0 +1,214 @@
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// ============== Frame Sinks ==============
+//
+// Every live frame can also be pushed, as raw concatenated JPEGs, into a
+// spawned command's stdin (--pipe-to-ffmpeg) or a TCP / unix socket
+// (--frame-sink). Each sink has its own writer thread behind a short queue:
+// when the consumer is slow, frames are dropped and counted instead of
+// stalling the capture loop. A sink that dies is respawned / reconnected
+// with backoff.
+
+/// Frames waiting for a slow sink; anything beyond is dropped.
+const QUEUE_CAPACITY: usize = 4;
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// `cmd` run through the platform shell.
+pub fn shell(cmd: &str) -> Command {
+    let (program, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut command = Command::new(program);
+    command.arg(flag).arg(cmd);
+    command
+}
+
+#[derive(Debug, Clone)]
+enum Target {
+    Command(String),
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+impl Target {
+    /// `tcp://HOST:PORT` or `unix:///PATH`.
+    fn parse_socket(s: &str) -> Result<Self, String> {
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            return Ok(Target::Tcp(addr.trim_end_matches('/').to_string()));
+        }
+        #[cfg(unix)]
+        if let Some(path) = s.strip_prefix("unix://") {
+            return Ok(Target::Unix(path.into()));
+        }
+        Err(format!("frame sink must be tcp://HOST:PORT or unix:///PATH, got '{}'", s))
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Target::Command(cmd) => format!("pipe: {}", cmd),
+            Target::Tcp(addr) => format!("tcp://{}", addr),
+            #[cfg(unix)]
+            Target::Unix(path) => format!("unix://{}", path.display()),
+        }
+    }
+
+    fn open(&self) -> std::io::Result<Connection> {
+        match self {
+            Target::Command(cmd) => {
+                let mut child = shell(cmd).stdin(Stdio::piped()).stdout(Stdio::null()).spawn()?;
+                let stdin = child.stdin.take().ok_or_else(|| std::io::Error::other("child has no stdin"))?;
+                Ok(Connection { writer: Box::new(stdin), child: Some(child) })
+            }
+            Target::Tcp(addr) => {
+                let stream = std::net::TcpStream::connect(addr)?;
+                let _ = stream.set_nodelay(true);
+                Ok(Connection { writer: Box::new(stream), child: None })
+            }
+            #[cfg(unix)]
+            Target::Unix(path) => {
+                let stream = std::os::unix::net::UnixStream::connect(path)?;
+                Ok(Connection { writer: Box::new(stream), child: None })
+            }
+        }
+    }
+}
+
+struct Connection {
+    writer: Box<dyn Write + Send>,
+    child: Option<Child>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SinkStats {
+    frames_written: u64,
+    frames_dropped: u64,
+    bytes_written: u64,
+    /// Respawns / reconnects after the first successful open.
+    restarts: u64,
+    connected: bool,
+    connected_at: Option<u128>,
+    last_error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct FrameSink {
+    tx: SyncSender<Vec<u8>>,
+    target: Target,
+    stats: Arc<Mutex<SinkStats>>,
+}
+
+impl FrameSink {
+    /// `--pipe-to-ffmpeg`: frames go to the command's stdin.
+    pub fn pipe(command: &str) -> Self {
+        Self::start(Target::Command(command.to_string()))
+    }
+
+    /// `--frame-sink tcp://HOST:PORT | unix:///PATH`.
+    pub fn socket(url: &str) -> Result<Self, String> {
+        Ok(Self::start(Target::parse_socket(url)?))
+    }
+
+    fn start(target: Target) -> Self {
+        let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let stats: Arc<Mutex<SinkStats>> = Arc::default();
+        let worker_target = target.clone();
+        let worker_stats = stats.clone();
+        thread::spawn(move || run(worker_target, rx, worker_stats));
+        log_info!("Frame sink: {}", target.describe());
+        FrameSink { tx, target, stats }
+    }
+
+    /// Queues a copy of `frame` without ever blocking.
+    pub fn push(&self, frame: &[u8]) {
+        match self.tx.try_send(frame.to_vec()) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                self.stats.lock().unwrap().frames_dropped += 1;
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let stats = self.stats.lock().unwrap();
+        serde_json::json!({
+            "target": self.target.describe(),
+            "connected": stats.connected,
+            "connected_at": stats.connected_at,
+            "frames_written": stats.frames_written,
+            "frames_dropped": stats.frames_dropped,
+            "bytes_written": stats.bytes_written,
+            "restarts": stats.restarts,
+            "last_error": stats.last_error,
+        })
+    }
+}
+
+fn run(target: Target, rx: Receiver<Vec<u8>>, stats: Arc<Mutex<SinkStats>>) {
+    let mut backoff = MIN_BACKOFF;
+    let mut opened_before = false;
+    loop {
+        let mut connection = match target.open() {
+            Ok(connection) => connection,
+            Err(e) => {
+                log_error!("Frame sink {} failed to open: {}", target.describe(), e);
+                stats.lock().unwrap().last_error = Some(e.to_string());
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                continue;
+            }
+        };
+        {
+            let mut s = stats.lock().unwrap();
+            if opened_before {
+                s.restarts += 1;
+            }
+            s.connected = true;
+            s.connected_at = Some(crate::now_millis());
+            // Frames queued while disconnected are stale; start from the next one
+            while rx.try_recv().is_ok() {
+                s.frames_dropped += 1;
+            }
+        }
+        opened_before = true;
+
+        loop {
+            let Ok(frame) = rx.recv() else {
+                // Capture loop is gone
+                return;
+            };
+            match connection.writer.write_all(&frame).and_then(|_| connection.writer.flush()) {
+                Ok(()) => {
+                    let mut s = stats.lock().unwrap();
+                    s.frames_written += 1;
+                    s.bytes_written += frame.len() as u64;
+                    backoff = MIN_BACKOFF;
+                }
+                Err(e) => {
+                    log_error!("Frame sink {} closed: {}", target.describe(), e);
+                    let mut s = stats.lock().unwrap();
+                    s.connected = false;
+                    s.frames_dropped += 1;
+                    s.last_error = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+        drop(connection);
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
@@ -0,0 +1,293 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::handoff::{HandoffState, Scroll};
+use crate::instance::Instance;
+use crate::workspaces::Workspace;
+
+// ============== Warm Standby ==============
+//
+// --snapshot-to DIR makes this instance the primary: every
+// --snapshot-interval-secs it writes DIR/snapshot.json with the GUI tabs and
+// a handoff export of the page (URL, cookies, storage, scroll), but only when
+// something other than the export time changed. Files are written to a
+// temporary name and renamed, so a reader never sees half a snapshot.
+//
+// DIR/owner.json marks who writes there and carries a heartbeat. Starting
+// with a DIR whose owner is another instance with a heartbeat younger than
+// three of its intervals fails; an owner that changes while running (two
+// primaries) is logged as a conflict and this instance stops writing.
+//
+// --follow DIR makes it the standby: it polls snapshot.json and applies what
+// changed since the last snapshot it applied. Different tabs replace the
+// tabs, a different URL, cookies or storage import the state (which loads
+// the page), and a different scroll position is only scrolled to, so an
+// unchanged page is never reloaded. Promotion is pointing the display at
+// the standby; a stale owner heartbeat is logged once as a hint.
+
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const OWNER_FILE: &str = "owner.json";
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// How often --follow checks the snapshot.
+const FOLLOW_POLL: Duration = Duration::from_secs(1);
+
+/// Missed intervals after which an owner no longer counts as running.
+const STALE_INTERVALS: u128 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Owner {
+    pub id: String,
+    pub hostname: String,
+    pub port: u16,
+    pub pid: u32,
+    pub interval_secs: u64,
+    pub heartbeat_at: u128,
+}
+
+impl Owner {
+    pub fn new(instance: &Instance, interval: Duration) -> Self {
+        Owner {
+            id: instance.id.clone(),
+            hostname: instance.hostname.clone(),
+            port: instance.port,
+            pid: std::process::id(),
+            interval_secs: interval.as_secs(),
+            heartbeat_at: crate::now_millis(),
+        }
+    }
+
+    fn age_secs(&self) -> u128 {
+        crate::now_millis().saturating_sub(self.heartbeat_at) / 1000
+    }
+
+    fn is_live(&self) -> bool {
+        self.age_secs() < STALE_INTERVALS * self.interval_secs.max(1) as u128
+    }
+
+    fn describe(&self) -> String {
+        format!("{} on {}:{} (pid {})", self.id, self.hostname, self.port, self.pid)
+    }
+}
+
+/// What a snapshot restores.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contents {
+    pub state: HandoffState,
+    /// GUI tabs; `None` from a headless primary.
+    #[serde(default)]
+    pub tabs: Option<Workspace>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    written_at: u128,
+    /// Id of the writing instance.
+    owner: String,
+    #[serde(flatten)]
+    contents: Contents,
+}
+
+/// What a standby has to do to catch up with a snapshot.
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub tabs: Option<Workspace>,
+    /// The page itself differs; importing it loads the URL.
+    pub state: Option<HandoffState>,
+    /// Same page, only scrolled elsewhere.
+    pub scroll: Option<Scroll>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.tabs.is_none() && self.state.is_none() && self.scroll.is_none()
+    }
+
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(tabs) = &self.tabs {
+            parts.push(format!("{} tabs", tabs.tabs.len()));
+        }
+        if let Some(state) = &self.state {
+            parts.push(format!("page {}", state.url));
+        }
+        if let Some(scroll) = &self.scroll {
+            parts.push(format!("scroll {},{}", scroll.x, scroll.y));
+        }
+        parts.join(", ")
+    }
+}
+
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// Tab URLs and which is active; titles and save times don't count.
+fn same_tabs(a: &Option<Workspace>, b: &Option<Workspace>) -> bool {
+    let key = |w: &Option<Workspace>| w.as_ref().map(|w| (w.tabs.iter().map(|t| t.url.clone()).collect::<Vec<_>>(), w.active));
+    key(a) == key(b)
+}
+
+fn same_page(a: &HandoffState, b: &HandoffState) -> bool {
+    a.url == b.url && same(&a.cookies, &b.cookies) && a.local_storage == b.local_storage && a.session_storage == b.session_storage
+}
+
+fn same_contents(a: &Contents, b: &Contents) -> bool {
+    same_tabs(&a.tabs, &b.tabs) && same_page(&a.state, &b.state) && same(&a.state.scroll, &b.state.scroll)
+}
+
+fn diff(applied: Option<&Contents>, next: &Contents) -> Changes {
+    let Some(applied) = applied else {
+        return Changes { tabs: next.tabs.clone(), state: Some(next.state.clone()), scroll: None };
+    };
+    let mut changes = Changes::default();
+    if !same_tabs(&applied.tabs, &next.tabs) {
+        changes.tabs = next.tabs.clone();
+    }
+    if !same_page(&applied.state, &next.state) {
+        changes.state = Some(next.state.clone());
+    } else if !same(&applied.state.scroll, &next.state.scroll) {
+        changes.scroll = Some(next.state.scroll);
+    }
+    changes
+}
+
+/// Writes `path` through a temporary file in the same directory.
+fn write_atomic<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let body = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, body).map_err(|e| format!("failed to write {}: {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("failed to replace {}: {}", path.display(), e))
+}
+
+fn read_owner(dir: &Path) -> Option<Owner> {
+    let text = std::fs::read_to_string(dir.join(OWNER_FILE)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn load(path: &Path) -> Result<Snapshot, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let snapshot: Snapshot = serde_json::from_str(&text).map_err(|e| format!("invalid snapshot {}: {}", path.display(), e))?;
+    if snapshot.version > SNAPSHOT_VERSION {
+        return Err(format!("snapshot version {} is newer than supported ({})", snapshot.version, SNAPSHOT_VERSION));
+    }
+    snapshot.contents.state.validate()?;
+    Ok(snapshot)
+}
+
+/// Takes `dir` for `owner`, unless another instance is still writing there.
+pub fn claim(dir: &Path, owner: &Owner) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    if let Some(current) = read_owner(dir) {
+        if current.id != owner.id && current.is_live() {
+            return Err(format!(
+                "{} is owned by instance {}, which wrote a heartbeat {}s ago; stop it or use another --snapshot-to directory",
+                dir.display(),
+                current.describe(),
+                current.age_secs()
+            ));
+        }
+        if current.id != owner.id {
+            log_info!("Taking over {} from stale instance {}", dir.display(), current.describe());
+        }
+    }
+    write_atomic(&dir.join(OWNER_FILE), owner)
+}
+
+/// Writes a snapshot from `export` every interval until another instance
+/// takes `dir` over. Call `claim` first.
+pub fn spawn_writer(dir: PathBuf, mut owner: Owner, export: impl Fn() -> Result<Contents, String> + Send + 'static) {
+    let interval = Duration::from_secs(owner.interval_secs.max(1));
+    log_info!("Snapshots:   {} every {}s", dir.display(), interval.as_secs());
+    thread::spawn(move || {
+        let mut written: Option<Contents> = None;
+        let mut last_error: Option<String> = None;
+        loop {
+            thread::sleep(interval);
+            if let Some(current) = read_owner(&dir) {
+                if current.id != owner.id {
+                    log_error!(
+                        "SNAPSHOT CONFLICT: {} is now owned by instance {}; two primaries were writing, this one stopped",
+                        dir.display(),
+                        current.describe()
+                    );
+                    return;
+                }
+            }
+            owner.heartbeat_at = crate::now_millis();
+            let result = write_atomic(&dir.join(OWNER_FILE), &owner).and_then(|_| {
+                let contents = export()?;
+                if written.as_ref().is_some_and(|w| same_contents(w, &contents)) {
+                    return Ok(());
+                }
+                let snapshot =
+                    Snapshot { version: SNAPSHOT_VERSION, written_at: crate::now_millis(), owner: owner.id.clone(), contents };
+                write_atomic(&dir.join(SNAPSHOT_FILE), &snapshot)?;
+                written = Some(snapshot.contents);
+                Ok(())
+            });
+            match result {
+                Ok(()) => last_error = None,
+                Err(e) => {
+                    // Once per distinct error, not every interval
+                    if last_error.as_ref() != Some(&e) {
+                        log_error!("Snapshot failed: {}", e);
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+    });
+}
+
+/// Applies snapshots written to `dir` by another instance as they change.
+/// `apply` failing leaves the last applied snapshot in place, so the next
+/// snapshot is compared against it and retries what is still different.
+pub fn spawn_follower(dir: PathBuf, own_id: String, apply: impl Fn(Changes) -> Result<(), String> + Send + 'static) {
+    log_info!("Following:   {}", dir.display());
+    thread::spawn(move || {
+        let path = dir.join(SNAPSHOT_FILE);
+        let mut seen: Option<SystemTime> = None;
+        let mut applied: Option<Contents> = None;
+        let mut stale_logged = false;
+        loop {
+            thread::sleep(FOLLOW_POLL);
+            if let Some(owner) = read_owner(&dir) {
+                if owner.is_live() {
+                    stale_logged = false;
+                } else if !stale_logged {
+                    log_error!("Snapshot owner {} stopped {}s ago; this standby has its last state", owner.describe(), owner.age_secs());
+                    stale_logged = true;
+                }
+            }
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+            if modified.is_none() || modified == seen {
+                continue;
+            }
+            seen = modified;
+            let snapshot = match load(&path) {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    log_error!("Ignoring snapshot: {}", e);
+                    continue;
+                }
+            };
+            if snapshot.owner == own_id {
+                continue;
+            }
+            let changes = diff(applied.as_ref(), &snapshot.contents);
+            if changes.is_empty() {
+                continue;
+            }
+            log_info!("Following snapshot from {}: {}", snapshot.owner, changes.describe());
+            match apply(changes) {
+                Ok(()) => applied = Some(snapshot.contents),
+                Err(e) => log_error!("Failed to apply snapshot: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,111 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::buffers::BufferStats;
+use crate::commands::{self, PageCommand, Stability};
+use crate::HeadlessState;
+
+// ============== Soak Test ==============
+//
+// --soak MINUTES runs the normal headless pipeline (capture loop, HTTP
+// server, sinks) against the benchmark fixture page while a separate
+// thread keeps every capped buffer busy: navigations and clicks through the
+// command queue (journal, logs), an event-track recording, short-lived
+// annotations and requests to the HTTP server. Every SAMPLE_INTERVAL it
+// samples the RSS of this process and its children (Chrome). The run fails
+// with exit code 4 as soon as RSS exceeds --soak-rss-budget-mb or a buffer
+// holds more than its capacity, and otherwise exits 0 after MINUTES. Both
+// outcomes print a JSON summary.
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+const ACTION_INTERVAL: Duration = Duration::from_millis(500);
+/// Chrome needs a moment after launch before RSS means anything.
+const WARMUP: Duration = Duration::from_secs(30);
+
+pub fn spawn(state: HeadlessState, url: String, duration: Duration, budget_mb: u64, port: u16) {
+    thread::spawn(move || {
+        log_info!("Soak test: {} min, RSS budget {} MB", duration.as_secs() / 60, budget_mb);
+        let budget_kb = budget_mb * 1024;
+        let started = Instant::now();
+        let mut last_sample = started;
+        let mut samples: Vec<u64> = Vec::new();
+        let _ = state.recorder.lock().unwrap().start();
+
+        let mut tick = 0u64;
+        while started.elapsed() < duration {
+            tick += 1;
+            exercise(&state, &url, port, tick);
+            thread::sleep(ACTION_INTERVAL);
+
+            if started.elapsed() < WARMUP || last_sample.elapsed() < SAMPLE_INTERVAL {
+                continue;
+            }
+            last_sample = Instant::now();
+            let rss_kb = crate::resources::process_tree_rss_kb();
+            samples.push(rss_kb);
+            let buffers = buffer_stats(&state);
+            let overfull: Vec<&BufferStats> = buffers.iter().filter(|b| b.len > b.capacity).collect();
+            log_info!("Soak: {} s, RSS {} MB", started.elapsed().as_secs(), rss_kb / 1024);
+            if rss_kb > budget_kb || !overfull.is_empty() {
+                let reason = match overfull.first() {
+                    Some(b) => format!("buffer {} holds {} of {}", b.name, b.len, b.capacity),
+                    None => format!("RSS {} MB is over the {} MB budget", rss_kb / 1024, budget_mb),
+                };
+                finish(&samples, budget_kb, &buffers, started, Some(reason));
+            }
+        }
+        finish(&samples, budget_kb, &buffer_stats(&state), started, None);
+    });
+}
+
+/// One round of work for every capped buffer.
+fn exercise(state: &HeadlessState, url: &str, port: u16, tick: u64) {
+    if tick.is_multiple_of(10) {
+        let _ = commands::send(&state.commands, PageCommand::Navigate { url: url.to_string(), source: "soak" });
+    }
+    let stability = Stability { stable: false, ..Stability::default() };
+    let _ = commands::send(&state.commands, PageCommand::Click { selector: "body".to_string(), frame: None, stability });
+    let annotation = serde_json::json!({"type": "text", "x": 10, "y": 10, "label": format!("soak {}", tick), "ttl_secs": 1});
+    if let Ok(annotation) = serde_json::from_value(annotation) {
+        let _ = state.annotations.lock().unwrap().add(annotation);
+    }
+    let _ = ureq::get(&format!("http://127.0.0.1:{}/stats/buffers", port)).call();
+    log_info!("Soak tick {}", tick);
+}
+
+fn buffer_stats(state: &HeadlessState) -> Vec<BufferStats> {
+    vec![
+        crate::logs::buffer_stats(),
+        crate::request_log::buffer_stats(),
+        state.journal.lock().unwrap().buffer_stats(),
+        state.recorder.lock().unwrap().buffer_stats(),
+        state.annotations.lock().unwrap().buffer_stats(),
+    ]
+}
+
+fn finish(samples: &[u64], budget_kb: u64, buffers: &[BufferStats], started: Instant, failure: Option<String>) -> ! {
+    let summary = serde_json::json!({
+        "passed": failure.is_none(),
+        "failure": failure,
+        "elapsed_secs": started.elapsed().as_secs(),
+        "samples": samples.len(),
+        "rss_kb": {
+            "first": samples.first(),
+            "max": samples.iter().max(),
+            "last": samples.last(),
+            "budget": budget_kb,
+        },
+        "buffers": buffers,
+    });
+    println!("{}", summary);
+    match failure {
+        Some(reason) => {
+            log_error!("Soak test failed: {}", reason);
+            std::process::exit(4);
+        }
+        None => {
+            log_info!("Soak test passed");
+            std::process::exit(0);
+        }
+    }
+}
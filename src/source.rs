@@ -0,0 +1,258 @@
+use std::sync::{Arc, Mutex};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chromiumoxide::cdp::browser_protocol::network::{
+    EnableParams, EventResponseReceived, GetResponseBodyParams, RequestId, ResourceType,
+};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use futures::StreamExt;
+
+use crate::commands::{self, CommandError, CommandResult};
+
+// ============== Page Source ==============
+//
+// GET /source returns the page HTML in one of two forms. `kind=dom` is the
+// live DOM serialised as it is now, after scripts ran. `kind=served` is
+// the document body as the server sent it. Headless reads the served body
+// from the Network domain for the last main-frame document response. GUI
+// mode has no network access to the webview, so it refetches the URL.
+// GET /source/view is an internal viewer with line numbers, syntax
+// highlighting and a DOM/served toggle. It renders in chunks so large
+// documents stay responsive. In the GUI, typing view-source:URL in the
+// URL bar opens it. GET /html is the plain headless variant: just the
+// outerHTML of the document or of one element, as text/html. GET /text is
+// the rendered text instead, with whitespace tidied for feeding elsewhere.
+
+/// Request id of the latest main-frame document response.
+pub type SharedDocument = Arc<Mutex<Option<RequestId>>>;
+
+/// Serialises the live DOM including the doctype.
+pub const DOM_SCRIPT: &str = r#"
+function() {
+    const dt = document.doctype;
+    const doctype = dt
+        ? '<!DOCTYPE ' + dt.name + (dt.publicId ? ' PUBLIC "' + dt.publicId + '"' : '') + (dt.systemId ? ' "' + dt.systemId + '"' : '') + '>\n'
+        : '';
+    return { url: location.href, html: doctype + document.documentElement.outerHTML };
+}
+"#;
+
+/// GET /html: outerHTML of the document element, or of the first match of
+/// a selector (with `>>>` piercing), `null` when nothing matches.
+const OUTER_HTML_SCRIPT: &str = r#"
+function(find, selector) {
+    const el = selector === null ? document.documentElement : find(selector, false);
+    return el ? el.outerHTML : null;
+}
+"#;
+
+/// GET /html[?selector=...] as text/html; no match is a 404.
+pub fn outer_html(tx: &commands::CommandSender, selector: Option<&str>) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let expression = commands::js_call_with_find(OUTER_HTML_SCRIPT, &[selector.into()]);
+    match commands::send(tx, commands::PageCommand::Evaluate { expression, await_promise: false }) {
+        Ok(serde_json::Value::String(html)) => tiny_http::Response::from_string(html)
+            .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]).unwrap()),
+        Ok(_) => crate::json_response(404, &serde_json::json!({"error": format!("no element matches {}", selector.unwrap_or_default())})),
+        Err(e) => crate::json_response(e.status, &serde_json::json!({"error": e.message})),
+    }
+}
+
+/// GET /text: rendered text of the body or of the first match of a
+/// selector. innerText already leaves out scripts, styles and hidden
+/// elements.
+const INNER_TEXT_SCRIPT: &str = r#"
+function(find, selector) {
+    const el = selector === null ? document.body : find(selector, false);
+    return el ? el.innerText : null;
+}
+"#;
+
+/// Trims each line and collapses runs of blank lines into one.
+fn normalize_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            blank = !out.is_empty();
+            continue;
+        }
+        if blank {
+            out.push('\n');
+            blank = false;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// GET /text[?selector=...][&max_length=N] as text/plain; no match is a 404
+/// and a cut response carries X-Truncated: true.
+pub fn visible_text(
+    tx: &commands::CommandSender,
+    selector: Option<&str>,
+    max_length: Option<usize>,
+) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let expression = commands::js_call_with_find(INNER_TEXT_SCRIPT, &[selector.into()]);
+    let text = match commands::send(tx, commands::PageCommand::Evaluate { expression, await_promise: false }) {
+        Ok(serde_json::Value::String(text)) => normalize_text(&text),
+        Ok(_) => {
+            let error = match selector {
+                Some(selector) => format!("no element matches {}", selector),
+                None => "page has no body".to_string(),
+            };
+            return crate::json_response(404, &serde_json::json!({"error": error}));
+        }
+        Err(e) => return crate::json_response(e.status, &serde_json::json!({"error": e.message})),
+    };
+    // Counted in characters so a cut never splits one
+    let cut = max_length.and_then(|max| text.char_indices().nth(max)).map(|(i, _)| i);
+    let mut response = tiny_http::Response::from_string(&text[..cut.unwrap_or(text.len())])
+        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/plain; charset=utf-8"[..]).unwrap());
+    if cut.is_some() {
+        response.add_header(tiny_http::Header::from_bytes(&b"X-Truncated"[..], &b"true"[..]).unwrap());
+    }
+    response
+}
+
+/// Keeps `document` pointed at the latest main-frame document response.
+pub async fn watch_documents(page: &Page, document: SharedDocument) -> Result<tokio::task::JoinHandle<()>, CdpError> {
+    let main_frame = page.mainframe().await?;
+    let mut responses = page.event_listener::<EventResponseReceived>().await?;
+    page.execute(EnableParams::default()).await?;
+    Ok(tokio::spawn(async move {
+        while let Some(event) = responses.next().await {
+            if event.r#type == ResourceType::Document && (main_frame.is_none() || event.frame_id == main_frame) {
+                *document.lock().unwrap() = Some(event.request_id.clone());
+            }
+        }
+    }))
+}
+
+/// `{url, kind, html}` for the streamed page.
+pub async fn capture(page: &Page, document: &SharedDocument, served: bool) -> CommandResult {
+    let dom = commands::evaluate(page, commands::js_call(DOM_SCRIPT, &[]), false).await?;
+    if !served {
+        return Ok(serde_json::json!({"url": dom["url"], "kind": "dom", "html": dom["html"]}));
+    }
+    let request_id = document
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| CommandError::new(404, "no document response recorded yet; reload the page"))?;
+    let body = page
+        .execute(GetResponseBodyParams::new(request_id))
+        .await
+        .map_err(|e| CommandError::new(410, format!("served body is no longer available: {}", e)))?;
+    let html = if body.result.base64_encoded {
+        let bytes = BASE64.decode(&body.result.body).map_err(|e| CommandError::new(500, e.to_string()))?;
+        String::from_utf8_lossy(&bytes).into_owned()
+    } else {
+        body.result.body.clone()
+    };
+    Ok(serde_json::json!({"url": dom["url"], "kind": "served", "html": html}))
+}
+
+/// GUI `kind=served`: a fresh fetch of `url`, which may differ from what
+/// the webview received for personalised pages.
+pub fn refetch(url: &str) -> CommandResult {
+    let response = ureq::get(url)
+        .timeout(std::time::Duration::from_secs(15))
+        .call()
+        .map_err(|e| CommandError::new(502, format!("failed to fetch {}: {}", url, e)))?;
+    let html = response.into_string().map_err(|e| CommandError::new(502, e.to_string()))?;
+    Ok(serde_json::json!({"url": url, "kind": "served", "html": html, "refetched": true}))
+}
+
+pub const VIEWER_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8">
+    <title>Source</title>
+    <style>
+        body { margin: 0; font: 12px/1.5 Menlo, Consolas, monospace; background: #1e1e1e; color: #d4d4d4; }
+        #bar { position: sticky; top: 0; display: flex; gap: 8px; align-items: center; padding: 6px 10px; background: #2d2d2d; border-bottom: 1px solid #444; font-family: sans-serif; }
+        #bar button { background: #3c3c3c; color: #ddd; border: 1px solid #555; border-radius: 4px; padding: 2px 10px; cursor: pointer; }
+        #bar button.on { background: #0e639c; border-color: #1177bb; }
+        #info { color: #999; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }
+        table { border-collapse: collapse; }
+        td.n { color: #858585; text-align: right; padding: 0 12px 0 10px; user-select: none; vertical-align: top; }
+        td.l { white-space: pre-wrap; word-break: break-all; }
+        .t { color: #569cd6; } .a { color: #9cdcfe; } .v { color: #ce9178; } .c { color: #6a9955; }
+    </style>
+</head>
+<body>
+    <div id="bar">
+        <button id="dom" class="on">Live DOM</button>
+        <button id="served">Served HTML</button>
+        <span id="info">Loading...</span>
+    </div>
+    <table><tbody id="src"></tbody></table>
+    <script>
+        const CHUNK = 500;
+        const params = new URLSearchParams(location.search);
+        const tbody = document.getElementById('src');
+        const info = document.getElementById('info');
+        let generation = 0;
+
+        const esc = s => s.replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;');
+        const span = (cls, s) => '<span class="' + cls + '">' + esc(s) + '</span>';
+        function highlight(line) {
+            const re = /(<!--.*?-->)|(<\/?[A-Za-z][\w:-]*)|([\w:-]+)(=)("[^"]*"|'[^']*')|(\/?>)/g;
+            let out = '', last = 0, m;
+            while ((m = re.exec(line))) {
+                out += esc(line.slice(last, m.index));
+                if (m[1]) out += span('c', m[1]);
+                else if (m[2]) out += span('t', m[2]);
+                else if (m[3]) out += span('a', m[3]) + esc(m[4]) + span('v', m[5]);
+                else out += span('t', m[6]);
+                last = re.lastIndex;
+            }
+            return out + esc(line.slice(last));
+        }
+
+        // Appends CHUNK lines per task so huge documents never freeze the tab
+        function render(lines, start, gen) {
+            if (gen !== generation) return;
+            const rows = [];
+            for (let i = start; i < Math.min(start + CHUNK, lines.length); i++) {
+                rows.push('<tr><td class="n">' + (i + 1) + '</td><td class="l">' + highlight(lines[i]) + '</td></tr>');
+            }
+            tbody.insertAdjacentHTML('beforeend', rows.join(''));
+            if (start + CHUNK < lines.length) setTimeout(() => render(lines, start + CHUNK, gen), 0);
+        }
+
+        async function load(kind) {
+            const gen = ++generation;
+            document.getElementById('dom').classList.toggle('on', kind === 'dom');
+            document.getElementById('served').classList.toggle('on', kind === 'served');
+            tbody.innerHTML = '';
+            info.textContent = 'Loading...';
+            const query = new URLSearchParams({ kind: kind });
+            if (params.get('url')) query.set('url', params.get('url'));
+            try {
+                const data = await (await fetch('/source?' + query)).json();
+                if (gen !== generation) return;
+                if (data.error) { info.textContent = data.error; return; }
+                const lines = data.html.split('\n');
+                info.textContent = data.url + ' — ' + lines.length + ' lines' + (data.refetched ? ' (refetched)' : '');
+                render(lines, 0, gen);
+            } catch (e) {
+                info.textContent = 'Error: ' + e.message;
+            }
+        }
+
+        document.getElementById('dom').onclick = () => load('dom');
+        document.getElementById('served').onclick = () => load('served');
+        load(params.get('kind') === 'served' ? 'served' : 'dom');
+    </script>
+</body>
+</html>"#;
+
+pub fn viewer_response() -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    tiny_http::Response::from_string(VIEWER_HTML)
+        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]).unwrap())
+}
@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use wry::dpi::{LogicalPosition, LogicalSize};
+
+// ============== Split View ==============
+//
+// The GUI can show two tabs side by side, each in its own webview. The left
+// pane is always the main webview; "Open in split" from a tab's context menu
+// (or POST /split) puts another tab in a second webview on the right. The
+// focused pane's tab is the active tab, so navigation, reload and the tab
+// shortcuts act on it. Switching to a tab already in a pane focuses that
+// pane; switching to any other tab replaces the focused pane's tab.
+// Closing either pane, or its tab, returns to a single view. The divider is
+// a drag handle drawn at the inner edge of each pane.
+
+const MIN_RATIO: f64 = 0.15;
+const MAX_RATIO: f64 = 0.85;
+/// Gap between the panes in logical pixels.
+const DIVIDER: f64 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pane {
+    Left,
+    Right,
+}
+
+impl Pane {
+    pub fn other(self) -> Pane {
+        match self {
+            Pane::Left => Pane::Right,
+            Pane::Right => Pane::Left,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Split {
+    pub left_tab: usize,
+    pub right_tab: usize,
+    pub focused: Pane,
+    /// Share of the window width given to the left pane.
+    pub ratio: f64,
+}
+
+/// What switching to a tab does while split.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Switch {
+    /// The tab already shows in this pane, which only takes the focus.
+    Focus(Pane),
+    /// The tab replaces the one in this, the focused, pane.
+    Replace(Pane),
+}
+
+/// Where POST /split or "Open in split" puts a tab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// The tab already shows in this pane, which takes the focus.
+    Focus(Pane),
+    /// Not split yet: the tab opens on the right of the active one.
+    Split,
+    /// Split already: the tab replaces the right pane's tab.
+    ReplaceRight,
+    /// Not split and the tab is the active one, so there is nothing to pair it with.
+    AlreadyShown,
+}
+
+pub fn place(split: Option<Split>, active_tab: usize, tab: usize) -> Placement {
+    match split {
+        Some(s) => s.pane_of(tab).map_or(Placement::ReplaceRight, Placement::Focus),
+        None if tab == active_tab => Placement::AlreadyShown,
+        None => Placement::Split,
+    }
+}
+
+impl Split {
+    /// A new split focuses the tab that was just opened on the right.
+    pub fn new(left_tab: usize, right_tab: usize) -> Self {
+        Split { left_tab, right_tab, focused: Pane::Right, ratio: 0.5 }
+    }
+
+    pub fn tab(&self, pane: Pane) -> usize {
+        match pane {
+            Pane::Left => self.left_tab,
+            Pane::Right => self.right_tab,
+        }
+    }
+
+    pub fn set_tab(&mut self, pane: Pane, tab: usize) {
+        match pane {
+            Pane::Left => self.left_tab = tab,
+            Pane::Right => self.right_tab = tab,
+        }
+    }
+
+    pub fn pane_of(&self, tab: usize) -> Option<Pane> {
+        if tab == self.left_tab {
+            Some(Pane::Left)
+        } else if tab == self.right_tab {
+            Some(Pane::Right)
+        } else {
+            None
+        }
+    }
+
+    pub fn switch_to(&self, tab: usize) -> Switch {
+        self.pane_of(tab).map_or(Switch::Replace(self.focused), Switch::Focus)
+    }
+
+    pub fn set_ratio(&mut self, ratio: f64) {
+        self.ratio = ratio.clamp(MIN_RATIO, MAX_RATIO);
+    }
+
+    /// Moves the divider to `x`, given in CSS pixels from the left edge of `pane`.
+    pub fn drag_to(&mut self, width: f64, pane: Pane, x: f64) {
+        let left_width = (width - DIVIDER) * self.ratio;
+        let offset = match pane {
+            Pane::Left => 0.0,
+            Pane::Right => left_width + DIVIDER,
+        };
+        self.set_ratio((offset + x) / width.max(1.0));
+    }
+
+    /// Bounds of the (left, right) panes in a `width` x `height` window.
+    pub fn bounds(&self, width: f64, height: f64) -> (wry::Rect, wry::Rect) {
+        let (left_width, right_x, right_width) = self.columns(width);
+        (rect(0.0, left_width, height), rect(right_x, right_width, height))
+    }
+
+    /// (left pane width, right pane x, right pane width) in a `width` wide window.
+    fn columns(&self, width: f64) -> (f64, f64, f64) {
+        let left_width = ((width - DIVIDER) * self.ratio).round();
+        let right_x = left_width + DIVIDER;
+        (left_width, right_x, (width - right_x).max(0.0))
+    }
+}
+
+fn rect(x: f64, width: f64, height: f64) -> wry::Rect {
+    wry::Rect { position: LogicalPosition::new(x, 0.0).into(), size: LogicalSize::new(width, height).into() }
+}
+
+/// Bounds of the main webview without a split.
+pub fn full(width: f64, height: f64) -> wry::Rect {
+    rect(0.0, width, height)
+}
+
+/// POST /split; every field is optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SplitRequest {
+    /// Tab to show in the right pane, splitting the window if needed.
+    pub tab: Option<usize>,
+    pub focus: Option<Pane>,
+    pub ratio: Option<f64>,
+}
+
+impl SplitRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tab.is_none() && self.focus.is_none() && self.ratio.is_none() {
+            return Err("expected at least one of tab, focus and ratio".to_string());
+        }
+        if let Some(ratio) = self.ratio {
+            if !(MIN_RATIO..=MAX_RATIO).contains(&ratio) {
+                return Err(format!("ratio must be between {} and {}", MIN_RATIO, MAX_RATIO));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Marks a pane's toolbar as focused or not and keeps the divider handle on
+/// its inner edge. Clicking into an unfocused pane focuses it.
+pub const PANE_SCRIPT: &str = r#"
+function(state) {
+    window.__rbPane = state;
+    if (!window.__rbPaneListeners) {
+        window.__rbPaneListeners = true;
+        const claimFocus = function() {
+            if (window.__rbPane.split && !window.__rbPane.focused) {
+                window.__rbPane.focused = true;
+                window.ipc.postMessage(JSON.stringify({paneFocus: true}));
+            }
+        };
+        document.addEventListener('mousedown', claimFocus, true);
+        window.addEventListener('focus', claimFocus);
+    }
+    const apply = function() {
+        const toolbar = document.getElementById('__rust_browser_toolbar__');
+        if (!toolbar && state.split) { setTimeout(apply, 50); return; }
+        if (toolbar) {
+            toolbar.style.boxShadow = state.split && state.focused ? 'inset 0 -3px 0 #4a90d9' : '';
+            toolbar.style.opacity = state.split && !state.focused ? '0.7' : '';
+        }
+        let handle = document.getElementById('__rb_split_handle__');
+        if (!state.split) {
+            if (handle) handle.remove();
+            return;
+        }
+        if (!handle) {
+            handle = document.createElement('div');
+            handle.id = '__rb_split_handle__';
+            handle.style.cssText = 'position:fixed;top:0;bottom:0;width:6px;cursor:col-resize;z-index:2147483647;background:rgba(0,0,0,0.08);';
+            let frame = 0;
+            handle.onpointerdown = function(e) { e.preventDefault(); handle.setPointerCapture(e.pointerId); };
+            handle.onpointermove = function(e) {
+                if (!handle.hasPointerCapture(e.pointerId) || frame) return;
+                const x = e.clientX;
+                frame = requestAnimationFrame(function() {
+                    frame = 0;
+                    window.ipc.postMessage(JSON.stringify({splitResize: x}));
+                });
+            };
+            document.documentElement.appendChild(handle);
+        }
+        handle.style.left = state.pane === 'right' ? '0' : '';
+        handle.style.right = state.pane === 'left' ? '0' : '';
+    };
+    apply();
+}
+"#;
+
+/// The PANE_SCRIPT call for `pane`; without a split it removes the handle.
+pub fn pane_script(split: Option<&Split>, pane: Pane) -> String {
+    let state = serde_json::json!({
+        "pane": pane,
+        "split": split.is_some(),
+        "focused": !matches!(split, Some(s) if s.focused != pane),
+    });
+    crate::commands::js_call(PANE_SCRIPT, &[state])
+}
+
+/// The pane that shortcuts and navigation act on.
+pub fn focused_pane(split: Option<Split>) -> Pane {
+    split.map_or(Pane::Left, |s| s.focused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabs_map_to_panes() {
+        let mut split = Split::new(1, 2);
+        assert_eq!((split.focused, split.ratio), (Pane::Right, 0.5));
+        assert_eq!((split.tab(Pane::Left), split.tab(Pane::Right)), (1, 2));
+        assert_eq!((split.pane_of(1), split.pane_of(2), split.pane_of(3)), (Some(Pane::Left), Some(Pane::Right), None));
+
+        split.set_tab(Pane::Left, 3);
+        assert_eq!((split.pane_of(1), split.pane_of(3)), (None, Some(Pane::Left)));
+        assert_eq!(Pane::Left.other(), Pane::Right);
+        assert_eq!(Pane::Right.other(), Pane::Left);
+    }
+
+    #[test]
+    fn switching_focuses_a_shown_tab_or_replaces_the_focused_one() {
+        let mut split = Split::new(1, 2);
+        assert_eq!(split.switch_to(1), Switch::Focus(Pane::Left));
+        assert_eq!(split.switch_to(2), Switch::Focus(Pane::Right));
+        assert_eq!(split.switch_to(3), Switch::Replace(Pane::Right));
+        split.focused = Pane::Left;
+        assert_eq!(split.switch_to(3), Switch::Replace(Pane::Left));
+    }
+
+    #[test]
+    fn placement_of_a_tab() {
+        assert_eq!(place(None, 1, 2), Placement::Split);
+        assert_eq!(place(None, 1, 1), Placement::AlreadyShown);
+        let split = Some(Split::new(1, 2));
+        assert_eq!(place(split, 2, 1), Placement::Focus(Pane::Left));
+        assert_eq!(place(split, 2, 2), Placement::Focus(Pane::Right));
+        assert_eq!(place(split, 2, 3), Placement::ReplaceRight);
+    }
+
+    #[test]
+    fn ratio_is_clamped_and_follows_the_divider() {
+        let mut split = Split::new(1, 2);
+        split.set_ratio(0.01);
+        assert_eq!(split.ratio, MIN_RATIO);
+        split.set_ratio(2.0);
+        assert_eq!(split.ratio, MAX_RATIO);
+
+        split.set_ratio(0.5);
+        split.drag_to(1000.0, Pane::Left, 300.0);
+        assert_eq!(split.ratio, 0.3);
+        // The right pane starts after the left pane and the divider
+        split.drag_to(1000.0, Pane::Right, 100.0);
+        let expected = ((1000.0 - DIVIDER) * 0.3 + DIVIDER + 100.0) / 1000.0;
+        assert!((split.ratio - expected).abs() < 1e-9, "{}", split.ratio);
+        split.drag_to(1000.0, Pane::Right, 900.0);
+        assert_eq!(split.ratio, MAX_RATIO);
+    }
+
+    #[test]
+    fn columns_leave_room_for_the_divider() {
+        let mut split = Split::new(1, 2);
+        assert_eq!(split.columns(1004.0), (500.0, 504.0, 500.0));
+        split.set_ratio(0.25);
+        assert_eq!(split.columns(1004.0), (250.0, 254.0, 750.0));
+        assert_eq!(split.columns(2.0).2, 0.0);
+    }
+
+    #[test]
+    fn requests_need_a_field_and_a_sane_ratio() {
+        assert!(SplitRequest::default().validate().unwrap_err().contains("at least one"));
+        assert!(SplitRequest { tab: Some(3), ..Default::default() }.validate().is_ok());
+        assert!(SplitRequest { focus: Some(Pane::Left), ..Default::default() }.validate().is_ok());
+        assert!(SplitRequest { ratio: Some(0.5), ..Default::default() }.validate().is_ok());
+        assert!(SplitRequest { ratio: Some(0.9), ..Default::default() }.validate().unwrap_err().contains("between"));
+
+        let request: SplitRequest = serde_json::from_str(r#"{"focus": "right"}"#).unwrap();
+        assert_eq!(request.focus, Some(Pane::Right));
+    }
+
+    #[test]
+    fn pane_script_marks_the_focused_pane() {
+        let split = Split::new(1, 2);
+        assert!(pane_script(Some(&split), Pane::Right).contains(r#"{"focused":true,"pane":"right","split":true}"#));
+        assert!(pane_script(Some(&split), Pane::Left).contains(r#"{"focused":false,"pane":"left","split":true}"#));
+        assert!(pane_script(None, Pane::Left).contains(r#"{"focused":true,"pane":"left","split":false}"#));
+        assert_eq!(focused_pane(Some(split)), Pane::Right);
+        assert_eq!(focused_pane(None), Pane::Left);
+    }
+}
@@ -0,0 +1,302 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use chromiumoxide::Page;
+
+use crate::commands::{self, CommandResult};
+use crate::types::{PageInfo, Size, Status, StatusError};
+use crate::HeadlessState;
+
+// ============== Status ==============
+//
+// GET /status is the one call an orchestration script polls to decide
+// whether the headless browser is healthy: the page as Chrome reports it
+// (actual URL, title, document.readyState), the viewport, the user-agent
+// override, frames captured since start, when the last one was captured,
+// the last screenshot error, the uncaught JavaScript exceptions
+// (js_error_count and last_js_error, see js_errors), the last failed
+// navigation, the emulation in effect (/cpu-throttle, /offline,
+// /media/emulation) and the server uptime. Next to these it carries the
+// state each subsystem reports on its own route: locale, fonts, host rules,
+// blocking and what it reaches in this mode, pipeline timings with the
+// encoder and capture scale, sinks, frame hook, uploads with their lag,
+// lifecycle, login helper and whether an event track is recording.
+// The capture loop keeps the counters; the page part is asked for through
+// the command channel, and when the browser does not answer it is `null`
+// with the reason in `page_error` while the rest still comes back.
+//
+// GUI mode has its own GET /status: the tab summary with the tabs the
+// watchdog marked unresponsive, the screen recording, blocking, host rules,
+// fonts and the pipeline with its encoder.
+//
+// GET /metrics renders the counters among these (frames, sinks, frame hook,
+// uploads, suspensions) in the Prometheus text format.
+
+const PAGE_SCRIPT: &str = "({ title: document.title, ready_state: document.readyState })";
+
+#[derive(Debug)]
+pub struct BrowserStatus {
+    started: Instant,
+    frames: u64,
+    last_frame_at: Option<u128>,
+    last_error: Option<StatusError>,
+    last_navigation_error: Option<StatusError>,
+}
+
+pub type SharedStatus = Arc<Mutex<BrowserStatus>>;
+
+impl Default for BrowserStatus {
+    fn default() -> Self {
+        BrowserStatus { started: Instant::now(), frames: 0, last_frame_at: None, last_error: None, last_navigation_error: None }
+    }
+}
+
+impl BrowserStatus {
+    pub fn frame_captured(&mut self) {
+        self.frames += 1;
+        self.last_frame_at = Some(crate::now_millis());
+    }
+
+    /// Kept after later successes; compare `at` with `last_frame_at`.
+    pub fn capture_failed(&mut self, message: String) {
+        self.last_error = Some(StatusError { at: crate::now_millis(), url: None, message });
+    }
+
+    /// A page.goto that failed, e.g. while /offline is on.
+    pub fn navigation_failed(&mut self, url: &str, message: String) {
+        self.last_navigation_error = Some(StatusError { at: crate::now_millis(), url: Some(url.to_string()), message });
+    }
+}
+
+/// `{url, title, ready_state}` of the streamed page.
+pub async fn page_info(page: &Page) -> CommandResult {
+    let mut info = commands::evaluate(page, PAGE_SCRIPT.to_string(), false).await?;
+    info["url"] = page.url().await?.into();
+    Ok(info)
+}
+
+/// The full /status answer; asks the page for its URL, title and state.
+pub fn report(state: &HeadlessState) -> Status {
+    collect(state, Some(commands::send(&state.commands, commands::PageCommand::PageInfo)))
+}
+
+fn collect(state: &HeadlessState, page: Option<CommandResult>) -> Status {
+    let (page, page_error) = match page {
+        Some(Ok(page)) => match serde_json::from_value::<PageInfo>(page) {
+            Ok(page) => (Some(page), None),
+            Err(e) => (None, Some(format!("unexpected page info: {}", e))),
+        },
+        Some(Err(e)) => (None, Some(e.message)),
+        None => (None, None),
+    };
+    let (js_error_count, last_js_error) = {
+        let errors = state.js_errors.lock().unwrap();
+        (errors.count(), errors.last().and_then(|e| serde_json::to_value(e).ok()))
+    };
+    let mut pipeline = state.pipeline.lock().unwrap().to_json();
+    pipeline["encoder"] = crate::encoder::NAME.into();
+    if let Some(scaler) = &state.scaler {
+        pipeline["capture_scale"] = scaler.lock().unwrap().to_json();
+    }
+    let status = state.status.lock().unwrap();
+    Status {
+        page,
+        page_error,
+        viewport: Size { width: state.viewport.0, height: state.viewport.1 },
+        user_agent_override: state.locale.lock().unwrap().user_agent_override().map(str::to_string),
+        cpu_throttle_rate: state.cpu_throttle.lock().unwrap().rate(),
+        offline: state.offline.lock().unwrap().is_offline(),
+        media_emulation: state.media_emulation.lock().unwrap().to_json(),
+        frames_captured: status.frames,
+        last_frame_at: status.last_frame_at,
+        last_error: status.last_error.clone(),
+        last_navigation_error: status.last_navigation_error.clone(),
+        js_error_count,
+        last_js_error,
+        uptime_secs: status.started.elapsed().as_secs(),
+        recording: state.recorder.lock().unwrap().is_recording(),
+        locale: state.locale.lock().unwrap().to_json(),
+        fonts: state.fonts.to_json(),
+        host_rules: state.host_rules.lock().unwrap().to_json(),
+        blocking: blocking(&state.blocker, false),
+        pipeline,
+        sinks: state.sinks.iter().map(|s| s.to_json()).collect(),
+        frame_hook: state.frame_hook.as_ref().map(|h| h.to_json()),
+        uploads: state.uploader.as_ref().map(|u| u.to_json()),
+        lifecycle: state.lifecycle.lock().unwrap().to_json(),
+        login: state.login.lock().unwrap().to_json(),
+    }
+}
+
+/// Filter list counters without the per-filter hits, and what blocking
+/// reaches in the mode.
+pub fn blocking(blocker: &crate::blocker::SharedBlocker, gui: bool) -> serde_json::Value {
+    let mut blocking = blocker.lock().unwrap().stats();
+    if let Some(stats) = blocking.as_object_mut() {
+        stats.remove("per_filter");
+    }
+    blocking["coverage"] = crate::blocker::coverage(gui);
+    blocking
+}
+
+/// GET /metrics: the counters of /status in the Prometheus text format.
+/// The page is not asked, so a hung browser does not stall the scrape.
+pub fn metrics(state: &HeadlessState) -> String {
+    render_metrics(&collect(state, None))
+}
+
+fn render_metrics(status: &Status) -> String {
+    let mut out = Metrics::default();
+    out.single("frames_captured_total", "counter", "Frames captured from the streamed page.", status.frames_captured as f64);
+    out.single("js_errors_total", "counter", "Uncaught JavaScript exceptions of the streamed page.", status.js_error_count as f64);
+    out.single("uptime_seconds", "gauge", "Seconds since the server started.", status.uptime_secs as f64);
+    out.single("pipeline_fps", "gauge", "Frames per second over the pipeline window.", number(&status.pipeline["fps"]));
+    out.single("lifecycle_suspensions_total", "counter", "Times the idle page was suspended.", number(&status.lifecycle["suspensions"]));
+    out.single("recording", "gauge", "1 while an event track recording runs.", f64::from(u8::from(status.recording)));
+
+    let sinks = [
+        ("sink_connected", "gauge", "1 while the sink's target is connected.", "connected"),
+        ("sink_frames_written_total", "counter", "Frames written to the sink.", "frames_written"),
+        ("sink_frames_dropped_total", "counter", "Frames dropped because the sink's queue was full.", "frames_dropped"),
+        ("sink_bytes_written_total", "counter", "Bytes written to the sink.", "bytes_written"),
+        ("sink_restarts_total", "counter", "Times the sink's target was reopened.", "restarts"),
+    ];
+    for (name, kind, help, field) in sinks {
+        let samples = status.sinks.iter().map(|s| (s["target"].as_str().unwrap_or_default(), number(&s[field]))).collect::<Vec<_>>();
+        out.labelled(name, kind, help, "target", &samples);
+    }
+
+    if let Some(hook) = &status.frame_hook {
+        out.single("frame_hook_runs_total", "counter", "Frame hook runs.", number(&hook["runs"]));
+        out.single("frame_hook_failures_total", "counter", "Frame hook runs that failed.", number(&hook["failures"]));
+        out.single("frame_hook_timeouts_total", "counter", "Frame hook runs that timed out.", number(&hook["timeouts"]));
+    }
+    if let Some(uploads) = &status.uploads {
+        out.single("uploads_total", "counter", "Frames uploaded.", number(&uploads["uploaded"]));
+        out.single("uploads_failed_total", "counter", "Uploads that failed after their retries.", number(&uploads["failed"]));
+        out.single("uploads_dropped_total", "counter", "Uploads dropped because the queue was full.", number(&uploads["dropped"]));
+        out.single("uploads_queued", "gauge", "Uploads waiting in the queue.", number(&uploads["queued"]));
+        out.single("uploads_lag_seconds", "gauge", "Age of the oldest queued upload.", number(&uploads["lag_ms"]) / 1000.0);
+    }
+    out.text
+}
+
+/// Numbers and booleans of a section as a sample value; anything else is 0.
+fn number(value: &serde_json::Value) -> f64 {
+    value.as_f64().or_else(|| value.as_bool().map(|b| f64::from(u8::from(b)))).unwrap_or(0.0)
+}
+
+#[derive(Default)]
+struct Metrics {
+    text: String,
+}
+
+impl Metrics {
+    const PREFIX: &'static str = "browser_";
+
+    fn header(&mut self, name: &str, kind: &str, help: &str) {
+        self.text += &format!("# HELP {}{} {}\n# TYPE {}{} {}\n", Self::PREFIX, name, help, Self::PREFIX, name, kind);
+    }
+
+    fn single(&mut self, name: &str, kind: &str, help: &str, value: f64) {
+        self.header(name, kind, help);
+        self.text += &format!("{}{} {}\n", Self::PREFIX, name, value);
+    }
+
+    fn labelled(&mut self, name: &str, kind: &str, help: &str, label: &str, samples: &[(&str, f64)]) {
+        if samples.is_empty() {
+            return;
+        }
+        self.header(name, kind, help);
+        for (label_value, value) in samples {
+            let escaped = label_value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            self.text += &format!("{}{}{{{}=\"{}\"}} {}\n", Self::PREFIX, name, label, escaped, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(sections: serde_json::Value) -> Status {
+        let mut body = serde_json::json!({
+            "page": null,
+            "page_error": null,
+            "viewport": {"width": 1280, "height": 720},
+            "user_agent_override": null,
+            "cpu_throttle_rate": 1.0,
+            "offline": false,
+            "media_emulation": {},
+            "frames_captured": 42,
+            "last_frame_at": null,
+            "last_error": null,
+            "last_navigation_error": null,
+            "js_error_count": 3,
+            "last_js_error": null,
+            "uptime_secs": 90,
+            "recording": true,
+            "locale": {},
+            "fonts": {},
+            "host_rules": {},
+            "blocking": {},
+            "pipeline": {"fps": 12.5},
+            "sinks": [],
+            "frame_hook": null,
+            "uploads": null,
+            "lifecycle": {"suspensions": 2},
+            "login": {},
+        });
+        for (key, value) in sections.as_object().unwrap() {
+            body[key] = value.clone();
+        }
+        serde_json::from_value(body).unwrap()
+    }
+
+    fn sample<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+        text.lines().find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+    }
+
+    #[test]
+    fn metrics_carry_the_counters() {
+        let text = render_metrics(&status(serde_json::json!({})));
+        assert_eq!(sample(&text, "browser_frames_captured_total"), Some("42"));
+        assert_eq!(sample(&text, "browser_js_errors_total"), Some("3"));
+        assert_eq!(sample(&text, "browser_pipeline_fps"), Some("12.5"));
+        assert_eq!(sample(&text, "browser_lifecycle_suspensions_total"), Some("2"));
+        assert_eq!(sample(&text, "browser_recording"), Some("1"));
+        assert!(text.contains("# TYPE browser_frames_captured_total counter\n"));
+        assert!(text.contains("# TYPE browser_uptime_seconds gauge\n"));
+        // Sections that are off leave their metrics out
+        assert!(!text.contains("browser_sink_"));
+        assert!(!text.contains("browser_frame_hook_"));
+        assert!(!text.contains("browser_uploads_"));
+    }
+
+    #[test]
+    fn metrics_label_each_sink() {
+        let sinks = serde_json::json!({"sinks": [
+            {"target": "tcp://10.0.0.1:9000", "connected": true, "frames_written": 10, "frames_dropped": 1, "bytes_written": 2048, "restarts": 0},
+            {"target": "pipe \"ffmpeg\"", "connected": false, "frames_written": 0, "frames_dropped": 7, "bytes_written": 0, "restarts": 3},
+        ]});
+        let text = render_metrics(&status(sinks));
+        assert_eq!(sample(&text, "browser_sink_frames_written_total{target=\"tcp://10.0.0.1:9000\"}"), Some("10"));
+        assert_eq!(sample(&text, "browser_sink_connected{target=\"tcp://10.0.0.1:9000\"}"), Some("1"));
+        assert_eq!(sample(&text, "browser_sink_frames_dropped_total{target=\"pipe \\\"ffmpeg\\\"\"}"), Some("7"));
+        assert_eq!(sample(&text, "browser_sink_restarts_total{target=\"pipe \\\"ffmpeg\\\"\"}"), Some("3"));
+        assert_eq!(text.matches("# TYPE browser_sink_restarts_total counter").count(), 1);
+    }
+
+    #[test]
+    fn metrics_report_hook_and_upload_lag() {
+        let sections = serde_json::json!({
+            "frame_hook": {"runs": 5, "failures": 1, "timeouts": 2},
+            "uploads": {"uploaded": 8, "failed": 0, "dropped": 1, "queued": 4, "lag_ms": 1500},
+        });
+        let text = render_metrics(&status(sections));
+        assert_eq!(sample(&text, "browser_frame_hook_runs_total"), Some("5"));
+        assert_eq!(sample(&text, "browser_frame_hook_timeouts_total"), Some("2"));
+        assert_eq!(sample(&text, "browser_uploads_queued"), Some("4"));
+        assert_eq!(sample(&text, "browser_uploads_lag_seconds"), Some("1.5"));
+    }
+}
@@ -0,0 +1,61 @@
+use serde::Deserialize;
+
+use crate::commands::{self, CommandError, CommandResult, CommandSender};
+
+// ============== Web Storage ==============
+//
+// /storage/local and /storage/session read and write the current page's
+// localStorage or sessionStorage through Runtime.evaluate, so they always
+// act on the origin that is loaded: GET dumps every key, PUT sets one key
+// and DELETE removes ?key= or, with ?all=true, everything. Each answer names
+// the origin it touched. Pages without storage access (about:blank, a
+// sandboxed or opaque origin) make the page throw, which is a 422.
+
+#[derive(Debug, Deserialize)]
+pub struct PutRequest {
+    pub key: String,
+    pub value: String,
+}
+
+pub enum Action {
+    Get,
+    Put(PutRequest),
+    Delete { key: Option<String>, all: bool },
+}
+
+const STORAGE_SCRIPT: &str = r#"
+function(area, action, key, value) {
+    const storage = area === 'local' ? window.localStorage : window.sessionStorage;
+    const dump = function() {
+        const items = {};
+        for (let i = 0; i < storage.length; i++) {
+            const k = storage.key(i);
+            items[k] = storage.getItem(k);
+        }
+        return items;
+    };
+    if (action === 'put') {
+        storage.setItem(key, value);
+        return { origin: location.origin, key: key };
+    }
+    if (action === 'delete') {
+        const removed = key === null ? storage.length : (storage.getItem(key) === null ? 0 : 1);
+        if (key === null) storage.clear(); else storage.removeItem(key);
+        return { origin: location.origin, removed: removed };
+    }
+    return { origin: location.origin, items: dump() };
+}
+"#;
+
+/// `area` is "local" or "session", from the route.
+pub fn handle(tx: &CommandSender, area: &str, action: Action) -> CommandResult {
+    let args = match action {
+        Action::Get => vec![area.into(), "get".into(), serde_json::Value::Null, serde_json::Value::Null],
+        Action::Put(req) => vec![area.into(), "put".into(), req.key.into(), req.value.into()],
+        Action::Delete { key: Some(key), all: false } => vec![area.into(), "delete".into(), key.into(), serde_json::Value::Null],
+        Action::Delete { key: None, all: true } => vec![area.into(), "delete".into(), serde_json::Value::Null, serde_json::Value::Null],
+        Action::Delete { .. } => return Err(CommandError::new(400, "pass either key or all=true")),
+    };
+    let expression = commands::js_call(STORAGE_SCRIPT, &args);
+    commands::send(tx, commands::PageCommand::Evaluate { expression, await_promise: false })
+}
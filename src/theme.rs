@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use chromiumoxide::cdp::browser_protocol::emulation::SetAutoDarkModeOverrideParams;
+use chromiumoxide::cdp::browser_protocol::page::{
+    AddScriptToEvaluateOnNewDocumentParams, RemoveScriptToEvaluateOnNewDocumentParams, ScriptIdentifier,
+};
+use chromiumoxide::Page;
+use chrono::Timelike;
+use serde::Deserialize;
+
+// ============== Forced Dark Theme ==============
+//
+// Dashboards without a dark theme glare on a wall at night. A page that is
+// dark gets a stylesheet: the first --theme-rules entry whose URL glob
+// matches, or DARK_CSS (the page inverted, media inverted back). In
+// headless mode --dark-auto lets Chrome's auto dark mode
+// (Emulation.setAutoDarkModeOverride) stand in for DARK_CSS; rules still
+// win. Pages are dark during --dark-schedule (local time, e.g. 20:00-07:00)
+// unless POST /theme, or in GUI mode the toolbar toggle, says otherwise; GUI
+// overrides are per tab. The stylesheet is a <style> on <html> that a
+// MutationObserver puts back when an SPA re-render drops it. It is applied
+// on every page load, next to the toolbar in GUI mode and as a new-document
+// script in headless mode.
+
+const STYLE_ID: &str = "__rb_theme__";
+
+const DARK_CSS: &str = "html { filter: invert(0.9) hue-rotate(180deg) !important; background: #fff !important; }\n\
+    img, video, picture, canvas, iframe, embed, object, [style*=\"background-image\"] { filter: invert(1) hue-rotate(180deg) !important; }\n";
+
+const THEME_SCRIPT: &str = r#"
+function(id, rules, fallback) {
+    if (window.__rbTheme) window.__rbTheme.disconnect();
+    window.__rbTheme = null;
+    const old = document.getElementById(id);
+    if (old) old.remove();
+    if (rules === null) return false;
+    let css = fallback;
+    for (const [glob, ruleCss] of rules) {
+        const source = glob.split('*').map(function(part) { return part.replace(/[.+?^${}()|[\]\\]/g, '\\$&'); }).join('.*');
+        if (new RegExp('^' + source + '$').test(location.href)) { css = ruleCss; break; }
+    }
+    if (css === null) return false;
+    function add() {
+        if (!document.documentElement || document.getElementById(id)) return;
+        const style = document.createElement('style');
+        style.id = id;
+        style.textContent = css;
+        document.documentElement.appendChild(style);
+    }
+    add();
+    window.__rbTheme = new MutationObserver(add);
+    window.__rbTheme.observe(document, { childList: true, subtree: true });
+    return true;
+}"#;
+
+/// `HH:MM-HH:MM` in local time; the end may be on the next day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DarkSchedule {
+    start: u32,
+    end: u32,
+}
+
+impl FromStr for DarkSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let minutes = |t: &str| -> Option<u32> {
+            let (h, m) = t.trim().split_once(':')?;
+            let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+            (h < 24 && m < 60).then_some(h * 60 + m)
+        };
+        let (start, end) = s.split_once('-').ok_or_else(|| format!("expected HH:MM-HH:MM, got '{}'", s))?;
+        match (minutes(start), minutes(end)) {
+            (Some(start), Some(end)) if start != end => Ok(DarkSchedule { start, end }),
+            (Some(_), Some(_)) => Err("schedule start and end must differ".to_string()),
+            _ => Err(format!("expected HH:MM-HH:MM, got '{}'", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for DarkSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}-{:02}:{:02}", self.start / 60, self.start % 60, self.end / 60, self.end % 60)
+    }
+}
+
+impl DarkSchedule {
+    fn contains(&self, minute: u32) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleSpec {
+    /// URL glob where `*` matches anything.
+    url: String,
+    css: Option<String>,
+    /// Relative to the rules file.
+    css_file: Option<PathBuf>,
+}
+
+/// Body of POST /theme. `dark: null` (or no `dark`) goes back to the
+/// schedule; `tab` limits the change to one GUI tab.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ThemeRequest {
+    pub dark: Option<bool>,
+    pub tab: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct Theme {
+    /// URL glob → stylesheet.
+    rules: Vec<(String, String)>,
+    schedule: Option<DarkSchedule>,
+    auto_dark: bool,
+    /// POST /theme without a tab.
+    forced: Option<bool>,
+    /// GUI tab → toolbar toggle or POST /theme with a tab.
+    tabs: HashMap<usize, bool>,
+    last_scheduled: bool,
+    /// Headless: what the page has, and the new-document script providing it.
+    applied: Option<bool>,
+    init_script: Option<ScriptIdentifier>,
+}
+
+pub type SharedTheme = Arc<Mutex<Theme>>;
+
+impl Theme {
+    pub fn load(rules: Option<&Path>, schedule: Option<DarkSchedule>, auto_dark: bool) -> Result<Self, String> {
+        let mut theme = Theme { schedule, auto_dark, ..Default::default() };
+        if let Some(path) = rules {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let specs: Vec<RuleSpec> =
+                serde_json::from_str(&text).map_err(|e| format!("Invalid theme rules in {}: {}", path.display(), e))?;
+            let base = path.parent().unwrap_or(Path::new("."));
+            for spec in specs {
+                let css = match (spec.css, spec.css_file) {
+                    (Some(css), None) => css,
+                    (None, Some(file)) => {
+                        let file = base.join(file);
+                        std::fs::read_to_string(&file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?
+                    }
+                    _ => return Err(format!("theme rule for {} needs either css or css_file", spec.url)),
+                };
+                theme.rules.push((spec.url, css));
+            }
+        }
+        theme.last_scheduled = theme.scheduled();
+        Ok(theme)
+    }
+
+    fn scheduled(&self) -> bool {
+        let now = chrono::Local::now();
+        self.schedule.is_some_and(|s| s.contains(now.hour() * 60 + now.minute()))
+    }
+
+    /// Whether `tab` (or the headless page, for `None`) should be dark.
+    pub fn is_dark(&self, tab: Option<usize>) -> bool {
+        tab.and_then(|id| self.tabs.get(&id).copied()).or(self.forced).unwrap_or_else(|| self.scheduled())
+    }
+
+    pub fn set(&mut self, request: &ThemeRequest) {
+        match (request.tab, request.dark) {
+            (Some(tab), Some(dark)) => {
+                self.tabs.insert(tab, dark);
+            }
+            (Some(tab), None) => {
+                self.tabs.remove(&tab);
+            }
+            (None, dark) => {
+                self.tabs.clear();
+                self.forced = dark;
+            }
+        }
+    }
+
+    /// POST /theme override of the schedule, if any.
+    pub fn forced(&self) -> Option<bool> {
+        self.forced
+    }
+
+    /// Toolbar toggle: flips the tab and pins it there.
+    pub fn toggle(&mut self, tab: usize) {
+        let dark = !self.is_dark(Some(tab));
+        self.tabs.insert(tab, dark);
+    }
+
+    pub fn forget_tab(&mut self, tab: usize) {
+        self.tabs.remove(&tab);
+    }
+
+    /// True once each time the schedule crosses a boundary.
+    pub fn schedule_flipped(&mut self) -> bool {
+        let scheduled = self.scheduled();
+        let flipped = scheduled != self.last_scheduled;
+        self.last_scheduled = scheduled;
+        flipped
+    }
+
+    /// Script that puts a page into (or out of) the forced dark theme.
+    pub fn script(&self, dark: bool) -> String {
+        if !dark {
+            return crate::commands::js_call(THEME_SCRIPT, &[STYLE_ID.into(), serde_json::Value::Null, serde_json::Value::Null]);
+        }
+        let rules: Vec<[&str; 2]> = self.rules.iter().map(|(url, css)| [url.as_str(), css.as_str()]).collect();
+        let fallback = if self.auto_dark { serde_json::Value::Null } else { DARK_CSS.into() };
+        crate::commands::js_call(THEME_SCRIPT, &[STYLE_ID.into(), serde_json::json!(rules), fallback])
+    }
+
+    pub fn to_json(&self, tab: Option<usize>) -> serde_json::Value {
+        let tabs: HashMap<String, bool> = self.tabs.iter().map(|(id, dark)| (id.to_string(), *dark)).collect();
+        serde_json::json!({
+            "dark": self.is_dark(tab),
+            "schedule": self.schedule.map(|s| s.to_string()),
+            "scheduled": self.scheduled(),
+            "forced": self.forced,
+            "tabs": tabs,
+            "auto_dark": self.auto_dark,
+            "rules": self.rules.iter().map(|(url, _)| url.as_str()).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Headless: brings the streamed page in line with the theme state.
+pub async fn sync(page: &Page, theme: &SharedTheme) {
+    let (dark, script, auto_dark, previous) = {
+        let mut state = theme.lock().unwrap();
+        let dark = state.is_dark(None);
+        if state.applied == Some(dark) {
+            return;
+        }
+        (dark, state.script(dark), state.auto_dark, state.init_script.take())
+    };
+    if let Some(identifier) = previous {
+        let _ = page.execute(RemoveScriptToEvaluateOnNewDocumentParams::new(identifier)).await;
+    }
+    let mut identifier = None;
+    if dark {
+        match page.execute(AddScriptToEvaluateOnNewDocumentParams::new(script.clone())).await {
+            Ok(added) => identifier = Some(added.result.identifier),
+            Err(e) => log_error!("Failed to install the dark theme: {}", e),
+        }
+    }
+    if let Err(e) = page.evaluate(script).await {
+        log_error!("Failed to apply the dark theme: {}", e);
+    }
+    if auto_dark {
+        if let Err(e) = page.execute(SetAutoDarkModeOverrideParams { enabled: Some(dark) }).await {
+            log_error!("Failed to set auto dark mode: {}", e);
+        }
+    }
+    log_info!("Theme: {}", if dark { "dark" } else { "page default" });
+    let mut state = theme.lock().unwrap();
+    state.applied = Some(dark);
+    state.init_script = identifier;
+}
+
+/// GET/POST /theme. GET reports on `active_tab` in GUI mode; the flag is
+/// true when a POST changed the theme and the pages need the new script.
+pub fn handle(request: &mut tiny_http::Request, theme: &SharedTheme, active_tab: Option<usize>) -> (tiny_http::Response<std::io::Cursor<Vec<u8>>>, bool) {
+    let method = request.method().clone();
+    match method {
+        tiny_http::Method::Get => (crate::json_response(200, &theme.lock().unwrap().to_json(active_tab)), false),
+        tiny_http::Method::Post => match crate::read_json_body::<ThemeRequest>(request) {
+            Ok(req) => {
+                let mut theme = theme.lock().unwrap();
+                theme.set(&req);
+                (crate::json_response(200, &theme.to_json(req.tab.or(active_tab))), true)
+            }
+            Err(e) => (crate::json_response(400, &serde_json::json!({"error": e})), false),
+        },
+        _ => (crate::json_response(405, &serde_json::json!({"error": "method not allowed"})), false),
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use image::RgbImage;
+use serde::Deserialize;
+
+use crate::overlay::{self, Color};
+
+// ============== Ticker Banner ==============
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum, Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    Top,
+    Bottom,
+}
+
+pub struct Ticker {
+    position: Position,
+    fg: Color,
+    bg: Color,
+    url: Option<String>,
+    /// Last text returned by the ticker URL.
+    fetched: Option<String>,
+    /// Set when the most recent poll failed; the last good text is kept.
+    stale: bool,
+    last_error: Option<String>,
+    last_fetch_at: Option<u128>,
+    /// Text set through POST /ticker, shown instead of the fetched text.
+    manual: Option<String>,
+    manual_expires_at: Option<u128>,
+}
+
+pub type SharedTicker = Arc<Mutex<Ticker>>;
+
+#[derive(Debug, Deserialize, serde::Serialize)]
+pub struct TickerRequest {
+    pub text: String,
+    pub ttl: Option<u64>,
+}
+
+impl Ticker {
+    pub fn new(url: Option<String>, position: Position, fg: Color, bg: Color) -> Self {
+        Ticker {
+            position,
+            fg,
+            bg,
+            url,
+            fetched: None,
+            stale: false,
+            last_error: None,
+            last_fetch_at: None,
+            manual: None,
+            manual_expires_at: None,
+        }
+    }
+
+    pub fn set_manual(&mut self, req: TickerRequest) {
+        self.manual = Some(req.text);
+        self.manual_expires_at = req.ttl.map(|ttl| crate::now_millis() + ttl as u128 * 1000);
+    }
+
+    /// Text set through POST /ticker and its remaining lifetime in seconds.
+    pub fn manual(&mut self) -> Option<TickerRequest> {
+        self.text();
+        let text = self.manual.clone()?;
+        let ttl = self.manual_expires_at.map(|exp| (exp.saturating_sub(crate::now_millis()) / 1000) as u64);
+        Some(TickerRequest { text, ttl })
+    }
+
+    pub fn clear_manual(&mut self) {
+        self.manual = None;
+        self.manual_expires_at = None;
+    }
+
+    /// The banner text to draw, or `None` when the banner is hidden.
+    fn text(&mut self) -> Option<String> {
+        if self.manual_expires_at.is_some_and(|exp| exp <= crate::now_millis()) {
+            self.clear_manual();
+        }
+        if let Some(text) = self.manual.as_ref().filter(|t| !t.is_empty()) {
+            return Some(text.clone());
+        }
+        let text = self.fetched.as_ref().filter(|t| !t.is_empty())?;
+        Some(if self.stale { format!("{} [stale]", text) } else { text.clone() })
+    }
+
+    pub fn is_active(&mut self) -> bool {
+        self.text().is_some()
+    }
+
+    pub fn render(&mut self, img: &mut RgbImage) {
+        let Some(text) = self.text() else {
+            return;
+        };
+        let scale = if img.width() >= 1000 { 2 } else { 1 };
+        let pad = 6 * scale;
+        let height = overlay::GLYPH_HEIGHT * scale + 2 * pad;
+        let max_chars = ((img.width() as i64 - 2 * pad) / (overlay::GLYPH_WIDTH * scale)).max(0) as usize;
+        let text: String = if text.chars().count() > max_chars {
+            text.chars().take(max_chars.saturating_sub(3)).chain("...".chars()).collect()
+        } else {
+            text
+        };
+        let y = match self.position {
+            Position::Top => 0,
+            Position::Bottom => img.height() as i64 - height,
+        };
+        overlay::fill_rect(img, 0, y, img.width() as i64, height, self.bg);
+        overlay::draw_text(img, pad, y + pad, &text, scale, self.fg);
+    }
+
+    fn apply_fetch(&mut self, result: Result<String, String>) {
+        self.last_fetch_at = Some(crate::now_millis());
+        match result {
+            Ok(text) => {
+                if self.stale {
+                    log_info!("Ticker URL recovered");
+                }
+                self.fetched = Some(text);
+                self.stale = false;
+                self.last_error = None;
+            }
+            Err(e) => {
+                // Log only the first failure of a streak to keep stderr readable
+                if !self.stale {
+                    log_error!("Ticker fetch failed, keeping last message: {}", e);
+                }
+                self.stale = self.fetched.is_some();
+                self.last_error = Some(e);
+            }
+        }
+    }
+
+    pub fn report(&mut self) -> serde_json::Value {
+        let text = self.text();
+        serde_json::json!({
+            "visible": text.is_some(),
+            "text": text,
+            "source": if self.manual.is_some() { "api" } else { "url" },
+            "url": self.url,
+            "stale": self.stale,
+            "last_error": self.last_error,
+            "last_fetch_at": self.last_fetch_at,
+            "manual_expires_at": self.manual_expires_at,
+        })
+    }
+}
+
+/// Polls the ticker URL on its own thread so a slow or failing endpoint can
+/// never delay frame delivery.
+pub fn spawn_poller(ticker: SharedTicker, url: String, interval: Duration) {
+    thread::spawn(move || loop {
+        let result = fetch_text(&url);
+        ticker.lock().unwrap().apply_fetch(result);
+        thread::sleep(interval);
+    });
+}
+
+/// Accepts either plain text or a JSON object with a "text" field.
+fn fetch_text(url: &str) -> Result<String, String> {
+    let body = ureq::get(url)
+        .timeout(Duration::from_secs(10))
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    let text = match serde_json::from_str::<serde_json::Value>(&body) {
+        Ok(serde_json::Value::Object(obj)) => obj.get("text").and_then(|t| t.as_str()).unwrap_or_default().to_string(),
+        _ => body,
+    };
+    Ok(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Serves `/ticker` for either HTTP server.
+pub fn handle(request: &mut tiny_http::Request, ticker: &SharedTicker) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let method = request.method().clone();
+    match method {
+        tiny_http::Method::Get => crate::json_response(200, &ticker.lock().unwrap().report()),
+        tiny_http::Method::Post => match crate::read_json_body::<TickerRequest>(request) {
+            Ok(req) => {
+                let mut ticker = ticker.lock().unwrap();
+                ticker.set_manual(req);
+                crate::json_response(200, &ticker.report())
+            }
+            Err(e) => crate::json_response(400, &serde_json::json!({"error": e})),
+        },
+        tiny_http::Method::Delete => {
+            let mut ticker = ticker.lock().unwrap();
+            ticker.clear_manual();
+            crate::json_response(200, &ticker.report())
+        }
+        _ => crate::json_response(405, &serde_json::json!({"error": "method not allowed"})),
+    }
+}
@@ -0,0 +1,196 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chromiumoxide::cdp::browser_protocol::tracing::{
+    EndParams, EventDataCollected, EventTracingComplete, StartParams, StartTransferMode, TraceConfig,
+};
+use chromiumoxide::Page;
+use futures::StreamExt;
+use serde::Deserialize;
+
+use crate::commands::{CommandError, CommandResult};
+
+// ============== Chrome Tracing (headless) ==============
+//
+// POST /trace/start runs Tracing.start on the streamed page with
+// DEFAULT_CATEGORIES (the devtools Performance panel's set) or the given
+// `categories`; Chrome streams the events back in Tracing.dataCollected
+// chunks, which are buffered until POST /trace/stop ends the trace and
+// answers with the complete trace JSON, loadable in the devtools Performance
+// panel or Perfetto. With `{"path": "..."}` the trace is written to that
+// file instead and the answer carries the path; the file is created before
+// the trace ends, so an existing file (never overwritten) or an unwritable
+// path is refused while the trace keeps running. Only one trace runs at a time, a second start is a 409.
+// Beyond MAX_EVENTS events are dropped and counted in the metadata.
+
+const DEFAULT_CATEGORIES: [&str; 10] = [
+    "devtools.timeline",
+    "v8.execute",
+    "disabled-by-default-devtools.timeline",
+    "disabled-by-default-devtools.timeline.frame",
+    "disabled-by-default-devtools.timeline.stack",
+    "disabled-by-default-v8.cpu_profiler",
+    "toplevel",
+    "blink.console",
+    "blink.user_timing",
+    "latencyInfo",
+];
+
+const MAX_EVENTS: usize = 2_000_000;
+/// How long Chrome gets to flush the buffered events after Tracing.end;
+/// within the 30 s a page command may take.
+const COMPLETE_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct StartRequest {
+    pub categories: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct StopRequest {
+    pub path: Option<PathBuf>,
+}
+
+struct Active {
+    started_at: u128,
+    categories: Vec<String>,
+    /// Collects the chunks until Tracing.tracingComplete; yields the events
+    /// and how many were dropped.
+    collector: tokio::task::JoinHandle<(Vec<serde_json::Value>, u64)>,
+}
+
+#[derive(Default)]
+pub struct Tracer {
+    active: Option<Active>,
+}
+
+pub type SharedTracer = Arc<Mutex<Tracer>>;
+
+/// `categories` with blanks dropped, or DEFAULT_CATEGORIES when not given.
+fn categories(requested: Option<Vec<String>>) -> Result<Vec<String>, CommandError> {
+    let Some(requested) = requested else {
+        return Ok(DEFAULT_CATEGORIES.iter().map(|c| c.to_string()).collect());
+    };
+    let categories: Vec<String> = requested.into_iter().map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect();
+    if categories.is_empty() {
+        return Err(CommandError::new(400, "categories must not be empty"));
+    }
+    Ok(categories)
+}
+
+pub async fn start(page: &Page, tracer: &SharedTracer, requested: Option<Vec<String>>) -> CommandResult {
+    if tracer.lock().unwrap().active.is_some() {
+        return Err(CommandError::new(409, "a trace is already running"));
+    }
+    let categories = categories(requested)?;
+    let mut chunks = page.event_listener::<EventDataCollected>().await?;
+    let mut complete = page.event_listener::<EventTracingComplete>().await?;
+    let collector = tokio::spawn(async move {
+        let mut events = Vec::new();
+        let mut dropped = 0;
+        loop {
+            tokio::select! {
+                // Chunks sent before tracingComplete must not lose the race to it
+                biased;
+                Some(chunk) = chunks.next() => {
+                    for event in chunk.value.iter() {
+                        if events.len() < MAX_EVENTS {
+                            events.push(event.clone());
+                        } else {
+                            dropped += 1;
+                        }
+                    }
+                }
+                Some(_) = complete.next() => break,
+                else => break,
+            }
+        }
+        (events, dropped)
+    });
+    let config = TraceConfig::builder().included_categories(categories.clone()).build();
+    let params = StartParams::builder().transfer_mode(StartTransferMode::ReportEvents).trace_config(config).build();
+    if let Err(e) = page.execute(params).await {
+        collector.abort();
+        return Err(e.into());
+    }
+    let started_at = crate::now_millis();
+    log_info!("Trace started: {}", categories.join(","));
+    let json = serde_json::json!({ "started_at": started_at, "categories": categories });
+    tracer.lock().unwrap().active = Some(Active { started_at, categories, collector });
+    Ok(json)
+}
+
+/// Ends the running trace and returns it in the devtools trace format.
+pub async fn stop(page: &Page, tracer: &SharedTracer) -> CommandResult {
+    let active = tracer.lock().unwrap().active.take().ok_or_else(|| CommandError::new(409, "no trace is running"))?;
+    let mut collector = active.collector;
+    if let Err(e) = page.execute(EndParams::default()).await {
+        collector.abort();
+        return Err(e.into());
+    }
+    let (events, dropped) = match tokio::time::timeout(COMPLETE_TIMEOUT, &mut collector).await {
+        Ok(Ok(collected)) => collected,
+        Ok(Err(e)) => return Err(CommandError::new(500, format!("trace collector failed: {}", e))),
+        Err(_) => {
+            collector.abort();
+            return Err(CommandError::new(504, "Chrome did not finish the trace in time"));
+        }
+    };
+    log_info!("Trace stopped: {} events", events.len());
+    Ok(serde_json::json!({
+        "traceEvents": events,
+        "metadata": {
+            "url": page.url().await?,
+            "started_at": active.started_at,
+            "stopped_at": crate::now_millis(),
+            "categories": active.categories,
+            "dropped_events": dropped,
+        },
+    }))
+}
+
+/// Creates `path`, which must not exist yet, before the trace is stopped so
+/// a bad path does not cost the recorded events.
+pub fn create(path: &Path) -> Result<std::fs::File, CommandError> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| CommandError::new(400, format!("cannot create {}: {}", path.display(), e)))
+}
+
+/// Writes `trace` to `file`, made by `create` at `path`.
+pub fn save(mut file: std::fs::File, path: &Path, trace: &serde_json::Value) -> Result<serde_json::Value, CommandError> {
+    let bytes = serde_json::to_vec(trace).unwrap_or_default();
+    file.write_all(&bytes).map_err(|e| CommandError::new(500, format!("failed to write {}: {}", path.display(), e)))?;
+    let events = trace["traceEvents"].as_array().map_or(0, Vec::len);
+    Ok(serde_json::json!({ "path": path, "events": events, "bytes": bytes.len() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_existing_files_before_saving() {
+        let dir = std::env::temp_dir().join(format!("rb-trace-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("trace.json");
+        let _ = std::fs::remove_file(&path);
+
+        let trace = serde_json::json!({"traceEvents": [{"name": "a"}, {"name": "b"}], "metadata": {}});
+        let saved = save(create(&path).unwrap(), &path, &trace).unwrap();
+        assert_eq!(saved["events"], 2);
+        let written: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(written, trace);
+
+        let error = create(&path).err().unwrap();
+        assert_eq!(error.status, 400);
+        assert!(create(&dir.join("missing").join("trace.json")).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
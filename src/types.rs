@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+// ============== API Types ==============
+//
+// Request and response bodies of the HTTP API that both the server and
+// `client::BrowserClient` use, so the two can't drift apart. Requests
+// deserialize the way the handlers always did (every field but the
+// essential one defaulted) and serialize back to the same shape; the
+// screenshot options travel as a query string, written by `to_query` and
+// read by `from_query` here.
+
+pub(crate) const DEFAULT_STABLE_TIMEOUT_MS: u64 = 2000;
+
+/// POST /navigate body; GET /navigate?url=...&locale=... is the same request
+/// without `wait_until`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NavigateRequest {
+    pub url: String,
+    /// Locale profile tag, applied with the navigation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub wait_until: WaitUntil,
+}
+
+/// Query parameters GET /navigate takes besides the target; `token` and
+/// `sig` are for auth and never part of the URL.
+const NAVIGATE_PARAMS: [&str; 4] = ["url", "locale", "token", "sig"];
+
+impl NavigateRequest {
+    /// GET /navigate?url=...&locale=... The target must be URL-encoded: any
+    /// other parameter is taken for a piece of an unencoded URL and refused.
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, String> {
+        if let Some(other) = query.keys().find(|k| !NAVIGATE_PARAMS.contains(&k.as_str())) {
+            return Err(format!("unexpected parameter '{}'; URL-encode the url parameter", other));
+        }
+        let url = query.get("url").ok_or("missing url parameter")?.clone();
+        Ok(NavigateRequest { url, locale: query.get("locale").cloned(), wait_until: WaitUntil::None })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WaitUntil {
+    /// Queue the URL for the capture loop and answer right away.
+    #[default]
+    None,
+    /// Answer once the page has loaded, or failed to.
+    Load,
+}
+
+/// How click/type/hover wait for their element. With `stable` (the default)
+/// the element must keep the same box for a few samples and must be the
+/// topmost element at its centre; otherwise it is used as soon as it exists.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Stability {
+    #[serde(default = "default_stable")]
+    pub stable: bool,
+    #[serde(default = "default_stable_timeout")]
+    pub timeout_ms: u64,
+}
+
+fn default_stable() -> bool {
+    true
+}
+
+fn default_stable_timeout() -> u64 {
+    DEFAULT_STABLE_TIMEOUT_MS
+}
+
+impl Default for Stability {
+    fn default() -> Self {
+        Stability { stable: true, timeout_ms: DEFAULT_STABLE_TIMEOUT_MS }
+    }
+}
+
+/// How /type enters text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TypeMethod {
+    /// Keys when every character is on a plain keyboard, insert otherwise.
+    #[default]
+    Auto,
+    /// One keydown/keypress/input/keyup per character, like a user at a US
+    /// keyboard; a newline presses Enter.
+    Keys,
+    /// Input.insertText, like an IME committing its text: a single
+    /// beforeinput and input with inputType "insertText" and no key or
+    /// composition events. Needed for CJK, emoji and RTL scripts.
+    Insert,
+}
+
+/// Mouse button for a /click at coordinates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClickButton {
+    #[default]
+    Left,
+    Middle,
+    Right,
+}
+
+fn default_click_count() -> u32 {
+    1
+}
+
+/// Body of POST /click, /hover and /type (`text`, `submit` and `method` only
+/// for /type). /click and /hover take either a `selector` or `x` and `y`,
+/// /click with optional `button` and `clickCount`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractRequest {
+    #[serde(default)]
+    pub selector: String,
+    /// Search open shadow roots for `selector`, like a leading `>>>`.
+    #[serde(default)]
+    pub pierce: bool,
+    /// Frame id, name or URL glob to resolve `selector` in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<String>,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub submit: bool,
+    #[serde(default)]
+    pub method: TypeMethod,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<f64>,
+    #[serde(default)]
+    pub button: ClickButton,
+    #[serde(default = "default_click_count", rename = "clickCount")]
+    pub click_count: u32,
+    #[serde(flatten)]
+    pub stability: Stability,
+}
+
+/// The same defaults as an empty JSON body.
+impl Default for InteractRequest {
+    fn default() -> Self {
+        InteractRequest {
+            selector: String::new(),
+            pierce: false,
+            frame: None,
+            text: String::new(),
+            submit: false,
+            method: TypeMethod::Auto,
+            x: None,
+            y: None,
+            button: ClickButton::Left,
+            click_count: default_click_count(),
+            stability: Stability::default(),
+        }
+    }
+}
+
+impl InteractRequest {
+    /// `selector` in FIND_SCRIPT syntax.
+    pub fn target_selector(&self) -> String {
+        if self.pierce && !self.selector.trim_start().starts_with(">>>") {
+            format!(">>> {}", self.selector)
+        } else {
+            self.selector.clone()
+        }
+    }
+}
+
+/// Body of POST /evaluate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluateRequest {
+    pub expression: String,
+    #[serde(default, rename = "awaitPromise", alias = "await_promise")]
+    pub await_promise: bool,
+}
+
+/// Answer of POST /evaluate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluateResponse {
+    pub result: serde_json::Value,
+}
+
+/// A capture rectangle in page CSS pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clip {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Clip {
+    fn from_parts(x: &str, y: &str, width: &str, height: &str) -> Result<Self, String> {
+        let number = |name: &str, v: &str| v.trim().parse::<f64>().ok().filter(|n| n.is_finite()).ok_or(format!("{} must be a number, got '{}'", name, v));
+        let clip = Clip { x: number("x", x)?, y: number("y", y)?, width: number("width", width)?, height: number("height", height)? };
+        if clip.width <= 0.0 || clip.height <= 0.0 {
+            return Err("clip width and height must be positive".to_string());
+        }
+        Ok(clip)
+    }
+
+    /// All of x, y, width and height, or none of them.
+    fn from_query(query: &HashMap<String, String>) -> Result<Option<Self>, String> {
+        let parts = ["x", "y", "width", "height"].map(|name| query.get(name));
+        match parts {
+            [Some(x), Some(y), Some(width), Some(height)] => Clip::from_parts(x, y, width, height).map(Some),
+            [None, None, None, None] => Ok(None),
+            _ => Err("a clip needs all of x, y, width and height".to_string()),
+        }
+    }
+
+    /// 400-style error unless the rectangle overlaps `width` x `height`.
+    pub fn check_within(&self, width: f64, height: f64) -> Result<(), String> {
+        if self.x < width && self.y < height && self.x + self.width > 0.0 && self.y + self.height > 0.0 {
+            return Ok(());
+        }
+        Err(format!("clip {},{} {}x{} lies outside the page, which is 0,0 {}x{}", self.x, self.y, self.width, self.height, width, height))
+    }
+}
+
+/// --clip X,Y,W,H
+impl std::str::FromStr for Clip {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split(',').collect::<Vec<_>>()[..] {
+            [x, y, width, height] => Clip::from_parts(x, y, width, height),
+            _ => Err(format!("invalid clip '{}', expected X,Y,WIDTH,HEIGHT", s)),
+        }
+    }
+}
+
+/// GET /screenshot options: at most one of `selector`, `full_page` and
+/// `clip`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenshotRequest {
+    pub selector: Option<String>,
+    pub full_page: bool,
+    pub clip: Option<Clip>,
+    pub png: bool,
+    pub quality: u8,
+}
+
+/// The viewport as JPEG at the stream's quality.
+impl Default for ScreenshotRequest {
+    fn default() -> Self {
+        ScreenshotRequest { selector: None, full_page: false, clip: None, png: false, quality: crate::JPEG_QUALITY }
+    }
+}
+
+impl ScreenshotRequest {
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, String> {
+        let png = match query.get("format").map(String::as_str) {
+            None | Some("jpeg") | Some("jpg") => false,
+            Some("png") => true,
+            Some(other) => return Err(format!("unknown format '{}', expected png or jpeg", other)),
+        };
+        let quality = match query.get("quality") {
+            None => crate::JPEG_QUALITY,
+            Some(_) if png => return Err("quality only applies to jpeg".to_string()),
+            Some(v) => v.parse::<u8>().ok().filter(|q| *q <= 100).ok_or("quality must be 0..100")?,
+        };
+        let selector = query.get("selector").filter(|s| !s.trim().is_empty()).cloned();
+        let full_page = query.get("fullpage").is_some_and(|v| v == "true" || v == "1");
+        let clip = Clip::from_query(query)?;
+        if [selector.is_some(), full_page, clip.is_some()].iter().filter(|set| **set).count() > 1 {
+            return Err("selector, fullpage and x/y/width/height can't be combined".to_string());
+        }
+        Ok(ScreenshotRequest { selector, full_page, clip, png, quality })
+    }
+
+    /// The query string `from_query` reads back, without the leading `?`.
+    pub fn to_query(&self) -> String {
+        let mut pairs = vec![("format", if self.png { "png".to_string() } else { "jpeg".to_string() })];
+        if !self.png {
+            pairs.push(("quality", self.quality.to_string()));
+        }
+        if let Some(selector) = &self.selector {
+            pairs.push(("selector", selector.clone()));
+        }
+        if self.full_page {
+            pairs.push(("fullpage", "true".to_string()));
+        }
+        if let Some(clip) = self.clip {
+            pairs.extend([("x", clip.x), ("y", clip.y), ("width", clip.width), ("height", clip.height)].map(|(k, v)| (k, v.to_string())));
+        }
+        pairs.iter().map(|(k, v)| format!("{}={}", k, urlencoding::encode(v))).collect::<Vec<_>>().join("&")
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        if self.png { "image/png" } else { "image/jpeg" }
+    }
+}
+
+/// `{url, title, ready_state}` of the streamed page as Chrome reports it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageInfo {
+    pub url: Option<String>,
+    pub title: String,
+    pub ready_state: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A failure remembered for /status; compare `at` with `last_frame_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusError {
+    pub at: u128,
+    /// Set for failed navigations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub message: String,
+}
+
+/// Answer of GET /status.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    /// `None` with the reason in `page_error` when the browser didn't answer.
+    pub page: Option<PageInfo>,
+    pub page_error: Option<String>,
+    pub viewport: Size,
+    pub user_agent_override: Option<String>,
+    pub cpu_throttle_rate: f64,
+    pub offline: bool,
+    pub media_emulation: serde_json::Value,
+    pub frames_captured: u64,
+    pub last_frame_at: Option<u128>,
+    pub last_error: Option<StatusError>,
+    pub last_navigation_error: Option<StatusError>,
+    pub js_error_count: u64,
+    pub last_js_error: Option<serde_json::Value>,
+    pub uptime_secs: u64,
+    /// An event track recording (/record/start) is running.
+    pub recording: bool,
+    /// As GET /locale reports it.
+    pub locale: serde_json::Value,
+    /// As GET /fonts reports it.
+    pub fonts: serde_json::Value,
+    /// As GET /host-rules reports it.
+    pub host_rules: serde_json::Value,
+    /// Filter list counters without the per-filter hits, and what blocking
+    /// reaches in this mode (`coverage`).
+    pub blocking: serde_json::Value,
+    /// GET /stats/pipeline plus the JPEG `encoder` and `capture_scale`.
+    pub pipeline: serde_json::Value,
+    /// As GET /sinks reports them.
+    pub sinks: Vec<serde_json::Value>,
+    /// `None` without --frame-hook.
+    pub frame_hook: Option<serde_json::Value>,
+    /// GET /uploads, `lag_ms` included; `None` without --upload-url.
+    pub uploads: Option<serde_json::Value>,
+    /// As GET /lifecycle reports it.
+    pub lifecycle: serde_json::Value,
+    /// As GET /login-helper reports it.
+    pub login: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    /// What the server's `parse_query` makes of `to_query`.
+    fn round_trip(req: &ScreenshotRequest) -> ScreenshotRequest {
+        let (_, query) = crate::parse_query(&format!("/screenshot?{}", req.to_query()));
+        ScreenshotRequest::from_query(&query).unwrap()
+    }
+
+    #[test]
+    fn navigate_body_parses() {
+        let req: NavigateRequest = serde_json::from_str(r#"{"url": "https://example.com/?q=a&b=c", "wait_until": "load"}"#).unwrap();
+        assert_eq!((req.url.as_str(), req.locale, req.wait_until), ("https://example.com/?q=a&b=c", None, WaitUntil::Load));
+        let req: NavigateRequest = serde_json::from_str(r#"{"url": "https://example.com", "locale": "de-DE"}"#).unwrap();
+        assert_eq!((req.locale.as_deref(), req.wait_until), (Some("de-DE"), WaitUntil::None));
+
+        for bad in [r#"{}"#, r#"{"url": 5}"#, r#"{"url": "x", "wait_until": "idle"}"#, r#"{"url": "x""#] {
+            assert!(serde_json::from_str::<NavigateRequest>(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn navigate_get_matches_the_body_form() {
+        let from_get = |url: &str| {
+            let (_, query) = crate::parse_query(url);
+            NavigateRequest::from_query(&query)
+        };
+        let get = from_get("/navigate?url=https%3A%2F%2Fexample.com%2F%3Fq%3Da%26b%3Dc").unwrap();
+        let post: NavigateRequest = serde_json::from_str(r#"{"url": "https://example.com/?q=a&b=c"}"#).unwrap();
+        assert_eq!(serde_json::to_value(&get).unwrap(), serde_json::to_value(&post).unwrap());
+
+        let req = from_get("/navigate?locale=ja%2DJP&url=https%3A%2F%2Fexample.com%2F").unwrap();
+        assert_eq!((req.url.as_str(), req.locale.as_deref()), ("https://example.com/", Some("ja-JP")));
+        assert!(from_get("/navigate?locale=ja-JP").unwrap_err().contains("missing url"));
+
+        // Auth parameters never reach the target
+        let req = from_get("/navigate?url=https://site/&token=SECRET&sig=abc.def").unwrap();
+        assert_eq!(req.url, "https://site/");
+        // An unencoded URL would lose everything after its first &
+        let error = from_get("/navigate?url=https://example.com/?q=a&b=c").unwrap_err();
+        assert!(error.contains("'b'"), "{}", error);
+    }
+
+    #[test]
+    fn screenshot_query_round_trips() {
+        let requests = [
+            ScreenshotRequest::default(),
+            ScreenshotRequest { png: true, ..Default::default() },
+            ScreenshotRequest { quality: 35, ..Default::default() },
+            ScreenshotRequest { selector: Some("#main > a[href=\"/x?a=1&b=2\"]".to_string()), ..Default::default() },
+            ScreenshotRequest { full_page: true, png: true, ..Default::default() },
+            ScreenshotRequest { clip: Some(Clip { x: 10.0, y: 20.5, width: 300.0, height: 150.0 }), ..Default::default() },
+        ];
+        for req in requests {
+            assert_eq!(round_trip(&req), req);
+        }
+    }
+
+    #[test]
+    fn screenshot_query_is_validated() {
+        assert!(ScreenshotRequest::from_query(&query(&[("format", "gif")])).is_err());
+        assert!(ScreenshotRequest::from_query(&query(&[("format", "png"), ("quality", "50")])).is_err());
+        assert!(ScreenshotRequest::from_query(&query(&[("quality", "101")])).is_err());
+        assert!(ScreenshotRequest::from_query(&query(&[("selector", "a"), ("fullpage", "true")])).is_err());
+        assert!(ScreenshotRequest::from_query(&query(&[("x", "1"), ("y", "1"), ("width", "10")])).is_err());
+        assert!(ScreenshotRequest::from_query(&query(&[("x", "1"), ("y", "1"), ("width", "0"), ("height", "10")])).is_err());
+        // A blank selector is no selector
+        let req = ScreenshotRequest::from_query(&query(&[("selector", "  "), ("fullpage", "1")])).unwrap();
+        assert_eq!((req.selector, req.full_page), (None, true));
+    }
+
+    #[test]
+    fn clip_parses_from_flag() {
+        let clip: Clip = "0, 10,200,100".parse().unwrap();
+        assert_eq!(clip, Clip { x: 0.0, y: 10.0, width: 200.0, height: 100.0 });
+        assert!("1,2,3".parse::<Clip>().is_err());
+        assert!(clip.check_within(100.0, 100.0).is_ok());
+        assert!(clip.check_within(100.0, 5.0).is_err());
+    }
+
+    #[test]
+    fn interact_request_default_matches_empty_body() {
+        let parsed: InteractRequest = serde_json::from_str("{}").unwrap();
+        let default = InteractRequest::default();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), serde_json::to_value(&default).unwrap());
+        assert_eq!((default.click_count, default.stability.stable, default.stability.timeout_ms), (1, true, DEFAULT_STABLE_TIMEOUT_MS));
+    }
+
+    #[test]
+    fn interact_request_round_trips() {
+        let req = InteractRequest {
+            selector: "button".to_string(),
+            frame: Some("child".to_string()),
+            text: "日本語".to_string(),
+            method: TypeMethod::Insert,
+            button: ClickButton::Right,
+            click_count: 2,
+            stability: Stability { stable: false, timeout_ms: 500 },
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&req).unwrap();
+        assert_eq!(json["clickCount"], 2);
+        assert_eq!(json["method"], "insert");
+        assert_eq!(json["stable"], false);
+        let back: InteractRequest = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&back).unwrap(), json);
+    }
+
+    #[test]
+    fn evaluate_request_takes_both_spellings() {
+        let camel: EvaluateRequest = serde_json::from_str(r#"{"expression": "1", "awaitPromise": true}"#).unwrap();
+        let snake: EvaluateRequest = serde_json::from_str(r#"{"expression": "1", "await_promise": true}"#).unwrap();
+        assert!(camel.await_promise && snake.await_promise);
+        assert_eq!(serde_json::to_value(&camel).unwrap()["awaitPromise"], true);
+    }
+
+    #[test]
+    fn target_selector_adds_pierce_once() {
+        let req = |selector: &str, pierce| InteractRequest { selector: selector.to_string(), pierce, ..Default::default() };
+        assert_eq!(req("x-app button", true).target_selector(), ">>> x-app button");
+        assert_eq!(req(">>> button", true).target_selector(), ">>> button");
+        assert_eq!(req("button", false).target_selector(), "button");
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+
+// ============== GUI UI State ==============
+//
+// Machine-readable model of the browser chrome. The GUI event loop rebuilds
+// it after every window or user event; the HTTP thread only reads it.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TabState {
+    pub id: usize,
+    pub url: String,
+    pub title: String,
+    pub favicon: Option<String>,
+    pub pinned: bool,
+    pub muted: bool,
+    pub loading: bool,
+    /// No live page: activating it reloads the URL and restores scroll and form fields.
+    pub hibernated: bool,
+    /// Split-view pane showing this tab, if any.
+    pub pane: Option<crate::split::Pane>,
+    /// The webview showing it stopped answering watchdog probes.
+    pub unresponsive: bool,
+    /// Forced dark theme, by --dark-schedule, POST /theme or the toolbar toggle.
+    pub dark: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WindowState {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(flatten)]
+    pub mode: crate::kiosk::WindowMode,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UiState {
+    pub tabs: Vec<TabState>,
+    pub active_tab: usize,
+    pub window: WindowState,
+    /// Present while two tabs are shown side by side.
+    pub split: Option<crate::split::Split>,
+    pub zoom: f64,
+    pub devtools_open: bool,
+    pub updated_at: u128,
+}
+
+pub type SharedUiState = Arc<RwLock<UiState>>;
+
+impl UiState {
+    pub fn active(&self) -> Option<&TabState> {
+        self.tabs.iter().find(|t| t.id == self.active_tab)
+    }
+
+    /// Trimmed view embedded in /live-stream responses.
+    pub fn summary(&self) -> serde_json::Value {
+        let active = self.active();
+        serde_json::json!({
+            "active_tab": self.active_tab,
+            "url": active.map(|t| t.url.as_str()),
+            "title": active.map(|t| t.title.as_str()),
+            "favicon": active.and_then(|t| t.favicon.as_deref()),
+            "tab_count": self.tabs.len(),
+            "split": self.split.is_some(),
+            "unresponsive": self.tabs.iter().any(|t| t.unresponsive),
+        })
+    }
+}
+
+#[cfg(test)]
+impl TabState {
+    /// A loaded, unpinned tab.
+    pub(crate) fn sample(id: usize, url: &str) -> TabState {
+        TabState {
+            id,
+            url: url.to_string(),
+            title: format!("Tab {}", id),
+            favicon: None,
+            pinned: false,
+            muted: false,
+            loading: false,
+            hibernated: false,
+            pane: None,
+            unresponsive: false,
+            dark: false,
+        }
+    }
+}
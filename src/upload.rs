@@ -0,0 +1,301 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// ============== Object Storage Upload ==============
+//
+// Saved frames are pushed to an S3-compatible bucket from a dedicated
+// thread. The queue is bounded: when the store falls behind, new jobs are
+// dropped and counted instead of piling up in memory. Request signing needs
+// the `upload` cargo feature.
+
+const QUEUE_CAPACITY: usize = 64;
+const MAX_ATTEMPTS: u32 = 5;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Path-style target parsed from `--upload-url https://ENDPOINT/BUCKET[/PREFIX]`.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub endpoint: url::Url,
+    pub bucket: String,
+    pub prefix: String,
+}
+
+impl Target {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let url = url::Url::parse(s).map_err(|e| format!("invalid upload URL '{}': {}", s, e))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(format!("upload URL must be http(s), got '{}'", url.scheme()));
+        }
+        let path = url.path().trim_matches('/').to_string();
+        let (bucket, prefix) = path.split_once('/').unwrap_or((path.as_str(), ""));
+        if bucket.is_empty() {
+            return Err("upload URL must include a bucket: https://ENDPOINT/BUCKET[/PREFIX]".to_string());
+        }
+        let mut endpoint = url.clone();
+        endpoint.set_path("");
+        endpoint.set_query(None);
+        Ok(Target { bucket: bucket.to_string(), prefix: prefix.to_string(), endpoint })
+    }
+
+    fn key(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.prefix, name)
+        }
+    }
+}
+
+struct Job {
+    key: String,
+    body: Vec<u8>,
+    content_type: &'static str,
+    enqueued_at: u128,
+}
+
+#[derive(Debug, Default)]
+struct UploadStats {
+    uploaded: u64,
+    failed: u64,
+    dropped: u64,
+    retries: u64,
+    /// Enqueue times of jobs not yet finished, oldest first.
+    pending: VecDeque<u128>,
+    last_success_at: Option<u128>,
+    last_error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Uploader {
+    tx: SyncSender<Job>,
+    target: Target,
+    stats: Arc<Mutex<UploadStats>>,
+}
+
+impl Uploader {
+    /// Validates the target and credentials and starts the upload thread.
+    pub fn start(url: &str) -> Result<Self, String> {
+        let target = Target::parse(url)?;
+        let credentials = sigv4::Credentials::from_env()?;
+        let (tx, rx) = mpsc::sync_channel::<Job>(QUEUE_CAPACITY);
+        let stats: Arc<Mutex<UploadStats>> = Arc::default();
+
+        let worker_target = target.clone();
+        let worker_stats = stats.clone();
+        thread::spawn(move || {
+            for job in rx {
+                let mut attempt = 0;
+                let result = loop {
+                    attempt += 1;
+                    match sigv4::put(&worker_target, &credentials, &job.key, &job.body, job.content_type) {
+                        Ok(()) => break Ok(()),
+                        Err(e) if attempt >= MAX_ATTEMPTS => break Err(e),
+                        Err(_) => {
+                            worker_stats.lock().unwrap().retries += 1;
+                            let backoff = Duration::from_millis(500 * 2u64.pow(attempt - 1)).min(MAX_BACKOFF);
+                            thread::sleep(backoff);
+                        }
+                    }
+                };
+
+                let mut stats = worker_stats.lock().unwrap();
+                stats.pending.pop_front();
+                match result {
+                    Ok(()) => {
+                        stats.uploaded += 1;
+                        stats.last_success_at = Some(crate::now_millis());
+                    }
+                    Err(e) => {
+                        log_error!("Upload of {} failed after {} attempts: {}", job.key, MAX_ATTEMPTS, e);
+                        stats.failed += 1;
+                        stats.last_error = Some(e);
+                    }
+                }
+            }
+        });
+
+        Ok(Uploader { tx, target, stats })
+    }
+
+    /// Queues an object under the configured prefix; never blocks the caller.
+    pub fn enqueue(&self, name: &str, body: Vec<u8>, content_type: &'static str) {
+        let job = Job { key: self.target.key(name), body, content_type, enqueued_at: crate::now_millis() };
+        let enqueued_at = job.enqueued_at;
+        // Record before sending so the worker can never pop an entry that isn't there yet
+        let mut stats = self.stats.lock().unwrap();
+        stats.pending.push_back(enqueued_at);
+        match self.tx.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(job)) | Err(TrySendError::Disconnected(job)) => {
+                stats.pending.pop_back();
+                stats.dropped += 1;
+                log_error!("Upload queue full, dropping {}", job.key);
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let stats = self.stats.lock().unwrap();
+        let lag_ms = stats.pending.front().map(|t| crate::now_millis().saturating_sub(*t)).unwrap_or(0);
+        serde_json::json!({
+            "endpoint": self.target.endpoint.as_str(),
+            "bucket": self.target.bucket,
+            "prefix": self.target.prefix,
+            "queued": stats.pending.len(),
+            "queue_capacity": QUEUE_CAPACITY,
+            "uploaded": stats.uploaded,
+            "failed": stats.failed,
+            "dropped": stats.dropped,
+            "retries": stats.retries,
+            "lag_ms": lag_ms,
+            "last_success_at": stats.last_success_at,
+            "last_error": stats.last_error,
+        })
+    }
+}
+
+#[cfg(feature = "upload")]
+mod sigv4 {
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+
+    use super::Target;
+
+    pub struct Credentials {
+        access_key: String,
+        secret_key: String,
+        session_token: Option<String>,
+        region: String,
+    }
+
+    impl Credentials {
+        pub fn from_env() -> Result<Self, String> {
+            let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+            Ok(Credentials {
+                access_key: var("AWS_ACCESS_KEY_ID").ok_or("AWS_ACCESS_KEY_ID is not set")?,
+                secret_key: var("AWS_SECRET_ACCESS_KEY").ok_or("AWS_SECRET_ACCESS_KEY is not set")?,
+                session_token: var("AWS_SESSION_TOKEN"),
+                region: var("AWS_REGION").or_else(|| var("AWS_DEFAULT_REGION")).unwrap_or_else(|| "us-east-1".to_string()),
+            })
+        }
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(data.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// RFC 3986 encoding of each path segment, keeping the slashes.
+    fn encode_path(path: &str) -> String {
+        path.split('/')
+            .map(|segment| {
+                segment
+                    .bytes()
+                    .map(|b| match b {
+                        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+                        _ => format!("%{:02X}", b),
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// `(20240101T000000Z, 20240101)` for a Unix timestamp.
+    fn amz_date(secs: u64) -> (String, String) {
+        let days = (secs / 86_400) as i64;
+        let rem = secs % 86_400;
+        // Howard Hinnant's civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        let date = format!("{:04}{:02}{:02}", year, month, day);
+        let stamp = format!("{}T{:02}{:02}{:02}Z", date, rem / 3600, rem % 3600 / 60, rem % 60);
+        (stamp, date)
+    }
+
+    pub fn put(target: &Target, creds: &Credentials, key: &str, body: &[u8], content_type: &str) -> Result<(), String> {
+        let host = match target.endpoint.port() {
+            Some(port) => format!("{}:{}", target.endpoint.host_str().unwrap_or_default(), port),
+            None => target.endpoint.host_str().unwrap_or_default().to_string(),
+        };
+        let path = format!("/{}/{}", encode_path(&target.bucket), encode_path(key));
+        let payload_hash = hex(&Sha256::digest(body));
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let (stamp, date) = amz_date(now);
+
+        let mut headers = vec![
+            ("host", host.clone()),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", stamp.clone()),
+        ];
+        if let Some(token) = &creds.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v.trim())).collect();
+        let signed_headers = headers.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(";");
+        let canonical_request = format!("PUT\n{}\n\n{}\n{}\n{}", path, canonical_headers, signed_headers, payload_hash);
+
+        let scope = format!("{}/{}/s3/aws4_request", date, creds.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            stamp,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut signing_key = hmac(format!("AWS4{}", creds.secret_key).as_bytes(), &date);
+        for part in [creds.region.as_str(), "s3", "aws4_request"] {
+            signing_key = hmac(&signing_key, part);
+        }
+        let signature = hex(&hmac(&signing_key, &string_to_sign));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            creds.access_key, scope, signed_headers, signature
+        );
+
+        let url = format!("{}{}", target.endpoint.as_str().trim_end_matches('/'), path);
+        let mut request = ureq::put(&url)
+            .timeout(std::time::Duration::from_secs(60))
+            .set("Authorization", &authorization)
+            .set("Content-Type", content_type);
+        for (name, value) in headers.iter().filter(|(k, _)| *k != "host") {
+            request = request.set(name, value);
+        }
+        request.send_bytes(body).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(feature = "upload"))]
+mod sigv4 {
+    use super::Target;
+
+    /// Uninhabited: uploads cannot be started without the feature.
+    pub enum Credentials {}
+
+    impl Credentials {
+        pub fn from_env() -> Result<Self, String> {
+            Err("this binary was built without the `upload` feature; rebuild with --features upload".to_string())
+        }
+    }
+
+    pub fn put(_: &Target, creds: &Credentials, _: &str, _: &[u8], _: &str) -> Result<(), String> {
+        match *creds {}
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+// ============== URL Pattern Blocking (headless) ==============
+//
+// A lighter alternative to --filter-list for stripping analytics and ads
+// from captures: --block takes comma-separated URL patterns where `*`
+// matches any run of characters (e.g. `*google-analytics.com*`), and POST
+// /block adds one at runtime, DELETE /block?pattern= removes it. Matching
+// requests are failed with BlockedByClient in the Fetch interceptor; GET
+// /block lists the patterns with how often each fired. A pattern that
+// matches the page being streamed is refused, and main-frame documents are
+// never blocked, so a rule can't brick navigation.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockPattern {
+    pub pattern: String,
+    pub hits: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlockRequest {
+    pub pattern: String,
+}
+
+#[derive(Debug, Default)]
+pub struct BlockList {
+    patterns: Vec<BlockPattern>,
+}
+
+pub type SharedBlockList = Arc<Mutex<BlockList>>;
+
+fn validate(pattern: &str) -> Result<String, String> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() || pattern.chars().all(|c| c == '*') {
+        return Err(format!("pattern '{}' would block everything", pattern));
+    }
+    if pattern.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("pattern '{}' contains whitespace", pattern));
+    }
+    Ok(pattern.to_string())
+}
+
+impl BlockList {
+    /// --block patterns, checked against the start URL like POST /block.
+    pub fn new(patterns: &[String], start_url: &str) -> Result<Self, String> {
+        let mut list = BlockList::default();
+        for pattern in patterns.iter().filter(|p| !p.trim().is_empty()) {
+            list.add(pattern, start_url)?;
+        }
+        Ok(list)
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.patterns.is_empty()
+    }
+
+    /// Adds `pattern` unless it is known or matches `page_url`.
+    pub fn add(&mut self, pattern: &str, page_url: &str) -> Result<(), String> {
+        let pattern = validate(pattern)?;
+        if crate::url_match::glob_matches(&pattern, page_url) {
+            return Err(format!("pattern '{}' matches the streamed page {}", pattern, page_url));
+        }
+        if !self.patterns.iter().any(|p| p.pattern == pattern) {
+            self.patterns.push(BlockPattern { pattern, hits: 0 });
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, pattern: &str) -> bool {
+        let before = self.patterns.len();
+        self.patterns.retain(|p| p.pattern != pattern);
+        self.patterns.len() != before
+    }
+
+    /// The first pattern matching `url`, counting the hit.
+    pub fn check(&mut self, url: &str) -> Option<&str> {
+        let pattern = self.patterns.iter_mut().find(|p| crate::url_match::glob_matches(&p.pattern, url))?;
+        pattern.hits += 1;
+        Some(&pattern.pattern)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "patterns": self.patterns })
+    }
+}
+
+/// GET, POST and DELETE /block.
+pub fn handle(
+    request: &mut tiny_http::Request,
+    query: &std::collections::HashMap<String, String>,
+    blocks: &SharedBlockList,
+    current_url: &str,
+) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let method = request.method().clone();
+    match method {
+        tiny_http::Method::Get => crate::json_response(200, &blocks.lock().unwrap().to_json()),
+        tiny_http::Method::Post => {
+            let added = crate::read_json_body::<BlockRequest>(request).and_then(|req| blocks.lock().unwrap().add(&req.pattern, current_url));
+            match added {
+                Ok(()) => crate::json_response(200, &blocks.lock().unwrap().to_json()),
+                Err(e) => crate::json_response(400, &serde_json::json!({"error": e})),
+            }
+        }
+        tiny_http::Method::Delete => {
+            let pattern = query.get("pattern").map(String::as_str).unwrap_or_default();
+            let mut blocks = blocks.lock().unwrap();
+            if blocks.remove(pattern) {
+                crate::json_response(200, &blocks.to_json())
+            } else {
+                crate::json_response(404, &serde_json::json!({"error": "no such pattern"}))
+            }
+        }
+        _ => crate::json_response(405, &serde_json::json!({"error": "method not allowed"})),
+    }
+}
@@ -0,0 +1,53 @@
+// ============== URL Patterns ==============
+//
+// The `*` glob shared by login recipes, --block, /mock and frame specs.
+
+/// `*` matches any run of characters, everything else literally.
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn without_star_matches_exactly() {
+        assert!(glob_matches("https://a.test/", "https://a.test/"));
+        assert!(!glob_matches("https://a.test/", "https://a.test/x"));
+        assert!(!glob_matches("https://a.test/x", "https://a.test/"));
+    }
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*", "anything"));
+        assert!(glob_matches("https://*.test/*", "https://a.b.test/path?q=1"));
+        assert!(glob_matches("*/api/*", "http://host/api/users"));
+        assert!(glob_matches("*.png", "http://host/img.png"));
+        assert!(!glob_matches("*.png", "http://host/img.png?v=2"));
+        assert!(!glob_matches("https://*.test/*", "http://a.test/"));
+    }
+
+    #[test]
+    fn parts_match_in_order_without_overlap() {
+        assert!(glob_matches("a*b*c", "abc"));
+        assert!(glob_matches("a*b*c", "a-c-b-c"));
+        assert!(!glob_matches("a*b*c", "acb"));
+        // The suffix can't reuse what the prefix consumed
+        assert!(!glob_matches("ab*ba", "aba"));
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use image::{RgbImage, Rgba};
+
+use crate::overlay::{self, Color};
+
+// ============== Responsiveness Watchdog ==============
+//
+// A renderer stuck in a script loop looks alive in the GUI but answers
+// nothing. Every PROBE_INTERVAL the event loop evaluates a trivial script
+// in each pane's webview and counts a miss when the previous probe is still
+// unanswered. MAX_MISSES misses in a row mark the pane's tab unresponsive
+// in /ui-state and put an UNRESPONSIVE badge on streamed frames; any answer
+// clears it. With --unresponsive-recover-secs the pane's webview is
+// recreated at the tab's last URL once it has been unresponsive that long.
+// Probing pauses while the pane's devtools are open, since a debugger
+// stopped at a breakpoint looks exactly like a hang.
+
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_MISSES: u32 = 3;
+const BADGE_SCALE: i64 = 3;
+const BADGE_FG: Color = Rgba([255, 255, 255, 255]);
+const BADGE_BG: Color = Rgba([200, 30, 30, 255]);
+
+#[derive(Default)]
+pub struct Probe {
+    /// Sequence number of the probe in flight; 0 before the first.
+    sent: u64,
+    answered: Arc<AtomicU64>,
+    misses: u32,
+    unresponsive_since: Option<Instant>,
+}
+
+impl Probe {
+    /// Scores the probe in flight and returns the next one: its sequence
+    /// number and the counter its callback stores it into.
+    pub fn next(&mut self) -> (u64, Arc<AtomicU64>) {
+        if self.sent > 0 && self.answered.load(Ordering::Relaxed) < self.sent {
+            self.misses += 1;
+            if self.misses == MAX_MISSES {
+                self.unresponsive_since = Some(Instant::now());
+            }
+        } else {
+            self.misses = 0;
+            self.unresponsive_since = None;
+        }
+        self.sent += 1;
+        (self.sent, self.answered.clone())
+    }
+
+    pub fn is_unresponsive(&self) -> bool {
+        self.unresponsive_since.is_some()
+    }
+
+    /// True once the pane has been unresponsive for `after`.
+    pub fn needs_recovery(&self, after: Duration) -> bool {
+        self.unresponsive_since.is_some_and(|since| since.elapsed() >= after)
+    }
+}
+
+/// Marks a frame whose page stopped answering.
+pub fn render_badge(img: &mut RgbImage) {
+    let (w, _) = overlay::text_size("UNRESPONSIVE", BADGE_SCALE);
+    let x = (img.width() as i64 - w) / 2;
+    overlay::draw_label(img, x.max(0), 12, "UNRESPONSIVE", BADGE_SCALE, BADGE_FG, BADGE_BG);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sends a probe and, if `answer`, has the page answer it.
+    fn tick(probe: &mut Probe, answer: bool) {
+        let (seq, answered) = probe.next();
+        if answer {
+            answered.store(seq, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn unresponsive_after_max_misses_in_a_row() {
+        let mut probe = Probe::default();
+        // The first probe has nothing to score yet
+        tick(&mut probe, false);
+        for _ in 0..MAX_MISSES - 1 {
+            tick(&mut probe, false);
+            assert!(!probe.is_unresponsive());
+        }
+        tick(&mut probe, false);
+        assert!(probe.is_unresponsive());
+        assert_eq!(probe.misses, MAX_MISSES);
+    }
+
+    #[test]
+    fn an_answer_clears_the_count() {
+        let mut probe = Probe::default();
+        tick(&mut probe, false);
+        for _ in 0..MAX_MISSES - 1 {
+            tick(&mut probe, false);
+        }
+        // Answering the probe in flight resets the count at the next tick
+        tick(&mut probe, true);
+        tick(&mut probe, false);
+        assert_eq!(probe.misses, 0);
+        assert!(!probe.is_unresponsive());
+
+        for _ in 0..MAX_MISSES {
+            tick(&mut probe, false);
+        }
+        assert!(probe.is_unresponsive());
+        let (seq, answered) = probe.next();
+        answered.store(seq, Ordering::Relaxed);
+        probe.next();
+        assert!(!probe.is_unresponsive() && !probe.needs_recovery(Duration::ZERO));
+    }
+
+    #[test]
+    fn a_late_answer_to_an_older_probe_is_still_a_miss() {
+        let mut probe = Probe::default();
+        let (first, answered) = probe.next();
+        probe.next();
+        answered.store(first, Ordering::Relaxed);
+        probe.next();
+        assert_eq!(probe.misses, 2);
+    }
+
+    #[test]
+    fn recovery_waits_for_the_configured_time() {
+        let mut probe = Probe::default();
+        assert!(!probe.needs_recovery(Duration::ZERO));
+        for _ in 0..=MAX_MISSES {
+            tick(&mut probe, false);
+        }
+        assert!(probe.needs_recovery(Duration::ZERO));
+        assert!(!probe.needs_recovery(Duration::from_secs(60)));
+        // Staying unresponsive keeps the original start time
+        probe.unresponsive_since = Some(Instant::now() - Duration::from_secs(61));
+        tick(&mut probe, false);
+        assert!(probe.needs_recovery(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn badge_is_drawn_at_the_top() {
+        let mut img = RgbImage::new(400, 200);
+        render_badge(&mut img);
+        let red = |y: u32| (0..img.width()).any(|x| img.get_pixel(x, y).0 == [200, 30, 30]);
+        assert!(red(14));
+        assert!(!red(150));
+    }
+}
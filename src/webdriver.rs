@@ -0,0 +1,250 @@
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::Deserialize;
+use tiny_http::{Method, Request, Response};
+
+use crate::commands::{self, CommandError, CommandSender, PageCommand};
+
+// ============== WebDriver Shim ==============
+//
+// With --webdriver, a small subset of W3C WebDriver (classic) is served under
+// /wd/hub so simple Selenium / WebdriverIO scripts can drive the headless
+// page. There is one page, so there is at most one session: a new session
+// replaces the previous one. Element references live in a page-side registry
+// keyed by document, so they go stale on navigation as the spec expects.
+
+pub const PREFIX: &str = "/wd/hub";
+
+/// W3C element reference key.
+const ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735466cecf";
+
+const FIND_SCRIPT: &str = r#"
+function(selector, all) {
+    const reg = window.__rbWebDriver || (window.__rbWebDriver = { next: 1, els: {} });
+    const found = all ? Array.from(document.querySelectorAll(selector)) : [document.querySelector(selector)].filter(Boolean);
+    return found.map(function(el) {
+        for (const id in reg.els) if (reg.els[id] === el) return id;
+        const id = performance.timeOrigin + '-' + (reg.next++);
+        reg.els[id] = el;
+        return id;
+    });
+}
+"#;
+
+const CLICK_SCRIPT: &str = r#"
+function(id) {
+    const el = window.__rbWebDriver && window.__rbWebDriver.els[id];
+    if (!el || !el.isConnected) return false;
+    el.scrollIntoView({ block: 'center', inline: 'center' });
+    el.click();
+    return true;
+}
+"#;
+
+/// Types `text` into the element. WebDriver key codes (U+E000..U+F8FF) are
+/// dropped except Enter/Return, which submits the element's form.
+const SEND_KEYS_SCRIPT: &str = r#"
+function(id, text) {
+    const el = window.__rbWebDriver && window.__rbWebDriver.els[id];
+    if (!el || !el.isConnected) return false;
+    el.focus();
+    const submit = /[\uE006\uE007]/.test(text);
+    const plain = text.replace(/[\uE000-\uF8FF]/g, '');
+    if (el.isContentEditable) {
+        document.execCommand('insertText', false, plain);
+    } else if ('value' in el) {
+        el.value += plain;
+        el.dispatchEvent(new Event('input', { bubbles: true }));
+        el.dispatchEvent(new Event('change', { bubbles: true }));
+    }
+    if (submit) {
+        el.dispatchEvent(new KeyboardEvent('keydown', { key: 'Enter', bubbles: true }));
+        if (el.form) el.form.requestSubmit();
+    }
+    return true;
+}
+"#;
+
+pub type SharedSession = Arc<Mutex<Option<String>>>;
+
+#[derive(Debug, Deserialize)]
+struct NavigateRequest {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FindRequest {
+    using: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SendKeysRequest {
+    text: String,
+}
+
+/// WebDriver error: HTTP status, error code, message.
+struct WdError(u16, &'static str, String);
+
+impl From<CommandError> for WdError {
+    fn from(e: CommandError) -> Self {
+        match e.status {
+            422 => WdError(500, "javascript error", e.message),
+            504 => WdError(500, "timeout", e.message),
+            _ => WdError(500, "unknown error", e.message),
+        }
+    }
+}
+
+type WdResult = Result<serde_json::Value, WdError>;
+
+fn respond(result: WdResult) -> Response<Cursor<Vec<u8>>> {
+    match result {
+        Ok(value) => crate::json_response(200, &serde_json::json!({ "value": value })),
+        Err(WdError(status, error, message)) => crate::json_response(status, &serde_json::json!({
+            "value": { "error": error, "message": message, "stacktrace": "" },
+        })),
+    }
+}
+
+fn body<T: serde::de::DeserializeOwned>(request: &mut Request) -> Result<T, WdError> {
+    crate::read_json_body(request).map_err(|e| WdError(400, "invalid argument", e))
+}
+
+fn element_ids(value: serde_json::Value) -> Vec<String> {
+    serde_json::from_value(value).unwrap_or_default()
+}
+
+fn element_ref(id: &str) -> serde_json::Value {
+    serde_json::json!({ ELEMENT_KEY: id })
+}
+
+fn stale(id: &str) -> WdError {
+    WdError(404, "stale element reference", format!("element {} is no longer attached to the page", id))
+}
+
+/// Serves everything under `/wd/hub`.
+pub fn handle(request: &mut Request, path: &str, tx: &CommandSender, session: &SharedSession) -> Response<Cursor<Vec<u8>>> {
+    let route = path.strip_prefix(PREFIX).unwrap_or(path).trim_end_matches('/');
+    let segments: Vec<&str> = route.split('/').filter(|s| !s.is_empty()).collect();
+    let method = request.method().clone();
+
+    let result = match (&method, segments.as_slice()) {
+        (Method::Get, ["status"]) => Ok(serde_json::json!({
+            "ready": true,
+            "message": "hello-cef headless WebDriver shim",
+        })),
+        (Method::Post, ["session"]) => new_session(tx, session),
+        (_, ["session", id, rest @ ..]) => {
+            if session.lock().unwrap().as_deref() != Some(*id) {
+                Err(WdError(404, "invalid session id", format!("no active session {}", id)))
+            } else {
+                session_command(request, &method, rest, tx, session)
+            }
+        }
+        _ => Err(WdError(404, "unknown command", format!("{} {} is not supported", method, path))),
+    };
+    respond(result)
+}
+
+fn new_session(tx: &CommandSender, session: &SharedSession) -> WdResult {
+    let version = commands::send(tx, PageCommand::BrowserVersion)?;
+    let product = version["product"].as_str().unwrap_or_default();
+    let browser_version = product.split_once('/').map(|(_, v)| v).unwrap_or(product);
+
+    let id = format!("{:x}", crate::now_millis());
+    if let Some(previous) = session.lock().unwrap().replace(id.clone()) {
+        log_info!("WebDriver session {} replaced by {}", previous, id);
+    }
+    Ok(serde_json::json!({
+        "sessionId": id,
+        "capabilities": {
+            "browserName": "chrome",
+            "browserVersion": browser_version,
+            "platformName": std::env::consts::OS,
+            "acceptInsecureCerts": false,
+            "pageLoadStrategy": "normal",
+            "setWindowRect": false,
+            "timeouts": { "implicit": 0, "pageLoad": 30000, "script": 30000 },
+            "chrome": { "product": product, "userAgent": version["user_agent"] },
+        },
+    }))
+}
+
+fn session_command(request: &mut Request, method: &Method, rest: &[&str], tx: &CommandSender, session: &SharedSession) -> WdResult {
+    match (method, rest) {
+        (Method::Delete, []) => {
+            *session.lock().unwrap() = None;
+            Ok(serde_json::Value::Null)
+        }
+        (Method::Post, ["url"]) => {
+            let req: NavigateRequest = body(request)?;
+            commands::send(tx, PageCommand::Navigate { url: req.url, source: "webdriver" })?;
+            Ok(serde_json::Value::Null)
+        }
+        (Method::Get, ["url"]) => Ok(commands::send(tx, PageCommand::Evaluate {
+            expression: "location.href".to_string(),
+            await_promise: false,
+        })?),
+        (Method::Post, [kind @ ("element" | "elements")]) => {
+            let req: FindRequest = body(request)?;
+            if req.using != "css selector" {
+                return Err(WdError(400, "invalid argument", format!("unsupported locator strategy '{}', only 'css selector'", req.using)));
+            }
+            let all = *kind == "elements";
+            let found = commands::send(tx, PageCommand::Evaluate {
+                expression: commands::js_call(FIND_SCRIPT, &[req.value.clone().into(), all.into()]),
+                await_promise: false,
+            })
+            .map_err(|e| match e.status {
+                422 => WdError(400, "invalid selector", e.message),
+                _ => e.into(),
+            })?;
+            let ids = element_ids(found);
+            if all {
+                Ok(ids.iter().map(|id| element_ref(id)).collect())
+            } else {
+                ids.first()
+                    .map(|id| element_ref(id))
+                    .ok_or_else(|| WdError(404, "no such element", format!("no element matches '{}'", req.value)))
+            }
+        }
+        (Method::Post, ["element", id, "click"]) => {
+            let clicked = commands::send(tx, PageCommand::Evaluate {
+                expression: commands::js_call(CLICK_SCRIPT, &[(*id).into()]),
+                await_promise: false,
+            })?;
+            match clicked.as_bool() {
+                Some(true) => Ok(serde_json::Value::Null),
+                _ => Err(stale(id)),
+            }
+        }
+        (Method::Post, ["element", id, "value"]) => {
+            let req: SendKeysRequest = body(request)?;
+            let typed = commands::send(tx, PageCommand::Evaluate {
+                expression: commands::js_call(SEND_KEYS_SCRIPT, &[(*id).into(), req.text.into()]),
+                await_promise: false,
+            })?;
+            match typed.as_bool() {
+                Some(true) => Ok(serde_json::Value::Null),
+                _ => Err(stale(id)),
+            }
+        }
+        (Method::Get, ["screenshot"]) => {
+            let rendered = commands::send(tx, PageCommand::Render { settle: None, deterministic: false })?;
+            let jpeg = rendered["frame"]
+                .as_str()
+                .and_then(|f| BASE64.decode(f).ok())
+                .ok_or_else(|| WdError(500, "unknown error", "render returned no frame".to_string()))?;
+            // WebDriver screenshots are PNG
+            let mut png = Vec::new();
+            image::load_from_memory(&jpeg)
+                .and_then(|img| img.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png))
+                .map_err(|e| WdError(500, "unable to capture screen", e.to_string()))?;
+            Ok(BASE64.encode(png).into())
+        }
+        _ => Err(WdError(404, "unknown command", format!("{} /session/:id/{} is not supported", method, rest.join("/")))),
+    }
+}
@@ -0,0 +1,497 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Method, Request, Response};
+
+use crate::ui_state::UiState;
+
+// ============== Workspaces ==============
+//
+// A workspace is a named set of GUI tabs (URL, title, order, pinned state
+// and which one was active), kept in --workspaces-file across runs.
+// Cmd+Shift+S saves the current tabs under a name. The rb://workspaces page
+// and the /workspaces API list, export, import and delete them. Activating
+// one replaces the current tabs, so the API asks for `{"confirm": true}`
+// first. The workspace that was active when the browser quit is updated
+// with the tabs open at that moment, and --restore-workspace reopens it.
+
+/// Longest accepted workspace name.
+const MAX_NAME_LEN: usize = 64;
+/// Path segments under /workspaces/ that are routes, not workspace names.
+const RESERVED_NAMES: &[&str] = &["import", "view"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceTab {
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    pub tabs: Vec<WorkspaceTab>,
+    /// Index into `tabs` of the tab shown on activation.
+    #[serde(default)]
+    pub active: usize,
+    #[serde(default)]
+    pub saved_at: u128,
+}
+
+impl Workspace {
+    /// The GUI's current tabs as a workspace.
+    pub fn from_ui(name: String, ui: &UiState) -> Self {
+        let tabs = ui
+            .tabs
+            .iter()
+            .map(|t| WorkspaceTab { url: t.url.clone(), title: t.title.clone(), pinned: t.pinned })
+            .collect();
+        let active = ui.tabs.iter().position(|t| t.id == ui.active_tab).unwrap_or_default();
+        Workspace { name, tabs, active, saved_at: crate::now_millis() }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        validate_name(&self.name)?;
+        if self.tabs.is_empty() {
+            return Err("a workspace needs at least one tab".to_string());
+        }
+        if self.active >= self.tabs.len() {
+            return Err(format!("active tab {} is out of range for {} tabs", self.active, self.tabs.len()));
+        }
+        if let Some(tab) = self.tabs.iter().find(|t| url::Url::parse(&t.url).is_err()) {
+            return Err(format!("invalid tab url '{}'", tab.url));
+        }
+        Ok(())
+    }
+
+    fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "tab_count": self.tabs.len(),
+            "active_url": self.tabs.get(self.active).map(|t| &t.url),
+            "saved_at": self.saved_at,
+        })
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("workspace name must not be empty".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(format!("workspace name is longer than {} characters", MAX_NAME_LEN));
+    }
+    if name.contains('/') || RESERVED_NAMES.contains(&name) {
+        return Err(format!("'{}' can't be used as a workspace name", name));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreFile {
+    #[serde(default)]
+    workspaces: BTreeMap<String, Workspace>,
+    #[serde(default)]
+    last_active: Option<String>,
+}
+
+pub struct Store {
+    path: PathBuf,
+    file: StoreFile,
+}
+
+pub type SharedWorkspaces = Arc<Mutex<Store>>;
+
+pub fn default_file() -> PathBuf {
+    crate::config_dir().join("workspaces.json")
+}
+
+impl Store {
+    /// Reads `path`; a missing file is an empty store.
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let file = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| format!("invalid workspaces file {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => StoreFile::default(),
+            Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+        };
+        Ok(Store { path, file })
+    }
+
+    /// Writes through a temporary file so a crash never leaves half a store.
+    fn persist(&self) -> Result<(), String> {
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let tmp = self.path.with_extension("json.tmp");
+            std::fs::write(&tmp, serde_json::to_vec_pretty(&self.file)?)?;
+            std::fs::rename(&tmp, &self.path)
+        };
+        write().map_err(|e| format!("failed to write {}: {}", self.path.display(), e))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Workspace> {
+        self.file.workspaces.get(name)
+    }
+
+    /// Adds or replaces a workspace.
+    pub fn put(&mut self, workspace: Workspace) -> Result<(), String> {
+        workspace.validate()?;
+        self.file.workspaces.insert(workspace.name.clone(), workspace);
+        self.persist()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<bool, String> {
+        if self.file.workspaces.remove(name).is_none() {
+            return Ok(false);
+        }
+        if self.file.last_active.as_deref() == Some(name) {
+            self.file.last_active = None;
+        }
+        self.persist().map(|_| true)
+    }
+
+    pub fn set_active(&mut self, name: &str) -> Result<(), String> {
+        self.file.last_active = Some(name.to_string());
+        self.persist()
+    }
+
+    /// What --restore-workspace reopens.
+    pub fn last_active(&self) -> Option<&Workspace> {
+        self.file.last_active.as_deref().and_then(|name| self.get(name))
+    }
+
+    /// Stores the current tabs into the active workspace, if there is one.
+    pub fn sync_active(&mut self, ui: &UiState) -> Result<(), String> {
+        match self.file.last_active.clone() {
+            Some(name) if self.file.workspaces.contains_key(&name) => self.put(Workspace::from_ui(name, ui)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let workspaces: Vec<serde_json::Value> = self.file.workspaces.values().map(Workspace::summary).collect();
+        serde_json::json!({
+            "workspaces": workspaces,
+            "last_active": self.file.last_active,
+            "file": self.path,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SaveRequest {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivateRequest {
+    #[serde(default)]
+    confirm: bool,
+}
+
+/// Serves /workspaces and everything below it. `activate` hands a confirmed
+/// workspace to the event loop.
+pub fn handle(
+    request: &mut Request,
+    path: &str,
+    store: &SharedWorkspaces,
+    ui_state: &crate::ui_state::SharedUiState,
+    activate: impl FnOnce(Workspace),
+) -> Response<Cursor<Vec<u8>>> {
+    let method = request.method().clone();
+    let rest = path.strip_prefix("/workspaces").unwrap_or_default().trim_start_matches('/');
+    let (name, action) = match rest.split_once('/') {
+        Some((name, action)) => (name, action),
+        None => (rest, ""),
+    };
+    let name = urlencoding::decode(name).map(|n| n.into_owned()).unwrap_or_else(|_| name.to_string());
+
+    match (method, name.as_str(), action) {
+        (Method::Get, "", "") => crate::json_response(200, &store.lock().unwrap().to_json()),
+        (Method::Post, "", "") => {
+            let result = crate::read_json_body::<SaveRequest>(request).and_then(|req| {
+                let workspace = Workspace::from_ui(req.name, &ui_state.read().unwrap());
+                let mut store = store.lock().unwrap();
+                store.put(workspace.clone())?;
+                // The saved set is what's open now, so it is also the active one
+                store.set_active(&workspace.name)?;
+                Ok(workspace)
+            });
+            match result {
+                Ok(workspace) => {
+                    log_info!("Saved workspace '{}' ({} tabs)", workspace.name, workspace.tabs.len());
+                    crate::json_response(201, &serde_json::json!(workspace))
+                }
+                Err(e) => crate::json_response(400, &serde_json::json!({"error": e})),
+            }
+        }
+        (Method::Get, "view", "") => Response::from_string(VIEWER_HTML)
+            .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..]).unwrap()),
+        (Method::Post, "import", "") => {
+            let result = crate::read_json_body::<Workspace>(request).and_then(|workspace| {
+                store.lock().unwrap().put(workspace.clone())?;
+                Ok(workspace)
+            });
+            match result {
+                Ok(workspace) => {
+                    log_info!("Imported workspace '{}' ({} tabs)", workspace.name, workspace.tabs.len());
+                    crate::json_response(201, &workspace.summary())
+                }
+                Err(e) => crate::json_response(400, &serde_json::json!({"error": e})),
+            }
+        }
+        (_, "import" | "view", _) => crate::json_response(405, &serde_json::json!({"error": "method not allowed"})),
+        (method, name, action) => {
+            let workspace = store.lock().unwrap().get(name).cloned();
+            let Some(workspace) = workspace else {
+                return crate::json_response(404, &serde_json::json!({"error": format!("no workspace named '{}'", name)}));
+            };
+            match (method, action) {
+                // The export format is exactly what POST /workspaces/import takes
+                (Method::Get, "") => crate::json_response(200, &serde_json::json!(workspace)),
+                (Method::Delete, "") => match store.lock().unwrap().remove(name) {
+                    Ok(_) => crate::json_response(200, &serde_json::json!({"status": "deleted", "name": name})),
+                    Err(e) => crate::json_response(500, &serde_json::json!({"error": e})),
+                },
+                (Method::Post, "activate") => match crate::read_json_body::<ActivateRequest>(request) {
+                    Ok(req) if !req.confirm => {
+                        let open = ui_state.read().unwrap().tabs.len();
+                        crate::json_response(409, &serde_json::json!({
+                            "error": "activating a workspace closes the current tabs; repeat with {\"confirm\": true}",
+                            "tabs_to_close": open,
+                        }))
+                    }
+                    Ok(_) => {
+                        if let Err(e) = store.lock().unwrap().set_active(name) {
+                            return crate::json_response(500, &serde_json::json!({"error": e}));
+                        }
+                        let body = workspace.summary();
+                        activate(workspace);
+                        // Applied by the event loop; GET /ui-state shows the new tabs once it has
+                        crate::json_response(202, &body)
+                    }
+                    Err(e) => crate::json_response(400, &serde_json::json!({"error": e})),
+                },
+                (_, "" | "activate") => crate::json_response(405, &serde_json::json!({"error": "method not allowed"})),
+                _ => crate::json_response(404, &serde_json::json!({"error": "not found"})),
+            }
+        }
+    }
+}
+
+/// rb://workspaces: list, activate, export and import.
+const VIEWER_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="utf-8">
+    <title>Workspaces</title>
+    <style>
+        body { margin: 0; padding: 24px 32px; font: 14px -apple-system, BlinkMacSystemFont, sans-serif; background: #f5f5f5; color: #222; }
+        h1 { font-size: 20px; margin: 0 0 16px; }
+        table { border-collapse: collapse; width: 100%; max-width: 900px; background: #fff; }
+        th, td { text-align: left; padding: 8px 10px; border-bottom: 1px solid #e0e0e0; }
+        th { font-weight: 600; color: #666; font-size: 12px; text-transform: uppercase; }
+        td.url { color: #666; max-width: 320px; overflow: hidden; text-overflow: ellipsis; white-space: nowrap; }
+        tr.active td:first-child { font-weight: 600; }
+        button { background: #fff; border: 1px solid #bbb; border-radius: 4px; padding: 3px 10px; cursor: pointer; margin-right: 4px; }
+        button.primary { background: #0e639c; border-color: #0e639c; color: #fff; }
+        #tools { margin: 20px 0 8px; display: flex; gap: 8px; align-items: center; }
+        textarea { width: 100%; max-width: 900px; height: 180px; font: 12px Menlo, Consolas, monospace; box-sizing: border-box; }
+        #status { color: #666; margin-left: 8px; }
+    </style>
+</head>
+<body>
+    <h1>Workspaces</h1>
+    <table>
+        <thead><tr><th>Name</th><th>Tabs</th><th>Active tab</th><th>Saved</th><th></th></tr></thead>
+        <tbody id="list"></tbody>
+    </table>
+    <div id="tools">
+        <button id="import" class="primary">Import JSON</button>
+        <span id="status"></span>
+    </div>
+    <textarea id="json" placeholder="Exported workspaces appear here; paste one and press Import JSON to add it."></textarea>
+    <script>
+        const list = document.getElementById('list');
+        const json = document.getElementById('json');
+        const status = document.getElementById('status');
+        const path = name => '/workspaces/' + encodeURIComponent(name);
+        const esc = s => String(s).replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;').replace(/"/g, '&quot;');
+
+        async function call(method, url, body) {
+            const response = await fetch(url, { method: method, body: body === undefined ? undefined : JSON.stringify(body) });
+            const data = await response.json();
+            if (!response.ok) throw new Error(data.error || response.statusText);
+            return data;
+        }
+
+        async function load() {
+            const data = await call('GET', '/workspaces');
+            list.innerHTML = data.workspaces.length ? '' : '<tr><td colspan="5">No workspaces yet. Press Cmd+Shift+S on any page to save the open tabs.</td></tr>';
+            data.workspaces.forEach(function(ws) {
+                const row = document.createElement('tr');
+                if (ws.name === data.last_active) row.className = 'active';
+                row.innerHTML = '<td>' + esc(ws.name) + '</td><td>' + ws.tab_count + '</td><td class="url">' + esc(ws.active_url || '') + '</td>'
+                    + '<td>' + new Date(ws.saved_at).toLocaleString() + '</td>'
+                    + '<td><button data-do="activate">Open</button><button data-do="export">Export</button><button data-do="delete">Delete</button></td>';
+                row.querySelectorAll('button').forEach(function(button) {
+                    button.onclick = () => act(button.dataset.do, ws).catch(e => { status.textContent = e.message; });
+                });
+                list.appendChild(row);
+            });
+        }
+
+        async function act(action, ws) {
+            if (action === 'activate') {
+                if (!confirm('Close the current tabs and open the ' + ws.tab_count + ' tab(s) of "' + ws.name + '"?')) return;
+                await call('POST', path(ws.name) + '/activate', { confirm: true });
+            } else if (action === 'export') {
+                json.value = JSON.stringify(await call('GET', path(ws.name)), null, 2);
+                json.select();
+                status.textContent = 'Exported "' + ws.name + '"';
+            } else if (action === 'delete') {
+                if (!confirm('Delete workspace "' + ws.name + '"?')) return;
+                await call('DELETE', path(ws.name));
+                status.textContent = 'Deleted "' + ws.name + '"';
+                await load();
+            }
+        }
+
+        document.getElementById('import').onclick = async function() {
+            try {
+                const ws = await call('POST', '/workspaces/import', JSON.parse(json.value));
+                status.textContent = 'Imported "' + ws.name + '"';
+                await load();
+            } catch (e) {
+                status.textContent = e.message;
+            }
+        };
+
+        load().catch(e => { status.textContent = e.message; });
+    </script>
+</body>
+</html>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui_state::TabState;
+
+    fn store_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rb-workspaces-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("workspaces.json")
+    }
+
+    fn ui(urls: &[&str], active_tab: usize) -> UiState {
+        let tabs = urls.iter().enumerate().map(|(i, url)| TabState::sample(i + 10, url)).collect();
+        UiState { tabs, active_tab, ..Default::default() }
+    }
+
+    #[test]
+    fn from_ui_keeps_order_pins_and_active_tab() {
+        let mut state = ui(&["https://a.example/", "https://b.example/", "https://c.example/"], 11);
+        state.tabs[0].pinned = true;
+        let workspace = Workspace::from_ui("work".to_string(), &state);
+        let urls: Vec<&str> = workspace.tabs.iter().map(|t| t.url.as_str()).collect();
+        assert_eq!(urls, ["https://a.example/", "https://b.example/", "https://c.example/"]);
+        let pinned: Vec<bool> = workspace.tabs.iter().map(|t| t.pinned).collect();
+        assert_eq!(pinned, [true, false, false]);
+        assert_eq!((workspace.active, workspace.tabs[1].title.as_str()), (1, "Tab 11"));
+        workspace.validate().unwrap();
+    }
+
+    #[test]
+    fn export_imports_into_another_store() {
+        let mut here = Store::load(store_path("export")).unwrap();
+        let mut state = ui(&["https://a.example/", "https://b.example/?q=1#top"], 11);
+        state.tabs[1].pinned = true;
+        here.put(Workspace::from_ui("shared".to_string(), &state)).unwrap();
+
+        // What GET /workspaces/<name> answers and POST /workspaces/import takes
+        let exported = serde_json::to_string(here.get("shared").unwrap()).unwrap();
+        let mut there = Store::load(store_path("import")).unwrap();
+        there.put(serde_json::from_str(&exported).unwrap()).unwrap();
+
+        let imported = Store::load(there.path.clone()).unwrap();
+        let workspace = imported.get("shared").unwrap();
+        assert_eq!(serde_json::to_string(workspace).unwrap(), exported);
+        assert_eq!((workspace.active, workspace.tabs[1].pinned), (1, true));
+        // Importing doesn't make it the active workspace
+        assert!(imported.last_active().is_none());
+    }
+
+    #[test]
+    fn minimal_exports_import_with_defaults() {
+        let workspace: Workspace = serde_json::from_value(serde_json::json!({
+            "name": "handwritten",
+            "tabs": [{"url": "https://a.example/"}],
+        }))
+        .unwrap();
+        workspace.validate().unwrap();
+        assert_eq!((workspace.active, workspace.saved_at, workspace.tabs[0].pinned), (0, 0, false));
+    }
+
+    #[test]
+    fn rejects_invalid_workspaces() {
+        let tab = |url: &str| WorkspaceTab { url: url.to_string(), title: String::new(), pinned: false };
+        let workspace = |name: &str, tabs: Vec<WorkspaceTab>, active: usize| Workspace { name: name.to_string(), tabs, active, saved_at: 0 };
+
+        assert!(workspace("ok", vec![tab("https://a.example/")], 0).validate().is_ok());
+        let cases = [
+            (workspace(" ", vec![tab("https://a.example/")], 0), "must not be empty"),
+            (workspace(&"x".repeat(MAX_NAME_LEN + 1), vec![tab("https://a.example/")], 0), "longer than 64"),
+            (workspace("a/b", vec![tab("https://a.example/")], 0), "can't be used"),
+            (workspace("import", vec![tab("https://a.example/")], 0), "can't be used"),
+            (workspace("empty", vec![], 0), "at least one tab"),
+            (workspace("range", vec![tab("https://a.example/")], 1), "out of range"),
+            (workspace("url", vec![tab("not a url")], 0), "invalid tab url 'not a url'"),
+        ];
+        for (workspace, error) in cases {
+            let message = workspace.validate().unwrap_err();
+            assert!(message.contains(error), "{}: {}", workspace.name, message);
+        }
+
+        let mut store = Store::load(store_path("invalid")).unwrap();
+        assert!(store.put(workspace("empty", vec![], 0)).is_err());
+        assert!(store.get("empty").is_none());
+    }
+
+    #[test]
+    fn last_active_follows_saves_syncs_and_removal() {
+        let path = store_path("active");
+        let mut store = Store::load(path.clone()).unwrap();
+        store.put(Workspace::from_ui("one".to_string(), &ui(&["https://a.example/"], 10))).unwrap();
+        store.put(Workspace::from_ui("two".to_string(), &ui(&["https://b.example/"], 10))).unwrap();
+        // Nothing active yet, so quitting stores nothing
+        store.sync_active(&ui(&["https://ignored.example/"], 10)).unwrap();
+        assert!(store.last_active().is_none());
+
+        store.set_active("two").unwrap();
+        store.sync_active(&ui(&["https://b.example/", "https://c.example/"], 11)).unwrap();
+        let reloaded = Store::load(path.clone()).unwrap();
+        let restored = reloaded.last_active().unwrap();
+        assert_eq!((restored.name.as_str(), restored.tabs.len(), restored.active), ("two", 2, 1));
+        assert_eq!(reloaded.get("one").unwrap().tabs[0].url, "https://a.example/");
+
+        assert!(store.remove("two").unwrap());
+        assert!(!store.remove("two").unwrap());
+        let reloaded = Store::load(path).unwrap();
+        assert!(reloaded.last_active().is_none());
+        assert_eq!(reloaded.to_json()["workspaces"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn loading_reports_a_corrupt_file() {
+        let path = store_path("corrupt");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(Store::load(path).err().unwrap().starts_with("invalid workspaces file"));
+    }
+}
@@ -0,0 +1,98 @@
+use std::io::Write;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use sha1::{Digest, Sha1};
+
+use crate::{CurrentUrl, FrameNotifier, ScreenshotBuffer};
+
+// ============== WebSocket Frames ==============
+//
+// GET /ws upgrades to a WebSocket that pushes every new live frame: a binary
+// message with the JPEG, or with ?format=json a text message shaped like the
+// /live-stream payload (frame, url, timestamp). Each socket has its own
+// thread that waits on the frame counter the capture loop bumps when it
+// publishes, so viewers never poll. The counter only keeps the latest value:
+// a client that is still receiving an old frame skips the ones published in
+// the meantime, and the capture loop never waits for anyone. Messages from
+// the client are not read; a client that goes away fails the next write and
+// ends its thread.
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+
+/// Where a socket gets its frames.
+pub struct FrameSource {
+    pub buffer: ScreenshotBuffer,
+    pub frames: FrameNotifier,
+    pub current_url: CurrentUrl,
+}
+
+fn header<'a>(request: &'a tiny_http::Request, name: &'static str) -> Option<&'a str> {
+    request.headers().iter().find(|h| h.field.equiv(name)).map(|h| h.value.as_str())
+}
+
+/// One unmasked, unfragmented server message.
+fn write_message(stream: &mut impl Write, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)?;
+    stream.flush()
+}
+
+/// GET /ws. Blocks until the client goes away, so call it from its own
+/// thread.
+pub fn serve(request: tiny_http::Request, source: FrameSource, json: bool) {
+    let upgrade = header(&request, "Upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+    let Some(key) = header(&request, "Sec-WebSocket-Key").filter(|_| upgrade).map(str::to_string) else {
+        let body = serde_json::json!({"error": "expected a WebSocket upgrade"});
+        let _ = crate::request_log::respond(request, crate::json_response(400, &body));
+        return;
+    };
+    let accept = BASE64.encode(Sha1::digest(format!("{}{}", key, ACCEPT_GUID).as_bytes()));
+    let response = tiny_http::Response::empty(101)
+        .with_header(tiny_http::Header::from_bytes(&b"Upgrade"[..], &b"websocket"[..]).unwrap())
+        .with_header(tiny_http::Header::from_bytes(&b"Connection"[..], &b"Upgrade"[..]).unwrap())
+        .with_header(tiny_http::Header::from_bytes(&b"Sec-WebSocket-Accept"[..], accept.as_bytes()).unwrap());
+    // Not a regular response, so it stays out of /requests/recent
+    crate::request_log::detach();
+    let mut stream = request.upgrade("websocket", response);
+    log_info!("WebSocket viewer connected ({})", if json { "json" } else { "binary" });
+
+    let mut frames = source.frames.subscribe();
+    // The current frame first, then one message per published frame
+    loop {
+        if let Some(frame) = source.buffer.lock().unwrap().clone() {
+            let sent = if json {
+                let message = serde_json::json!({
+                    "frame": BASE64.encode(&frame),
+                    "url": source.current_url.lock().unwrap().clone(),
+                    "timestamp": crate::now_millis(),
+                });
+                write_message(&mut stream, OPCODE_TEXT, message.to_string().as_bytes())
+            } else {
+                write_message(&mut stream, OPCODE_BINARY, &frame)
+            };
+            if sent.is_err() {
+                break;
+            }
+        }
+        if futures::executor::block_on(frames.changed()).is_err() {
+            break;
+        }
+        frames.borrow_and_update();
+    }
+    log_info!("WebSocket viewer disconnected");
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use hello_cef_one_shoot_claude::types::{Clip, ScreenshotRequest, WaitUntil};
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>Client fixture</title></head>
+<body style="margin: 0">
+    <button id="go" style="position: absolute; left: 20px; top: 20px; width: 120px; height: 40px" onclick="window.clicks = (window.clicks || 0) + 1">Go</button>
+</body>
+</html>"#;
+
+#[test]
+#[ignore = "needs Chrome"]
+fn navigates_and_reports_status() {
+    let browser = common::browser();
+    let url = browser.load(PAGE);
+
+    let status = browser.status().unwrap();
+    let page = status.page.expect("page info");
+    assert_eq!(page.url.as_deref(), Some(url.as_str()));
+    assert_eq!(page.title, "Client fixture");
+    assert_eq!(page.ready_state, "complete");
+    assert!(status.page_error.is_none());
+    assert_eq!(status.blocking["coverage"]["mode"], "headless");
+    assert!(status.pipeline["encoder"].is_string());
+    assert!(status.sinks.is_empty() && status.uploads.is_none() && status.frame_hook.is_none());
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn queued_navigation_answers_before_the_load() {
+    let browser = common::browser();
+    let url = common::fixtures().page(PAGE);
+    let answer = browser.navigate(&url, WaitUntil::None).unwrap();
+    assert_eq!(answer["status"], "navigating");
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn clicks_and_evaluates() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    browser.click("#go").unwrap();
+    browser.click("#go").unwrap();
+    assert_eq!(browser.evaluate::<u32>("window.clicks").unwrap(), 2);
+    assert_eq!(browser.evaluate::<String>("Promise.resolve(document.title)").unwrap(), "Client fixture");
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn errors_carry_status_and_message() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    let missing = browser.click("#nothing-here").unwrap_err();
+    assert!(missing.status.is_some_and(|s| s >= 400), "{}", missing);
+    assert!(!missing.message.is_empty());
+    assert!(browser.evaluate::<u32>("throw new Error('boom')").is_err());
+    // A result of the wrong type fails on this side
+    let wrong = browser.evaluate::<u32>("'text'").unwrap_err();
+    assert_eq!(wrong.status, None);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn screenshots_in_both_formats() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    let jpeg = browser.screenshot(&ScreenshotRequest::default()).unwrap();
+    assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+
+    let png = browser.screenshot(&ScreenshotRequest { png: true, ..Default::default() }).unwrap();
+    assert_eq!(&png[..4], b"\x89PNG");
+
+    let element = browser.screenshot(&ScreenshotRequest { selector: Some("#go".to_string()), png: true, ..Default::default() }).unwrap();
+    let element = image::load_from_memory(&element).unwrap();
+    assert_eq!((element.width(), element.height()), (120, 40));
+
+    let clip = Clip { x: 0.0, y: 0.0, width: 50.0, height: 30.0 };
+    let clipped = browser.screenshot(&ScreenshotRequest { clip: Some(clip), png: true, ..Default::default() }).unwrap();
+    let clipped = image::load_from_memory(&clipped).unwrap();
+    assert_eq!((clipped.width(), clipped.height()), (50, 30));
+}
@@ -0,0 +1,204 @@
+// Shared harness of the integration tests: a fixture server for test pages
+// and one headless browser server per test binary, started in-process
+// through `run` and driven with `BrowserClient`.
+//
+// The browser tests need Chrome and are #[ignore]d; run them with
+// `cargo test -- --include-ignored`. They share one page, so `browser()`
+// hands out a guard that runs them one at a time.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::ops::Deref;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use hello_cef_one_shoot_claude::client::BrowserClient;
+use hello_cef_one_shoot_claude::types::WaitUntil;
+use hello_cef_one_shoot_claude::Args;
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A port nothing listens on right now.
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+type Headers = Vec<(String, String)>;
+
+struct Page {
+    content_type: String,
+    headers: Headers,
+    body: Vec<u8>,
+}
+
+/// Serves registered pages on 127.0.0.1 and remembers the headers of every
+/// request, so tests can see what the browser sent.
+pub struct Fixtures {
+    port: u16,
+    pages: Mutex<HashMap<String, Page>>,
+    seen: Mutex<Vec<(String, Headers)>>,
+}
+
+pub fn fixtures() -> &'static Fixtures {
+    static FIXTURES: OnceLock<&'static Fixtures> = OnceLock::new();
+    FIXTURES.get_or_init(|| {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let fixtures: &'static Fixtures =
+            Box::leak(Box::new(Fixtures { port, pages: Mutex::new(HashMap::new()), seen: Mutex::new(Vec::new()) }));
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let path = request.url().split('?').next().unwrap_or_default().to_string();
+                let headers = request.headers().iter().map(|h| (h.field.to_string(), h.value.to_string())).collect();
+                fixtures.seen.lock().unwrap().push((path.clone(), headers));
+                let response = match fixtures.pages.lock().unwrap().get(&path) {
+                    Some(page) => {
+                        let mut response = tiny_http::Response::from_data(page.body.clone())
+                            .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], page.content_type.as_bytes()).unwrap());
+                        for (name, value) in &page.headers {
+                            response.add_header(tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap());
+                        }
+                        response
+                    }
+                    None => tiny_http::Response::from_string("not found").with_status_code(404),
+                };
+                let _ = request.respond(response);
+            }
+        });
+        fixtures
+    })
+}
+
+impl Fixtures {
+    /// Serves `html` at a fresh path and returns its URL on 127.0.0.1.
+    pub fn page(&self, html: &str) -> String {
+        self.serve(html, &[])
+    }
+
+    /// Like `page`, with extra response headers such as Set-Cookie.
+    pub fn serve(&self, html: &str, headers: &[(&str, &str)]) -> String {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = format!("/page-{}.html", NEXT.fetch_add(1, Ordering::Relaxed));
+        self.serve_at(&path, "text/html; charset=utf-8", html.as_bytes(), headers);
+        self.url(&path)
+    }
+
+    pub fn serve_at(&self, path: &str, content_type: &str, body: &[u8], headers: &[(&str, &str)]) {
+        let headers = headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let page = Page { content_type: content_type.to_string(), headers, body: body.to_vec() };
+        self.pages.lock().unwrap().insert(path.to_string(), page);
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{}", self.port, path)
+    }
+
+    /// The same server under another origin, for cross-origin iframes.
+    pub fn cross_origin_url(&self, path: &str) -> String {
+        format!("http://localhost:{}{}", self.port, path)
+    }
+
+    /// Value of header `name` on the latest request for `path`.
+    pub fn last_header(&self, path: &str, name: &str) -> Option<String> {
+        let seen = self.seen.lock().unwrap();
+        let (_, headers) = seen.iter().rev().find(|(p, _)| p == path)?;
+        headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.clone())
+    }
+}
+
+/// The browser server with the page to itself until dropped.
+pub struct Session {
+    client: BrowserClient,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl Deref for Session {
+    type Target = BrowserClient;
+
+    fn deref(&self) -> &BrowserClient {
+        &self.client
+    }
+}
+
+impl Session {
+    /// Serves `html` and navigates the streamed page to it.
+    pub fn load(&self, html: &str) -> String {
+        let url = fixtures().page(html);
+        self.client.navigate(&url, WaitUntil::Load).unwrap();
+        url
+    }
+}
+
+/// Port of the server's gRPC API; it is only served with `--features grpc`.
+pub fn grpc_port() -> u16 {
+    static PORT: OnceLock<u16> = OnceLock::new();
+    *PORT.get_or_init(free_port)
+}
+
+/// Base URL of the headless server, started on the first call.
+pub fn server_url() -> &'static str {
+    static SERVER: OnceLock<String> = OnceLock::new();
+    SERVER.get_or_init(|| {
+        let port = free_port().to_string();
+        let grpc_port = grpc_port().to_string();
+        let mut argv = vec!["hello-cef-one-shoot-claude", "--headless", "--port", &port, "--url", "about:blank"];
+        if cfg!(feature = "grpc") {
+            argv.extend(["--grpc-port", &grpc_port]);
+        }
+        let args = Args::try_parse_from(argv).unwrap();
+        std::thread::spawn(move || {
+            if let Err(e) = hello_cef_one_shoot_claude::run(args) {
+                eprintln!("browser server stopped: {}", e);
+            }
+        });
+        let base = format!("http://127.0.0.1:{}", port);
+        wait_until_up(&BrowserClient::new(base.clone()));
+        base
+    })
+}
+
+fn wait_until_up(client: &BrowserClient) {
+    let started = Instant::now();
+    while !client.status().is_ok_and(|s| s.page.is_some()) {
+        assert!(started.elapsed() < STARTUP_TIMEOUT, "the headless server did not come up; is Chrome installed?");
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
+
+/// Another headless server, in a process of its own, stopped when dropped.
+pub struct Instance {
+    child: Child,
+    pub client: BrowserClient,
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Starts a second server next to `server_url()`, for tests that move state
+/// between two instances.
+pub fn instance() -> Instance {
+    let port = free_port().to_string();
+    let child = Command::new(env!("CARGO_BIN_EXE_hello-cef-one-shoot-claude"))
+        .args(["--headless", "--port", &port, "--url", "about:blank"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let instance = Instance { child, client: BrowserClient::new(format!("http://127.0.0.1:{}", port)) };
+    wait_until_up(&instance.client);
+    instance
+}
+
+pub fn browser() -> Session {
+    static LOCK: Mutex<()> = Mutex::new(());
+    let guard = LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    Session { client: BrowserClient::new(server_url()), _guard: guard }
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use serde_json::{json, Value};
+
+// /cookies round trips against the fixture server: cookies set through the
+// API or by a Set-Cookie header show up in GET /cookies and in the Cookie
+// header of the next request, and DELETE takes them out of both.
+
+const PAGE: &str = "<!DOCTYPE html><html><body><p>cookies</p></body></html>";
+
+/// Path of a fixture URL, as `last_header` wants it.
+fn path_of(url: &str) -> String {
+    url.strip_prefix(&common::fixtures().url("")).unwrap().to_string()
+}
+
+/// The Cookie header the fixture server saw for a fresh page load.
+fn sent_cookies(browser: &common::Session) -> String {
+    let url = browser.load(PAGE);
+    common::fixtures().last_header(&path_of(&url), "Cookie").unwrap_or_default()
+}
+
+fn cookie<'a>(cookies: &'a Value, name: &str) -> Option<&'a Value> {
+    cookies.as_array().unwrap().iter().find(|c| c["name"] == name)
+}
+
+fn clear(browser: &common::Session, names: &[&str]) {
+    for name in names {
+        browser.delete_json(&format!("/cookies?name={}&domain=127.0.0.1", name)).unwrap();
+    }
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn set_cookies_are_sent_and_read_back() {
+    let browser = common::browser();
+    browser.load(PAGE);
+    clear(&browser, &["session", "wide"]);
+
+    let expires = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() + 3600;
+    let answer = browser
+        .post_json(
+            "/cookies",
+            &json!([
+                {"name": "session", "value": "abc"},
+                {"name": "wide", "value": "x", "domain": "127.0.0.1", "httpOnly": true, "sameSite": "Lax", "expires": expires},
+            ]),
+        )
+        .unwrap();
+    assert_eq!(answer, json!({"set": 2}));
+
+    let header = sent_cookies(&browser);
+    assert!(header.contains("session=abc"), "{}", header);
+    assert!(header.contains("wide=x"), "{}", header);
+
+    let cookies = browser.get_json("/cookies").unwrap();
+    let session = cookie(&cookies, "session").unwrap();
+    assert_eq!((&session["value"], &session["domain"], &session["path"]), (&json!("abc"), &json!("127.0.0.1"), &json!("/")));
+    assert_eq!((&session["httpOnly"], &session["expires"]), (&json!(false), &Value::Null));
+    let wide = cookie(&cookies, "wide").unwrap();
+    assert_eq!((&wide["httpOnly"], &wide["sameSite"]), (&json!(true), &json!("Lax")));
+    assert!((wide["expires"].as_f64().unwrap() - expires as f64).abs() < 2.0, "{}", wide);
+
+    // HttpOnly stays out of the page's reach
+    let visible: String = browser.evaluate("document.cookie").unwrap();
+    assert!(visible.contains("session=abc") && !visible.contains("wide"), "{}", visible);
+
+    clear(&browser, &["session", "wide"]);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn served_cookies_are_listed_and_deleted() {
+    let browser = common::browser();
+    let url = common::fixtures().serve(PAGE, &[("Set-Cookie", "served=1; Path=/")]);
+    browser.navigate(&url, hello_cef_one_shoot_claude::types::WaitUntil::Load).unwrap();
+
+    let cookies = browser.get_json("/cookies").unwrap();
+    assert_eq!(cookie(&cookies, "served").unwrap()["value"], "1");
+    assert!(sent_cookies(&browser).contains("served=1"));
+
+    // Without a domain the page's URL decides
+    assert_eq!(browser.delete_json("/cookies?name=served").unwrap(), json!({"deleted": 1}));
+    assert!(cookie(&browser.get_json("/cookies").unwrap(), "served").is_none());
+    assert!(!sent_cookies(&browser).contains("served="));
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn a_bad_cookie_sets_nothing() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    let error = browser
+        .post_json("/cookies", &json!([{"name": "good", "value": "1"}, {"name": "bad", "value": "1", "domain": "not a domain"}]))
+        .unwrap_err();
+    assert_eq!(error.status, Some(400));
+    assert!(error.message.contains("does not parse"), "{}", error);
+
+    let error = browser.post_json("/cookies", &json!([{"name": "good", "value": "1", "sameSite": "Sometimes"}])).unwrap_err();
+    assert_eq!(error.status, Some(400));
+
+    let error = browser.delete_json("/cookies").unwrap_err();
+    assert_eq!(error.status, Some(400));
+
+    let cookies = browser.get_json("/cookies").unwrap();
+    assert!(cookie(&cookies, "good").is_none() && cookie(&cookies, "bad").is_none());
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use base64::Engine as _;
+use hello_cef_one_shoot_claude::client::BrowserClient;
+
+// Golden-image check of /render?deterministic=true: a page with a running
+// CSS animation, a blinking caret and a scrollbar renders to the same bytes
+// every time with the option on, and to different bytes without it, so the
+// test shows the option is what makes captures repeatable.
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<style>
+    @keyframes spin { from { transform: rotate(0deg); background: red; } to { transform: rotate(360deg); background: blue; } }
+    #box { width: 120px; height: 120px; margin: 20px; animation: spin 700ms linear infinite; }
+    input { font-size: 24px; }
+</style>
+</head>
+<body>
+    <input id="field" autofocus value="caret">
+    <div id="box"></div>
+    <div style="height: 3000px">tall enough for a scrollbar</div>
+    <script>document.getElementById('field').focus();</script>
+</body>
+</html>"#;
+
+fn render(client: &BrowserClient, url: &str, deterministic: bool) -> Vec<u8> {
+    let path = format!(
+        "/render?format=json&width=400&height=300&deterministic={}&url={}",
+        deterministic,
+        urlencoding::encode(url)
+    );
+    let body = client.get_json(&path).unwrap();
+    base64::engine::general_purpose::STANDARD.decode(body["frame"].as_str().unwrap()).unwrap()
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn deterministic_renders_are_byte_identical() {
+    let browser = common::browser();
+    let url = common::fixtures().page(PAGE);
+
+    let first = render(&browser, &url, true);
+    let second = render(&browser, &url, true);
+    assert!(first == second, "deterministic renders differ ({} vs {} bytes)", first.len(), second.len());
+
+    // Without the option the animation is caught at a different point each time
+    let renders: Vec<Vec<u8>> = (0..3).map(|_| render(&browser, &url, false)).collect();
+    assert!(renders.windows(2).any(|pair| pair[0] != pair[1]), "plain renders of an animated page were identical");
+}
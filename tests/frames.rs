@@ -0,0 +1,160 @@
+mod common;
+
+use hello_cef_one_shoot_claude::types::{InteractRequest, WaitUntil};
+use serde_json::{json, Value};
+
+// Frame-scoped /click, /type and /hover against a page with a same-origin
+// iframe that nests another one, and a cross-origin iframe (localhost
+// instead of 127.0.0.1). The frames report back to the top page with
+// postMessage, which the tests read through /evaluate.
+
+/// A button and a field at a fixed spot; clicks send the field's value up.
+fn widget(name: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<body style="margin: 0">
+    <input id="field" style="position: absolute; left: 10px; top: 60px">
+    <button id="send" style="position: absolute; left: 10px; top: 20px; width: 100px; height: 30px"
+        onclick="top.postMessage({{frame: '{name}', event: 'click', value: document.getElementById('field').value}}, '*')"
+        onmouseover="top.postMessage({{frame: '{name}', event: 'hover'}}, '*')">Send</button>
+</body>
+</html>"#
+    )
+}
+
+const OUTER: &str = r#"<!DOCTYPE html>
+<html>
+<body style="margin: 0">
+    <iframe name="nested" src="/frames/nested.html" style="position: absolute; left: 40px; top: 30px; width: 200px; height: 120px; border: 0"></iframe>
+</body>
+</html>"#;
+
+/// Loads the top page and answers its URL and the cross-origin frame's URL.
+fn load(browser: &common::Session) -> (String, String) {
+    let fixtures = common::fixtures();
+    let html = "text/html; charset=utf-8";
+    fixtures.serve_at("/frames/outer.html", html, OUTER.as_bytes(), &[]);
+    fixtures.serve_at("/frames/nested.html", html, widget("nested").as_bytes(), &[]);
+    fixtures.serve_at("/frames/cross.html", html, widget("cross").as_bytes(), &[]);
+    let cross = fixtures.cross_origin_url("/frames/cross.html");
+    let top = format!(
+        r#"<!DOCTYPE html>
+<html>
+<body style="margin: 0">
+    <iframe name="outer" src="/frames/outer.html" style="position: absolute; left: 0; top: 0; width: 300px; height: 200px; border: 0"></iframe>
+    <iframe name="cross" src="{}" style="position: absolute; left: 320px; top: 100px; width: 200px; height: 120px; border: 0"></iframe>
+    <button id="send">Top</button>
+    <script>
+        window.messages = [];
+        window.addEventListener('message', function(e) {{ messages.push(e.data); }});
+    </script>
+</body>
+</html>"#,
+        cross
+    );
+    let url = fixtures.page(&top);
+    browser.navigate(&url, WaitUntil::Load).unwrap();
+    (url, cross)
+}
+
+fn in_frame(frame: &str, selector: &str) -> InteractRequest {
+    InteractRequest { selector: selector.to_string(), frame: Some(frame.to_string()), ..Default::default() }
+}
+
+fn messages(browser: &common::Session) -> Vec<Value> {
+    browser.evaluate("window.messages").unwrap()
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn tree_lists_nested_and_cross_origin_frames() {
+    let browser = common::browser();
+    let (url, cross) = load(&browser);
+
+    let tree = browser.get_json("/frames-tree").unwrap();
+    assert_eq!(tree["url"], url);
+    let children = tree["children"].as_array().unwrap();
+    let names: Vec<&str> = children.iter().map(|c| c["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["outer", "cross"]);
+    assert_eq!(children[1]["url"], cross);
+    assert!(children[1]["children"].as_array().unwrap().is_empty());
+
+    let nested = &children[0]["children"][0];
+    assert_eq!(nested["name"], "nested");
+    assert_eq!(nested["url"], common::fixtures().url("/frames/nested.html"));
+    assert!(nested["id"].as_str().is_some_and(|id| !id.is_empty()));
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn types_and_clicks_in_a_nested_same_origin_frame() {
+    let browser = common::browser();
+    load(&browser);
+
+    let typing = InteractRequest { text: "hello".to_string(), ..in_frame("nested", "#field") };
+    browser.interact("/type", &typing).unwrap();
+    browser.interact("/click", &in_frame("nested", "#send")).unwrap();
+    assert_eq!(messages(&browser), [json!({"frame": "nested", "event": "click", "value": "hello"})]);
+
+    // Without a frame the selector resolves in the top document
+    browser.click("#send").unwrap();
+    assert_eq!(messages(&browser).len(), 1);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn types_and_clicks_in_a_cross_origin_frame() {
+    let browser = common::browser();
+    load(&browser);
+
+    let glob = "http://localhost:*/frames/cross.html";
+    let typing = InteractRequest { text: "across".to_string(), ..in_frame(glob, "#field") };
+    browser.interact("/type", &typing).unwrap();
+    browser.interact("/click", &in_frame("cross", "#send")).unwrap();
+    assert_eq!(messages(&browser), [json!({"frame": "cross", "event": "click", "value": "across"})]);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn frames_resolve_by_id() {
+    let browser = common::browser();
+    load(&browser);
+
+    let tree = browser.get_json("/frames-tree").unwrap();
+    let id = tree["children"][0]["children"][0]["id"].as_str().unwrap().to_string();
+    browser.interact("/click", &in_frame(&id, "#send")).unwrap();
+    assert_eq!(messages(&browser)[0]["frame"], "nested");
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn hover_translates_through_frame_offsets() {
+    let browser = common::browser();
+    load(&browser);
+
+    // The button's centre is (60, 35) inside its frame
+    for (frame, expected) in [("nested", (40.0 + 60.0, 30.0 + 35.0)), ("cross", (320.0 + 60.0, 100.0 + 35.0))] {
+        let point = browser.interact("/hover", &in_frame(frame, "#send")).unwrap();
+        let (x, y) = (point["x"].as_f64().unwrap(), point["y"].as_f64().unwrap());
+        assert!((x - expected.0).abs() < 1.0 && (y - expected.1).abs() < 1.0, "{}: ({}, {}) != {:?}", frame, x, y, expected);
+    }
+    let hovered: Vec<Value> = messages(&browser).into_iter().filter(|m| m["event"] == "hover").map(|m| m["frame"].clone()).collect();
+    assert_eq!(hovered, [json!("nested"), json!("cross")]);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn unknown_frames_and_selectors_are_errors() {
+    let browser = common::browser();
+    load(&browser);
+
+    let error = browser.interact("/click", &in_frame("nope", "#send")).unwrap_err();
+    assert_eq!(error.status, Some(404));
+    assert!(error.message.contains("no frame matches 'nope'"), "{}", error);
+
+    // #field only exists inside the frames
+    let error = browser.interact("/hover", &InteractRequest { selector: "#field".to_string(), ..Default::default() }).unwrap_err();
+    assert_eq!(error.status, Some(404));
+    assert!(messages(&browser).is_empty());
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "grpc")]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use hello_cef_one_shoot_claude::grpc_proto::browser_control_client::BrowserControlClient;
+use hello_cef_one_shoot_claude::grpc_proto::*;
+use tonic::transport::Channel;
+
+// Round trips through the generated client against --grpc-port, which the
+// shared test server serves when the tests are built with `--features grpc`.
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<body style="background: white">
+    <button id="go" onclick="document.title = 'clicked'; document.body.style.background = 'navy'">Go</button>
+</body>
+</html>"#;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+async fn connect() -> BrowserControlClient<Channel> {
+    common::server_url();
+    let endpoint = format!("http://127.0.0.1:{}", common::grpc_port());
+    let started = Instant::now();
+    loop {
+        match BrowserControlClient::connect(endpoint.clone()).await {
+            Ok(client) => return client,
+            Err(e) => assert!(started.elapsed() < CONNECT_TIMEOUT, "gRPC server did not come up: {}", e),
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+fn is_jpeg(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0xFF, 0xD8])
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn unary_calls_round_trip() {
+    let _browser = common::browser();
+    let url = common::fixtures().page(PAGE);
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let mut client = connect().await;
+
+        let navigated = client.navigate(NavigateRequest { url: url.clone() }).await.unwrap().into_inner();
+        assert_eq!(navigated.url, url);
+
+        let status = client.status(StatusRequest {}).await.unwrap().into_inner();
+        assert_eq!(status.url, url);
+        assert!(status.navigation_seq > 0);
+        assert!(status.viewport_width > 0 && status.viewport_height > 0);
+
+        client.click(ClickRequest { selector: "#go".into(), ..Default::default() }).await.unwrap();
+        let title = client
+            .evaluate(EvaluateRequest { expression: "document.title".into(), await_promise: false })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(title.result_json, "\"clicked\"");
+
+        let awaited = client
+            .evaluate(EvaluateRequest { expression: "Promise.resolve({n: 1})".into(), await_promise: true })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&awaited.result_json).unwrap(), serde_json::json!({"n": 1}));
+
+        let frame = client.screenshot(ScreenshotRequest::default()).await.unwrap().into_inner();
+        assert!(is_jpeg(&frame.jpeg));
+        assert_eq!((frame.url.as_str(), frame.seq), (url.as_str(), 0));
+
+        let missing = client.click(ClickRequest { selector: "#nothing-here".into(), ..Default::default() }).await.unwrap_err();
+        assert_ne!(missing.code(), tonic::Code::Ok);
+        assert!(!missing.message().is_empty());
+    });
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn streams_live_frames() {
+    let _browser = common::browser();
+    let url = common::fixtures().page(PAGE);
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let mut client = connect().await;
+        client.navigate(NavigateRequest { url: url.clone() }).await.unwrap();
+
+        let mut frames = client.stream_frames(StreamFramesRequest { max_fps: 0 }).await.unwrap().into_inner();
+        // A repaint publishes a new live frame
+        client.click(ClickRequest { selector: "#go".into(), ..Default::default() }).await.unwrap();
+
+        let mut last_seq = 0;
+        for _ in 0..2 {
+            let frame = tokio::time::timeout(Duration::from_secs(10), frames.message())
+                .await
+                .expect("no live frame within 10s")
+                .unwrap()
+                .expect("stream ended");
+            assert!(is_jpeg(&frame.jpeg));
+            assert_eq!(frame.url, url);
+            assert!(frame.seq > last_seq, "{} after {}", frame.seq, last_seq);
+            last_seq = frame.seq;
+            client.evaluate(EvaluateRequest { expression: "document.body.style.background = 'teal'".into(), await_promise: false }).await.unwrap();
+        }
+    });
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+// A logged-in session moved between two headless instances: the cookie,
+// local storage and scroll position set on the shared server come back on
+// a second instance after POST /handoff/export there and /handoff/import
+// here.
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<body style="margin: 0">
+    <div style="height: 5000px; background: linear-gradient(white, black)">tall</div>
+</body>
+</html>"#;
+
+#[test]
+#[ignore = "needs Chrome"]
+fn session_moves_to_another_instance() {
+    let a = common::browser();
+    let url = a.load(PAGE);
+    let scroll: f64 = a
+        .evaluate(
+            "document.cookie = 'session=s3cret; path=/'; \
+             localStorage.setItem('user', 'alice'); \
+             window.scrollTo(0, 1200); \
+             window.scrollY",
+        )
+        .unwrap();
+    assert_eq!(scroll, 1200.0);
+
+    let exported = a.post_json("/handoff/export", &serde_json::json!({})).unwrap();
+    assert_eq!(exported["url"], url.as_str());
+
+    let b = common::instance();
+    b.client.post_json("/handoff/import", &exported).unwrap();
+
+    let (cookie, user): (String, Option<String>) =
+        b.client.evaluate("[document.cookie, localStorage.getItem('user')]").unwrap();
+    assert!(cookie.split("; ").any(|c| c == "session=s3cret"), "cookies on B: {}", cookie);
+    assert_eq!(user.as_deref(), Some("alice"));
+
+    // The stream resumes where A left off
+    let started = Instant::now();
+    loop {
+        let (location, scroll): (String, f64) = b.client.evaluate("[location.href, window.scrollY]").unwrap();
+        if location == url && scroll == 1200.0 {
+            break;
+        }
+        assert!(started.elapsed() < Duration::from_secs(10), "B is at {} scrolled to {}", location, scroll);
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use serde_json::{json, Value};
+
+// /press against a page that records every keydown: chords, plain
+// characters and named keys arrive with the key, code and keyCode a real
+// keyboard would produce.
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<body>
+    <input id="field">
+    <script>
+        window.keys = [];
+        document.addEventListener('keydown', e => keys.push({
+            key: e.key, code: e.code, keyCode: e.keyCode,
+            ctrl: e.ctrlKey, shift: e.shiftKey, alt: e.altKey, meta: e.metaKey,
+        }));
+    </script>
+</body>
+</html>"#;
+
+fn load(browser: &common::Session) {
+    browser.load(PAGE);
+    browser.evaluate::<Value>("document.getElementById('field').focus()").unwrap();
+}
+
+fn press(browser: &common::Session, body: Value) -> Value {
+    browser.post_json("/press", &body).unwrap()
+}
+
+fn keys(browser: &common::Session) -> Vec<Value> {
+    browser.evaluate("window.keys").unwrap()
+}
+
+fn value(browser: &common::Session) -> String {
+    browser.evaluate("document.getElementById('field').value").unwrap()
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn chords_press_the_key_with_modifiers() {
+    let browser = common::browser();
+    load(&browser);
+
+    let answer = press(&browser, json!({"key": "Control+Shift+A"}));
+    assert_eq!(answer, json!({"key": "A", "modifiers": ["control", "shift"]}));
+
+    // Only the final key is pressed; the modifiers ride along as flags
+    assert_eq!(
+        keys(&browser),
+        [json!({"key": "A", "code": "KeyA", "keyCode": 65, "ctrl": true, "shift": true, "alt": false, "meta": false})]
+    );
+    // A shortcut types nothing
+    assert_eq!(value(&browser), "");
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn characters_are_typed_into_the_focused_element() {
+    let browser = common::browser();
+    load(&browser);
+
+    press(&browser, json!({"key": "a"}));
+    press(&browser, json!({"key": "b", "modifiers": ["shift"]}));
+    press(&browser, json!({"key": "7"}));
+    assert_eq!(value(&browser), "aB7");
+
+    let recorded: Vec<(String, String, u64)> = keys(&browser)
+        .iter()
+        .map(|k| (k["key"].as_str().unwrap().to_string(), k["code"].as_str().unwrap().to_string(), k["keyCode"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(
+        recorded,
+        [("a".into(), "KeyA".into(), 65), ("B".into(), "KeyB".into(), 66), ("7".into(), "Digit7".into(), 55)]
+    );
+
+    press(&browser, json!({"key": "Backspace"}));
+    assert_eq!(value(&browser), "aB");
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn named_keys_carry_their_codes() {
+    let browser = common::browser();
+    load(&browser);
+
+    press(&browser, json!({"key": "enter"}));
+    press(&browser, json!({"key": "ArrowDown"}));
+    press(&browser, json!({"key": "Shift+Tab"}));
+
+    let keys = keys(&browser);
+    assert_eq!(keys.len(), 3);
+    assert_eq!((&keys[0]["key"], &keys[0]["code"], &keys[0]["keyCode"]), (&json!("Enter"), &json!("Enter"), &json!(13)));
+    assert_eq!((&keys[1]["key"], &keys[1]["code"], &keys[1]["keyCode"]), (&json!("ArrowDown"), &json!("ArrowDown"), &json!(40)));
+    assert_eq!((&keys[2]["key"], &keys[2]["keyCode"], &keys[2]["shift"]), (&json!("Tab"), &json!(9), &json!(true)));
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn unknown_keys_are_rejected() {
+    let browser = common::browser();
+    load(&browser);
+
+    let error = browser.post_json("/press", &json!({"key": "Control+Foo"})).unwrap_err();
+    assert_eq!(error.status, Some(400));
+    assert!(error.message.contains("unknown key 'Foo'"), "{}", error);
+
+    let error = browser.post_json("/press", &json!({"key": "Hyper+A"})).unwrap_err();
+    assert_eq!(error.status, Some(400));
+    assert!(error.message.contains("unknown modifier 'Hyper'"), "{}", error);
+
+    assert!(keys(&browser).is_empty());
+}
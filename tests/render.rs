@@ -0,0 +1,119 @@
+mod common;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use base64::Engine as _;
+use hello_cef_one_shoot_claude::client::BrowserClient;
+use hello_cef_one_shoot_claude::types::WaitUntil;
+
+// GET /render runs in its own page and browser context: hammering it with
+// page B while page A streams must never put a B frame into A's stream.
+
+/// A full-viewport colour with a ticking counter, so every capture differs.
+fn page(colour: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<body style="margin: 0; height: 100vh; background: {}">
+    <p id="tick" style="margin: 0; font-size: 10px">0</p>
+    <script>
+        let n = 0;
+        setInterval(function() {{ document.getElementById('tick').textContent = ++n; }}, 30);
+    </script>
+</body>
+</html>"#,
+        colour
+    )
+}
+
+/// Mean (r, g, b) of a JPEG.
+fn mean_colour(jpeg: &[u8]) -> (u32, u32, u32) {
+    let image = image::load_from_memory(jpeg).unwrap().to_rgb8();
+    let count = image.pixels().len() as u64;
+    let sum = image.pixels().fold([0u64; 3], |acc, p| [acc[0] + p[0] as u64, acc[1] + p[1] as u64, acc[2] + p[2] as u64]);
+    ((sum[0] / count) as u32, (sum[1] / count) as u32, (sum[2] / count) as u32)
+}
+
+fn is_red((r, g, b): (u32, u32, u32)) -> bool {
+    r > 180 && g < 80 && b < 80
+}
+
+fn is_blue((r, g, b): (u32, u32, u32)) -> bool {
+    b > 180 && r < 80 && g < 80
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn renders_never_reach_the_stream() {
+    let browser = common::browser();
+    let a = common::fixtures().page(&page("rgb(220, 0, 0)"));
+    let b = common::fixtures().page(&page("rgb(0, 0, 220)"));
+    browser.navigate(&a, WaitUntil::Load).unwrap();
+
+    let started = Instant::now();
+    while browser.frame().map(|(_, url)| url != a).unwrap_or(true) {
+        assert!(started.elapsed() < Duration::from_secs(10), "no live frame of page A");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let done = Arc::new(AtomicBool::new(false));
+    let (rendered, saturated) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    let path = format!("/render?format=json&width=320&height=240&url={}", urlencoding::encode(&b));
+    let hammers: Vec<_> = (0..4)
+        .map(|_| {
+            let (path, rendered, saturated) = (path.clone(), rendered.clone(), saturated.clone());
+            let b = b.clone();
+            std::thread::spawn(move || {
+                let client = BrowserClient::new(common::server_url());
+                for _ in 0..5 {
+                    match client.get_json(&path) {
+                        Ok(body) => {
+                            assert_eq!(body["url"], b.as_str());
+                            let jpeg = base64::engine::general_purpose::STANDARD.decode(body["frame"].as_str().unwrap()).unwrap();
+                            assert!(is_blue(mean_colour(&jpeg)), "render of B is not blue: {:?}", mean_colour(&jpeg));
+                            rendered.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) if e.status == Some(503) => {
+                            saturated.fetch_add(1, Ordering::Relaxed);
+                            std::thread::sleep(Duration::from_millis(100));
+                        }
+                        Err(e) => panic!("render failed: {}", e),
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let watcher = {
+        let done = done.clone();
+        let a = a.clone();
+        std::thread::spawn(move || {
+            let client = BrowserClient::new(common::server_url());
+            let mut frames = 0;
+            while !done.load(Ordering::Relaxed) {
+                let (jpeg, url) = client.frame().unwrap();
+                assert_eq!(url, a, "a live frame showed another page");
+                let colour = mean_colour(&jpeg);
+                assert!(is_red(colour), "live frame {} is not page A: {:?}", frames, colour);
+                frames += 1;
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            frames
+        })
+    };
+
+    for hammer in hammers {
+        hammer.join().unwrap();
+    }
+    done.store(true, Ordering::Relaxed);
+    let frames = watcher.join().unwrap();
+
+    assert!(frames > 10, "only {} live frames checked", frames);
+    assert!(rendered.load(Ordering::Relaxed) > 0, "every render was turned away ({} saturated)", saturated.load(Ordering::Relaxed));
+    // The streamed page stayed where it was
+    assert_eq!(browser.evaluate::<String>("location.href").unwrap(), a);
+    let viewport: (u32, u32) = browser.evaluate("[innerWidth, innerHeight]").unwrap();
+    assert_ne!(viewport, (320, 240));
+}
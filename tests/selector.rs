@@ -0,0 +1,91 @@
+mod common;
+
+use hello_cef_one_shoot_claude::RECORDER_SCRIPT;
+
+// The selector generator of the GUI recorder (window.__rbSelector in
+// journal.rs), run in the headless page: ids first, then stable data-*
+// attributes, then nth-of-type paths, always unique in the document.
+
+const PAGE: &str = r##"<!DOCTYPE html>
+<html>
+<body>
+    <header><button id="save">Save</button></header>
+    <form>
+        <button id="btn-4f3a9c2e1b" data-testid="submit">Submit</button>
+        <input id=":r0:" name="email">
+        <button id="ember1234">Generated</button>
+    </form>
+    <ul id="menu"><li>One</li><li>Two</li><li>Three</li></ul>
+    <ol><li>First</li><li>Second</li></ol>
+    <section><p>First</p></section>
+    <section><p>Second</p><p>Third <em>emphasis</em></p></section>
+    <div class="card"><span><a href="#a">A</a></span></div>
+    <div class="card"><span><a href="#b">B</a></span></div>
+</body>
+</html>"##;
+
+fn selector_of(browser: &common::Session, css: &str) -> String {
+    let expression = format!("window.__rbSelector(document.querySelector({}))", serde_json::to_string(css).unwrap());
+    browser.evaluate(&expression).unwrap()
+}
+
+fn load(browser: &common::Session) {
+    browser.load(PAGE);
+    browser.evaluate::<serde_json::Value>(RECORDER_SCRIPT).unwrap();
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn prefers_ids() {
+    let browser = common::browser();
+    load(&browser);
+    assert_eq!(selector_of(&browser, "header button"), "#save");
+    assert_eq!(selector_of(&browser, "#menu"), "#menu");
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn skips_generated_ids_for_stable_attributes() {
+    let browser = common::browser();
+    load(&browser);
+    assert_eq!(selector_of(&browser, "[data-testid=submit]"), "button[data-testid=\"submit\"]");
+    assert_eq!(selector_of(&browser, "input"), "input[name=\"email\"]");
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn falls_back_to_nth_of_type() {
+    let browser = common::browser();
+    load(&browser);
+    assert_eq!(selector_of(&browser, "#menu li:nth-child(2)"), "#menu > li:nth-of-type(2)");
+    // A generated id is no anchor; its position is used instead
+    assert_eq!(selector_of(&browser, "form button:last-of-type"), "button:nth-of-type(2)");
+    assert_eq!(selector_of(&browser, "section:nth-of-type(2) p:nth-of-type(1)"), "section:nth-of-type(2) > p:nth-of-type(1)");
+    assert_eq!(selector_of(&browser, "div:nth-of-type(2) a"), "div:nth-of-type(2) > span > a");
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn stops_at_the_shortest_unique_suffix() {
+    let browser = common::browser();
+    load(&browser);
+    assert_eq!(selector_of(&browser, "em"), "em");
+    assert_eq!(selector_of(&browser, "body"), "body");
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn every_selector_is_unique_and_finds_its_element() {
+    let browser = common::browser();
+    load(&browser);
+    let failures: Vec<String> = browser
+        .evaluate(
+            "Array.from(document.querySelectorAll('*'))
+                .map(el => [el, window.__rbSelector(el)])
+                .filter(([el, s]) => document.querySelectorAll(s).length !== 1 || document.querySelector(s) !== el)
+                .map(([el, s]) => el.outerHTML.slice(0, 60) + ' -> ' + s)",
+        )
+        .unwrap();
+    assert!(failures.is_empty(), "{:#?}", failures);
+    assert!(browser.evaluate::<serde_json::Value>("window.__rbSelector(document)").unwrap().is_null());
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use hello_cef_one_shoot_claude::types::{InteractRequest, Stability, TypeMethod};
+
+// Stable click/type/hover on elements inside open shadow roots: the
+// obscured check must look through the shadow host at the element on top.
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<body>
+    <x-card></x-card>
+    <x-outer></x-outer>
+    <x-covered></x-covered>
+    <div style="position: relative; width: 200px">
+        <x-card id="under"></x-card>
+        <div id="overlay" style="position: absolute; inset: 0; background: rgba(0, 0, 0, 0.5)"></div>
+    </div>
+    <script>
+        const count = (name) => 'window.' + name + ' = (window.' + name + ' || 0) + 1';
+        customElements.define('x-card', class extends HTMLElement {
+            constructor() {
+                super();
+                this.attachShadow({ mode: 'open' }).innerHTML =
+                    '<button id="inner" onclick="' + count('clicks') + '" onmouseover="' + count('hovers') + '"><span>Inner</span></button>' +
+                    '<input id="field">';
+            }
+        });
+        customElements.define('x-inner', class extends HTMLElement {
+            constructor() {
+                super();
+                this.attachShadow({ mode: 'open' }).innerHTML = '<button id="deep" onclick="' + count('deep') + '">Deep</button>';
+            }
+        });
+        customElements.define('x-outer', class extends HTMLElement {
+            constructor() {
+                super();
+                this.attachShadow({ mode: 'open' }).innerHTML = '<div><x-inner></x-inner></div>';
+            }
+        });
+        customElements.define('x-covered', class extends HTMLElement {
+            constructor() {
+                super();
+                this.attachShadow({ mode: 'open' }).innerHTML =
+                    '<div style="position: relative; width: 200px"><button id="covered">Covered</button>' +
+                    '<div class="cover" style="position: absolute; inset: 0; background: red"></div></div>';
+            }
+        });
+    </script>
+</body>
+</html>"#;
+
+fn pierced(selector: &str) -> InteractRequest {
+    InteractRequest { selector: selector.to_string(), pierce: true, ..Default::default() }
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn clicks_inside_an_open_shadow_root() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    browser.click("x-card >>> #inner").unwrap();
+    browser.interact("/click", &pierced("#inner")).unwrap();
+    assert_eq!(browser.evaluate::<u32>("window.clicks").unwrap(), 2);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn clicks_through_nested_shadow_roots() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    browser.click("x-outer >>> x-inner >>> #deep").unwrap();
+    browser.click(">>> #deep").unwrap();
+    assert_eq!(browser.evaluate::<u32>("window.deep").unwrap(), 2);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn types_and_hovers_inside_a_shadow_root() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    let typed = InteractRequest { text: "shadow text".to_string(), method: TypeMethod::Keys, ..pierced("#field") };
+    browser.interact("/type", &typed).unwrap();
+    let value: String = browser.evaluate("document.querySelector('x-card').shadowRoot.querySelector('#field').value").unwrap();
+    assert_eq!(value, "shadow text");
+
+    browser.interact("/hover", &pierced("#inner")).unwrap();
+    assert!(browser.evaluate::<u32>("window.hovers").unwrap() >= 1);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn cover_inside_the_shadow_root_obscures() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    let error = browser.click("x-covered >>> #covered").unwrap_err();
+    assert!(error.message.contains("obscured by .cover"), "{}", error);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn light_dom_overlay_obscures_a_shadow_element() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    let error = browser.click("#under >>> #inner").unwrap_err();
+    assert!(error.message.contains("obscured by #overlay"), "{}", error);
+    // Without the stability check the overlay is not looked at
+    let unchecked = InteractRequest { stability: Stability { stable: false, ..Default::default() }, ..pierced("#under >>> #inner") };
+    browser.interact("/click", &unchecked).unwrap();
+}
+
+fn element(browser: &common::Session, selector: &str, all: bool) -> serde_json::Value {
+    browser.get_json(&format!("/element?all={}&selector={}", all, urlencoding::encode(selector))).unwrap()
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn element_lookups_return_a_path_per_match() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    let deep = element(&browser, "x-outer >>> x-inner >>> button", true);
+    assert_eq!(deep[0]["path"], "x-outer:nth-of-type(1) >>> x-inner:nth-of-type(1) >>> #deep");
+    browser.click(deep[0]["path"].as_str().unwrap()).unwrap();
+    assert_eq!(browser.evaluate::<u32>("window.deep").unwrap(), 1);
+
+    // Both cards share a selector; each path finds its own one again
+    let cards = element(&browser, "x-card", true);
+    let cards = cards.as_array().unwrap();
+    assert_eq!(cards.len(), 2);
+    assert_eq!(cards[1]["path"], "#under");
+    for card in cards {
+        let found = element(&browser, card["path"].as_str().unwrap(), false);
+        assert_eq!((&found["page_x"], &found["page_y"]), (&card["page_x"], &card["page_y"]), "{}", card["path"]);
+    }
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use std::process::Command;
+
+// The soak run as a test: the real binary with --soak against
+// the benchmark fixture, which fails if RSS leaves the budget or a buffer
+// outgrows its cap. It takes minutes, so besides needing Chrome it only runs
+// when SOAK_MINUTES is set:
+//
+//     SOAK_MINUTES=30 cargo test --release --test soak -- --include-ignored
+//
+// SOAK_RSS_BUDGET_MB overrides the binary's default budget.
+
+#[test]
+#[ignore = "needs Chrome"]
+fn memory_stays_within_budget() {
+    let Some(minutes) = std::env::var("SOAK_MINUTES").ok() else {
+        eprintln!("SOAK_MINUTES is not set, skipping the soak run");
+        return;
+    };
+    let port = common::free_port().to_string();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_hello-cef-one-shoot-claude"));
+    command.args(["--headless", "--port", &port, "--soak", &minutes]);
+    if let Ok(budget) = std::env::var("SOAK_RSS_BUDGET_MB") {
+        command.args(["--soak-rss-budget-mb", &budget]);
+    }
+    let output = command.output().unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let summary: serde_json::Value = stdout
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str(line).ok())
+        .unwrap_or_else(|| panic!("no soak summary in the output:\n{}", stdout));
+    assert!(output.status.success() && summary["passed"] == true, "soak failed: {:#}", summary);
+    assert!(summary["samples"].as_u64() > Some(0), "no RSS samples taken: {:#}", summary);
+    for buffer in summary["buffers"].as_array().unwrap() {
+        assert!(buffer["len"].as_u64() <= buffer["capacity"].as_u64(), "{}", buffer);
+    }
+}
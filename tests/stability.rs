@@ -0,0 +1,123 @@
+mod common;
+
+use hello_cef_one_shoot_claude::types::{InteractRequest, Stability, WaitUntil};
+
+// Stable click/type against delayed layout shifts: an accordion that keeps
+// growing above the target after load, an element that never stops moving
+// and a cookie banner that shows up once the layout settles.
+
+/// The slot above the target grows 10px every 20ms for 300ms after load.
+const SHIFTING: &str = r#"<!DOCTYPE html>
+<html>
+<body>
+    <div id="slot" style="height: 0"></div>
+    <button id="target" onclick="window.clickedAt = performance.now(); window.clickedTop = this.getBoundingClientRect().top">Target</button>
+    <input id="field">
+    <script>
+        window.addEventListener('load', function() {
+            let height = 0;
+            const grow = setInterval(function() {
+                height += 10;
+                document.getElementById('slot').style.height = height + 'px';
+                if (height >= 150) {
+                    clearInterval(grow);
+                    window.settledAt = performance.now();
+                    if (location.hash === '#banner') {
+                        const banner = document.createElement('div');
+                        banner.className = 'cookie-banner';
+                        banner.style = 'position: fixed; inset: 0; background: rgba(0, 0, 0, 0.6)';
+                        document.body.appendChild(banner);
+                    }
+                }
+            }, 20);
+        });
+    </script>
+</body>
+</html>"#;
+
+const ANIMATED: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <style>
+        @keyframes slide { from { transform: translateX(0) } to { transform: translateX(200px) } }
+        #moving { animation: slide 400ms linear infinite alternate }
+    </style>
+</head>
+<body>
+    <button id="moving" onclick="window.clicked = true">Moving</button>
+</body>
+</html>"#;
+
+fn request(selector: &str, stable: bool, timeout_ms: u64) -> InteractRequest {
+    InteractRequest { selector: selector.to_string(), stability: Stability { stable, timeout_ms }, ..Default::default() }
+}
+
+fn number(browser: &common::Session, expression: &str) -> Option<f64> {
+    browser.evaluate(expression).unwrap()
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn waits_for_a_delayed_layout_shift() {
+    let browser = common::browser();
+    browser.load(SHIFTING);
+
+    browser.click("#target").unwrap();
+    let settled_at = number(&browser, "window.settledAt").expect("the click came before the shift ended");
+    let clicked_at = number(&browser, "window.clickedAt").unwrap();
+    assert!(clicked_at >= settled_at, "clicked at {} before the layout settled at {}", clicked_at, settled_at);
+    let final_top = number(&browser, "document.getElementById('target').getBoundingClientRect().top");
+    assert_eq!(number(&browser, "window.clickedTop"), final_top);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn without_stability_acts_right_away() {
+    let browser = common::browser();
+    browser.load(SHIFTING);
+
+    browser.interact("/click", &request("#target", false, 2000)).unwrap();
+    assert!(number(&browser, "window.clickedAt").is_some());
+    assert_eq!(number(&browser, "window.settledAt"), None);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn types_once_the_field_stops_moving() {
+    let browser = common::browser();
+    browser.load(SHIFTING);
+
+    let typing = InteractRequest { text: "settled".to_string(), ..request("#field", true, 2000) };
+    browser.interact("/type", &typing).unwrap();
+    assert!(number(&browser, "window.settledAt").is_some());
+    assert_eq!(browser.evaluate::<String>("document.getElementById('field').value").unwrap(), "settled");
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn gives_up_on_an_element_that_keeps_moving() {
+    let browser = common::browser();
+    browser.load(ANIMATED);
+
+    let started = std::time::Instant::now();
+    let error = browser.interact("/click", &request("#moving", true, 300)).unwrap_err();
+    assert_eq!(error.status, Some(422));
+    assert!(error.message.contains("#moving did not stop moving within 300 ms"), "{}", error);
+    assert!(started.elapsed().as_millis() >= 300);
+    assert!(!browser.evaluate::<bool>("window.clicked === true").unwrap());
+
+    browser.interact("/click", &request("#moving", false, 300)).unwrap();
+    assert!(browser.evaluate::<bool>("window.clicked === true").unwrap());
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn a_banner_after_the_shift_obscures_the_target() {
+    let browser = common::browser();
+    let url = format!("{}#banner", common::fixtures().page(SHIFTING));
+    browser.navigate(&url, WaitUntil::Load).unwrap();
+
+    let error = browser.click("#target").unwrap_err();
+    assert!(error.message.contains("element obscured by .cookie-banner"), "{}", error);
+    assert_eq!(number(&browser, "window.clickedAt"), None);
+}
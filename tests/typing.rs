@@ -0,0 +1,78 @@
+mod common;
+
+use hello_cef_one_shoot_claude::types::{InteractRequest, TypeMethod};
+use serde_json::Value;
+
+// /type with text outside the plain keyboard: Japanese and emoji go in
+// through Input.insertText and read back intact through /evaluate, and
+// frameworks listening to beforeinput see one insertText per call.
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<body>
+    <input id="field">
+    <textarea id="area"></textarea>
+    <div id="editor" contenteditable="true"></div>
+    <script>
+        window.inputs = [];
+        document.addEventListener('beforeinput', function(e) { inputs.push(e.inputType); });
+    </script>
+</body>
+</html>"#;
+
+const JAPANESE: &str = "こんにちは、世界";
+const EMOJI: &str = "ok 👍🏽 👨‍👩‍👧";
+
+fn type_into(browser: &common::Session, selector: &str, text: &str, method: TypeMethod) -> Value {
+    let request = InteractRequest { selector: selector.to_string(), text: text.to_string(), method, ..Default::default() };
+    browser.interact("/type", &request).unwrap()
+}
+
+fn read(browser: &common::Session, expression: &str) -> String {
+    browser.evaluate(expression).unwrap()
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn japanese_and_emoji_round_trip() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    let answer = type_into(&browser, "#field", JAPANESE, TypeMethod::Auto);
+    assert_eq!(answer["method"], "insert");
+    assert_eq!(answer["typed"], JAPANESE.chars().count());
+    assert_eq!(read(&browser, "document.getElementById('field').value"), JAPANESE);
+
+    type_into(&browser, "#area", EMOJI, TypeMethod::Auto);
+    assert_eq!(read(&browser, "document.getElementById('area').value"), EMOJI);
+
+    type_into(&browser, "#editor", &format!("{} {}", JAPANESE, EMOJI), TypeMethod::Auto);
+    assert_eq!(read(&browser, "document.getElementById('editor').textContent"), format!("{} {}", JAPANESE, EMOJI));
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn insert_appends_as_one_input_event() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    assert_eq!(type_into(&browser, "#field", "abc", TypeMethod::Auto)["method"], "keys");
+    type_into(&browser, "#field", "日本語", TypeMethod::Auto);
+    assert_eq!(read(&browser, "document.getElementById('field').value"), "abc日本語");
+
+    let inputs: Vec<String> = browser.evaluate("window.inputs").unwrap();
+    assert_eq!(inputs, ["insertText", "insertText", "insertText", "insertText"]);
+}
+
+#[test]
+#[ignore = "needs Chrome"]
+fn explicit_method_wins() {
+    let browser = common::browser();
+    browser.load(PAGE);
+
+    let answer = type_into(&browser, "#field", "plain", TypeMethod::Insert);
+    assert_eq!(answer["method"], "insert");
+    assert_eq!(read(&browser, "document.getElementById('field').value"), "plain");
+    let inputs: Vec<String> = browser.evaluate("window.inputs").unwrap();
+    assert_eq!(inputs, ["insertText"]);
+}